@@ -20,6 +20,9 @@ pub enum BPlusTreeError {
 
     #[error("Invalid order: {0} (must be >= 3)")]
     InvalidOrder(usize),
+
+    #[error("Invalid fill factor: {0} (must be between 1 and 100)")]
+    InvalidFillFactor(u8),
 }
 
 pub type BPlusTreeResult<T> = Result<T, BPlusTreeError>;
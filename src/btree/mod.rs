@@ -4,7 +4,7 @@
 //! database index operations. It supports:
 //! - Duplicate keys (for non-unique indexes)
 //! - Range queries (via linked leaf nodes)
-//! - i64 keys with RecordId values
+//! - i64 keys with any `Copy` value type (RecordId by default)
 //!
 //! The tree is currently in-memory; disk persistence will be added
 //! when integrating with the index file layer.
@@ -27,6 +27,15 @@ pub type BPlusKey = i64;
 /// This ensures one node fits comfortably in one 8KB page
 pub const DEFAULT_ORDER: usize = 500;
 
+/// Default bulk-load fill factor (percent of `max_leaf_entries` per leaf)
+///
+/// 100 packs leaves completely; lower values leave room for future inserts
+pub const DEFAULT_FILL_FACTOR: u8 = 100;
+
+/// `bulk_delete` rebuilds the tree only for batches of at least
+/// 1/`BULK_DELETE_MIN_SHARE` of its entries
+const BULK_DELETE_MIN_SHARE: usize = 16;
+
 /// B+ Tree data structure
 ///
 /// Order `m` means:
@@ -34,8 +43,11 @@ pub const DEFAULT_ORDER: usize = 500;
 /// - Internal nodes (except root) have at least `ceil(m/2)` children
 /// - Leaf nodes have at most `m-1` entries
 /// - Leaf nodes (except root) have at least `ceil((m-1)/2)` entries
+///
+/// Values are generic so the tree can store more than record locations; the
+/// index layer uses the default `RecordId` instantiation.
 #[derive(Debug)]
-pub struct BPlusTree {
+pub struct BPlusTree<V = RecordId> {
     /// Root node ID (None if tree is empty)
     root: Option<NodeId>,
 
@@ -43,7 +55,7 @@ pub struct BPlusTree {
     order: usize,
 
     /// Node storage
-    nodes: Vec<Option<BPlusNode>>,
+    nodes: Vec<Option<BPlusNode<V>>>,
 
     /// Free list for recycling deleted nodes
     free_list: Vec<NodeId>,
@@ -51,11 +63,14 @@ pub struct BPlusTree {
     /// First leaf node (for full range scans)
     first_leaf: Option<NodeId>,
 
+    /// Last leaf node (for reverse scans)
+    last_leaf: Option<NodeId>,
+
     /// Total number of entries in the tree
     entry_count: usize,
 }
 
-impl BPlusTree {
+impl<V: Copy + PartialEq> BPlusTree<V> {
     /// Create a new empty B+ tree with the given order
     ///
     /// # Arguments
@@ -75,6 +90,7 @@ impl BPlusTree {
             nodes: Vec::new(),
             free_list: Vec::new(),
             first_leaf: None,
+            last_leaf: None,
             entry_count: 0,
         })
     }
@@ -84,17 +100,42 @@ impl BPlusTree {
         Self::new(DEFAULT_ORDER).expect("Default order is valid")
     }
 
+    /// Rebuild a tree from stored nodes
+    ///
+    /// Only `next` links are stored, so the `prev` links and the last leaf
+    /// are recovered by walking the leaf chain from `first_leaf`.
     pub(crate) fn from_persistent_state(
         order: usize,
         root: Option<NodeId>,
         first_leaf: Option<NodeId>,
         entry_count: usize,
-        nodes: Vec<Option<BPlusNode>>,
+        mut nodes: Vec<Option<BPlusNode<V>>>,
     ) -> BPlusTreeResult<Self> {
         if order < 3 {
             return Err(BPlusTreeError::InvalidOrder(order));
         }
 
+        // A broken or cyclic chain is reported as the node it reaches, since
+        // a chain longer than the node count must revisit one
+        let mut last_leaf = None;
+        let mut current_id = first_leaf;
+        for _ in 0..nodes.len() {
+            let Some(id) = current_id else {
+                break;
+            };
+            let leaf = nodes
+                .get_mut(id)
+                .and_then(|node| node.as_mut())
+                .and_then(|node| node.as_leaf_mut())
+                .ok_or(BPlusTreeError::NodeNotFound(id))?;
+            leaf.prev = last_leaf;
+            last_leaf = Some(id);
+            current_id = leaf.next;
+        }
+        if let Some(id) = current_id {
+            return Err(BPlusTreeError::NodeNotFound(id));
+        }
+
         let mut free_list = Vec::new();
         for (idx, node) in nodes.iter().enumerate() {
             if node.is_none() {
@@ -108,6 +149,7 @@ impl BPlusTree {
             nodes,
             free_list,
             first_leaf,
+            last_leaf,
             entry_count,
         })
     }
@@ -205,7 +247,7 @@ impl BPlusTree {
     // ========== Node Management ==========
 
     /// Allocate a new node, returning its ID
-    fn allocate_node(&mut self, node: BPlusNode) -> NodeId {
+    fn allocate_node(&mut self, node: BPlusNode<V>) -> NodeId {
         if let Some(id) = self.free_list.pop() {
             self.nodes[id] = Some(node);
             id
@@ -217,12 +259,12 @@ impl BPlusTree {
     }
 
     /// Get a reference to a node by ID (public for index layer)
-    pub fn get_node(&self, id: NodeId) -> Option<&BPlusNode> {
+    pub fn get_node(&self, id: NodeId) -> Option<&BPlusNode<V>> {
         self.nodes.get(id).and_then(|n| n.as_ref())
     }
 
     /// Get a mutable reference to a node by ID
-    fn get_node_mut(&mut self, id: NodeId) -> Option<&mut BPlusNode> {
+    fn get_node_mut(&mut self, id: NodeId) -> Option<&mut BPlusNode<V>> {
         self.nodes.get_mut(id).and_then(|n| n.as_mut())
     }
 
@@ -236,6 +278,11 @@ impl BPlusTree {
         self.first_leaf
     }
 
+    /// Get the last leaf node ID
+    pub fn last_leaf_id(&self) -> Option<NodeId> {
+        self.last_leaf
+    }
+
     /// Get the total number of nodes
     pub fn node_count(&self) -> usize {
         self.nodes.len()
@@ -251,15 +298,15 @@ impl BPlusTree {
 
     // ========== Search Operations ==========
 
-    /// Search for a key, returning the first matching RecordId
-    pub fn search(&self, key: BPlusKey) -> Option<RecordId> {
+    /// Search for a key, returning the first matching value
+    pub fn search(&self, key: BPlusKey) -> Option<V> {
         let leaf_id = self.find_leaf(key)?;
         let leaf = self.get_node(leaf_id)?.as_leaf()?;
         leaf.search(key)
     }
 
     /// Search for all entries with the given key
-    pub fn search_all(&self, key: BPlusKey) -> Vec<RecordId> {
+    pub fn search_all(&self, key: BPlusKey) -> Vec<V> {
         let mut results = Vec::new();
 
         let leaf_id = match self.find_leaf(key) {
@@ -299,42 +346,40 @@ impl BPlusTree {
     }
 
     /// Range search: return all entries where lower <= key <= upper
-    pub fn range_search(&self, lower: BPlusKey, upper: BPlusKey) -> Vec<(BPlusKey, RecordId)> {
-        let mut results = Vec::new();
-
-        if lower > upper {
-            return results;
-        }
+    pub fn range_search(&self, lower: BPlusKey, upper: BPlusKey) -> Vec<(BPlusKey, V)> {
+        self.range_iter(lower, upper).collect()
+    }
 
-        // Find the leaf containing the lower bound
-        let leaf_id = match self.find_leaf(lower) {
-            Some(id) => id,
-            None => return results,
-        };
+    /// Iterate lazily over entries where lower <= key <= upper, in key order
+    pub fn range_iter(&self, lower: BPlusKey, upper: BPlusKey) -> BPlusTreeRangeIter<'_, V> {
+        BPlusTreeRangeIter::new(self, lower, upper)
+    }
 
-        let mut current_id = Some(leaf_id);
+    /// Smallest key in the tree, found by walking the leaf chain from the first leaf
+    pub fn min_key(&self) -> Option<BPlusKey> {
+        let mut current_id = self.first_leaf;
 
-        // Scan through leaf nodes
         while let Some(id) = current_id {
-            let leaf = match self.get_node(id).and_then(|n| n.as_leaf()) {
-                Some(l) => l,
-                None => break,
-            };
-
-            // Collect entries in range
-            for (i, &k) in leaf.keys.iter().enumerate() {
-                if k > upper {
-                    return results;
-                }
-                if k >= lower {
-                    results.push((k, leaf.values[i]));
-                }
+            let leaf = self.get_node(id)?.as_leaf()?;
+            if let Some(&key) = leaf.keys.first() {
+                return Some(key);
             }
-
             current_id = leaf.next;
         }
 
-        results
+        None
+    }
+
+    /// Largest key in the tree, found by descending along the rightmost children
+    pub fn max_key(&self) -> Option<BPlusKey> {
+        let mut current = self.root?;
+
+        loop {
+            match self.get_node(current)? {
+                BPlusNode::Leaf(leaf) => return leaf.keys.last().copied(),
+                BPlusNode::Internal(node) => current = *node.children.last()?,
+            }
+        }
     }
 
     /// Find the leaf node that should contain the given key
@@ -369,10 +414,37 @@ impl BPlusTree {
         }
     }
 
+    /// Find the leaf after the one reached by `path`, along with its own path
+    fn next_leaf_with_path(
+        &self,
+        path: &[(NodeId, usize)],
+    ) -> Option<(NodeId, Vec<(NodeId, usize)>)> {
+        let mut path = path.to_vec();
+
+        // Climb until an ancestor has a child to the right, then descend along leftmost children
+        loop {
+            let (node_id, child_idx) = path.pop()?;
+            let node = self.get_node(node_id)?.as_internal()?;
+            if child_idx + 1 < node.len() {
+                path.push((node_id, child_idx + 1));
+                let mut current = node.children[child_idx + 1];
+                loop {
+                    match self.get_node(current)? {
+                        BPlusNode::Leaf(_) => return Some((current, path)),
+                        BPlusNode::Internal(node) => {
+                            path.push((current, 0));
+                            current = node.children[0];
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     // ========== Insert Operations ==========
 
     /// Insert a key-value pair into the tree
-    pub fn insert(&mut self, key: BPlusKey, rid: RecordId) -> BPlusTreeResult<()> {
+    pub fn insert(&mut self, key: BPlusKey, rid: V) -> BPlusTreeResult<()> {
         if self.root.is_none() {
             // Create first leaf as root
             let mut leaf = LeafNode::new();
@@ -380,6 +452,7 @@ impl BPlusTree {
             let leaf_id = self.allocate_node(BPlusNode::Leaf(leaf));
             self.root = Some(leaf_id);
             self.first_leaf = Some(leaf_id);
+            self.last_leaf = Some(leaf_id);
             self.entry_count = 1;
             return Ok(());
         }
@@ -440,6 +513,19 @@ impl BPlusTree {
                 .ok_or(BPlusTreeError::NodeNotFound(leaf_id))?;
             leaf.next = Some(right_id);
 
+            // The old successor now follows the right leaf
+            right.prev = Some(leaf_id);
+            match right.next {
+                Some(next_id) => {
+                    let next = self
+                        .get_node_mut(next_id)
+                        .and_then(|n| n.as_leaf_mut())
+                        .ok_or(BPlusTreeError::NodeNotFound(next_id))?;
+                    next.prev = Some(right_id);
+                }
+                None => self.last_leaf = Some(right_id),
+            }
+
             // Store the right leaf
             self.nodes[right_id] = Some(BPlusNode::Leaf(right));
 
@@ -497,6 +583,9 @@ impl BPlusTree {
 
         if parent_len > self.max_internal_children() {
             self.split_internal(parent_id, parent_path)?;
+        } else {
+            // The split may have raised the subtree's max key (e.g. appending past the end)
+            self.update_ancestor_keys(parent_id, &parent_path)?;
         }
 
         Ok(())
@@ -535,6 +624,34 @@ impl BPlusTree {
         Ok(())
     }
 
+    /// Reset the parent's keys for the given children to their actual max keys,
+    /// then propagate any change in the parent's own max upwards
+    ///
+    /// Used after redistribution, where the node that lost an entry may also
+    /// have lost its max key.
+    fn refresh_child_keys(
+        &mut self,
+        parent_id: NodeId,
+        children: &[NodeId],
+        parent_path: &[(NodeId, usize)],
+    ) -> BPlusTreeResult<()> {
+        for &child_id in children {
+            let max_key = self
+                .get_node(child_id)
+                .and_then(|n| n.max_key())
+                .ok_or(BPlusTreeError::NodeNotFound(child_id))?;
+            let parent = self
+                .get_node_mut(parent_id)
+                .and_then(|n| n.as_internal_mut())
+                .ok_or(BPlusTreeError::NodeNotFound(parent_id))?;
+            if let Some(idx) = parent.children.iter().position(|&c| c == child_id) {
+                parent.keys[idx] = max_key;
+            }
+        }
+
+        self.update_ancestor_keys(parent_id, parent_path)
+    }
+
     /// Update ancestor keys after an insertion
     fn update_ancestor_keys(
         &mut self,
@@ -593,27 +710,60 @@ impl BPlusTree {
     /// ```
     pub fn bulk_load<I>(&mut self, entries: I) -> BPlusTreeResult<()>
     where
-        I: Iterator<Item = (BPlusKey, RecordId)>,
+        I: Iterator<Item = (BPlusKey, V)>,
+    {
+        self.bulk_load_with_fill_factor(entries, DEFAULT_FILL_FACTOR)
+    }
+
+    /// Like [`bulk_load`](Self::bulk_load), but fills each leaf only up to
+    /// `fill_factor` percent of its capacity
+    ///
+    /// Leaving headroom avoids splitting nearly every leaf once inserts resume
+    /// after the load. Leaves are never filled below the minimum occupancy, so
+    /// very low factors are effectively clamped to 50%.
+    pub fn bulk_load_with_fill_factor<I>(
+        &mut self,
+        entries: I,
+        fill_factor: u8,
+    ) -> BPlusTreeResult<()>
+    where
+        I: Iterator<Item = (BPlusKey, V)>,
     {
         // Collect entries into a Vec - we need random access for bulk loading
-        let all_entries: Vec<(BPlusKey, RecordId)> = entries.collect();
-        self.bulk_load_from_slice(&all_entries)
+        let all_entries: Vec<(BPlusKey, V)> = entries.collect();
+        self.bulk_load_from_slice_with_fill_factor(&all_entries, fill_factor)
     }
 
     /// Bulk load from a pre-sorted slice (more memory efficient)
-    pub fn bulk_load_from_slice(
+    pub fn bulk_load_from_slice(&mut self, all_entries: &[(BPlusKey, V)]) -> BPlusTreeResult<()> {
+        self.bulk_load_from_slice_with_fill_factor(all_entries, DEFAULT_FILL_FACTOR)
+    }
+
+    /// Bulk load from a pre-sorted slice with the given leaf fill factor
+    ///
+    /// See [`bulk_load_with_fill_factor`](Self::bulk_load_with_fill_factor).
+    pub fn bulk_load_from_slice_with_fill_factor(
         &mut self,
-        all_entries: &[(BPlusKey, RecordId)],
+        all_entries: &[(BPlusKey, V)],
+        fill_factor: u8,
     ) -> BPlusTreeResult<()> {
+        if !(1..=100).contains(&fill_factor) {
+            return Err(BPlusTreeError::InvalidFillFactor(fill_factor));
+        }
+
         // Clear existing tree
         self.root = None;
         self.nodes.clear();
         self.free_list.clear();
         self.first_leaf = None;
+        self.last_leaf = None;
         self.entry_count = 0;
 
-        let max_leaf_entries = self.max_leaf_entries();
-        let _min_leaf_entries = self.min_leaf_entries();
+        // Target leaf size: the requested share of capacity, but never below
+        // the minimum occupancy of a non-root leaf
+        let max_leaf_entries = (self.max_leaf_entries() * fill_factor as usize / 100)
+            .max(self.min_leaf_entries())
+            .max(1);
         let max_internal_children = self.max_internal_children();
 
         if all_entries.is_empty() {
@@ -676,16 +826,18 @@ impl BPlusTree {
         }
 
         // Link leaves together
-        for i in 0..leaves.len() - 1 {
+        for i in 0..leaves.len() {
             let leaf = self
                 .get_node_mut(leaves[i])
                 .and_then(|n| n.as_leaf_mut())
                 .ok_or(BPlusTreeError::NodeNotFound(leaves[i]))?;
-            leaf.next = Some(leaves[i + 1]);
+            leaf.next = leaves.get(i + 1).copied();
+            leaf.prev = i.checked_sub(1).map(|prev| leaves[prev]);
         }
 
-        // Track first leaf
+        // Track first and last leaves
         self.first_leaf = Some(leaves[0]);
+        self.last_leaf = leaves.last().copied();
 
         // Build internal levels bottom-up
         let mut current_level = leaves;
@@ -791,28 +943,36 @@ impl BPlusTree {
 
     /// Delete a specific key-value pair
     /// Returns true if the entry was found and deleted
-    pub fn delete_entry(&mut self, key: BPlusKey, rid: RecordId) -> BPlusTreeResult<bool> {
+    pub fn delete_entry(&mut self, key: BPlusKey, rid: V) -> BPlusTreeResult<bool> {
         if self.root.is_none() {
             return Ok(false);
         }
 
         // Find the leaf and path
-        let (leaf_id, path) = match self.find_leaf_with_path(key) {
+        let (mut leaf_id, mut path) = match self.find_leaf_with_path(key) {
             Some(result) => result,
             None => return Ok(false),
         };
 
-        // Delete from leaf
-        let deleted = {
+        // Delete from leaf; duplicates of the key may continue into the following leaves
+        loop {
             let leaf = self
                 .get_node_mut(leaf_id)
                 .and_then(|n| n.as_leaf_mut())
                 .ok_or(BPlusTreeError::NodeNotFound(leaf_id))?;
-            leaf.delete_entry(key, rid)
-        };
-
-        if !deleted {
-            return Ok(false);
+            if leaf.delete_entry(key, rid) {
+                break;
+            }
+            if leaf.max_key() != Some(key) {
+                return Ok(false);
+            }
+            match self.next_leaf_with_path(&path) {
+                Some((next_id, next_path)) => {
+                    leaf_id = next_id;
+                    path = next_path;
+                }
+                None => return Ok(false),
+            }
         }
 
         self.entry_count -= 1;
@@ -823,6 +983,65 @@ impl BPlusTree {
         Ok(true)
     }
 
+    /// Delete many key-value pairs at once, returning how many were found
+    ///
+    /// Each pair removes at most one stored entry, as with `delete_entry`.
+    /// The pairs are sorted and matched against the leaf chain in a single
+    /// pass, and the tree is then rebuilt from the surviving entries, so the
+    /// batch is rebalanced once instead of walking from the root and fixing
+    /// underflow per entry. A batch that is small next to the tree falls back
+    /// to `delete_entry`, where a few root-to-leaf walks cost less than
+    /// rebuilding every node.
+    pub fn bulk_delete(&mut self, entries: &[(BPlusKey, V)]) -> BPlusTreeResult<usize> {
+        if entries.is_empty() || self.root.is_none() {
+            return Ok(0);
+        }
+
+        if entries.len() * BULK_DELETE_MIN_SHARE < self.entry_count {
+            let mut deleted = 0;
+            for &(key, value) in entries {
+                if self.delete_entry(key, value)? {
+                    deleted += 1;
+                }
+            }
+            return Ok(deleted);
+        }
+
+        let mut pending = entries.to_vec();
+        pending.sort_by_key(|&(key, _)| key);
+
+        // Values still to delete under the key being scanned
+        let mut run: Vec<V> = Vec::new();
+        let mut run_key = None;
+        let mut next = 0;
+        let mut survivors = Vec::with_capacity(self.entry_count.saturating_sub(pending.len()));
+        for (key, value) in self.iter() {
+            if run_key != Some(key) {
+                run.clear();
+                while next < pending.len() && pending[next].0 < key {
+                    next += 1;
+                }
+                while next < pending.len() && pending[next].0 == key {
+                    run.push(pending[next].1);
+                    next += 1;
+                }
+                run_key = Some(key);
+            }
+            match run.iter().position(|&pending| pending == value) {
+                Some(pos) => {
+                    run.swap_remove(pos);
+                }
+                None => survivors.push((key, value)),
+            }
+        }
+
+        let deleted = self.entry_count - survivors.len();
+        if deleted > 0 {
+            self.bulk_load_from_slice(&survivors)?;
+        }
+        Ok(deleted)
+    }
+
     /// Handle underflow in a leaf node after deletion
     fn handle_leaf_underflow(
         &mut self,
@@ -842,6 +1061,7 @@ impl BPlusTree {
                 self.free_node(leaf_id);
                 self.root = None;
                 self.first_leaf = None;
+                self.last_leaf = None;
             }
             return Ok(());
         }
@@ -882,6 +1102,7 @@ impl BPlusTree {
         if sibling_len > self.min_leaf_entries() {
             // Redistribute from sibling
             self.redistribute_leaves(leaf_id, sibling_id, sibling_is_left, parent_id, child_idx)?;
+            self.refresh_child_keys(parent_id, &[leaf_id, sibling_id], &parent_path)?;
         } else {
             // Merge with sibling
             self.merge_leaves(
@@ -1002,7 +1223,7 @@ impl BPlusTree {
 
         // Merge right into left
         {
-            let right_entries: Vec<(BPlusKey, RecordId)> = {
+            let right_entries: Vec<(BPlusKey, V)> = {
                 let right = self
                     .get_node(right_id)
                     .and_then(|n| n.as_leaf())
@@ -1031,6 +1252,17 @@ impl BPlusTree {
                 left.values.push(v);
             }
             left.next = right_next;
+
+            match right_next {
+                Some(next_id) => {
+                    let next = self
+                        .get_node_mut(next_id)
+                        .and_then(|n| n.as_leaf_mut())
+                        .ok_or(BPlusTreeError::NodeNotFound(next_id))?;
+                    next.prev = Some(left_id);
+                }
+                None => self.last_leaf = Some(left_id),
+            }
         }
 
         // Update first_leaf if needed - when right node is being removed and was first_leaf
@@ -1161,6 +1393,7 @@ impl BPlusTree {
         if sibling_len > self.min_internal_children() {
             // Redistribute from sibling
             self.redistribute_internal(node_id, sibling_id, sibling_is_left, parent_id, child_idx)?;
+            self.refresh_child_keys(parent_id, &[node_id, sibling_id], &parent_path)?;
         } else {
             // Merge with sibling
             self.merge_internal(
@@ -1311,20 +1544,27 @@ impl BPlusTree {
     // ========== Iterator ==========
 
     /// Iterate over all entries in key order
-    pub fn iter(&self) -> BPlusTreeIter<'_> {
+    pub fn iter(&self) -> BPlusTreeIter<'_, V> {
         BPlusTreeIter::new(self)
     }
+
+    /// Iterate over all entries in descending key order
+    ///
+    /// Entries that share a key come out in the reverse of their `iter` order.
+    pub fn rev_iter(&self) -> BPlusTreeRevIter<'_, V> {
+        BPlusTreeRevIter::new(self)
+    }
 }
 
 /// Iterator over B+ tree entries
-pub struct BPlusTreeIter<'a> {
-    tree: &'a BPlusTree,
+pub struct BPlusTreeIter<'a, V = RecordId> {
+    tree: &'a BPlusTree<V>,
     current_leaf: Option<NodeId>,
     current_idx: usize,
 }
 
-impl<'a> BPlusTreeIter<'a> {
-    fn new(tree: &'a BPlusTree) -> Self {
+impl<'a, V: Copy + PartialEq> BPlusTreeIter<'a, V> {
+    fn new(tree: &'a BPlusTree<V>) -> Self {
         Self {
             tree,
             current_leaf: tree.first_leaf,
@@ -1333,8 +1573,8 @@ impl<'a> BPlusTreeIter<'a> {
     }
 }
 
-impl Iterator for BPlusTreeIter<'_> {
-    type Item = (BPlusKey, RecordId);
+impl<V: Copy + PartialEq> Iterator for BPlusTreeIter<'_, V> {
+    type Item = (BPlusKey, V);
 
     fn next(&mut self) -> Option<Self::Item> {
         loop {
@@ -1355,6 +1595,110 @@ impl Iterator for BPlusTreeIter<'_> {
     }
 }
 
+/// Iterator over B+ tree entries from the last leaf backwards
+pub struct BPlusTreeRevIter<'a, V = RecordId> {
+    tree: &'a BPlusTree<V>,
+    current_leaf: Option<NodeId>,
+    /// Number of entries of the current leaf not yet returned
+    remaining: usize,
+}
+
+impl<'a, V: Copy + PartialEq> BPlusTreeRevIter<'a, V> {
+    fn new(tree: &'a BPlusTree<V>) -> Self {
+        let remaining = tree
+            .last_leaf
+            .and_then(|id| tree.get_node(id))
+            .and_then(|node| node.as_leaf())
+            .map_or(0, |leaf| leaf.len());
+        Self {
+            tree,
+            current_leaf: tree.last_leaf,
+            remaining,
+        }
+    }
+}
+
+impl<V: Copy + PartialEq> Iterator for BPlusTreeRevIter<'_, V> {
+    type Item = (BPlusKey, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let leaf_id = self.current_leaf?;
+            let leaf = self.tree.get_node(leaf_id)?.as_leaf()?;
+
+            if self.remaining > 0 {
+                self.remaining -= 1;
+                return Some((leaf.keys[self.remaining], leaf.values[self.remaining]));
+            }
+
+            // Move to previous leaf
+            self.current_leaf = leaf.prev;
+            self.remaining = self
+                .current_leaf
+                .and_then(|id| self.tree.get_node(id))
+                .and_then(|node| node.as_leaf())
+                .map_or(0, |leaf| leaf.len());
+        }
+    }
+}
+
+/// Iterator over the B+ tree entries within a key range
+///
+/// Starts at the leaf that would hold `lower` and follows leaf links,
+/// stopping at the first key above `upper`.
+pub struct BPlusTreeRangeIter<'a, V = RecordId> {
+    tree: &'a BPlusTree<V>,
+    current_leaf: Option<NodeId>,
+    current_idx: usize,
+    lower: BPlusKey,
+    upper: BPlusKey,
+}
+
+impl<'a, V: Copy + PartialEq> BPlusTreeRangeIter<'a, V> {
+    fn new(tree: &'a BPlusTree<V>, lower: BPlusKey, upper: BPlusKey) -> Self {
+        let current_leaf = if lower > upper {
+            None
+        } else {
+            tree.find_leaf(lower)
+        };
+        Self {
+            tree,
+            current_leaf,
+            current_idx: 0,
+            lower,
+            upper,
+        }
+    }
+}
+
+impl<V: Copy + PartialEq> Iterator for BPlusTreeRangeIter<'_, V> {
+    type Item = (BPlusKey, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let leaf_id = self.current_leaf?;
+            let leaf = self.tree.get_node(leaf_id)?.as_leaf()?;
+
+            while self.current_idx < leaf.len() {
+                let key = leaf.keys[self.current_idx];
+                let value = leaf.values[self.current_idx];
+                self.current_idx += 1;
+                if key > self.upper {
+                    self.current_leaf = None;
+                    return None;
+                }
+                if key >= self.lower {
+                    return Some((key, value));
+                }
+            }
+
+            // Move to next leaf
+            self.current_leaf = leaf.next;
+            self.current_idx = 0;
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1520,6 +1864,7 @@ mod tests {
         let mut current_id = tree.first_leaf_id();
         let mut visited = std::collections::HashSet::new();
         let mut prev_max_key: Option<i64> = None;
+        let mut prev_id: Option<NodeId> = None;
 
         while let Some(id) = current_id {
             // Check for cycles
@@ -1534,27 +1879,43 @@ mod tests {
                 .ok_or(format!("Leaf {} not found or not a leaf", id))?;
 
             // Check ordering between leaves
-            if let Some(prev_max) = prev_max_key {
-                if let Some(curr_min) = leaf.min_key() {
-                    if curr_min < prev_max {
-                        return Err(format!(
-                            "Leaf chain out of order: prev max = {}, curr min = {}",
-                            prev_max, curr_min
-                        ));
-                    }
-                }
+            if let Some(prev_max) = prev_max_key
+                && let Some(curr_min) = leaf.min_key()
+                && curr_min < prev_max
+            {
+                return Err(format!(
+                    "Leaf chain out of order: prev max = {}, curr min = {}",
+                    prev_max, curr_min
+                ));
+            }
+
+            // Check the backward link
+            if leaf.prev != prev_id {
+                return Err(format!(
+                    "Leaf {} has prev = {:?}, expected {:?}",
+                    id, leaf.prev, prev_id
+                ));
             }
 
             prev_max_key = leaf.max_key();
+            prev_id = Some(id);
             current_id = leaf.next;
         }
 
+        if tree.last_leaf_id() != prev_id {
+            return Err(format!(
+                "Last leaf is {:?}, but the chain ends at {:?}",
+                tree.last_leaf_id(),
+                prev_id
+            ));
+        }
+
         Ok(())
     }
 
     #[test]
     fn test_new_tree() {
-        let tree = BPlusTree::new(4).unwrap();
+        let tree = BPlusTree::<RecordId>::new(4).unwrap();
         assert!(tree.is_empty());
         assert_eq!(tree.len(), 0);
         assert_eq!(tree.height(), 0);
@@ -1563,9 +1924,9 @@ mod tests {
 
     #[test]
     fn test_invalid_order() {
-        assert!(BPlusTree::new(2).is_err());
-        assert!(BPlusTree::new(1).is_err());
-        assert!(BPlusTree::new(0).is_err());
+        assert!(BPlusTree::<RecordId>::new(2).is_err());
+        assert!(BPlusTree::<RecordId>::new(1).is_err());
+        assert!(BPlusTree::<RecordId>::new(0).is_err());
     }
 
     #[test]
@@ -1684,6 +2045,162 @@ mod tests {
         assert!(results.is_empty());
     }
 
+    #[test]
+    fn test_range_iter_is_lazy_and_bounded() {
+        let mut tree = BPlusTree::new(4).unwrap();
+
+        for i in 0..100 {
+            tree.insert(i, rid(1, i as usize)).unwrap();
+            tree.insert(i, rid(2, i as usize)).unwrap();
+        }
+
+        // Spans many leaves, duplicates included, and matches range_search
+        let entries: Vec<_> = tree.range_iter(17, 63).collect();
+        assert_eq!(entries.len(), 2 * 47);
+        assert!(entries.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        assert_eq!(entries.first().map(|entry| entry.0), Some(17));
+        assert_eq!(entries.last().map(|entry| entry.0), Some(63));
+        assert_eq!(entries, tree.range_search(17, 63));
+
+        let mut iter = tree.range_iter(90, i64::MAX);
+        assert_eq!(iter.next().map(|entry| entry.0), Some(90));
+        assert_eq!(iter.by_ref().count(), 19);
+        assert_eq!(iter.next(), None);
+
+        assert_eq!(tree.range_iter(i64::MIN, i64::MAX).count(), 200);
+        assert_eq!(tree.range_iter(50, 20).next(), None);
+        assert_eq!(tree.range_iter(200, 300).next(), None);
+    }
+
+    #[test]
+    fn test_min_max_key() {
+        let mut tree = BPlusTree::new(4).unwrap();
+        assert_eq!(tree.min_key(), None);
+        assert_eq!(tree.max_key(), None);
+
+        tree.insert(7, rid(1, 0)).unwrap();
+        assert_eq!(tree.min_key(), Some(7));
+        assert_eq!(tree.max_key(), Some(7));
+        tree.delete(7).unwrap();
+
+        for i in 0..100 {
+            let key = (i * 37) % 100 - 50;
+            tree.insert(key, rid(1, i as usize)).unwrap();
+        }
+        assert!(tree.height() > 2);
+        assert_eq!(tree.min_key(), Some(-50));
+        assert_eq!(tree.max_key(), Some(49));
+
+        // Deleting the extremes exposes the next keys in
+        for key in -50..-40 {
+            tree.delete(key).unwrap();
+        }
+        for key in 40..50 {
+            tree.delete(key).unwrap();
+        }
+        assert_eq!(tree.min_key(), Some(-40));
+        assert_eq!(tree.max_key(), Some(39));
+
+        for key in -40..40 {
+            tree.delete(key).unwrap();
+        }
+        assert_eq!(tree.min_key(), None);
+        assert_eq!(tree.max_key(), None);
+    }
+
+    #[test]
+    fn test_bulk_delete() {
+        let mut tree = BPlusTree::new(4).unwrap();
+        for i in 0..300 {
+            tree.insert(i % 100, rid(1, i as usize)).unwrap();
+        }
+
+        // Unsorted input, duplicate keys, a pair given twice and pairs that
+        // are not stored
+        let mut batch: Vec<_> = (0..300)
+            .filter(|i| i % 3 != 0)
+            .map(|i| (i % 100, rid(1, i as usize)))
+            .rev()
+            .collect();
+        batch.push((7, rid(1, 7)));
+        batch.push((7, rid(9, 9)));
+        batch.push((500, rid(1, 0)));
+        assert_eq!(tree.bulk_delete(&batch).unwrap(), 200);
+        validate_btree_structure(&tree).unwrap();
+        validate_leaf_chain(&tree).unwrap();
+
+        assert_eq!(tree.len(), 100);
+        let expected: Vec<_> = (0..300)
+            .filter(|i| i % 3 == 0)
+            .map(|i| (i % 100, rid(1, i as usize)))
+            .collect();
+        let mut remaining: Vec<_> = tree.iter().collect();
+        remaining.sort_by_key(|&(key, rid)| (key, rid.slot_id));
+        let mut expected = expected;
+        expected.sort_by_key(|&(key, rid)| (key, rid.slot_id));
+        assert_eq!(remaining, expected);
+
+        // A small batch takes the per-entry path with the same result
+        assert_eq!(
+            tree.bulk_delete(&[(0, rid(1, 0)), (0, rid(1, 0))]).unwrap(),
+            1
+        );
+        assert_eq!(tree.len(), 99);
+        validate_btree_structure(&tree).unwrap();
+
+        let everything: Vec<_> = tree.iter().collect();
+        assert_eq!(tree.bulk_delete(&everything).unwrap(), 99);
+        assert!(tree.is_empty());
+        assert_eq!(tree.bulk_delete(&everything).unwrap(), 0);
+    }
+
+    /// Compare `bulk_delete` with a loop of `delete_entry` on 100k keys,
+    /// checking both leave the same entries behind; run with
+    /// `cargo test --release -- --ignored --nocapture` to see the timings
+    #[test]
+    #[ignore]
+    fn bench_bulk_delete_vs_delete_entry() {
+        use std::time::Instant;
+
+        let entries: Vec<_> = (0..100_000).map(|i| (i, rid(i as usize, 0))).collect();
+        let mut victims: Vec<_> = entries
+            .iter()
+            .copied()
+            .filter(|(key, _)| key % 2 == 0)
+            .collect();
+        // Shuffle deterministically so neither side gets sorted input for free
+        victims.sort_by_key(|&(key, _)| (key * 7919) % 100_003);
+
+        let mut looped_tree = BPlusTree::default_order();
+        looped_tree.bulk_load_from_slice(&entries).unwrap();
+        let start = Instant::now();
+        for &(key, value) in &victims {
+            looped_tree.delete_entry(key, value).unwrap();
+        }
+        let looped = start.elapsed();
+        validate_btree_structure(&looped_tree).unwrap();
+
+        let mut tree = BPlusTree::default_order();
+        tree.bulk_load_from_slice(&entries).unwrap();
+        let start = Instant::now();
+        assert_eq!(tree.bulk_delete(&victims).unwrap(), victims.len());
+        let bulk = start.elapsed();
+        validate_btree_structure(&tree).unwrap();
+
+        let survivors: Vec<_> = tree.iter().collect();
+        assert_eq!(survivors.len(), entries.len() - victims.len());
+        assert!(survivors.iter().all(|(key, _)| key % 2 == 1));
+        assert_eq!(survivors, looped_tree.iter().collect::<Vec<_>>());
+
+        println!(
+            "delete_entry loop: {:?}, bulk_delete: {:?} ({} of {} keys)",
+            looped,
+            bulk,
+            victims.len(),
+            entries.len()
+        );
+    }
+
     #[test]
     fn test_split_preserves_leaf_chain() {
         let mut tree = BPlusTree::new(4).unwrap();
@@ -1823,8 +2340,8 @@ mod tests {
         let entries: Vec<_> = tree.iter().collect();
         assert_eq!(entries.len(), 10);
 
-        for i in 0..10 {
-            assert_eq!(entries[i], (i as i64, rid(1, i)));
+        for (i, entry) in entries.iter().enumerate() {
+            assert_eq!(*entry, (i as i64, rid(1, i)));
         }
     }
 
@@ -1944,34 +2461,72 @@ mod tests {
         assert_eq!(first.0, 10);
     }
 
+    #[test]
+    fn test_rev_iter_mirrors_iter() {
+        let mut tree = BPlusTree::new(4).unwrap();
+        assert_eq!(tree.rev_iter().next(), None);
+        assert_eq!(tree.last_leaf_id(), None);
+
+        for i in (0..200).rev() {
+            tree.insert(i % 50, rid(1, i as usize)).unwrap();
+        }
+        validate_leaf_chain(&tree).unwrap();
+        let mut forward: Vec<_> = tree.iter().collect();
+        forward.reverse();
+        assert_eq!(tree.rev_iter().collect::<Vec<_>>(), forward);
+
+        // Merges and redistributions keep the backward chain
+        for i in 0..150 {
+            assert!(tree.delete_entry(i % 50, rid(1, i as usize)).unwrap());
+            validate_leaf_chain(&tree).unwrap();
+        }
+        let keys: Vec<i64> = tree.rev_iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, (0..50).rev().collect::<Vec<_>>());
+
+        for i in 150..200 {
+            assert!(tree.delete_entry(i % 50, rid(1, i as usize)).unwrap());
+        }
+        assert_eq!(tree.last_leaf_id(), None);
+        assert_eq!(tree.rev_iter().next(), None);
+
+        let entries: Vec<_> = (0..100).map(|i| (i, rid(2, i as usize))).collect();
+        tree.bulk_load_from_slice(&entries).unwrap();
+        validate_leaf_chain(&tree).unwrap();
+        assert_eq!(tree.rev_iter().next(), Some((99, rid(2, 99))));
+        assert_eq!(tree.rev_iter().count(), 100);
+    }
+
     #[test]
     fn test_calculate_optimal_depth() {
         // Empty tree
-        assert_eq!(BPlusTree::calculate_optimal_depth(0, 500), 0);
+        assert_eq!(BPlusTree::<RecordId>::calculate_optimal_depth(0, 500), 0);
 
         // Single leaf (order=500, max_leaf_entries=499)
-        assert_eq!(BPlusTree::calculate_optimal_depth(1, 500), 1);
-        assert_eq!(BPlusTree::calculate_optimal_depth(100, 500), 1);
-        assert_eq!(BPlusTree::calculate_optimal_depth(499, 500), 1);
+        assert_eq!(BPlusTree::<RecordId>::calculate_optimal_depth(1, 500), 1);
+        assert_eq!(BPlusTree::<RecordId>::calculate_optimal_depth(100, 500), 1);
+        assert_eq!(BPlusTree::<RecordId>::calculate_optimal_depth(499, 500), 1);
 
         // Two levels (1 leaf + 1 internal)
-        assert_eq!(BPlusTree::calculate_optimal_depth(500, 500), 2);
-        assert_eq!(BPlusTree::calculate_optimal_depth(1000, 500), 2);
+        assert_eq!(BPlusTree::<RecordId>::calculate_optimal_depth(500, 500), 2);
+        assert_eq!(BPlusTree::<RecordId>::calculate_optimal_depth(1000, 500), 2);
 
         // Three levels
         // 500 leaves * 499 entries = 249,500 entries max with 2 levels
         // So 249,501 entries needs 3 levels
-        assert_eq!(BPlusTree::calculate_optimal_depth(250_000, 500), 3);
+        assert_eq!(
+            BPlusTree::<RecordId>::calculate_optimal_depth(250_000, 500),
+            3
+        );
 
         // Smaller tree for easier verification
         // Order 4: max_leaf_entries = 3
         // 1 leaf: up to 3 entries (depth 1)
         // 2-4 leaves: up to 12 entries (depth 2)
         // 5-16 leaves: up to 48 entries (depth 3)
-        assert_eq!(BPlusTree::calculate_optimal_depth(3, 4), 1);
-        assert_eq!(BPlusTree::calculate_optimal_depth(4, 4), 2);
-        assert_eq!(BPlusTree::calculate_optimal_depth(12, 4), 2);
-        assert_eq!(BPlusTree::calculate_optimal_depth(13, 4), 3);
+        assert_eq!(BPlusTree::<RecordId>::calculate_optimal_depth(3, 4), 1);
+        assert_eq!(BPlusTree::<RecordId>::calculate_optimal_depth(4, 4), 2);
+        assert_eq!(BPlusTree::<RecordId>::calculate_optimal_depth(12, 4), 2);
+        assert_eq!(BPlusTree::<RecordId>::calculate_optimal_depth(13, 4), 3);
     }
 
     #[test]
@@ -2001,8 +2556,7 @@ mod tests {
     #[test]
     fn test_bulk_load_small() {
         let mut tree = BPlusTree::new(4).unwrap();
-        let mut entries: Vec<(i64, RecordId)> =
-            (0..10).map(|i| (i * 10, rid(1, i as usize))).collect();
+        let entries: Vec<(i64, RecordId)> = (0..10).map(|i| (i * 10, rid(1, i as usize))).collect();
 
         tree.bulk_load(entries.into_iter()).unwrap();
 
@@ -2015,8 +2569,8 @@ mod tests {
 
         // Verify iterator returns sorted order
         let iter_entries: Vec<_> = tree.iter().collect();
-        for i in 0..10 {
-            assert_eq!(iter_entries[i], ((i * 10) as i64, rid(1, i as usize)));
+        for (i, entry) in iter_entries.iter().enumerate() {
+            assert_eq!(*entry, ((i * 10) as i64, rid(1, i)));
         }
     }
 
@@ -2033,7 +2587,7 @@ mod tests {
 
         // Verify tree structure is correct
         let depth = tree.height();
-        let expected_depth = BPlusTree::calculate_optimal_depth(10000, 500);
+        let expected_depth = BPlusTree::<RecordId>::calculate_optimal_depth(10000, 500);
         assert_eq!(depth, expected_depth);
 
         // Spot check some entries
@@ -2044,8 +2598,8 @@ mod tests {
         // Verify all entries via iterator
         let iter_entries: Vec<_> = tree.iter().collect();
         assert_eq!(iter_entries.len(), 10000);
-        for i in 0..10000 {
-            assert_eq!(iter_entries[i].0, i as i64);
+        for (i, entry) in iter_entries.iter().enumerate() {
+            assert_eq!(entry.0, i as i64);
         }
     }
 
@@ -2169,6 +2723,58 @@ mod tests {
         assert_eq!(first.0, -50);
     }
 
+    #[test]
+    fn test_bulk_load_fill_factor() {
+        let leaf_sizes = |tree: &BPlusTree| {
+            let mut sizes = Vec::new();
+            let mut current = tree.first_leaf;
+            while let Some(id) = current {
+                let leaf = tree.get_node(id).and_then(|n| n.as_leaf()).unwrap();
+                sizes.push(leaf.keys.len());
+                current = leaf.next;
+            }
+            sizes
+        };
+        let entries: Vec<(i64, RecordId)> =
+            (0..100).map(|i| (i * 10, rid(1, i as usize))).collect();
+
+        // Order 11: up to 10 entries per leaf, at least 5
+        let mut tree = BPlusTree::new(11).unwrap();
+        tree.bulk_load_with_fill_factor(entries.iter().copied(), 70)
+            .unwrap();
+        assert_eq!(tree.len(), 100);
+        assert!(leaf_sizes(&tree).iter().all(|&n| (5..=7).contains(&n)));
+        validate_btree_structure(&tree).unwrap();
+
+        // One insert per leaf fits into the headroom without any split
+        let nodes_before = tree.node_count();
+        for i in (0..100).step_by(7) {
+            tree.insert(i * 10 + 5, rid(2, i as usize)).unwrap();
+        }
+        assert_eq!(tree.node_count(), nodes_before);
+        validate_btree_structure(&tree).unwrap();
+
+        // The default packs leaves completely
+        let mut full = BPlusTree::new(11).unwrap();
+        full.bulk_load_from_slice(&entries).unwrap();
+        assert!(leaf_sizes(&full).iter().all(|&n| n == 10));
+
+        // Very low factors are clamped to the minimum occupancy
+        let mut sparse = BPlusTree::new(11).unwrap();
+        sparse
+            .bulk_load_from_slice_with_fill_factor(&entries, 10)
+            .unwrap();
+        assert!(leaf_sizes(&sparse).iter().all(|&n| n == 5));
+        validate_btree_structure(&sparse).unwrap();
+
+        for bad in [0, 101] {
+            assert!(matches!(
+                tree.bulk_load_from_slice_with_fill_factor(&entries, bad),
+                Err(BPlusTreeError::InvalidFillFactor(f)) if f == bad
+            ));
+        }
+    }
+
     #[test]
     fn test_btree_constraints_after_bulk_load_small() {
         let mut tree = BPlusTree::new(4).unwrap();
@@ -2388,4 +2994,180 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_non_record_id_values() {
+        // e.g. a per-key counter rather than a record location
+        let mut tree: BPlusTree<u64> = BPlusTree::new(4).unwrap();
+        for i in 0..100 {
+            tree.insert(i, (i * 2) as u64).unwrap();
+        }
+        tree.insert(7, 1000).unwrap();
+
+        assert_eq!(tree.len(), 101);
+        assert_eq!(tree.search(42), Some(84));
+        assert_eq!(tree.search_all(7), vec![14, 1000]);
+        assert_eq!(
+            tree.range_search(10, 12),
+            vec![(10, 20), (11, 22), (12, 24)]
+        );
+
+        assert!(tree.delete_entry(7, 1000).unwrap());
+        assert!(!tree.delete_entry(7, 1000).unwrap());
+        assert_eq!(tree.search_all(7), vec![14]);
+
+        let mut loaded: BPlusTree<u64> = BPlusTree::new(4).unwrap();
+        loaded.bulk_load(tree.iter()).unwrap();
+        assert_eq!(
+            loaded.iter().collect::<Vec<_>>(),
+            tree.iter().collect::<Vec<_>>()
+        );
+    }
+
+    /// Small deterministic PRNG (xorshift64*) so failures reproduce from the seed
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+    }
+
+    /// Apply a random interleaving of inserts, deletes and lookups to both the
+    /// tree and a `BTreeMap` oracle, checking they agree and the tree stays
+    /// structurally valid after every operation
+    fn run_random_ops(order: usize, seed: u64, ops: usize, key_space: u64) {
+        let mut rng = XorShift(seed);
+        let mut tree = BPlusTree::new(order).unwrap();
+        let mut oracle: std::collections::BTreeMap<i64, Vec<RecordId>> =
+            std::collections::BTreeMap::new();
+        let mut next_slot = 0usize;
+        let mut oracle_len = 0usize;
+
+        for step in 0..ops {
+            let key = rng.below(key_space) as i64;
+            let context = format!("order={} seed={} step={}", order, seed, step);
+            match rng.below(10) {
+                // Insert, biased so the tree grows before shrinking
+                0..=4 => {
+                    let value = rid(1, next_slot);
+                    next_slot += 1;
+                    tree.insert(key, value).unwrap();
+                    oracle.entry(key).or_default().push(value);
+                    oracle_len += 1;
+                }
+                // Delete a specific entry, or a missing one
+                5..=7 => {
+                    let value = match oracle.get(&key) {
+                        Some(values) if rng.below(4) != 0 => {
+                            values[rng.below(values.len() as u64) as usize]
+                        }
+                        _ => rid(2, rng.below(8) as usize),
+                    };
+                    let expected = match oracle.get_mut(&key) {
+                        Some(values) => match values.iter().position(|&v| v == value) {
+                            Some(pos) => {
+                                values.remove(pos);
+                                if values.is_empty() {
+                                    oracle.remove(&key);
+                                }
+                                oracle_len -= 1;
+                                true
+                            }
+                            None => false,
+                        },
+                        None => false,
+                    };
+                    assert_eq!(
+                        tree.delete_entry(key, value).unwrap(),
+                        expected,
+                        "{}",
+                        context
+                    );
+                }
+                // Delete the first entry for a key; which duplicate that is depends on
+                // the tree's layout, so ask `search` (same leaf, same rule) for it
+                8 => {
+                    let victim = tree.search(key);
+                    let expected = match (oracle.get_mut(&key), victim) {
+                        (Some(values), Some(victim)) => {
+                            let pos = values.iter().position(|&v| v == victim).unwrap();
+                            values.remove(pos);
+                            if values.is_empty() {
+                                oracle.remove(&key);
+                            }
+                            oracle_len -= 1;
+                            true
+                        }
+                        (None, None) => false,
+                        (values, victim) => {
+                            panic!(
+                                "{}: oracle has {:?}, search found {:?}",
+                                context, values, victim
+                            )
+                        }
+                    };
+                    let deleted = tree.delete(key).unwrap();
+                    assert_eq!(deleted, expected, "{}", context);
+                }
+                // Point and range lookups
+                _ => {
+                    let mut found = tree.search_all(key);
+                    found.sort_by_key(|r| r.slot_id);
+                    let mut expected = oracle.get(&key).cloned().unwrap_or_default();
+                    expected.sort_by_key(|r| r.slot_id);
+                    assert_eq!(found, expected, "{}", context);
+
+                    let upper = key + rng.below(key_space / 4 + 1) as i64;
+                    let mut found = tree.range_search(key, upper);
+                    found.sort_by_key(|&(k, r)| (k, r.slot_id));
+                    let mut expected: Vec<(i64, RecordId)> = oracle
+                        .range(key..=upper)
+                        .flat_map(|(&k, values)| values.iter().map(move |&v| (k, v)))
+                        .collect();
+                    expected.sort_by_key(|&(k, r)| (k, r.slot_id));
+                    assert_eq!(found, expected, "{}", context);
+                }
+            }
+
+            assert_eq!(tree.len(), oracle_len, "{}", context);
+            if let Err(err) = validate_btree_structure(&tree) {
+                panic!("{}: {}", context, err);
+            }
+        }
+
+        // The full scan must match the oracle in key order
+        let scanned: Vec<i64> = tree.iter().map(|(k, _)| k).collect();
+        let expected: Vec<i64> = oracle
+            .iter()
+            .flat_map(|(&k, values)| std::iter::repeat_n(k, values.len()))
+            .collect();
+        assert_eq!(scanned, expected, "order={} seed={}", order, seed);
+    }
+
+    #[test]
+    fn test_random_ops_match_oracle() {
+        for order in [3, 4, 5, 8] {
+            for seed in 1..=6u64 {
+                run_random_ops(order, seed.wrapping_mul(0x9E37_79B9_7F4A_7C15), 800, 200);
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_ops_heavy_duplicates() {
+        // A tiny key space makes long runs of equal keys span several leaves
+        for order in [3, 4, 6] {
+            for seed in 1..=6u64 {
+                run_random_ops(order, seed.wrapping_mul(0xD1B5_4A32_D192_ED03), 800, 12);
+            }
+        }
+    }
 }
@@ -74,34 +74,38 @@ impl InternalNode {
     }
 }
 
-/// Leaf node: stores key-value pairs, linked to next leaf
+/// Leaf node: stores key-value pairs, linked to its neighbouring leaves
 #[derive(Debug, Clone)]
-pub struct LeafNode {
+pub struct LeafNode<V = RecordId> {
     /// Keys (sorted)
     pub keys: Vec<BPlusKey>,
-    /// Values (RecordIds) corresponding to keys
-    pub values: Vec<RecordId>,
+    /// Values corresponding to keys
+    pub values: Vec<V>,
     /// Link to next leaf for range queries
     pub next: Option<NodeId>,
+    /// Link to previous leaf for reverse scans
+    pub prev: Option<NodeId>,
 }
 
-impl LeafNode {
+impl<V: Copy + PartialEq> LeafNode<V> {
     /// Create a new empty leaf node
     pub fn new() -> Self {
         Self {
             keys: Vec::new(),
             values: Vec::new(),
             next: None,
+            prev: None,
         }
     }
 
     /// Create a leaf node with given entries
-    pub fn with_entries(keys: Vec<BPlusKey>, values: Vec<RecordId>) -> Self {
+    pub fn with_entries(keys: Vec<BPlusKey>, values: Vec<V>) -> Self {
         debug_assert_eq!(keys.len(), values.len());
         Self {
             keys,
             values,
             next: None,
+            prev: None,
         }
     }
 
@@ -117,7 +121,7 @@ impl LeafNode {
 
     /// Insert a key-value pair in sorted order
     /// Allows duplicate keys
-    pub fn insert(&mut self, key: BPlusKey, value: RecordId) {
+    pub fn insert(&mut self, key: BPlusKey, value: V) {
         // Find insertion position (insert after existing keys with same value for stability)
         let pos = self
             .keys
@@ -128,8 +132,8 @@ impl LeafNode {
         self.values.insert(pos, value);
     }
 
-    /// Search for a key, return the first matching RecordId
-    pub fn search(&self, key: BPlusKey) -> Option<RecordId> {
+    /// Search for a key, return the first matching value
+    pub fn search(&self, key: BPlusKey) -> Option<V> {
         for (i, &k) in self.keys.iter().enumerate() {
             if k == key {
                 return Some(self.values[i]);
@@ -142,7 +146,7 @@ impl LeafNode {
     }
 
     /// Search for all entries with the given key
-    pub fn search_all(&self, key: BPlusKey) -> Vec<RecordId> {
+    pub fn search_all(&self, key: BPlusKey) -> Vec<V> {
         let mut results = Vec::new();
         for (i, &k) in self.keys.iter().enumerate() {
             match k.cmp(&key) {
@@ -172,9 +176,9 @@ impl LeafNode {
 
     /// Delete a specific key-value pair
     /// Returns true if the entry was found and deleted
-    pub fn delete_entry(&mut self, key: BPlusKey, rid: RecordId) -> bool {
+    pub fn delete_entry(&mut self, key: BPlusKey, value: V) -> bool {
         for i in 0..self.keys.len() {
-            if self.keys[i] == key && self.values[i] == rid {
+            if self.keys[i] == key && self.values[i] == value {
                 self.keys.remove(i);
                 self.values.remove(i);
                 return true;
@@ -197,8 +201,9 @@ impl LeafNode {
     }
 
     /// Split this leaf node, returning the new right sibling
-    /// This node keeps the first half, new node gets the second half
-    pub fn split(&mut self) -> LeafNode {
+    /// This node keeps the first half, new node gets the second half.
+    /// The caller links the two halves, since only it knows the new node's ID.
+    pub fn split(&mut self) -> LeafNode<V> {
         let mid = self.keys.len() / 2;
 
         let right_keys = self.keys.split_off(mid);
@@ -211,7 +216,7 @@ impl LeafNode {
     }
 }
 
-impl Default for LeafNode {
+impl<V: Copy + PartialEq> Default for LeafNode<V> {
     fn default() -> Self {
         Self::new()
     }
@@ -219,12 +224,12 @@ impl Default for LeafNode {
 
 /// B+ tree node (either internal or leaf)
 #[derive(Debug, Clone)]
-pub enum BPlusNode {
+pub enum BPlusNode<V = RecordId> {
     Internal(InternalNode),
-    Leaf(LeafNode),
+    Leaf(LeafNode<V>),
 }
 
-impl BPlusNode {
+impl<V: Copy + PartialEq> BPlusNode<V> {
     /// Check if this is a leaf node
     pub fn is_leaf(&self) -> bool {
         matches!(self, BPlusNode::Leaf(_))
@@ -260,7 +265,7 @@ impl BPlusNode {
     }
 
     /// Get as leaf node reference
-    pub fn as_leaf(&self) -> Option<&LeafNode> {
+    pub fn as_leaf(&self) -> Option<&LeafNode<V>> {
         match self {
             BPlusNode::Internal(_) => None,
             BPlusNode::Leaf(node) => Some(node),
@@ -268,7 +273,7 @@ impl BPlusNode {
     }
 
     /// Get as leaf node mutable reference
-    pub fn as_leaf_mut(&mut self) -> Option<&mut LeafNode> {
+    pub fn as_leaf_mut(&mut self) -> Option<&mut LeafNode<V>> {
         match self {
             BPlusNode::Internal(_) => None,
             BPlusNode::Leaf(node) => Some(node),
@@ -1,11 +1,12 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
-use crate::lexer_parser::{ColumnType, Value as ParserValue};
-use crate::record::{DataType, Value as RecordValue};
+use crate::lexer_parser::{ColumnType, DefaultValue as ParserDefault, Value as ParserValue};
+use crate::record::{DataType, Value as RecordValue, parse_decimal};
 
 #[derive(Debug, Error)]
 pub enum CatalogError {
@@ -20,6 +21,12 @@ pub enum CatalogError {
 
     #[error("Column {0} not found")]
     ColumnNotFound(String),
+
+    #[error("Column {0} has invalid type {1}")]
+    InvalidColumnType(String, String),
+
+    #[error("Column {0} has invalid default {1}")]
+    InvalidDefault(String, String),
 }
 
 pub type CatalogResult<T> = Result<T, CatalogError>;
@@ -28,67 +35,214 @@ pub type CatalogResult<T> = Result<T, CatalogError>;
 pub struct ColumnMetadata {
     pub name: String,
     #[serde(rename = "type")]
-    pub column_type: String, // Store as string for JSON: "INT", "FLOAT", "VARCHAR(n)"
+    pub column_type: String, // Store as string for JSON: "INT", "FLOAT", "VARCHAR(n)", "DECIMAL(p,s)", "BOOLEAN"
     pub not_null: bool,
     pub default_value: Option<String>, // Store as string for JSON
+    /// `DEFAULT NULL` was spelled out; `default_value` is `None` in that case too,
+    /// so this is what tells it apart from a column without any DEFAULT clause.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub default_null: bool,
+    /// `CURRENT_DATE` or `CURRENT_TIMESTAMP` for a default evaluated at insert time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_function: Option<String>,
+    /// A VARCHAR stored with its actual length; VARCHAR columns of catalogs
+    /// written before variable-length storage load as fixed-width
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub variable_length: bool,
+}
+
+/// A column default as declared, before it is evaluated for a new row
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefaultValue {
+    Literal(RecordValue),
+    /// Today's UTC date as `YYYY-MM-DD`
+    CurrentDate,
+    /// The current UTC time as `YYYY-MM-DD HH:MM:SS`
+    CurrentTimestamp,
+}
+
+impl DefaultValue {
+    /// Width of the text a dynamic default produces
+    pub fn text_len(&self) -> Option<usize> {
+        match self {
+            DefaultValue::Literal(_) => None,
+            DefaultValue::CurrentDate => Some(10),
+            DefaultValue::CurrentTimestamp => Some(19),
+        }
+    }
+
+    /// The value a row inserted now gets
+    pub fn evaluate(&self) -> RecordValue {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let (year, month, day) = civil_from_days((secs / 86400) as i64);
+        match self {
+            DefaultValue::Literal(value) => value.clone(),
+            DefaultValue::CurrentDate => {
+                RecordValue::String(format!("{:04}-{:02}-{:02}", year, month, day))
+            }
+            DefaultValue::CurrentTimestamp => {
+                let time = secs % 86400;
+                RecordValue::String(format!(
+                    "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                    year,
+                    month,
+                    day,
+                    time / 3600,
+                    time / 60 % 60,
+                    time % 60
+                ))
+            }
+        }
+    }
+}
+
+/// Gregorian (year, month, day) of a day count since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // Shift the epoch to 0000-03-01 so leap days fall at the end of each year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
 }
 
 impl ColumnMetadata {
-    pub fn from_parser(name: String, ct: ColumnType, not_null: bool, default: ParserValue) -> Self {
-        let column_type = match ct {
-            ColumnType::Int => "INT".to_string(),
-            ColumnType::Float => "FLOAT".to_string(),
-            ColumnType::Char(n) => format!("VARCHAR({})", n),
-        };
+    pub fn from_parser(
+        name: String,
+        ct: ColumnType,
+        not_null: bool,
+        default: Option<ParserDefault>,
+    ) -> Self {
+        let column_type = Self::type_name(&ct);
 
-        let default_value = match default {
-            ParserValue::Null => None,
-            ParserValue::Integer(i) => Some(i.to_string()),
-            ParserValue::Float(f) => Some(f.to_string()),
-            ParserValue::String(s) => Some(s),
+        let default_null = matches!(default, Some(ParserDefault::Literal(ParserValue::Null)));
+        let (default_value, default_function) = match default {
+            // The database rejects unbound placeholders before building metadata
+            None
+            | Some(ParserDefault::Literal(ParserValue::Null))
+            | Some(ParserDefault::Literal(ParserValue::Placeholder(_))) => (None, None),
+            Some(ParserDefault::Literal(ParserValue::Integer(i))) => (Some(i.to_string()), None),
+            Some(ParserDefault::Literal(ParserValue::Float(f))) => (Some(f.to_string()), None),
+            Some(ParserDefault::Literal(ParserValue::String(s))) => (Some(s), None),
+            Some(ParserDefault::Literal(ParserValue::Bool(b))) => (Some(b.to_string()), None),
+            Some(ParserDefault::CurrentDate) => (None, Some("CURRENT_DATE".to_string())),
+            Some(ParserDefault::CurrentTimestamp) => (None, Some("CURRENT_TIMESTAMP".to_string())),
+            // Kept in the table's `auto_increment` counters instead
+            Some(ParserDefault::AutoIncrement) => (None, None),
         };
 
         Self {
             name,
+            variable_length: matches!(ct, ColumnType::Char(_)),
             column_type,
             not_null,
             default_value,
+            default_null,
+            default_function,
+        }
+    }
+
+    /// Catalog spelling of a column type, e.g. `VARCHAR(20)`
+    pub fn type_name(ct: &ColumnType) -> String {
+        match ct {
+            ColumnType::Int => "INT".to_string(),
+            ColumnType::Float => "FLOAT".to_string(),
+            ColumnType::Char(n) => format!("VARCHAR({})", n),
+            ColumnType::Decimal(precision, scale) => format!("DECIMAL({},{})", precision, scale),
+            ColumnType::Bool => "BOOLEAN".to_string(),
+        }
+    }
+
+    /// The column's DEFAULT clause, `None` when there is none
+    pub fn declared_default(&self) -> Option<DefaultValue> {
+        match self.default_function.as_deref() {
+            Some("CURRENT_DATE") => Some(DefaultValue::CurrentDate),
+            Some("CURRENT_TIMESTAMP") => Some(DefaultValue::CurrentTimestamp),
+            _ if self.default_value.is_some() || self.default_null => {
+                Some(DefaultValue::Literal(self.parse_default_value()))
+            }
+            _ => None,
         }
     }
 
+    /// The column's default for a row inserted now: `None` when there is no
+    /// DEFAULT clause, `Some(Value::Null)` for an explicit `DEFAULT NULL`.
+    pub fn default(&self) -> Option<RecordValue> {
+        self.declared_default().map(|default| default.evaluate())
+    }
+
+    /// The column's storage type. Columns are checked when the catalog is
+    /// loaded, so this cannot fail afterwards.
     pub fn to_data_type(&self) -> DataType {
+        self.try_data_type()
+            .expect("column types are checked by DatabaseMetadata::load")
+    }
+
+    /// Parse the stored type string, which a hand-edited or damaged
+    /// metadata.json may have left malformed
+    pub fn try_data_type(&self) -> CatalogResult<DataType> {
+        let invalid =
+            || CatalogError::InvalidColumnType(self.name.clone(), self.column_type.clone());
+        let sized = |prefix: &str| {
+            self.column_type
+                .strip_prefix(prefix)
+                .and_then(|spec| spec.strip_suffix(')'))
+        };
         if self.column_type == "INT" {
-            DataType::Int
+            Ok(DataType::Int)
         } else if self.column_type == "FLOAT" {
-            DataType::Float
-        } else if self.column_type.starts_with("VARCHAR(") {
-            let size: usize = self.column_type[8..self.column_type.len() - 1]
-                .parse()
-                .unwrap();
-            DataType::Char(size)
-        } else if self.column_type.starts_with("CHAR(") {
+            Ok(DataType::Float)
+        } else if self.column_type == "BOOLEAN" {
+            Ok(DataType::Bool)
+        } else if let Some(size) = sized("VARCHAR(") {
+            let size: usize = size.parse().map_err(|_| invalid())?;
+            if self.variable_length {
+                Ok(DataType::Varchar(size))
+            } else {
+                Ok(DataType::Char(size))
+            }
+        } else if let Some(size) = sized("CHAR(") {
             // Backward compatibility for existing metadata
-            let size: usize = self.column_type[5..self.column_type.len() - 1]
-                .parse()
-                .unwrap();
-            DataType::Char(size)
+            Ok(DataType::Char(size.parse().map_err(|_| invalid())?))
+        } else if let Some(spec) = sized("DECIMAL(") {
+            let (precision, scale) = spec.split_once(',').ok_or_else(invalid)?;
+            let precision = precision.parse().map_err(|_| invalid())?;
+            let scale = scale.parse().map_err(|_| invalid())?;
+            Ok(DataType::Decimal(precision, scale))
         } else {
-            panic!("Unknown column type: {}", self.column_type);
+            Err(invalid())
         }
     }
 
+    /// The stored literal default, checked like the type by
+    /// `DatabaseMetadata::load`
     pub fn parse_default_value(&self) -> RecordValue {
-        match &self.default_value {
-            None => RecordValue::Null,
-            Some(s) => {
-                if self.column_type == "INT" {
-                    RecordValue::Int(s.parse().unwrap())
-                } else if self.column_type == "FLOAT" {
-                    RecordValue::Float(s.parse().unwrap())
-                } else {
-                    RecordValue::String(s.clone())
-                }
-            }
+        self.try_default_value()
+            .expect("column defaults are checked by DatabaseMetadata::load")
+    }
+
+    /// Parse the stored literal default for the column's type
+    pub fn try_default_value(&self) -> CatalogResult<RecordValue> {
+        let Some(s) = &self.default_value else {
+            return Ok(RecordValue::Null);
+        };
+        let invalid = || CatalogError::InvalidDefault(self.name.clone(), s.clone());
+        match self.try_data_type()? {
+            DataType::Int => s.parse().map(RecordValue::Int).map_err(|_| invalid()),
+            DataType::Float => s.parse().map(RecordValue::Float).map_err(|_| invalid()),
+            DataType::Bool => s.parse().map(RecordValue::Bool).map_err(|_| invalid()),
+            DataType::Decimal(_, scale) => parse_decimal(s, scale)
+                .map(|value| RecordValue::Decimal(value, scale))
+                .ok_or_else(invalid),
+            DataType::Char(_) | DataType::Varchar(_) => Ok(RecordValue::String(s.clone())),
         }
     }
 }
@@ -99,6 +253,14 @@ pub struct IndexMetadata {
     pub columns: Vec<String>,
     #[serde(default)]
     pub implicit: bool,
+    /// B+ tree order requested with `WITH (order = N)`; `None` means the
+    /// default order.
+    #[serde(default)]
+    pub order: Option<usize>,
+    /// Set for indexes created by a `UNIQUE` column constraint. Uniqueness is
+    /// enforced on every column type, but only INT columns get a B+ tree.
+    #[serde(default)]
+    pub unique: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -116,6 +278,13 @@ pub struct TableMetadata {
     pub primary_key: Option<Vec<String>>,
     pub foreign_keys: Vec<ForeignKeyMetadata>,
     pub indexes: Vec<IndexMetadata>,
+    /// Data version, bumped by every statement that inserts, updates or
+    /// deletes rows. Catalogs written before it existed load as version 0.
+    #[serde(default)]
+    pub version: u64,
+    /// Next value of each AUTO_INCREMENT column, by column name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub auto_increment: BTreeMap<String, i64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -135,7 +304,13 @@ impl DatabaseMetadata {
     pub fn load(db_path: &Path) -> CatalogResult<Self> {
         let metadata_path = db_path.join("metadata.json");
         let content = fs::read_to_string(&metadata_path)?;
-        let metadata = serde_json::from_str(&content)?;
+        let metadata: Self = serde_json::from_str(&content)?;
+        for table in metadata.tables.values() {
+            for column in &table.columns {
+                column.try_data_type()?;
+                column.try_default_value()?;
+            }
+        }
         Ok(metadata)
     }
 
@@ -169,3 +344,100 @@ impl DatabaseMetadata {
             .ok_or_else(|| CatalogError::TableNotFound(name.to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(20_453), (2025, 12, 31));
+    }
+
+    #[test]
+    fn test_varchar_storage_follows_catalog() {
+        let column = ColumnMetadata::from_parser("c".to_string(), ColumnType::Char(8), false, None);
+        assert_eq!(column.column_type, "VARCHAR(8)");
+        assert_eq!(column.to_data_type(), DataType::Varchar(8));
+
+        // Catalogs from before variable-length storage keep fixed-width rows
+        let legacy: ColumnMetadata = serde_json::from_str(
+            r#"{"name":"c","type":"VARCHAR(8)","not_null":false,"default_value":null}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.to_data_type(), DataType::Char(8));
+        let round_trip: ColumnMetadata =
+            serde_json::from_str(&serde_json::to_string(&column).unwrap()).unwrap();
+        assert_eq!(round_trip.to_data_type(), DataType::Varchar(8));
+    }
+
+    #[test]
+    fn test_malformed_column_type_fails_to_load() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let write_type = |column_type: &str| {
+            let json = format!(
+                r#"{{"name":"db","tables":{{"t":{{"name":"t","columns":[{{"name":"price","type":"{}","not_null":false,"default_value":null}}],"primary_key":null,"foreign_keys":[],"indexes":[]}}}}}}"#,
+                column_type
+            );
+            fs::write(temp_dir.path().join("metadata.json"), json).unwrap();
+        };
+
+        write_type("DECIMAL(10,2)");
+        let metadata = DatabaseMetadata::load(temp_dir.path()).unwrap();
+        assert_eq!(
+            metadata.tables["t"].columns[0].to_data_type(),
+            DataType::Decimal(10, 2)
+        );
+
+        for column_type in ["DECIMAL(10)", "DECIMAL(x,2)", "VARCHAR()", "TEXT"] {
+            write_type(column_type);
+            assert!(
+                matches!(
+                    DatabaseMetadata::load(temp_dir.path()),
+                    Err(CatalogError::InvalidColumnType(column, found))
+                        if column == "price" && found == column_type
+                ),
+                "{}",
+                column_type
+            );
+        }
+    }
+
+    #[test]
+    fn test_malformed_default_fails_to_load() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let write_default = |column_type: &str, default: &str| {
+            let json = format!(
+                r#"{{"name":"db","tables":{{"t":{{"name":"t","columns":[{{"name":"flag","type":"{}","not_null":false,"default_value":"{}"}}],"primary_key":null,"foreign_keys":[],"indexes":[]}}}}}}"#,
+                column_type, default
+            );
+            fs::write(temp_dir.path().join("metadata.json"), json).unwrap();
+        };
+
+        write_default("BOOLEAN", "true");
+        let metadata = DatabaseMetadata::load(temp_dir.path()).unwrap();
+        assert_eq!(
+            metadata.tables["t"].columns[0].parse_default_value(),
+            RecordValue::Bool(true)
+        );
+
+        for (column_type, default) in [("BOOLEAN", "yes"), ("INT", "1.5"), ("DECIMAL(4,1)", "x")] {
+            write_default(column_type, default);
+            assert!(
+                matches!(
+                    DatabaseMetadata::load(temp_dir.path()),
+                    Err(CatalogError::InvalidDefault(column, found))
+                        if column == "flag" && found == default
+                ),
+                "{} DEFAULT {}",
+                column_type,
+                default
+            );
+        }
+    }
+}
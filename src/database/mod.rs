@@ -1,32 +1,56 @@
-use csv::ReaderBuilder;
-use regex::Regex;
+mod prepared;
+mod registry;
+
+pub use prepared::PreparedStatement;
+pub use registry::{QueryRegistry, RunningQuery};
+
+use csv::{ReaderBuilder, WriterBuilder};
+use regex::{Regex, RegexBuilder};
 use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+use crate::btree::DEFAULT_ORDER;
 use crate::catalog::{
     CatalogError, ColumnMetadata, DatabaseMetadata, ForeignKeyMetadata, IndexMetadata,
     TableMetadata,
 };
-use crate::file::{BufferManager, PagedFileManager};
+use crate::file::{BufferManager, BufferStats, EvictionPolicy, PagedFileManager};
 use crate::index::{IndexError, IndexManager};
 use crate::lexer_parser::{
-    AlterStatement, CreateTableField, DBStatement, Expression, Operator, SelectClause, Selector,
-    Selectors, TableColumn, TableStatement, Value as ParserValue, WhereClause,
+    AlterStatement, ArithmeticOperator, CastType, ColumnType, CreateTableField, DBStatement,
+    DefaultValue as ParserDefault, Expression, Operator, Query, SelectClause, Selector, Selectors,
+    TableColumn, TableStatement, Value as ParserValue, WhereClause,
 };
 use crate::record::{
-    ColumnDef, DataType, Record, RecordId, RecordManager, TableFile, TableScanIter, TableSchema,
-    Value as RecordValue,
+    ColumnDef, DataType, MAX_DECIMAL_PRECISION, Page, Record, RecordError, RecordId, RecordManager,
+    TableScanIter, TableSchema, Value as RecordValue, compare_decimals, decimal_fits,
+    decimal_to_f64, format_decimal, parse_decimal, rescale_decimal,
 };
 
+/// Longest LIKE pattern accepted, in characters
+pub const MAX_LIKE_PATTERN_LEN: usize = 1024;
+
+/// Upper bound on the compiled size of a LIKE pattern's regex, in bytes
+pub const LIKE_REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+/// Undo log in the data directory; database names cannot start with a dot,
+/// so it never clashes with one
+const WAL_FILE_NAME: &str = ".wal";
+
 #[derive(Debug, Error)]
 pub enum DatabaseError {
     #[error("Database {0} already exists")]
     DatabaseExists(String),
 
+    #[error("Invalid database name {0}: names cannot start with '.'")]
+    InvalidDatabaseName(String),
+
     #[error("Database {0} not found")]
     DatabaseNotFound(String),
 
@@ -42,6 +66,9 @@ pub enum DatabaseError {
     #[error("Column {0} not found in table {1}")]
     ColumnNotFound(String, String),
 
+    #[error("Column {0} not found in any of the joined tables ({1})")]
+    ColumnNotFoundInJoin(String, String),
+
     #[error("Type mismatch: {0}")]
     TypeMismatch(String),
 
@@ -60,8 +87,11 @@ pub enum DatabaseError {
     #[error("File error: {0}")]
     FileError(#[from] crate::file::FileError),
 
-    #[error("Primary key violation: duplicate key value")]
-    PrimaryKeyViolation,
+    #[error("Primary key violation: duplicate key value {0}")]
+    PrimaryKeyViolation(String),
+
+    #[error("Unique constraint {0} violated: duplicate key value {1}")]
+    UniqueViolation(String, String),
 
     #[error("primary")]
     PrimaryKeyError,
@@ -69,11 +99,44 @@ pub enum DatabaseError {
     #[error("Foreign key violation: {0}")]
     ForeignKeyViolation(String),
 
+    #[error(
+        "Foreign key cycle {0}: each table needs an existing row in the next, so none of them can be populated"
+    )]
+    ForeignKeyCycle(String),
+
     #[error("Not null constraint violation for column {0}")]
     NotNullViolation(String),
 
     #[error("Column {0} specified more than once")]
     DuplicateColumn(String),
+
+    #[error("Query exceeded timeout of {0} ms")]
+    Timeout(u128),
+
+    #[error("Parse error: {0}")]
+    ParseError(String),
+
+    #[error("No value bound for parameter {0}")]
+    UnboundParameter(usize),
+
+    #[error("Statement takes {0} parameters but {1} were given")]
+    ParameterCount(usize, usize),
+
+    #[error("Invalid delimiter {0:?}: must be a single ASCII character")]
+    InvalidDelimiter(char),
+
+    #[error("Query {0} was cancelled")]
+    Cancelled(u64),
+
+    #[error("A transaction is already in progress")]
+    TransactionInProgress,
+
+    #[error("No transaction is in progress")]
+    NoTransaction,
+
+    /// Statements changing the catalog, which ROLLBACK does not restore
+    #[error("{0} is not allowed inside a transaction; COMMIT or ROLLBACK first")]
+    NotAllowedInTransaction(String),
 }
 
 pub type DatabaseResult<T> = Result<T, DatabaseError>;
@@ -82,24 +145,122 @@ pub struct DatabaseManager {
     data_dir: PathBuf,
     current_db: Option<String>,
     current_metadata: Option<DatabaseMetadata>,
-    buffer_manager: Arc<Mutex<BufferManager>>,
+    buffer_manager: Arc<RwLock<BufferManager>>,
     record_manager: RecordManager,
     index_manager: IndexManager,
     use_indexes: bool,
+    /// Abort `SELECT`/`UPDATE`/`DELETE` statements running longer than this
+    statement_timeout: Option<Duration>,
+    /// Where statements run through `execute_query` are listed for cancellation
+    query_registry: Option<Arc<QueryRegistry>>,
+    /// Id and cancel flag of the registered statement now executing
+    running_query: Option<(u64, Arc<AtomicBool>)>,
+    /// Database catalogs for in-memory mode, standing in for `metadata.json`
+    memory_catalog: Option<HashMap<String, DatabaseMetadata>>,
+    /// Transaction opened by `BEGIN`, if any
+    transaction: Option<Transaction>,
+}
+
+/// State of the transaction opened by `BEGIN`
+///
+/// The transaction is the outermost logged unit: the statements in it only
+/// dirty pages in the buffer pool, and pages evicted before `COMMIT` have
+/// their before-images in the write-ahead log, so `ROLLBACK` (or recovery
+/// after a crash) can restore every table and index file.
+struct Transaction {
+    /// Data version of each table when the transaction began
+    table_versions: HashMap<String, u64>,
+}
+
+/// Index key of a single-column index entry, or `None` for a value that is
+/// not indexed (NULL)
+///
+/// INT values are their own key. VARCHAR values are keyed by a 64-bit
+/// FNV-1a hash of their bytes, so the B+ tree stays keyed on `i64`: a string
+/// index answers equality only, and since different strings may share a
+/// hash, every row it returns must be checked against the query again, as
+/// `index_candidates_for_where` callers already do. FNV-1a is used rather
+/// than `std`'s hasher because the keys are stored on disk and must not
+/// change between builds.
+fn index_value_key(value: &RecordValue) -> Option<i64> {
+    match value {
+        RecordValue::Int(val) => Some(*val as i64),
+        RecordValue::String(s) => Some(string_index_key(s)),
+        RecordValue::Float(_)
+        | RecordValue::Decimal(..)
+        | RecordValue::Bool(_)
+        | RecordValue::Null => None,
+    }
+}
+
+/// Like `format_decimal`, for sums that may not fit an `i64`
+fn format_wide_decimal(units: i128, scale: u8) -> String {
+    if let Ok(units) = i64::try_from(units) {
+        return format_decimal(units, scale);
+    }
+    let sign = if units < 0 { "-" } else { "" };
+    let digits = units.unsigned_abs().to_string();
+    let (int_part, frac_part) = digits.split_at(digits.len() - usize::from(scale));
+    if scale == 0 {
+        format!("{}{}", sign, int_part)
+    } else {
+        format!("{}{}.{}", sign, int_part, frac_part)
+    }
+}
+
+/// A BOOLEAN field of a loaded file: `true`/`false` in any case, or `1`/`0`
+fn parse_bool_field(field: &str) -> Option<bool> {
+    if field.eq_ignore_ascii_case("true") || field == "1" {
+        Some(true)
+    } else if field.eq_ignore_ascii_case("false") || field == "0" {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Decimal text such as `-1.250` as units at the scale it is written with,
+/// trailing zeros included
+fn decimal_literal(text: &str) -> Option<(i64, u8)> {
+    let scale = text
+        .trim()
+        .split_once('.')
+        .map_or(0, |(_, frac)| frac.len());
+    let scale = u8::try_from(scale)
+        .ok()
+        .filter(|scale| *scale <= MAX_DECIMAL_PRECISION)?;
+    parse_decimal(text, scale).map(|units| (units, scale))
 }
 
-struct TableIntColumnIter {
+/// Decimal units rescaled from `from` to `to`, or `None` if that would
+/// round away a nonzero digit or overflow
+fn exact_rescale(units: i64, from: u8, to: u8) -> Option<i64> {
+    let rescaled = rescale_decimal(units, from, to)?;
+    (rescale_decimal(rescaled, to, from)? == units).then_some(rescaled)
+}
+
+fn string_index_key(value: &str) -> i64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    let hash = value.bytes().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    });
+    hash as i64
+}
+
+/// Yields `(rid, key)` for building a single-column index, skipping NULLs
+struct TableColumnKeyIter {
     scan_iter: TableScanIter,
     col_idx: usize,
 }
 
-impl TableIntColumnIter {
+impl TableColumnKeyIter {
     fn new(scan_iter: TableScanIter, col_idx: usize) -> Self {
         Self { scan_iter, col_idx }
     }
 }
 
-impl Iterator for TableIntColumnIter {
+impl Iterator for TableColumnKeyIter {
     type Item = crate::index::IndexResult<(RecordId, i64)>;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -107,8 +268,8 @@ impl Iterator for TableIntColumnIter {
             let item = self.scan_iter.next()?;
             match item {
                 Ok((rid, record)) => {
-                    if let Some(RecordValue::Int(val)) = record.get(self.col_idx) {
-                        return Some(Ok((rid, *val as i64)));
+                    if let Some(key) = record.get(self.col_idx).and_then(index_value_key) {
+                        return Some(Ok((rid, key)));
                     }
                 }
                 Err(err) => {
@@ -121,6 +282,12 @@ impl Iterator for TableIntColumnIter {
     }
 }
 
+/// Yields `(rid, composite key)` for building a two-column INT index
+///
+/// Rows with a NULL in either column are skipped, so a composite index only
+/// holds rows where both columns are set. `index_candidates_for_where` relies
+/// on this: it answers a predicate from the index only when no row with a NULL
+/// in the index could satisfy it.
 struct TableCompositeIntColumnIter {
     scan_iter: TableScanIter,
     col_idx_left: usize,
@@ -172,9 +339,25 @@ impl Iterator for TableCompositeIntColumnIter {
 }
 
 struct IndexDef {
-    columns: Vec<String>,
     indices: Vec<usize>,
     storage_name: String,
+    order: usize,
+}
+
+/// How a SELECT, UPDATE or DELETE reaches the rows its WHERE clause can match
+#[derive(Debug, Clone, PartialEq)]
+enum AccessPath {
+    /// The WHERE clause can never hold, so nothing is read
+    NoRows,
+    /// Every row is read and checked
+    FullScan,
+    /// Rows whose key in index `storage_name` falls in one of the inclusive
+    /// `ranges`; a range with equal ends is a single-key lookup
+    IndexScan {
+        storage_name: String,
+        composite: bool,
+        ranges: Vec<(i64, i64)>,
+    },
 }
 
 #[derive(Debug, Default)]
@@ -189,6 +372,8 @@ struct ColumnBounds {
 enum GroupKey {
     Int(i32),
     Float(u64),
+    Decimal(i64),
+    Bool(bool),
     String(String),
     Null,
 }
@@ -197,6 +382,8 @@ enum GroupKey {
 enum NumericType {
     Int,
     Float,
+    /// DECIMAL of the given scale, summed exactly
+    Decimal(u8),
 }
 
 #[derive(Debug, Clone)]
@@ -211,6 +398,7 @@ enum AggSpec {
     },
     Avg {
         col_idx: usize,
+        numeric: NumericType,
     },
     Min {
         col_idx: usize,
@@ -223,22 +411,44 @@ enum AggSpec {
 #[derive(Debug, Clone)]
 enum AggState {
     Count(i64),
-    SumInt { sum: i64, has_value: bool },
-    SumFloat { sum: f64, has_value: bool },
-    Avg { sum: f64, count: i64 },
+    SumInt {
+        sum: i64,
+        has_value: bool,
+    },
+    SumFloat {
+        sum: f64,
+        has_value: bool,
+    },
+    /// Units of 10^-scale
+    SumDecimal {
+        sum: i128,
+        scale: u8,
+        has_value: bool,
+    },
+    Avg {
+        sum: f64,
+        count: i64,
+    },
+    AvgDecimal {
+        sum: i128,
+        scale: u8,
+        count: i64,
+    },
     Min(Option<RecordValue>),
     Max(Option<RecordValue>),
 }
 
 #[derive(Debug, Clone, Copy)]
 enum OutputSelector {
-    GroupKey,
+    /// The value of the GROUP BY column at this position
+    GroupKey(usize),
     Agg(usize),
 }
 
 #[derive(Debug, Clone)]
 struct GroupState {
-    key: RecordValue,
+    /// One value per GROUP BY column
+    key: Vec<RecordValue>,
     aggs: Vec<AggState>,
 }
 
@@ -272,9 +482,92 @@ enum JoinSide {
 
 enum PreparedWhereClause {
     Op(TableColumn, Operator, Expression),
+    CastOp(TableColumn, CastType, Operator, Expression),
     Null(TableColumn),
     NotNull(TableColumn),
     Like(TableColumn, Regex),
+    NotLike(TableColumn, Regex),
+    In(TableColumn, Vec<ParserValue>),
+    /// A constant predicate that is false: no row matches
+    Never,
+    /// Holds when every clause of at least one alternative holds
+    Or(Vec<Vec<PreparedWhereClause>>),
+}
+
+/// Equality join condition answered by looking up the right table's index
+/// with each left row's value
+struct JoinProbe {
+    left_index: usize,
+    storage_name: String,
+}
+
+/// Join column value used as a hash join key
+///
+/// Only built for types whose equality is exact, so two keys are equal
+/// exactly when the values compare equal; NULL has no key since it never
+/// satisfies `=`.
+#[derive(PartialEq, Eq, Hash)]
+enum JoinKey {
+    Int(i32),
+    String(String),
+    Decimal(i64),
+    Bool(bool),
+}
+
+impl JoinKey {
+    fn from_value(value: &RecordValue) -> Option<Self> {
+        match value {
+            RecordValue::Int(v) => Some(JoinKey::Int(*v)),
+            RecordValue::String(v) => Some(JoinKey::String(v.clone())),
+            RecordValue::Decimal(units, _) => Some(JoinKey::Decimal(*units)),
+            RecordValue::Bool(v) => Some(JoinKey::Bool(*v)),
+            RecordValue::Float(_) | RecordValue::Null => None,
+        }
+    }
+}
+
+/// Elapsed-time budget and cancel flag for one statement, checked from inside scan loops
+struct QueryBudget {
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+    cancel: Option<(u64, Arc<AtomicBool>)>,
+    ticks: u32,
+}
+
+impl QueryBudget {
+    /// Rows processed between two clock reads
+    const CHECK_INTERVAL: u32 = 1024;
+
+    fn new(timeout: Option<Duration>, cancel: Option<(u64, Arc<AtomicBool>)>) -> Self {
+        Self {
+            timeout,
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            cancel,
+            ticks: 0,
+        }
+    }
+
+    fn tick(&mut self) -> DatabaseResult<()> {
+        if self.timeout.is_none() && self.cancel.is_none() {
+            return Ok(());
+        }
+        let check = self.ticks.is_multiple_of(Self::CHECK_INTERVAL);
+        self.ticks = self.ticks.wrapping_add(1);
+        if !check {
+            return Ok(());
+        }
+        if let Some((id, cancelled)) = &self.cancel
+            && cancelled.load(AtomicOrdering::Relaxed)
+        {
+            return Err(DatabaseError::Cancelled(*id));
+        }
+        if let (Some(timeout), Some(deadline)) = (self.timeout, self.deadline)
+            && Instant::now() >= deadline
+        {
+            return Err(DatabaseError::Timeout(timeout.as_millis()));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -288,28 +581,264 @@ impl DatabaseManager {
         let data_path = PathBuf::from(data_dir);
         fs::create_dir_all(&data_path)?;
 
-        let file_manager = PagedFileManager::new();
-        let buffer_manager = Arc::new(Mutex::new(BufferManager::new(file_manager)));
+        let mut manager = Self::with_file_manager(data_path, PagedFileManager::new(), None);
+        manager.recover()?;
+        Ok(manager)
+    }
+
+    /// Create a database manager that never touches the filesystem
+    ///
+    /// Table, index and catalog data live only in memory and are discarded
+    /// when the manager is dropped.
+    pub fn in_memory() -> Self {
+        Self::with_file_manager(
+            PathBuf::from(":memory:"),
+            PagedFileManager::in_memory(),
+            Some(HashMap::new()),
+        )
+    }
+
+    fn with_file_manager(
+        data_dir: PathBuf,
+        file_manager: PagedFileManager,
+        memory_catalog: Option<HashMap<String, DatabaseMetadata>>,
+    ) -> Self {
+        let buffer_manager = Arc::new(RwLock::new(BufferManager::new(file_manager)));
         let record_manager = RecordManager::new(buffer_manager.clone());
         let index_manager = IndexManager::new(buffer_manager.clone());
 
-        Ok(Self {
-            data_dir: data_path,
+        Self {
+            data_dir,
             current_db: None,
             current_metadata: None,
             buffer_manager,
             record_manager,
             index_manager,
             use_indexes: true,
-        })
+            statement_timeout: None,
+            query_registry: None,
+            running_query: None,
+            memory_catalog,
+            transaction: None,
+        }
+    }
+
+    /// Roll back a bulk insert, `LOAD DATA INFILE` or index rebuild that the
+    /// process died in the middle of, restoring its table and index files
+    /// from the write-ahead log in the data directory
+    ///
+    /// Called by [`Self::new`] before any file is opened; returns whether
+    /// anything was rolled back.
+    pub fn recover(&mut self) -> DatabaseResult<bool> {
+        let wal_path = self.wal_path();
+        let recovered = self
+            .buffer_manager
+            .write()
+            .unwrap()
+            .file_manager_mut()
+            .recover(wal_path)?;
+        Ok(recovered)
+    }
+
+    fn wal_path(&self) -> PathBuf {
+        self.data_dir.join(WAL_FILE_NAME)
+    }
+
+    /// Run `body` as one logged unit: if the process dies before it returns,
+    /// the next [`Self::recover`] undoes every page it wrote
+    ///
+    /// An error returned by `body` does not roll anything back; as before,
+    /// whatever the statement wrote until then stays.
+    fn logged_unit<T>(
+        &mut self,
+        body: impl FnOnce(&mut Self) -> DatabaseResult<T>,
+    ) -> DatabaseResult<T> {
+        let wal_path = self.wal_path();
+        // Inside a transaction the pages are written at COMMIT instead
+        let outermost = self
+            .buffer_manager
+            .read()
+            .unwrap()
+            .file_manager()
+            .logged_unit_depth()
+            == 0;
+        // Index nodes live in memory until flushed into their pages
+        if outermost {
+            self.index_manager.flush_all()?;
+        }
+        self.buffer_manager
+            .write()
+            .unwrap()
+            .begin_logged_unit(wal_path)?;
+
+        let result = body(self);
+
+        if outermost {
+            self.index_manager.flush_all()?;
+        }
+        self.buffer_manager.write().unwrap().commit_logged_unit()?;
+        result
+    }
+
+    /// Start a transaction (`BEGIN`)
+    ///
+    /// Until [`Self::commit_transaction`], inserts, updates, deletes, loads
+    /// and reindexing of the current database are written only to the
+    /// buffer pool and to in-memory index trees; statements that change the
+    /// catalog (CREATE/DROP/ALTER, `USE`) are refused with
+    /// [`DatabaseError::NotAllowedInTransaction`]. A statement that fails
+    /// inside the transaction keeps what it had written, as outside of one;
+    /// only `ROLLBACK` undoes it.
+    ///
+    /// Isolation is READ UNCOMMITTED: this manager always reads its own
+    /// uncommitted changes, and there are no locks, so another
+    /// `DatabaseManager` on the same data directory may see pages the buffer
+    /// pool had to evict before `COMMIT`. Transactions are meant for a
+    /// single writer.
+    pub fn begin_transaction(&mut self) -> DatabaseResult<()> {
+        if self.transaction.is_some() {
+            return Err(DatabaseError::TransactionInProgress);
+        }
+        let table_versions = self
+            .current_metadata
+            .as_ref()
+            .ok_or(DatabaseError::NoDatabaseSelected)?
+            .tables
+            .iter()
+            .map(|(name, table)| (name.clone(), table.version))
+            .collect();
+
+        let wal_path = self.wal_path();
+        self.index_manager.flush_all()?;
+        self.buffer_manager
+            .write()
+            .unwrap()
+            .begin_logged_unit(wal_path)?;
+        self.transaction = Some(Transaction { table_versions });
+        Ok(())
+    }
+
+    /// Make the open transaction's changes permanent (`COMMIT`), writing
+    /// every dirty table and index page
+    pub fn commit_transaction(&mut self) -> DatabaseResult<()> {
+        self.transaction
+            .take()
+            .ok_or(DatabaseError::NoTransaction)?;
+        self.index_manager.flush_all()?;
+        self.buffer_manager.write().unwrap().commit_logged_unit()?;
+        Ok(())
+    }
+
+    /// Undo every change of the open transaction (`ROLLBACK`)
+    ///
+    /// Dirty pages and in-memory index trees are discarded unwritten, pages
+    /// written early are restored from the write-ahead log, and tables and
+    /// indexes are reopened from disk on next use.
+    pub fn rollback_transaction(&mut self) -> DatabaseResult<()> {
+        let transaction = self
+            .transaction
+            .take()
+            .ok_or(DatabaseError::NoTransaction)?;
+        self.index_manager.discard_all();
+        self.record_manager.close_all_tables();
+        self.buffer_manager
+            .write()
+            .unwrap()
+            .rollback_logged_unit()?;
+
+        // A version read inside the transaction must not match the restored data
+        let mut bumped = false;
+        if let Some(metadata) = &mut self.current_metadata {
+            for (name, table) in metadata.tables.iter_mut() {
+                if transaction.table_versions.get(name) != Some(&table.version) {
+                    table.version += 1;
+                    bumped = true;
+                }
+            }
+        }
+        if bumped {
+            self.save_current_metadata()?;
+        }
+        Ok(())
+    }
+
+    /// Whether `BEGIN` has been run without a matching `COMMIT` or `ROLLBACK`
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    /// Refuse `statement` inside a transaction, since it changes the catalog
+    fn reject_in_transaction(&self, statement: &str) -> DatabaseResult<()> {
+        match self.transaction {
+            Some(_) => Err(DatabaseError::NotAllowedInTransaction(
+                statement.to_string(),
+            )),
+            None => Ok(()),
+        }
     }
 
     pub fn set_use_indexes(&mut self, use_indexes: bool) {
         self.use_indexes = use_indexes;
     }
 
+    /// Limit how long a single `SELECT`, `UPDATE` or `DELETE` may run
+    ///
+    /// `None` disables the limit. The budget is checked while rows are
+    /// scanned, so a statement is aborted with [`DatabaseError::Timeout`]
+    /// shortly after the limit passes rather than at the exact instant.
+    pub fn set_statement_timeout(&mut self, timeout: Option<Duration>) {
+        self.statement_timeout = timeout;
+    }
+
+    /// List statements run through [`Self::execute_query`] in `registry`
+    ///
+    /// Managers serving different connections can share one registry, so a
+    /// statement hung on one connection can be cancelled from another.
+    /// Cancellation is checked where the statement timeout is.
+    pub fn set_query_registry(&mut self, registry: Option<Arc<QueryRegistry>>) {
+        self.query_registry = registry;
+    }
+
+    /// Choose the buffer pool's page replacement policy
+    ///
+    /// Pages already cached stay in the pool; only future evictions change.
+    /// Pair with [`DatabaseManager::buffer_miss_count`] to compare policies.
+    pub fn set_eviction_policy(&mut self, policy: EvictionPolicy) {
+        self.buffer_manager
+            .write()
+            .unwrap()
+            .set_eviction_policy(policy);
+    }
+
+    /// Number of pages the buffer pool has loaded from disk
+    pub fn buffer_miss_count(&self) -> usize {
+        self.buffer_manager.read().unwrap().miss_count()
+    }
+
+    /// Buffer pool hits, misses, evictions and write-backs, as listed by `SHOW STATUS`
+    pub fn buffer_stats(&self) -> BufferStats {
+        self.buffer_manager.read().unwrap().stats()
+    }
+
+    /// Start counting the buffer pool statistics from zero (`FLUSH STATUS`)
+    pub fn reset_buffer_stats(&mut self) {
+        self.buffer_manager.write().unwrap().reset_stats();
+    }
+
     // Database operations
     pub fn create_database(&mut self, name: &str) -> DatabaseResult<()> {
+        // Dot-files in the data directory, like the undo log, are not databases
+        if name.starts_with('.') {
+            return Err(DatabaseError::InvalidDatabaseName(name.to_string()));
+        }
+        if let Some(catalog) = &mut self.memory_catalog {
+            if catalog.contains_key(name) {
+                return Err(DatabaseError::DatabaseExists(name.to_string()));
+            }
+            catalog.insert(name.to_string(), DatabaseMetadata::new(name.to_string()));
+            return Ok(());
+        }
+
         let db_path = self.data_dir.join(name);
         if db_path.exists() {
             return Err(DatabaseError::DatabaseExists(name.to_string()));
@@ -325,41 +854,106 @@ impl DatabaseManager {
 
     pub fn drop_database(&mut self, name: &str) -> DatabaseResult<()> {
         let db_path = self.data_dir.join(name);
-        if !db_path.exists() {
+        let exists = match &self.memory_catalog {
+            Some(catalog) => catalog.contains_key(name),
+            None => db_path.exists(),
+        };
+        if !exists {
             return Err(DatabaseError::DatabaseNotFound(name.to_string()));
         }
 
         // Close current database if it's the one being dropped
         if self.current_db.as_ref() == Some(&name.to_string()) {
+            self.index_manager.close_all()?;
             self.current_db = None;
             self.current_metadata = None;
         }
 
+        if let Some(catalog) = &mut self.memory_catalog {
+            catalog.remove(name);
+            let mut buffer_manager = self.buffer_manager.write().unwrap();
+            buffer_manager.flush_all()?;
+            buffer_manager.file_manager_mut().remove_dir_all(&db_path)?;
+            return Ok(());
+        }
+
         fs::remove_dir_all(&db_path)?;
         Ok(())
     }
 
-    pub fn show_databases(&self) -> DatabaseResult<Vec<String>> {
+    /// Write every dirty table and index page to disk, returning how many
+    /// pages were written. Once this returns the data directory is
+    /// consistent on disk and can be copied while the process keeps running.
+    pub fn checkpoint(&mut self) -> DatabaseResult<usize> {
+        // Index nodes live in memory until flushed into their pages
+        let index_pages = self.index_manager.flush_all()?;
+        let pages = self.buffer_manager.write().unwrap().flush_all()?;
+        Ok(index_pages + pages)
+    }
+
+    /// Database names, restricted to those matching the LIKE `pattern` if given
+    pub fn show_databases(&self, pattern: Option<&str>) -> DatabaseResult<Vec<String>> {
         let mut databases = Vec::new();
-        for entry in fs::read_dir(&self.data_dir)? {
-            let entry = entry?;
-            if entry.file_type()?.is_dir()
-                && let Some(name) = entry.file_name().to_str()
-            {
-                databases.push(name.to_string());
+        if let Some(catalog) = &self.memory_catalog {
+            databases.extend(catalog.keys().cloned());
+        } else {
+            for entry in fs::read_dir(&self.data_dir)? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir()
+                    && let Some(name) = entry.file_name().to_str()
+                    && !name.starts_with('.')
+                {
+                    databases.push(name.to_string());
+                }
             }
         }
-        databases.sort();
+        self.filter_listing(&mut databases, pattern)?;
+        Self::sort_listing(&mut databases);
         Ok(databases)
     }
 
-    pub fn use_database(&mut self, name: &str) -> DatabaseResult<()> {
-        let db_path = self.data_dir.join(name);
-        if !db_path.exists() {
-            return Err(DatabaseError::DatabaseNotFound(name.to_string()));
+    /// Keep only the names matching the LIKE `pattern`, if one is given
+    fn filter_listing(&self, names: &mut Vec<String>, pattern: Option<&str>) -> DatabaseResult<()> {
+        if let Some(pattern) = pattern {
+            let regex = self.like_regex(pattern, None)?;
+            names.retain(|name| regex.is_match(name));
+        }
+        Ok(())
+    }
+
+    /// Alphabetical regardless of case, so `Apple`, `banana` and `Cherry`
+    /// list in that order; names differing only in case keep a fixed order
+    fn sort_listing(names: &mut [String]) {
+        names.sort_by_cached_key(|name| (name.to_lowercase(), name.clone()));
+    }
+
+    fn load_database_metadata(&self, name: &str) -> DatabaseResult<DatabaseMetadata> {
+        match &self.memory_catalog {
+            Some(catalog) => catalog
+                .get(name)
+                .cloned()
+                .ok_or_else(|| DatabaseError::DatabaseNotFound(name.to_string())),
+            None => {
+                let db_path = self.data_dir.join(name);
+                if !db_path.exists() {
+                    return Err(DatabaseError::DatabaseNotFound(name.to_string()));
+                }
+                Ok(DatabaseMetadata::load(&db_path)?)
+            }
         }
+    }
 
-        let metadata = DatabaseMetadata::load(&db_path)?;
+    pub fn use_database(&mut self, name: &str) -> DatabaseResult<()> {
+        let metadata = self.load_database_metadata(name)?;
+        // Open tables and cached index handles are keyed by table name
+        // alone, so they must not outlive the database they were opened in
+        if self.current_db.as_deref() != Some(name) {
+            self.index_manager.close_all()?;
+            // A reopened table counts its pages from the file, so pages
+            // appended in the buffer pool must reach it first
+            self.buffer_manager.write().unwrap().flush_all()?;
+            self.record_manager.close_all_tables();
+        }
         self.current_db = Some(name.to_string());
         self.current_metadata = Some(metadata);
         let updated = self.ensure_foreign_key_indexes()?;
@@ -390,18 +984,57 @@ impl DatabaseManager {
         let mut columns = Vec::new();
         let mut primary_key = None;
         let mut foreign_keys = Vec::new();
+        let mut indexes = Vec::new();
         let mut seen_columns = HashSet::new();
+        let mut auto_increment = BTreeMap::new();
 
         for field in fields {
             match field {
-                CreateTableField::Col(col_name, col_type, not_null, default) => {
+                CreateTableField::Col(col_name, col_type, not_null, default, unique) => {
                     // Check for duplicate column names
                     if !seen_columns.insert(col_name.clone()) {
                         return Err(DatabaseError::DuplicateColumn(col_name));
                     }
-                    columns.push(ColumnMetadata::from_parser(
-                        col_name, col_type, not_null, default,
-                    ));
+                    if let Some(ParserDefault::Literal(ParserValue::Placeholder(n))) = default {
+                        return Err(DatabaseError::UnboundParameter(n));
+                    }
+                    if col_type == ColumnType::Bool
+                        && let Some(ParserDefault::Literal(value)) = &default
+                    {
+                        self.parser_value_to_record_value(value, &DataType::Bool)?;
+                    }
+                    if default == Some(ParserDefault::AutoIncrement) {
+                        if col_type != ColumnType::Int {
+                            return Err(DatabaseError::TypeMismatch(format!(
+                                "AUTO_INCREMENT column {} must be INT",
+                                col_name
+                            )));
+                        }
+                        auto_increment.insert(col_name.clone(), 1);
+                    }
+                    let column =
+                        ColumnMetadata::from_parser(col_name.clone(), col_type, not_null, default);
+                    // Dynamic defaults produce text, which must fit the column
+                    if let Some(len) = column.declared_default().and_then(|d| d.text_len())
+                        && !matches!(column.to_data_type(), DataType::Char(n) | DataType::Varchar(n) if n >= len)
+                    {
+                        return Err(DatabaseError::TypeMismatch(format!(
+                            "{} default of column {} needs a VARCHAR({}) or wider column",
+                            column.default_function.as_deref().unwrap_or_default(),
+                            col_name,
+                            len
+                        )));
+                    }
+                    if unique {
+                        indexes.push(IndexMetadata {
+                            name: format!("uniq_{}", col_name),
+                            columns: vec![col_name.clone()],
+                            implicit: false,
+                            order: None,
+                            unique: true,
+                        });
+                    }
+                    columns.push(column);
                 }
                 CreateTableField::Pkey(alter_stmt) => {
                     if let AlterStatement::AddPKey(_, pk_cols) = *alter_stmt {
@@ -432,17 +1065,151 @@ impl DatabaseManager {
             }
         }
 
+        if let Some(col) = columns.iter().find(|col| col.not_null && col.default_null) {
+            return Err(DatabaseError::TypeMismatch(format!(
+                "Invalid default value NULL for NOT NULL column {}",
+                col.name
+            )));
+        }
+
         let table_metadata = TableMetadata {
             name: name.to_string(),
             columns,
             primary_key,
             foreign_keys,
-            indexes: Vec::new(),
+            indexes,
+            version: 0,
+            auto_increment,
+        };
+        for fk in &table_metadata.foreign_keys {
+            self.check_foreign_key_cycle(&table_metadata, fk)?;
+        }
+
+        self.create_table_from_metadata(table_metadata)
+    }
+
+    /// Create a table from the rows of a single-table `SELECT`
+    ///
+    /// The new table takes the selected columns' definitions (type, NOT NULL
+    /// and DEFAULT) and a copy of every matching row. The source table's
+    /// primary key, foreign keys and indexes are not carried over. Returns
+    /// the number of rows copied.
+    pub fn create_table_as(
+        &mut self,
+        name: &str,
+        mut clause: SelectClause,
+    ) -> DatabaseResult<usize> {
+        if clause.table.len() != 1
+            || self.select_has_aggregate(&clause.selectors)
+            || !clause.group_by.is_empty()
+            || !clause.having.is_empty()
+            || !clause.order_by.is_empty()
+            || clause.limit.is_some()
+            || clause.offset.is_some()
+        {
+            return Err(DatabaseError::TypeMismatch(
+                "CREATE TABLE ... AS only supports selecting columns from one table with an optional WHERE"
+                    .to_string(),
+            ));
+        }
+        let table_refs = Self::table_refs(&clause)?;
+        let source = clause.table[0].clone();
+        Self::rename_table_qualifier(&mut clause, &table_refs[0], &source);
+
+        let (source_meta, schema) = {
+            let metadata = self
+                .current_metadata
+                .as_ref()
+                .ok_or(DatabaseError::NoDatabaseSelected)?;
+            if metadata.tables.contains_key(name) {
+                return Err(DatabaseError::TableExists(name.to_string()));
+            }
+            let source_meta = metadata.get_table(&source)?.clone();
+            let schema = self.metadata_to_schema(&source_meta);
+            (source_meta, schema)
+        };
+
+        let col_indices: Vec<usize> = match &clause.selectors {
+            Selectors::All => (0..schema.columns.len()).collect(),
+            Selectors::List(selectors) => selectors
+                .iter()
+                .map(|selector| match selector {
+                    Selector::Column(tc) => self.resolve_single_column_index(&schema, tc),
+                    Selector::Cast(..) => Err(DatabaseError::TypeMismatch(
+                        "CREATE TABLE ... AS does not support CAST".to_string(),
+                    )),
+                    _ => unreachable!("aggregates are rejected above"),
+                })
+                .collect::<DatabaseResult<_>>()?,
+        };
+        let mut seen_columns = HashSet::new();
+        let mut columns = Vec::with_capacity(col_indices.len());
+        for &idx in &col_indices {
+            let column = source_meta.columns[idx].clone();
+            if !seen_columns.insert(column.name.clone()) {
+                return Err(DatabaseError::DuplicateColumn(column.name));
+            }
+            columns.push(column);
+        }
+
+        let db_name = self.current_db.as_ref().unwrap();
+        let table_path = self.table_path(db_name, &source);
+        let _ = self
+            .record_manager
+            .open_table(&table_path.to_string_lossy(), schema.clone());
+
+        let prepared_where = if clause.where_clauses.is_empty() {
+            None
+        } else {
+            Some(self.prepare_where_clauses(&clause.where_clauses)?)
         };
+        let mut rows = Vec::new();
+        let mut budget = QueryBudget::new(self.statement_timeout, self.running_query.clone());
+        let scan_iter = self.record_manager.scan_iter(&source)?;
+        for item in scan_iter {
+            let (_rid, record) = item?;
+            budget.tick()?;
+            let matches = match &prepared_where {
+                None => true,
+                Some(clauses) => self.evaluate_prepared_where(&record, &schema, clauses)?,
+            };
+            if !matches {
+                continue;
+            }
+            let row = col_indices
+                .iter()
+                .map(|&idx| match record.get(idx).unwrap() {
+                    RecordValue::Int(i) => ParserValue::Integer(i64::from(*i)),
+                    RecordValue::Float(f) => ParserValue::Float(*f),
+                    // Decimal text converts back without going through f64
+                    RecordValue::Decimal(units, scale) => {
+                        ParserValue::String(format_decimal(*units, *scale))
+                    }
+                    RecordValue::Bool(b) => ParserValue::Bool(*b),
+                    RecordValue::String(s) => ParserValue::String(s.clone()),
+                    RecordValue::Null => ParserValue::Null,
+                })
+                .collect();
+            rows.push(row);
+        }
+
+        self.create_table_from_metadata(TableMetadata {
+            name: name.to_string(),
+            columns,
+            primary_key: None,
+            foreign_keys: Vec::new(),
+            indexes: Vec::new(),
+            version: 0,
+            auto_increment: BTreeMap::new(),
+        })?;
+        // The copy has no keys or indexes to check or maintain
+        self.bulk_insert(name, rows, true, true, true)
+    }
 
+    fn create_table_from_metadata(&mut self, table_metadata: TableMetadata) -> DatabaseResult<()> {
         // Create the table file
         let db_name = self.current_db.as_ref().unwrap().clone();
-        let table_path = self.table_path(&db_name, name);
+        let table_path = self.table_path(&db_name, &table_metadata.name);
         let schema = self.metadata_to_schema(&table_metadata);
         self.record_manager
             .create_table(&table_path.to_string_lossy(), schema)?;
@@ -462,27 +1229,97 @@ impl DatabaseManager {
             .as_mut()
             .ok_or(DatabaseError::NoDatabaseSelected)?;
 
+        let table_meta = metadata.get_table(name)?.clone();
         metadata.remove_table(name)?;
 
-        // Delete the table file
+        // Drop every index file named after the table, including ones no longer
+        // in the catalog, which also evicts their cached handles. A file that
+        // could equally be an index of another table is left alone.
         let db_name = self.current_db.as_ref().unwrap();
-        let table_path = self.table_path(db_name, name);
-        if table_path.exists() {
-            fs::remove_file(&table_path)?;
-        }
-
-        self.save_current_metadata()?;
-        Ok(())
-    }
-
-    pub fn show_tables(&self) -> DatabaseResult<Vec<String>> {
+        let db_path = self.data_dir.join(db_name).to_string_lossy().to_string();
+        let files = self
+            .buffer_manager
+            .read()
+            .unwrap()
+            .file_manager()
+            .list_files(&db_path)?;
+        for path in files {
+            let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if !Self::is_index_file_of(file_name, &table_meta)
+                || metadata
+                    .tables
+                    .values()
+                    .any(|other| Self::is_index_file_of(file_name, other))
+            {
+                continue;
+            }
+            let storage_name = &file_name[name.len() + 1..file_name.len() - ".idx".len()];
+            match self.index_manager.drop_index(&db_path, name, storage_name) {
+                Ok(()) | Err(IndexError::IndexNotFound(_)) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        // Delete the table file
+        let db_name = self.current_db.as_ref().unwrap();
+        let table_path = self.table_path(db_name, name);
+        if self.memory_catalog.is_some() {
+            self.record_manager.close_table(name)?;
+            let mut buffer_manager = self.buffer_manager.write().unwrap();
+            buffer_manager.flush_all()?;
+            buffer_manager.file_manager_mut().remove_file(&table_path)?;
+        } else if table_path.exists() {
+            fs::remove_file(&table_path)?;
+        }
+
+        self.save_current_metadata()?;
+        Ok(())
+    }
+
+    /// Delete the index files of the current database that belong to no
+    /// table in the catalog, such as those of tables dropped before
+    /// `drop_table` removed them. Returns the names of the deleted files.
+    pub fn gc_orphaned_indexes(&mut self) -> DatabaseResult<Vec<String>> {
+        let metadata = self
+            .current_metadata
+            .as_ref()
+            .ok_or(DatabaseError::NoDatabaseSelected)?;
+        let db_name = self.current_db.as_ref().unwrap();
+        let db_path = self.data_dir.join(db_name);
+
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
+        let mut removed = Vec::new();
+        for path in buffer_manager.file_manager().list_files(&db_path)? {
+            let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if !file_name.ends_with(".idx")
+                || metadata
+                    .tables
+                    .values()
+                    .any(|table_meta| Self::is_index_file_of(file_name, table_meta))
+            {
+                continue;
+            }
+            buffer_manager.file_manager_mut().remove_file(&path)?;
+            removed.push(file_name.to_string());
+        }
+        Ok(removed)
+    }
+
+    /// Table names of the current database, restricted to those matching
+    /// the LIKE `pattern` if given
+    pub fn show_tables(&self, pattern: Option<&str>) -> DatabaseResult<Vec<String>> {
         let metadata = self
             .current_metadata
             .as_ref()
             .ok_or(DatabaseError::NoDatabaseSelected)?;
 
         let mut tables: Vec<String> = metadata.tables.keys().cloned().collect();
-        tables.sort();
+        self.filter_listing(&mut tables, pattern)?;
+        Self::sort_listing(&mut tables);
         Ok(tables)
     }
 
@@ -495,6 +1332,25 @@ impl DatabaseManager {
         Ok(metadata.get_table(name)?.clone())
     }
 
+    /// Describe `name` restricted to the columns whose name matches the LIKE
+    /// `pattern`. Keys and indexes are kept only if all of their columns match.
+    pub fn show_columns(&self, name: &str, pattern: Option<&str>) -> DatabaseResult<TableMetadata> {
+        let mut meta = self.describe_table(name)?;
+        let Some(pattern) = pattern else {
+            return Ok(meta);
+        };
+
+        let regex = self.like_regex(pattern, None)?;
+        meta.columns.retain(|col| regex.is_match(&col.name));
+        let shown = |cols: &[String]| cols.iter().all(|c| regex.is_match(c));
+        if meta.primary_key.as_deref().is_some_and(|pk| !shown(pk)) {
+            meta.primary_key = None;
+        }
+        meta.foreign_keys.retain(|fk| shown(&fk.columns));
+        meta.indexes.retain(|idx| shown(&idx.columns));
+        Ok(meta)
+    }
+
     // Data operations
     pub fn insert(&mut self, table: &str, rows: Vec<Vec<ParserValue>>) -> DatabaseResult<usize> {
         self.bulk_insert(table, rows, false, false, false)
@@ -508,8 +1364,11 @@ impl DatabaseManager {
     /// 3. Scanning the table only once for all rows instead of once per row
     /// 4. Optionally skipping primary key checks when data is known to be valid (e.g., from LOAD DATA INFILE)
     ///
-    /// When skip_pk_check is true, primary key checking is skipped entirely for maximum performance.
-    /// Use this only when data is known to be valid and without duplicates.
+    /// When skip_pk_check is true, primary key and UNIQUE checking is skipped entirely for maximum
+    /// performance. Use this only when data is known to be valid and without duplicates.
+    ///
+    /// The insert and its index updates run as one logged unit, so a crash
+    /// partway through is rolled back on the next start.
     pub fn bulk_insert(
         &mut self,
         table: &str,
@@ -517,6 +1376,19 @@ impl DatabaseManager {
         skip_pk_check: bool,
         skip_fk_check: bool,
         skip_index_update: bool,
+    ) -> DatabaseResult<usize> {
+        self.logged_unit(|db| {
+            db.bulk_insert_unlogged(table, rows, skip_pk_check, skip_fk_check, skip_index_update)
+        })
+    }
+
+    fn bulk_insert_unlogged(
+        &mut self,
+        table: &str,
+        rows: Vec<Vec<ParserValue>>,
+        skip_pk_check: bool,
+        skip_fk_check: bool,
+        skip_index_update: bool,
     ) -> DatabaseResult<usize> {
         let (table_meta, schema) = {
             let metadata = self
@@ -555,28 +1427,22 @@ impl DatabaseManager {
                 .collect()
         });
 
+        // Next value of each AUTO_INCREMENT column, by column position
+        let mut counters: Vec<(usize, i64)> = table_meta
+            .auto_increment
+            .iter()
+            .filter_map(|(name, next)| {
+                let idx = table_meta.columns.iter().position(|c| &c.name == name)?;
+                Some((idx, *next))
+            })
+            .collect();
+
         // Convert all rows and check for duplicates within batch
         let mut records = Vec::with_capacity(rows.len());
 
         for row in rows {
-            // Convert parser values to record values
-            let mut record_values = Vec::with_capacity(table_meta.columns.len());
-
-            for (i, value) in row.iter().enumerate() {
-                if i >= table_meta.columns.len() {
-                    break;
-                }
-                let col = &table_meta.columns[i];
-
-                // Check NOT NULL constraint
-                if matches!(value, ParserValue::Null) && col.not_null {
-                    return Err(DatabaseError::NotNullViolation(col.name.clone()));
-                }
-
-                record_values.push(self.parser_value_to_record_value(value, &col.to_data_type())?);
-            }
-
-            let record = Record::new(record_values);
+            let mut record = self.row_to_record(&table_meta, &row)?;
+            self.assign_auto_increment(&table_meta, &mut record, &mut counters)?;
 
             // Check for duplicates within the batch itself
             if !skip_pk_check && let Some(ref indices) = pk_indices {
@@ -587,7 +1453,7 @@ impl DatabaseManager {
                 let pk_string = pk_key.join("|");
 
                 if !batch_pk_set.insert(pk_string) {
-                    return Err(DatabaseError::PrimaryKeyViolation);
+                    return Err(self.primary_key_violation(&record, indices));
                 }
             }
 
@@ -608,8 +1474,11 @@ impl DatabaseManager {
                 let db_path = self.data_dir.join(db_name.as_str());
                 let _index_key = (table.to_string(), pk_col_name.clone());
 
+                // A VARCHAR key index is hashed, so only INT keys are probed
                 let mut used_index = false;
-                if self.use_indexes {
+                if self.use_indexes
+                    && table_meta.columns[pk_col_idx].to_data_type() == DataType::Int
+                {
                     let has_index = self
                         .index_manager
                         .open_index(&db_path.to_string_lossy(), table, pk_col_name)
@@ -623,7 +1492,7 @@ impl DatabaseManager {
                                     .search(table, pk_col_name, *pk_val as i64)
                                     .is_some()
                             {
-                                return Err(DatabaseError::PrimaryKeyViolation);
+                                return Err(self.primary_key_violation(record, &[pk_col_idx]));
                             }
                         }
                     }
@@ -642,17 +1511,15 @@ impl DatabaseManager {
                                 }
                             }
                             if is_duplicate {
-                                return Err(DatabaseError::PrimaryKeyViolation);
+                                return Err(self
+                                    .primary_key_violation(record, pk_indices.as_ref().unwrap()));
                             }
                         }
                     }
                 }
             } else if pk_cols.len() == 2 {
                 let pk_indices = pk_indices.as_ref().unwrap();
-                let storage_name = match Self::index_storage_name(pk_cols) {
-                    Some(name) => name,
-                    None => String::new(),
-                };
+                let storage_name = Self::index_storage_name(pk_cols).unwrap_or_default();
                 let mut used_index = false;
                 if self.use_indexes && !storage_name.is_empty() {
                     let has_index = self
@@ -670,14 +1537,13 @@ impl DatabaseManager {
                                 RecordValue::Int(val) => *val,
                                 _ => continue,
                             };
-                            let key =
-                                TableCompositeIntColumnIter::composite_key(left, right);
+                            let key = TableCompositeIntColumnIter::composite_key(left, right);
                             if self
                                 .index_manager
                                 .search(table, &storage_name, key)
                                 .is_some()
                             {
-                                return Err(DatabaseError::PrimaryKeyViolation);
+                                return Err(self.primary_key_violation(record, pk_indices));
                             }
                         }
                     }
@@ -696,7 +1562,7 @@ impl DatabaseManager {
                                 }
                             }
                             if is_duplicate {
-                                return Err(DatabaseError::PrimaryKeyViolation);
+                                return Err(self.primary_key_violation(record, pk_indices));
                             }
                         }
                     }
@@ -714,13 +1580,19 @@ impl DatabaseManager {
                             }
                         }
                         if is_duplicate {
-                            return Err(DatabaseError::PrimaryKeyViolation);
+                            return Err(
+                                self.primary_key_violation(record, pk_indices.as_ref().unwrap())
+                            );
                         }
                     }
                 }
             }
         }
 
+        if !skip_pk_check {
+            self.check_unique_constraints(table, &table_meta, &records, &HashSet::new())?;
+        }
+
         if !skip_fk_check {
             self.validate_foreign_keys_for_records(&table_meta, &records)?;
         }
@@ -737,10 +1609,7 @@ impl DatabaseManager {
                 let mut row_keys = Vec::with_capacity(indexed_defs.len());
                 for def in &indexed_defs {
                     let key = match def.indices.as_slice() {
-                        [col_idx] => match record.get(*col_idx) {
-                            Some(RecordValue::Int(val)) => Some(*val as i64),
-                            _ => None,
-                        },
+                        [col_idx] => record.get(*col_idx).and_then(index_value_key),
                         [left_idx, right_idx] => {
                             let left = match record.get(*left_idx) {
                                 Some(RecordValue::Int(val)) => *val,
@@ -769,6 +1638,19 @@ impl DatabaseManager {
         // Insert all records in one batch - much faster as it holds the lock only once
         let record_ids = self.record_manager.bulk_insert(table, records)?;
 
+        // Saved along with the version bump below
+        if !counters.is_empty() {
+            let auto_increment = &mut self
+                .current_metadata
+                .as_mut()
+                .ok_or(DatabaseError::NoDatabaseSelected)?
+                .get_table_mut(table)?
+                .auto_increment;
+            for (idx, next) in counters {
+                auto_increment.insert(table_meta.columns[idx].name.clone(), next);
+            }
+        }
+
         if !indexed_defs.is_empty() {
             for (row_idx, rid) in record_ids.iter().enumerate() {
                 let row_keys = &index_keys[row_idx];
@@ -781,14 +1663,295 @@ impl DatabaseManager {
             }
         }
 
+        if !record_ids.is_empty() {
+            self.bump_table_version(table)?;
+        }
         Ok(record_ids.len())
     }
 
-    pub fn delete(
+    /// Convert one row of parser values into a record, enforcing NOT NULL
+    ///
+    /// Omitted trailing columns that declare a DEFAULT take it, evaluated now.
+    fn row_to_record(
+        &self,
+        table_meta: &TableMetadata,
+        row: &[ParserValue],
+    ) -> DatabaseResult<Record> {
+        if row.len() > table_meta.columns.len() {
+            return Err(DatabaseError::TypeMismatch(format!(
+                "Row has {} values but table {} has {} columns",
+                row.len(),
+                table_meta.name,
+                table_meta.columns.len()
+            )));
+        }
+        let mut record_values = Vec::with_capacity(table_meta.columns.len());
+
+        for (value, col) in row.iter().zip(&table_meta.columns) {
+            // Check NOT NULL constraint; AUTO_INCREMENT fills a NULL in later
+            if matches!(value, ParserValue::Null)
+                && col.not_null
+                && !table_meta.auto_increment.contains_key(&col.name)
+            {
+                return Err(DatabaseError::NotNullViolation(col.name.clone()));
+            }
+
+            let data_type = col.to_data_type();
+            let record_value = self
+                .parser_value_to_record_value(value, &data_type)
+                .map_err(|err| match err {
+                    DatabaseError::TypeMismatch(msg) => {
+                        DatabaseError::TypeMismatch(format!("{} (column {})", msg, col.name))
+                    }
+                    other => other,
+                })?;
+            record_values.push(self.round_to_column_scale(record_value, &data_type)?);
+        }
+
+        // Omitted trailing columns take their default, or NULL where NULL is allowed
+        for col in table_meta.columns.iter().skip(row.len()) {
+            let value = match col.default() {
+                Some(value) => value,
+                None if !col.not_null || table_meta.auto_increment.contains_key(&col.name) => {
+                    RecordValue::Null
+                }
+                None => return Err(DatabaseError::NotNullViolation(col.name.clone())),
+            };
+            record_values.push(value);
+        }
+
+        Ok(Record::new(record_values))
+    }
+
+    /// Give each AUTO_INCREMENT column of `record` that is NULL the next
+    /// value of its counter in `counters`; an explicit value at or past the
+    /// counter moves the counter past it
+    fn assign_auto_increment(
+        &self,
+        table_meta: &TableMetadata,
+        record: &mut Record,
+        counters: &mut [(usize, i64)],
+    ) -> DatabaseResult<()> {
+        for (idx, next) in counters.iter_mut() {
+            match record.get(*idx) {
+                Some(RecordValue::Null) => {
+                    let value = i32::try_from(*next).map_err(|_| {
+                        DatabaseError::TypeMismatch(format!(
+                            "AUTO_INCREMENT column {} has run out of INT values",
+                            table_meta.columns[*idx].name
+                        ))
+                    })?;
+                    record.set(*idx, RecordValue::Int(value));
+                    *next += 1;
+                }
+                Some(RecordValue::Int(value)) if i64::from(*value) >= *next => {
+                    *next = i64::from(*value) + 1;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that `records` break none of the table's UNIQUE constraints,
+    /// neither among themselves nor against the stored rows outside
+    /// `replaced` (rows the statement is about to overwrite). Keys containing
+    /// NULL never conflict. INT columns are probed through their index when
+    /// it is open; everything else is checked with one scan of the table.
+    fn check_unique_constraints(
         &mut self,
         table: &str,
-        where_clauses: Option<Vec<WhereClause>>,
-    ) -> DatabaseResult<usize> {
+        table_meta: &TableMetadata,
+        records: &[Record],
+        replaced: &HashSet<RecordId>,
+    ) -> DatabaseResult<()> {
+        let mut constraints: Vec<(&IndexMetadata, Vec<usize>, HashSet<String>)> = Vec::new();
+        for index in table_meta.indexes.iter().filter(|idx| idx.unique) {
+            let indices: Vec<usize> = index
+                .columns
+                .iter()
+                .map(|col_name| {
+                    table_meta
+                        .columns
+                        .iter()
+                        .position(|c| &c.name == col_name)
+                        .ok_or_else(|| {
+                            DatabaseError::ColumnNotFound(col_name.clone(), table.to_string())
+                        })
+                })
+                .collect::<DatabaseResult<_>>()?;
+            constraints.push((index, indices, HashSet::new()));
+        }
+        if constraints.is_empty() || records.is_empty() {
+            return Ok(());
+        }
+
+        let unique_key = |record: &Record, indices: &[usize]| -> Option<String> {
+            let mut parts = Vec::with_capacity(indices.len());
+            for &idx in indices {
+                match record.get(idx) {
+                    Some(RecordValue::Null) | None => return None,
+                    Some(value) => parts.push(format!("{:?}", value)),
+                }
+            }
+            Some(parts.join("|"))
+        };
+        let violation = |this: &Self, index: &IndexMetadata, record: &Record, indices: &[usize]| {
+            let values: Vec<String> = indices
+                .iter()
+                .map(|&idx| this.format_value(record.get(idx).unwrap()))
+                .collect();
+            DatabaseError::UniqueViolation(index.name.clone(), format!("({})", values.join(", ")))
+        };
+
+        for record in records {
+            for (index, indices, keys) in &mut constraints {
+                if let Some(key) = unique_key(record, indices)
+                    && !keys.insert(key)
+                {
+                    return Err(violation(self, index, record, indices));
+                }
+            }
+        }
+
+        let db_name = self.current_db.as_ref().unwrap();
+        let db_path_str = self.data_dir.join(db_name).to_string_lossy().to_string();
+        let mut needs_scan = Vec::new();
+        for (index, indices, keys) in &constraints {
+            let probe = self.use_indexes
+                && indices.len() == 1
+                && table_meta.columns[indices[0]].to_data_type() == DataType::Int
+                && self
+                    .index_manager
+                    .open_index(&db_path_str, table, &index.columns[0])
+                    .is_ok();
+            if !probe {
+                needs_scan.push((*index, indices, keys));
+                continue;
+            }
+            for record in records {
+                if let Some(RecordValue::Int(val)) = record.get(indices[0])
+                    && self
+                        .index_manager
+                        .search_all(table, &index.columns[0], *val as i64)
+                        .iter()
+                        .any(|rid| !replaced.contains(rid))
+                {
+                    return Err(violation(self, index, record, indices));
+                }
+            }
+        }
+
+        if !needs_scan.is_empty() {
+            for item in self.record_manager.scan_iter(table)? {
+                let (rid, existing) = item?;
+                if replaced.contains(&rid) {
+                    continue;
+                }
+                for (index, indices, keys) in &needs_scan {
+                    if let Some(key) = unique_key(&existing, indices)
+                        && keys.contains(&key)
+                    {
+                        return Err(violation(self, index, &existing, indices));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that the primary keys of `records`, the new contents of the rows
+    /// in `replaced`, are distinct and collide with no other stored row.
+    /// Integer keys are probed through the primary key index when it is open;
+    /// otherwise the table is scanned once.
+    fn check_primary_key_on_update(
+        &mut self,
+        table: &str,
+        table_meta: &TableMetadata,
+        pk_cols: &[String],
+        records: &[&Record],
+        replaced: &HashSet<RecordId>,
+    ) -> DatabaseResult<()> {
+        let pk_indices = Self::column_indices(table_meta, pk_cols)?;
+        let pk_key = |record: &Record| -> String {
+            pk_indices
+                .iter()
+                .map(|&idx| format!("{:?}", record.get(idx).unwrap()))
+                .collect::<Vec<_>>()
+                .join("|")
+        };
+
+        let mut keys = HashSet::new();
+        for record in records {
+            if !keys.insert(pk_key(record)) {
+                return Err(self.primary_key_violation(record, &pk_indices));
+            }
+        }
+
+        let db_name = self.current_db.as_ref().unwrap();
+        let db_path_str = self.data_dir.join(db_name).to_string_lossy().to_string();
+        let storage_name = match pk_cols {
+            [column] => Some(column.clone()),
+            [_, _] => Self::index_storage_name(pk_cols),
+            _ => None,
+        };
+        let int_key = |record: &Record| -> Option<i64> {
+            match pk_indices.as_slice() {
+                [idx] => match record.get(*idx)? {
+                    RecordValue::Int(val) => Some(*val as i64),
+                    _ => None,
+                },
+                [left, right] => match (record.get(*left)?, record.get(*right)?) {
+                    (RecordValue::Int(left), RecordValue::Int(right)) => {
+                        Some(TableCompositeIntColumnIter::composite_key(*left, *right))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            }
+        };
+        let int_keys: Option<Vec<i64>> = records.iter().map(|record| int_key(record)).collect();
+        if self.use_indexes
+            && let Some(storage_name) = storage_name
+            && let Some(int_keys) = int_keys
+            && self
+                .index_manager
+                .open_index(&db_path_str, table, &storage_name)
+                .is_ok()
+        {
+            for (record, key) in records.iter().zip(int_keys) {
+                if self
+                    .index_manager
+                    .search_all(table, &storage_name, key)
+                    .iter()
+                    .any(|rid| !replaced.contains(rid))
+                {
+                    return Err(self.primary_key_violation(record, &pk_indices));
+                }
+            }
+            return Ok(());
+        }
+
+        for item in self.record_manager.scan_iter(table)? {
+            let (rid, existing) = item?;
+            if !replaced.contains(&rid) && keys.contains(&pk_key(&existing)) {
+                return Err(self.primary_key_violation(&existing, &pk_indices));
+            }
+        }
+        Ok(())
+    }
+
+    /// Insert rows, first deleting any existing row with the same primary key
+    ///
+    /// Deleting the old row follows the same rules as `DELETE`: if another
+    /// table's foreign key still references it the statement fails with a
+    /// foreign key violation (RESTRICT) and nothing is changed. Within one
+    /// statement a later row replaces an earlier row with the same key. A
+    /// table without a primary key behaves like `INSERT`. A new row that
+    /// duplicates a UNIQUE column of a row it does not replace is an error.
+    /// Returns the number of rows deleted plus the number inserted.
+    pub fn replace(&mut self, table: &str, rows: Vec<Vec<ParserValue>>) -> DatabaseResult<usize> {
         let (table_meta, schema) = {
             let metadata = self
                 .current_metadata
@@ -799,6 +1962,9 @@ impl DatabaseManager {
             let schema = self.metadata_to_schema(&table_meta);
             (table_meta, schema)
         };
+        let Some(pk_cols) = table_meta.primary_key.clone() else {
+            return self.insert(table, rows);
+        };
 
         let db_name = self.current_db.as_ref().unwrap();
         let db_path = self.data_dir.join(db_name);
@@ -811,70 +1977,311 @@ impl DatabaseManager {
             .record_manager
             .open_table(&table_path_str, schema.clone());
 
-        let referencing_checks = self.build_referencing_fk_checks(&table_meta)?;
-        let where_slice: &[WhereClause] = match &where_clauses {
-            Some(clauses) => clauses,
-            None => &[],
-        };
-        let prepared_where = match &where_clauses {
-            Some(clauses) => Some(self.prepare_where_clauses(clauses)?),
-            None => None,
-        };
+        let pk_indices: Vec<usize> = pk_cols
+            .iter()
+            .map(|col_name| {
+                table_meta
+                    .columns
+                    .iter()
+                    .position(|c| &c.name == col_name)
+                    .unwrap()
+            })
+            .collect();
 
-        let indexed_defs = self.open_indexed_defs(&db_path_str, &table_meta)?;
-        let mut deleted = 0;
-        let mut targets = Vec::new();
-        let index_candidates =
-            self.index_candidates_for_where(&db_path_str, &table_meta, &schema, where_slice)?;
-        if let Some(rids) = index_candidates {
-            for rid in rids {
-                let record = self.record_manager.get(table, rid)?;
-                let should_delete = match &prepared_where {
-                    None => true,
-                    Some(clauses) => self.evaluate_prepared_where(&record, &schema, clauses)?,
-                };
-                if should_delete {
-                    targets.push((rid, record));
-                }
+        // Keep only the last row for each key, then validate the new rows before touching old ones
+        let mut rows_by_key: HashMap<String, usize> = HashMap::new();
+        let mut kept: Vec<Option<(Vec<ParserValue>, Record)>> = Vec::with_capacity(rows.len());
+        for row in rows {
+            let record = self.row_to_record(&table_meta, &row)?;
+            let pk_key = pk_indices
+                .iter()
+                .map(|&idx| format!("{:?}", record.get(idx)))
+                .collect::<Vec<_>>()
+                .join("|");
+            if let Some(previous) = rows_by_key.insert(pk_key, kept.len()) {
+                kept[previous] = None;
             }
-        } else {
-            let scan_iter = self.record_manager.scan_iter(table)?;
-            for item in scan_iter {
-                let (rid, record) = item?;
-                let should_delete = match &prepared_where {
-                    None => true,
-                    Some(clauses) => self.evaluate_prepared_where(&record, &schema, clauses)?,
-                };
-                if should_delete {
+            kept.push(Some((row, record)));
+        }
+        let (rows, records): (Vec<_>, Vec<_>) = kept.into_iter().flatten().unzip();
+        self.validate_foreign_keys_for_records(&table_meta, &records)?;
+
+        let mut targets = Vec::new();
+        let mut seen = HashSet::new();
+        for row in &rows {
+            let pk_where: Vec<WhereClause> = pk_indices
+                .iter()
+                .map(|&idx| {
+                    WhereClause::Op(
+                        TableColumn {
+                            table: None,
+                            column: table_meta.columns[idx].name.clone(),
+                        },
+                        Operator::Eq,
+                        Expression::Value(row.get(idx).cloned().unwrap_or(ParserValue::Null)),
+                    )
+                })
+                .collect();
+            for (rid, record) in
+                self.collect_matching_rows(table, &table_meta, &schema, &db_path_str, &pk_where)?
+            {
+                if seen.insert(rid) {
                     targets.push((rid, record));
                 }
             }
         }
 
-        if !referencing_checks.is_empty() {
-            let db_name = self
-                .current_db
-                .clone()
-                .ok_or(DatabaseError::NoDatabaseSelected)?;
-            for (_rid, record) in &targets {
-                self.validate_foreign_keys_on_delete_record(
-                    &db_name,
-                    &db_path_str,
-                    &referencing_checks,
+        self.check_unique_constraints(table, &table_meta, &records, &seen)?;
+
+        let deleted = self.delete_rows(table, &table_meta, &db_path_str, targets)?;
+        let inserted = self.insert(table, rows)?;
+        Ok(deleted + inserted)
+    }
+
+    /// `INSERT ... ON DUPLICATE KEY UPDATE`: insert each row in turn, or apply
+    /// `updates` to the existing row if one with the same primary key exists
+    ///
+    /// Returns the number of rows inserted plus the number updated. Tables
+    /// without a primary key have nothing to collide on, so every row is
+    /// inserted.
+    pub fn upsert(
+        &mut self,
+        table: &str,
+        rows: Vec<Vec<ParserValue>>,
+        updates: Vec<(String, ParserValue)>,
+    ) -> DatabaseResult<usize> {
+        let table_meta = self
+            .current_metadata
+            .as_ref()
+            .ok_or(DatabaseError::NoDatabaseSelected)?
+            .get_table(table)?
+            .clone();
+        let Some(pk_cols) = table_meta.primary_key.clone() else {
+            return self.insert(table, rows);
+        };
+        let schema = self.metadata_to_schema(&table_meta);
+
+        let db_name = self.current_db.as_ref().unwrap();
+        let db_path_str = self.data_dir.join(db_name).to_string_lossy().to_string();
+        let table_path_str = self
+            .table_path(db_name, table)
+            .to_string_lossy()
+            .to_string();
+
+        // Try to open table if not already open (ignore error if already open)
+        let _ = self
+            .record_manager
+            .open_table(&table_path_str, schema.clone());
+
+        let pk_indices: Vec<usize> = pk_cols
+            .iter()
+            .map(|col_name| {
+                table_meta
+                    .columns
+                    .iter()
+                    .position(|c| &c.name == col_name)
+                    .unwrap()
+            })
+            .collect();
+
+        // Rows are handled one at a time, so a later row sees the ones before it
+        self.logged_unit(|db| {
+            let mut count = 0;
+            for row in rows {
+                let pk_where: Vec<WhereClause> = pk_indices
+                    .iter()
+                    .map(|&idx| {
+                        WhereClause::Op(
+                            TableColumn {
+                                table: None,
+                                column: table_meta.columns[idx].name.clone(),
+                            },
+                            Operator::Eq,
+                            Expression::Value(row.get(idx).cloned().unwrap_or(ParserValue::Null)),
+                        )
+                    })
+                    .collect();
+                let exists = !db
+                    .collect_matching_rows(table, &table_meta, &schema, &db_path_str, &pk_where)?
+                    .is_empty();
+                count += if exists {
+                    db.update(table, updates.clone(), Some(pk_where))?
+                } else {
+                    db.insert(table, vec![row])?
+                };
+            }
+            Ok(count)
+        })
+    }
+
+    pub fn delete(
+        &mut self,
+        table: &str,
+        where_clauses: Option<Vec<WhereClause>>,
+    ) -> DatabaseResult<usize> {
+        let (table_meta, schema) = {
+            let metadata = self
+                .current_metadata
+                .as_ref()
+                .ok_or(DatabaseError::NoDatabaseSelected)?;
+
+            let table_meta = metadata.get_table(table)?.clone();
+            let schema = self.metadata_to_schema(&table_meta);
+            (table_meta, schema)
+        };
+
+        let db_name = self.current_db.as_ref().unwrap();
+        let db_path = self.data_dir.join(db_name);
+        let db_path_str = db_path.to_string_lossy().to_string();
+        let table_path = self.table_path(db_name, table);
+        let table_path_str = table_path.to_string_lossy().to_string();
+
+        // Try to open table if not already open (ignore error if already open)
+        let _ = self
+            .record_manager
+            .open_table(&table_path_str, schema.clone());
+
+        let where_slice: &[WhereClause] = match &where_clauses {
+            Some(clauses) => clauses,
+            None => &[],
+        };
+        let targets =
+            self.collect_matching_rows(table, &table_meta, &schema, &db_path_str, where_slice)?;
+        self.delete_rows(table, &table_meta, &db_path_str, targets)
+    }
+
+    /// `DELETE ... RETURNING *`: delete like [`delete`](Self::delete) and
+    /// return the deleted rows, formatted as `SELECT *` would show them
+    pub fn delete_returning(
+        &mut self,
+        table: &str,
+        where_clauses: Option<Vec<WhereClause>>,
+    ) -> DatabaseResult<(Vec<String>, Vec<Vec<String>>)> {
+        let (table_meta, schema) = {
+            let metadata = self
+                .current_metadata
+                .as_ref()
+                .ok_or(DatabaseError::NoDatabaseSelected)?;
+
+            let table_meta = metadata.get_table(table)?.clone();
+            let schema = self.metadata_to_schema(&table_meta);
+            (table_meta, schema)
+        };
+
+        let db_name = self.current_db.as_ref().unwrap();
+        let db_path_str = self.data_dir.join(db_name).to_string_lossy().to_string();
+        let table_path = self.table_path(db_name, table);
+        let _ = self
+            .record_manager
+            .open_table(&table_path.to_string_lossy(), schema.clone());
+
+        let where_slice: &[WhereClause] = match &where_clauses {
+            Some(clauses) => clauses,
+            None => &[],
+        };
+        let targets =
+            self.collect_matching_rows(table, &table_meta, &schema, &db_path_str, where_slice)?;
+        let rows = targets
+            .iter()
+            .map(|(_rid, record)| {
+                (0..schema.columns.len())
+                    .map(|idx| self.format_value(record.get(idx).unwrap()))
+                    .collect()
+            })
+            .collect();
+        self.delete_rows(table, &table_meta, &db_path_str, targets)?;
+
+        let headers = schema.columns.iter().map(|col| col.name.clone()).collect();
+        Ok((headers, rows))
+    }
+
+    /// Collect the rows of an open table that satisfy `where_slice`, using an index when one applies
+    fn collect_matching_rows(
+        &mut self,
+        table: &str,
+        table_meta: &TableMetadata,
+        schema: &TableSchema,
+        db_path_str: &str,
+        where_slice: &[WhereClause],
+    ) -> DatabaseResult<Vec<(RecordId, Record)>> {
+        let prepared_where = if where_slice.is_empty() {
+            None
+        } else {
+            Some(self.prepare_where_clauses(where_slice)?)
+        };
+
+        let mut targets = Vec::new();
+        let mut budget = QueryBudget::new(self.statement_timeout, self.running_query.clone());
+        let index_candidates =
+            self.index_candidates_for_where(db_path_str, table_meta, schema, where_slice)?;
+        if let Some(rids) = index_candidates {
+            for rid in rids {
+                budget.tick()?;
+                let record = self.record_manager.get(table, rid)?;
+                let should_delete = match &prepared_where {
+                    None => true,
+                    Some(clauses) => self.evaluate_prepared_where(&record, schema, clauses)?,
+                };
+                if should_delete {
+                    targets.push((rid, record));
+                }
+            }
+        } else {
+            let scan_iter = self.record_manager.scan_iter(table)?;
+            for item in scan_iter {
+                let (rid, record) = item?;
+                budget.tick()?;
+                let should_delete = match &prepared_where {
+                    None => true,
+                    Some(clauses) => self.evaluate_prepared_where(&record, schema, clauses)?,
+                };
+                if should_delete {
+                    targets.push((rid, record));
+                }
+            }
+        }
+
+        Ok(targets)
+    }
+
+    /// Delete rows and their index entries
+    ///
+    /// Every row is checked against referencing foreign keys before anything
+    /// is removed, so a RESTRICT violation leaves the table untouched.
+    fn delete_rows(
+        &mut self,
+        table: &str,
+        table_meta: &TableMetadata,
+        db_path_str: &str,
+        targets: Vec<(RecordId, Record)>,
+    ) -> DatabaseResult<usize> {
+        let referencing_checks = self.build_referencing_fk_checks(table_meta)?;
+        if !referencing_checks.is_empty() {
+            let db_name = self
+                .current_db
+                .clone()
+                .ok_or(DatabaseError::NoDatabaseSelected)?;
+            for (_rid, record) in &targets {
+                self.validate_foreign_keys_on_delete_record(
+                    &db_name,
+                    db_path_str,
+                    &referencing_checks,
                     record,
                 )?;
             }
         }
 
+        let indexed_defs = self.open_indexed_defs(db_path_str, table_meta)?;
+        let mut deleted = 0;
+
+        // Index entries are removed per index in one batch once the rows are gone
+        let mut index_entries: Vec<Vec<(i64, RecordId)>> = vec![Vec::new(); indexed_defs.len()];
         for (rid, record) in targets {
             self.record_manager.delete(table, rid)?;
             if !indexed_defs.is_empty() {
-                for def in &indexed_defs {
+                for (def, entries) in indexed_defs.iter().zip(&mut index_entries) {
                     let key = match def.indices.as_slice() {
-                        [col_idx] => match record.get(*col_idx) {
-                            Some(RecordValue::Int(val)) => Some(*val as i64),
-                            _ => None,
-                        },
+                        [col_idx] => record.get(*col_idx).and_then(index_value_key),
                         [left_idx, right_idx] => {
                             let left = match record.get(*left_idx) {
                                 Some(RecordValue::Int(val)) => *val,
@@ -889,15 +2296,22 @@ impl DatabaseManager {
                         _ => None,
                     };
                     if let Some(key) = key {
-                        let _ = self
-                            .index_manager
-                            .delete_entry(table, &def.storage_name, key, rid)?;
+                        entries.push((key, rid));
                     }
                 }
             }
             deleted += 1;
         }
+        for (def, entries) in indexed_defs.iter().zip(&index_entries) {
+            if !entries.is_empty() {
+                self.index_manager
+                    .bulk_delete(table, &def.storage_name, entries)?;
+            }
+        }
 
+        if deleted > 0 {
+            self.bump_table_version(table)?;
+        }
         Ok(deleted)
     }
 
@@ -973,10 +2387,12 @@ impl DatabaseManager {
         };
         let mut updated = 0;
         let mut targets = Vec::new();
+        let mut budget = QueryBudget::new(self.statement_timeout, self.running_query.clone());
         let index_candidates =
             self.index_candidates_for_where(&db_path_str, &table_meta, &schema, where_slice)?;
         if let Some(rids) = index_candidates {
             for rid in rids {
+                budget.tick()?;
                 let record = self.record_manager.get(table, rid)?;
                 let should_update = match &prepared_where {
                     None => true,
@@ -990,6 +2406,7 @@ impl DatabaseManager {
             let scan_iter = self.record_manager.scan_iter(table)?;
             for item in scan_iter {
                 let (rid, record) = item?;
+                budget.tick()?;
                 let should_update = match &prepared_where {
                     None => true,
                     Some(clauses) => self.evaluate_prepared_where(&record, &schema, clauses)?,
@@ -1000,15 +2417,50 @@ impl DatabaseManager {
             }
         }
 
-        for (rid, mut record) in targets {
-            let original = record.clone();
+        let mut changes = Vec::with_capacity(targets.len());
+        for (rid, original) in targets {
+            let mut record = original.clone();
             // Apply updates
             for (col_idx, new_value) in &update_map {
                 let data_type = &schema.columns[*col_idx].data_type;
                 let record_value = self.parser_value_to_record_value(new_value, data_type)?;
-                record.set(*col_idx, record_value);
+                record.set(
+                    *col_idx,
+                    self.round_to_column_scale(record_value, data_type)?,
+                );
             }
+            changes.push((rid, original, record));
+        }
+
+        // Updated rows are checked together, so swapping unique values between them is fine
+        if table_meta.indexes.iter().any(|idx| {
+            idx.unique
+                && idx.columns.iter().any(|col| {
+                    update_indices
+                        .iter()
+                        .any(|&i| schema.columns[i].name == *col)
+                })
+        }) {
+            let replaced: HashSet<RecordId> = changes.iter().map(|(rid, _, _)| *rid).collect();
+            let records: Vec<Record> = changes
+                .iter()
+                .map(|(_, _, record)| record.clone())
+                .collect();
+            self.check_unique_constraints(table, &table_meta, &records, &replaced)?;
+        }
+        if let Some(pk_cols) = &table_meta.primary_key
+            && pk_cols.iter().any(|col| {
+                update_indices
+                    .iter()
+                    .any(|&i| schema.columns[i].name == *col)
+            })
+        {
+            let replaced: HashSet<RecordId> = changes.iter().map(|(rid, _, _)| *rid).collect();
+            let records: Vec<&Record> = changes.iter().map(|(_, _, record)| record).collect();
+            self.check_primary_key_on_update(table, &table_meta, pk_cols, &records, &replaced)?;
+        }
 
+        for (rid, original, record) in changes {
             if should_check_referencing {
                 let mut changed_fks = Vec::new();
                 for fk in &referencing_checks {
@@ -1061,7 +2513,10 @@ impl DatabaseManager {
             }
 
             let updated_record = record.clone();
-            self.record_manager.update(table, rid, updated_record)?;
+            // A row that outgrew its page comes back under a new RID, which
+            // every index entry for it has to follow
+            let new_rid = self.record_manager.update(table, rid, updated_record)?;
+            let moved = new_rid != rid;
             if !indexed_defs.is_empty() {
                 for def in &indexed_defs {
                     let mut uses_update = false;
@@ -1071,124 +2526,376 @@ impl DatabaseManager {
                             break;
                         }
                     }
-                    if !uses_update {
+                    if !uses_update && !moved {
                         continue;
                     }
 
                     let old_key = match def.indices.as_slice() {
-                        [col_idx] => match original.get(*col_idx).unwrap() {
-                            RecordValue::Int(val) => Some(*val as i64),
-                            _ => None,
-                        },
+                        [col_idx] => index_value_key(original.get(*col_idx).unwrap()),
                         [left_idx, right_idx] => match (
                             original.get(*left_idx).unwrap(),
                             original.get(*right_idx).unwrap(),
                         ) {
-                            (RecordValue::Int(left), RecordValue::Int(right)) => Some(
-                                TableCompositeIntColumnIter::composite_key(*left, *right),
-                            ),
+                            (RecordValue::Int(left), RecordValue::Int(right)) => {
+                                Some(TableCompositeIntColumnIter::composite_key(*left, *right))
+                            }
                             _ => None,
                         },
                         _ => None,
                     };
                     let new_key = match def.indices.as_slice() {
-                        [col_idx] => match record.get(*col_idx).unwrap() {
-                            RecordValue::Int(val) => Some(*val as i64),
-                            _ => None,
-                        },
+                        [col_idx] => index_value_key(record.get(*col_idx).unwrap()),
                         [left_idx, right_idx] => match (
                             record.get(*left_idx).unwrap(),
                             record.get(*right_idx).unwrap(),
                         ) {
-                            (RecordValue::Int(left), RecordValue::Int(right)) => Some(
-                                TableCompositeIntColumnIter::composite_key(*left, *right),
-                            ),
+                            (RecordValue::Int(left), RecordValue::Int(right)) => {
+                                Some(TableCompositeIntColumnIter::composite_key(*left, *right))
+                            }
                             _ => None,
                         },
                         _ => None,
                     };
 
-                    if old_key == new_key {
+                    if old_key == new_key && !moved {
                         continue;
                     }
 
                     if let Some(key) = old_key {
-                        let _ = self
-                            .index_manager
-                            .delete_entry(table, &def.storage_name, key, rid)?;
+                        let _ =
+                            self.index_manager
+                                .delete_entry(table, &def.storage_name, key, rid)?;
                     }
                     if let Some(key) = new_key {
                         self.index_manager
-                            .insert(table, &def.storage_name, key, rid)?;
+                            .insert(table, &def.storage_name, key, new_rid)?;
                     }
                 }
             }
             updated += 1;
         }
 
+        if updated > 0 {
+            self.bump_table_version(table)?;
+        }
         Ok(updated)
     }
 
-    pub fn select(
-        &mut self,
-        clause: SelectClause,
-    ) -> DatabaseResult<(Vec<String>, Vec<Vec<String>>)> {
-        match clause.table.len() {
-            1 => self.select_single_table(clause),
-            2 => self.select_two_table_join(clause),
-            _ => Err(DatabaseError::TypeMismatch(
-                "Only single-table and two-table queries are supported".to_string(),
-            )),
+    /// Fetch a row by its record id
+    ///
+    /// Returns `None` if the RID does not address a live row. See
+    /// [`RecordId`] for when RIDs stay stable.
+    pub fn get_by_rid(&mut self, table: &str, rid: RecordId) -> DatabaseResult<Option<Record>> {
+        let schema = {
+            let metadata = self
+                .current_metadata
+                .as_ref()
+                .ok_or(DatabaseError::NoDatabaseSelected)?;
+            let table_meta = metadata.get_table(table)?;
+            self.metadata_to_schema(table_meta)
+        };
+
+        let db_name = self.current_db.as_ref().unwrap();
+        let table_path = self.table_path(db_name, table);
+        let _ = self
+            .record_manager
+            .open_table(&table_path.to_string_lossy(), schema);
+
+        match self.record_manager.get(table, rid) {
+            Ok(record) => Ok(Some(record)),
+            Err(crate::record::RecordError::InvalidSlot(..)) => Ok(None),
+            Err(e) => Err(e.into()),
         }
     }
 
-    fn select_single_table(
+    /// Stream every row of a table as typed values paired with column names
+    pub fn scan_table(
         &mut self,
-        clause: SelectClause,
-    ) -> DatabaseResult<(Vec<String>, Vec<Vec<String>>)> {
-        let table_name = &clause.table[0];
-        let (table_meta, schema) = {
+        table: &str,
+    ) -> DatabaseResult<impl Iterator<Item = DatabaseResult<TypedRow>> + use<>> {
+        let (schema, columns) = {
             let metadata = self
                 .current_metadata
                 .as_ref()
                 .ok_or(DatabaseError::NoDatabaseSelected)?;
-            let table_meta = metadata.get_table(table_name)?.clone();
-            let schema = self.metadata_to_schema(&table_meta);
-            (table_meta, schema)
+            let table_meta = metadata.get_table(table)?;
+            let columns: Arc<[String]> = table_meta
+                .columns
+                .iter()
+                .map(|col| col.name.clone())
+                .collect();
+            (self.metadata_to_schema(table_meta), columns)
         };
 
         let db_name = self.current_db.as_ref().unwrap();
-        let db_path = self.data_dir.join(db_name);
-        let db_path_str = db_path.to_string_lossy();
-        let table_path = self.table_path(db_name, table_name);
-        let table_path_str = table_path.to_string_lossy().to_string();
+        let table_path = self.table_path(db_name, table);
+        let _ = self
+            .record_manager
+            .open_table(&table_path.to_string_lossy(), schema);
+
+        let scan_iter = self.record_manager.scan_iter(table)?;
+        Ok(scan_iter.map(move |item| {
+            let (rid, record) = item?;
+            Ok(TypedRow {
+                rid,
+                columns: columns.clone(),
+                values: record.into_values(),
+            })
+        }))
+    }
 
-        // Open table if not already open
-        self.record_manager
-            .open_table(&table_path_str, schema.clone())?;
+    pub fn select(
+        &mut self,
+        clause: SelectClause,
+    ) -> DatabaseResult<(Vec<String>, Vec<Vec<String>>)> {
+        let (columns, rows) = self.select_typed(clause)?;
+        let headers = columns.into_iter().map(|column| column.name).collect();
+        Ok((headers, rows))
+    }
 
-        if self.select_has_aggregate(&clause.selectors) || clause.group_by.is_some() {
-            return self.select_single_table_aggregate(&clause, &schema, table_name);
+    /// Like [`select`](Self::select), but each header comes with the type
+    /// and nullability of the values under it
+    pub fn select_typed(
+        &mut self,
+        clause: SelectClause,
+    ) -> DatabaseResult<(Vec<ResultColumn>, Vec<Vec<String>>)> {
+        if clause.table.iter().any(|table| table.contains('.')) {
+            return self.select_across_databases(clause);
         }
+        self.select_tables(clause)
+    }
 
-        let (selected_columns, col_indices) = match &clause.selectors {
-            Selectors::All => {
-                let columns = schema.columns.iter().map(|c| c.name.clone()).collect();
-                let indices = (0..schema.columns.len()).collect();
-                (columns, indices)
+    /// SELECT over tables named `db.table`
+    ///
+    /// Each table from another database is attached to the current catalog
+    /// under its qualified name for the duration of the query, so the rest of
+    /// the SELECT machinery reads it like a local table. Attached tables are
+    /// read-only and always scanned: their indexes belong to the other
+    /// database and are not consulted.
+    fn select_across_databases(
+        &mut self,
+        mut clause: SelectClause,
+    ) -> DatabaseResult<(Vec<ResultColumn>, Vec<Vec<String>>)> {
+        let current_db = self
+            .current_db
+            .clone()
+            .ok_or(DatabaseError::NoDatabaseSelected)?;
+
+        clause.table_aliases.resize(clause.table.len(), None);
+        let mut attached = Vec::new();
+        for (table, alias) in clause.table.iter_mut().zip(&mut clause.table_aliases) {
+            let Some((db, name)) = table.split_once('.') else {
+                continue;
+            };
+            // Unaliased, the table answers to its bare name like a local one
+            if alias.is_none() {
+                *alias = Some(name.to_string());
             }
-            Selectors::List(selectors) => {
-                let mut columns = Vec::new();
-                let mut indices = Vec::new();
-                for selector in selectors {
-                    match selector {
-                        Selector::Column(tc) => {
-                            let col_idx = self.resolve_single_column_index(&schema, tc)?;
-                            columns.push(tc.column.clone());
-                            indices.push(col_idx);
-                        }
-                        _ => {
-                            return Err(DatabaseError::TypeMismatch(
+            if db == current_db {
+                *table = name.to_string();
+                continue;
+            }
+            let mut table_meta = self.load_database_metadata(db)?.get_table(name)?.clone();
+            table_meta.name = table.clone();
+            attached.push(table_meta);
+        }
+
+        let names: Vec<String> = attached.iter().map(|meta| meta.name.clone()).collect();
+        let metadata = self.current_metadata.as_mut().unwrap();
+        for table_meta in attached {
+            metadata.tables.insert(table_meta.name.clone(), table_meta);
+        }
+
+        let result = self.select_tables(clause);
+
+        let metadata = self.current_metadata.as_mut().unwrap();
+        for name in &names {
+            metadata.tables.remove(name);
+            self.record_manager.close_table(name)?;
+        }
+        result
+    }
+
+    fn select_tables(
+        &mut self,
+        mut clause: SelectClause,
+    ) -> DatabaseResult<(Vec<ResultColumn>, Vec<Vec<String>>)> {
+        let table_refs = Self::table_refs(&clause)?;
+        let aliases = std::mem::take(&mut clause.selector_aliases);
+        Self::resolve_order_by_aliases(&mut clause, &aliases);
+        let (mut columns, rows) = match clause.table.len() {
+            1 => {
+                // With a single table an alias is just another name for it
+                let table_name = clause.table[0].clone();
+                Self::rename_table_qualifier(&mut clause, &table_refs[0], &table_name);
+                self.select_single_table(clause)
+            }
+            2 => self.select_two_table_join(clause, &table_refs[0], &table_refs[1]),
+            3 => self.select_three_table_join(clause, &table_refs),
+            _ => Err(DatabaseError::TypeMismatch(
+                "Joins of more than three tables are not supported".to_string(),
+            )),
+        }?;
+        // Every path yields one column per selector, so aliases line up with the headers
+        for (column, alias) in columns.iter_mut().zip(aliases) {
+            if let Some(alias) = alias {
+                column.name = alias;
+            }
+        }
+        Ok((columns, rows))
+    }
+
+    /// `EXPLAIN SELECT`: describe how the query would read its table, without
+    /// running it
+    ///
+    /// The access path comes from the same planning the SELECT itself goes
+    /// through, so the line names the index a WHERE clause actually gets.
+    /// Aggregate queries always scan, and only single-table queries on the
+    /// current database can be explained.
+    pub fn explain(&mut self, mut clause: SelectClause) -> DatabaseResult<Vec<String>> {
+        if clause.table.len() != 1 || clause.table[0].contains('.') {
+            return Err(DatabaseError::TypeMismatch(
+                "EXPLAIN supports only single-table SELECT".to_string(),
+            ));
+        }
+        let table_refs = Self::table_refs(&clause)?;
+        let table_name = clause.table[0].clone();
+        Self::rename_table_qualifier(&mut clause, &table_refs[0], &table_name);
+
+        let table_meta = self
+            .current_metadata
+            .as_ref()
+            .ok_or(DatabaseError::NoDatabaseSelected)?
+            .get_table(&table_name)?
+            .clone();
+        let schema = self.metadata_to_schema(&table_meta);
+        let db_name = self.current_db.as_ref().unwrap();
+        let db_path_str = self.data_dir.join(db_name).to_string_lossy().to_string();
+        let table_path_str = self
+            .table_path(db_name, &table_name)
+            .to_string_lossy()
+            .to_string();
+        self.record_manager
+            .open_table(&table_path_str, schema.clone())?;
+
+        let path = if !self.is_aggregate_query(&clause) {
+            self.plan_index_access(&db_path_str, &table_meta, &schema, &clause.where_clauses)?
+        } else if self.where_never_matches(&clause.where_clauses)? {
+            AccessPath::NoRows
+        } else {
+            AccessPath::FullScan
+        };
+        let line = match path {
+            AccessPath::NoRows => format!("no rows read from {}: WHERE never matches", table_name),
+            AccessPath::FullScan => format!("full table scan on {}", table_name),
+            AccessPath::IndexScan {
+                storage_name,
+                composite,
+                ranges,
+            } => {
+                let index = if composite {
+                    "composite index"
+                } else {
+                    "index"
+                };
+                if ranges.iter().all(|(lower, upper)| lower == upper) {
+                    format!(
+                        "{} lookup on {} using index {}",
+                        index, table_name, storage_name
+                    )
+                } else {
+                    format!(
+                        "range scan on {} using {} {}",
+                        table_name, index, storage_name
+                    )
+                }
+            }
+        };
+        Ok(vec![line])
+    }
+
+    /// Replace each unqualified ORDER BY column named like a selector alias
+    /// with the selector it stands for, so `ORDER BY n` sorts by `COUNT(*) AS n`
+    fn resolve_order_by_aliases(clause: &mut SelectClause, aliases: &[Option<String>]) {
+        let Selectors::List(selectors) = &clause.selectors else {
+            return;
+        };
+        for (selector, _) in &mut clause.order_by {
+            let Selector::Column(TableColumn {
+                table: None,
+                column,
+            }) = selector
+            else {
+                continue;
+            };
+            if let Some(pos) = aliases
+                .iter()
+                .position(|alias| alias.as_deref() == Some(column.as_str()))
+            {
+                *selector = selectors[pos].clone();
+            }
+        }
+    }
+
+    fn select_single_table(
+        &mut self,
+        clause: SelectClause,
+    ) -> DatabaseResult<(Vec<ResultColumn>, Vec<Vec<String>>)> {
+        let table_name = &clause.table[0];
+        let (table_meta, schema) = {
+            let metadata = self
+                .current_metadata
+                .as_ref()
+                .ok_or(DatabaseError::NoDatabaseSelected)?;
+            let table_meta = metadata.get_table(table_name)?.clone();
+            let schema = self.metadata_to_schema(&table_meta);
+            (table_meta, schema)
+        };
+
+        let db_name = self.current_db.as_ref().unwrap();
+        let db_path = self.data_dir.join(db_name);
+        let db_path_str = db_path.to_string_lossy();
+        let table_path = self.table_path(db_name, table_name);
+        let table_path_str = table_path.to_string_lossy().to_string();
+
+        // Open table if not already open
+        self.record_manager
+            .open_table(&table_path_str, schema.clone())?;
+
+        if self.is_aggregate_query(&clause) {
+            return self.select_single_table_aggregate(&clause, &schema, &table_meta, &db_path_str);
+        }
+
+        let (selected_columns, col_indices) = match &clause.selectors {
+            Selectors::All => {
+                let columns = schema
+                    .columns
+                    .iter()
+                    .map(|c| ResultColumn::from_column(c.name.clone(), c))
+                    .collect();
+                let indices = (0..schema.columns.len()).map(|idx| (idx, None)).collect();
+                (columns, indices)
+            }
+            Selectors::List(selectors) => {
+                let mut columns = Vec::new();
+                let mut indices = Vec::new();
+                for selector in selectors {
+                    match selector {
+                        Selector::Column(tc) => {
+                            let col_idx = self.resolve_single_column_index(&schema, tc)?;
+                            columns.push(ResultColumn::from_column(
+                                tc.column.clone(),
+                                &schema.columns[col_idx],
+                            ));
+                            indices.push((col_idx, None));
+                        }
+                        Selector::Cast(tc, ty) => {
+                            let col_idx = self.resolve_single_column_index(&schema, tc)?;
+                            columns.push(self.cast_result_column(tc, *ty));
+                            indices.push((col_idx, Some(*ty)));
+                        }
+                        _ => {
+                            return Err(DatabaseError::TypeMismatch(
                                 "Aggregates not yet supported".to_string(),
                             ));
                         }
@@ -1198,11 +2905,10 @@ impl DatabaseManager {
             }
         };
 
-        let order_by_idx = clause
-            .order_by
-            .as_ref()
-            .map(|(col, _)| self.resolve_single_column_index(&schema, col))
-            .transpose()?;
+        let order_by = Self::order_by_columns(&clause)?
+            .into_iter()
+            .map(|(col, asc)| Ok((self.resolve_single_column_index(&schema, col)?, asc)))
+            .collect::<DatabaseResult<Vec<(usize, bool)>>>()?;
         let prepared_where = if clause.where_clauses.is_empty() {
             None
         } else {
@@ -1211,14 +2917,79 @@ impl DatabaseManager {
 
         let mut result_rows = Vec::new();
         let mut order_rows = Vec::new();
+        let mut budget = QueryBudget::new(self.statement_timeout, self.running_query.clone());
         let index_candidates = self.index_candidates_for_where(
             db_path_str.as_ref(),
             &table_meta,
             &schema,
             &clause.where_clauses,
         )?;
-        if let Some(rids) = index_candidates {
+        let descending_rids = match index_candidates {
+            Some(_) => None,
+            None => {
+                self.index_descending_rids(db_path_str.as_ref(), &table_meta, &schema, &order_by)?
+            }
+        };
+        let presorted = descending_rids.is_some();
+        if let Some(rids) = descending_rids {
+            // Rows arrive in output order, so reading stops once LIMIT is met
+            let wanted = match clause.limit {
+                Some(limit) if !clause.limit_percent => {
+                    Some(clause.offset.unwrap_or(0).saturating_add(limit))
+                }
+                _ => None,
+            };
+            let done = |rows: &Vec<Vec<String>>| wanted.is_some_and(|wanted| rows.len() >= wanted);
+            let order_idx = order_by[0].0;
+            for rid in rids {
+                if done(&result_rows) {
+                    break;
+                }
+                budget.tick()?;
+                let record = self.record_manager.get(table_name, rid)?;
+                let matches = match &prepared_where {
+                    None => true,
+                    Some(clauses) => self.evaluate_prepared_where(&record, &schema, clauses)?,
+                };
+                if matches {
+                    let mut row = Vec::new();
+                    for &(idx, cast) in &col_indices {
+                        let value = record.get(idx).unwrap();
+                        row.push(self.format_projected_value(value, cast));
+                    }
+                    result_rows.push(row);
+                }
+            }
+
+            // NULLs are not indexed and sort last when descending
+            if !schema.columns[order_idx].not_null && !done(&result_rows) {
+                let scan_iter = self.record_manager.scan_iter(table_name)?;
+                for item in scan_iter {
+                    let (_rid, record) = item?;
+                    budget.tick()?;
+                    if record.get(order_idx) != Some(&RecordValue::Null) {
+                        continue;
+                    }
+                    let matches = match &prepared_where {
+                        None => true,
+                        Some(clauses) => self.evaluate_prepared_where(&record, &schema, clauses)?,
+                    };
+                    if matches {
+                        let mut row = Vec::new();
+                        for &(idx, cast) in &col_indices {
+                            let value = record.get(idx).unwrap();
+                            row.push(self.format_projected_value(value, cast));
+                        }
+                        result_rows.push(row);
+                        if done(&result_rows) {
+                            break;
+                        }
+                    }
+                }
+            }
+        } else if let Some(rids) = index_candidates {
             for rid in rids {
+                budget.tick()?;
                 let record = self.record_manager.get(table_name, rid)?;
                 let matches = match &prepared_where {
                     None => true,
@@ -1227,16 +2998,19 @@ impl DatabaseManager {
 
                 if matches {
                     let mut row = Vec::new();
-                    for &idx in &col_indices {
+                    for &(idx, cast) in &col_indices {
                         let value = record.get(idx).unwrap();
-                        row.push(self.format_value(value));
+                        row.push(self.format_projected_value(value, cast));
                     }
 
-                    if let Some(order_idx) = order_by_idx {
-                        let key = record.get(order_idx).unwrap().clone();
-                        order_rows.push((key, row));
-                    } else {
+                    if order_by.is_empty() {
                         result_rows.push(row);
+                    } else {
+                        let keys = order_by
+                            .iter()
+                            .map(|&(idx, _)| record.get(idx).unwrap().clone())
+                            .collect();
+                        order_rows.push((keys, row));
                     }
                 }
             }
@@ -1245,6 +3019,7 @@ impl DatabaseManager {
             let scan_iter = self.record_manager.scan_iter(table_name)?;
             for item in scan_iter {
                 let (_rid, record) = item?;
+                budget.tick()?;
                 // Evaluate WHERE clause
                 let matches = match &prepared_where {
                     None => true,
@@ -1254,58 +3029,87 @@ impl DatabaseManager {
                 if matches {
                     // Project selected columns
                     let mut row = Vec::new();
-                    for &idx in &col_indices {
+                    for &(idx, cast) in &col_indices {
                         let value = record.get(idx).unwrap();
-                        row.push(self.format_value(value));
+                        row.push(self.format_projected_value(value, cast));
                     }
 
-                    if let Some(order_idx) = order_by_idx {
-                        let key = record.get(order_idx).unwrap().clone();
-                        order_rows.push((key, row));
-                    } else {
+                    if order_by.is_empty() {
                         result_rows.push(row);
+                    } else {
+                        let keys = order_by
+                            .iter()
+                            .map(|&(idx, _)| record.get(idx).unwrap().clone())
+                            .collect();
+                        order_rows.push((keys, row));
                     }
                 }
             }
         }
 
-        let mut result_rows = if let Some((_, asc)) = clause.order_by {
-            let mut ordering_error = None;
-            order_rows.sort_by(|(left_key, _), (right_key, _)| {
-                match self.compare_order_values(left_key, right_key) {
-                    Ok(ordering) => {
-                        if asc {
-                            ordering
-                        } else {
-                            ordering.reverse()
-                        }
-                    }
-                    Err(err) => {
-                        if ordering_error.is_none() {
-                            ordering_error = Some(err);
-                        }
-                        Ordering::Equal
-                    }
-                }
-            });
-            if let Some(err) = ordering_error {
-                return Err(err);
-            }
-            order_rows.into_iter().map(|(_, row)| row).collect()
-        } else {
-            result_rows
-        };
+        if !order_by.is_empty() && !presorted {
+            let directions: Vec<bool> = order_by.iter().map(|&(_, asc)| asc).collect();
+            result_rows = self.sort_order_rows(order_rows, &directions)?;
+        }
 
-        result_rows = self.apply_limit_offset(result_rows, clause.limit, clause.offset);
+        result_rows = Self::apply_limit_offset(result_rows, &clause);
 
         Ok((selected_columns, result_rows))
     }
 
+    /// Row locations in `ORDER BY col DESC` order, read backwards from the
+    /// index when the single ORDER BY column is an indexed INT column
+    ///
+    /// Rows sharing a key are kept in RID order, as the stable sort they
+    /// replace would leave them. Rows with a NULL key are not indexed and
+    /// are left to the caller.
+    fn index_descending_rids(
+        &mut self,
+        db_path: &str,
+        table_meta: &TableMetadata,
+        schema: &TableSchema,
+        order_by: &[(usize, bool)],
+    ) -> DatabaseResult<Option<Vec<RecordId>>> {
+        let &[(col_idx, false)] = order_by else {
+            return Ok(None);
+        };
+        let column = &schema.columns[col_idx];
+        if !self.use_indexes
+            || column.data_type != DataType::Int
+            || !Self::has_single_column_index(table_meta, &column.name)
+            || !self.ensure_index_open_for_columns(
+                db_path,
+                table_meta,
+                schema,
+                std::slice::from_ref(&column.name),
+            )?
+        {
+            return Ok(None);
+        }
+
+        let mut rids = Vec::new();
+        let mut run: Vec<RecordId> = Vec::new();
+        let mut run_key = None;
+        for (key, rid) in self.index_manager.rev_iter(&table_meta.name, &column.name) {
+            if run_key != Some(key) {
+                run.sort_by_key(|rid| (rid.page_id, rid.slot_id));
+                rids.append(&mut run);
+                run_key = Some(key);
+            }
+            run.push(rid);
+        }
+        run.sort_by_key(|rid| (rid.page_id, rid.slot_id));
+        rids.append(&mut run);
+        Ok(Some(rids))
+    }
+
     fn select_two_table_join(
         &mut self,
         clause: SelectClause,
-    ) -> DatabaseResult<(Vec<String>, Vec<Vec<String>>)> {
-        if self.select_has_aggregate(&clause.selectors) || clause.group_by.is_some() {
+        left_ref: &str,
+        right_ref: &str,
+    ) -> DatabaseResult<(Vec<ResultColumn>, Vec<Vec<String>>)> {
+        if self.is_aggregate_query(&clause) {
             return Err(DatabaseError::TypeMismatch(
                 "Aggregates are not supported with joins".to_string(),
             ));
@@ -1339,27 +3143,69 @@ impl DatabaseManager {
         self.record_manager
             .open_table(&right_path_str, right_schema.clone())?;
 
-        // Materialize the right table to keep join logic simple.
-        let right_rows = self.record_manager.scan(right_name)?;
-        let right_records: Vec<Record> = right_rows.into_iter().map(|(_, r)| r).collect();
+        // Probe the right table's index on the join column when there is one;
+        // otherwise materialize the right table, hashed on the join column
+        // when there is an equality join condition.
+        let db_path = self.data_dir.join(db_name).to_string_lossy().to_string();
+        let join_probe = self.join_index_probe(
+            &db_path,
+            &clause.where_clauses,
+            left_ref,
+            &left_schema,
+            right_ref,
+            &right_meta,
+            &right_schema,
+        )?;
+        let prepared_where = if clause.where_clauses.is_empty() {
+            None
+        } else {
+            Some(self.prepare_where_clauses(&clause.where_clauses)?)
+        };
+        let hash_join = match (&join_probe, &prepared_where) {
+            (None, Some(clauses)) => {
+                self.hash_join_columns(clauses, left_ref, &left_schema, right_ref, &right_schema)
+            }
+            _ => None,
+        };
+        let mut budget = QueryBudget::new(self.statement_timeout, self.running_query.clone());
+        let mut right_records: Vec<Record> = Vec::new();
+        let mut right_buckets: HashMap<JoinKey, Vec<Record>> = HashMap::new();
+        if join_probe.is_none() {
+            for (_, record) in self.record_manager.scan(right_name)? {
+                match hash_join {
+                    Some((_, right_index)) => {
+                        if let Some(key) = record.get(right_index).and_then(JoinKey::from_value) {
+                            right_buckets.entry(key).or_default().push(record);
+                        }
+                    }
+                    None => right_records.push(record),
+                }
+            }
+        }
 
         let (selected_columns, col_refs) = match &clause.selectors {
             Selectors::All => {
                 let mut columns = Vec::new();
                 let mut refs = Vec::new();
                 for (idx, col) in left_schema.columns.iter().enumerate() {
-                    columns.push(col.name.clone());
-                    refs.push(JoinColumnRef {
-                        side: JoinSide::Left,
-                        index: idx,
-                    });
+                    columns.push(ResultColumn::from_column(col.name.clone(), col));
+                    refs.push((
+                        JoinColumnRef {
+                            side: JoinSide::Left,
+                            index: idx,
+                        },
+                        None,
+                    ));
                 }
                 for (idx, col) in right_schema.columns.iter().enumerate() {
-                    columns.push(col.name.clone());
-                    refs.push(JoinColumnRef {
-                        side: JoinSide::Right,
-                        index: idx,
-                    });
+                    columns.push(ResultColumn::from_column(col.name.clone(), col));
+                    refs.push((
+                        JoinColumnRef {
+                            side: JoinSide::Right,
+                            index: idx,
+                        },
+                        None,
+                    ));
                 }
                 (columns, refs)
             }
@@ -1371,13 +3217,28 @@ impl DatabaseManager {
                         Selector::Column(tc) => {
                             let col_ref = self.resolve_join_column_ref(
                                 tc,
-                                left_name,
+                                left_ref,
                                 &left_schema,
-                                right_name,
+                                right_ref,
                                 &right_schema,
                             )?;
-                            columns.push(tc.column.clone());
-                            refs.push(col_ref);
+                            let column = match col_ref.side {
+                                JoinSide::Left => &left_schema.columns[col_ref.index],
+                                JoinSide::Right => &right_schema.columns[col_ref.index],
+                            };
+                            columns.push(ResultColumn::from_column(tc.column.clone(), column));
+                            refs.push((col_ref, None));
+                        }
+                        Selector::Cast(tc, ty) => {
+                            let col_ref = self.resolve_join_column_ref(
+                                tc,
+                                left_ref,
+                                &left_schema,
+                                right_ref,
+                                &right_schema,
+                            )?;
+                            columns.push(self.cast_result_column(tc, *ty));
+                            refs.push((col_ref, Some(*ty)));
                         }
                         _ => {
                             return Err(DatabaseError::TypeMismatch(
@@ -1390,69 +3251,115 @@ impl DatabaseManager {
             }
         };
 
-        let order_by_ref = clause
-            .order_by
-            .as_ref()
-            .map(|(col, _)| {
-                self.resolve_join_column_ref(
+        let order_by = Self::order_by_columns(&clause)?
+            .into_iter()
+            .map(|(col, asc)| {
+                let col_ref = self.resolve_join_column_ref(
                     col,
-                    left_name,
+                    left_ref,
                     &left_schema,
-                    right_name,
+                    right_ref,
                     &right_schema,
-                )
+                )?;
+                Ok((col_ref, asc))
             })
-            .transpose()?;
-        let prepared_where = if clause.where_clauses.is_empty() {
-            None
-        } else {
-            Some(self.prepare_where_clauses(&clause.where_clauses)?)
-        };
+            .collect::<DatabaseResult<Vec<(JoinColumnRef, bool)>>>()?;
 
         let mut result_rows = Vec::new();
         let mut order_rows = Vec::new();
-        let index_candidates = self.index_candidates_for_where(
-            &self.data_dir.join(db_name).to_string_lossy(),
-            &left_meta,
-            &left_schema,
-            &clause.where_clauses,
-        )?;
+        // Only conditions on the left table can narrow its scan. Columns are
+        // resolved the same way the join predicate resolves them, so an
+        // unqualified name that belongs to the right table is left out, and
+        // the survivors are spelled with the real table name the index lookup
+        // expects
+        let left_where: Vec<WhereClause> = clause
+            .where_clauses
+            .iter()
+            .filter_map(|where_clause| {
+                let mut where_clause = where_clause.clone();
+                // Constant predicates hold for every row on both sides, while
+                // an OR may read either table and cannot narrow the scan
+                let Some(column) = Self::where_clause_column_mut(&mut where_clause) else {
+                    return matches!(where_clause, WhereClause::Constant(..))
+                        .then_some(where_clause);
+                };
+                match self.resolve_join_column_ref(
+                    column,
+                    left_ref,
+                    &left_schema,
+                    right_ref,
+                    &right_schema,
+                ) {
+                    Ok(JoinColumnRef {
+                        side: JoinSide::Left,
+                        ..
+                    }) => {
+                        column.table = Some(left_name.clone());
+                        Some(where_clause)
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+        let index_candidates =
+            self.index_candidates_for_where(&db_path, &left_meta, &left_schema, &left_where)?;
         if let Some(rids) = index_candidates {
             for rid in rids {
+                budget.tick()?;
                 let left_record = self.record_manager.get(left_name, rid)?;
-                for right_record in &right_records {
+                let probed;
+                let right_candidates: &[Record] = match (&join_probe, hash_join) {
+                    (Some(probe), _) => {
+                        probed = self.probe_join_index(right_name, probe, &left_record)?;
+                        &probed
+                    }
+                    (None, Some((left_index, _))) => left_record
+                        .get(left_index)
+                        .and_then(JoinKey::from_value)
+                        .and_then(|key| right_buckets.get(&key))
+                        .map_or(&[], Vec::as_slice),
+                    (None, None) => &right_records,
+                };
+                for right_record in right_candidates {
+                    budget.tick()?;
                     let matches = match &prepared_where {
                         None => true,
                         Some(clauses) => self.evaluate_prepared_join_where(
                             &left_record,
                             &left_schema,
-                            left_name,
+                            left_ref,
                             right_record,
                             &right_schema,
-                            right_name,
+                            right_ref,
                             clauses,
                         )?,
                     };
 
                     if matches {
                         let mut row = Vec::new();
-                        for col_ref in &col_refs {
+                        for &(col_ref, cast) in &col_refs {
                             let value = match col_ref.side {
                                 JoinSide::Left => left_record.get(col_ref.index).unwrap(),
                                 JoinSide::Right => right_record.get(col_ref.index).unwrap(),
                             };
-                            row.push(self.format_value(value));
+                            row.push(self.format_projected_value(value, cast));
                         }
 
-                        if let Some(order_ref) = &order_by_ref {
-                            let key = match order_ref.side {
-                                JoinSide::Left => left_record.get(order_ref.index).unwrap(),
-                                JoinSide::Right => right_record.get(order_ref.index).unwrap(),
-                            }
-                            .clone();
-                            order_rows.push((key, row));
-                        } else {
+                        if order_by.is_empty() {
                             result_rows.push(row);
+                        } else {
+                            let keys = order_by
+                                .iter()
+                                .map(|(order_ref, _)| {
+                                    match order_ref.side {
+                                        JoinSide::Left => left_record.get(order_ref.index),
+                                        JoinSide::Right => right_record.get(order_ref.index),
+                                    }
+                                    .unwrap()
+                                    .clone()
+                                })
+                                .collect();
+                            order_rows.push((keys, row));
                         }
                     }
                 }
@@ -1461,75 +3368,496 @@ impl DatabaseManager {
             let scan_iter = self.record_manager.scan_iter(left_name)?;
             for item in scan_iter {
                 let (_rid, left_record) = item?;
-                for right_record in &right_records {
+                budget.tick()?;
+                let probed;
+                let right_candidates: &[Record] = match (&join_probe, hash_join) {
+                    (Some(probe), _) => {
+                        probed = self.probe_join_index(right_name, probe, &left_record)?;
+                        &probed
+                    }
+                    (None, Some((left_index, _))) => left_record
+                        .get(left_index)
+                        .and_then(JoinKey::from_value)
+                        .and_then(|key| right_buckets.get(&key))
+                        .map_or(&[], Vec::as_slice),
+                    (None, None) => &right_records,
+                };
+                for right_record in right_candidates {
+                    budget.tick()?;
                     let matches = match &prepared_where {
                         None => true,
                         Some(clauses) => self.evaluate_prepared_join_where(
                             &left_record,
                             &left_schema,
-                            left_name,
+                            left_ref,
                             right_record,
                             &right_schema,
-                            right_name,
+                            right_ref,
                             clauses,
                         )?,
                     };
 
                     if matches {
                         let mut row = Vec::new();
-                        for col_ref in &col_refs {
+                        for &(col_ref, cast) in &col_refs {
                             let value = match col_ref.side {
                                 JoinSide::Left => left_record.get(col_ref.index).unwrap(),
                                 JoinSide::Right => right_record.get(col_ref.index).unwrap(),
                             };
-                            row.push(self.format_value(value));
+                            row.push(self.format_projected_value(value, cast));
                         }
 
-                        if let Some(order_ref) = &order_by_ref {
-                            let key = match order_ref.side {
-                                JoinSide::Left => left_record.get(order_ref.index).unwrap(),
-                                JoinSide::Right => right_record.get(order_ref.index).unwrap(),
-                            }
-                            .clone();
-                            order_rows.push((key, row));
-                        } else {
+                        if order_by.is_empty() {
                             result_rows.push(row);
+                        } else {
+                            let keys = order_by
+                                .iter()
+                                .map(|(order_ref, _)| {
+                                    match order_ref.side {
+                                        JoinSide::Left => left_record.get(order_ref.index),
+                                        JoinSide::Right => right_record.get(order_ref.index),
+                                    }
+                                    .unwrap()
+                                    .clone()
+                                })
+                                .collect();
+                            order_rows.push((keys, row));
                         }
                     }
                 }
             }
         }
 
-        let mut result_rows = if let Some((_, asc)) = clause.order_by {
-            let mut ordering_error = None;
-            order_rows.sort_by(|(left_key, _), (right_key, _)| {
-                match self.compare_order_values(left_key, right_key) {
-                    Ok(ordering) => {
-                        if asc {
-                            ordering
-                        } else {
-                            ordering.reverse()
-                        }
-                    }
-                    Err(err) => {
-                        if ordering_error.is_none() {
-                            ordering_error = Some(err);
+        if !order_by.is_empty() {
+            let directions: Vec<bool> = order_by.iter().map(|&(_, asc)| asc).collect();
+            result_rows = self.sort_order_rows(order_rows, &directions)?;
+        }
+
+        result_rows = Self::apply_limit_offset(result_rows, &clause);
+
+        Ok((selected_columns, result_rows))
+    }
+
+    /// Nested-loop join of three tables, named by `table_refs` (aliases
+    /// where given). The middle and right tables are materialized and the
+    /// left one is scanned, through an index when its own conditions allow.
+    /// Each WHERE condition is checked as soon as every table it mentions has
+    /// a row, so conditions on the first two tables prune the inner loop.
+    fn select_three_table_join(
+        &mut self,
+        clause: SelectClause,
+        table_refs: &[String],
+    ) -> DatabaseResult<(Vec<ResultColumn>, Vec<Vec<String>>)> {
+        if self.is_aggregate_query(&clause) {
+            return Err(DatabaseError::TypeMismatch(
+                "Aggregates are not supported with joins".to_string(),
+            ));
+        }
+
+        let metas = {
+            let metadata = self
+                .current_metadata
+                .as_ref()
+                .ok_or(DatabaseError::NoDatabaseSelected)?;
+            clause
+                .table
+                .iter()
+                .map(|name| Ok(metadata.get_table(name)?.clone()))
+                .collect::<DatabaseResult<Vec<TableMetadata>>>()?
+        };
+        let schemas: Vec<TableSchema> = metas
+            .iter()
+            .map(|meta| self.metadata_to_schema(meta))
+            .collect();
+
+        let db_name = self.current_db.clone().unwrap();
+        for (name, schema) in clause.table.iter().zip(&schemas) {
+            let path = self.table_path(&db_name, name);
+            self.record_manager
+                .open_table(&path.to_string_lossy(), schema.clone())?;
+        }
+
+        let names: Vec<&str> = table_refs.iter().map(String::as_str).collect();
+        let schema_refs: Vec<&TableSchema> = schemas.iter().collect();
+        let resolve =
+            |column: &TableColumn| Self::resolve_multi_join_column(column, &names, &schema_refs);
+
+        let (selected_columns, col_refs) = match &clause.selectors {
+            Selectors::All => {
+                let mut columns = Vec::new();
+                let mut refs = Vec::new();
+                for (table, schema) in schemas.iter().enumerate() {
+                    for (idx, col) in schema.columns.iter().enumerate() {
+                        columns.push(ResultColumn::from_column(col.name.clone(), col));
+                        refs.push(((table, idx), None));
+                    }
+                }
+                (columns, refs)
+            }
+            Selectors::List(selectors) => {
+                let mut columns = Vec::new();
+                let mut refs = Vec::new();
+                for selector in selectors {
+                    match selector {
+                        Selector::Column(tc) => {
+                            let (table, idx) = resolve(tc)?;
+                            columns.push(ResultColumn::from_column(
+                                tc.column.clone(),
+                                &schemas[table].columns[idx],
+                            ));
+                            refs.push(((table, idx), None));
+                        }
+                        Selector::Cast(tc, ty) => {
+                            let col_ref = resolve(tc)?;
+                            columns.push(self.cast_result_column(tc, *ty));
+                            refs.push((col_ref, Some(*ty)));
+                        }
+                        _ => {
+                            return Err(DatabaseError::TypeMismatch(
+                                "Aggregates not yet supported".to_string(),
+                            ));
                         }
-                        Ordering::Equal
                     }
                 }
-            });
-            if let Some(err) = ordering_error {
-                return Err(err);
+                (columns, refs)
             }
-            order_rows.into_iter().map(|(_, row)| row).collect()
-        } else {
-            result_rows
         };
+        let order_by = Self::order_by_columns(&clause)?
+            .into_iter()
+            .map(|(col, asc)| Ok((resolve(col)?, asc)))
+            .collect::<DatabaseResult<Vec<((usize, usize), bool)>>>()?;
+
+        // stages[t] holds the conditions needing rows up to table t. A column
+        // that does not resolve leaves its condition for the innermost loop,
+        // where evaluating it reports the error.
+        let mut stages: [Vec<PreparedWhereClause>; 3] = Default::default();
+        for prepared in self.prepare_where_clauses(&clause.where_clauses)? {
+            let mut columns = Vec::new();
+            Self::prepared_clause_columns(&prepared, &mut columns);
+            let stage = columns
+                .iter()
+                .map(|column| resolve(column).map_or(2, |(table, _)| table))
+                .max()
+                .unwrap_or(0);
+            stages[stage].push(prepared);
+        }
 
-        result_rows = self.apply_limit_offset(result_rows, clause.limit, clause.offset);
+        // Conditions on the left table alone can narrow its scan; they are
+        // spelled with the real table name the index lookup expects
+        let left_where: Vec<WhereClause> = clause
+            .where_clauses
+            .iter()
+            .filter_map(|where_clause| {
+                let mut where_clause = where_clause.clone();
+                let Some(column) = Self::where_clause_column_mut(&mut where_clause) else {
+                    return matches!(where_clause, WhereClause::Constant(..))
+                        .then_some(where_clause);
+                };
+                match resolve(column) {
+                    Ok((0, _)) => {
+                        column.table = Some(clause.table[0].clone());
+                        Some(where_clause)
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
 
-        Ok((selected_columns, result_rows))
+        let mut budget = QueryBudget::new(self.statement_timeout, self.running_query.clone());
+        let middle_records: Vec<Record> = self
+            .record_manager
+            .scan(&clause.table[1])?
+            .into_iter()
+            .map(|(_, record)| record)
+            .collect();
+        let right_records: Vec<Record> = self
+            .record_manager
+            .scan(&clause.table[2])?
+            .into_iter()
+            .map(|(_, record)| record)
+            .collect();
+
+        let db_path = self.data_dir.join(&db_name).to_string_lossy().to_string();
+        let left_rows: Box<dyn Iterator<Item = DatabaseResult<Record>>> =
+            match self.index_candidates_for_where(&db_path, &metas[0], &schemas[0], &left_where)? {
+                Some(rids) => {
+                    let mut records = Vec::with_capacity(rids.len());
+                    for rid in rids {
+                        records.push(self.record_manager.get(&clause.table[0], rid)?);
+                    }
+                    Box::new(records.into_iter().map(Ok))
+                }
+                None => Box::new(
+                    self.record_manager
+                        .scan_iter(&clause.table[0])?
+                        .map(|item| Ok(item?.1)),
+                ),
+            };
+
+        let matches = |records: &[&Record], clauses: &[PreparedWhereClause]| {
+            if clauses.is_empty() {
+                return Ok(true);
+            }
+            self.evaluate_prepared_where_with(clauses, &|tc| {
+                let (table, idx) = resolve(tc)?;
+                Ok((
+                    records[table].get(idx).unwrap(),
+                    &schemas[table].columns[idx].data_type,
+                ))
+            })
+        };
+
+        let mut result_rows = Vec::new();
+        let mut order_rows = Vec::new();
+        for left_record in left_rows {
+            let left_record = left_record?;
+            budget.tick()?;
+            if !matches(&[&left_record], &stages[0])? {
+                continue;
+            }
+            for middle_record in &middle_records {
+                budget.tick()?;
+                if !matches(&[&left_record, middle_record], &stages[1])? {
+                    continue;
+                }
+                for right_record in &right_records {
+                    budget.tick()?;
+                    let records = [&left_record, middle_record, right_record];
+                    if !matches(&records, &stages[2])? {
+                        continue;
+                    }
+
+                    let row = col_refs
+                        .iter()
+                        .map(|&((table, idx), cast)| {
+                            self.format_projected_value(records[table].get(idx).unwrap(), cast)
+                        })
+                        .collect();
+                    if order_by.is_empty() {
+                        result_rows.push(row);
+                    } else {
+                        let keys = order_by
+                            .iter()
+                            .map(|&((table, idx), _)| records[table].get(idx).unwrap().clone())
+                            .collect();
+                        order_rows.push((keys, row));
+                    }
+                }
+            }
+        }
+
+        if !order_by.is_empty() {
+            let directions: Vec<bool> = order_by.iter().map(|&(_, asc)| asc).collect();
+            result_rows = self.sort_order_rows(order_rows, &directions)?;
+        }
+        Ok((
+            selected_columns,
+            Self::apply_limit_offset(result_rows, &clause),
+        ))
+    }
+
+    /// Every column a prepared WHERE condition reads
+    fn prepared_clause_columns<'c>(
+        clause: &'c PreparedWhereClause,
+        columns: &mut Vec<&'c TableColumn>,
+    ) {
+        match clause {
+            PreparedWhereClause::Op(col, _, expr)
+            | PreparedWhereClause::CastOp(col, _, _, expr) => {
+                columns.push(col);
+                Self::expression_columns(expr, columns);
+            }
+            PreparedWhereClause::Null(col)
+            | PreparedWhereClause::NotNull(col)
+            | PreparedWhereClause::Like(col, _)
+            | PreparedWhereClause::NotLike(col, _)
+            | PreparedWhereClause::In(col, _) => columns.push(col),
+            PreparedWhereClause::Never => {}
+            PreparedWhereClause::Or(alternatives) => {
+                for clause in alternatives.iter().flatten() {
+                    Self::prepared_clause_columns(clause, columns);
+                }
+            }
+        }
+    }
+
+    fn expression_columns<'e>(expr: &'e Expression, columns: &mut Vec<&'e TableColumn>) {
+        match expr {
+            Expression::Value(_) => {}
+            Expression::Column(tc) => columns.push(tc),
+            Expression::Arithmetic(left, _, right) => {
+                Self::expression_columns(left, columns);
+                Self::expression_columns(right, columns);
+            }
+            Expression::Cast(inner, _) => Self::expression_columns(inner, columns),
+        }
+    }
+
+    /// Sort rows by their ORDER BY keys and drop the keys. `directions`
+    /// holds, for each key, true for ascending.
+    fn sort_order_rows(
+        &self,
+        mut order_rows: Vec<(Vec<RecordValue>, Vec<String>)>,
+        directions: &[bool],
+    ) -> DatabaseResult<Vec<Vec<String>>> {
+        let mut ordering_error = None;
+        order_rows.sort_by(|(left_keys, _), (right_keys, _)| {
+            match self.compare_order_keys(left_keys, right_keys, directions) {
+                Ok(ordering) => ordering,
+                Err(err) => {
+                    if ordering_error.is_none() {
+                        ordering_error = Some(err);
+                    }
+                    Ordering::Equal
+                }
+            }
+        });
+        if let Some(err) = ordering_error {
+            return Err(err);
+        }
+        Ok(order_rows.into_iter().map(|(_, row)| row).collect())
+    }
+
+    /// Compare two rows' ORDER BY keys, the first key deciding unless it
+    /// ties, and each key ascending or descending as `directions` says
+    fn compare_order_keys(
+        &self,
+        left: &[RecordValue],
+        right: &[RecordValue],
+        directions: &[bool],
+    ) -> DatabaseResult<Ordering> {
+        for ((left, right), &asc) in left.iter().zip(right).zip(directions) {
+            let ordering = self.compare_order_values(left, right)?;
+            if ordering != Ordering::Equal {
+                return Ok(if asc { ordering } else { ordering.reverse() });
+            }
+        }
+        Ok(Ordering::Equal)
+    }
+
+    /// Find a `left.col = right.col` condition whose right column has a
+    /// single-column index, so the join can probe it instead of scanning
+    #[allow(clippy::too_many_arguments)]
+    fn join_index_probe(
+        &mut self,
+        db_path: &str,
+        where_clauses: &[WhereClause],
+        left_ref: &str,
+        left_schema: &TableSchema,
+        right_ref: &str,
+        right_meta: &TableMetadata,
+        right_schema: &TableSchema,
+    ) -> DatabaseResult<Option<JoinProbe>> {
+        if !self.use_indexes {
+            return Ok(None);
+        }
+
+        for clause in where_clauses {
+            let WhereClause::Op(col_a, Operator::Eq, Expression::Column(col_b)) = clause else {
+                continue;
+            };
+            // Unresolvable columns are reported when the condition is evaluated
+            let (Ok(a), Ok(b)) = (
+                self.resolve_join_column_ref(col_a, left_ref, left_schema, right_ref, right_schema),
+                self.resolve_join_column_ref(col_b, left_ref, left_schema, right_ref, right_schema),
+            ) else {
+                continue;
+            };
+            let (left_index, right_index) = match (a.side, b.side) {
+                (JoinSide::Left, JoinSide::Right) => (a.index, b.index),
+                (JoinSide::Right, JoinSide::Left) => (b.index, a.index),
+                _ => continue,
+            };
+
+            let right_column = &right_schema.columns[right_index];
+            if left_schema.columns[left_index].data_type != DataType::Int
+                || right_column.data_type != DataType::Int
+                || !Self::has_single_column_index(right_meta, &right_column.name)
+            {
+                continue;
+            }
+
+            let columns = [right_column.name.clone()];
+            if self.ensure_index_open_for_columns(db_path, right_meta, right_schema, &columns)? {
+                return Ok(Some(JoinProbe {
+                    left_index,
+                    storage_name: right_column.name.clone(),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Find a `left.col = right.col` condition whose two columns can be
+    /// hashed, returning the left and right column indexes
+    ///
+    /// Both columns must have the same type with an exact equality, so equal
+    /// values always share a `JoinKey`; other conditions still run on every
+    /// pair the hash lookup finds.
+    fn hash_join_columns(
+        &self,
+        where_clauses: &[PreparedWhereClause],
+        left_ref: &str,
+        left_schema: &TableSchema,
+        right_ref: &str,
+        right_schema: &TableSchema,
+    ) -> Option<(usize, usize)> {
+        where_clauses.iter().find_map(|clause| {
+            let PreparedWhereClause::Op(col_a, Operator::Eq, Expression::Column(col_b)) = clause
+            else {
+                return None;
+            };
+            let a = self
+                .resolve_join_column_ref(col_a, left_ref, left_schema, right_ref, right_schema)
+                .ok()?;
+            let b = self
+                .resolve_join_column_ref(col_b, left_ref, left_schema, right_ref, right_schema)
+                .ok()?;
+            let (left_index, right_index) = match (a.side, b.side) {
+                (JoinSide::Left, JoinSide::Right) => (a.index, b.index),
+                (JoinSide::Right, JoinSide::Left) => (b.index, a.index),
+                _ => return None,
+            };
+            let hashable = match (
+                &left_schema.columns[left_index].data_type,
+                &right_schema.columns[right_index].data_type,
+            ) {
+                (DataType::Int, DataType::Int)
+                | (
+                    DataType::Char(_) | DataType::Varchar(_),
+                    DataType::Char(_) | DataType::Varchar(_),
+                )
+                | (DataType::Bool, DataType::Bool) => true,
+                (DataType::Decimal(_, left_scale), DataType::Decimal(_, right_scale)) => {
+                    left_scale == right_scale
+                }
+                _ => false,
+            };
+            hashable.then_some((left_index, right_index))
+        })
+    }
+
+    /// Right-table rows whose indexed join column equals the left row's value
+    fn probe_join_index(
+        &mut self,
+        right_name: &str,
+        probe: &JoinProbe,
+        left_record: &Record,
+    ) -> DatabaseResult<Vec<Record>> {
+        let key = match left_record.get(probe.left_index) {
+            Some(RecordValue::Int(value)) => *value as i64,
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut records = Vec::new();
+        for rid in self
+            .index_manager
+            .search_all(right_name, &probe.storage_name, key)
+        {
+            records.push(self.record_manager.get(right_name, rid)?);
+        }
+        Ok(records)
     }
 
     fn select_has_aggregate(&self, selectors: &Selectors) -> bool {
@@ -1537,16 +3865,25 @@ impl DatabaseManager {
             Selectors::All => false,
             Selectors::List(list) => list
                 .iter()
-                .any(|selector| !matches!(selector, Selector::Column(_))),
+                .any(|selector| !matches!(selector, Selector::Column(_) | Selector::Cast(..))),
         }
     }
 
+    /// Whether a SELECT computes one row per group rather than one per record
+    fn is_aggregate_query(&self, clause: &SelectClause) -> bool {
+        self.select_has_aggregate(&clause.selectors)
+            || !clause.group_by.is_empty()
+            || !clause.having.is_empty()
+    }
+
     fn select_single_table_aggregate(
         &mut self,
         clause: &SelectClause,
         schema: &TableSchema,
-        table_name: &str,
-    ) -> DatabaseResult<(Vec<String>, Vec<Vec<String>>)> {
+        table_meta: &TableMetadata,
+        db_path: &str,
+    ) -> DatabaseResult<(Vec<ResultColumn>, Vec<Vec<String>>)> {
+        let table_name = &table_meta.name;
         let selectors = match &clause.selectors {
             Selectors::All => {
                 return Err(DatabaseError::TypeMismatch(
@@ -1556,10 +3893,12 @@ impl DatabaseManager {
             Selectors::List(list) => list,
         };
 
-        let group_by_idx = match &clause.group_by {
-            Some(tc) => Some(self.resolve_single_column_index(schema, tc)?),
-            None => None,
-        };
+        let group_by_indices = clause
+            .group_by
+            .iter()
+            .map(|tc| self.resolve_single_column_index(schema, tc))
+            .collect::<DatabaseResult<Vec<usize>>>()?;
+        let grouped = !group_by_indices.is_empty();
 
         let mut headers = Vec::new();
         let mut output_selectors = Vec::new();
@@ -1569,133 +3908,485 @@ impl DatabaseManager {
             match selector {
                 Selector::Column(tc) => {
                     let col_idx = self.resolve_single_column_index(schema, tc)?;
-                    match group_by_idx {
-                        Some(group_idx) if group_idx == col_idx => {
-                            headers.push(tc.column.clone());
-                            output_selectors.push(OutputSelector::GroupKey);
-                        }
-                        Some(_) => {
-                            return Err(DatabaseError::TypeMismatch(
-                                "Non-aggregate column must match GROUP BY".to_string(),
-                            ));
-                        }
-                        None => {
-                            return Err(DatabaseError::TypeMismatch(
-                                "Non-aggregate column requires GROUP BY".to_string(),
-                            ));
-                        }
+                    if !grouped {
+                        return Err(DatabaseError::TypeMismatch(
+                            "Non-aggregate column requires GROUP BY".to_string(),
+                        ));
                     }
+                    let key_pos = group_by_indices
+                        .iter()
+                        .position(|&group_idx| group_idx == col_idx)
+                        .ok_or_else(|| {
+                            DatabaseError::TypeMismatch(
+                                "Non-aggregate column must match GROUP BY".to_string(),
+                            )
+                        })?;
+                    headers.push(ResultColumn::from_column(
+                        tc.column.clone(),
+                        &schema.columns[col_idx],
+                    ));
+                    output_selectors.push(OutputSelector::GroupKey(key_pos));
+                }
+                Selector::Cast(..) => {
+                    return Err(DatabaseError::TypeMismatch(
+                        "CAST is not supported in aggregate queries".to_string(),
+                    ));
                 }
                 Selector::CountAll => {
-                    headers.push("COUNT(*)".to_string());
+                    headers.push(ResultColumn {
+                        name: "COUNT(*)".to_string(),
+                        data_type: DataType::Int,
+                        nullable: false,
+                    });
                     agg_specs.push(AggSpec::CountAll);
                     output_selectors.push(OutputSelector::Agg(agg_specs.len() - 1));
                 }
                 Selector::Count(tc) => {
                     let col_idx = self.resolve_single_column_index(schema, tc)?;
-                    headers.push(format!("COUNT({})", self.format_table_column_name(tc)));
+                    headers.push(ResultColumn {
+                        name: format!("COUNT({})", self.format_table_column_name(tc)),
+                        data_type: DataType::Int,
+                        nullable: false,
+                    });
                     agg_specs.push(AggSpec::Count { col_idx });
                     output_selectors.push(OutputSelector::Agg(agg_specs.len() - 1));
                 }
                 Selector::Average(tc) => {
                     let col_idx = self.resolve_single_column_index(schema, tc)?;
-                    self.ensure_numeric_column(schema, col_idx)?;
-                    headers.push(format!("AVG({})", self.format_table_column_name(tc)));
-                    agg_specs.push(AggSpec::Avg { col_idx });
+                    let numeric = self.numeric_type_for_column(schema, col_idx)?;
+                    headers.push(ResultColumn {
+                        name: format!("AVG({})", self.format_table_column_name(tc)),
+                        data_type: DataType::Float,
+                        nullable: true,
+                    });
+                    agg_specs.push(AggSpec::Avg { col_idx, numeric });
                     output_selectors.push(OutputSelector::Agg(agg_specs.len() - 1));
                 }
                 Selector::Max(tc) => {
                     let col_idx = self.resolve_single_column_index(schema, tc)?;
-                    headers.push(format!("MAX({})", self.format_table_column_name(tc)));
+                    headers.push(ResultColumn {
+                        name: format!("MAX({})", self.format_table_column_name(tc)),
+                        data_type: schema.columns[col_idx].data_type.clone(),
+                        nullable: true,
+                    });
                     agg_specs.push(AggSpec::Max { col_idx });
                     output_selectors.push(OutputSelector::Agg(agg_specs.len() - 1));
                 }
                 Selector::Min(tc) => {
                     let col_idx = self.resolve_single_column_index(schema, tc)?;
-                    headers.push(format!("MIN({})", self.format_table_column_name(tc)));
+                    headers.push(ResultColumn {
+                        name: format!("MIN({})", self.format_table_column_name(tc)),
+                        data_type: schema.columns[col_idx].data_type.clone(),
+                        nullable: true,
+                    });
                     agg_specs.push(AggSpec::Min { col_idx });
                     output_selectors.push(OutputSelector::Agg(agg_specs.len() - 1));
                 }
                 Selector::Sum(tc) => {
                     let col_idx = self.resolve_single_column_index(schema, tc)?;
                     let numeric = self.numeric_type_for_column(schema, col_idx)?;
-                    headers.push(format!("SUM({})", self.format_table_column_name(tc)));
+                    headers.push(ResultColumn {
+                        name: format!("SUM({})", self.format_table_column_name(tc)),
+                        data_type: match numeric {
+                            NumericType::Int => DataType::Int,
+                            NumericType::Float => DataType::Float,
+                            NumericType::Decimal(scale) => {
+                                DataType::Decimal(MAX_DECIMAL_PRECISION, scale)
+                            }
+                        },
+                        nullable: true,
+                    });
                     agg_specs.push(AggSpec::Sum { col_idx, numeric });
                     output_selectors.push(OutputSelector::Agg(agg_specs.len() - 1));
                 }
             }
         }
 
-        let scan_iter = self.record_manager.scan_iter(table_name)?;
-        let prepared_where = if clause.where_clauses.is_empty() {
-            None
-        } else {
-            Some(self.prepare_where_clauses(&clause.where_clauses)?)
-        };
-
-        let mut group_states = Vec::new();
-        let mut group_index: HashMap<GroupKey, usize> = HashMap::new();
-        let mut agg_state = if group_by_idx.is_none() {
-            Some(self.init_agg_states(&agg_specs))
-        } else {
-            None
-        };
+        // HAVING may name aggregates that are not in the select list; they are
+        // computed alongside the output ones and never printed
+        let mut having = Vec::with_capacity(clause.having.len());
+        for (term, op, value) in &clause.having {
+            let spec = self.having_agg_spec(schema, term)?;
+            let literal = self.having_literal(schema, &spec, value)?;
+            let mut spec_idx = None;
+            for (pos, selector) in selectors.iter().enumerate() {
+                if let OutputSelector::Agg(idx) = output_selectors[pos]
+                    && self.same_aggregate(schema, term, selector)?
+                {
+                    spec_idx = Some(idx);
+                    break;
+                }
+            }
+            let spec_idx = spec_idx.unwrap_or_else(|| {
+                agg_specs.push(spec);
+                agg_specs.len() - 1
+            });
+            having.push((spec_idx, op.clone(), literal));
+        }
+
+        // ORDER BY names an output column: a GROUP BY column or one of the
+        // aggregates in the select list
+        let mut order_by = Vec::with_capacity(clause.order_by.len());
+        for (term, asc) in &clause.order_by {
+            if let Selector::Column(tc) = term {
+                let col_idx = self.resolve_single_column_index(schema, tc)?;
+                let key_pos = group_by_indices
+                    .iter()
+                    .position(|&group_idx| group_idx == col_idx)
+                    .ok_or_else(|| {
+                        DatabaseError::TypeMismatch(
+                            "ORDER BY column must match GROUP BY".to_string(),
+                        )
+                    })?;
+                order_by.push((OutputSelector::GroupKey(key_pos), *asc));
+            } else {
+                let mut position = None;
+                for (pos, selector) in selectors.iter().enumerate() {
+                    if self.same_aggregate(schema, term, selector)? {
+                        position = Some(pos);
+                        break;
+                    }
+                }
+                let position = position.ok_or_else(|| {
+                    DatabaseError::TypeMismatch(
+                        "ORDER BY aggregate must appear in the select list".to_string(),
+                    )
+                })?;
+                order_by.push((output_selectors[position], *asc));
+            }
+        }
+        // Groups otherwise tied, or not ordered at all, come out by ascending group key
+        for key_pos in 0..group_by_indices.len() {
+            if !order_by
+                .iter()
+                .any(|&(selector, _)| matches!(selector, OutputSelector::GroupKey(pos) if pos == key_pos))
+            {
+                order_by.push((OutputSelector::GroupKey(key_pos), true));
+            }
+        }
+
+        // A bare MIN/MAX over indexed columns is read off the ends of the index
+        if !grouped
+            && clause.where_clauses.is_empty()
+            && let Some(state) =
+                self.min_max_from_indexes(db_path, table_meta, schema, &agg_specs)?
+        {
+            let mut rows = Vec::new();
+            if self.having_holds(&state, &having) {
+                rows.push(self.build_aggregate_row(None, &output_selectors, &agg_specs, &state)?);
+            }
+            let rows = Self::apply_limit_offset(rows, clause);
+            return Ok((headers, rows));
+        }
+
+        let mut budget = QueryBudget::new(self.statement_timeout, self.running_query.clone());
+        // A false constant predicate leaves nothing to scan
+        let scan_limit = if self.where_never_matches(&clause.where_clauses)? {
+            0
+        } else {
+            usize::MAX
+        };
+        let scan_iter = self.record_manager.scan_iter(table_name)?.take(scan_limit);
+        let prepared_where = if clause.where_clauses.is_empty() {
+            None
+        } else {
+            Some(self.prepare_where_clauses(&clause.where_clauses)?)
+        };
+
+        let mut group_states = Vec::new();
+        let mut group_index: HashMap<Vec<GroupKey>, usize> = HashMap::new();
+        let mut agg_state = if !grouped {
+            Some(self.init_agg_states(&agg_specs))
+        } else {
+            None
+        };
+
+        for item in scan_iter {
+            let (_rid, record) = item?;
+            budget.tick()?;
+            let matches = match &prepared_where {
+                None => true,
+                Some(clauses) => self.evaluate_prepared_where(&record, schema, clauses)?,
+            };
+
+            if !matches {
+                continue;
+            }
+
+            if grouped {
+                let values = group_by_indices
+                    .iter()
+                    .map(|&group_idx| {
+                        record.get(group_idx).cloned().ok_or_else(|| {
+                            DatabaseError::TypeMismatch("Invalid GROUP BY column".to_string())
+                        })
+                    })
+                    .collect::<DatabaseResult<Vec<RecordValue>>>()?;
+                let key: Vec<GroupKey> = values
+                    .iter()
+                    .map(|value| self.group_key_from_value(value))
+                    .collect();
+                let entry_idx = match group_index.get(&key) {
+                    Some(idx) => *idx,
+                    None => {
+                        let idx = group_states.len();
+                        group_states.push(GroupState {
+                            key: values,
+                            aggs: self.init_agg_states(&agg_specs),
+                        });
+                        group_index.insert(key, idx);
+                        idx
+                    }
+                };
+                let state = &mut group_states[entry_idx].aggs;
+                self.update_agg_states(state, &agg_specs, &record)?;
+            } else if let Some(state) = agg_state.as_mut() {
+                self.update_agg_states(state, &agg_specs, &record)?;
+            }
+        }
+
+        group_states.retain(|group| self.having_holds(&group.aggs, &having));
+
+        if !order_by.is_empty() {
+            let sort_keys = |group: &GroupState| -> Vec<RecordValue> {
+                order_by
+                    .iter()
+                    .map(|&(order_selector, _)| match order_selector {
+                        OutputSelector::GroupKey(pos) => group.key[pos].clone(),
+                        OutputSelector::Agg(idx) => self.aggregate_sort_value(&group.aggs[idx]),
+                    })
+                    .collect()
+            };
+            let directions: Vec<bool> = order_by.iter().map(|&(_, asc)| asc).collect();
+            let mut ordering_error = None;
+            group_states.sort_by(|left, right| {
+                match self.compare_order_keys(&sort_keys(left), &sort_keys(right), &directions) {
+                    Ok(ordering) => ordering,
+                    Err(err) => {
+                        if ordering_error.is_none() {
+                            ordering_error = Some(err);
+                        }
+                        Ordering::Equal
+                    }
+                }
+            });
+            if let Some(err) = ordering_error {
+                return Err(err);
+            }
+        }
+
+        let mut rows = Vec::new();
+        if let Some(state) = agg_state {
+            if self.having_holds(&state, &having) {
+                rows.push(self.build_aggregate_row(None, &output_selectors, &agg_specs, &state)?);
+            }
+        } else {
+            for group in &group_states {
+                rows.push(self.build_aggregate_row(
+                    Some(&group.key),
+                    &output_selectors,
+                    &agg_specs,
+                    &group.aggs,
+                )?);
+            }
+        }
+
+        let rows = Self::apply_limit_offset(rows, clause);
+
+        Ok((headers, rows))
+    }
+
+    /// Aggregate states for a query made up only of `MIN`/`MAX` over INT
+    /// columns with a single-column index, or `None` if any of them needs a
+    /// table scan
+    fn min_max_from_indexes(
+        &mut self,
+        db_path: &str,
+        table_meta: &TableMetadata,
+        schema: &TableSchema,
+        agg_specs: &[AggSpec],
+    ) -> DatabaseResult<Option<Vec<AggState>>> {
+        if !self.use_indexes || agg_specs.is_empty() {
+            return Ok(None);
+        }
+
+        let mut targets = Vec::with_capacity(agg_specs.len());
+        for spec in agg_specs {
+            let (col_idx, is_min) = match spec {
+                AggSpec::Min { col_idx } => (*col_idx, true),
+                AggSpec::Max { col_idx } => (*col_idx, false),
+                _ => return Ok(None),
+            };
+            let column = &schema.columns[col_idx];
+            if column.data_type != DataType::Int
+                || !Self::has_single_column_index(table_meta, &column.name)
+            {
+                return Ok(None);
+            }
+            targets.push((column.name.clone(), is_min));
+        }
+
+        let mut states = Vec::with_capacity(targets.len());
+        for (column, is_min) in targets {
+            if !self.ensure_index_open_for_columns(
+                db_path,
+                table_meta,
+                schema,
+                std::slice::from_ref(&column),
+            )? {
+                return Ok(None);
+            }
+            let key = if is_min {
+                self.index_manager.min_key(&table_meta.name, &column)
+            } else {
+                self.index_manager.max_key(&table_meta.name, &column)
+            };
+            let value = key.map(|key| RecordValue::Int(key as i32));
+            states.push(if is_min {
+                AggState::Min(value)
+            } else {
+                AggState::Max(value)
+            });
+        }
+
+        Ok(Some(states))
+    }
+
+    /// Name each FROM entry is referred to by in the rest of the query: its
+    /// alias if it has one, otherwise the table name itself
+    fn table_refs(clause: &SelectClause) -> DatabaseResult<Vec<String>> {
+        let mut refs: Vec<String> = Vec::with_capacity(clause.table.len());
+        for (i, table) in clause.table.iter().enumerate() {
+            let table_ref = clause
+                .table_aliases
+                .get(i)
+                .cloned()
+                .flatten()
+                .unwrap_or_else(|| table.clone());
+            if refs.contains(&table_ref) {
+                return Err(DatabaseError::TypeMismatch(format!(
+                    "Not unique table/alias: {}",
+                    table_ref
+                )));
+            }
+            refs.push(table_ref);
+        }
+        Ok(refs)
+    }
+
+    /// Rewrite every column qualified with `from` to be qualified with `to`
+    fn rename_table_qualifier(clause: &mut SelectClause, from: &str, to: &str) {
+        if from == to {
+            return;
+        }
+
+        let mut rename = |column: &mut TableColumn| {
+            if column.table.as_deref() == Some(from) {
+                column.table = Some(to.to_string());
+            }
+        };
+
+        if let Selectors::List(selectors) = &mut clause.selectors {
+            for selector in selectors {
+                if let Some(tc) = Self::selector_column_mut(selector) {
+                    rename(tc);
+                }
+            }
+        }
+        Self::rename_where_qualifier(&mut clause.where_clauses, &mut rename);
+        for tc in &mut clause.group_by {
+            rename(tc);
+        }
+        for (selector, _, _) in &mut clause.having {
+            if let Some(tc) = Self::selector_column_mut(selector) {
+                rename(tc);
+            }
+        }
+        for (selector, _) in &mut clause.order_by {
+            if let Some(tc) = Self::selector_column_mut(selector) {
+                rename(tc);
+            }
+        }
+    }
+
+    fn selector_column_mut(selector: &mut Selector) -> Option<&mut TableColumn> {
+        match selector {
+            Selector::Column(tc)
+            | Selector::Count(tc)
+            | Selector::Average(tc)
+            | Selector::Max(tc)
+            | Selector::Min(tc)
+            | Selector::Sum(tc)
+            | Selector::Cast(tc, _) => Some(tc),
+            Selector::CountAll => None,
+        }
+    }
 
-        for item in scan_iter {
-            let (_rid, record) = item?;
-            let matches = match &prepared_where {
-                None => true,
-                Some(clauses) => self.evaluate_prepared_where(&record, schema, clauses)?,
-            };
+    /// The columns a non-aggregate query is ordered by, with true for ASC
+    fn order_by_columns(clause: &SelectClause) -> DatabaseResult<Vec<(&TableColumn, bool)>> {
+        clause
+            .order_by
+            .iter()
+            .map(|(selector, asc)| match selector {
+                Selector::Column(tc) => Ok((tc, *asc)),
+                Selector::Cast(..) => Err(DatabaseError::TypeMismatch(
+                    "ORDER BY does not support CAST".to_string(),
+                )),
+                _ => Err(DatabaseError::TypeMismatch(
+                    "ORDER BY an aggregate requires an aggregate query".to_string(),
+                )),
+            })
+            .collect()
+    }
 
-            if !matches {
-                continue;
-            }
+    fn rename_expression_qualifier(
+        expr: &mut Expression,
+        rename: &mut dyn FnMut(&mut TableColumn),
+    ) {
+        match expr {
+            Expression::Value(_) => {}
+            Expression::Column(tc) => rename(tc),
+            Expression::Arithmetic(left, _, right) => {
+                Self::rename_expression_qualifier(left, rename);
+                Self::rename_expression_qualifier(right, rename);
+            }
+            Expression::Cast(inner, _) => Self::rename_expression_qualifier(inner, rename),
+        }
+    }
 
-            if let Some(group_idx) = group_by_idx {
-                let value = record
-                    .get(group_idx)
-                    .ok_or_else(|| {
-                        DatabaseError::TypeMismatch("Invalid GROUP BY column".to_string())
-                    })?
-                    .clone();
-                let key = self.group_key_from_value(&value);
-                let entry_idx = match group_index.get(&key) {
-                    Some(idx) => *idx,
-                    None => {
-                        let idx = group_states.len();
-                        group_states.push(GroupState {
-                            key: value,
-                            aggs: self.init_agg_states(&agg_specs),
-                        });
-                        group_index.insert(key, idx);
-                        idx
+    fn rename_where_qualifier(
+        where_clauses: &mut [WhereClause],
+        rename: &mut dyn FnMut(&mut TableColumn),
+    ) {
+        for where_clause in where_clauses {
+            match where_clause {
+                WhereClause::Op(_, _, expr) | WhereClause::CastOp(_, _, _, expr) => {
+                    Self::rename_expression_qualifier(expr, rename);
+                }
+                WhereClause::Or(alternatives) => {
+                    for alternative in alternatives {
+                        Self::rename_where_qualifier(alternative, rename);
                     }
-                };
-                let state = &mut group_states[entry_idx].aggs;
-                self.update_agg_states(state, &agg_specs, &record)?;
-            } else if let Some(state) = agg_state.as_mut() {
-                self.update_agg_states(state, &agg_specs, &record)?;
+                }
+                _ => {}
             }
-        }
-
-        let mut rows = Vec::new();
-        if let Some(state) = agg_state {
-            rows.push(self.build_aggregate_row(None, &output_selectors, &agg_specs, &state)?);
-        } else {
-            for group in &group_states {
-                rows.push(self.build_aggregate_row(
-                    Some(&group.key),
-                    &output_selectors,
-                    &agg_specs,
-                    &group.aggs,
-                )?);
+            if let Some(tc) = Self::where_clause_column_mut(where_clause) {
+                rename(tc);
             }
         }
+    }
 
-        let rows = self.apply_limit_offset(rows, clause.limit, clause.offset);
-
-        Ok((headers, rows))
+    fn where_clause_column_mut(where_clause: &mut WhereClause) -> Option<&mut TableColumn> {
+        match where_clause {
+            WhereClause::Op(tc, _, _)
+            | WhereClause::CastOp(tc, ..)
+            | WhereClause::Null(tc)
+            | WhereClause::NotNull(tc)
+            | WhereClause::In(tc, _)
+            | WhereClause::Like(tc, ..)
+            | WhereClause::NotLike(tc, ..) => Some(tc),
+            WhereClause::Constant(..) | WhereClause::Or(_) => None,
+        }
     }
 
     fn format_table_column_name(&self, column: &TableColumn) -> String {
@@ -1726,11 +4417,40 @@ impl DatabaseManager {
         }
     }
 
+    /// B+ tree order of the index on exactly `columns`, falling back to the
+    /// default when none was requested.
+    fn index_order(table_meta: &TableMetadata, columns: &[String]) -> usize {
+        table_meta
+            .indexes
+            .iter()
+            .find(|idx| idx.columns == columns)
+            .and_then(|idx| idx.order)
+            .unwrap_or(DEFAULT_ORDER)
+    }
+
+    /// Whether `file_name` follows the `<table>_<storage name>.idx` naming of
+    /// an index on `table_meta`
+    fn is_index_file_of(file_name: &str, table_meta: &TableMetadata) -> bool {
+        let Some(storage_name) = file_name
+            .strip_suffix(".idx")
+            .and_then(|stem| stem.strip_prefix(table_meta.name.as_str()))
+            .and_then(|rest| rest.strip_prefix('_'))
+        else {
+            return false;
+        };
+        let has_column = |column: &str| table_meta.columns.iter().any(|c| c.name == column);
+        has_column(storage_name)
+            || storage_name.match_indices("__").any(|(pos, _)| {
+                has_column(&storage_name[..pos]) && has_column(&storage_name[pos + 2..])
+            })
+    }
+
     fn has_single_column_index(table_meta: &TableMetadata, column: &str) -> bool {
-        if let Some(pk_cols) = &table_meta.primary_key {
-            if pk_cols.len() == 1 && pk_cols[0] == column {
-                return true;
-            }
+        if let Some(pk_cols) = &table_meta.primary_key
+            && pk_cols.len() == 1
+            && pk_cols[0] == column
+        {
+            return true;
         }
         table_meta
             .indexes
@@ -1738,6 +4458,21 @@ impl DatabaseManager {
             .any(|idx| idx.columns.len() == 1 && idx.columns[0] == column)
     }
 
+    /// Whether an index can be built over these columns: one or two INT
+    /// columns, or a single VARCHAR column (see [`index_value_key`])
+    fn indexable(table_meta: &TableMetadata, indices: &[usize]) -> bool {
+        let types: Vec<DataType> = indices
+            .iter()
+            .map(|&idx| table_meta.columns[idx].to_data_type())
+            .collect();
+        matches!(
+            types.as_slice(),
+            [DataType::Int]
+                | [DataType::Char(_) | DataType::Varchar(_)]
+                | [DataType::Int, DataType::Int]
+        )
+    }
+
     fn build_index_defs(&self, table_meta: &TableMetadata) -> DatabaseResult<Vec<IndexDef>> {
         let mut defs = Vec::new();
         let mut seen = HashSet::new();
@@ -1759,34 +4494,25 @@ impl DatabaseManager {
                 continue;
             }
             let mut indices = Vec::with_capacity(columns.len());
-            let mut valid = true;
             for col_name in &columns {
-                let idx = match table_meta
-                    .columns
-                    .iter()
-                    .position(|c| &c.name == col_name)
-                {
+                let idx = match table_meta.columns.iter().position(|c| &c.name == col_name) {
                     Some(idx) => idx,
                     None => {
                         return Err(DatabaseError::ColumnNotFound(
                             col_name.clone(),
                             table_meta.name.clone(),
-                        ))
+                        ));
                     }
                 };
-                if table_meta.columns[idx].to_data_type() != DataType::Int {
-                    valid = false;
-                    break;
-                }
                 indices.push(idx);
             }
-            if !valid {
+            if !Self::indexable(table_meta, &indices) {
                 continue;
             }
             defs.push(IndexDef {
-                columns,
                 indices,
                 storage_name,
+                order: Self::index_order(table_meta, &columns),
             });
         }
 
@@ -1805,7 +4531,7 @@ impl DatabaseManager {
             None => {
                 return Err(DatabaseError::TypeMismatch(
                     "Composite index requires exactly two columns".to_string(),
-                ))
+                ));
             }
         };
         if columns.len() != 1 && columns.len() != 2 {
@@ -1823,13 +4549,13 @@ impl DatabaseManager {
                 .ok_or_else(|| {
                     DatabaseError::ColumnNotFound(col_name.clone(), table_meta.name.clone())
                 })?;
-            if table_meta.columns[col_idx].to_data_type() != DataType::Int {
-                return Err(DatabaseError::TypeMismatch(
-                    "Only INT columns can be indexed".to_string(),
-                ));
-            }
             col_indices.push(col_idx);
         }
+        if !Self::indexable(table_meta, &col_indices) {
+            return Err(DatabaseError::TypeMismatch(
+                "Only INT columns or a single VARCHAR column can be indexed".to_string(),
+            ));
+        }
 
         let table_path = PathBuf::from(db_path).join(format!("{}.tbl", table_meta.name));
         let _ = self
@@ -1840,22 +4566,25 @@ impl DatabaseManager {
             .index_manager
             .drop_index(db_path, &table_meta.name, &storage_name);
 
+        let order = Self::index_order(table_meta, columns);
         let scan_iter = self.record_manager.scan_iter(&table_meta.name)?;
         if col_indices.len() == 1 {
-            let table_iter = TableIntColumnIter::new(scan_iter, col_indices[0]);
-            self.index_manager.create_index_from_table(
+            let table_iter = TableColumnKeyIter::new(scan_iter, col_indices[0]);
+            self.index_manager.create_index_from_table_with_order(
                 db_path,
                 &table_meta.name,
                 &storage_name,
+                order,
                 table_iter,
             )?;
         } else {
             let table_iter =
                 TableCompositeIntColumnIter::new(scan_iter, col_indices[0], col_indices[1]);
-            self.index_manager.create_index_from_table(
+            self.index_manager.create_index_from_table_with_order(
                 db_path,
                 &table_meta.name,
                 &storage_name,
+                order,
                 table_iter,
             )?;
         }
@@ -1870,6 +4599,10 @@ impl DatabaseManager {
         schema: &TableSchema,
         columns: &[String],
     ) -> DatabaseResult<bool> {
+        // An attached table's indexes live in its own database
+        if table_meta.name.contains('.') {
+            return Ok(false);
+        }
         let storage_name = match Self::index_storage_name(columns) {
             Some(name) => name,
             None => return Ok(false),
@@ -1890,6 +4623,8 @@ impl DatabaseManager {
         }
     }
 
+    /// Row ids `where_clauses` can be narrowed to through an index, sorted
+    /// by position, or `None` if every row has to be scanned
     fn index_candidates_for_where(
         &mut self,
         db_path: &str,
@@ -1897,28 +4632,90 @@ impl DatabaseManager {
         schema: &TableSchema,
         where_clauses: &[WhereClause],
     ) -> DatabaseResult<Option<Vec<RecordId>>> {
+        let (storage_name, ranges) =
+            match self.plan_index_access(db_path, table_meta, schema, where_clauses)? {
+                AccessPath::NoRows => return Ok(Some(Vec::new())),
+                AccessPath::FullScan => return Ok(None),
+                AccessPath::IndexScan {
+                    storage_name,
+                    ranges,
+                    ..
+                } => (storage_name, ranges),
+            };
+        let table_name = &table_meta.name;
+        let mut rids = Vec::new();
+        for (lower, upper) in ranges {
+            if lower == upper {
+                rids.extend(
+                    self.index_manager
+                        .search_all(table_name, &storage_name, lower),
+                );
+            } else {
+                rids.extend(
+                    self.index_manager
+                        .range_iter(table_name, &storage_name, lower, upper)
+                        .map(|(_key, rid)| rid),
+                );
+            }
+        }
+        rids.sort_by_key(|rid| (rid.page_id, rid.slot_id));
+        Ok(Some(rids))
+    }
+
+    /// Choose how `where_clauses` reaches the rows of the table, opening (or
+    /// rebuilding) the index it picks
+    fn plan_index_access(
+        &mut self,
+        db_path: &str,
+        table_meta: &TableMetadata,
+        schema: &TableSchema,
+        where_clauses: &[WhereClause],
+    ) -> DatabaseResult<AccessPath> {
+        // A false constant predicate matches nothing, so there is nothing to read
+        if self.where_never_matches(where_clauses)? {
+            return Ok(AccessPath::NoRows);
+        }
         if !self.use_indexes || where_clauses.is_empty() {
-            return Ok(None);
+            return Ok(AccessPath::FullScan);
         }
 
+        // Only the top-level AND list narrows the lookup. An OR is skipped
+        // here and left to the row-by-row check, so a WHERE that is nothing
+        // but an OR falls back to a full scan.
+        //
+        // Equality values are collected by column name, so the composite lookup
+        // below does not depend on the order the clauses were written in.
         let table_name = &table_meta.name;
         let mut eq_values: HashMap<String, i64> = HashMap::new();
+        let mut eq_conflicts: HashSet<String> = HashSet::new();
+        // Against a non-NULL literal, <=> is plain equality and can use an index
         for clause in where_clauses {
-            if let WhereClause::Op(col, Operator::Eq, Expression::Value(ParserValue::Integer(value))) =
-                clause
+            if let WhereClause::Op(
+                col,
+                Operator::Eq | Operator::NullSafeEq,
+                Expression::Value(ParserValue::Integer(value)),
+            ) = clause
             {
                 if !self.table_column_matches(table_name, col) {
                     continue;
                 }
-                eq_values.insert(col.column.clone(), *value);
+                if let Some(existing) = eq_values.insert(col.column.clone(), *value)
+                    && existing != *value
+                    && schema
+                        .columns
+                        .iter()
+                        .any(|c| c.name == col.column && c.data_type == DataType::Int)
+                {
+                    eq_conflicts.insert(col.column.clone());
+                }
             }
         }
 
         let mut composite_defs = Vec::new();
-        if let Some(pk_cols) = &table_meta.primary_key {
-            if pk_cols.len() == 2 {
-                composite_defs.push(pk_cols.clone());
-            }
+        if let Some(pk_cols) = &table_meta.primary_key
+            && pk_cols.len() == 2
+        {
+            composite_defs.push(pk_cols.clone());
         }
         for index_meta in &table_meta.indexes {
             if index_meta.columns.len() == 2 {
@@ -1927,6 +4724,9 @@ impl DatabaseManager {
         }
 
         for columns in &composite_defs {
+            if columns.iter().any(|col| eq_conflicts.contains(col)) {
+                return Ok(AccessPath::NoRows);
+            }
             let left_val = match eq_values.get(&columns[0]) {
                 Some(val) => *val,
                 None => continue,
@@ -1943,28 +4743,34 @@ impl DatabaseManager {
                 None => continue,
             };
             let key = Self::composite_key_from_i64(left_val, right_val);
-            let mut rids = self
-                .index_manager
-                .search_all(table_name, &storage_name, key);
-            rids.sort_by_key(|rid| (rid.page_id, rid.slot_id));
-            return Ok(Some(rids));
+            return Ok(AccessPath::IndexScan {
+                storage_name,
+                composite: true,
+                ranges: vec![(key, key)],
+            });
         }
 
         for columns in &composite_defs {
+            // A row with a NULL second column still matches a range on the first,
+            // but has no index entry, so the prefix range needs the second column NOT NULL
+            let right_not_null = table_meta
+                .columns
+                .iter()
+                .any(|c| c.name == columns[1] && c.not_null);
+            if !right_not_null {
+                continue;
+            }
             let mut lower = None;
             let mut upper = None;
             for clause in where_clauses {
-                if let WhereClause::Op(
-                    col,
-                    op,
-                    Expression::Value(ParserValue::Integer(value)),
-                ) = clause
+                if let WhereClause::Op(col, op, Expression::Value(ParserValue::Integer(value))) =
+                    clause
                 {
                     if col.column != columns[0] || !self.table_column_matches(table_name, col) {
                         continue;
                     }
                     match op {
-                        Operator::Eq => {
+                        Operator::Eq | Operator::NullSafeEq => {
                             lower = Some(*value);
                             upper = Some(*value);
                         }
@@ -1999,33 +4805,28 @@ impl DatabaseManager {
                 None => continue,
             };
 
-            let left_min = lower.unwrap_or(0).clamp(i64::from(i32::MIN), i64::from(i32::MAX));
+            let left_min = lower
+                .unwrap_or(i64::from(i32::MIN))
+                .clamp(i64::from(i32::MIN), i64::from(i32::MAX));
             let left_max = upper
                 .unwrap_or(i64::from(i32::MAX))
                 .clamp(i64::from(i32::MIN), i64::from(i32::MAX));
             if left_min > left_max {
-                return Ok(Some(Vec::new()));
+                return Ok(AccessPath::NoRows);
             }
             let lower_key = TableCompositeIntColumnIter::composite_key(left_min as i32, 0);
             let upper_key = TableCompositeIntColumnIter::composite_key(left_max as i32, -1);
-            let mut rids = self
-                .index_manager
-                .range_search(table_name, &storage_name, lower_key, upper_key)
-                .into_iter()
-                .map(|(_key, rid)| rid)
-                .collect::<Vec<_>>();
-            rids.sort_by_key(|rid| (rid.page_id, rid.slot_id));
-            return Ok(Some(rids));
+            return Ok(AccessPath::IndexScan {
+                storage_name,
+                composite: true,
+                ranges: vec![(lower_key, upper_key)],
+            });
         }
 
         let mut single_bounds: HashMap<String, ColumnBounds> = HashMap::new();
         let mut single_order: Vec<String> = Vec::new();
         for clause in where_clauses {
-            if let WhereClause::Op(
-                col,
-                op,
-                Expression::Value(ParserValue::Integer(value)),
-            ) = clause
+            if let WhereClause::Op(col, op, Expression::Value(ParserValue::Integer(value))) = clause
             {
                 if !self.table_column_matches(table_name, col) {
                     continue;
@@ -2048,7 +4849,7 @@ impl DatabaseManager {
                 }
 
                 match op {
-                    Operator::Eq => {
+                    Operator::Eq | Operator::NullSafeEq => {
                         if let Some(existing) = entry.eq {
                             if existing != *value {
                                 entry.conflict = true;
@@ -2083,32 +4884,34 @@ impl DatabaseManager {
             };
 
             if bounds.conflict {
-                return Ok(Some(Vec::new()));
+                return Ok(AccessPath::NoRows);
             }
 
             if !self.ensure_index_open_for_columns(
                 db_path,
                 table_meta,
                 schema,
-                &vec![column.clone()],
+                std::slice::from_ref(column),
             )? {
                 continue;
             }
 
             if let Some(eq) = bounds.eq {
-                if let Some(lower) = bounds.lower {
-                    if eq < lower {
-                        return Ok(Some(Vec::new()));
-                    }
+                if let Some(lower) = bounds.lower
+                    && eq < lower
+                {
+                    return Ok(AccessPath::NoRows);
                 }
-                if let Some(upper) = bounds.upper {
-                    if eq > upper {
-                        return Ok(Some(Vec::new()));
-                    }
+                if let Some(upper) = bounds.upper
+                    && eq > upper
+                {
+                    return Ok(AccessPath::NoRows);
                 }
-                let mut rids = self.index_manager.search_all(table_name, column, eq);
-                rids.sort_by_key(|rid| (rid.page_id, rid.slot_id));
-                return Ok(Some(rids));
+                return Ok(AccessPath::IndexScan {
+                    storage_name: column.clone(),
+                    composite: false,
+                    ranges: vec![(eq, eq)],
+                });
             }
 
             if bounds.lower.is_none() && bounds.upper.is_none() {
@@ -2118,21 +4921,53 @@ impl DatabaseManager {
             let lower = bounds.lower.unwrap_or(i64::MIN);
             let upper = bounds.upper.unwrap_or(i64::MAX);
             if lower > upper {
-                return Ok(Some(Vec::new()));
+                return Ok(AccessPath::NoRows);
             }
 
-            let mut rids = self
-                .index_manager
-                .range_search(table_name, column, lower, upper)
-                .into_iter()
-                .map(|(_key, rid)| rid)
-                .collect::<Vec<_>>();
-            rids.sort_by_key(|rid| (rid.page_id, rid.slot_id));
-            return Ok(Some(rids));
+            return Ok(AccessPath::IndexScan {
+                storage_name: column.clone(),
+                composite: false,
+                ranges: vec![(lower, upper)],
+            });
         }
 
         for clause in where_clauses {
             match clause {
+                // A VARCHAR index is keyed by hash, so it only answers equality,
+                // and a row that merely shares the hash fails the WHERE re-check
+                WhereClause::Op(
+                    col,
+                    Operator::Eq | Operator::NullSafeEq,
+                    Expression::Value(ParserValue::String(value)),
+                ) => {
+                    if !self.table_column_matches(table_name, col) {
+                        continue;
+                    }
+                    if !Self::has_single_column_index(table_meta, &col.column) {
+                        continue;
+                    }
+                    let col_idx = self.resolve_single_column_index(schema, col)?;
+                    // A value too long for the column is reported by the scan
+                    if !matches!(schema.columns[col_idx].data_type, DataType::Char(len) | DataType::Varchar(len) if value.len() <= len)
+                    {
+                        continue;
+                    }
+                    if !self.ensure_index_open_for_columns(
+                        db_path,
+                        table_meta,
+                        schema,
+                        std::slice::from_ref(&col.column),
+                    )? {
+                        continue;
+                    }
+
+                    let key = string_index_key(value);
+                    return Ok(AccessPath::IndexScan {
+                        storage_name: col.column.clone(),
+                        composite: false,
+                        ranges: vec![(key, key)],
+                    });
+                }
                 WhereClause::In(col, values) => {
                     if !self.table_column_matches(table_name, col) {
                         continue;
@@ -2148,40 +4983,77 @@ impl DatabaseManager {
                         db_path,
                         table_meta,
                         schema,
-                        &vec![col.column.clone()],
+                        std::slice::from_ref(&col.column),
                     )? {
                         continue;
                     }
 
-                    let mut rids = Vec::new();
-                    for value in values {
-                        if let ParserValue::Integer(int_val) = value {
-                            rids.extend(
-                                self.index_manager
-                                    .search_all(table_name, &col.column, *int_val),
-                            );
-                        }
+                    let keys = values
+                        .iter()
+                        .filter_map(|value| match value {
+                            ParserValue::Integer(int_val) => Some(*int_val),
+                            _ => None,
+                        })
+                        .collect();
+                    // A run of consecutive values costs one range search
+                    // instead of one lookup per value
+                    return Ok(AccessPath::IndexScan {
+                        storage_name: col.column.clone(),
+                        composite: false,
+                        ranges: Self::contiguous_runs(keys),
+                    });
+                }
+                // NULLs are never indexed, so every entry of the index is a
+                // non-NULL row. IS NULL has no such shortcut.
+                WhereClause::NotNull(col) => {
+                    if !self.table_column_matches(table_name, col) {
+                        continue;
+                    }
+                    if !Self::has_single_column_index(table_meta, &col.column) {
+                        continue;
+                    }
+                    let col_idx = self.resolve_single_column_index(schema, col)?;
+                    if matches!(
+                        schema.columns[col_idx].data_type,
+                        DataType::Float | DataType::Decimal(..)
+                    ) {
+                        continue;
                     }
-                    if rids.is_empty() {
-                        return Ok(Some(Vec::new()));
+                    if !self.ensure_index_open_for_columns(
+                        db_path,
+                        table_meta,
+                        schema,
+                        std::slice::from_ref(&col.column),
+                    )? {
+                        continue;
                     }
-                    rids.sort_by_key(|rid| (rid.page_id, rid.slot_id));
-                    return Ok(Some(rids));
+
+                    return Ok(AccessPath::IndexScan {
+                        storage_name: col.column.clone(),
+                        composite: false,
+                        ranges: vec![(i64::MIN, i64::MAX)],
+                    });
                 }
                 _ => continue,
             }
         }
 
-        Ok(None)
+        Ok(AccessPath::FullScan)
     }
 
-    fn ensure_numeric_column(&self, schema: &TableSchema, col_idx: usize) -> DatabaseResult<()> {
-        match schema.columns[col_idx].data_type {
-            DataType::Int | DataType::Float => Ok(()),
-            _ => Err(DatabaseError::TypeMismatch(
-                "Aggregate requires numeric column".to_string(),
-            )),
+    /// Split `keys` into runs of consecutive integers, as inclusive
+    /// `(first, last)` pairs in ascending order. Duplicates are dropped.
+    fn contiguous_runs(mut keys: Vec<i64>) -> Vec<(i64, i64)> {
+        keys.sort_unstable();
+        keys.dedup();
+        let mut runs: Vec<(i64, i64)> = Vec::new();
+        for key in keys {
+            match runs.last_mut() {
+                Some((_, last)) if last.checked_add(1) == Some(key) => *last = key,
+                _ => runs.push((key, key)),
+            }
         }
+        runs
     }
 
     fn numeric_type_for_column(
@@ -2192,6 +5064,7 @@ impl DatabaseManager {
         match schema.columns[col_idx].data_type {
             DataType::Int => Ok(NumericType::Int),
             DataType::Float => Ok(NumericType::Float),
+            DataType::Decimal(_, scale) => Ok(NumericType::Decimal(scale)),
             _ => Err(DatabaseError::TypeMismatch(
                 "Aggregate requires numeric column".to_string(),
             )),
@@ -2217,6 +5090,22 @@ impl DatabaseManager {
                     sum: 0.0,
                     has_value: false,
                 },
+                AggSpec::Sum {
+                    numeric: NumericType::Decimal(scale),
+                    ..
+                } => AggState::SumDecimal {
+                    sum: 0,
+                    scale: *scale,
+                    has_value: false,
+                },
+                AggSpec::Avg {
+                    numeric: NumericType::Decimal(scale),
+                    ..
+                } => AggState::AvgDecimal {
+                    sum: 0,
+                    scale: *scale,
+                    count: 0,
+                },
                 AggSpec::Avg { .. } => AggState::Avg { sum: 0.0, count: 0 },
                 AggSpec::Min { .. } => AggState::Min(None),
                 AggSpec::Max { .. } => AggState::Max(None),
@@ -2287,6 +5176,14 @@ impl DatabaseManager {
                         *sum += *v as f64;
                         *has_value = true;
                     }
+                    (
+                        NumericType::Decimal(_),
+                        RecordValue::Decimal(v, _),
+                        AggState::SumDecimal { sum, has_value, .. },
+                    ) => {
+                        *sum += i128::from(*v);
+                        *has_value = true;
+                    }
                     (_, RecordValue::Null, _) => {}
                     _ => {
                         return Err(DatabaseError::TypeMismatch(
@@ -2295,11 +5192,24 @@ impl DatabaseManager {
                     }
                 }
             }
-            AggSpec::Avg { col_idx } => {
+            AggSpec::Avg { col_idx, .. } => {
                 let value = record
                     .get(*col_idx)
                     .ok_or_else(|| DatabaseError::TypeMismatch("Invalid AVG column".to_string()))?;
-                if let AggState::Avg { sum, count } = state {
+                if let AggState::AvgDecimal { sum, count, .. } = state {
+                    match value {
+                        RecordValue::Decimal(v, _) => {
+                            *sum += i128::from(*v);
+                            *count += 1;
+                        }
+                        RecordValue::Null => {}
+                        _ => {
+                            return Err(DatabaseError::TypeMismatch(
+                                "AVG requires numeric column".to_string(),
+                            ));
+                        }
+                    }
+                } else if let AggState::Avg { sum, count } = state {
                     match value {
                         RecordValue::Int(v) => {
                             *sum += *v as f64;
@@ -2361,7 +5271,7 @@ impl DatabaseManager {
 
     fn build_aggregate_row(
         &self,
-        group_key: Option<&RecordValue>,
+        group_key: Option<&[RecordValue]>,
         output_selectors: &[OutputSelector],
         agg_specs: &[AggSpec],
         aggs: &[AggState],
@@ -2369,8 +5279,8 @@ impl DatabaseManager {
         let mut row = Vec::with_capacity(output_selectors.len());
         for selector in output_selectors {
             match selector {
-                OutputSelector::GroupKey => {
-                    let value = group_key.ok_or_else(|| {
+                OutputSelector::GroupKey(pos) => {
+                    let value = group_key.and_then(|key| key.get(*pos)).ok_or_else(|| {
                         DatabaseError::TypeMismatch("Missing GROUP BY value".to_string())
                     })?;
                     row.push(self.format_value(value));
@@ -2386,7 +5296,137 @@ impl DatabaseManager {
                 }
             }
         }
-        Ok(row)
+        Ok(row)
+    }
+
+    /// Whether two aggregate selectors compute the same thing, however their
+    /// columns are qualified
+    fn same_aggregate(
+        &self,
+        schema: &TableSchema,
+        left: &Selector,
+        right: &Selector,
+    ) -> DatabaseResult<bool> {
+        let column = |selector: &Selector| match selector {
+            Selector::Count(tc)
+            | Selector::Average(tc)
+            | Selector::Max(tc)
+            | Selector::Min(tc)
+            | Selector::Sum(tc) => Some(tc.clone()),
+            Selector::Column(_) | Selector::Cast(..) | Selector::CountAll => None,
+        };
+        if std::mem::discriminant(left) != std::mem::discriminant(right) {
+            return Ok(false);
+        }
+        match (column(left), column(right)) {
+            (Some(l), Some(r)) => Ok(self.resolve_single_column_index(schema, &l)?
+                == self.resolve_single_column_index(schema, &r)?),
+            _ => Ok(true),
+        }
+    }
+
+    /// The aggregate a HAVING condition compares
+    fn having_agg_spec(
+        &self,
+        schema: &TableSchema,
+        selector: &Selector,
+    ) -> DatabaseResult<AggSpec> {
+        Ok(match selector {
+            Selector::CountAll => AggSpec::CountAll,
+            Selector::Count(tc) => AggSpec::Count {
+                col_idx: self.resolve_single_column_index(schema, tc)?,
+            },
+            Selector::Average(tc) => {
+                let col_idx = self.resolve_single_column_index(schema, tc)?;
+                let numeric = self.numeric_type_for_column(schema, col_idx)?;
+                AggSpec::Avg { col_idx, numeric }
+            }
+            Selector::Max(tc) => AggSpec::Max {
+                col_idx: self.resolve_single_column_index(schema, tc)?,
+            },
+            Selector::Min(tc) => AggSpec::Min {
+                col_idx: self.resolve_single_column_index(schema, tc)?,
+            },
+            Selector::Sum(tc) => {
+                let col_idx = self.resolve_single_column_index(schema, tc)?;
+                let numeric = self.numeric_type_for_column(schema, col_idx)?;
+                AggSpec::Sum { col_idx, numeric }
+            }
+            Selector::Column(_) | Selector::Cast(..) => {
+                return Err(DatabaseError::TypeMismatch(
+                    "HAVING conditions must compare an aggregate".to_string(),
+                ));
+            }
+        })
+    }
+
+    /// The literal on the right of a HAVING condition, as a value comparable
+    /// with what `aggregate_sort_value` gives for `spec`
+    fn having_literal(
+        &self,
+        schema: &TableSchema,
+        spec: &AggSpec,
+        value: &ParserValue,
+    ) -> DatabaseResult<RecordValue> {
+        let result_type = match spec {
+            AggSpec::Min { col_idx } | AggSpec::Max { col_idx } => {
+                Some(&schema.columns[*col_idx].data_type)
+            }
+            _ => None,
+        };
+        let string_result = matches!(result_type, Some(DataType::Char(_) | DataType::Varchar(_)));
+        let bool_result = matches!(result_type, Some(DataType::Bool));
+        match value {
+            ParserValue::Null => Ok(RecordValue::Null),
+            ParserValue::Placeholder(n) => Err(DatabaseError::UnboundParameter(*n)),
+            ParserValue::Bool(b) if bool_result => Ok(RecordValue::Bool(*b)),
+            ParserValue::Bool(_) => Err(DatabaseError::TypeMismatch(
+                "Cannot compare a non-boolean aggregate with a boolean in HAVING".to_string(),
+            )),
+            _ if bool_result => Err(DatabaseError::TypeMismatch(
+                "Cannot compare a boolean aggregate with a non-boolean in HAVING".to_string(),
+            )),
+            ParserValue::Integer(i) if !string_result => Ok(RecordValue::Float(*i as f64)),
+            ParserValue::Float(f) if !string_result => Ok(RecordValue::Float(*f)),
+            ParserValue::String(s) if string_result => Ok(RecordValue::String(s.clone())),
+            ParserValue::String(_) => Err(DatabaseError::TypeMismatch(
+                "Cannot compare a numeric aggregate with a string in HAVING".to_string(),
+            )),
+            ParserValue::Integer(_) | ParserValue::Float(_) => Err(DatabaseError::TypeMismatch(
+                "Cannot compare a string aggregate with a number in HAVING".to_string(),
+            )),
+        }
+    }
+
+    /// Whether a group's aggregates meet every HAVING condition. Aggregates
+    /// are compared at full precision, not as rounded for display.
+    fn having_holds(&self, aggs: &[AggState], having: &[(usize, Operator, RecordValue)]) -> bool {
+        having.iter().all(|(idx, op, literal)| {
+            self.compare_values(&self.aggregate_sort_value(&aggs[*idx]), op, literal)
+        })
+    }
+
+    /// Aggregate result as a value `compare_order_values` can sort by
+    fn aggregate_sort_value(&self, state: &AggState) -> RecordValue {
+        match state {
+            AggState::Count(count) => RecordValue::Float(*count as f64),
+            AggState::SumInt { sum, has_value } if *has_value => RecordValue::Float(*sum as f64),
+            AggState::SumFloat { sum, has_value } if *has_value => RecordValue::Float(*sum),
+            AggState::Avg { sum, count } if *count > 0 => RecordValue::Float(sum / *count as f64),
+            AggState::SumDecimal {
+                sum,
+                scale,
+                has_value,
+            } if *has_value => match i64::try_from(*sum) {
+                Ok(units) => RecordValue::Decimal(units, *scale),
+                Err(_) => RecordValue::Float(*sum as f64 / 10f64.powi(i32::from(*scale))),
+            },
+            AggState::AvgDecimal { sum, scale, count } if *count > 0 => {
+                RecordValue::Float(*sum as f64 / 10f64.powi(i32::from(*scale)) / *count as f64)
+            }
+            AggState::Min(Some(value)) | AggState::Max(Some(value)) => value.clone(),
+            _ => RecordValue::Null,
+        }
     }
 
     fn format_aggregate_value(&self, state: &AggState, spec: &AggSpec) -> String {
@@ -2426,6 +5466,35 @@ impl DatabaseManager {
                     "NULL".to_string()
                 }
             }
+            (
+                AggSpec::Sum { .. },
+                AggState::SumDecimal {
+                    sum,
+                    scale,
+                    has_value,
+                },
+            ) => {
+                if *has_value {
+                    format_wide_decimal(*sum, *scale)
+                } else {
+                    "NULL".to_string()
+                }
+            }
+            // Divided exactly and rounded to two places like a FLOAT average
+            (AggSpec::Avg { .. }, AggState::AvgDecimal { sum, scale, count }) => {
+                if *count > 0 {
+                    let divisor = i128::from(*count) * 10i128.pow(u32::from(*scale));
+                    let scaled = sum * 100;
+                    let half = if scaled < 0 {
+                        -divisor / 2
+                    } else {
+                        divisor / 2
+                    };
+                    format_wide_decimal((scaled + half) / divisor, 2)
+                } else {
+                    "NULL".to_string()
+                }
+            }
             (AggSpec::Min { .. }, AggState::Min(value))
             | (AggSpec::Max { .. }, AggState::Max(value)) => value
                 .as_ref()
@@ -2438,6 +5507,9 @@ impl DatabaseManager {
         match value {
             RecordValue::Int(v) => GroupKey::Int(*v),
             RecordValue::Float(v) => GroupKey::Float(v.to_bits()),
+            // Every value of a DECIMAL column has the column's scale
+            RecordValue::Decimal(units, _) => GroupKey::Decimal(*units),
+            RecordValue::Bool(b) => GroupKey::Bool(*b),
             RecordValue::String(s) => GroupKey::String(s.clone()),
             RecordValue::Null => GroupKey::Null,
         }
@@ -2453,6 +5525,10 @@ impl DatabaseManager {
             (RecordValue::Float(l), RecordValue::Float(r)) => l
                 .partial_cmp(r)
                 .ok_or_else(|| DatabaseError::TypeMismatch("Invalid float comparison".to_string())),
+            (RecordValue::Decimal(l, ls), RecordValue::Decimal(r, rs)) => {
+                Ok(compare_decimals(*l, *ls, *r, *rs))
+            }
+            (RecordValue::Bool(l), RecordValue::Bool(r)) => Ok(l.cmp(r)),
             (RecordValue::String(l), RecordValue::String(r)) => Ok(l.cmp(r)),
             _ => Err(DatabaseError::TypeMismatch(
                 "Aggregate comparison type mismatch".to_string(),
@@ -2473,6 +5549,10 @@ impl DatabaseManager {
             (RecordValue::Float(l), RecordValue::Float(r)) => l
                 .partial_cmp(r)
                 .ok_or_else(|| DatabaseError::TypeMismatch("Invalid float comparison".to_string())),
+            (RecordValue::Decimal(l, ls), RecordValue::Decimal(r, rs)) => {
+                Ok(compare_decimals(*l, *ls, *r, *rs))
+            }
+            (RecordValue::Bool(l), RecordValue::Bool(r)) => Ok(l.cmp(r)),
             (RecordValue::String(l), RecordValue::String(r)) => Ok(l.cmp(r)),
             _ => Err(DatabaseError::TypeMismatch(
                 "ORDER BY comparison type mismatch".to_string(),
@@ -2480,17 +5560,20 @@ impl DatabaseManager {
         }
     }
 
-    fn apply_limit_offset(
-        &self,
-        rows: Vec<Vec<String>>,
-        limit: Option<usize>,
-        offset: Option<usize>,
-    ) -> Vec<Vec<String>> {
-        let start = offset.unwrap_or(0);
+    /// Apply LIMIT and OFFSET to materialized result rows
+    ///
+    /// A percentage limit is taken of all result rows, rounded up, before
+    /// OFFSET skips any of them.
+    fn apply_limit_offset(rows: Vec<Vec<String>>, clause: &SelectClause) -> Vec<Vec<String>> {
+        let start = clause.offset.unwrap_or(0);
         if start >= rows.len() {
             return Vec::new();
         }
 
+        let limit = match clause.limit {
+            Some(percent) if clause.limit_percent => Some((rows.len() * percent).div_ceil(100)),
+            limit => limit,
+        };
         let iter = rows.into_iter().skip(start);
         match limit {
             Some(count) => iter.take(count).collect(),
@@ -2498,12 +5581,69 @@ impl DatabaseManager {
         }
     }
 
+    /// `SELECT ... INTO OUTFILE`: write the rows of a SELECT to a new
+    /// delimited file, returning how many rows were written
+    ///
+    /// Values are written as SELECT shows them, with NULL as `NULL`, so LOAD
+    /// DATA INFILE with the same delimiter reads the file back. An existing
+    /// file is never overwritten.
+    pub fn select_into_outfile(
+        &mut self,
+        clause: SelectClause,
+        file_path: &str,
+        delimiter: char,
+        header: bool,
+    ) -> DatabaseResult<usize> {
+        if !delimiter.is_ascii() {
+            return Err(DatabaseError::TypeMismatch(format!(
+                "Delimiter {:?} is not an ASCII character",
+                delimiter
+            )));
+        }
+        let (headers, rows) = self.select(clause)?;
+
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(file_path)?;
+        let mut writer = WriterBuilder::new()
+            .delimiter(delimiter as u8)
+            .from_writer(std::io::BufWriter::new(file));
+        let csv_error = |e: csv::Error| std::io::Error::other(e.to_string());
+        if header {
+            writer.write_record(&headers).map_err(csv_error)?;
+        }
+        for row in &rows {
+            writer.write_record(row).map_err(csv_error)?;
+        }
+        writer.flush()?;
+        Ok(rows.len())
+    }
+
+    /// Replace the contents of `table` with the rows of a delimited file
+    ///
+    /// The whole load is one logged unit: a crash partway through restores
+    /// the table and its indexes as they were before the load.
     pub fn load_data_infile(
         &mut self,
         file_path: &str,
         table: &str,
         delimiter: char,
     ) -> DatabaseResult<usize> {
+        self.logged_unit(|db| db.load_data_infile_unlogged(file_path, table, delimiter))
+    }
+
+    fn load_data_infile_unlogged(
+        &mut self,
+        file_path: &str,
+        table: &str,
+        delimiter: char,
+    ) -> DatabaseResult<usize> {
+        // The CSV reader splits on a single byte; check before the table is cleared
+        if !delimiter.is_ascii() {
+            return Err(DatabaseError::InvalidDelimiter(delimiter));
+        }
+
         // Get table metadata to know column types and indexes
         let table_meta = {
             let metadata = self
@@ -2535,15 +5675,21 @@ impl DatabaseManager {
         let table_path_str = table_path.to_string_lossy().to_string();
 
         // Step 3a: Flush all buffers first - this ensures all OTHER tables' data is safe
-        self.buffer_manager.lock().unwrap().flush_all()?;
+        self.buffer_manager.write().unwrap().flush_all()?;
 
         // Step 3b: Close the table (remove from open_tables)
         self.record_manager.close_table(table)?;
 
         // Step 3c: Delete the old table file using the file manager
         // This ensures the file handle is properly closed before deletion
-        if table_path.exists() {
-            let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        if self
+            .buffer_manager
+            .read()
+            .unwrap()
+            .file_manager()
+            .file_exists(&table_path)
+        {
+            let mut buffer_manager = self.buffer_manager.write().unwrap();
             let _ = buffer_manager.file_manager_mut().remove_file(&table_path);
         }
 
@@ -2602,7 +5748,18 @@ impl DatabaseManager {
                             Ok(f) => ParserValue::Float(f),
                             Err(_) => ParserValue::Null,
                         },
-                        crate::record::DataType::Char(_) => {
+                        crate::record::DataType::Bool => match parse_bool_field(trimmed) {
+                            Some(b) => ParserValue::Bool(b),
+                            None => ParserValue::Null,
+                        },
+                        // Kept as text so the digits are not rounded through f64
+                        crate::record::DataType::Decimal(_, scale) => {
+                            match parse_decimal(trimmed, scale) {
+                                Some(_) => ParserValue::String(trimmed.to_string()),
+                                None => ParserValue::Null,
+                            }
+                        }
+                        crate::record::DataType::Char(_) | crate::record::DataType::Varchar(_) => {
                             // Reuse string buffer to avoid allocation for each string
                             string_buffer.clear();
                             string_buffer.push_str(field);
@@ -2626,7 +5783,7 @@ impl DatabaseManager {
                     // Flush and clear buffer pool periodically to prevent memory buildup
                     // During bulk insert, we're appending to the end of the file, so old pages
                     // won't be accessed again. Flushing frees up memory and prevents thrashing.
-                    self.buffer_manager.lock().unwrap().flush_and_clear()?;
+                    self.buffer_manager.write().unwrap().flush_and_clear()?;
                 }
             }
         }
@@ -2635,7 +5792,7 @@ impl DatabaseManager {
         if !batch_rows.is_empty() {
             total_inserted += self.bulk_insert(table, batch_rows, true, true, true)?;
             // Final flush after last batch
-            self.buffer_manager.lock().unwrap().flush_and_clear()?;
+            self.buffer_manager.write().unwrap().flush_and_clear()?;
         }
 
         eprintln!(
@@ -2643,59 +5800,541 @@ impl DatabaseManager {
             total_inserted, file_path, table
         );
 
-        // Step 5: Reconstruct all indexes using bulk create
-        for def in &index_defs {
-            let scan_iter = self.record_manager.scan_iter(table)?;
+        // Step 5: Reconstruct all indexes using bulk create
+        for def in &index_defs {
+            let scan_iter = self.record_manager.scan_iter(table)?;
+
+            match def.indices.as_slice() {
+                [col_idx] => {
+                    let table_data = TableColumnKeyIter::new(scan_iter, *col_idx);
+                    self.index_manager.create_index_from_table_with_order(
+                        &db_path_str,
+                        table,
+                        &def.storage_name,
+                        def.order,
+                        table_data,
+                    )?;
+                }
+                [left_idx, right_idx] => {
+                    let table_data =
+                        TableCompositeIntColumnIter::new(scan_iter, *left_idx, *right_idx);
+                    self.index_manager.create_index_from_table_with_order(
+                        &db_path_str,
+                        table,
+                        &def.storage_name,
+                        def.order,
+                        table_data,
+                    )?;
+                }
+                _ => continue,
+            }
+
+            // Flush and close the index immediately to free memory before building the next one.
+            self.index_manager.close_index(table, &def.storage_name)?;
+
+            // Flush and clear buffer pool after each index creation
+            // This releases ALL cached pages to prevent memory buildup.
+            self.buffer_manager.write().unwrap().flush_and_clear()?;
+        }
+
+        // Step 6: Flush buffer manager to release page cache
+        // This ensures we're not holding onto all the index pages in memory
+        {
+            let mut buffer_manager = self.buffer_manager.write().unwrap();
+            // Ignore flush errors - they're not critical for correctness
+            let _ = buffer_manager.flush_all();
+        }
+
+        if total_inserted > 0 {
+            self.bump_table_version(table)?;
+        }
+        Ok(total_inserted)
+    }
+
+    /// Drop and rebuild every index of `table` (primary key included) from the
+    /// current table data. Returns the number of indexes rebuilt.
+    pub fn reindex_table(&mut self, table: &str) -> DatabaseResult<usize> {
+        self.logged_unit(|db| db.reindex_table_unlogged(table))
+    }
+
+    fn reindex_table_unlogged(&mut self, table: &str) -> DatabaseResult<usize> {
+        let db_name = self
+            .current_db
+            .clone()
+            .ok_or(DatabaseError::NoDatabaseSelected)?;
+        let table_meta = {
+            let metadata = self
+                .current_metadata
+                .as_ref()
+                .ok_or(DatabaseError::NoDatabaseSelected)?;
+            metadata.get_table(table)?.clone()
+        };
+        let schema = self.metadata_to_schema(&table_meta);
+        let db_path = self.data_dir.join(&db_name).to_string_lossy().to_string();
+
+        let defs = self.build_index_defs(&table_meta)?;
+        for def in &defs {
+            let columns: Vec<String> = def
+                .indices
+                .iter()
+                .map(|&idx| table_meta.columns[idx].name.clone())
+                .collect();
+            self.rebuild_index_for_columns(&db_path, &table_meta, &schema, &columns)?;
+        }
+
+        Ok(defs.len())
+    }
+
+    /// Drop and rebuild the index called `index_name` from the current table data.
+    pub fn reindex_index(&mut self, index_name: &str) -> DatabaseResult<()> {
+        let db_name = self
+            .current_db
+            .clone()
+            .ok_or(DatabaseError::NoDatabaseSelected)?;
+        let (table_meta, columns) = {
+            let metadata = self
+                .current_metadata
+                .as_ref()
+                .ok_or(DatabaseError::NoDatabaseSelected)?;
+            let mut matches: Vec<(&TableMetadata, &IndexMetadata)> = metadata
+                .tables
+                .values()
+                .filter_map(|table_meta| {
+                    table_meta
+                        .indexes
+                        .iter()
+                        .find(|idx| idx.name == index_name)
+                        .map(|idx| (table_meta, idx))
+                })
+                .collect();
+            if matches.len() > 1 {
+                return Err(DatabaseError::TypeMismatch(format!(
+                    "Index name {} is ambiguous, use REINDEX TABLE instead",
+                    index_name
+                )));
+            }
+            let (table_meta, index_meta) = matches.pop().ok_or_else(|| {
+                DatabaseError::TypeMismatch(format!("Index {} not found", index_name))
+            })?;
+            (table_meta.clone(), index_meta.columns.clone())
+        };
+        let schema = self.metadata_to_schema(&table_meta);
+        let db_path = self.data_dir.join(&db_name).to_string_lossy().to_string();
+
+        self.rebuild_index_for_columns(&db_path, &table_meta, &schema, &columns)
+    }
+
+    /// Change the type of `column` in `table`, converting every stored value
+    ///
+    /// INT widens to FLOAT losslessly and VARCHAR values keep their text when
+    /// the length changes; any other conversion has to be exact for every row
+    /// (and the column's default) or nothing is changed. Non-unique indexes
+    /// on the column are dropped when the new type cannot back them: a FLOAT
+    /// column, or a non-INT column in a composite index (see `indexable`).
+    /// Returns the number of rows rewritten.
+    pub fn modify_column(
+        &mut self,
+        table: &str,
+        column: &str,
+        new_type: ColumnType,
+    ) -> DatabaseResult<usize> {
+        let db_name = self
+            .current_db
+            .clone()
+            .ok_or(DatabaseError::NoDatabaseSelected)?;
+        let table_meta = {
+            let metadata = self
+                .current_metadata
+                .as_ref()
+                .ok_or(DatabaseError::NoDatabaseSelected)?;
+            let table_meta = metadata.get_table(table)?.clone();
+
+            // Both sides of a foreign key have to keep the same type
+            let foreign_key = table_meta
+                .foreign_keys
+                .iter()
+                .find(|fk| fk.columns.iter().any(|c| c == column))
+                .or_else(|| {
+                    metadata
+                        .tables
+                        .values()
+                        .flat_map(|other| &other.foreign_keys)
+                        .find(|fk| {
+                            fk.ref_table == table && fk.ref_columns.iter().any(|c| c == column)
+                        })
+                });
+            if let Some(fk) = foreign_key {
+                return Err(DatabaseError::TypeMismatch(format!(
+                    "Cannot change the type of column {} used by foreign key {}",
+                    column, fk.name
+                )));
+            }
+            table_meta
+        };
+
+        let col_idx = table_meta
+            .columns
+            .iter()
+            .position(|c| c.name == column)
+            .ok_or_else(|| DatabaseError::ColumnNotFound(column.to_string(), table.to_string()))?;
+        let mut new_column = table_meta.columns[col_idx].clone();
+        new_column.column_type = ColumnMetadata::type_name(&new_type);
+        // The table is rewritten below, so even an old fixed-width column
+        // becomes variable-length
+        new_column.variable_length = matches!(new_type, ColumnType::Char(_));
+        let data_type = new_column.to_data_type();
+        if data_type != DataType::Int && table_meta.auto_increment.contains_key(column) {
+            return Err(DatabaseError::TypeMismatch(format!(
+                "AUTO_INCREMENT column {} must be INT",
+                column
+            )));
+        }
+        let cannot_convert = |value: &str| {
+            DatabaseError::TypeMismatch(format!(
+                "Cannot convert {} in column {} to {}",
+                value, column, new_column.column_type
+            ))
+        };
+
+        if new_column.default_value.is_some() {
+            let old_default = table_meta.columns[col_idx].parse_default_value();
+            new_column.default_value = match Self::convert_column_value(&old_default, &data_type) {
+                Some(RecordValue::Int(i)) => Some(i.to_string()),
+                Some(RecordValue::Float(f)) => Some(f.to_string()),
+                Some(RecordValue::Decimal(units, scale)) => Some(format_decimal(units, scale)),
+                Some(RecordValue::Bool(b)) => Some(b.to_string()),
+                Some(RecordValue::String(s)) => Some(s),
+                Some(RecordValue::Null) => None,
+                None => {
+                    return Err(cannot_convert(&format!(
+                        "default {}",
+                        self.format_value(&old_default)
+                    )));
+                }
+            };
+        }
+        if let Some(len) = new_column.declared_default().and_then(|d| d.text_len())
+            && !matches!(data_type, DataType::Char(n) | DataType::Varchar(n) if n >= len)
+        {
+            return Err(DatabaseError::TypeMismatch(format!(
+                "{} default of column {} needs a VARCHAR({}) or wider column",
+                new_column.default_function.as_deref().unwrap_or_default(),
+                column,
+                len
+            )));
+        }
+
+        let table_path = self.table_path(&db_name, table);
+        let table_path_str = table_path.to_string_lossy().to_string();
+        let _ = self
+            .record_manager
+            .open_table(&table_path_str, self.metadata_to_schema(&table_meta));
+        let mut records = Vec::new();
+        for (_rid, mut record) in self.record_manager.scan(table)? {
+            let value = record.get(col_idx).unwrap();
+            let converted = Self::convert_column_value(value, &data_type)
+                .ok_or_else(|| cannot_convert(&self.format_value(value)))?;
+            record.set(col_idx, converted);
+            records.push(record);
+        }
+
+        // Converting text to numbers can make distinct keys collide
+        let mut keys: Vec<(Option<&IndexMetadata>, Vec<usize>)> = Vec::new();
+        if let Some(pk) = &table_meta.primary_key
+            && pk.iter().any(|c| c == column)
+        {
+            keys.push((None, Self::column_indices(&table_meta, pk)?));
+        }
+        for index in &table_meta.indexes {
+            if index.unique && index.columns.iter().any(|c| c == column) {
+                keys.push((
+                    Some(index),
+                    Self::column_indices(&table_meta, &index.columns)?,
+                ));
+            }
+        }
+        for (index, indices) in keys {
+            let mut seen = HashSet::new();
+            for record in &records {
+                let values: Vec<&RecordValue> = indices
+                    .iter()
+                    .map(|&idx| record.get(idx).unwrap())
+                    .collect();
+                if values.iter().any(|value| value.is_null())
+                    || seen.insert(format!("{:?}", values))
+                {
+                    continue;
+                }
+                return Err(match index {
+                    None => self.primary_key_violation(record, &indices),
+                    Some(index) => {
+                        let values: Vec<String> = values
+                            .iter()
+                            .map(|value| self.format_value(value))
+                            .collect();
+                        DatabaseError::UniqueViolation(
+                            index.name.clone(),
+                            format!("({})", values.join(", ")),
+                        )
+                    }
+                });
+            }
+        }
+
+        let mut new_meta = table_meta.clone();
+        new_meta.columns[col_idx] = new_column;
+        // Plain indexes the new type cannot back are dropped
+        let unsupported: Vec<String> = new_meta
+            .indexes
+            .iter()
+            .filter(|idx| !idx.unique && idx.columns.iter().any(|c| c == column))
+            .filter(|idx| {
+                let indices: Vec<usize> = idx
+                    .columns
+                    .iter()
+                    .filter_map(|c| new_meta.columns.iter().position(|col| &col.name == c))
+                    .collect();
+                !Self::indexable(&new_meta, &indices)
+            })
+            .map(|idx| idx.name.clone())
+            .collect();
+        new_meta
+            .indexes
+            .retain(|idx| !unsupported.contains(&idx.name));
+
+        let new_schema = self.metadata_to_schema(&new_meta);
+        if new_schema.record_size() > Page::max_record_size_for(&new_schema) {
+            return Err(RecordError::RecordTooLarge {
+                size: new_schema.record_size(),
+                max: Page::max_record_size_for(&new_schema),
+            }
+            .into());
+        }
+
+        // Every record moves, so the index files are rebuilt from scratch
+        let db_path = self.data_dir.join(&db_name).to_string_lossy().to_string();
+        for def in self.build_index_defs(&table_meta)? {
+            let _ = self
+                .index_manager
+                .drop_index(&db_path, table, &def.storage_name);
+        }
+        self.buffer_manager.write().unwrap().flush_all()?;
+        self.record_manager.close_table(table)?;
+        {
+            let mut buffer_manager = self.buffer_manager.write().unwrap();
+            if buffer_manager.file_manager().file_exists(&table_path) {
+                buffer_manager.file_manager_mut().remove_file(&table_path)?;
+            }
+        }
+        self.record_manager
+            .create_table(&table_path_str, new_schema)?;
+        let rewritten = records.len();
+        self.record_manager.bulk_insert(table, records)?;
+
+        *self
+            .current_metadata
+            .as_mut()
+            .unwrap()
+            .get_table_mut(table)? = new_meta;
+        self.save_current_metadata()?;
+        self.reindex_table(table)?;
+        self.bump_table_version(table)?;
+        Ok(rewritten)
+    }
+
+    /// `value` as a value of a column of type `to`, or `None` if it does not
+    /// fit exactly
+    fn convert_column_value(value: &RecordValue, to: &DataType) -> Option<RecordValue> {
+        match (value, to) {
+            (RecordValue::Null, _) => Some(RecordValue::Null),
+            (RecordValue::Int(i), DataType::Int) => Some(RecordValue::Int(*i)),
+            (RecordValue::Int(i), DataType::Float) => Some(RecordValue::Float(f64::from(*i))),
+            (RecordValue::Float(f), DataType::Float) => Some(RecordValue::Float(*f)),
+            (RecordValue::Float(f), DataType::Int) => {
+                let i = *f as i32;
+                (f64::from(i) == *f).then_some(RecordValue::Int(i))
+            }
+            (RecordValue::String(s), DataType::Int) => s.parse().ok().map(RecordValue::Int),
+            (RecordValue::String(s), DataType::Float) => s
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .map(RecordValue::Float),
+            (RecordValue::Decimal(units, scale), DataType::Int) => exact_rescale(*units, *scale, 0)
+                .and_then(|i| i32::try_from(i).ok().map(RecordValue::Int)),
+            (RecordValue::Decimal(units, scale), DataType::Float) => {
+                let f = decimal_to_f64(*units, *scale);
+                (parse_decimal(&f.to_string(), *scale) == Some(*units))
+                    .then_some(RecordValue::Float(f))
+            }
+            (RecordValue::Bool(b), DataType::Bool) => Some(RecordValue::Bool(*b)),
+            (RecordValue::Bool(b), DataType::Int) => Some(RecordValue::Int(i32::from(*b))),
+            (RecordValue::Int(i @ (0 | 1)), DataType::Bool) => Some(RecordValue::Bool(*i == 1)),
+            (RecordValue::String(s), DataType::Bool) => parse_bool_field(s).map(RecordValue::Bool),
+            (RecordValue::Bool(_), DataType::Float | DataType::Decimal(..))
+            | (_, DataType::Bool) => None,
+            (value, DataType::Decimal(precision, scale)) => {
+                let (units, from) = match value {
+                    RecordValue::Int(i) => (i64::from(*i), 0),
+                    RecordValue::Float(f) => decimal_literal(&f.to_string())?,
+                    RecordValue::String(s) => decimal_literal(s)?,
+                    RecordValue::Decimal(units, from) => (*units, *from),
+                    RecordValue::Bool(_) | RecordValue::Null => unreachable!(),
+                };
+                exact_rescale(units, from, *scale)
+                    .filter(|units| decimal_fits(*units, *precision))
+                    .map(|units| RecordValue::Decimal(units, *scale))
+            }
+            (value, DataType::Char(len) | DataType::Varchar(len)) => {
+                let text = match value {
+                    RecordValue::Int(i) => i.to_string(),
+                    RecordValue::Float(f) => f.to_string(),
+                    RecordValue::Decimal(units, scale) => format_decimal(*units, *scale),
+                    RecordValue::Bool(b) => b.to_string(),
+                    RecordValue::String(s) => s.clone(),
+                    RecordValue::Null => unreachable!(),
+                };
+                (text.len() <= *len).then_some(RecordValue::String(text))
+            }
+        }
+    }
+
+    /// Positions of `columns` in `table_meta`
+    fn column_indices(
+        table_meta: &TableMetadata,
+        columns: &[String],
+    ) -> DatabaseResult<Vec<usize>> {
+        columns
+            .iter()
+            .map(|col_name| {
+                table_meta
+                    .columns
+                    .iter()
+                    .position(|c| &c.name == col_name)
+                    .ok_or_else(|| {
+                        DatabaseError::ColumnNotFound(col_name.clone(), table_meta.name.clone())
+                    })
+            })
+            .collect()
+    }
+
+    /// Verify that every index of `table` agrees with the table data: each live
+    /// record's key must be in the index and each index entry must point at a
+    /// live record with that key. Discrepancies are reported, not repaired.
+    pub fn check_table(&mut self, table: &str) -> DatabaseResult<Vec<IndexCheckIssue>> {
+        let db_name = self
+            .current_db
+            .clone()
+            .ok_or(DatabaseError::NoDatabaseSelected)?;
+        let table_meta = {
+            let metadata = self
+                .current_metadata
+                .as_ref()
+                .ok_or(DatabaseError::NoDatabaseSelected)?;
+            metadata.get_table(table)?.clone()
+        };
+        let schema = self.metadata_to_schema(&table_meta);
+        let db_path = self.data_dir.join(&db_name).to_string_lossy().to_string();
+        let table_path = self.table_path(&db_name, table);
+        let _ = self
+            .record_manager
+            .open_table(table_path.to_string_lossy().as_ref(), schema);
+
+        let mut issues = Vec::new();
+        for def in self.build_index_defs(&table_meta)? {
+            let index_name = table_meta
+                .indexes
+                .iter()
+                .find(|idx| {
+                    Self::index_storage_name(&idx.columns).as_deref()
+                        == Some(def.storage_name.as_str())
+                })
+                .map(|idx| idx.name.clone())
+                .unwrap_or_else(|| "PRIMARY".to_string());
 
-            match def.indices.as_slice() {
-                [col_idx] => {
-                    let table_data = TableIntColumnIter::new(scan_iter, *col_idx);
-                    self.index_manager.create_index_from_table(
-                        &db_path_str,
-                        table,
-                        &def.storage_name,
-                        table_data,
-                    )?;
-                }
-                [left_idx, right_idx] => {
-                    let table_data =
-                        TableCompositeIntColumnIter::new(scan_iter, *left_idx, *right_idx);
-                    self.index_manager.create_index_from_table(
-                        &db_path_str,
-                        table,
-                        &def.storage_name,
-                        table_data,
-                    )?;
-                }
-                _ => continue,
+            if let Err(err) = self
+                .index_manager
+                .open_index(&db_path, table, &def.storage_name)
+            {
+                issues.push(IndexCheckIssue {
+                    index: index_name,
+                    kind: IndexCheckIssueKind::Unreadable(err.to_string()),
+                });
+                continue;
             }
 
-            // Flush and close the index immediately to free memory before building the next one.
-            self.index_manager.close_index(table, &def.storage_name)?;
+            let scan_iter = self.record_manager.scan_iter(table)?;
+            let expected: HashSet<(i64, RecordId)> = if def.indices.len() == 1 {
+                TableColumnKeyIter::new(scan_iter, def.indices[0])
+                    .map(|item| item.map(|(rid, key)| (key, rid)))
+                    .collect::<Result<_, _>>()?
+            } else {
+                TableCompositeIntColumnIter::new(scan_iter, def.indices[0], def.indices[1])
+                    .map(|item| item.map(|(rid, key)| (key, rid)))
+                    .collect::<Result<_, _>>()?
+            };
+            let actual: HashSet<(i64, RecordId)> = self
+                .index_manager
+                .range_iter(table, &def.storage_name, i64::MIN, i64::MAX)
+                .collect();
 
-            // Flush and clear buffer pool after each index creation
-            // This releases ALL cached pages to prevent memory buildup.
-            self.buffer_manager.lock().unwrap().flush_and_clear()?;
-        }
+            let mut missing: Vec<_> = expected.difference(&actual).copied().collect();
+            missing.sort_by_key(|(key, rid)| (*key, rid.page_id, rid.slot_id));
+            for (key, rid) in missing {
+                issues.push(IndexCheckIssue {
+                    index: index_name.clone(),
+                    kind: IndexCheckIssueKind::MissingEntry(key, rid),
+                });
+            }
 
-        // Step 6: Flush buffer manager to release page cache
-        // This ensures we're not holding onto all the index pages in memory
-        {
-            let mut buffer_manager = self.buffer_manager.lock().unwrap();
-            // Ignore flush errors - they're not critical for correctness
-            let _ = buffer_manager.flush_all();
+            let mut dangling: Vec<_> = actual.difference(&expected).copied().collect();
+            dangling.sort_by_key(|(key, rid)| (*key, rid.page_id, rid.slot_id));
+            for (key, rid) in dangling {
+                issues.push(IndexCheckIssue {
+                    index: index_name.clone(),
+                    kind: IndexCheckIssueKind::DanglingPointer(key, rid),
+                });
+            }
         }
 
-        Ok(total_inserted)
+        Ok(issues)
     }
 
     // Helper methods
     fn table_path(&self, db: &str, table: &str) -> PathBuf {
+        // Tables attached from another database are named `db.table`
+        let (db, table) = table.split_once('.').unwrap_or((db, table));
         self.data_dir.join(db).join(format!("{}.tbl", table))
     }
 
-    fn save_current_metadata(&self) -> DatabaseResult<()> {
+    /// Version of `table`'s data
+    ///
+    /// The number grows with every statement that inserts, updates or
+    /// deletes at least one row of the table, and is persisted with the
+    /// catalog, so comparing two readings tells whether the data changed.
+    pub fn table_version(&self, table: &str) -> DatabaseResult<u64> {
+        let metadata = self
+            .current_metadata
+            .as_ref()
+            .ok_or(DatabaseError::NoDatabaseSelected)?;
+        Ok(metadata.get_table(table)?.version)
+    }
+
+    fn bump_table_version(&mut self, table: &str) -> DatabaseResult<()> {
+        let metadata = self
+            .current_metadata
+            .as_mut()
+            .ok_or(DatabaseError::NoDatabaseSelected)?;
+        metadata.get_table_mut(table)?.version += 1;
+        self.save_current_metadata()
+    }
+
+    fn save_current_metadata(&mut self) -> DatabaseResult<()> {
         if let (Some(db_name), Some(metadata)) = (&self.current_db, &self.current_metadata) {
+            if let Some(catalog) = &mut self.memory_catalog {
+                catalog.insert(db_name.clone(), metadata.clone());
+                return Ok(());
+            }
             let db_path = self.data_dir.join(db_name);
             metadata.save(&db_path)?;
         }
@@ -2738,30 +6377,30 @@ impl DatabaseManager {
                         .or_default()
                         .insert(fk.ref_columns[0].clone());
                 }
-                if fk.columns.len() == 2 {
-                    if let Some(name) = Self::index_storage_name(&fk.columns) {
-                        let seen = required_composite_seen
+                if fk.columns.len() == 2
+                    && let Some(name) = Self::index_storage_name(&fk.columns)
+                {
+                    let seen = required_composite_seen
+                        .entry(table_name.clone())
+                        .or_default();
+                    if seen.insert(name) {
+                        required_composite
                             .entry(table_name.clone())
-                            .or_default();
-                        if seen.insert(name) {
-                            required_composite
-                                .entry(table_name.clone())
-                                .or_default()
-                                .push(fk.columns.clone());
-                        }
+                            .or_default()
+                            .push(fk.columns.clone());
                     }
                 }
-                if fk.ref_columns.len() == 2 {
-                    if let Some(name) = Self::index_storage_name(&fk.ref_columns) {
-                        let seen = required_composite_seen
+                if fk.ref_columns.len() == 2
+                    && let Some(name) = Self::index_storage_name(&fk.ref_columns)
+                {
+                    let seen = required_composite_seen
+                        .entry(fk.ref_table.clone())
+                        .or_default();
+                    if seen.insert(name) {
+                        required_composite
                             .entry(fk.ref_table.clone())
-                            .or_default();
-                        if seen.insert(name) {
-                            required_composite
-                                .entry(fk.ref_table.clone())
-                                .or_default()
-                                .push(fk.ref_columns.clone());
-                        }
+                            .or_default()
+                            .push(fk.ref_columns.clone());
                     }
                 }
             }
@@ -2808,14 +6447,10 @@ impl DatabaseManager {
                     return true;
                 }
                 match idx.columns.len() {
-                    1 => {
-                        if !required_cols.contains(&idx.columns[0]) {
-                            columns_to_drop.push(idx.columns.clone());
-                            updated = true;
-                            false
-                        } else {
-                            true
-                        }
+                    1 if !required_cols.contains(&idx.columns[0]) => {
+                        columns_to_drop.push(idx.columns.clone());
+                        updated = true;
+                        false
                     }
                     2 => {
                         let name = match Self::index_storage_name(&idx.columns) {
@@ -2867,22 +6502,16 @@ impl DatabaseManager {
                 let left_idx = table_meta
                     .columns
                     .iter()
-                    .position(|c| &c.name == &columns[0])
+                    .position(|c| c.name == columns[0])
                     .ok_or_else(|| {
-                        DatabaseError::ColumnNotFound(
-                            columns[0].clone(),
-                            table_meta.name.clone(),
-                        )
+                        DatabaseError::ColumnNotFound(columns[0].clone(), table_meta.name.clone())
                     })?;
                 let right_idx = table_meta
                     .columns
                     .iter()
-                    .position(|c| &c.name == &columns[1])
+                    .position(|c| c.name == columns[1])
                     .ok_or_else(|| {
-                        DatabaseError::ColumnNotFound(
-                            columns[1].clone(),
-                            table_meta.name.clone(),
-                        )
+                        DatabaseError::ColumnNotFound(columns[1].clone(), table_meta.name.clone())
                     })?;
                 if table_meta.columns[left_idx].to_data_type() != DataType::Int
                     || table_meta.columns[right_idx].to_data_type() != DataType::Int
@@ -2910,7 +6539,7 @@ impl DatabaseManager {
                     Ok(()) => {}
                     Err(IndexError::IndexNotFound(_)) => {
                         let scan_iter = self.record_manager.scan_iter(&table_meta.name)?;
-                        let table_iter = TableIntColumnIter::new(scan_iter, col_idx);
+                        let table_iter = TableColumnKeyIter::new(scan_iter, col_idx);
                         self.index_manager.create_index_from_table(
                             &db_path_str,
                             &table_meta.name,
@@ -2922,9 +6551,12 @@ impl DatabaseManager {
                 }
 
                 table_meta.indexes.push(IndexMetadata {
-                    name: self.implicit_fk_index_name(&table_meta.name, &vec![col_name.clone()]),
+                    name: self
+                        .implicit_fk_index_name(&table_meta.name, std::slice::from_ref(&col_name)),
                     columns: vec![col_name],
                     implicit: true,
+                    order: None,
+                    unique: false,
                 });
                 updated = true;
             }
@@ -2957,6 +6589,8 @@ impl DatabaseManager {
                     name: self.implicit_fk_index_name(&table_meta.name, &columns),
                     columns,
                     implicit: true,
+                    order: None,
+                    unique: false,
                 });
                 updated = true;
             }
@@ -2966,14 +6600,12 @@ impl DatabaseManager {
                     Some(name) => name,
                     None => continue,
                 };
-                if let Err(err) = self.index_manager.drop_index(
-                    &db_path_str,
-                    &table_meta.name,
-                    &storage_name,
-                ) {
-                    if !matches!(err, crate::index::IndexError::IndexNotFound(_)) {
-                        return Err(DatabaseError::IndexError(err));
-                    }
+                if let Err(err) =
+                    self.index_manager
+                        .drop_index(&db_path_str, &table_meta.name, &storage_name)
+                    && !matches!(err, crate::index::IndexError::IndexNotFound(_))
+                {
+                    return Err(DatabaseError::IndexError(err));
                 }
             }
 
@@ -2989,6 +6621,73 @@ impl DatabaseManager {
         Ok(updated)
     }
 
+    /// Reject a foreign key that would close a cycle of mandatory references
+    ///
+    /// A foreign key whose columns are all NOT NULL requires every row to have
+    /// its parent row present already. If such keys lead from `fk.ref_table`
+    /// back to `table`, no table on the cycle can receive its first row. A
+    /// cycle through a nullable foreign key is allowed, since rows can be
+    /// inserted with NULL there and updated once the other side exists.
+    fn check_foreign_key_cycle(
+        &self,
+        table: &TableMetadata,
+        fk: &ForeignKeyMetadata,
+    ) -> DatabaseResult<()> {
+        let metadata = self
+            .current_metadata
+            .as_ref()
+            .ok_or(DatabaseError::NoDatabaseSelected)?;
+        // `table` may not be registered yet (CREATE TABLE) or may be about to change
+        let lookup = |name: &str| {
+            if name == table.name {
+                Some(table)
+            } else {
+                metadata.tables.get(name)
+            }
+        };
+        let mandatory = |owner: &TableMetadata, fk: &ForeignKeyMetadata| {
+            fk.columns.iter().all(|col_name| {
+                owner
+                    .columns
+                    .iter()
+                    .any(|col| &col.name == col_name && col.not_null)
+            })
+        };
+        if !mandatory(table, fk) {
+            return Ok(());
+        }
+
+        // Depth-first search over mandatory references, keeping the path for the error
+        let mut stack = vec![vec![fk.ref_table.clone()]];
+        let mut visited = HashSet::new();
+        while let Some(path) = stack.pop() {
+            let current = path.last().unwrap();
+            if current == &table.name {
+                let cycle = std::iter::once(table.name.as_str())
+                    .chain(path.iter().map(String::as_str))
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(DatabaseError::ForeignKeyCycle(cycle));
+            }
+            if !visited.insert(current.clone()) {
+                continue;
+            }
+            if let Some(owner) = lookup(current) {
+                for next in owner
+                    .foreign_keys
+                    .iter()
+                    .filter(|next| mandatory(owner, next))
+                {
+                    let mut next_path = path.clone();
+                    next_path.push(next.ref_table.clone());
+                    stack.push(next_path);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn build_foreign_key_check(
         &self,
         table_meta: &TableMetadata,
@@ -3225,11 +6924,10 @@ impl DatabaseManager {
                 };
                 let mut used_index = false;
                 let mut index_found = false;
-                match self.index_manager.open_index(
-                    &db_path_str,
-                    &fk.ref_table,
-                    &storage_name,
-                ) {
+                match self
+                    .index_manager
+                    .open_index(&db_path_str, &fk.ref_table, &storage_name)
+                {
                     Ok(()) => {
                         used_index = true;
                         let key = TableCompositeIntColumnIter::composite_key(left, right);
@@ -3443,11 +7141,10 @@ impl DatabaseManager {
 
                 let mut used_index = false;
                 let mut index_found = false;
-                match self.index_manager.open_index(
-                    db_path_str,
-                    &fk.child_table,
-                    &storage_name,
-                ) {
+                match self
+                    .index_manager
+                    .open_index(db_path_str, &fk.child_table, &storage_name)
+                {
                     Ok(()) => {
                         used_index = true;
                         let key = TableCompositeIntColumnIter::composite_key(left, right);
@@ -3520,29 +7217,28 @@ impl DatabaseManager {
     ) -> DatabaseResult<RecordValue> {
         match (value, data_type) {
             (ParserValue::Null, _) => Ok(RecordValue::Null),
-            (ParserValue::Integer(i), DataType::Int) => Ok(RecordValue::Int(*i as i32)),
-            (ParserValue::Integer(_), DataType::Float) => {
-                Err(DatabaseError::TypeMismatch(format!(
-                    "Cannot compare integer value with float column"
-                )))
-            }
-            (ParserValue::Integer(_), DataType::Char(_)) => {
-                Err(DatabaseError::TypeMismatch(format!(
-                    "Cannot compare integer value with string column"
-                )))
+            (ParserValue::Placeholder(n), _) => Err(DatabaseError::UnboundParameter(*n)),
+            (ParserValue::Integer(i), DataType::Int) => i32::try_from(*i)
+                .map(RecordValue::Int)
+                .map_err(|_| DatabaseError::TypeMismatch(format!("Integer {} out of range", i))),
+            (ParserValue::Integer(_), DataType::Float) => Err(DatabaseError::TypeMismatch(
+                "Cannot compare integer value with float column".to_string(),
+            )),
+            (ParserValue::Integer(_), DataType::Char(_) | DataType::Varchar(_)) => {
+                Err(DatabaseError::TypeMismatch(
+                    "Cannot compare integer value with string column".to_string(),
+                ))
             }
             (ParserValue::Float(f), DataType::Float) => Ok(RecordValue::Float(*f)),
-            (ParserValue::Float(_), DataType::Int) => {
-                Err(DatabaseError::TypeMismatch(format!(
-                    "Cannot compare float value with integer column"
-                )))
-            }
-            (ParserValue::Float(_), DataType::Char(_)) => {
-                Err(DatabaseError::TypeMismatch(format!(
-                    "Cannot compare float value with string column"
-                )))
+            (ParserValue::Float(_), DataType::Int) => Err(DatabaseError::TypeMismatch(
+                "Cannot compare float value with integer column".to_string(),
+            )),
+            (ParserValue::Float(_), DataType::Char(_) | DataType::Varchar(_)) => {
+                Err(DatabaseError::TypeMismatch(
+                    "Cannot compare float value with string column".to_string(),
+                ))
             }
-            (ParserValue::String(s), DataType::Char(max_len)) => {
+            (ParserValue::String(s), DataType::Char(max_len) | DataType::Varchar(max_len)) => {
                 if s.len() > *max_len {
                     return Err(DatabaseError::TypeMismatch(format!(
                         "String value too long for column: {} > {}",
@@ -3552,19 +7248,70 @@ impl DatabaseManager {
                 }
                 Ok(RecordValue::String(s.clone()))
             }
-            (ParserValue::String(_), DataType::Int) => {
-                Err(DatabaseError::TypeMismatch(format!(
-                    "Cannot compare string value with integer column"
-                )))
-            }
-            (ParserValue::String(_), DataType::Float) => {
-                Err(DatabaseError::TypeMismatch(format!(
-                    "Cannot compare string value with float column"
-                )))
-            }
+            (ParserValue::String(_), DataType::Int) => Err(DatabaseError::TypeMismatch(
+                "Cannot compare string value with integer column".to_string(),
+            )),
+            (ParserValue::String(_), DataType::Float) => Err(DatabaseError::TypeMismatch(
+                "Cannot compare string value with float column".to_string(),
+            )),
+            // Decimal literals keep the scale they are written with, so a
+            // comparison is not affected by the column's rounding
+            (ParserValue::Integer(i), DataType::Decimal(..)) => Ok(RecordValue::Decimal(*i, 0)),
+            (ParserValue::Float(f), DataType::Decimal(..)) => Ok(decimal_literal(&f.to_string())
+                .map_or(RecordValue::Float(*f), |(units, scale)| {
+                    RecordValue::Decimal(units, scale)
+                })),
+            (ParserValue::String(s), DataType::Decimal(..)) => decimal_literal(s)
+                .map(|(units, scale)| RecordValue::Decimal(units, scale))
+                .ok_or_else(|| {
+                    DatabaseError::TypeMismatch(
+                        "Cannot compare string value with decimal column".to_string(),
+                    )
+                }),
+            (ParserValue::Bool(b), DataType::Bool) => Ok(RecordValue::Bool(*b)),
+            (ParserValue::Bool(_), _) => Err(DatabaseError::TypeMismatch(format!(
+                "Cannot compare boolean value with {} column",
+                Self::type_kind(data_type)
+            ))),
+            (_, DataType::Bool) => Err(DatabaseError::TypeMismatch(format!(
+                "Cannot compare {} value with boolean column",
+                match value {
+                    ParserValue::Integer(_) => "integer",
+                    ParserValue::Float(_) => "float",
+                    _ => "string",
+                }
+            ))),
         }
     }
 
+    /// Round a number about to be stored in a DECIMAL column to the column's
+    /// scale, rejecting one with too many digits for its precision
+    fn round_to_column_scale(
+        &self,
+        value: RecordValue,
+        data_type: &DataType,
+    ) -> DatabaseResult<RecordValue> {
+        let DataType::Decimal(precision, scale) = *data_type else {
+            return Ok(value);
+        };
+        let rounded = match &value {
+            RecordValue::Decimal(units, from) => rescale_decimal(*units, *from, scale)
+                .filter(|units| decimal_fits(*units, precision)),
+            RecordValue::Float(_) => None,
+            _ => return Ok(value),
+        };
+        rounded
+            .map(|units| RecordValue::Decimal(units, scale))
+            .ok_or_else(|| {
+                DatabaseError::TypeMismatch(format!(
+                    "Value {} out of range for DECIMAL({},{})",
+                    self.format_value(&value),
+                    precision,
+                    scale
+                ))
+            })
+    }
+
     fn open_indexed_defs(
         &mut self,
         db_path: &str,
@@ -3616,60 +7363,58 @@ impl DatabaseManager {
         right_name: &str,
         right_schema: &TableSchema,
     ) -> DatabaseResult<JoinColumnRef> {
-        let left_idx = left_schema
-            .columns
-            .iter()
-            .position(|c| c.name == column.column);
-        let right_idx = right_schema
-            .columns
-            .iter()
-            .position(|c| c.name == column.column);
+        let (table, index) = Self::resolve_multi_join_column(
+            column,
+            &[left_name, right_name],
+            &[left_schema, right_schema],
+        )?;
+        let side = if table == 0 {
+            JoinSide::Left
+        } else {
+            JoinSide::Right
+        };
+        Ok(JoinColumnRef { side, index })
+    }
+
+    /// Resolve `column` against the joined tables called `names` (their
+    /// aliases where given), returning the position of its table in `names`
+    /// and its index in that table's schema. An unqualified name must belong
+    /// to exactly one of the tables.
+    fn resolve_multi_join_column(
+        column: &TableColumn,
+        names: &[&str],
+        schemas: &[&TableSchema],
+    ) -> DatabaseResult<(usize, usize)> {
+        let position =
+            |schema: &TableSchema| schema.columns.iter().position(|c| c.name == column.column);
 
         if let Some(table) = &column.table {
-            if table == left_name {
-                return left_idx
-                    .map(|index| JoinColumnRef {
-                        side: JoinSide::Left,
-                        index,
-                    })
-                    .ok_or_else(|| {
-                        DatabaseError::ColumnNotFound(column.column.clone(), left_name.to_string())
-                    });
-            }
-            if table == right_name {
-                return right_idx
-                    .map(|index| JoinColumnRef {
-                        side: JoinSide::Right,
-                        index,
-                    })
-                    .ok_or_else(|| {
-                        DatabaseError::ColumnNotFound(column.column.clone(), right_name.to_string())
-                    });
-            }
-            return Err(DatabaseError::ColumnNotFound(
-                column.column.clone(),
-                table.clone(),
-            ));
+            let Some(t) = names.iter().position(|name| name == table) else {
+                return Err(DatabaseError::ColumnNotFound(
+                    column.column.clone(),
+                    table.clone(),
+                ));
+            };
+            return position(schemas[t]).map(|index| (t, index)).ok_or_else(|| {
+                DatabaseError::ColumnNotFound(column.column.clone(), table.clone())
+            });
         }
 
-        match (left_idx, right_idx) {
-            (Some(index), None) => Ok(JoinColumnRef {
-                side: JoinSide::Left,
-                index,
-            }),
-            (None, Some(index)) => Ok(JoinColumnRef {
-                side: JoinSide::Right,
-                index,
-            }),
-            (Some(_), Some(_)) => Err(DatabaseError::TypeMismatch(format!(
-                "Ambiguous column {}",
-                column.column
-            ))),
-            (None, None) => Err(DatabaseError::ColumnNotFound(
-                column.column.clone(),
-                left_name.to_string(),
-            )),
+        let mut found = None;
+        for (t, schema) in schemas.iter().enumerate() {
+            if let Some(index) = position(schema) {
+                if found.is_some() {
+                    return Err(DatabaseError::TypeMismatch(format!(
+                        "Ambiguous column {}",
+                        column.column
+                    )));
+                }
+                found = Some((t, index));
+            }
         }
+        found.ok_or_else(|| {
+            DatabaseError::ColumnNotFoundInJoin(column.column.clone(), names.join(", "))
+        })
     }
 
     fn prepare_where_clauses(
@@ -3686,36 +7431,95 @@ impl DatabaseManager {
                         expr.clone(),
                     ));
                 }
+                WhereClause::CastOp(col, ty, op, expr) => {
+                    prepared.push(PreparedWhereClause::CastOp(
+                        col.clone(),
+                        *ty,
+                        op.clone(),
+                        expr.clone(),
+                    ));
+                }
                 WhereClause::Null(col) => {
                     prepared.push(PreparedWhereClause::Null(col.clone()));
                 }
                 WhereClause::NotNull(col) => {
                     prepared.push(PreparedWhereClause::NotNull(col.clone()));
                 }
-                WhereClause::Like(col, pattern) => {
-                    let regex =
-                        Regex::new(&self.like_pattern_to_regex(pattern)).map_err(|err| {
-                            DatabaseError::TypeMismatch(format!("Invalid LIKE pattern: {}", err))
-                        })?;
+                WhereClause::Like(col, pattern, escape) => {
+                    let regex = self.like_regex(pattern, *escape)?;
                     prepared.push(PreparedWhereClause::Like(col.clone(), regex));
                 }
-                _ => {
-                    return Err(DatabaseError::TypeMismatch(
-                        "WHERE clause type not yet supported".to_string(),
-                    ));
+                WhereClause::NotLike(col, pattern, escape) => {
+                    let regex = self.like_regex(pattern, *escape)?;
+                    prepared.push(PreparedWhereClause::NotLike(col.clone(), regex));
+                }
+                WhereClause::In(col, values) => {
+                    prepared.push(PreparedWhereClause::In(col.clone(), values.clone()));
+                }
+                WhereClause::Constant(left, op, right) => {
+                    // A true constant filters nothing and is dropped
+                    if !self.constant_predicate_holds(left, op, right)? {
+                        prepared.push(PreparedWhereClause::Never);
+                    }
+                }
+                WhereClause::Or(alternatives) => {
+                    let mut prepared_alternatives = Vec::with_capacity(alternatives.len());
+                    for alternative in alternatives {
+                        let alternative = self.prepare_where_clauses(alternative)?;
+                        // An alternative with nothing left to check holds for every row
+                        if alternative.is_empty() {
+                            prepared_alternatives.clear();
+                            break;
+                        }
+                        prepared_alternatives.push(alternative);
+                    }
+                    if !prepared_alternatives.is_empty() {
+                        prepared.push(PreparedWhereClause::Or(prepared_alternatives));
+                    }
                 }
             }
         }
         Ok(prepared)
     }
 
+    /// Evaluate `left op right` between two literals, with the same
+    /// comparison rules (and NULL handling) as a column predicate
+    fn constant_predicate_holds(
+        &self,
+        left: &ParserValue,
+        op: &Operator,
+        right: &ParserValue,
+    ) -> DatabaseResult<bool> {
+        let no_columns = |tc: &TableColumn| -> DatabaseResult<RecordValue> {
+            Err(DatabaseError::TypeMismatch(format!(
+                "Unexpected column {} in a constant predicate",
+                tc.column
+            )))
+        };
+        let left = self.evaluate_expression(&Expression::Value(left.clone()), &no_columns)?;
+        let right = self.evaluate_expression(&Expression::Value(right.clone()), &no_columns)?;
+        Ok(self.compare_values(&left, op, &right))
+    }
+
+    /// Whether one of `where_clauses` is a constant predicate that is false
+    fn where_never_matches(&self, where_clauses: &[WhereClause]) -> DatabaseResult<bool> {
+        for clause in where_clauses {
+            if let WhereClause::Constant(left, op, right) = clause
+                && !self.constant_predicate_holds(left, op, right)?
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     fn evaluate_prepared_where(
         &self,
         record: &Record,
         schema: &TableSchema,
         where_clauses: &[PreparedWhereClause],
     ) -> DatabaseResult<bool> {
-        // All clauses must be true (AND logic)
+        // All clauses must be true (AND logic); an OR holds if any of its alternatives does
         for clause in where_clauses {
             match clause {
                 PreparedWhereClause::Op(col, op, expr) => {
@@ -3728,10 +7532,22 @@ impl DatabaseManager {
                             let data_type = &schema.columns[col_idx].data_type;
                             self.parser_value_to_record_value(v, data_type)?
                         }
-                        Expression::Column(_) => {
-                            return Err(DatabaseError::TypeMismatch(
-                                "Column expressions not yet supported".to_string(),
-                            ));
+                        Expression::Column(tc) => {
+                            let idx = self.resolve_single_column_index(schema, tc)?;
+                            self.check_column_comparison(
+                                col,
+                                &schema.columns[col_idx].data_type,
+                                tc,
+                                &schema.columns[idx].data_type,
+                            )?;
+                            record.get(idx).unwrap().clone()
+                        }
+                        Expression::Arithmetic(..) | Expression::Cast(..) => {
+                            let value = self.evaluate_expression(expr, &|tc| {
+                                let idx = self.resolve_single_column_index(schema, tc)?;
+                                Ok(record.get(idx).unwrap().clone())
+                            })?;
+                            self.coerce_to_column_type(value, &schema.columns[col_idx].data_type)?
                         }
                     };
 
@@ -3739,6 +7555,18 @@ impl DatabaseManager {
                         return Ok(false);
                     }
                 }
+                PreparedWhereClause::CastOp(col, ty, op, expr) => {
+                    let col_idx = self.resolve_single_column_index(schema, col)?;
+                    let left_val = self.cast_value(record.get(col_idx).unwrap(), *ty);
+                    let right_val = self.evaluate_expression(expr, &|tc| {
+                        let idx = self.resolve_single_column_index(schema, tc)?;
+                        Ok(record.get(idx).unwrap().clone())
+                    })?;
+                    let right_val = self.cast_value(&right_val, *ty);
+                    if !self.compare_values(&left_val, op, &right_val) {
+                        return Ok(false);
+                    }
+                }
                 PreparedWhereClause::Null(col) => {
                     let col_idx = self.resolve_single_column_index(schema, col)?;
                     if !record.get(col_idx).unwrap().is_null() {
@@ -3754,11 +7582,35 @@ impl DatabaseManager {
                 PreparedWhereClause::Like(col, regex) => {
                     let col_idx = self.resolve_single_column_index(schema, col)?;
                     let value = record.get(col_idx).unwrap();
-                    let matches = match value {
-                        RecordValue::String(s) => regex.is_match(s),
-                        _ => false,
-                    };
-                    if !matches {
+                    if !self.like_matches(regex, value) {
+                        return Ok(false);
+                    }
+                }
+                PreparedWhereClause::NotLike(col, regex) => {
+                    let col_idx = self.resolve_single_column_index(schema, col)?;
+                    let value = record.get(col_idx).unwrap();
+                    if value.is_null() || self.like_matches(regex, value) {
+                        return Ok(false);
+                    }
+                }
+                PreparedWhereClause::In(col, values) => {
+                    let col_idx = self.resolve_single_column_index(schema, col)?;
+                    let value = record.get(col_idx).unwrap();
+                    let data_type = &schema.columns[col_idx].data_type;
+                    if !self.in_list_matches(value, data_type, values)? {
+                        return Ok(false);
+                    }
+                }
+                PreparedWhereClause::Never => return Ok(false),
+                PreparedWhereClause::Or(alternatives) => {
+                    let mut any = false;
+                    for alternative in alternatives {
+                        if self.evaluate_prepared_where(record, schema, alternative)? {
+                            any = true;
+                            break;
+                        }
+                    }
+                    if !any {
                         return Ok(false);
                     }
                 }
@@ -3768,6 +7620,7 @@ impl DatabaseManager {
         Ok(true)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn join_value_and_type<'a>(
         &self,
         column: &TableColumn,
@@ -3791,6 +7644,7 @@ impl DatabaseManager {
         Ok((value, data_type))
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn evaluate_prepared_join_where(
         &self,
         left_record: &Record,
@@ -3800,83 +7654,104 @@ impl DatabaseManager {
         right_schema: &TableSchema,
         right_name: &str,
         where_clauses: &[PreparedWhereClause],
+    ) -> DatabaseResult<bool> {
+        self.evaluate_prepared_where_with(where_clauses, &|tc| {
+            self.join_value_and_type(
+                tc,
+                left_record,
+                left_schema,
+                left_name,
+                right_record,
+                right_schema,
+                right_name,
+            )
+        })
+    }
+
+    /// Evaluate `where_clauses` over the rows of a join, with `lookup`
+    /// giving the value and type behind each column reference
+    fn evaluate_prepared_where_with<'r>(
+        &self,
+        where_clauses: &[PreparedWhereClause],
+        lookup: &dyn Fn(&TableColumn) -> DatabaseResult<(&'r RecordValue, &'r DataType)>,
     ) -> DatabaseResult<bool> {
         for clause in where_clauses {
             match clause {
                 PreparedWhereClause::Op(col, op, expr) => {
-                    let (left_val, data_type) = self.join_value_and_type(
-                        col,
-                        left_record,
-                        left_schema,
-                        left_name,
-                        right_record,
-                        right_schema,
-                        right_name,
-                    )?;
+                    let (left_val, data_type) = lookup(col)?;
 
                     let right_val = match expr {
                         Expression::Value(v) => self.parser_value_to_record_value(v, data_type)?,
                         Expression::Column(tc) => {
-                            let (value, _) = self.join_value_and_type(
-                                tc,
-                                left_record,
-                                left_schema,
-                                left_name,
-                                right_record,
-                                right_schema,
-                                right_name,
-                            )?;
+                            let (value, right_type) = lookup(tc)?;
+                            self.check_column_comparison(col, data_type, tc, right_type)?;
                             value.clone()
                         }
+                        Expression::Arithmetic(..) | Expression::Cast(..) => {
+                            let value = self.evaluate_expression(expr, &|tc| {
+                                let (value, _) = lookup(tc)?;
+                                Ok(value.clone())
+                            })?;
+                            self.coerce_to_column_type(value, data_type)?
+                        }
                     };
 
                     if !self.compare_values(left_val, op, &right_val) {
                         return Ok(false);
                     }
                 }
+                PreparedWhereClause::CastOp(col, ty, op, expr) => {
+                    let value_of = |tc: &TableColumn| {
+                        let (value, _) = lookup(tc)?;
+                        Ok(value.clone())
+                    };
+                    let left_val = self.cast_value(&value_of(col)?, *ty);
+                    let right_val = self.evaluate_expression(expr, &value_of)?;
+                    let right_val = self.cast_value(&right_val, *ty);
+                    if !self.compare_values(&left_val, op, &right_val) {
+                        return Ok(false);
+                    }
+                }
                 PreparedWhereClause::Null(col) => {
-                    let (value, _) = self.join_value_and_type(
-                        col,
-                        left_record,
-                        left_schema,
-                        left_name,
-                        right_record,
-                        right_schema,
-                        right_name,
-                    )?;
+                    let (value, _) = lookup(col)?;
                     if !value.is_null() {
                         return Ok(false);
                     }
                 }
                 PreparedWhereClause::NotNull(col) => {
-                    let (value, _) = self.join_value_and_type(
-                        col,
-                        left_record,
-                        left_schema,
-                        left_name,
-                        right_record,
-                        right_schema,
-                        right_name,
-                    )?;
+                    let (value, _) = lookup(col)?;
                     if value.is_null() {
                         return Ok(false);
                     }
                 }
                 PreparedWhereClause::Like(col, regex) => {
-                    let (value, _) = self.join_value_and_type(
-                        col,
-                        left_record,
-                        left_schema,
-                        left_name,
-                        right_record,
-                        right_schema,
-                        right_name,
-                    )?;
-                    let matches = match value {
-                        RecordValue::String(s) => regex.is_match(s),
-                        _ => false,
-                    };
-                    if !matches {
+                    let (value, _) = lookup(col)?;
+                    if !self.like_matches(regex, value) {
+                        return Ok(false);
+                    }
+                }
+                PreparedWhereClause::NotLike(col, regex) => {
+                    let (value, _) = lookup(col)?;
+                    if value.is_null() || self.like_matches(regex, value) {
+                        return Ok(false);
+                    }
+                }
+                PreparedWhereClause::In(col, values) => {
+                    let (value, data_type) = lookup(col)?;
+                    if !self.in_list_matches(value, data_type, values)? {
+                        return Ok(false);
+                    }
+                }
+                PreparedWhereClause::Never => return Ok(false),
+                PreparedWhereClause::Or(alternatives) => {
+                    let mut any = false;
+                    for alternative in alternatives {
+                        if self.evaluate_prepared_where_with(alternative, lookup)? {
+                            any = true;
+                            break;
+                        }
+                    }
+                    if !any {
                         return Ok(false);
                     }
                 }
@@ -3886,12 +7761,199 @@ impl DatabaseManager {
         Ok(true)
     }
 
+    /// Evaluate an expression to a value, resolving column references through `lookup`.
+    fn evaluate_expression(
+        &self,
+        expr: &Expression,
+        lookup: &dyn Fn(&TableColumn) -> DatabaseResult<RecordValue>,
+    ) -> DatabaseResult<RecordValue> {
+        match expr {
+            Expression::Value(ParserValue::Integer(i)) => i32::try_from(*i)
+                .map(RecordValue::Int)
+                .map_err(|_| DatabaseError::TypeMismatch(format!("Integer {} out of range", i))),
+            Expression::Value(ParserValue::Placeholder(n)) => {
+                Err(DatabaseError::UnboundParameter(*n))
+            }
+            Expression::Value(value) => Ok(RecordValue::from_parser_value(value)),
+            Expression::Column(column) => lookup(column),
+            Expression::Arithmetic(left, op, right) => {
+                let left = self.evaluate_expression(left, lookup)?;
+                let right = self.evaluate_expression(right, lookup)?;
+                self.apply_arithmetic(&left, op, &right)
+            }
+            Expression::Cast(inner, ty) => {
+                let value = self.evaluate_expression(inner, lookup)?;
+                Ok(self.cast_value(&value, *ty))
+            }
+        }
+    }
+
+    /// Strings are parsed (NULL when they are not a number), FLOAT and
+    /// DECIMAL to INT truncate toward zero (NULL when out of range) and INT
+    /// to FLOAT widens
+    fn cast_value(&self, value: &RecordValue, ty: CastType) -> RecordValue {
+        match (value, ty) {
+            (RecordValue::Null, _) => RecordValue::Null,
+            (RecordValue::Int(i), CastType::Int) => RecordValue::Int(*i),
+            (RecordValue::Int(i), CastType::Float) => RecordValue::Float(f64::from(*i)),
+            (RecordValue::Float(f), CastType::Int) => {
+                let truncated = f.trunc();
+                if truncated >= f64::from(i32::MIN) && truncated <= f64::from(i32::MAX) {
+                    RecordValue::Int(truncated as i32)
+                } else {
+                    RecordValue::Null
+                }
+            }
+            (RecordValue::Float(f), CastType::Float) => RecordValue::Float(*f),
+            (RecordValue::Decimal(units, scale), CastType::Int) => {
+                let truncated = units / 10i64.pow(u32::from(*scale));
+                i32::try_from(truncated).map_or(RecordValue::Null, RecordValue::Int)
+            }
+            (RecordValue::Decimal(units, scale), CastType::Float) => {
+                RecordValue::Float(decimal_to_f64(*units, *scale))
+            }
+            (RecordValue::Bool(b), CastType::Int) => RecordValue::Int(i32::from(*b)),
+            (RecordValue::Bool(b), CastType::Float) => RecordValue::Float(f64::from(u8::from(*b))),
+            (RecordValue::String(s), CastType::Int) => {
+                s.trim().parse().map_or(RecordValue::Null, RecordValue::Int)
+            }
+            (RecordValue::String(s), CastType::Float) => match s.trim().parse::<f64>() {
+                Ok(f) if f.is_finite() => RecordValue::Float(f),
+                _ => RecordValue::Null,
+            },
+        }
+    }
+
+    /// A failed cast yields NULL, so the result is always nullable
+    fn cast_result_column(&self, column: &TableColumn, ty: CastType) -> ResultColumn {
+        let (type_name, data_type) = match ty {
+            CastType::Int => ("INT", DataType::Int),
+            CastType::Float => ("FLOAT", DataType::Float),
+        };
+        ResultColumn {
+            name: format!(
+                "CAST({} AS {})",
+                self.format_table_column_name(column),
+                type_name
+            ),
+            data_type,
+            nullable: true,
+        }
+    }
+
+    /// INT op INT stays integral (`/` truncates), anything involving a FLOAT is
+    /// computed in floating point. Division or modulo by zero yields NULL.
+    fn apply_arithmetic(
+        &self,
+        left: &RecordValue,
+        op: &ArithmeticOperator,
+        right: &RecordValue,
+    ) -> DatabaseResult<RecordValue> {
+        let as_float = |value: &RecordValue| match value {
+            RecordValue::Int(i) => Some(*i as f64),
+            RecordValue::Float(f) => Some(*f),
+            RecordValue::Decimal(units, scale) => Some(decimal_to_f64(*units, *scale)),
+            _ => None,
+        };
+
+        match (left, right) {
+            (RecordValue::Null, _) | (_, RecordValue::Null) => Ok(RecordValue::Null),
+            (RecordValue::Int(l), RecordValue::Int(r)) => {
+                let result = match op {
+                    ArithmeticOperator::Add => l.checked_add(*r),
+                    ArithmeticOperator::Sub => l.checked_sub(*r),
+                    ArithmeticOperator::Mul => l.checked_mul(*r),
+                    ArithmeticOperator::Div | ArithmeticOperator::Mod if *r == 0 => {
+                        return Ok(RecordValue::Null);
+                    }
+                    ArithmeticOperator::Div => l.checked_div(*r),
+                    ArithmeticOperator::Mod => l.checked_rem(*r),
+                };
+                result.map(RecordValue::Int).ok_or_else(|| {
+                    DatabaseError::TypeMismatch("Integer overflow in expression".to_string())
+                })
+            }
+            _ => {
+                let (Some(l), Some(r)) = (as_float(left), as_float(right)) else {
+                    return Err(DatabaseError::TypeMismatch(
+                        "Arithmetic requires numeric operands".to_string(),
+                    ));
+                };
+                let result = match op {
+                    ArithmeticOperator::Add => l + r,
+                    ArithmeticOperator::Sub => l - r,
+                    ArithmeticOperator::Mul => l * r,
+                    ArithmeticOperator::Div | ArithmeticOperator::Mod if r == 0.0 => {
+                        return Ok(RecordValue::Null);
+                    }
+                    ArithmeticOperator::Div => l / r,
+                    ArithmeticOperator::Mod => l % r,
+                };
+                Ok(RecordValue::Float(result))
+            }
+        }
+    }
+
+    fn coerce_to_column_type(
+        &self,
+        value: RecordValue,
+        data_type: &DataType,
+    ) -> DatabaseResult<RecordValue> {
+        match (value, data_type) {
+            (RecordValue::Int(i), DataType::Float) => Ok(RecordValue::Float(i as f64)),
+            (RecordValue::Float(_), DataType::Int) => Err(DatabaseError::TypeMismatch(
+                "Cannot compare float value with integer column".to_string(),
+            )),
+            (value, _) => Ok(value),
+        }
+    }
+
+    /// Reject a comparison between a string, boolean or numeric column and
+    /// one of another of those kinds, which could never hold; INT, FLOAT and
+    /// DECIMAL columns compare by value
+    fn check_column_comparison(
+        &self,
+        left: &TableColumn,
+        left_type: &DataType,
+        right: &TableColumn,
+        right_type: &DataType,
+    ) -> DatabaseResult<()> {
+        let kind = Self::type_kind;
+        let family = |data_type: &DataType| match data_type {
+            DataType::Char(_) | DataType::Varchar(_) => "string",
+            DataType::Bool => "boolean",
+            _ => "numeric",
+        };
+        if family(left_type) != family(right_type) {
+            return Err(DatabaseError::TypeMismatch(format!(
+                "Cannot compare {} column {} with {} column {}",
+                kind(left_type),
+                self.format_table_column_name(left),
+                kind(right_type),
+                self.format_table_column_name(right)
+            )));
+        }
+        Ok(())
+    }
+
+    /// How a column type is named in type mismatch errors
+    fn type_kind(data_type: &DataType) -> &'static str {
+        match data_type {
+            DataType::Int => "integer",
+            DataType::Float => "float",
+            DataType::Decimal(..) => "decimal",
+            DataType::Bool => "boolean",
+            DataType::Char(_) | DataType::Varchar(_) => "string",
+        }
+    }
+
     fn compare_values(&self, left: &RecordValue, op: &Operator, right: &RecordValue) -> bool {
         use std::cmp::Ordering;
 
         // Handle NULL comparisons
         if left.is_null() || right.is_null() {
-            return false; // NULL comparisons are always false
+            // <=> is the one comparison where NULL matches NULL
+            return *op == Operator::NullSafeEq && left.is_null() && right.is_null();
         }
 
         let cmp = match (left, right) {
@@ -3906,11 +7968,44 @@ impl DatabaseManager {
                 }
             }
             (RecordValue::String(l), RecordValue::String(r)) => l.cmp(r),
+            // FALSE sorts before TRUE
+            (RecordValue::Bool(l), RecordValue::Bool(r)) => l.cmp(r),
+            // An INT column compared with a FLOAT column
+            (RecordValue::Int(l), RecordValue::Float(r)) => match (*l as f64).partial_cmp(r) {
+                Some(ordering) => ordering,
+                None => return false,
+            },
+            (RecordValue::Float(l), RecordValue::Int(r)) => match l.partial_cmp(&(*r as f64)) {
+                Some(ordering) => ordering,
+                None => return false,
+            },
+            // Decimals compare exactly with decimals and integers
+            (RecordValue::Decimal(l, ls), RecordValue::Decimal(r, rs)) => {
+                compare_decimals(*l, *ls, *r, *rs)
+            }
+            (RecordValue::Decimal(l, ls), RecordValue::Int(r)) => {
+                compare_decimals(*l, *ls, i64::from(*r), 0)
+            }
+            (RecordValue::Int(l), RecordValue::Decimal(r, rs)) => {
+                compare_decimals(i64::from(*l), 0, *r, *rs)
+            }
+            (RecordValue::Decimal(l, ls), RecordValue::Float(r)) => {
+                match decimal_to_f64(*l, *ls).partial_cmp(r) {
+                    Some(ordering) => ordering,
+                    None => return false,
+                }
+            }
+            (RecordValue::Float(l), RecordValue::Decimal(r, rs)) => {
+                match l.partial_cmp(&decimal_to_f64(*r, *rs)) {
+                    Some(ordering) => ordering,
+                    None => return false,
+                }
+            }
             _ => return false, // Type mismatch
         };
 
         match op {
-            Operator::Eq => cmp == Ordering::Equal,
+            Operator::Eq | Operator::NullSafeEq => cmp == Ordering::Equal,
             Operator::Ne => cmp != Ordering::Equal,
             Operator::Lt => cmp == Ordering::Less,
             Operator::Le => cmp != Ordering::Greater,
@@ -3919,55 +8014,177 @@ impl DatabaseManager {
         }
     }
 
-    fn like_pattern_to_regex(&self, pattern: &str) -> String {
+    /// Compile a LIKE pattern, rejecting patterns longer than
+    /// [`MAX_LIKE_PATTERN_LEN`] characters or whose compiled form would exceed
+    /// [`LIKE_REGEX_SIZE_LIMIT`] bytes.
+    ///
+    /// Within the pattern, `escape` makes the character after it match
+    /// itself, so `50!%` with `ESCAPE '!'` matches a literal `50%`.
+    fn like_regex(&self, pattern: &str, escape: Option<char>) -> DatabaseResult<Regex> {
+        let len = pattern.chars().count();
+        if len > MAX_LIKE_PATTERN_LEN {
+            return Err(DatabaseError::TypeMismatch(format!(
+                "LIKE pattern is too long ({} characters, at most {} allowed)",
+                len, MAX_LIKE_PATTERN_LEN
+            )));
+        }
+        RegexBuilder::new(&self.like_pattern_to_regex(pattern, escape))
+            .size_limit(LIKE_REGEX_SIZE_LIMIT)
+            .build()
+            .map_err(|err| DatabaseError::TypeMismatch(format!("Invalid LIKE pattern: {}", err)))
+    }
+
+    fn like_pattern_to_regex(&self, pattern: &str, escape: Option<char>) -> String {
+        let push_literal = |regex: &mut String, ch: char| {
+            if matches!(
+                ch,
+                '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' | '\\'
+            ) {
+                regex.push('\\');
+            }
+            regex.push(ch);
+        };
+
         let mut regex = String::with_capacity(pattern.len() * 2 + 2);
         regex.push('^');
-        for ch in pattern.chars() {
+        let mut chars = pattern.chars();
+        while let Some(ch) = chars.next() {
             match ch {
+                // An escaped character is literal; a trailing escape stands for itself
+                _ if Some(ch) == escape => push_literal(&mut regex, chars.next().unwrap_or(ch)),
+                // A run of `%` matches the same strings as a single one
+                '%' if regex.ends_with(".*") => {}
                 '%' => regex.push_str(".*"),
                 '_' => regex.push('.'),
-                '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$'
-                | '\\' => {
-                    regex.push('\\');
-                    regex.push(ch);
-                }
-                _ => regex.push(ch),
+                _ => push_literal(&mut regex, ch),
             }
         }
         regex.push('$');
         regex
     }
 
+    fn primary_key_violation(&self, record: &Record, pk_indices: &[usize]) -> DatabaseError {
+        let values: Vec<String> = pk_indices
+            .iter()
+            .map(|&idx| self.format_value(record.get(idx).unwrap()))
+            .collect();
+        DatabaseError::PrimaryKeyViolation(format!("({})", values.join(", ")))
+    }
+
+    /// LIKE compares against the value's displayed text, so non-string
+    /// columns are coerced through `format_value`; NULL never matches
+    /// Whether `value` equals one of `values`; NULL matches nothing
+    fn in_list_matches(
+        &self,
+        value: &RecordValue,
+        data_type: &DataType,
+        values: &[ParserValue],
+    ) -> DatabaseResult<bool> {
+        if value.is_null() {
+            return Ok(false);
+        }
+        for candidate in values {
+            if matches!(candidate, ParserValue::Null) {
+                continue;
+            }
+            let candidate = self.parser_value_to_record_value(candidate, data_type)?;
+            if self.compare_values(value, &Operator::Eq, &candidate) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    fn like_matches(&self, regex: &Regex, value: &RecordValue) -> bool {
+        match value {
+            RecordValue::Null => false,
+            RecordValue::String(s) => regex.is_match(s),
+            other => regex.is_match(&self.format_value(other)),
+        }
+    }
+
+    fn format_projected_value(&self, value: &RecordValue, cast: Option<CastType>) -> String {
+        match cast {
+            Some(ty) => self.format_value(&self.cast_value(value, ty)),
+            None => self.format_value(value),
+        }
+    }
+
     fn format_value(&self, value: &RecordValue) -> String {
         match value {
             RecordValue::Null => "NULL".to_string(),
             RecordValue::Int(i) => i.to_string(),
             RecordValue::Float(f) => format!("{:.2}", f),
+            RecordValue::Decimal(units, scale) => format_decimal(*units, *scale),
+            RecordValue::Bool(b) => b.to_string(),
             RecordValue::String(s) => s.clone(),
         }
     }
 
+    pub fn execute_query(&mut self, query: Query) -> DatabaseResult<QueryResult> {
+        self.execute_query_typed(query).map(|(result, _)| result)
+    }
+
+    /// Like [`execute_query`](Self::execute_query), but a SELECT also
+    /// reports the type and nullability of each result column
+    pub fn execute_query_typed(
+        &mut self,
+        query: Query,
+    ) -> DatabaseResult<(QueryResult, Option<Vec<ResultColumn>>)> {
+        // Dropped on return, which takes the statement off the registry
+        let registered = self
+            .query_registry
+            .as_ref()
+            .zip(registry::describe(&query))
+            .map(|(registry, statement)| registry.register(statement));
+        self.running_query = registered
+            .as_ref()
+            .map(|registered| (registered.id, registered.cancelled.clone()));
+
+        let result = match query {
+            Query::TableStmt(TableStatement::Select(clause)) => {
+                self.select_typed(clause).map(|(columns, rows)| {
+                    let headers = columns.iter().map(|column| column.name.clone()).collect();
+                    (QueryResult::ResultSet(headers, rows), Some(columns))
+                })
+            }
+            Query::DBStmt(stmt) => self.execute_db_statement(stmt).map(|result| (result, None)),
+            Query::TableStmt(stmt) => self
+                .execute_table_statement(stmt)
+                .map(|result| (result, None)),
+            Query::AlterStmt(stmt) => self
+                .execute_alter_statement(stmt)
+                .map(|result| (result, None)),
+            Query::Null => Ok((QueryResult::Empty, None)),
+        };
+        self.running_query = None;
+        result
+    }
+
     pub fn execute_db_statement(&mut self, stmt: DBStatement) -> DatabaseResult<QueryResult> {
         match stmt {
             DBStatement::CreateDatabase(name) => {
+                self.reject_in_transaction("CREATE DATABASE")?;
                 self.create_database(&name)?;
                 Ok(QueryResult::Empty)
             }
             DBStatement::DropDatabase(name) => {
+                self.reject_in_transaction("DROP DATABASE")?;
                 self.drop_database(&name)?;
                 Ok(QueryResult::Empty)
             }
-            DBStatement::ShowDatabases => {
-                let dbs = self.show_databases()?;
+            DBStatement::ShowDatabases(pattern) => {
+                let dbs = self.show_databases(pattern.as_deref())?;
                 let rows = dbs.into_iter().map(|db| vec![db]).collect();
                 Ok(QueryResult::ResultSet(vec!["DATABASES".to_string()], rows))
             }
             DBStatement::UseDatabase(name) => {
+                self.reject_in_transaction("USE")?;
                 self.use_database(&name)?;
                 Ok(QueryResult::Empty)
             }
-            DBStatement::ShowTables => {
-                let tables = self.show_tables()?;
+            DBStatement::ShowTables(pattern) => {
+                let tables = self.show_tables(pattern.as_deref())?;
                 let rows = tables.into_iter().map(|t| vec![t]).collect();
                 Ok(QueryResult::ResultSet(vec!["TABLES".to_string()], rows))
             }
@@ -3975,16 +8192,63 @@ impl DatabaseManager {
                 // TODO: Implement show indexes
                 Ok(QueryResult::Empty)
             }
+            DBStatement::Flush => {
+                let pages = self.checkpoint()?;
+                Ok(QueryResult::ResultSet(
+                    vec!["PAGES".to_string()],
+                    vec![vec![pages.to_string()]],
+                ))
+            }
+            DBStatement::ShowStatus => {
+                let stats = self.buffer_stats();
+                let rows = [
+                    ("buffer_fetches", stats.fetches),
+                    ("buffer_hits", stats.hits),
+                    ("buffer_misses", stats.misses),
+                    ("buffer_evictions", stats.evictions),
+                    ("buffer_dirty_flushes", stats.dirty_flushes),
+                ]
+                .into_iter()
+                .map(|(name, value)| vec![name.to_string(), value.to_string()])
+                .collect();
+                Ok(QueryResult::ResultSet(
+                    vec!["VARIABLE".to_string(), "VALUE".to_string()],
+                    rows,
+                ))
+            }
+            DBStatement::FlushStatus => {
+                self.reset_buffer_stats();
+                Ok(QueryResult::Empty)
+            }
+            DBStatement::Begin => {
+                self.begin_transaction()?;
+                Ok(QueryResult::Empty)
+            }
+            DBStatement::Commit => {
+                self.commit_transaction()?;
+                Ok(QueryResult::Empty)
+            }
+            DBStatement::Rollback => {
+                self.rollback_transaction()?;
+                Ok(QueryResult::Empty)
+            }
         }
     }
 
     pub fn execute_table_statement(&mut self, stmt: TableStatement) -> DatabaseResult<QueryResult> {
         match stmt {
             TableStatement::CreateTable(name, fields) => {
+                self.reject_in_transaction("CREATE TABLE")?;
                 self.create_table(&name, fields)?;
                 Ok(QueryResult::Empty)
             }
+            TableStatement::CreateTableAs(name, clause) => {
+                self.reject_in_transaction("CREATE TABLE")?;
+                let count = self.create_table_as(&name, clause)?;
+                Ok(QueryResult::RowsAffected(count))
+            }
             TableStatement::DropTable(name) => {
+                self.reject_in_transaction("DROP TABLE")?;
                 self.drop_table(&name)?;
                 Ok(QueryResult::Empty)
             }
@@ -3992,6 +8256,10 @@ impl DatabaseManager {
                 let meta = self.describe_table(&name)?;
                 Ok(QueryResult::TableDescription(meta))
             }
+            TableStatement::ShowColumns(name, pattern) => {
+                let meta = self.show_columns(&name, pattern.as_deref())?;
+                Ok(QueryResult::TableDescription(meta))
+            }
             TableStatement::LoadDataInfile(path, table, delim) => {
                 let count = self.load_data_infile(&path, &table, delim)?;
                 Ok(QueryResult::RowsAffected(count))
@@ -4000,10 +8268,22 @@ impl DatabaseManager {
                 let count = self.insert(&table, rows)?;
                 Ok(QueryResult::RowsAffected(count))
             }
-            TableStatement::DeleteFrom(table, where_clauses) => {
+            TableStatement::Replace(table, rows) => {
+                let count = self.replace(&table, rows)?;
+                Ok(QueryResult::RowsAffected(count))
+            }
+            TableStatement::Upsert(table, rows, updates) => {
+                let count = self.upsert(&table, rows, updates)?;
+                Ok(QueryResult::RowsAffected(count))
+            }
+            TableStatement::DeleteFrom(table, where_clauses, false) => {
                 let count = self.delete(&table, where_clauses)?;
                 Ok(QueryResult::RowsAffected(count))
             }
+            TableStatement::DeleteFrom(table, where_clauses, true) => {
+                let (headers, rows) = self.delete_returning(&table, where_clauses)?;
+                Ok(QueryResult::ResultSet(headers, rows))
+            }
             TableStatement::Update(table, updates, where_clauses) => {
                 let count = self.update(&table, updates, where_clauses)?;
                 Ok(QueryResult::RowsAffected(count))
@@ -4012,18 +8292,81 @@ impl DatabaseManager {
                 let (headers, rows) = self.select(clause)?;
                 Ok(QueryResult::ResultSet(headers, rows))
             }
+            TableStatement::Explain(clause) => Ok(QueryResult::List(self.explain(clause)?)),
+            TableStatement::SelectIntoOutfile(clause, path, delimiter, header) => {
+                let count = self.select_into_outfile(clause, &path, delimiter, header)?;
+                Ok(QueryResult::RowsAffected(count))
+            }
+            TableStatement::ReindexTable(table) => {
+                self.reindex_table(&table)?;
+                Ok(QueryResult::Empty)
+            }
+            TableStatement::ReindexIndex(index_name) => {
+                self.reindex_index(&index_name)?;
+                Ok(QueryResult::Empty)
+            }
+            TableStatement::CheckTable(table) => {
+                let issues = self.check_table(&table)?;
+                let headers = vec![
+                    "INDEX".to_string(),
+                    "PROBLEM".to_string(),
+                    "KEY".to_string(),
+                    "RECORD".to_string(),
+                ];
+                let rows = if issues.is_empty() {
+                    vec![vec![
+                        table.clone(),
+                        "OK".to_string(),
+                        String::new(),
+                        String::new(),
+                    ]]
+                } else {
+                    issues
+                        .into_iter()
+                        .map(|issue| {
+                            let (problem, key, record) = match issue.kind {
+                                IndexCheckIssueKind::MissingEntry(key, rid) => (
+                                    "missing entry".to_string(),
+                                    key.to_string(),
+                                    format!("{}:{}", rid.page_id, rid.slot_id),
+                                ),
+                                IndexCheckIssueKind::DanglingPointer(key, rid) => (
+                                    "dangling pointer".to_string(),
+                                    key.to_string(),
+                                    format!("{}:{}", rid.page_id, rid.slot_id),
+                                ),
+                                IndexCheckIssueKind::Unreadable(msg) => {
+                                    (format!("unreadable: {}", msg), String::new(), String::new())
+                                }
+                            };
+                            vec![issue.index, problem, key, record]
+                        })
+                        .collect()
+                };
+                Ok(QueryResult::ResultSet(headers, rows))
+            }
         }
     }
 
     pub fn execute_alter_statement(&mut self, stmt: AlterStatement) -> DatabaseResult<QueryResult> {
+        self.reject_in_transaction("ALTER TABLE")?;
         match stmt {
-            AlterStatement::AddIndex(table_name, index_name, columns) => {
+            AlterStatement::AddIndex(table_name, index_name, columns, order) => {
                 if columns.is_empty() || columns.len() > 2 {
                     return Err(DatabaseError::TypeMismatch(
                         "Only one- or two-column indexes are supported".to_string(),
                     ));
                 }
 
+                if let Some(order) = order
+                    && !(3..=DEFAULT_ORDER).contains(&order)
+                {
+                    return Err(DatabaseError::TypeMismatch(format!(
+                        "Index order must be between 3 and {}, got {}",
+                        DEFAULT_ORDER, order
+                    )));
+                }
+
                 let index_name = if let Some(name) = index_name {
                     name
                 } else if columns.len() == 1 {
@@ -4073,18 +8416,16 @@ impl DatabaseManager {
                             .iter()
                             .position(|c| c.name == *col_name)
                             .ok_or_else(|| {
-                                DatabaseError::ColumnNotFound(
-                                    col_name.clone(),
-                                    table_name.clone(),
-                                )
+                                DatabaseError::ColumnNotFound(col_name.clone(), table_name.clone())
                             })?;
-                        if table_meta.columns[col_idx].to_data_type() != DataType::Int {
-                            return Err(DatabaseError::TypeMismatch(
-                                "Only INT columns can be indexed".to_string(),
-                            ));
-                        }
                         col_indices.push(col_idx);
                     }
+                    if !Self::indexable(&table_meta, &col_indices) {
+                        return Err(DatabaseError::TypeMismatch(
+                            "Only INT columns or a single VARCHAR column can be indexed"
+                                .to_string(),
+                        ));
+                    }
 
                     let schema = self.metadata_to_schema(&table_meta);
                     (schema, col_indices, implicit_on_column)
@@ -4097,98 +8438,75 @@ impl DatabaseManager {
                 let db_path = self.data_dir.join(db_name);
                 let db_path_str = db_path.to_string_lossy().to_string();
 
-                if reuse_implicit {
-                    match self
+                // An implicit index is reused as-is unless a different order was requested
+                let reopened = reuse_implicit
+                    && order.is_none()
+                    && match self
                         .index_manager
                         .open_index(&db_path_str, &table_name, &storage_name)
                     {
-                        Ok(()) => {}
-                        Err(IndexError::IndexNotFound(_)) => {
-                            let table_path = self.table_path(db_name, &table_name);
-                            self.record_manager.open_table(
-                                table_path.to_string_lossy().as_ref(),
-                                schema.clone(),
-                            )?;
-                            let scan_iter = self.record_manager.scan_iter(&table_name)?;
-                            if col_indices.len() == 1 {
-                                let table_iter = TableIntColumnIter::new(scan_iter, col_indices[0]);
-                                self.index_manager.create_index_from_table(
-                                    &db_path_str,
-                                    &table_name,
-                                    &storage_name,
-                                    table_iter,
-                                )?;
-                            } else {
-                                let table_iter = TableCompositeIntColumnIter::new(
-                                    scan_iter,
-                                    col_indices[0],
-                                    col_indices[1],
-                                );
-                                self.index_manager.create_index_from_table(
-                                    &db_path_str,
-                                    &table_name,
-                                    &storage_name,
-                                    table_iter,
-                                )?;
-                            }
-                        }
+                        Ok(()) => true,
+                        Err(IndexError::IndexNotFound(_)) => false,
                         Err(err) => return Err(DatabaseError::IndexError(err)),
-                    }
+                    };
 
-                    let metadata = self.current_metadata.as_mut().unwrap();
-                    let table_meta_mut = metadata.get_table_mut(&table_name)?;
-                    if let Some(idx_meta) = table_meta_mut
-                        .indexes
-                        .iter_mut()
-                        .find(|idx| idx.columns == columns && idx.implicit)
-                    {
-                        idx_meta.name = index_name;
-                        idx_meta.implicit = false;
-                    } else {
-                        table_meta_mut.indexes.push(IndexMetadata {
-                            name: index_name,
-                            columns: columns.clone(),
-                            implicit: false,
-                        });
+                if !reopened {
+                    if reuse_implicit {
+                        let _ =
+                            self.index_manager
+                                .drop_index(&db_path_str, &table_name, &storage_name);
                     }
-                    self.save_current_metadata()?;
-                    return Ok(QueryResult::Empty);
-                }
 
-                let table_path = self.table_path(db_name, &table_name);
-                self.record_manager
-                    .open_table(table_path.to_string_lossy().as_ref(), schema.clone())?;
+                    let table_path = self.table_path(db_name, &table_name);
+                    self.record_manager
+                        .open_table(table_path.to_string_lossy().as_ref(), schema.clone())?;
 
-                let scan_iter = self.record_manager.scan_iter(&table_name)?;
-                if col_indices.len() == 1 {
-                    let table_iter = TableIntColumnIter::new(scan_iter, col_indices[0]);
-                    self.index_manager.create_index_from_table(
-                        &db_path_str,
-                        &table_name,
-                        &storage_name,
-                        table_iter,
-                    )?;
-                } else {
-                    let table_iter = TableCompositeIntColumnIter::new(
-                        scan_iter,
-                        col_indices[0],
-                        col_indices[1],
-                    );
-                    self.index_manager.create_index_from_table(
-                        &db_path_str,
-                        &table_name,
-                        &storage_name,
-                        table_iter,
-                    )?;
+                    let btree_order = order.unwrap_or(DEFAULT_ORDER);
+                    let scan_iter = self.record_manager.scan_iter(&table_name)?;
+                    if col_indices.len() == 1 {
+                        let table_iter = TableColumnKeyIter::new(scan_iter, col_indices[0]);
+                        self.index_manager.create_index_from_table_with_order(
+                            &db_path_str,
+                            &table_name,
+                            &storage_name,
+                            btree_order,
+                            table_iter,
+                        )?;
+                    } else {
+                        let table_iter = TableCompositeIntColumnIter::new(
+                            scan_iter,
+                            col_indices[0],
+                            col_indices[1],
+                        );
+                        self.index_manager.create_index_from_table_with_order(
+                            &db_path_str,
+                            &table_name,
+                            &storage_name,
+                            btree_order,
+                            table_iter,
+                        )?;
+                    }
                 }
 
                 let metadata = self.current_metadata.as_mut().unwrap();
                 let table_meta_mut = metadata.get_table_mut(&table_name)?;
-                table_meta_mut.indexes.push(IndexMetadata {
-                    name: index_name,
-                    columns,
-                    implicit: false,
-                });
+                if let Some(idx_meta) = table_meta_mut
+                    .indexes
+                    .iter_mut()
+                    .find(|idx| idx.columns == columns && idx.implicit)
+                {
+                    idx_meta.name = index_name;
+                    idx_meta.implicit = false;
+                    idx_meta.order = order;
+                } else {
+                    table_meta_mut.indexes.push(IndexMetadata {
+                        name: index_name,
+                        columns,
+                        implicit: false,
+                        order,
+                        unique: false,
+                    });
+                }
                 self.save_current_metadata()?;
 
                 Ok(QueryResult::Empty)
@@ -4227,11 +8545,12 @@ impl DatabaseManager {
                         "Only one- or two-column indexes are supported".to_string(),
                     ));
                 }
-                let storage_name = Self::index_storage_name(&index_meta.columns).ok_or_else(|| {
-                    DatabaseError::TypeMismatch(
-                        "Only one- or two-column indexes are supported".to_string(),
-                    )
-                })?;
+                let storage_name =
+                    Self::index_storage_name(&index_meta.columns).ok_or_else(|| {
+                        DatabaseError::TypeMismatch(
+                            "Only one- or two-column indexes are supported".to_string(),
+                        )
+                    })?;
 
                 if let Err(err) =
                     self.index_manager
@@ -4289,18 +8608,23 @@ impl DatabaseManager {
                     .record_manager
                     .open_table(table_path.to_string_lossy().as_ref(), schema);
 
+                // Key columns become NOT NULL, so existing rows must already
+                // satisfy that before the catalog claims it
                 let scan_iter = self.record_manager.scan_iter(&table_name)?;
                 let mut pk_set = HashSet::new();
                 for item in scan_iter {
                     let (_rid, record) = item?;
                     let mut key_parts = Vec::with_capacity(pk_indices.len());
-                    for &idx in &pk_indices {
+                    for (col_name, &idx) in columns.iter().zip(&pk_indices) {
                         let value = record.get(idx).unwrap();
+                        if value.is_null() {
+                            return Err(DatabaseError::NotNullViolation(col_name.clone()));
+                        }
                         key_parts.push(format!("{:?}", value));
                     }
 
                     if !pk_set.insert(key_parts.join("|")) {
-                        return Err(DatabaseError::PrimaryKeyViolation);
+                        return Err(self.primary_key_violation(&record, &pk_indices));
                     }
                 }
 
@@ -4323,11 +8647,34 @@ impl DatabaseManager {
                     .ok_or(DatabaseError::NoDatabaseSelected)?;
                 let table_meta = metadata.get_table_mut(&table_name)?;
 
-                if table_meta.primary_key.is_none() {
-                    return Err(DatabaseError::PrimaryKeyError);
+                let pk_cols = table_meta
+                    .primary_key
+                    .take()
+                    .ok_or(DatabaseError::PrimaryKeyError)?;
+
+                // The primary key's index file is shared with any index on the
+                // same columns, including the implicit ones foreign keys need,
+                // so it is only removed when nothing else is backed by it
+                if let Some(storage_name) = Self::index_storage_name(&pk_cols)
+                    && !table_meta.indexes.iter().any(|idx| {
+                        Self::index_storage_name(&idx.columns).as_ref() == Some(&storage_name)
+                    })
+                {
+                    let db_name = self
+                        .current_db
+                        .as_ref()
+                        .ok_or(DatabaseError::NoDatabaseSelected)?;
+                    let db_path = self.data_dir.join(db_name);
+                    if let Err(err) = self.index_manager.drop_index(
+                        &db_path.to_string_lossy(),
+                        &table_name,
+                        &storage_name,
+                    ) && !matches!(err, crate::index::IndexError::IndexNotFound(_))
+                    {
+                        return Err(DatabaseError::IndexError(err));
+                    }
                 }
 
-                table_meta.primary_key = None;
                 self.save_current_metadata()?;
                 Ok(QueryResult::Empty)
             }
@@ -4365,6 +8712,7 @@ impl DatabaseManager {
                 };
 
                 let fk_check = self.build_foreign_key_check(&table_meta, &fk_meta)?;
+                self.check_foreign_key_cycle(&table_meta, &fk_meta)?;
                 let db_name = self
                     .current_db
                     .as_ref()
@@ -4403,6 +8751,10 @@ impl DatabaseManager {
 
                 Ok(QueryResult::Empty)
             }
+            AlterStatement::ModifyColumn(table_name, column, column_type) => {
+                let rewritten = self.modify_column(&table_name, &column, column_type)?;
+                Ok(QueryResult::RowsAffected(rewritten))
+            }
             AlterStatement::DropFKey(table_name, fk_name) => {
                 let mut table_meta = {
                     let metadata = self
@@ -4439,6 +8791,57 @@ impl DatabaseManager {
     }
 }
 
+/// A discrepancy between a table and one of its indexes found by `check_table`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexCheckIssue {
+    pub index: String,
+    pub kind: IndexCheckIssueKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IndexCheckIssueKind {
+    /// A live record whose key is not present in the index
+    MissingEntry(i64, RecordId),
+    /// An index entry that does not point at a live record with that key
+    DanglingPointer(i64, RecordId),
+    /// The index file could not be opened
+    Unreadable(String),
+}
+
+/// A column of a SELECT result
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultColumn {
+    pub name: String,
+    pub data_type: DataType,
+    pub nullable: bool,
+}
+
+impl ResultColumn {
+    fn from_column(name: String, column: &ColumnDef) -> Self {
+        Self {
+            name,
+            data_type: column.data_type.clone(),
+            nullable: !column.not_null,
+        }
+    }
+}
+
+/// A table row from [`DatabaseManager::scan_table`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedRow {
+    pub rid: RecordId,
+    pub columns: Arc<[String]>,
+    pub values: Vec<RecordValue>,
+}
+
+impl TypedRow {
+    /// Get a value by column name
+    pub fn get(&self, column: &str) -> Option<&RecordValue> {
+        let idx = self.columns.iter().position(|name| name == column)?;
+        self.values.get(idx)
+    }
+}
+
 #[derive(Debug)]
 pub enum QueryResult {
     Empty,
@@ -4448,5 +8851,14 @@ pub enum QueryResult {
     TableDescription(TableMetadata),
 }
 
+impl Drop for DatabaseManager {
+    fn drop(&mut self) {
+        // A transaction never committed is rolled back, as after a crash
+        if self.transaction.is_some() {
+            let _ = self.rollback_transaction();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests;
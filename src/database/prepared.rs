@@ -0,0 +1,180 @@
+//! Prepared statements with `?` parameters
+
+use super::{DatabaseError, DatabaseManager, DatabaseResult, QueryResult};
+use crate::lexer_parser::{
+    CreateTableField, DefaultValue, Expression, Query, SelectClause, TableStatement,
+    Value as ParserValue, WhereClause,
+};
+use crate::record::{Value as RecordValue, format_decimal};
+
+/// A statement parsed once by [`DatabaseManager::prepare`] and executed any
+/// number of times with different parameters
+///
+/// ```ignore
+/// let mut lookup = db.prepare("SELECT * FROM t WHERE id = ?;")?;
+/// for id in 0..100 {
+///     let result = lookup.execute(&[Value::Int(id)])?;
+/// }
+/// ```
+pub struct PreparedStatement<'a> {
+    db: &'a mut DatabaseManager,
+    query: Query,
+    param_count: usize,
+}
+
+impl DatabaseManager {
+    /// Parse a single statement whose values may be `?` placeholders
+    ///
+    /// Placeholders can stand wherever a literal value is accepted: in
+    /// `VALUES` lists, `SET` assignments, `DEFAULT` clauses and on the right
+    /// of `WHERE` conditions and `IN` lists. They are bound in order of
+    /// appearance.
+    pub fn prepare(&mut self, sql: &str) -> DatabaseResult<PreparedStatement<'_>> {
+        let mut queries: Vec<Query> = crate::lexer_parser::parse(sql)
+            .map_err(DatabaseError::ParseError)?
+            .into_iter()
+            .filter(|query| *query != Query::Null)
+            .collect();
+        if queries.len() != 1 {
+            return Err(DatabaseError::ParseError(format!(
+                "Expected exactly one statement, found {}",
+                queries.len()
+            )));
+        }
+        let mut query = queries.remove(0);
+
+        let mut param_count = 0;
+        visit_values(&mut query, &mut |value| {
+            if matches!(value, ParserValue::Placeholder(_)) {
+                param_count += 1;
+            }
+        });
+
+        Ok(PreparedStatement {
+            db: self,
+            query,
+            param_count,
+        })
+    }
+}
+
+impl PreparedStatement<'_> {
+    /// Number of `?` placeholders in the statement
+    pub fn param_count(&self) -> usize {
+        self.param_count
+    }
+
+    /// Bind `params` to the placeholders in order and execute the statement
+    pub fn execute(&mut self, params: &[RecordValue]) -> DatabaseResult<QueryResult> {
+        if params.len() != self.param_count {
+            return Err(DatabaseError::ParameterCount(
+                self.param_count,
+                params.len(),
+            ));
+        }
+
+        let mut query = self.query.clone();
+        visit_values(&mut query, &mut |value| {
+            if let ParserValue::Placeholder(n) = *value {
+                *value = match &params[n] {
+                    RecordValue::Int(i) => ParserValue::Integer(*i as i64),
+                    RecordValue::Float(f) => ParserValue::Float(*f),
+                    RecordValue::Decimal(units, scale) => {
+                        ParserValue::String(format_decimal(*units, *scale))
+                    }
+                    RecordValue::Bool(b) => ParserValue::Bool(*b),
+                    RecordValue::String(s) => ParserValue::String(s.clone()),
+                    RecordValue::Null => ParserValue::Null,
+                };
+            }
+        });
+        self.db.execute_query(query)
+    }
+}
+
+/// Call `f` on every literal value of `query`
+fn visit_values(query: &mut Query, f: &mut dyn FnMut(&mut ParserValue)) {
+    let Query::TableStmt(stmt) = query else {
+        return;
+    };
+    match stmt {
+        TableStatement::CreateTable(_, fields) => {
+            for field in fields {
+                if let CreateTableField::Col(_, _, _, Some(DefaultValue::Literal(default)), _) =
+                    field
+                {
+                    f(default);
+                }
+            }
+        }
+        TableStatement::CreateTableAs(_, clause)
+        | TableStatement::Select(clause)
+        | TableStatement::Explain(clause)
+        | TableStatement::SelectIntoOutfile(clause, ..) => visit_select(clause, f),
+        TableStatement::InsertInto(_, rows) | TableStatement::Replace(_, rows) => {
+            rows.iter_mut().flatten().for_each(f)
+        }
+        TableStatement::Upsert(_, rows, updates) => {
+            rows.iter_mut().flatten().for_each(&mut *f);
+            updates.iter_mut().for_each(|(_, value)| f(value));
+        }
+        TableStatement::DeleteFrom(_, where_clauses, _) => {
+            visit_where(where_clauses.iter_mut().flatten(), f)
+        }
+        TableStatement::Update(_, updates, where_clauses) => {
+            updates.iter_mut().for_each(|(_, value)| f(value));
+            visit_where(where_clauses.iter_mut().flatten(), f);
+        }
+        TableStatement::DropTable(_)
+        | TableStatement::DescribeTable(_)
+        | TableStatement::ShowColumns(_, _)
+        | TableStatement::LoadDataInfile(_, _, _)
+        | TableStatement::ReindexTable(_)
+        | TableStatement::ReindexIndex(_)
+        | TableStatement::CheckTable(_) => {}
+    }
+}
+
+fn visit_select(clause: &mut SelectClause, f: &mut dyn FnMut(&mut ParserValue)) {
+    visit_where(clause.where_clauses.iter_mut(), f);
+    for (_, _, value) in &mut clause.having {
+        f(value);
+    }
+}
+
+fn visit_where<'a>(
+    clauses: impl Iterator<Item = &'a mut WhereClause>,
+    f: &mut dyn FnMut(&mut ParserValue),
+) {
+    for clause in clauses {
+        match clause {
+            WhereClause::Op(_, _, expr) | WhereClause::CastOp(_, _, _, expr) => {
+                visit_expression(expr, f)
+            }
+            WhereClause::In(_, values) => values.iter_mut().for_each(&mut *f),
+            WhereClause::Constant(left, _, right) => {
+                f(left);
+                f(right);
+            }
+            WhereClause::Or(alternatives) => {
+                visit_where(alternatives.iter_mut().flatten(), f);
+            }
+            WhereClause::Null(_)
+            | WhereClause::NotNull(_)
+            | WhereClause::Like(..)
+            | WhereClause::NotLike(..) => {}
+        }
+    }
+}
+
+fn visit_expression(expr: &mut Expression, f: &mut dyn FnMut(&mut ParserValue)) {
+    match expr {
+        Expression::Value(value) => f(value),
+        Expression::Column(_) => {}
+        Expression::Arithmetic(left, _, right) => {
+            visit_expression(left, f);
+            visit_expression(right, f);
+        }
+        Expression::Cast(inner, _) => visit_expression(inner, f),
+    }
+}
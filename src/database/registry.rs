@@ -0,0 +1,161 @@
+//! Registry of running statements, for listing and cancelling them from other threads
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::lexer_parser::{AlterStatement, Query, TableStatement};
+
+/// In-flight statements of every [`DatabaseManager`](super::DatabaseManager)
+/// sharing this registry
+///
+/// ```ignore
+/// let registry = Arc::new(QueryRegistry::new());
+/// db.set_query_registry(Some(registry.clone()));
+/// // From another connection's thread:
+/// for query in registry.list_running() {
+///     if query.elapsed > Duration::from_secs(60) {
+///         registry.cancel(query.id);
+///     }
+/// }
+/// ```
+#[derive(Default)]
+pub struct QueryRegistry {
+    next_id: AtomicU64,
+    running: Mutex<HashMap<u64, Entry>>,
+}
+
+struct Entry {
+    statement: String,
+    started: Instant,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Snapshot of one running statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunningQuery {
+    pub id: u64,
+    /// Statement kind and the tables it touches, e.g. `UPDATE t`
+    pub statement: String,
+    pub elapsed: Duration,
+}
+
+impl QueryRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Statements currently running, oldest first
+    pub fn list_running(&self) -> Vec<RunningQuery> {
+        let running = self.running.lock().unwrap();
+        let mut queries: Vec<RunningQuery> = running
+            .iter()
+            .map(|(id, entry)| RunningQuery {
+                id: *id,
+                statement: entry.statement.clone(),
+                elapsed: entry.started.elapsed(),
+            })
+            .collect();
+        queries.sort_by_key(|query| query.id);
+        queries
+    }
+
+    /// Ask a running statement to stop
+    ///
+    /// Cancellation is cooperative: the statement fails with
+    /// [`DatabaseError::Cancelled`](super::DatabaseError::Cancelled) the next
+    /// time its scan loop checks in. Returns false if no statement has this id.
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.running.lock().unwrap().get(&id) {
+            Some(entry) => {
+                entry.cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(super) fn register(self: &Arc<Self>, statement: String) -> RegisteredQuery {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.running.lock().unwrap().insert(
+            id,
+            Entry {
+                statement,
+                started: Instant::now(),
+                cancelled: cancelled.clone(),
+            },
+        );
+        RegisteredQuery {
+            registry: self.clone(),
+            id,
+            cancelled,
+        }
+    }
+}
+
+/// A statement's registration, removed from the registry when dropped
+pub(super) struct RegisteredQuery {
+    registry: Arc<QueryRegistry>,
+    pub(super) id: u64,
+    pub(super) cancelled: Arc<AtomicBool>,
+}
+
+impl Drop for RegisteredQuery {
+    fn drop(&mut self) {
+        self.registry.running.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Label a statement for [`QueryRegistry::list_running`]; `None` for statements
+/// too quick to be worth registering
+pub(super) fn describe(query: &Query) -> Option<String> {
+    let statement = match query {
+        Query::TableStmt(stmt) => match stmt {
+            TableStatement::Select(clause) => format!("SELECT FROM {}", clause.table.join(", ")),
+            TableStatement::SelectIntoOutfile(clause, path, ..) => format!(
+                "SELECT FROM {} INTO OUTFILE '{}'",
+                clause.table.join(", "),
+                path
+            ),
+            TableStatement::Explain(clause) => {
+                format!("EXPLAIN SELECT FROM {}", clause.table.join(", "))
+            }
+            TableStatement::CreateTable(name, _) => format!("CREATE TABLE {}", name),
+            TableStatement::CreateTableAs(name, clause) => format!(
+                "CREATE TABLE {} AS SELECT FROM {}",
+                name,
+                clause.table.join(", ")
+            ),
+            TableStatement::DropTable(name) => format!("DROP TABLE {}", name),
+            TableStatement::DescribeTable(name) => format!("DESC {}", name),
+            TableStatement::ShowColumns(name, _) => format!("SHOW COLUMNS FROM {}", name),
+            TableStatement::InsertInto(name, _) => format!("INSERT INTO {}", name),
+            TableStatement::Replace(name, _) => format!("REPLACE INTO {}", name),
+            TableStatement::Upsert(name, ..) => format!("INSERT INTO {}", name),
+            TableStatement::DeleteFrom(name, ..) => format!("DELETE FROM {}", name),
+            TableStatement::Update(name, _, _) => format!("UPDATE {}", name),
+            TableStatement::LoadDataInfile(file, name, _) => {
+                format!("LOAD DATA INFILE '{}' INTO TABLE {}", file, name)
+            }
+            TableStatement::ReindexTable(name) => format!("REINDEX TABLE {}", name),
+            TableStatement::ReindexIndex(name) => format!("REINDEX INDEX {}", name),
+            TableStatement::CheckTable(name) => format!("CHECK TABLE {}", name),
+        },
+        Query::AlterStmt(stmt) => {
+            let name = match stmt {
+                AlterStatement::AddIndex(name, ..)
+                | AlterStatement::DropIndex(name, _)
+                | AlterStatement::DropPKey(name, _)
+                | AlterStatement::DropFKey(name, _)
+                | AlterStatement::AddPKey(name, _)
+                | AlterStatement::AddFKey(name, ..)
+                | AlterStatement::ModifyColumn(name, ..) => name,
+            };
+            format!("ALTER TABLE {}", name)
+        }
+        Query::DBStmt(_) | Query::Null => return None,
+    };
+    Some(statement)
+}
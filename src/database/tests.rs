@@ -1,7 +1,7 @@
 use super::*;
 use crate::lexer_parser::{
-    AlterStatement, ColumnType, CreateTableField, Expression, Operator, SelectClause, Selector,
-    Selectors, TableColumn, WhereClause,
+    AlterStatement, ArithmeticOperator, ColumnType, CreateTableField, Expression, Operator,
+    SelectClause, Selector, Selectors, TableColumn, WhereClause,
 };
 use tempfile::TempDir;
 
@@ -11,12 +11,30 @@ fn setup_test_db() -> (TempDir, DatabaseManager) {
     (temp_dir, db_manager)
 }
 
+fn run(db: &mut DatabaseManager, sql: &str) -> DatabaseResult<QueryResult> {
+    db.execute_query(crate::lexer_parser::parse(sql).unwrap().remove(0))
+}
+
+/// Run a statement that must return a result set, returning its rows
+fn select_rows(db: &mut DatabaseManager, sql: &str) -> Vec<Vec<String>> {
+    select_with_headers(db, sql).1
+}
+
+/// Run a statement that must return a result set, returning its column
+/// names and rows
+fn select_with_headers(db: &mut DatabaseManager, sql: &str) -> (Vec<String>, Vec<Vec<String>>) {
+    match run(db, sql).unwrap() {
+        QueryResult::ResultSet(headers, rows) => (headers, rows),
+        other => panic!("not a result set: {:?}", other),
+    }
+}
+
 #[test]
 fn test_create_and_show_databases() {
     let (_temp, mut db_manager) = setup_test_db();
 
     // Initially no databases
-    let dbs = db_manager.show_databases().unwrap();
+    let dbs = db_manager.show_databases(None).unwrap();
     assert_eq!(dbs.len(), 0);
 
     // Create databases
@@ -25,7 +43,7 @@ fn test_create_and_show_databases() {
     db_manager.create_database("db3").unwrap();
 
     // Show databases
-    let dbs = db_manager.show_databases().unwrap();
+    let dbs = db_manager.show_databases(None).unwrap();
     assert_eq!(dbs.len(), 3);
     assert!(dbs.contains(&"db1".to_string()));
     assert!(dbs.contains(&"db2".to_string()));
@@ -36,6 +54,35 @@ fn test_create_and_show_databases() {
     assert!(result.is_err());
 }
 
+#[test]
+fn test_show_listings_sort_case_insensitively() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    for db in ["Cherry", "apple", "Banana", "Apple"] {
+        db_manager.create_database(db).unwrap();
+    }
+    assert_eq!(
+        db_manager.show_databases(None).unwrap(),
+        vec!["Apple", "apple", "Banana", "Cherry"]
+    );
+
+    db_manager.use_database("apple").unwrap();
+    for table in ["zeta", "Beta", "alpha"] {
+        let fields = vec![CreateTableField::Col(
+            "id".to_string(),
+            ColumnType::Int,
+            false,
+            None,
+            false,
+        )];
+        db_manager.create_table(table, fields).unwrap();
+    }
+    assert_eq!(
+        db_manager.show_tables(None).unwrap(),
+        vec!["alpha", "Beta", "zeta"]
+    );
+}
+
 #[test]
 fn test_use_database() {
     let (_temp, mut db_manager) = setup_test_db();
@@ -63,7 +110,7 @@ fn test_drop_database() {
     // Drop database
     db_manager.drop_database("db1").unwrap();
 
-    let dbs = db_manager.show_databases().unwrap();
+    let dbs = db_manager.show_databases(None).unwrap();
     assert_eq!(dbs.len(), 1);
     assert!(!dbs.contains(&"db1".to_string()));
     assert!(dbs.contains(&"db2".to_string()));
@@ -73,6 +120,75 @@ fn test_drop_database() {
     assert!(result.is_err());
 }
 
+#[test]
+fn test_in_memory_database() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    db_manager.create_database("memdb").unwrap();
+    assert!(db_manager.create_database("memdb").is_err());
+    db_manager.use_database("memdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
+            "test".to_string(),
+            vec!["a".to_string()],
+        ))),
+    ];
+    db_manager.create_table("test", fields.clone()).unwrap();
+
+    let rows = (0..500)
+        .map(|i| vec![ParserValue::Integer(i), ParserValue::Integer(i % 7)])
+        .collect();
+    db_manager.insert("test", rows).unwrap();
+    db_manager
+        .execute_alter_statement(AlterStatement::AddIndex(
+            "test".to_string(),
+            Some("idx_b".to_string()),
+            vec!["b".to_string()],
+            None,
+        ))
+        .unwrap();
+
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![WhereClause::Op(
+            TableColumn {
+                table: None,
+                column: "b".to_string(),
+            },
+            Operator::Eq,
+            Expression::Value(ParserValue::Integer(3)),
+        )],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+    let (_, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows.len(), 71);
+
+    // The catalog survives switching databases
+    db_manager.create_database("other").unwrap();
+    db_manager.use_database("other").unwrap();
+    db_manager.use_database("memdb").unwrap();
+    assert_eq!(db_manager.show_tables(None).unwrap(), vec!["test"]);
+
+    db_manager.drop_table("test").unwrap();
+    db_manager.create_table("test", fields).unwrap();
+
+    db_manager.drop_database("memdb").unwrap();
+    assert_eq!(db_manager.show_databases(None).unwrap(), vec!["other"]);
+    assert!(db_manager.use_database("memdb").is_err());
+    assert!(!db_manager.data_dir.exists());
+}
+
 #[test]
 fn test_create_table() {
     let (_temp, mut db_manager) = setup_test_db();
@@ -83,25 +199,21 @@ fn test_create_table() {
 
     // Create table
     let fields = vec![
-        CreateTableField::Col("id".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "name".to_string(),
-            ColumnType::Char(20),
-            true,
-            ParserValue::Null,
-        ),
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("name".to_string(), ColumnType::Char(20), true, None, false),
         CreateTableField::Col(
             "score".to_string(),
             ColumnType::Float,
             false,
-            ParserValue::Float(0.0),
+            Some(ParserDefault::Literal(ParserValue::Float(0.0))),
+            false,
         ),
     ];
 
     db_manager.create_table("students", fields).unwrap();
 
     // Show tables
-    let tables = db_manager.show_tables().unwrap();
+    let tables = db_manager.show_tables(None).unwrap();
     assert_eq!(tables.len(), 1);
     assert!(tables.contains(&"students".to_string()));
 
@@ -121,7 +233,8 @@ fn test_create_table_without_database() {
         "id".to_string(),
         ColumnType::Int,
         true,
-        ParserValue::Null,
+        None,
+        false,
     )];
 
     let result = db_manager.create_table("test", fields);
@@ -139,7 +252,8 @@ fn test_drop_table() {
         "id".to_string(),
         ColumnType::Int,
         true,
-        ParserValue::Null,
+        None,
+        false,
     )];
 
     db_manager.create_table("table1", fields.clone()).unwrap();
@@ -148,7 +262,7 @@ fn test_drop_table() {
     // Drop table
     db_manager.drop_table("table1").unwrap();
 
-    let tables = db_manager.show_tables().unwrap();
+    let tables = db_manager.show_tables(None).unwrap();
     assert_eq!(tables.len(), 1);
     assert!(tables.contains(&"table2".to_string()));
 }
@@ -161,14 +275,9 @@ fn test_insert_and_select() {
     db_manager.use_database("testdb").unwrap();
 
     let fields = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "b".to_string(),
-            ColumnType::Char(10),
-            true,
-            ParserValue::Null,
-        ),
-        CreateTableField::Col("c".to_string(), ColumnType::Float, true, ParserValue::Null),
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Char(10), true, None, false),
+        CreateTableField::Col("c".to_string(), ColumnType::Float, true, None, false),
     ];
 
     db_manager.create_table("test", fields).unwrap();
@@ -204,10 +313,14 @@ fn test_insert_and_select() {
     let clause = SelectClause {
         selectors: Selectors::All,
         table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
         where_clauses: vec![],
-        group_by: None,
-        order_by: None,
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
         limit: None,
+        limit_percent: false,
         offset: None,
     };
 
@@ -219,6 +332,56 @@ fn test_insert_and_select() {
     assert_eq!(rows[2], vec!["3", "rust", "3.50"]);
 }
 
+#[test]
+fn test_insert_numeric_literal_bounds() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Float, false, None, false),
+    ];
+    db_manager.create_table("test", fields).unwrap();
+
+    let rows = vec![
+        vec![
+            ParserValue::Integer(i32::MAX as i64),
+            ParserValue::Float(-3.5e10),
+        ],
+        vec![
+            ParserValue::Integer(i32::MIN as i64),
+            ParserValue::Float(-0.0),
+        ],
+    ];
+    assert_eq!(db_manager.insert("test", rows).unwrap(), 2);
+
+    // Out-of-range integers are rejected instead of being truncated
+    for value in [i32::MAX as i64 + 1, i32::MIN as i64 - 1, i64::MAX] {
+        let rows = vec![vec![ParserValue::Integer(value), ParserValue::Null]];
+        assert!(db_manager.insert("test", rows).is_err());
+    }
+
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+    let (_, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0][0], "2147483647");
+    assert_eq!(rows[1][0], "-2147483648");
+}
+
 #[test]
 fn test_select_aggregate_no_group() {
     let (_temp, mut db_manager) = setup_test_db();
@@ -227,14 +390,9 @@ fn test_select_aggregate_no_group() {
     db_manager.use_database("testdb").unwrap();
 
     let fields = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col("b".to_string(), ColumnType::Float, false, ParserValue::Null),
-        CreateTableField::Col(
-            "c".to_string(),
-            ColumnType::Char(10),
-            false,
-            ParserValue::Null,
-        ),
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Float, false, None, false),
+        CreateTableField::Col("c".to_string(), ColumnType::Char(10), false, None, false),
     ];
     db_manager.create_table("t", fields).unwrap();
 
@@ -274,10 +432,14 @@ fn test_select_aggregate_no_group() {
             Selector::CountAll,
         ]),
         table: vec!["t".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
         where_clauses: vec![],
-        group_by: None,
-        order_by: None,
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
         limit: None,
+        limit_percent: false,
         offset: None,
     };
 
@@ -286,6 +448,102 @@ fn test_select_aggregate_no_group() {
     assert_eq!(rows, vec![vec!["1", "3.50", "6", "3"]]);
 }
 
+#[test]
+fn test_select_min_max_from_index() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Int, false, None, false),
+    ];
+    db_manager.create_table("t", fields).unwrap();
+    db_manager
+        .execute_alter_statement(AlterStatement::AddIndex(
+            "t".to_string(),
+            Some("idx_a".to_string()),
+            vec!["a".to_string()],
+            None,
+        ))
+        .unwrap();
+
+    let col = |name: &str| TableColumn {
+        table: None,
+        column: name.to_string(),
+    };
+    let query = |selectors: Vec<Selector>, where_clauses: Vec<WhereClause>| SelectClause {
+        selectors: Selectors::List(selectors),
+        table: vec!["t".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses,
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+    let min_max = || vec![Selector::Min(col("a")), Selector::Max(col("a"))];
+
+    let (_, rows) = db_manager.select(query(min_max(), vec![])).unwrap();
+    assert_eq!(rows, vec![vec!["NULL", "NULL"]]);
+
+    let mut rows: Vec<Vec<ParserValue>> = (0..300)
+        .map(|i| {
+            vec![
+                ParserValue::Integer((i * 7) % 300 - 100),
+                ParserValue::Integer(i),
+            ]
+        })
+        .collect();
+    rows.push(vec![ParserValue::Null, ParserValue::Integer(300)]);
+    db_manager.insert("t", rows).unwrap();
+
+    let (headers, rows) = db_manager.select(query(min_max(), vec![])).unwrap();
+    assert_eq!(headers, vec!["MIN(a)", "MAX(a)"]);
+    assert_eq!(rows, vec![vec!["-100", "199"]]);
+
+    // Plant an entry only the index knows about: the bare aggregate reads it,
+    // while WHERE and mixed aggregates still scan the table
+    db_manager
+        .index_manager
+        .insert(
+            "t",
+            "a",
+            5000,
+            RecordId {
+                page_id: 0,
+                slot_id: 0,
+            },
+        )
+        .unwrap();
+    let (_, rows) = db_manager.select(query(min_max(), vec![])).unwrap();
+    assert_eq!(rows, vec![vec!["-100", "5000"]]);
+
+    let where_b = vec![WhereClause::Op(
+        col("b"),
+        Operator::Ge,
+        Expression::Value(ParserValue::Integer(0)),
+    )];
+    let (_, rows) = db_manager.select(query(min_max(), where_b)).unwrap();
+    assert_eq!(rows, vec![vec!["-100", "199"]]);
+
+    let (_, rows) = db_manager
+        .select(query(
+            vec![Selector::Max(col("a")), Selector::CountAll],
+            vec![],
+        ))
+        .unwrap();
+    assert_eq!(rows, vec![vec!["199", "301"]]);
+
+    db_manager.set_use_indexes(false);
+    let (_, rows) = db_manager.select(query(min_max(), vec![])).unwrap();
+    assert_eq!(rows, vec![vec!["-100", "199"]]);
+}
+
 #[test]
 fn test_select_aggregate_group_by() {
     let (_temp, mut db_manager) = setup_test_db();
@@ -294,14 +552,9 @@ fn test_select_aggregate_group_by() {
     db_manager.use_database("testdb").unwrap();
 
     let fields = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col("b".to_string(), ColumnType::Float, false, ParserValue::Null),
-        CreateTableField::Col(
-            "c".to_string(),
-            ColumnType::Char(10),
-            false,
-            ParserValue::Null,
-        ),
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Float, false, None, false),
+        CreateTableField::Col("c".to_string(), ColumnType::Char(10), false, None, false),
     ];
     db_manager.create_table("t", fields).unwrap();
 
@@ -342,13 +595,17 @@ fn test_select_aggregate_group_by() {
             }),
         ]),
         table: vec!["t".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
         where_clauses: vec![],
-        group_by: Some(TableColumn {
+        group_by: vec![TableColumn {
             table: None,
             column: "c".to_string(),
-        }),
-        order_by: None,
+        }],
+        having: Vec::new(),
+        order_by: vec![],
         limit: None,
+        limit_percent: false,
         offset: None,
     };
 
@@ -364,1353 +621,6617 @@ fn test_select_aggregate_group_by() {
 }
 
 #[test]
-fn test_add_and_drop_index() {
-    let (temp_dir, mut db_manager) = setup_test_db();
-
+fn test_count_column_skips_nulls() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "b".to_string(),
-            ColumnType::Char(12),
-            true,
-            ParserValue::Null,
+    // Rows shaped like an outer join's output, with the right side NULL-filled
+    // for unmatched left rows
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (customer INT NOT NULL, order_id INT);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 10), (1, 11), (2, NULL), (3, 12), (4, NULL);",
+    )
+    .unwrap();
+
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT COUNT(*), COUNT(order_id) FROM t;"),
+        vec![vec!["5", "3"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT customer, COUNT(*), COUNT(order_id) FROM t GROUP BY customer ORDER BY customer;"
         ),
-        CreateTableField::Col("c".to_string(), ColumnType::Float, true, ParserValue::Null),
-    ];
+        vec![
+            vec!["1", "2", "2"],
+            vec!["2", "1", "0"],
+            vec!["3", "1", "1"],
+            vec!["4", "1", "0"],
+        ]
+    );
+}
 
-    db_manager.create_table("tbl9", fields).unwrap();
+#[test]
+fn test_where_constant_predicates() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
 
-    let rows = vec![
-        vec![
-            ParserValue::Integer(1),
-            ParserValue::String("1".to_string()),
-            ParserValue::Float(1.0),
-        ],
-        vec![
-            ParserValue::Integer(2),
-            ParserValue::String("2".to_string()),
-            ParserValue::Float(2.0),
-        ],
-    ];
-    db_manager.insert("tbl9", rows).unwrap();
+    run(&mut db_manager, "CREATE TABLE a (id INT, v INT);").unwrap();
+    run(&mut db_manager, "CREATE TABLE b (id INT, w INT);").unwrap();
+    run(&mut db_manager, "INSERT INTO a VALUES (1, 10), (2, 20);").unwrap();
+    run(&mut db_manager, "INSERT INTO b VALUES (1, 100), (2, 200);").unwrap();
 
-    db_manager
-        .execute_alter_statement(AlterStatement::AddIndex(
-            "tbl9".to_string(),
-            Some("idx_a".to_string()),
-            vec!["a".to_string()],
-        ))
-        .unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM a WHERE 1 = 1;").len(),
+        2
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM a WHERE 1 = 1 AND v > 10;"),
+        vec![vec!["2"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM a WHERE 2 > 1.5 AND 'b' > 'a';"
+        )
+        .len(),
+        2
+    );
+    assert!(select_rows(&mut db_manager, "SELECT id FROM a WHERE 1 = 2;").is_empty());
+    // NULL compares false, as it does against a column
+    assert!(select_rows(&mut db_manager, "SELECT id FROM a WHERE NULL = NULL;").is_empty());
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT COUNT(*) FROM a WHERE 1 = 0;"),
+        vec![vec!["0"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT a.id, b.w FROM a, b WHERE a.id = b.id AND 1 = 1;"
+        )
+        .len(),
+        2
+    );
+    assert!(
+        select_rows(
+            &mut db_manager,
+            "SELECT a.id, b.w FROM a, b WHERE a.id = b.id AND 1 <> 1;"
+        )
+        .is_empty()
+    );
 
-    let meta = db_manager.describe_table("tbl9").unwrap();
-    assert_eq!(meta.indexes.len(), 1);
-    assert_eq!(meta.indexes[0].name, "idx_a");
-    assert_eq!(meta.indexes[0].columns, vec!["a".to_string()]);
+    assert!(matches!(
+        run(&mut db_manager, "UPDATE a SET v = 0 WHERE 1 = 2;").unwrap(),
+        QueryResult::RowsAffected(0)
+    ));
+    assert!(matches!(
+        run(&mut db_manager, "DELETE FROM a WHERE 1 > 2;").unwrap(),
+        QueryResult::RowsAffected(0)
+    ));
+    assert!(matches!(
+        run(&mut db_manager, "DELETE FROM a WHERE 1 = 1 AND id = 1;").unwrap(),
+        QueryResult::RowsAffected(1)
+    ));
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM a;"),
+        vec![vec!["2"]]
+    );
+}
 
-    let index_path = temp_dir.path().join("testdb").join("tbl9_a.idx");
-    assert!(index_path.exists());
+#[test]
+fn test_where_or() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
 
-    db_manager
-        .execute_alter_statement(AlterStatement::DropIndex(
-            "tbl9".to_string(),
-            "idx_a".to_string(),
-        ))
-        .unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT, a INT, b INT, c VARCHAR(8), PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(&mut db_manager, "CREATE TABLE u (id INT, w INT);").unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 1, 2, 'x'), (2, 1, 3, 'y'), (3, 2, 2, 'x'), (4, 3, 3, NULL);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO u VALUES (1, 10), (3, 30), (4, 40);",
+    )
+    .unwrap();
+
+    for use_indexes in [true, false] {
+        db_manager.set_use_indexes(use_indexes);
+        let mut ids = |sql: &str| -> Vec<String> {
+            select_rows(&mut db_manager, sql)
+                .into_iter()
+                .map(|row| row[0].clone())
+                .collect()
+        };
 
-    let meta = db_manager.describe_table("tbl9").unwrap();
-    assert!(meta.indexes.is_empty());
-    assert!(!index_path.exists());
+        // AND binds tighter than OR
+        assert_eq!(
+            ids("SELECT id FROM t WHERE a = 3 OR b = 2 AND c = 'x' ORDER BY id;"),
+            vec!["1", "3", "4"]
+        );
+        assert_eq!(
+            ids("SELECT id FROM t WHERE (a = 3 OR b = 2) AND c = 'x' ORDER BY id;"),
+            vec!["1", "3"]
+        );
+        assert_eq!(
+            ids("SELECT id FROM t WHERE b = 2 AND c = 'x' OR a = 3 ORDER BY id;"),
+            vec!["1", "3", "4"]
+        );
+        assert_eq!(
+            ids("SELECT id FROM t WHERE a = 1 AND (b = 3 OR c IS NULL OR id > 3) ORDER BY id;"),
+            vec!["2"]
+        );
+        // An OR next to an indexed equality still narrows through the index
+        assert_eq!(
+            ids("SELECT id FROM t WHERE id = 2 AND (a = 1 OR a = 2);"),
+            vec!["2"]
+        );
+        assert_eq!(
+            ids("SELECT id FROM t WHERE id = 1 OR id = 4 ORDER BY id;"),
+            vec!["1", "4"]
+        );
+        assert_eq!(
+            ids("SELECT id FROM t WHERE ((id = 1) OR (a = 2 AND (b = 3 OR b = 2))) ORDER BY id;"),
+            vec!["1", "3"]
+        );
+        assert_eq!(ids("SELECT id FROM t WHERE 1 = 0 OR a = 2;"), vec!["3"]);
+        assert_eq!(ids("SELECT id FROM t WHERE 1 = 1 OR a = 2;").len(), 4);
+        assert_eq!(
+            ids("SELECT COUNT(*) FROM t WHERE a = 1 OR c = 'x';"),
+            vec!["3"]
+        );
+        assert_eq!(
+            ids("SELECT t.id FROM t, u WHERE t.id = u.id AND (u.w = 10 OR t.a = 3) ORDER BY t.id;"),
+            vec!["1", "4"]
+        );
+    }
+
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "UPDATE t SET b = 0 WHERE a = 2 OR c IS NULL;"
+        )
+        .unwrap(),
+        QueryResult::RowsAffected(2)
+    ));
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "DELETE FROM t WHERE b = 0 AND (id = 3 OR id = 1);"
+        )
+        .unwrap(),
+        QueryResult::RowsAffected(1)
+    ));
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id, b FROM t ORDER BY id;"),
+        vec![vec!["1", "2"], vec!["2", "3"], vec!["4", "0"]]
+    );
 }
 
 #[test]
-fn test_composite_index_select_update_delete() {
-    let (temp_dir, mut db_manager) = setup_test_db();
-
+fn test_where_column_comparison() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col("b".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col("c".to_string(), ColumnType::Int, false, ParserValue::Null),
-    ];
-    db_manager.create_table("tcomp", fields).unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE spans (id INT, start_col INT, end_col INT, ratio FLOAT, label VARCHAR(8));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE other (id INT, name VARCHAR(8));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO spans VALUES (1, 1, 5, 2.5, 'a'), (2, 7, 3, 7.0, 'b'), (3, 4, 4, 1.0, NULL), (4, NULL, 2, 0.5, 'd');",
+    )
+    .unwrap();
+    run(&mut db_manager, "INSERT INTO other VALUES (1, 'a');").unwrap();
 
-    let rows = vec![
-        vec![
-            ParserValue::Integer(1),
-            ParserValue::Integer(1),
-            ParserValue::Integer(10),
-        ],
-        vec![
-            ParserValue::Integer(1),
-            ParserValue::Integer(2),
-            ParserValue::Integer(20),
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM spans WHERE start_col < end_col;"
+        ),
+        vec![vec!["1"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM spans WHERE start_col >= end_col;"
+        ),
+        vec![vec!["2"], vec!["3"]]
+    );
+    // INT and FLOAT columns compare by value
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM spans WHERE spans.start_col = ratio;"
+        ),
+        vec![vec!["2"]]
+    );
+
+    for sql in [
+        "SELECT id FROM spans WHERE start_col = label;",
+        "SELECT id FROM spans WHERE label <> ratio;",
+        "SELECT spans.id FROM spans, other WHERE spans.id = other.id AND spans.end_col = other.name;",
+    ] {
+        match run(&mut db_manager, sql) {
+            Err(DatabaseError::TypeMismatch(message)) => {
+                assert!(message.starts_with("Cannot compare"), "{}", message)
+            }
+            other => panic!("expected a type mismatch for {}, got {:?}", sql, other),
+        }
+    }
+}
+
+#[test]
+fn test_having() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE emp (id INT NOT NULL, dept VARCHAR(8), salary INT, bonus FLOAT, PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO emp VALUES (1, 'ops', 10, 1.0), (2, 'ops', 20, NULL), (3, 'ops', 30, 2.0), \
+         (4, 'dev', 50, 0.5), (5, 'dev', 70, 0.25), (6, 'hr', NULL, NULL);",
+    )
+    .unwrap();
+
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT dept, COUNT(*) FROM emp GROUP BY dept HAVING COUNT(*) > 1 ORDER BY dept;"
+        ),
+        vec![vec!["dev", "2"], vec!["ops", "3"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT dept, SUM(salary) FROM emp GROUP BY dept HAVING SUM(salary) >= 100 ORDER BY dept;"
+        ),
+        vec![vec!["dev", "120"]]
+    );
+    // The aggregate need not be selected, and an empty group's NULL never matches
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT dept FROM emp GROUP BY dept HAVING AVG(salary) < 60 ORDER BY dept;"
+        ),
+        vec![vec!["ops"]]
+    );
+    // Compared at full precision: the AVG of 0.375 prints as 0.38
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT dept, AVG(bonus) FROM emp GROUP BY dept HAVING AVG(bonus) < 0.38;"
+        ),
+        vec![vec!["dev", "0.38"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT e.dept FROM emp e GROUP BY e.dept HAVING COUNT(e.salary) = 2 AND MAX(e.salary) > 20;"
+        ),
+        vec![vec!["dev"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT dept, COUNT(*) FROM emp GROUP BY dept HAVING COUNT(*) > 0 ORDER BY COUNT(*) DESC LIMIT 1;"
+        ),
+        vec![vec!["ops", "3"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT dept FROM emp GROUP BY dept HAVING MIN(dept) > 'e' ORDER BY dept;"
+        ),
+        vec![vec!["hr"], vec!["ops"]]
+    );
+
+    // Without GROUP BY the whole table is one group
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT COUNT(*) FROM emp HAVING COUNT(*) > 5;"
+        ),
+        vec![vec!["6"]]
+    );
+    assert!(
+        select_rows(
+            &mut db_manager,
+            "SELECT COUNT(*) FROM emp HAVING SUM(salary) < 100;"
+        )
+        .is_empty()
+    );
+    for use_indexes in [true, false] {
+        db_manager.set_use_indexes(use_indexes);
+        assert!(
+            select_rows(
+                &mut db_manager,
+                "SELECT MAX(id) FROM emp HAVING MAX(id) > 6;"
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            select_rows(
+                &mut db_manager,
+                "SELECT MAX(id) FROM emp HAVING MIN(id) = 1;"
+            ),
+            vec![vec!["6"]]
+        );
+    }
+
+    for sql in [
+        "SELECT dept FROM emp GROUP BY dept HAVING dept = 'ops';",
+        "SELECT dept FROM emp GROUP BY dept HAVING COUNT(*) > 'x';",
+        "SELECT dept FROM emp GROUP BY dept HAVING MAX(dept) > 1;",
+        "SELECT dept FROM emp GROUP BY dept HAVING AVG(dept) > 1;",
+    ] {
+        assert!(
+            matches!(
+                run(&mut db_manager, sql),
+                Err(DatabaseError::TypeMismatch(_))
+            ),
+            "{}",
+            sql
+        );
+    }
+}
+
+#[test]
+fn test_group_by_multiple_columns() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE places (country VARCHAR(4), city VARCHAR(8), pop INT);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO places VALUES ('FR', 'Paris', 10), ('US', 'Paris', 1), ('FR', 'Paris', 5), \
+         ('FR', 'Lyon', 3), ('US', 'Austin', 7), ('US', NULL, 2), ('US', NULL, 4);",
+    )
+    .unwrap();
+
+    // Each column alone merges rows that differ in the other
+    let (_, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT country, COUNT(*) FROM places GROUP BY country ORDER BY country;",
+    );
+    assert_eq!(rows, vec![vec!["FR", "3"], vec!["US", "4"]]);
+    let (_, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT city, COUNT(*) FROM places GROUP BY city ORDER BY city;",
+    );
+    assert_eq!(
+        rows,
+        vec![
+            vec!["NULL", "2"],
+            vec!["Austin", "1"],
+            vec!["Lyon", "1"],
+            vec!["Paris", "3"]
+        ]
+    );
+
+    let (headers, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT COUNT(*), city, SUM(pop), country FROM places GROUP BY country, city ORDER BY SUM(pop);",
+    );
+    assert_eq!(headers, vec!["COUNT(*)", "city", "SUM(pop)", "country"]);
+    assert_eq!(
+        rows,
+        vec![
+            vec!["1", "Paris", "1", "US"],
+            vec!["1", "Lyon", "3", "FR"],
+            vec!["2", "NULL", "6", "US"],
+            vec!["1", "Austin", "7", "US"],
+            vec!["2", "Paris", "15", "FR"]
+        ]
+    );
+    // Grouping columns need not all be selected, and either may order the groups
+    let (_, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT city, COUNT(*) FROM places GROUP BY country, city HAVING COUNT(*) = 1 ORDER BY city DESC;",
+    );
+    assert_eq!(
+        rows,
+        vec![vec!["Paris", "1"], vec!["Lyon", "1"], vec!["Austin", "1"]]
+    );
+
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "SELECT country, pop FROM places GROUP BY country, city;"
+        ),
+        Err(DatabaseError::TypeMismatch(_))
+    ));
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "SELECT country FROM places GROUP BY country, city ORDER BY pop;"
+        ),
+        Err(DatabaseError::TypeMismatch(_))
+    ));
+}
+
+#[test]
+fn test_group_by_orders_groups_by_key() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(&mut db_manager, "CREATE TABLE t (k VARCHAR(4), v INT);").unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES ('c', 1), ('a', 2), (NULL, 3), ('b', 4), ('c', 5), ('a', 6);",
+    )
+    .unwrap();
+
+    // Without ORDER BY the groups come out by ascending key, every time
+    let query = "SELECT k, COUNT(*) FROM t GROUP BY k;";
+    let expected = vec![
+        vec!["NULL", "1"],
+        vec!["a", "2"],
+        vec!["b", "1"],
+        vec!["c", "2"],
+    ];
+    for _ in 0..3 {
+        assert_eq!(select_rows(&mut db_manager, query), expected);
+    }
+
+    // An explicit ORDER BY on the grouping column wins
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT k FROM t GROUP BY k ORDER BY k DESC;"
+        ),
+        vec![vec!["c"], vec!["b"], vec!["a"], vec!["NULL"]]
+    );
+    // Groups tied on an aggregate fall back to the key
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT k, COUNT(*) FROM t GROUP BY k ORDER BY COUNT(*) DESC;"
+        ),
+        vec![
+            vec!["a", "2"],
+            vec!["c", "2"],
+            vec!["NULL", "1"],
+            vec!["b", "1"]
+        ]
+    );
+}
+
+#[test]
+fn test_order_by_multiple_columns() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT, a INT, b VARCHAR(4));",
+    )
+    .unwrap();
+    run(&mut db_manager, "CREATE TABLE u (id INT, w INT);").unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 2, 'x'), (2, 1, 'y'), (3, 2, NULL), (4, NULL, 'x'), (5, 1, 'x'), (6, 2, 'z');",
+    ).unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO u VALUES (1, 7), (2, 7), (5, 3), (6, 3);",
+    )
+    .unwrap();
+
+    let ids = |rows: Vec<Vec<String>>| -> Vec<String> {
+        rows.into_iter().map(|row| row[0].clone()).collect()
+    };
+
+    // NULLs come first ascending and last descending, per column
+    assert_eq!(
+        ids(select_rows(
+            &mut db_manager,
+            "SELECT id FROM t ORDER BY a, b DESC;"
+        )),
+        vec!["4", "2", "5", "6", "1", "3"]
+    );
+    assert_eq!(
+        ids(select_rows(
+            &mut db_manager,
+            "SELECT id FROM t ORDER BY a DESC, b ASC;"
+        )),
+        vec!["3", "1", "6", "5", "2", "4"]
+    );
+    // A single column sorts as before; ties keep insertion order
+    assert_eq!(
+        ids(select_rows(&mut db_manager, "SELECT id FROM t ORDER BY b;")),
+        vec!["3", "1", "4", "5", "2", "6"]
+    );
+    assert_eq!(
+        ids(select_rows(
+            &mut db_manager,
+            "SELECT id FROM t ORDER BY b, a DESC, id DESC LIMIT 3 OFFSET 1;"
+        )),
+        vec!["1", "5", "4"]
+    );
+
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT t.id, u.w FROM t, u WHERE t.id = u.id ORDER BY u.w, t.a DESC, t.b;"
+        ),
+        vec![
+            vec!["6", "3"],
+            vec!["5", "3"],
+            vec!["1", "7"],
+            vec!["2", "7"]
+        ]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT a, b, COUNT(*) FROM t GROUP BY a, b ORDER BY COUNT(*) DESC, a DESC, b;"
+        )[..3],
+        [
+            vec!["2", "NULL", "1"],
+            vec!["2", "x", "1"],
+            vec!["2", "z", "1"]
+        ]
+    );
+}
+
+#[test]
+fn test_aggregate_output_order() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (g INT NOT NULL, x INT, y FLOAT);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 5, 1.0), (1, 7, NULL), (2, -3, 2.5), (2, NULL, 4.5), (3, 10, 0.5);",
+    ).unwrap();
+
+    // No GROUP BY: one row, one column per aggregate in select-list order
+    let (headers, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT COUNT(*), MAX(x), MIN(y), SUM(x), COUNT(x), AVG(y) FROM t;",
+    );
+    assert_eq!(
+        headers,
+        vec![
+            "COUNT(*)", "MAX(x)", "MIN(y)", "SUM(x)", "COUNT(x)", "AVG(y)"
+        ]
+    );
+    assert_eq!(rows, vec![vec!["5", "10", "0.50", "19", "4", "2.12"]]);
+
+    // The same aggregate twice keeps both columns
+    let (_, rows) = select_with_headers(&mut db_manager, "SELECT MAX(x), MIN(x), MAX(x) FROM t;");
+    assert_eq!(rows, vec![vec!["10", "-3", "10"]]);
+
+    // The group column may sit anywhere among the aggregates
+    let expected = [
+        ("1", "2", "7", "1.00"),
+        ("2", "2", "-3", "4.50"),
+        ("3", "1", "10", "0.50"),
+    ];
+    let (headers, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT COUNT(*), g, MAX(x), MAX(y) FROM t GROUP BY g ORDER BY g;",
+    );
+    assert_eq!(headers, vec!["COUNT(*)", "g", "MAX(x)", "MAX(y)"]);
+    assert_eq!(
+        rows,
+        expected
+            .iter()
+            .map(|&(g, n, x, y)| vec![n, g, x, y])
+            .collect::<Vec<_>>()
+    );
+    let (headers, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT MAX(y), MAX(x), COUNT(*), g FROM t GROUP BY g ORDER BY g;",
+    );
+    assert_eq!(headers, vec!["MAX(y)", "MAX(x)", "COUNT(*)", "g"]);
+    assert_eq!(
+        rows,
+        expected
+            .iter()
+            .map(|&(g, n, x, y)| vec![y, x, n, g])
+            .collect::<Vec<_>>()
+    );
+
+    // Ordering by an aggregate that is not the first output column
+    let (_, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT g, COUNT(*), SUM(x) FROM t GROUP BY g ORDER BY SUM(x) DESC;",
+    );
+    assert_eq!(
+        rows,
+        vec![
+            vec!["1", "2", "12"],
+            vec!["3", "1", "10"],
+            vec!["2", "2", "-3"],
+        ]
+    );
+}
+
+#[test]
+fn test_select_aggregate_empty_table() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Float, false, None, false),
+        CreateTableField::Col("c".to_string(), ColumnType::Char(10), false, None, false),
+    ];
+    db_manager.create_table("t", fields).unwrap();
+
+    let col = |name: &str| TableColumn {
+        table: None,
+        column: name.to_string(),
+    };
+    let selectors = Selectors::List(vec![
+        Selector::CountAll,
+        Selector::Count(col("a")),
+        Selector::Sum(col("a")),
+        Selector::Sum(col("b")),
+        Selector::Average(col("b")),
+        Selector::Min(col("a")),
+        Selector::Max(col("c")),
+    ]);
+    let expected = vec![vec!["0", "0", "NULL", "NULL", "NULL", "NULL", "NULL"]];
+    let query = |where_clauses: Vec<WhereClause>, group_by: Vec<TableColumn>| SelectClause {
+        selectors: selectors.clone(),
+        table: vec!["t".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses,
+        group_by,
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+
+    // No rows at all still produce the single implicit group
+    let (_, rows) = db_manager.select(query(vec![], vec![])).unwrap();
+    assert_eq!(rows, expected);
+
+    // GROUP BY over nothing produces no groups
+    let grouped = SelectClause {
+        selectors: Selectors::List(vec![Selector::Column(col("a")), Selector::CountAll]),
+        ..query(vec![], vec![col("a")])
+    };
+    let (_, rows) = db_manager.select(grouped.clone()).unwrap();
+    assert!(rows.is_empty());
+
+    // Rows that are all filtered out behave like an empty table
+    db_manager
+        .insert(
+            "t",
+            vec![vec![
+                ParserValue::Integer(1),
+                ParserValue::Float(2.0),
+                ParserValue::String("x".to_string()),
+            ]],
+        )
+        .unwrap();
+    let none_match = || {
+        vec![WhereClause::Op(
+            col("a"),
+            Operator::Gt,
+            Expression::Value(ParserValue::Integer(100)),
+        )]
+    };
+    let (_, rows) = db_manager.select(query(none_match(), vec![])).unwrap();
+    assert_eq!(rows, expected);
+    let (_, rows) = db_manager
+        .select(SelectClause {
+            where_clauses: none_match(),
+            ..grouped
+        })
+        .unwrap();
+    assert!(rows.is_empty());
+}
+
+#[test]
+fn test_add_and_drop_index() {
+    let (temp_dir, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Char(12), true, None, false),
+        CreateTableField::Col("c".to_string(), ColumnType::Float, true, None, false),
+    ];
+
+    db_manager.create_table("tbl9", fields).unwrap();
+
+    let rows = vec![
+        vec![
+            ParserValue::Integer(1),
+            ParserValue::String("1".to_string()),
+            ParserValue::Float(1.0),
         ],
         vec![
             ParserValue::Integer(2),
-            ParserValue::Integer(1),
-            ParserValue::Integer(30),
+            ParserValue::String("2".to_string()),
+            ParserValue::Float(2.0),
         ],
     ];
-    db_manager.insert("tcomp", rows).unwrap();
+    db_manager.insert("tbl9", rows).unwrap();
+
+    db_manager
+        .execute_alter_statement(AlterStatement::AddIndex(
+            "tbl9".to_string(),
+            Some("idx_a".to_string()),
+            vec!["a".to_string()],
+            None,
+        ))
+        .unwrap();
+
+    let meta = db_manager.describe_table("tbl9").unwrap();
+    assert_eq!(meta.indexes.len(), 1);
+    assert_eq!(meta.indexes[0].name, "idx_a");
+    assert_eq!(meta.indexes[0].columns, vec!["a".to_string()]);
+
+    let index_path = temp_dir.path().join("testdb").join("tbl9_a.idx");
+    assert!(index_path.exists());
+
+    db_manager
+        .execute_alter_statement(AlterStatement::DropIndex(
+            "tbl9".to_string(),
+            "idx_a".to_string(),
+        ))
+        .unwrap();
+
+    let meta = db_manager.describe_table("tbl9").unwrap();
+    assert!(meta.indexes.is_empty());
+    assert!(!index_path.exists());
+}
+
+#[test]
+fn test_reindex_table_and_index() {
+    let (temp_dir, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Int, true, None, false),
+    ];
+    db_manager.create_table("tbl_ri", fields).unwrap();
+
+    let rows = (0..20)
+        .map(|i| vec![ParserValue::Integer(i), ParserValue::Integer(i * 10)])
+        .collect();
+    db_manager.insert("tbl_ri", rows).unwrap();
+
+    db_manager
+        .execute_alter_statement(AlterStatement::AddIndex(
+            "tbl_ri".to_string(),
+            Some("idx_a".to_string()),
+            vec!["a".to_string()],
+            None,
+        ))
+        .unwrap();
+    db_manager
+        .execute_alter_statement(AlterStatement::AddIndex(
+            "tbl_ri".to_string(),
+            Some("idx_b".to_string()),
+            vec!["b".to_string()],
+            None,
+        ))
+        .unwrap();
+
+    let db_path = temp_dir.path().join("testdb");
+    let db_path_str = db_path.to_string_lossy().to_string();
+    let index_path = db_path.join("tbl_ri_a.idx");
+
+    // Lose the index file behind the catalog's back, then rebuild it by name
+    db_manager
+        .index_manager
+        .drop_index(&db_path_str, "tbl_ri", "a")
+        .unwrap();
+    assert!(!index_path.exists());
+
+    db_manager
+        .execute_table_statement(TableStatement::ReindexIndex("idx_a".to_string()))
+        .unwrap();
+    assert!(index_path.exists());
+    db_manager
+        .index_manager
+        .open_index(&db_path_str, "tbl_ri", "a")
+        .unwrap();
+    assert_eq!(
+        db_manager.index_manager.search_all("tbl_ri", "a", 7).len(),
+        1
+    );
+
+    assert_eq!(db_manager.reindex_table("tbl_ri").unwrap(), 2);
+    db_manager
+        .index_manager
+        .open_index(&db_path_str, "tbl_ri", "b")
+        .unwrap();
+    assert_eq!(
+        db_manager.index_manager.search_all("tbl_ri", "b", 70).len(),
+        1
+    );
+
+    assert!(db_manager.reindex_index("idx_missing").is_err());
+    assert!(db_manager.reindex_table("missing").is_err());
+}
+
+#[test]
+fn test_check_table_reports_index_discrepancies() {
+    let (temp_dir, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Int, false, None, false),
+    ];
+    db_manager.create_table("tbl_chk", fields).unwrap();
+
+    let rows = (0..10)
+        .map(|i| vec![ParserValue::Integer(i), ParserValue::Integer(i)])
+        .collect();
+    db_manager.insert("tbl_chk", rows).unwrap();
+    db_manager
+        .execute_alter_statement(AlterStatement::AddIndex(
+            "tbl_chk".to_string(),
+            Some("idx_a".to_string()),
+            vec!["a".to_string()],
+            None,
+        ))
+        .unwrap();
+
+    assert!(db_manager.check_table("tbl_chk").unwrap().is_empty());
+
+    let db_path_str = temp_dir.path().join("testdb").to_string_lossy().to_string();
+    db_manager
+        .index_manager
+        .open_index(&db_path_str, "tbl_chk", "a")
+        .unwrap();
+    let rid = db_manager.index_manager.search("tbl_chk", "a", 3).unwrap();
+    db_manager
+        .index_manager
+        .delete_entry("tbl_chk", "a", 3, rid)
+        .unwrap();
+    let bogus = RecordId::new(rid.page_id + 100, 0);
+    db_manager
+        .index_manager
+        .insert("tbl_chk", "a", 42, bogus)
+        .unwrap();
+
+    let issues = db_manager.check_table("tbl_chk").unwrap();
+    assert_eq!(
+        issues,
+        vec![
+            IndexCheckIssue {
+                index: "idx_a".to_string(),
+                kind: IndexCheckIssueKind::MissingEntry(3, rid),
+            },
+            IndexCheckIssue {
+                index: "idx_a".to_string(),
+                kind: IndexCheckIssueKind::DanglingPointer(42, bogus),
+            },
+        ]
+    );
+
+    // CHECK only reports; REINDEX repairs
+    db_manager.reindex_table("tbl_chk").unwrap();
+    assert!(db_manager.check_table("tbl_chk").unwrap().is_empty());
+}
+
+#[test]
+fn test_composite_index_select_update_delete() {
+    let (temp_dir, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("c".to_string(), ColumnType::Int, false, None, false),
+    ];
+    db_manager.create_table("tcomp", fields).unwrap();
+
+    let rows = vec![
+        vec![
+            ParserValue::Integer(1),
+            ParserValue::Integer(1),
+            ParserValue::Integer(10),
+        ],
+        vec![
+            ParserValue::Integer(1),
+            ParserValue::Integer(2),
+            ParserValue::Integer(20),
+        ],
+        vec![
+            ParserValue::Integer(2),
+            ParserValue::Integer(1),
+            ParserValue::Integer(30),
+        ],
+    ];
+    db_manager.insert("tcomp", rows).unwrap();
+
+    db_manager
+        .execute_alter_statement(AlterStatement::AddIndex(
+            "tcomp".to_string(),
+            None,
+            vec!["a".to_string(), "b".to_string()],
+            None,
+        ))
+        .unwrap();
+
+    let meta = db_manager.describe_table("tcomp").unwrap();
+    assert_eq!(meta.indexes.len(), 1);
+    assert_eq!(meta.indexes[0].name, "idx_a_b");
+    assert_eq!(
+        meta.indexes[0].columns,
+        vec!["a".to_string(), "b".to_string()]
+    );
+
+    let index_path = temp_dir.path().join("testdb").join("tcomp_a__b.idx");
+    assert!(index_path.exists());
+
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["tcomp".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![
+            WhereClause::Op(
+                TableColumn {
+                    table: None,
+                    column: "a".to_string(),
+                },
+                Operator::Eq,
+                Expression::Value(ParserValue::Integer(1)),
+            ),
+            WhereClause::Op(
+                TableColumn {
+                    table: None,
+                    column: "b".to_string(),
+                },
+                Operator::Eq,
+                Expression::Value(ParserValue::Integer(2)),
+            ),
+        ],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+    let (_headers, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows, vec![vec!["1", "2", "20"]]);
+
+    let updated = db_manager
+        .update(
+            "tcomp",
+            vec![("c".to_string(), ParserValue::Integer(99))],
+            Some(vec![
+                WhereClause::Op(
+                    TableColumn {
+                        table: None,
+                        column: "a".to_string(),
+                    },
+                    Operator::Eq,
+                    Expression::Value(ParserValue::Integer(1)),
+                ),
+                WhereClause::Op(
+                    TableColumn {
+                        table: None,
+                        column: "b".to_string(),
+                    },
+                    Operator::Eq,
+                    Expression::Value(ParserValue::Integer(2)),
+                ),
+            ]),
+        )
+        .unwrap();
+    assert_eq!(updated, 1);
+
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["tcomp".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![
+            WhereClause::Op(
+                TableColumn {
+                    table: None,
+                    column: "a".to_string(),
+                },
+                Operator::Eq,
+                Expression::Value(ParserValue::Integer(1)),
+            ),
+            WhereClause::Op(
+                TableColumn {
+                    table: None,
+                    column: "b".to_string(),
+                },
+                Operator::Eq,
+                Expression::Value(ParserValue::Integer(2)),
+            ),
+        ],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+    let (_headers, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows, vec![vec!["1", "2", "99"]]);
+
+    let deleted = db_manager
+        .delete(
+            "tcomp",
+            Some(vec![
+                WhereClause::Op(
+                    TableColumn {
+                        table: None,
+                        column: "a".to_string(),
+                    },
+                    Operator::Eq,
+                    Expression::Value(ParserValue::Integer(1)),
+                ),
+                WhereClause::Op(
+                    TableColumn {
+                        table: None,
+                        column: "b".to_string(),
+                    },
+                    Operator::Eq,
+                    Expression::Value(ParserValue::Integer(2)),
+                ),
+            ]),
+        )
+        .unwrap();
+    assert_eq!(deleted, 1);
+
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["tcomp".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![
+            WhereClause::Op(
+                TableColumn {
+                    table: None,
+                    column: "a".to_string(),
+                },
+                Operator::Eq,
+                Expression::Value(ParserValue::Integer(1)),
+            ),
+            WhereClause::Op(
+                TableColumn {
+                    table: None,
+                    column: "b".to_string(),
+                },
+                Operator::Eq,
+                Expression::Value(ParserValue::Integer(2)),
+            ),
+        ],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+    let (_headers, rows) = db_manager.select(clause).unwrap();
+    assert!(rows.is_empty());
+
+    db_manager
+        .execute_alter_statement(AlterStatement::DropIndex(
+            "tcomp".to_string(),
+            "idx_a_b".to_string(),
+        ))
+        .unwrap();
+    assert!(!index_path.exists());
+}
+
+#[test]
+fn test_composite_index_equality_any_clause_order() {
+    let (temp_dir, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Int, true, None, false),
+    ];
+    db_manager.create_table("tcomp_order", fields).unwrap();
+    let rows = vec![(3, 1), (3, 2), (3, 3), (4, 2)]
+        .into_iter()
+        .map(|(a, b)| vec![ParserValue::Integer(a), ParserValue::Integer(b)])
+        .collect();
+    db_manager.insert("tcomp_order", rows).unwrap();
+    db_manager
+        .execute_alter_statement(AlterStatement::AddIndex(
+            "tcomp_order".to_string(),
+            Some("idx_ab".to_string()),
+            vec!["a".to_string(), "b".to_string()],
+            None,
+        ))
+        .unwrap();
+
+    let eq = |column: &str, value: i64| {
+        WhereClause::Op(
+            TableColumn {
+                table: None,
+                column: column.to_string(),
+            },
+            Operator::Eq,
+            Expression::Value(ParserValue::Integer(value)),
+        )
+    };
+
+    let db_path_str = temp_dir.path().join("testdb").to_string_lossy().to_string();
+    let table_meta = db_manager.describe_table("tcomp_order").unwrap();
+    let schema = db_manager.metadata_to_schema(&table_meta);
+
+    // Both orders must hit the exact composite key, not the leading-column range
+    for where_clauses in [vec![eq("a", 3), eq("b", 2)], vec![eq("b", 2), eq("a", 3)]] {
+        let rids = db_manager
+            .index_candidates_for_where(&db_path_str, &table_meta, &schema, &where_clauses)
+            .unwrap()
+            .unwrap();
+        assert_eq!(rids.len(), 1);
+
+        let clause = SelectClause {
+            selectors: Selectors::All,
+            table: vec!["tcomp_order".to_string()],
+            table_aliases: vec![],
+            selector_aliases: vec![],
+            where_clauses,
+            group_by: vec![],
+            having: Vec::new(),
+            order_by: vec![],
+            limit: None,
+            limit_percent: false,
+            offset: None,
+        };
+        let (_headers, rows) = db_manager.select(clause).unwrap();
+        assert_eq!(rows, vec![vec!["3", "2"]]);
+    }
+
+    // Contradictory equalities on an indexed column can never match
+    let rids = db_manager
+        .index_candidates_for_where(
+            &db_path_str,
+            &table_meta,
+            &schema,
+            &[eq("b", 2), eq("a", 3), eq("b", 1)],
+        )
+        .unwrap()
+        .unwrap();
+    assert!(rids.is_empty());
+}
+
+#[test]
+fn test_composite_index_partial_range() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("c".to_string(), ColumnType::Int, false, None, false),
+    ];
+    db_manager.create_table("tcomp2", fields).unwrap();
+
+    let rows = vec![
+        vec![
+            ParserValue::Integer(1),
+            ParserValue::Integer(10),
+            ParserValue::Integer(100),
+        ],
+        vec![
+            ParserValue::Integer(1),
+            ParserValue::Integer(20),
+            ParserValue::Integer(200),
+        ],
+        vec![
+            ParserValue::Integer(2),
+            ParserValue::Integer(10),
+            ParserValue::Integer(300),
+        ],
+    ];
+    db_manager.insert("tcomp2", rows).unwrap();
+
+    db_manager
+        .execute_alter_statement(AlterStatement::AddIndex(
+            "tcomp2".to_string(),
+            None,
+            vec!["a".to_string(), "b".to_string()],
+            None,
+        ))
+        .unwrap();
+
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["tcomp2".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![WhereClause::Op(
+            TableColumn {
+                table: None,
+                column: "a".to_string(),
+            },
+            Operator::Lt,
+            Expression::Value(ParserValue::Integer(2)),
+        )],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+    let (_headers, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows, vec![vec!["1", "10", "100"], vec!["1", "20", "200"],]);
+}
+
+#[test]
+fn test_where_arithmetic_int_and_float_division() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("f".to_string(), ColumnType::Float, true, None, false),
+    ];
+    db_manager.create_table("arith", fields).unwrap();
+    let rows = vec![
+        vec![ParserValue::Integer(1), ParserValue::Float(2.0)],
+        vec![ParserValue::Integer(2), ParserValue::Float(2.5)],
+        vec![ParserValue::Integer(3), ParserValue::Float(3.0)],
+    ];
+    db_manager.insert("arith", rows).unwrap();
+
+    let select_where = |db_manager: &mut DatabaseManager, column: &str, expr: Expression| {
+        let clause = SelectClause {
+            selectors: Selectors::List(vec![Selector::Column(TableColumn {
+                table: None,
+                column: "a".to_string(),
+            })]),
+            table: vec!["arith".to_string()],
+            table_aliases: vec![],
+            selector_aliases: vec![],
+            where_clauses: vec![WhereClause::Op(
+                TableColumn {
+                    table: None,
+                    column: column.to_string(),
+                },
+                Operator::Eq,
+                expr,
+            )],
+            group_by: vec![],
+            having: Vec::new(),
+            order_by: vec![],
+            limit: None,
+            limit_percent: false,
+            offset: None,
+        };
+        db_manager.select(clause).unwrap().1
+    };
+    let arith = |left: ParserValue, op: ArithmeticOperator, right: ParserValue| {
+        Expression::Arithmetic(
+            Box::new(Expression::Value(left)),
+            op,
+            Box::new(Expression::Value(right)),
+        )
+    };
+
+    // 5 / 2 = 2 between INTs
+    let rows = select_where(
+        &mut db_manager,
+        "a",
+        arith(
+            ParserValue::Integer(5),
+            ArithmeticOperator::Div,
+            ParserValue::Integer(2),
+        ),
+    );
+    assert_eq!(rows, vec![vec!["2"]]);
+
+    // 5.0 / 2 = 2.5 once a FLOAT is involved
+    let rows = select_where(
+        &mut db_manager,
+        "f",
+        arith(
+            ParserValue::Float(5.0),
+            ArithmeticOperator::Div,
+            ParserValue::Integer(2),
+        ),
+    );
+    assert_eq!(rows, vec![vec!["2"]]);
+
+    // 5 / 2 against a FLOAT column is still the integer 2
+    let rows = select_where(
+        &mut db_manager,
+        "f",
+        arith(
+            ParserValue::Integer(5),
+            ArithmeticOperator::Div,
+            ParserValue::Integer(2),
+        ),
+    );
+    assert_eq!(rows, vec![vec!["1"]]);
+
+    let rows = select_where(
+        &mut db_manager,
+        "a",
+        arith(
+            ParserValue::Integer(7),
+            ArithmeticOperator::Mod,
+            ParserValue::Integer(4),
+        ),
+    );
+    assert_eq!(rows, vec![vec!["3"]]);
+
+    // Column references inside the expression: f = a + 0.5 only holds for a = 2
+    let rows = select_where(
+        &mut db_manager,
+        "f",
+        Expression::Arithmetic(
+            Box::new(Expression::Column(TableColumn {
+                table: None,
+                column: "a".to_string(),
+            })),
+            ArithmeticOperator::Add,
+            Box::new(Expression::Value(ParserValue::Float(0.5))),
+        ),
+    );
+    assert_eq!(rows, vec![vec!["2"]]);
+
+    // Division and modulo by zero are NULL, which never compares equal
+    let rows = select_where(
+        &mut db_manager,
+        "a",
+        arith(
+            ParserValue::Integer(1),
+            ArithmeticOperator::Div,
+            ParserValue::Integer(0),
+        ),
+    );
+    assert!(rows.is_empty());
+    let rows = select_where(
+        &mut db_manager,
+        "f",
+        arith(
+            ParserValue::Float(1.0),
+            ArithmeticOperator::Mod,
+            ParserValue::Float(0.0),
+        ),
+    );
+    assert!(rows.is_empty());
+}
+
+#[test]
+fn test_where_unary_minus() {
+    let (_temp, mut db_manager) = setup_test_db();
+    run(&mut db_manager, "CREATE DATABASE testdb;").unwrap();
+    run(&mut db_manager, "USE testdb;").unwrap();
+    run(&mut db_manager, "CREATE TABLE t (a INT, b INT);").unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (5, 3), (4, -6), (-2, 6);",
+    )
+    .unwrap();
+
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT a FROM t WHERE b = a -1*2;"),
+        vec![vec!["5"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT a FROM t WHERE b = -a * 3;"),
+        vec![vec!["-2"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT a FROM t WHERE b = -(a + 2);"),
+        vec![vec!["4"]]
+    );
+}
+
+#[test]
+fn test_select_specific_columns() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Char(10), true, None, false),
+        CreateTableField::Col("c".to_string(), ColumnType::Float, true, None, false),
+    ];
+
+    db_manager.create_table("test", fields).unwrap();
+
+    let rows = vec![vec![
+        ParserValue::Integer(1),
+        ParserValue::String("hello".to_string()),
+        ParserValue::Float(1.5),
+    ]];
+    db_manager.insert("test", rows).unwrap();
+
+    // Select specific columns
+    let clause = SelectClause {
+        selectors: Selectors::List(vec![
+            crate::lexer_parser::Selector::Column(crate::lexer_parser::TableColumn {
+                table: None,
+                column: "b".to_string(),
+            }),
+            crate::lexer_parser::Selector::Column(crate::lexer_parser::TableColumn {
+                table: None,
+                column: "a".to_string(),
+            }),
+        ]),
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+
+    let (headers, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(headers, vec!["b", "a"]);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0], vec!["hello", "1"]);
+}
+
+#[test]
+fn test_select_with_where() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Char(10), true, None, false),
+    ];
+
+    db_manager.create_table("test", fields).unwrap();
+
+    // Insert test data
+    let rows = vec![
+        vec![
+            ParserValue::Integer(1),
+            ParserValue::String("a".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(2),
+            ParserValue::String("b".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(3),
+            ParserValue::String("c".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(4),
+            ParserValue::String("d".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(5),
+            ParserValue::String("e".to_string()),
+        ],
+    ];
+    db_manager.insert("test", rows).unwrap();
+
+    // WHERE a > 2
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![WhereClause::Op(
+            crate::lexer_parser::TableColumn {
+                table: None,
+                column: "a".to_string(),
+            },
+            Operator::Gt,
+            crate::lexer_parser::Expression::Value(ParserValue::Integer(2)),
+        )],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+
+    let (_, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0][0], "3");
+    assert_eq!(rows[1][0], "4");
+    assert_eq!(rows[2][0], "5");
+}
+
+#[test]
+fn test_select_with_like() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("name".to_string(), ColumnType::Char(10), true, None, false),
+    ];
+
+    db_manager.create_table("test", fields).unwrap();
+
+    let rows = vec![
+        vec![
+            ParserValue::Integer(1),
+            ParserValue::String("hello".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(2),
+            ParserValue::String("help".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(3),
+            ParserValue::String("shell".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(4),
+            ParserValue::String("world".to_string()),
+        ],
+    ];
+    db_manager.insert("test", rows).unwrap();
+
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![WhereClause::Like(
+            crate::lexer_parser::TableColumn {
+                table: None,
+                column: "name".to_string(),
+            },
+            "he%".to_string(),
+            None,
+        )],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+
+    let (_, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0][1], "hello");
+    assert_eq!(rows[1][1], "help");
+}
+
+#[test]
+fn test_like_coerces_non_string_columns() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(&mut db_manager, "CREATE TABLE a (id INT, score FLOAT);").unwrap();
+    run(&mut db_manager, "CREATE TABLE b (id INT, tag VARCHAR(4));").unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO a VALUES (1, 1.5), (12, NULL), (21, 2.25);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO b VALUES (1, 'x'), (12, 'y'), (21, 'z');",
+    )
+    .unwrap();
+
+    let rows = select_rows(&mut db_manager, "SELECT id FROM a WHERE id LIKE '1%';");
+    assert_eq!(rows, vec![vec!["1".to_string()], vec!["12".to_string()]]);
+
+    // Floats match their displayed two-decimal text, and NULL never matches
+    let rows = select_rows(&mut db_manager, "SELECT id FROM a WHERE score LIKE '%.50';");
+    assert_eq!(rows, vec![vec!["1".to_string()]]);
+    let rows = select_rows(&mut db_manager, "SELECT id FROM a WHERE score LIKE '%';");
+    assert_eq!(rows.len(), 2);
+
+    let rows = select_rows(
+        &mut db_manager,
+        "SELECT b.tag FROM a, b WHERE a.id = b.id AND b.id LIKE '_1';",
+    );
+    assert_eq!(rows, vec![vec!["z".to_string()]]);
+}
+
+#[test]
+fn test_not_like_and_escape() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(&mut db_manager, "CREATE TABLE t (id INT, s VARCHAR(16));").unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, '50%'), (2, '500'), (3, '%off'), (4, 'a_b'), (5, 'axb'), \
+         (6, NULL), (7, 'end_');",
+    )
+    .unwrap();
+
+    // NOT LIKE is the inverse of LIKE, except that NULL matches neither
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE s NOT LIKE '5%';"),
+        vec![vec!["3"], vec!["4"], vec!["5"], vec!["7"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM t WHERE s NOT LIKE 'a%' AND id > 2 AND s NOT LIKE '%!_' ESCAPE '!';"
+        ),
+        vec![vec!["3"]]
+    );
+
+    // An escaped wildcard matches itself, at the end, start or middle of the pattern
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM t WHERE s LIKE '50!%' ESCAPE '!';"
+        ),
+        vec![vec!["1"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM t WHERE s LIKE '!%%' ESCAPE '!';"
+        ),
+        vec![vec!["3"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM t WHERE s LIKE 'a!_b' ESCAPE '!';"
+        ),
+        vec![vec!["4"]]
+    );
+    // A backslash is doubled in the string literal, as it escapes the next character there
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            r"SELECT id FROM t WHERE s LIKE '%\\_' ESCAPE '\\';"
+        ),
+        vec![vec!["7"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM t WHERE s NOT LIKE '%!%%' ESCAPE '!' AND s NOT LIKE '%!_%' ESCAPE '!';"
+        ),
+        vec![vec!["2"], vec!["5"]]
+    );
+    // Without ESCAPE the same patterns keep their wildcards
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE s LIKE 'a!_b';"),
+        Vec::<Vec<String>>::new()
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE s LIKE 'a_b';"),
+        vec![vec!["4"], vec!["5"]]
+    );
+}
+
+#[test]
+fn test_like_pattern_limits() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(&mut db_manager, "CREATE TABLE t (id INT, s VARCHAR(255));").unwrap();
+    let long = "a".repeat(255);
+    run(
+        &mut db_manager,
+        &format!("INSERT INTO t VALUES (1, '{}'), (2, 'ab');", long),
+    )
+    .unwrap();
+
+    // Many wildcards still compile and match
+    let pattern = "%a".repeat(crate::database::MAX_LIKE_PATTERN_LEN / 2);
+    match run(
+        &mut db_manager,
+        &format!("SELECT id FROM t WHERE s LIKE '{}';", pattern),
+    )
+    .unwrap()
+    {
+        QueryResult::ResultSet(_, rows) => assert!(rows.is_empty()),
+        other => panic!("not a result set: {:?}", other),
+    }
+    match run(&mut db_manager, "SELECT id FROM t WHERE s LIKE 'a%%%b';").unwrap() {
+        QueryResult::ResultSet(_, rows) => assert_eq!(rows, vec![vec!["2".to_string()]]),
+        other => panic!("not a result set: {:?}", other),
+    }
+
+    let pattern = "a".repeat(crate::database::MAX_LIKE_PATTERN_LEN + 1);
+    match run(
+        &mut db_manager,
+        &format!("SELECT id FROM t WHERE s LIKE '{}';", pattern),
+    ) {
+        Err(DatabaseError::TypeMismatch(msg)) => assert!(msg.contains("too long"), "{}", msg),
+        other => panic!("expected a pattern length error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_cast_in_projection_and_where() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT, code VARCHAR(8), price FLOAT);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, '42', 2.75), (2, 'n/a', -1.5), (3, ' 7 ', NULL), (4, '3.5', 10.0);",
+    )
+    .unwrap();
+
+    let (headers, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT id, CAST(code AS INT), CAST(code AS FLOAT), CAST(price AS INT), CAST(id AS FLOAT) FROM t;",
+    );
+    assert_eq!(
+        headers,
+        vec![
+            "id",
+            "CAST(code AS INT)",
+            "CAST(code AS FLOAT)",
+            "CAST(price AS INT)",
+            "CAST(id AS FLOAT)"
+        ]
+    );
+    assert_eq!(
+        rows,
+        vec![
+            vec!["1", "42", "42.00", "2", "1.00"],
+            vec!["2", "NULL", "NULL", "-1", "2.00"],
+            vec!["3", "7", "7.00", "NULL", "3.00"],
+            vec!["4", "NULL", "3.50", "10", "4.00"],
+        ]
+    );
+
+    // Unparseable strings become NULL and so never compare
+    let (_, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT id FROM t WHERE CAST(code AS INT) > 5;",
+    );
+    assert_eq!(rows, vec![vec!["1"], vec!["3"]]);
+    let (_, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT id FROM t WHERE CAST(code AS FLOAT) < price;",
+    );
+    assert_eq!(rows, vec![vec!["4"]]);
+    let (_, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT id FROM t WHERE id = CAST(price AS INT) - 6;",
+    );
+    assert_eq!(rows, vec![vec!["4"]]);
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE u (code INT, label VARCHAR(4));",
+    )
+    .unwrap();
+    run(&mut db_manager, "INSERT INTO u VALUES (42, 'x'), (7, 'y');").unwrap();
+    let (_, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT t.id, CAST(t.code AS INT), u.label FROM t, u WHERE CAST(t.code AS INT) = u.code;",
+    );
+    assert_eq!(rows, vec![vec!["1", "42", "x"], vec!["3", "7", "y"]]);
+
+    assert!(
+        run(
+            &mut db_manager,
+            "SELECT CAST(code AS INT), COUNT(*) FROM t;"
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_select_with_multiple_where() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Char(10), true, None, false),
+    ];
+
+    db_manager.create_table("test", fields).unwrap();
+
+    let rows = vec![
+        vec![
+            ParserValue::Integer(1),
+            ParserValue::String("a".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(2),
+            ParserValue::String("b".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(3),
+            ParserValue::String("c".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(4),
+            ParserValue::String("d".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(5),
+            ParserValue::String("e".to_string()),
+        ],
+    ];
+    db_manager.insert("test", rows).unwrap();
+
+    // WHERE a >= 2 AND a <= 4
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![
+            WhereClause::Op(
+                crate::lexer_parser::TableColumn {
+                    table: None,
+                    column: "a".to_string(),
+                },
+                Operator::Ge,
+                crate::lexer_parser::Expression::Value(ParserValue::Integer(2)),
+            ),
+            WhereClause::Op(
+                crate::lexer_parser::TableColumn {
+                    table: None,
+                    column: "a".to_string(),
+                },
+                Operator::Le,
+                crate::lexer_parser::Expression::Value(ParserValue::Integer(4)),
+            ),
+        ],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+
+    let (_, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0][0], "2");
+    assert_eq!(rows[1][0], "3");
+    assert_eq!(rows[2][0], "4");
+}
+
+#[test]
+fn test_select_order_by_limit_offset() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Char(10), true, None, false),
+    ];
+
+    db_manager.create_table("test", fields).unwrap();
+
+    let rows = vec![
+        vec![
+            ParserValue::Integer(1),
+            ParserValue::String("one".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(2),
+            ParserValue::String("two".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(3),
+            ParserValue::String("three".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(4),
+            ParserValue::String("four".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(5),
+            ParserValue::String("five".to_string()),
+        ],
+    ];
+    db_manager.insert("test", rows).unwrap();
+
+    let clause = SelectClause {
+        selectors: Selectors::List(vec![Selector::Column(TableColumn {
+            table: None,
+            column: "b".to_string(),
+        })]),
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![(
+            Selector::Column(TableColumn {
+                table: None,
+                column: "a".to_string(),
+            }),
+            false,
+        )],
+        limit: Some(2),
+        limit_percent: false,
+        offset: Some(1),
+    };
+
+    let (headers, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(headers, vec!["b"]);
+    assert_eq!(
+        rows,
+        vec![vec!["four".to_string()], vec!["three".to_string()]]
+    );
+}
+
+#[test]
+fn test_select_order_by_offset_past_end() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![CreateTableField::Col(
+        "a".to_string(),
+        ColumnType::Int,
+        true,
+        None,
+        false,
+    )];
+
+    db_manager.create_table("test", fields).unwrap();
+    db_manager
+        .insert(
+            "test",
+            vec![vec![ParserValue::Integer(1)], vec![ParserValue::Integer(2)]],
+        )
+        .unwrap();
+
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![(
+            Selector::Column(TableColumn {
+                table: None,
+                column: "a".to_string(),
+            }),
+            true,
+        )],
+        limit: None,
+        limit_percent: false,
+        offset: Some(5),
+    };
+
+    let (_, rows) = db_manager.select(clause).unwrap();
+    assert!(rows.is_empty());
+}
+
+#[test]
+fn test_update() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Char(10), true, None, false),
+    ];
+
+    db_manager.create_table("test", fields).unwrap();
+
+    let rows = vec![
+        vec![
+            ParserValue::Integer(1),
+            ParserValue::String("a".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(2),
+            ParserValue::String("b".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(3),
+            ParserValue::String("c".to_string()),
+        ],
+    ];
+    db_manager.insert("test", rows).unwrap();
+
+    // Update WHERE a = 2
+    let updates = vec![("b".to_string(), ParserValue::String("updated".to_string()))];
+    let where_clauses = Some(vec![WhereClause::Op(
+        crate::lexer_parser::TableColumn {
+            table: None,
+            column: "a".to_string(),
+        },
+        Operator::Eq,
+        crate::lexer_parser::Expression::Value(ParserValue::Integer(2)),
+    )]);
+
+    let count = db_manager.update("test", updates, where_clauses).unwrap();
+    assert_eq!(count, 1);
+
+    // Verify update
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+
+    let (_, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows[1][1], "updated");
+}
+
+#[test]
+fn test_update_primary_key_to_duplicate() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT NOT NULL, name VARCHAR(10), PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE p (a INT NOT NULL, b INT NOT NULL, PRIMARY KEY (a, b));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (5, 'x'), (7, 'y'), (9, 'z');",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO p VALUES (1, 1), (1, 2), (2, 1);",
+    )
+    .unwrap();
+
+    for use_indexes in [true, false] {
+        db_manager.set_use_indexes(use_indexes);
+        assert!(matches!(
+            run(&mut db_manager, "UPDATE t SET id = 5 WHERE id = 7;"),
+            Err(DatabaseError::PrimaryKeyViolation(key)) if key == "(5)"
+        ));
+        // Two updated rows may not end up with the same key either
+        assert!(matches!(
+            run(&mut db_manager, "UPDATE t SET id = 1 WHERE id > 6;"),
+            Err(DatabaseError::PrimaryKeyViolation(_))
+        ));
+        assert!(matches!(
+            run(&mut db_manager, "UPDATE p SET b = 2 WHERE a = 1 AND b = 1;"),
+            Err(DatabaseError::PrimaryKeyViolation(_))
+        ));
+    }
+    db_manager.set_use_indexes(true);
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t;"),
+        vec![vec!["5"], vec!["7"], vec!["9"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT * FROM p WHERE a = 1;").len(),
+        2
+    );
+
+    // Keeping a row's own key, or moving it to a free one, is fine
+    run(
+        &mut db_manager,
+        "UPDATE t SET id = 5, name = 'w' WHERE id = 5;",
+    )
+    .unwrap();
+    run(&mut db_manager, "UPDATE t SET id = 8 WHERE id = 7;").unwrap();
+    run(&mut db_manager, "UPDATE p SET b = 3 WHERE a = 1 AND b = 1;").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE id = 8;"),
+        vec![vec!["8"]]
+    );
+    assert!(matches!(
+        run(&mut db_manager, "UPDATE t SET id = 8 WHERE id = 9;"),
+        Err(DatabaseError::PrimaryKeyViolation(_))
+    ));
+}
+
+#[test]
+fn test_delete() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![CreateTableField::Col(
+        "a".to_string(),
+        ColumnType::Int,
+        true,
+        None,
+        false,
+    )];
+
+    db_manager.create_table("test", fields).unwrap();
+
+    let rows = vec![
+        vec![ParserValue::Integer(1)],
+        vec![ParserValue::Integer(2)],
+        vec![ParserValue::Integer(3)],
+        vec![ParserValue::Integer(4)],
+        vec![ParserValue::Integer(5)],
+    ];
+    db_manager.insert("test", rows).unwrap();
+
+    // Delete WHERE a > 3
+    let where_clauses = Some(vec![WhereClause::Op(
+        crate::lexer_parser::TableColumn {
+            table: None,
+            column: "a".to_string(),
+        },
+        Operator::Gt,
+        crate::lexer_parser::Expression::Value(ParserValue::Integer(3)),
+    )]);
+
+    let count = db_manager.delete("test", where_clauses).unwrap();
+    assert_eq!(count, 2);
+
+    // Verify deletion
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+
+    let (_, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows.len(), 3);
+}
+
+#[test]
+fn test_column_comparison_in_where() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT, created INT, updated INT, score FLOAT, flag INT);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 5, 3, 4.5, 0), (2, 2, 7, 2.0, 0), (3, 4, 4, 1.0, 0), (4, NULL, 1, 0.5, 0);",
+    ).unwrap();
+
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM t WHERE created >= updated;"
+        ),
+        vec![vec!["1"], vec!["3"]]
+    );
+    // INT and FLOAT columns compare numerically
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM t WHERE score = updated - 5;"
+        ),
+        vec![vec!["2"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE score < created;"),
+        vec![vec!["1"], vec!["3"]]
+    );
+
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "UPDATE t SET flag = 1 WHERE created = updated;"
+        )
+        .unwrap(),
+        QueryResult::RowsAffected(1)
+    ));
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE flag = 1;"),
+        vec![vec!["3"]]
+    );
+
+    // NULL never compares, so row 4 survives
+    assert!(matches!(
+        run(&mut db_manager, "DELETE FROM t WHERE created > updated;").unwrap(),
+        QueryResult::RowsAffected(1)
+    ));
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t;"),
+        vec![vec!["2"], vec!["3"], vec!["4"]]
+    );
+}
+
+#[test]
+fn test_delete_all() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![CreateTableField::Col(
+        "a".to_string(),
+        ColumnType::Int,
+        true,
+        None,
+        false,
+    )];
+
+    db_manager.create_table("test", fields).unwrap();
+
+    let rows = vec![
+        vec![ParserValue::Integer(1)],
+        vec![ParserValue::Integer(2)],
+        vec![ParserValue::Integer(3)],
+    ];
+    db_manager.insert("test", rows).unwrap();
+
+    // Delete all
+    let count = db_manager.delete("test", None).unwrap();
+    assert_eq!(count, 3);
+
+    // Verify deletion
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+
+    let (_, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows.len(), 0);
+}
+
+#[test]
+fn test_not_null_constraint() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![CreateTableField::Col(
+        "a".to_string(),
+        ColumnType::Int,
+        true, // NOT NULL
+        None,
+        false,
+    )];
+
+    db_manager.create_table("test", fields).unwrap();
+
+    // Try to insert NULL into NOT NULL column
+    let rows = vec![vec![ParserValue::Null]];
+    let result = db_manager.insert("test", rows);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_default_null_vs_no_default() {
+    let (temp_dir, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    // NOT NULL together with DEFAULT NULL can never be satisfied
+    let fields = vec![CreateTableField::Col(
+        "a".to_string(),
+        ColumnType::Int,
+        true,
+        Some(ParserDefault::Literal(ParserValue::Null)),
+        false,
+    )];
+    assert!(db_manager.create_table("bad", fields).is_err());
+    assert!(db_manager.describe_table("bad").is_err());
+
+    let fields = vec![
+        CreateTableField::Col(
+            "a".to_string(),
+            ColumnType::Int,
+            false,
+            Some(ParserDefault::Literal(ParserValue::Null)),
+            false,
+        ),
+        CreateTableField::Col("b".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Col(
+            "c".to_string(),
+            ColumnType::Int,
+            true,
+            Some(ParserDefault::Literal(ParserValue::Integer(7))),
+            false,
+        ),
+    ];
+    db_manager.create_table("test", fields).unwrap();
+
+    let check = |meta: &TableMetadata| {
+        assert_eq!(meta.columns[0].default(), Some(RecordValue::Null));
+        assert_eq!(meta.columns[1].default(), None);
+        assert_eq!(meta.columns[2].default(), Some(RecordValue::Int(7)));
+    };
+    check(&db_manager.describe_table("test").unwrap());
+
+    // The distinction survives a round trip through the catalog
+    drop(db_manager);
+    let mut db_manager = DatabaseManager::new(temp_dir.path().to_str().unwrap()).unwrap();
+    db_manager.use_database("testdb").unwrap();
+    check(&db_manager.describe_table("test").unwrap());
+}
+
+#[test]
+fn test_dynamic_defaults() {
+    use crate::catalog::DefaultValue;
+
+    let (temp_dir, mut db_manager) = setup_test_db();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    // The generated text has to fit the column
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "CREATE TABLE bad (d INT DEFAULT CURRENT_DATE);"
+        ),
+        Err(DatabaseError::TypeMismatch(_))
+    ));
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "CREATE TABLE bad (ts VARCHAR(10) DEFAULT CURRENT_TIMESTAMP);"
+        ),
+        Err(DatabaseError::TypeMismatch(_))
+    ));
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT NOT NULL, note VARCHAR(8) DEFAULT 'none', d VARCHAR(10) DEFAULT CURRENT_DATE, ts VARCHAR(19) DEFAULT CURRENT_TIMESTAMP);",
+    )
+    .unwrap();
+
+    let before = DefaultValue::CurrentDate.evaluate();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1), (2, 'x'), (3, 'y', '2000-01-01');",
+    )
+    .unwrap();
+    let after = DefaultValue::CurrentDate.evaluate();
+    let today = [before, after].map(|date| match date {
+        RecordValue::String(date) => date,
+        other => panic!("not a date: {:?}", other),
+    });
+
+    let rows = select_rows(&mut db_manager, "SELECT id, note, d, ts FROM t;");
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0][1], "none");
+    assert_eq!(rows[1][1], "x");
+    assert!(today.contains(&rows[0][2]));
+    assert!(today.contains(&rows[1][2]));
+    assert_eq!(rows[2][2], "2000-01-01");
+    for row in &rows {
+        let ts = &row[3];
+        assert_eq!(ts.len(), 19);
+        assert!(today.iter().any(|date| ts.starts_with(date.as_str())));
+        assert_eq!(&ts[10..11], " ");
+    }
+
+    // The dynamic default survives a round trip through the catalog
+    drop(db_manager);
+    let mut db_manager = DatabaseManager::new(temp_dir.path().to_str().unwrap()).unwrap();
+    db_manager.use_database("testdb").unwrap();
+    let meta = db_manager.describe_table("t").unwrap();
+    assert_eq!(
+        meta.columns[1].declared_default(),
+        Some(DefaultValue::Literal(RecordValue::String("none".into())))
+    );
+    assert_eq!(
+        meta.columns[2].declared_default(),
+        Some(DefaultValue::CurrentDate)
+    );
+    assert_eq!(
+        meta.columns[3].declared_default(),
+        Some(DefaultValue::CurrentTimestamp)
+    );
+    run(&mut db_manager, "INSERT INTO t VALUES (4);").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT d FROM t WHERE id = 4;")[0][0].len(),
+        10
+    );
+
+    // An omitted DEFAULT NULL column is filled in like any other default
+    run(
+        &mut db_manager,
+        "CREATE TABLE n (id INT, note VARCHAR(4) DEFAULT NULL, d VARCHAR(10) DEFAULT CURRENT_DATE);",
+    )
+    .unwrap();
+    run(&mut db_manager, "INSERT INTO n VALUES (1);").unwrap();
+    let rows = select_rows(&mut db_manager, "SELECT id, note, d FROM n;");
+    assert_eq!(rows[0][..2], ["1", "NULL"]);
+    assert_eq!(rows[0][2].len(), 10);
+}
+
+#[test]
+fn test_insert_fills_omitted_columns() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT NOT NULL, name VARCHAR(8), qty INT NOT NULL DEFAULT 1, flag BOOLEAN);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1), (2, 'b'), (3, 'c', 5), (4, 'd', 6, TRUE);",
+    )
+    .unwrap();
+    // Omitted columns take their default, or NULL when they have none
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT * FROM t ORDER BY id;"),
+        vec![
+            vec!["1", "NULL", "1", "NULL"],
+            vec!["2", "b", "1", "NULL"],
+            vec!["3", "c", "5", "NULL"],
+            vec!["4", "d", "6", "true"]
+        ]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE name IS NULL;"),
+        vec![vec!["1"]]
+    );
+
+    // A NOT NULL column without a default cannot be left out
+    run(
+        &mut db_manager,
+        "CREATE TABLE u (id INT NOT NULL, note VARCHAR(8) DEFAULT 'x', code INT NOT NULL);",
+    )
+    .unwrap();
+    assert!(matches!(
+        run(&mut db_manager, "INSERT INTO u VALUES (1, 'a', 7), (2, 'b');"),
+        Err(DatabaseError::NotNullViolation(column)) if column == "code"
+    ));
+    assert!(matches!(
+        run(&mut db_manager, "INSERT INTO u VALUES (3);"),
+        Err(DatabaseError::NotNullViolation(column)) if column == "code"
+    ));
+    assert!(select_rows(&mut db_manager, "SELECT * FROM u;").is_empty());
+}
+
+#[test]
+fn test_insert_validates_values() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (i INT, f FLOAT, s VARCHAR(4), d DECIMAL(5,2), b BOOLEAN);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 1.5, 'ab', 2.25, TRUE), (NULL, NULL, NULL, NULL, NULL), (2, 0.5, 'cd', 3, FALSE);",
+    )
+    .unwrap();
+
+    // Each literal of the wrong kind for its column is rejected, naming the column
+    for (row, column) in [
+        ("('1', 1.5, 'ab', 1, TRUE)", "i"),
+        ("(1.5, 1.5, 'ab', 1, TRUE)", "i"),
+        ("(TRUE, 1.5, 'ab', 1, TRUE)", "i"),
+        ("(1, 'x', 'ab', 1, TRUE)", "f"),
+        ("(1, 1, 'ab', 1, TRUE)", "f"),
+        ("(1, FALSE, 'ab', 1, TRUE)", "f"),
+        ("(1, 1.5, 12, 1, TRUE)", "s"),
+        ("(1, 1.5, 1.5, 1, TRUE)", "s"),
+        ("(1, 1.5, TRUE, 1, TRUE)", "s"),
+        ("(1, 1.5, 'abcde', 1, TRUE)", "s"),
+        ("(1, 1.5, 'ab', 'x', TRUE)", "d"),
+        ("(1, 1.5, 'ab', TRUE, TRUE)", "d"),
+        ("(1, 1.5, 'ab', 1, 1)", "b"),
+        ("(1, 1.5, 'ab', 1, 'true')", "b"),
+        ("(1, 1.5, 'ab', 1, 0.5)", "b"),
+        ("(3000000000, 1.5, 'ab', 1, TRUE)", "i"),
+    ] {
+        match run(&mut db_manager, &format!("INSERT INTO t VALUES {};", row)) {
+            Err(DatabaseError::TypeMismatch(message)) => assert!(
+                message.ends_with(&format!("(column {})", column)),
+                "{}: {}",
+                row,
+                message
+            ),
+            other => panic!("expected a type mismatch for {}, got {:?}", row, other),
+        }
+    }
+
+    // More values than columns is an error rather than a silent truncation
+    assert!(matches!(
+        run(&mut db_manager, "INSERT INTO t VALUES (1, 1.5, 'ab', 1, TRUE, 9);"),
+        Err(DatabaseError::TypeMismatch(message)) if message.contains("6 values")
+    ));
+
+    match run(&mut db_manager, "SELECT COUNT(*) FROM t;").unwrap() {
+        QueryResult::ResultSet(_, rows) => assert_eq!(rows, vec![vec!["3"]]),
+        other => panic!("not a result set: {:?}", other),
+    }
+}
+
+#[test]
+fn test_select_into_outfile() {
+    let (temp_dir, mut db_manager) = setup_test_db();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT, name VARCHAR(16), score FLOAT);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE copy (id INT, name VARCHAR(16), score FLOAT);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 'plain', 1.5), (2, 'a|b, \"c\"', NULL), (3, NULL, 0.25);",
+    )
+    .unwrap();
+
+    // The file reads back through LOAD DATA with the same delimiter
+    let out = temp_dir.path().join("t.txt");
+    let out = out.to_str().unwrap();
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            &format!(
+                "SELECT * FROM t INTO OUTFILE '{}' FIELDS TERMINATED BY '|';",
+                out
+            )
+        ),
+        Ok(QueryResult::RowsAffected(3))
+    ));
+    run(
+        &mut db_manager,
+        &format!(
+            "LOAD DATA INFILE '{}' INTO TABLE copy FIELDS TERMINATED BY '|';",
+            out
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT * FROM copy;"),
+        select_rows(&mut db_manager, "SELECT * FROM t;")
+    );
+
+    // The header line is optional, and the SELECT can filter and project
+    let out = temp_dir.path().join("names.csv");
+    let out = out.to_str().unwrap();
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            &format!(
+                "SELECT id, name AS label FROM t WHERE id > 1 INTO OUTFILE '{}' WITH HEADER;",
+                out
+            )
+        ),
+        Ok(QueryResult::RowsAffected(2))
+    ));
+    assert_eq!(
+        std::fs::read_to_string(out).unwrap(),
+        "id,label\n2,\"a|b, \"\"c\"\"\"\n3,NULL\n"
+    );
+
+    // An existing file is left alone
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            &format!("SELECT * FROM t INTO OUTFILE '{}';", out)
+        ),
+        Err(DatabaseError::IoError(_))
+    ));
+    assert!(
+        std::fs::read_to_string(out)
+            .unwrap()
+            .starts_with("id,label")
+    );
+}
+
+#[test]
+fn test_show_status() {
+    let (temp_dir, mut db_manager) = setup_test_db();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let status = |db_manager: &mut DatabaseManager| match run(db_manager, "SHOW STATUS;").unwrap() {
+        QueryResult::ResultSet(headers, rows) => {
+            assert_eq!(headers, vec!["VARIABLE", "VALUE"]);
+            rows.into_iter()
+                .map(|row| (row[0].clone(), row[1].parse::<usize>().unwrap()))
+                .collect::<HashMap<_, _>>()
+        }
+        other => panic!("not a result set: {:?}", other),
+    };
+
+    run(&mut db_manager, "CREATE TABLE t (id INT, name VARCHAR(8));").unwrap();
+    let csv_file = temp_dir.path().join("t.csv");
+    std::fs::write(&csv_file, "1,a\n2,b\n3,c\n").unwrap();
+    run(&mut db_manager, "FLUSH STATUS;").unwrap();
+    assert!(status(&mut db_manager).values().all(|&value| value == 0));
+
+    // The bulk load clears the pool, which shows up as evictions
+    run(
+        &mut db_manager,
+        &format!(
+            "LOAD DATA INFILE '{}' INTO TABLE t FIELDS TERMINATED BY ',';",
+            csv_file.to_str().unwrap()
+        ),
+    )
+    .unwrap();
+    let after_load = status(&mut db_manager);
+    assert!(after_load["buffer_evictions"] > 0);
+    assert!(after_load["buffer_dirty_flushes"] > 0);
+
+    // Scanning the loaded pages again is served from the pool
+    run(&mut db_manager, "SELECT * FROM t;").unwrap();
+    run(&mut db_manager, "FLUSH STATUS;").unwrap();
+    run(&mut db_manager, "SELECT * FROM t;").unwrap();
+    let stats = status(&mut db_manager);
+    assert!(stats["buffer_hits"] > 0);
+    assert_eq!(stats["buffer_misses"], 0);
+    assert_eq!(
+        stats["buffer_fetches"],
+        stats["buffer_hits"] + stats["buffer_misses"]
+    );
+    assert_eq!(db_manager.buffer_stats().hits, stats["buffer_hits"]);
+}
+
+#[test]
+fn test_status_is_not_reserved() {
+    let (_temp, mut db_manager) = setup_test_db();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    // Only SHOW/FLUSH give STATUS a meaning, in either keyword mode
+    for sql in [
+        "CREATE TABLE status (id INT, status VARCHAR(8));",
+        "INSERT INTO status VALUES (1, 'open'), (2, 'closed');",
+    ] {
+        run(&mut db_manager, sql).unwrap();
+    }
+    let query = crate::lexer_parser::parse_case_insensitive(
+        "select status from status where status = 'open';",
+    )
+    .unwrap()
+    .remove(0);
+    match db_manager.execute_query(query).unwrap() {
+        QueryResult::ResultSet(headers, rows) => {
+            assert_eq!(headers, vec!["status"]);
+            assert_eq!(rows, vec![vec!["open"]]);
+        }
+        other => panic!("not a result set: {:?}", other),
+    }
+    let query = crate::lexer_parser::parse_case_insensitive("show status;")
+        .unwrap()
+        .remove(0);
+    assert!(matches!(
+        db_manager.execute_query(query).unwrap(),
+        QueryResult::ResultSet(..)
+    ));
+}
+
+#[test]
+fn test_load_data_infile() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Char(10), true, None, false),
+        CreateTableField::Col("c".to_string(), ColumnType::Float, true, None, false),
+    ];
+
+    db_manager.create_table("test", fields).unwrap();
+
+    // Create test CSV file
+    let csv_content = "1,hello,1.5\n2, world,2.5\n3,rust,3.5\n";
+    let csv_file = _temp.path().join("test.csv");
+    std::fs::write(&csv_file, csv_content).unwrap();
+
+    // Load data
+    let count = db_manager
+        .load_data_infile(csv_file.to_str().unwrap(), "test", ',')
+        .unwrap();
+    assert_eq!(count, 3);
+
+    // Verify loaded data
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+
+    let (_, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1][1], " world");
+}
+
+#[test]
+fn test_load_data_infile_delimiters() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Char(10), true, None, false),
+    ];
+    db_manager.create_table("test", fields).unwrap();
+
+    let all_rows = |db_manager: &mut DatabaseManager| {
+        let clause = SelectClause {
+            selectors: Selectors::All,
+            table: vec!["test".to_string()],
+            table_aliases: vec![],
+            selector_aliases: vec![],
+            where_clauses: vec![],
+            group_by: vec![],
+            having: Vec::new(),
+            order_by: vec![],
+            limit: None,
+            limit_percent: false,
+            offset: None,
+        };
+        db_manager.select(clause).unwrap().1
+    };
+
+    let tsv_file = _temp.path().join("test.tsv");
+    std::fs::write(&tsv_file, "1\ta,b\n2\tc|d\n").unwrap();
+    let count = db_manager
+        .load_data_infile(tsv_file.to_str().unwrap(), "test", '\t')
+        .unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(all_rows(&mut db_manager)[0], vec!["1", "a,b"]);
+
+    let psv_file = _temp.path().join("test.psv");
+    std::fs::write(&psv_file, "3|e,f\n").unwrap();
+    let count = db_manager
+        .load_data_infile(psv_file.to_str().unwrap(), "test", '|')
+        .unwrap();
+    assert_eq!(count, 1);
+    assert_eq!(all_rows(&mut db_manager), vec![vec!["3", "e,f"]]);
+
+    // A full-width comma does not fit in one byte; the table is left untouched
+    let csv_file = _temp.path().join("test.csv");
+    std::fs::write(&csv_file, "4，g\n").unwrap();
+    assert!(matches!(
+        db_manager.load_data_infile(csv_file.to_str().unwrap(), "test", '，'),
+        Err(DatabaseError::InvalidDelimiter('，'))
+    ));
+    assert_eq!(all_rows(&mut db_manager), vec![vec!["3", "e,f"]]);
+}
+
+#[test]
+fn test_varchar_short_string() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![CreateTableField::Col(
+        "name".to_string(),
+        ColumnType::Char(5), // VARCHAR(5)
+        true,
+        None,
+        false,
+    )];
+
+    db_manager.create_table("test", fields).unwrap();
+
+    // Insert short string
+    let rows = vec![vec![ParserValue::String("hi".to_string())]];
+    db_manager.insert("test", rows).unwrap();
+
+    // Verify it's stored and retrieved correctly
+    let clause = SelectClause {
+        selectors: Selectors::All,
+        table: vec!["test".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+
+    let (_, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows.len(), 1);
+    // String should be trimmed when displayed
+    assert_eq!(rows[0][0], "hi");
+}
+
+#[test]
+fn test_two_table_join_basic() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields1 = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Char(10), true, None, false),
+    ];
+    let fields2 = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("c".to_string(), ColumnType::Char(10), true, None, false),
+    ];
+
+    db_manager.create_table("t1", fields1).unwrap();
+    db_manager.create_table("t2", fields2).unwrap();
+
+    let rows_t1 = vec![
+        vec![
+            ParserValue::Integer(1),
+            ParserValue::String("x".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(2),
+            ParserValue::String("y".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(3),
+            ParserValue::String("z".to_string()),
+        ],
+    ];
+    let rows_t2 = vec![
+        vec![
+            ParserValue::Integer(2),
+            ParserValue::String("p".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(3),
+            ParserValue::String("q".to_string()),
+        ],
+        vec![
+            ParserValue::Integer(4),
+            ParserValue::String("r".to_string()),
+        ],
+    ];
+
+    db_manager.insert("t1", rows_t1).unwrap();
+    db_manager.insert("t2", rows_t2).unwrap();
+
+    let clause = SelectClause {
+        selectors: Selectors::List(vec![
+            Selector::Column(TableColumn {
+                table: Some("t1".to_string()),
+                column: "a".to_string(),
+            }),
+            Selector::Column(TableColumn {
+                table: Some("t1".to_string()),
+                column: "b".to_string(),
+            }),
+            Selector::Column(TableColumn {
+                table: Some("t2".to_string()),
+                column: "a".to_string(),
+            }),
+            Selector::Column(TableColumn {
+                table: Some("t2".to_string()),
+                column: "c".to_string(),
+            }),
+        ]),
+        table: vec!["t1".to_string(), "t2".to_string()],
+        table_aliases: vec![],
+        selector_aliases: vec![],
+        where_clauses: vec![WhereClause::Op(
+            TableColumn {
+                table: Some("t1".to_string()),
+                column: "a".to_string(),
+            },
+            Operator::Eq,
+            Expression::Column(TableColumn {
+                table: Some("t2".to_string()),
+                column: "a".to_string(),
+            }),
+        )],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+
+    let (headers, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(headers, vec!["a", "b", "a", "c"]);
+    assert_eq!(
+        rows,
+        vec![
+            vec![
+                "2".to_string(),
+                "y".to_string(),
+                "2".to_string(),
+                "p".to_string()
+            ],
+            vec![
+                "3".to_string(),
+                "z".to_string(),
+                "3".to_string(),
+                "q".to_string()
+            ],
+        ]
+    );
+}
+
+#[test]
+fn test_select_column_aliases() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(&mut db_manager, "CREATE TABLE t (id INT, k VARCHAR(4));").unwrap();
+    run(&mut db_manager, "CREATE TABLE u (id INT, v INT);").unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 'a'), (2, 'b'), (3, 'b');",
+    )
+    .unwrap();
+    run(&mut db_manager, "INSERT INTO u VALUES (1, 10), (3, 30);").unwrap();
+
+    // Without aliases the headers are unchanged
+    let (headers, _) = select_with_headers(&mut db_manager, "SELECT id, k FROM t;");
+    assert_eq!(headers, vec!["id", "k"]);
+
+    let (headers, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT id AS ident, k FROM t WHERE id = 1;",
+    );
+    assert_eq!(headers, vec!["ident", "k"]);
+    assert_eq!(rows, vec![vec!["1", "a"]]);
+
+    // An aggregate alias also names an ORDER BY target
+    let (headers, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT k AS key, COUNT(*) AS n FROM t GROUP BY k ORDER BY n DESC;",
+    );
+    assert_eq!(headers, vec!["key", "n"]);
+    assert_eq!(rows, vec![vec!["b", "2"], vec!["a", "1"]]);
+
+    let (headers, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT t.k AS name, u.v AS value FROM t, u WHERE t.id = u.id ORDER BY value;",
+    );
+    assert_eq!(headers, vec!["name", "value"]);
+    assert_eq!(rows, vec![vec!["a", "10"], vec!["b", "30"]]);
+}
+
+#[test]
+fn test_select_with_table_aliases() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let select = |db_manager: &mut DatabaseManager, sql: &str| match crate::lexer_parser::parse(sql)
+        .unwrap()
+        .remove(0)
+    {
+        crate::lexer_parser::Query::TableStmt(TableStatement::Select(clause)) => {
+            db_manager.select(clause)
+        }
+        other => panic!("not a SELECT: {:?}", other),
+    };
+
+    let fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("name".to_string(), ColumnType::Char(10), false, None, false),
+        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
+            "customers".to_string(),
+            vec!["id".to_string()],
+        ))),
+    ];
+    db_manager.create_table("customers", fields).unwrap();
+    let fields = vec![
+        CreateTableField::Col("oid".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("cid".to_string(), ColumnType::Int, false, None, false),
+    ];
+    db_manager.create_table("orders", fields).unwrap();
+    let fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("manager".to_string(), ColumnType::Int, false, None, false),
+    ];
+    db_manager.create_table("staff", fields).unwrap();
+
+    let customers = [(1, "ann"), (2, "bob"), (3, "cat")]
+        .into_iter()
+        .map(|(id, name)| vec![ParserValue::Integer(id), ParserValue::String(name.into())])
+        .collect();
+    db_manager.insert("customers", customers).unwrap();
+    let orders = [(10, 2), (11, 3), (12, 2)]
+        .into_iter()
+        .map(|(oid, cid)| vec![ParserValue::Integer(oid), ParserValue::Integer(cid)])
+        .collect();
+    db_manager.insert("orders", orders).unwrap();
+    let staff = vec![
+        vec![ParserValue::Integer(1), ParserValue::Null],
+        vec![ParserValue::Integer(2), ParserValue::Integer(1)],
+        vec![ParserValue::Integer(3), ParserValue::Integer(1)],
+    ];
+    db_manager.insert("staff", staff).unwrap();
+
+    let (headers, rows) = select(
+        &mut db_manager,
+        "SELECT c.name, o.oid FROM customers c, orders o WHERE c.id = o.cid ORDER BY o.oid;",
+    )
+    .unwrap();
+    assert_eq!(headers, vec!["name", "oid"]);
+    assert_eq!(
+        rows,
+        vec![vec!["bob", "10"], vec!["cat", "11"], vec!["bob", "12"]]
+    );
+
+    // An aliased condition on the left table can still use its primary key index
+    let (_, rows) = select(
+        &mut db_manager,
+        "SELECT o.oid FROM customers c, orders o WHERE c.id = 2 AND o.cid = c.id;",
+    )
+    .unwrap();
+    assert_eq!(rows, vec![vec!["10"], vec!["12"]]);
+
+    // Self-join: the aliases tell the two sides apart
+    let (_, rows) = select(
+        &mut db_manager,
+        "SELECT e.id, m.id FROM staff e, staff m WHERE e.manager = m.id;",
+    )
+    .unwrap();
+    assert_eq!(rows, vec![vec!["2", "1"], vec!["3", "1"]]);
+
+    // A single aliased table
+    let (_, rows) = select(
+        &mut db_manager,
+        "SELECT c.name FROM customers c WHERE c.id > 1 ORDER BY c.id DESC;",
+    )
+    .unwrap();
+    assert_eq!(rows, vec![vec!["cat"], vec!["bob"]]);
+
+    assert!(select(&mut db_manager, "SELECT * FROM staff, staff;").is_err());
+    assert!(select(&mut db_manager, "SELECT * FROM staff s, orders s;").is_err());
+    assert!(select(&mut db_manager, "SELECT x.id FROM customers c;").is_err());
+}
+
+#[test]
+fn test_self_join_probes_index() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("name".to_string(), ColumnType::Char(10), false, None, false),
+        CreateTableField::Col(
+            "manager_id".to_string(),
+            ColumnType::Int,
+            false,
+            None,
+            false,
+        ),
+        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
+            "employees".to_string(),
+            vec!["id".to_string()],
+        ))),
+    ];
+    db_manager.create_table("employees", fields).unwrap();
+
+    let employees = vec![
+        vec![
+            ParserValue::Integer(1),
+            ParserValue::String("boss".into()),
+            ParserValue::Null,
+        ],
+        vec![
+            ParserValue::Integer(2),
+            ParserValue::String("mid".into()),
+            ParserValue::Integer(1),
+        ],
+        vec![
+            ParserValue::Integer(3),
+            ParserValue::String("low".into()),
+            ParserValue::Integer(2),
+        ],
+        vec![
+            ParserValue::Integer(4),
+            ParserValue::String("new".into()),
+            ParserValue::Integer(2),
+        ],
+    ];
+    db_manager.insert("employees", employees).unwrap();
+
+    let column = |table: &str, column: &str| TableColumn {
+        table: Some(table.to_string()),
+        column: column.to_string(),
+    };
+    let clause = SelectClause {
+        selectors: Selectors::List(vec![
+            Selector::Column(column("a", "name")),
+            Selector::Column(column("b", "name")),
+        ]),
+        table: vec!["employees".to_string(), "employees".to_string()],
+        table_aliases: vec![Some("a".to_string()), Some("b".to_string())],
+        selector_aliases: vec![],
+        where_clauses: vec![WhereClause::Op(
+            column("a", "manager_id"),
+            Operator::Eq,
+            Expression::Column(column("b", "id")),
+        )],
+        group_by: vec![],
+        having: Vec::new(),
+        order_by: vec![],
+        limit: None,
+        limit_percent: false,
+        offset: None,
+    };
+
+    let expected = vec![vec!["mid", "boss"], vec!["low", "mid"], vec!["new", "mid"]];
+    let (headers, rows) = db_manager.select(clause.clone()).unwrap();
+    assert_eq!(headers, vec!["name", "name"]);
+    assert_eq!(rows, expected);
+
+    // Hide employee 2 from the primary key index: the probe no longer finds
+    // a manager for 3 and 4, which shows the index is what drives the join
+    let rid = db_manager
+        .index_manager
+        .search("employees", "id", 2)
+        .unwrap();
+    db_manager
+        .index_manager
+        .delete_entry("employees", "id", 2, rid)
+        .unwrap();
+    let (_, rows) = db_manager.select(clause.clone()).unwrap();
+    assert_eq!(rows, vec![vec!["mid", "boss"]]);
+
+    db_manager.set_use_indexes(false);
+    let (_, rows) = db_manager.select(clause).unwrap();
+    assert_eq!(rows, expected);
+}
+
+#[test]
+fn test_persistence() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_path = temp_dir.path().to_str().unwrap();
+
+    // Create database and table in first session
+    {
+        let mut db_manager = DatabaseManager::new(data_path).unwrap();
+        db_manager.create_database("testdb").unwrap();
+        db_manager.use_database("testdb").unwrap();
+
+        let fields = vec![CreateTableField::Col(
+            "a".to_string(),
+            ColumnType::Int,
+            true,
+            None,
+            false,
+        )];
+        db_manager.create_table("test", fields).unwrap();
+
+        let rows = vec![vec![ParserValue::Integer(42)]];
+        db_manager.insert("test", rows).unwrap();
+    }
+
+    // Reopen and verify data persists
+    {
+        let mut db_manager = DatabaseManager::new(data_path).unwrap();
+        db_manager.use_database("testdb").unwrap();
+
+        let clause = SelectClause {
+            selectors: Selectors::All,
+            table: vec!["test".to_string()],
+            table_aliases: vec![],
+            selector_aliases: vec![],
+            where_clauses: vec![],
+            group_by: vec![],
+            having: Vec::new(),
+            order_by: vec![],
+            limit: None,
+            limit_percent: false,
+            offset: None,
+        };
+
+        let (_, rows) = db_manager.select(clause).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][0], "42");
+    }
+}
+
+#[test]
+fn test_foreign_key_insert_update_validation() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let parent_fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
+            "parent".to_string(),
+            vec!["id".to_string()],
+        ))),
+    ];
+    db_manager.create_table("parent", parent_fields).unwrap();
+    db_manager
+        .execute_alter_statement(AlterStatement::AddIndex(
+            "parent".to_string(),
+            None,
+            vec!["id".to_string()],
+            None,
+        ))
+        .unwrap();
+
+    let child_fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("parent_id".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Fkey(Box::new(AlterStatement::AddFKey(
+            "child".to_string(),
+            Some("fk_parent".to_string()),
+            vec!["parent_id".to_string()],
+            "parent".to_string(),
+            vec!["id".to_string()],
+        ))),
+    ];
+    db_manager.create_table("child", child_fields).unwrap();
+
+    db_manager
+        .insert("parent", vec![vec![ParserValue::Integer(1)]])
+        .unwrap();
+    db_manager
+        .insert(
+            "child",
+            vec![vec![ParserValue::Integer(1), ParserValue::Integer(1)]],
+        )
+        .unwrap();
+    db_manager
+        .insert(
+            "child",
+            vec![vec![ParserValue::Integer(2), ParserValue::Null]],
+        )
+        .unwrap();
+
+    let bad_insert = db_manager.insert(
+        "child",
+        vec![vec![ParserValue::Integer(3), ParserValue::Integer(99)]],
+    );
+    assert!(matches!(
+        bad_insert,
+        Err(DatabaseError::ForeignKeyViolation(_))
+    ));
+
+    let updates = vec![("parent_id".to_string(), ParserValue::Integer(100))];
+    let bad_update = db_manager.update("child", updates, None);
+    assert!(matches!(
+        bad_update,
+        Err(DatabaseError::ForeignKeyViolation(_))
+    ));
+}
+
+#[test]
+fn test_foreign_key_add_constraint_validation() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let parent_fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
+            "parent".to_string(),
+            vec!["id".to_string()],
+        ))),
+    ];
+    db_manager.create_table("parent", parent_fields).unwrap();
+    db_manager
+        .execute_alter_statement(AlterStatement::AddIndex(
+            "parent".to_string(),
+            None,
+            vec!["id".to_string()],
+            None,
+        ))
+        .unwrap();
+
+    let child_fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("parent_id".to_string(), ColumnType::Int, false, None, false),
+    ];
+    db_manager.create_table("child", child_fields).unwrap();
+
+    db_manager
+        .insert("parent", vec![vec![ParserValue::Integer(1)]])
+        .unwrap();
+    db_manager
+        .insert(
+            "child",
+            vec![vec![ParserValue::Integer(1), ParserValue::Integer(1)]],
+        )
+        .unwrap();
+    db_manager
+        .insert(
+            "child",
+            vec![vec![ParserValue::Integer(2), ParserValue::Integer(99)]],
+        )
+        .unwrap();
+
+    let add_fk = db_manager.execute_alter_statement(AlterStatement::AddFKey(
+        "child".to_string(),
+        Some("fk_parent".to_string()),
+        vec!["parent_id".to_string()],
+        "parent".to_string(),
+        vec!["id".to_string()],
+    ));
+    assert!(matches!(add_fk, Err(DatabaseError::ForeignKeyViolation(_))));
+
+    let where_clauses = vec![WhereClause::Op(
+        TableColumn {
+            table: None,
+            column: "parent_id".to_string(),
+        },
+        Operator::Eq,
+        Expression::Value(ParserValue::Integer(99)),
+    )];
+    db_manager.delete("child", Some(where_clauses)).unwrap();
+
+    db_manager
+        .execute_alter_statement(AlterStatement::AddFKey(
+            "child".to_string(),
+            Some("fk_parent".to_string()),
+            vec!["parent_id".to_string()],
+            "parent".to_string(),
+            vec!["id".to_string()],
+        ))
+        .unwrap();
+}
+
+#[test]
+fn test_foreign_key_delete_restriction() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let parent_fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
+            "parent".to_string(),
+            vec!["id".to_string()],
+        ))),
+    ];
+    db_manager.create_table("parent", parent_fields).unwrap();
+    db_manager
+        .execute_alter_statement(AlterStatement::AddIndex(
+            "parent".to_string(),
+            None,
+            vec!["id".to_string()],
+            None,
+        ))
+        .unwrap();
+
+    let child_fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("parent_id".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Fkey(Box::new(AlterStatement::AddFKey(
+            "child".to_string(),
+            Some("fk_parent".to_string()),
+            vec!["parent_id".to_string()],
+            "parent".to_string(),
+            vec!["id".to_string()],
+        ))),
+    ];
+    db_manager.create_table("child", child_fields).unwrap();
+
+    db_manager
+        .insert("parent", vec![vec![ParserValue::Integer(1)]])
+        .unwrap();
+    db_manager
+        .insert(
+            "child",
+            vec![vec![ParserValue::Integer(1), ParserValue::Integer(1)]],
+        )
+        .unwrap();
+
+    let delete_parent = db_manager.delete("parent", None);
+    assert!(matches!(
+        delete_parent,
+        Err(DatabaseError::ForeignKeyViolation(_))
+    ));
+
+    let where_child = vec![WhereClause::Op(
+        TableColumn {
+            table: None,
+            column: "id".to_string(),
+        },
+        Operator::Eq,
+        Expression::Value(ParserValue::Integer(1)),
+    )];
+    db_manager.delete("child", Some(where_child)).unwrap();
+    db_manager.delete("parent", None).unwrap();
+}
+
+#[test]
+fn test_statement_timeout() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![CreateTableField::Col(
+        "id".to_string(),
+        ColumnType::Int,
+        false,
+        None,
+        false,
+    )];
+    db_manager.create_table("t", fields).unwrap();
+    let rows = (0..50).map(|i| vec![ParserValue::Integer(i)]).collect();
+    db_manager.insert("t", rows).unwrap();
+
+    let select = |db_manager: &mut DatabaseManager, sql: &str| match crate::lexer_parser::parse(sql)
+        .unwrap()
+        .remove(0)
+    {
+        crate::lexer_parser::Query::TableStmt(TableStatement::Select(clause)) => {
+            db_manager.select(clause)
+        }
+        other => panic!("not a SELECT: {:?}", other),
+    };
+    let cross_join = "SELECT * FROM t a, t b;";
+
+    db_manager.set_statement_timeout(Some(Duration::ZERO));
+    assert!(matches!(
+        select(&mut db_manager, cross_join),
+        Err(DatabaseError::Timeout(0))
+    ));
+    assert!(matches!(
+        db_manager.delete("t", None),
+        Err(DatabaseError::Timeout(0))
+    ));
+    assert!(matches!(
+        db_manager.update("t", vec![("id".to_string(), ParserValue::Integer(0))], None),
+        Err(DatabaseError::Timeout(0))
+    ));
+
+    db_manager.set_statement_timeout(None);
+    let (_, rows) = select(&mut db_manager, cross_join).unwrap();
+    assert_eq!(rows.len(), 2500);
+    assert_eq!(db_manager.delete("t", None).unwrap(), 50);
+}
+
+#[test]
+fn test_cancel_running_query() {
+    let registry = Arc::new(QueryRegistry::new());
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.set_query_registry(Some(registry.clone()));
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+    run(&mut db_manager, "CREATE TABLE t (id INT);").unwrap();
+    let rows = (0..3000).map(|i| vec![ParserValue::Integer(i)]).collect();
+    db_manager.insert("t", rows).unwrap();
+    assert!(registry.list_running().is_empty());
+
+    // A nested loop join over 9M pairs that never matches
+    let handle = std::thread::spawn(move || {
+        let result = run(
+            &mut db_manager,
+            "SELECT a.id FROM t a, t b WHERE a.id > b.id + 5000;",
+        );
+        (db_manager, result)
+    });
+
+    let running = loop {
+        let running = registry.list_running();
+        if !running.is_empty() {
+            break running;
+        }
+        std::thread::yield_now();
+    };
+    assert_eq!(running.len(), 1);
+    assert_eq!(running[0].statement, "SELECT FROM t, t");
+    assert!(registry.cancel(running[0].id));
+
+    let (mut db_manager, result) = handle.join().unwrap();
+    assert!(matches!(result, Err(DatabaseError::Cancelled(id)) if id == running[0].id));
+    assert!(registry.list_running().is_empty());
+    assert!(!registry.cancel(running[0].id));
+
+    // Later statements get fresh ids and are unaffected
+    assert!(matches!(
+        run(&mut db_manager, "SELECT * FROM t WHERE id = 7;"),
+        Ok(QueryResult::ResultSet(_, rows)) if rows.len() == 1
+    ));
+}
+
+#[test]
+fn test_get_by_rid() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Col("name".to_string(), ColumnType::Char(10), false, None, false),
+    ];
+    db_manager.create_table("t", fields).unwrap();
+    db_manager
+        .insert(
+            "t",
+            vec![
+                vec![ParserValue::Integer(1), ParserValue::String("a".into())],
+                vec![ParserValue::Integer(2), ParserValue::String("b".into())],
+            ],
+        )
+        .unwrap();
+
+    let first = RecordId::new(0, 0);
+    let second = RecordId::new(0, 1);
+    let record = db_manager.get_by_rid("t", first).unwrap().unwrap();
+    assert_eq!(record.get(0), Some(&RecordValue::Int(1)));
+
+    // Updates happen in place, so the RID keeps addressing the same row
+    let where_id = |id| {
+        Some(vec![WhereClause::Op(
+            TableColumn {
+                table: None,
+                column: "id".to_string(),
+            },
+            Operator::Eq,
+            Expression::Value(ParserValue::Integer(id)),
+        )])
+    };
+    db_manager
+        .update(
+            "t",
+            vec![("name".to_string(), ParserValue::String("z".into()))],
+            where_id(2),
+        )
+        .unwrap();
+    let record = db_manager.get_by_rid("t", second).unwrap().unwrap();
+    assert_eq!(record.get(1), Some(&RecordValue::String("z".into())));
+
+    db_manager.delete("t", where_id(1)).unwrap();
+    assert!(db_manager.get_by_rid("t", first).unwrap().is_none());
+    assert!(
+        db_manager
+            .get_by_rid("t", RecordId::new(0, 10_000))
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        db_manager
+            .get_by_rid("t", RecordId::new(99, 0))
+            .unwrap()
+            .is_none()
+    );
+    assert!(matches!(
+        db_manager.get_by_rid("missing", first),
+        Err(DatabaseError::CatalogError(_))
+    ));
+}
+
+#[test]
+fn test_scan_table() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("name".to_string(), ColumnType::Char(10), false, None, false),
+    ];
+    db_manager.create_table("t", fields).unwrap();
+    db_manager
+        .insert(
+            "t",
+            vec![
+                vec![ParserValue::Integer(1), ParserValue::String("a".into())],
+                vec![ParserValue::Integer(2), ParserValue::Null],
+            ],
+        )
+        .unwrap();
+
+    let rows: Vec<TypedRow> = db_manager
+        .scan_table("t")
+        .unwrap()
+        .collect::<DatabaseResult<_>>()
+        .unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(&*rows[0].columns, ["id".to_string(), "name".to_string()]);
+    assert_eq!(rows[0].get("id"), Some(&RecordValue::Int(1)));
+    assert_eq!(rows[0].get("name"), Some(&RecordValue::String("a".into())));
+    assert_eq!(rows[1].get("name"), Some(&RecordValue::Null));
+    assert_eq!(rows[1].get("missing"), None);
+
+    // The RID addresses the same row through get_by_rid
+    let record = db_manager.get_by_rid("t", rows[1].rid).unwrap().unwrap();
+    assert_eq!(record.values(), rows[1].values.as_slice());
+
+    assert!(matches!(
+        db_manager.scan_table("nope"),
+        Err(DatabaseError::CatalogError(_))
+    ));
+}
+
+#[test]
+fn test_primary_key_violation_reports_key() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("name".to_string(), ColumnType::Char(10), false, None, false),
+        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
+            "single".to_string(),
+            vec!["id".to_string()],
+        ))),
+    ];
+    db_manager.create_table("single", fields).unwrap();
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Char(10), true, None, false),
+        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
+            "pair".to_string(),
+            vec!["a".to_string(), "b".to_string()],
+        ))),
+    ];
+    db_manager.create_table("pair", fields).unwrap();
+
+    let row = |id, name: &str| vec![ParserValue::Integer(id), ParserValue::String(name.into())];
+    let violation = |result: DatabaseResult<usize>| match result {
+        Err(DatabaseError::PrimaryKeyViolation(key)) => key,
+        other => panic!("expected primary key violation, got {:?}", other),
+    };
+
+    // Collision inside a single batch
+    assert_eq!(
+        violation(db_manager.insert("single", vec![row(1, "x"), row(1, "y")])),
+        "(1)"
+    );
+
+    // Collision with an existing row, via the index and via a scan
+    db_manager.insert("single", vec![row(7, "x")]).unwrap();
+    assert_eq!(
+        violation(db_manager.insert("single", vec![row(7, "y")])),
+        "(7)"
+    );
+    db_manager.set_use_indexes(false);
+    assert_eq!(
+        violation(db_manager.insert("single", vec![row(7, "y")])),
+        "(7)"
+    );
+
+    db_manager.insert("pair", vec![row(1, "k")]).unwrap();
+    assert_eq!(
+        violation(db_manager.insert("pair", vec![row(1, "k")])),
+        "(1, k)"
+    );
+}
+
+#[test]
+fn test_hash_join_matches_nested_loop() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    run(&mut db_manager, "CREATE DATABASE testdb;").unwrap();
+    run(&mut db_manager, "USE testdb;").unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE a (id INT, name VARCHAR(8), price DECIMAL(6,2));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE b (aid INT, name VARCHAR(8), price DECIMAL(6,1));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO a VALUES (1, 'x', 1.50), (2, 'y', 2.00), (2, 'z', NULL), (NULL, 'x', 3.00);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO b VALUES (2, 'y', 2.0), (1, 'x', 1.5), (NULL, NULL, 3.0), (2, 'q', 9.9), (3, 'x', NULL);",
+    )
+    .unwrap();
+
+    // Duplicate keys pair up on both sides and NULL joins nothing; a
+    // condition only `<=` and `>=` can express runs as a nested loop
+    for (hashed, nested) in [
+        (
+            "SELECT a.name, b.name FROM a, b WHERE a.id = b.aid;",
+            "SELECT a.name, b.name FROM a, b WHERE a.id <= b.aid AND a.id >= b.aid;",
+        ),
+        (
+            "SELECT a.id, b.aid FROM a, b WHERE b.name = a.name AND b.aid > 1;",
+            "SELECT a.id, b.aid FROM a, b WHERE b.name <= a.name AND b.name >= a.name AND b.aid > 1;",
+        ),
+        // Decimals of different scales are compared, not hashed
+        (
+            "SELECT a.id, b.aid FROM a, b WHERE a.price = b.price;",
+            "SELECT a.id, b.aid FROM a, b WHERE a.price <= b.price AND a.price >= b.price;",
+        ),
+    ] {
+        let rows = select_rows(&mut db_manager, hashed);
+        assert!(!rows.is_empty(), "{}", hashed);
+        assert_eq!(rows, select_rows(&mut db_manager, nested), "{}", hashed);
+    }
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT a.name, b.name FROM a, b WHERE a.id = b.aid;"
+        ),
+        vec![
+            vec!["x", "x"],
+            vec!["y", "y"],
+            vec!["y", "q"],
+            vec!["z", "y"],
+            vec!["z", "q"]
+        ]
+    );
+}
+
+/// Time a 10k x 10k equality join through the hash join against the
+/// nested loop; run with `cargo test --release -- --ignored --nocapture`
+#[test]
+#[ignore]
+fn bench_hash_join_vs_nested_loop() {
+    use std::time::Instant;
+
+    let mut db_manager = DatabaseManager::in_memory();
+    run(&mut db_manager, "CREATE DATABASE testdb;").unwrap();
+    run(&mut db_manager, "USE testdb;").unwrap();
+    run(&mut db_manager, "CREATE TABLE l (k INT, v INT);").unwrap();
+    run(&mut db_manager, "CREATE TABLE r (k INT, v INT);").unwrap();
+    let rows: Vec<Vec<ParserValue>> = (0..10_000)
+        .map(|i| {
+            vec![
+                ParserValue::Integer(i),
+                ParserValue::Integer(i * 7 % 10_000),
+            ]
+        })
+        .collect();
+    db_manager.insert("l", rows.clone()).unwrap();
+    db_manager.insert("r", rows).unwrap();
+
+    let mut time = |sql: &str| {
+        let start = Instant::now();
+        let QueryResult::ResultSet(_, rows) = run(&mut db_manager, sql).unwrap() else {
+            panic!("not a result set");
+        };
+        (start.elapsed(), rows.len())
+    };
+    let (hashed, hashed_rows) = time("SELECT l.v, r.v FROM l, r WHERE l.k = r.v;");
+    let (nested, nested_rows) = time("SELECT l.v, r.v FROM l, r WHERE l.k <= r.v AND l.k >= r.v;");
+    assert_eq!(hashed_rows, 10_000);
+    assert_eq!(nested_rows, hashed_rows);
+    println!("hash join: {:?}, nested loop: {:?}", hashed, nested);
+}
+
+#[test]
+fn test_join_where_uses_left_composite_index() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let select = |db_manager: &mut DatabaseManager, sql: &str| match crate::lexer_parser::parse(sql)
+        .unwrap()
+        .remove(0)
+    {
+        crate::lexer_parser::Query::TableStmt(TableStatement::Select(clause)) => {
+            db_manager.select(clause)
+        }
+        other => panic!("not a SELECT: {:?}", other),
+    };
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("v".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
+            "l".to_string(),
+            vec!["a".to_string(), "b".to_string()],
+        ))),
+    ];
+    db_manager.create_table("l", fields).unwrap();
+    let fields = vec![
+        CreateTableField::Col("x".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Int, false, None, false),
+    ];
+    db_manager.create_table("r", fields).unwrap();
+
+    let rows = (0..5)
+        .flat_map(|a| (0..5).map(move |b| (a, b)))
+        .map(|(a, b)| {
+            vec![
+                ParserValue::Integer(a),
+                ParserValue::Integer(b),
+                ParserValue::Integer(a * 10 + b),
+            ]
+        })
+        .collect();
+    db_manager.insert("l", rows).unwrap();
+    let rows = (0..5)
+        .map(|x| vec![ParserValue::Integer(x), ParserValue::Integer(4 - x)])
+        .collect();
+    db_manager.insert("r", rows).unwrap();
+
+    let queries = [
+        "SELECT l.v, r.x FROM l, r WHERE l.a = 3 AND l.b = 4 AND l.a = r.x;",
+        "SELECT p.v, q.x FROM l p, r q WHERE p.b = 4 AND p.a = 3 AND p.a = q.x;",
+        // `r.b` names the right table's column, so it must not narrow `l`
+        "SELECT l.v, r.x FROM l, r WHERE l.a = 3 AND l.b = 4 AND r.b = 1;",
+        "SELECT l.v, r.x FROM l, r WHERE a = 3 AND l.b = 4 AND x = 3;",
+    ];
+    let expected = vec![vec!["34", "3"]];
+    for sql in queries {
+        assert_eq!(select(&mut db_manager, sql).unwrap().1, expected, "{}", sql);
+    }
+
+    // Hide (3, 4) from the primary key index: the composite lookup now finds
+    // nothing, which shows it is what narrows the left table
+    let key = TableCompositeIntColumnIter::composite_key(3, 4);
+    let rid = db_manager.index_manager.search("l", "a__b", key).unwrap();
+    db_manager
+        .index_manager
+        .delete_entry("l", "a__b", key, rid)
+        .unwrap();
+    for sql in queries {
+        assert!(
+            select(&mut db_manager, sql).unwrap().1.is_empty(),
+            "{}",
+            sql
+        );
+    }
+
+    db_manager.set_use_indexes(false);
+    for sql in queries {
+        assert_eq!(select(&mut db_manager, sql).unwrap().1, expected, "{}", sql);
+    }
+}
+
+#[test]
+fn test_drop_primary_key_drops_backing_index() {
+    let (temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let pk_table = |name: &str| {
+        vec![
+            CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+            CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
+                name.to_string(),
+                vec!["id".to_string()],
+            ))),
+        ]
+    };
+    db_manager.create_table("solo", pk_table("solo")).unwrap();
+    db_manager
+        .create_table("parent", pk_table("parent"))
+        .unwrap();
+    let child_fields = vec![
+        CreateTableField::Col("parent_id".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Fkey(Box::new(AlterStatement::AddFKey(
+            "child".to_string(),
+            Some("fk_parent".to_string()),
+            vec!["parent_id".to_string()],
+            "parent".to_string(),
+            vec!["id".to_string()],
+        ))),
+    ];
+    db_manager.create_table("child", child_fields).unwrap();
+    for table in ["solo", "parent"] {
+        db_manager
+            .insert(table, vec![vec![ParserValue::Integer(1)]])
+            .unwrap();
+    }
+
+    // The primary key index is built the first time a lookup needs it
+    let lookup = crate::lexer_parser::parse("SELECT * FROM solo WHERE id = 1;")
+        .unwrap()
+        .remove(0);
+    let crate::lexer_parser::Query::TableStmt(TableStatement::Select(lookup)) = lookup else {
+        panic!("not a SELECT");
+    };
+    assert_eq!(db_manager.select(lookup).unwrap().1.len(), 1);
+
+    let index_file = |table: &str| temp.path().join("testdb").join(format!("{}_id.idx", table));
+    assert!(index_file("solo").exists());
+    assert!(index_file("parent").exists());
+
+    for table in ["solo", "parent"] {
+        db_manager
+            .execute_alter_statement(AlterStatement::DropPKey(table.to_string(), None))
+            .unwrap();
+    }
+
+    // Nothing else uses the solo table's key index
+    assert!(!index_file("solo").exists());
+    db_manager
+        .insert("solo", vec![vec![ParserValue::Integer(1)]])
+        .unwrap();
+
+    // The foreign key still looks parents up through the same index
+    assert!(index_file("parent").exists());
+    db_manager
+        .insert("child", vec![vec![ParserValue::Integer(1)]])
+        .unwrap();
+    assert!(
+        db_manager
+            .insert("child", vec![vec![ParserValue::Integer(2)]])
+            .is_err()
+    );
+}
+
+#[test]
+fn test_add_primary_key_rejects_existing_nulls() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let fields = vec![
+        CreateTableField::Col("a".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Col("b".to_string(), ColumnType::Int, false, None, false),
+    ];
+    db_manager.create_table("t", fields).unwrap();
+    db_manager
+        .insert(
+            "t",
+            vec![
+                vec![ParserValue::Integer(1), ParserValue::Integer(1)],
+                vec![ParserValue::Integer(2), ParserValue::Null],
+            ],
+        )
+        .unwrap();
 
-    db_manager
-        .execute_alter_statement(AlterStatement::AddIndex(
-            "tcomp".to_string(),
-            None,
+    let add_pk = |db_manager: &mut DatabaseManager| {
+        db_manager.execute_alter_statement(AlterStatement::AddPKey(
+            "t".to_string(),
             vec!["a".to_string(), "b".to_string()],
         ))
+    };
+    assert!(matches!(
+        add_pk(&mut db_manager),
+        Err(DatabaseError::NotNullViolation(col)) if col == "b"
+    ));
+    let table_meta = db_manager.describe_table("t").unwrap();
+    assert!(table_meta.primary_key.is_none());
+    assert!(table_meta.columns.iter().all(|col| !col.not_null));
+
+    db_manager
+        .update("t", vec![("b".to_string(), ParserValue::Integer(2))], None)
         .unwrap();
+    add_pk(&mut db_manager).unwrap();
+    let table_meta = db_manager.describe_table("t").unwrap();
+    assert_eq!(
+        table_meta.primary_key,
+        Some(vec!["a".to_string(), "b".to_string()])
+    );
+    assert!(table_meta.columns.iter().all(|col| col.not_null));
+}
 
-    let meta = db_manager.describe_table("tcomp").unwrap();
-    assert_eq!(meta.indexes.len(), 1);
-    assert_eq!(meta.indexes[0].name, "idx_a_b");
-    assert_eq!(meta.indexes[0].columns, vec!["a".to_string(), "b".to_string()]);
+#[test]
+fn test_create_table_as_select() {
+    let mut db_manager = DatabaseManager::in_memory();
 
-    let index_path = temp_dir
-        .path()
-        .join("testdb")
-        .join("tcomp_a__b.idx");
-    assert!(index_path.exists());
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
 
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["tcomp".to_string()],
-        where_clauses: vec![
-            WhereClause::Op(
-                TableColumn {
-                    table: None,
-                    column: "a".to_string(),
-                },
-                Operator::Eq,
-                Expression::Value(ParserValue::Integer(1)),
-            ),
-            WhereClause::Op(
-                TableColumn {
-                    table: None,
-                    column: "b".to_string(),
-                },
-                Operator::Eq,
-                Expression::Value(ParserValue::Integer(2)),
-            ),
-        ],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
+    run(
+        &mut db_manager,
+        "CREATE TABLE src (id INT NOT NULL, score FLOAT, name VARCHAR(8) DEFAULT 'x', PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO src VALUES (1, 1.25, 'a'), (2, NULL, 'b'), (3, 3.5, 'c');",
+    )
+    .unwrap();
+
+    let copied = run(&mut db_manager, "CREATE TABLE copy AS SELECT * FROM src;").unwrap();
+    assert!(matches!(copied, QueryResult::RowsAffected(3)));
+    let copy_meta = db_manager.describe_table("copy").unwrap();
+    let src_meta = db_manager.describe_table("src").unwrap();
+    let column_defs = |meta: &TableMetadata| {
+        meta.columns
+            .iter()
+            .map(|c| {
+                (
+                    c.name.clone(),
+                    c.column_type.clone(),
+                    c.not_null,
+                    c.default_value.clone(),
+                )
+            })
+            .collect::<Vec<_>>()
     };
-    let (_headers, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(rows, vec![vec!["1", "2", "20"]]);
+    assert_eq!(column_defs(&copy_meta), column_defs(&src_meta));
+    assert!(copy_meta.primary_key.is_none());
+    assert!(copy_meta.indexes.is_empty());
+    match run(&mut db_manager, "SELECT * FROM copy;").unwrap() {
+        QueryResult::ResultSet(_, rows) => assert_eq!(
+            rows,
+            vec![
+                vec!["1", "1.25", "a"],
+                vec!["2", "NULL", "b"],
+                vec!["3", "3.50", "c"],
+            ]
+        ),
+        other => panic!("unexpected result: {:?}", other),
+    }
 
-    let updated = db_manager
-        .update(
-            "tcomp",
-            vec![("c".to_string(), ParserValue::Integer(99))],
-            Some(vec![
-                WhereClause::Op(
-                    TableColumn {
-                        table: None,
-                        column: "a".to_string(),
-                    },
-                    Operator::Eq,
-                    Expression::Value(ParserValue::Integer(1)),
-                ),
-                WhereClause::Op(
-                    TableColumn {
-                        table: None,
-                        column: "b".to_string(),
-                    },
-                    Operator::Eq,
-                    Expression::Value(ParserValue::Integer(2)),
-                ),
-            ]),
+    // Without the key, the copy accepts rows the source would reject
+    run(&mut db_manager, "INSERT INTO copy VALUES (1, 0.0, 'd');").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE names AS SELECT s.name, s.id FROM src s WHERE s.id >= 2;",
+    )
+    .unwrap();
+    let names_meta = db_manager.describe_table("names").unwrap();
+    let names: Vec<_> = names_meta.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["name", "id"]);
+    match run(&mut db_manager, "SELECT * FROM names;").unwrap() {
+        QueryResult::ResultSet(_, rows) => {
+            assert_eq!(rows, vec![vec!["b", "2"], vec!["c", "3"]])
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    assert!(matches!(
+        run(&mut db_manager, "CREATE TABLE copy AS SELECT * FROM src;"),
+        Err(DatabaseError::TableExists(_))
+    ));
+    assert!(
+        run(
+            &mut db_manager,
+            "CREATE TABLE agg AS SELECT COUNT(*) FROM src;"
         )
-        .unwrap();
-    assert_eq!(updated, 1);
+        .is_err()
+    );
+    assert!(
+        run(
+            &mut db_manager,
+            "CREATE TABLE dup AS SELECT id, id FROM src;"
+        )
+        .is_err()
+    );
+    assert!(db_manager.describe_table("dup").is_err());
+}
 
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["tcomp".to_string()],
-        where_clauses: vec![
-            WhereClause::Op(
-                TableColumn {
-                    table: None,
-                    column: "a".to_string(),
-                },
-                Operator::Eq,
-                Expression::Value(ParserValue::Integer(1)),
-            ),
-            WhereClause::Op(
-                TableColumn {
-                    table: None,
-                    column: "b".to_string(),
-                },
-                Operator::Eq,
-                Expression::Value(ParserValue::Integer(2)),
-            ),
-        ],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
+#[test]
+fn test_table_version_tracks_changes() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_path = temp_dir.path().to_str().unwrap();
+
+    let where_a = |a| {
+        Some(vec![WhereClause::Op(
+            TableColumn {
+                table: None,
+                column: "a".to_string(),
+            },
+            Operator::Eq,
+            Expression::Value(ParserValue::Integer(a)),
+        )])
     };
-    let (_headers, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(rows, vec![vec!["1", "2", "99"]]);
 
-    let deleted = db_manager
-        .delete(
-            "tcomp",
-            Some(vec![
-                WhereClause::Op(
-                    TableColumn {
-                        table: None,
-                        column: "a".to_string(),
-                    },
-                    Operator::Eq,
-                    Expression::Value(ParserValue::Integer(1)),
-                ),
-                WhereClause::Op(
-                    TableColumn {
-                        table: None,
-                        column: "b".to_string(),
-                    },
-                    Operator::Eq,
-                    Expression::Value(ParserValue::Integer(2)),
-                ),
-            ]),
+    {
+        let mut db_manager = DatabaseManager::new(data_path).unwrap();
+        db_manager.create_database("testdb").unwrap();
+        db_manager.use_database("testdb").unwrap();
+
+        let fields = vec![CreateTableField::Col(
+            "a".to_string(),
+            ColumnType::Int,
+            false,
+            None,
+            false,
+        )];
+        db_manager.create_table("t", fields).unwrap();
+        assert_eq!(db_manager.table_version("t").unwrap(), 0);
+
+        db_manager
+            .insert("t", vec![vec![ParserValue::Integer(1)]])
+            .unwrap();
+        assert_eq!(db_manager.table_version("t").unwrap(), 1);
+
+        db_manager
+            .update("t", vec![("a".to_string(), ParserValue::Integer(2))], None)
+            .unwrap();
+        assert_eq!(db_manager.table_version("t").unwrap(), 2);
+
+        // Statements that touch no rows leave the version alone
+        db_manager.delete("t", where_a(1)).unwrap();
+        assert_eq!(db_manager.table_version("t").unwrap(), 2);
+
+        db_manager.delete("t", where_a(2)).unwrap();
+        assert_eq!(db_manager.table_version("t").unwrap(), 3);
+    }
+
+    // The version is persisted with the catalog
+    let mut db_manager = DatabaseManager::new(data_path).unwrap();
+    db_manager.use_database("testdb").unwrap();
+    assert_eq!(db_manager.table_version("t").unwrap(), 3);
+    assert!(db_manager.table_version("missing").is_err());
+}
+
+#[test]
+fn test_select_order_by_aggregate() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let select = |db_manager: &mut DatabaseManager, sql: &str| match crate::lexer_parser::parse(sql)
+        .unwrap()
+        .remove(0)
+    {
+        crate::lexer_parser::Query::TableStmt(TableStatement::Select(clause)) => {
+            db_manager.select(clause)
+        }
+        other => panic!("not a SELECT: {:?}", other),
+    };
+
+    let fields = vec![
+        CreateTableField::Col("g".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Col("v".to_string(), ColumnType::Float, false, None, false),
+    ];
+    db_manager.create_table("t", fields).unwrap();
+    let rows = [(1, 1.0), (2, 5.0), (2, 7.0), (3, 0.5), (3, 0.5), (3, 2.0)]
+        .into_iter()
+        .map(|(g, v)| vec![ParserValue::Integer(g), ParserValue::Float(v)])
+        .collect();
+    db_manager.insert("t", rows).unwrap();
+
+    let (headers, rows) = select(
+        &mut db_manager,
+        "SELECT g, COUNT(*) FROM t GROUP BY g ORDER BY COUNT(*) DESC LIMIT 2;",
+    )
+    .unwrap();
+    assert_eq!(headers, vec!["g", "COUNT(*)"]);
+    assert_eq!(rows, vec![vec!["3", "3"], vec!["2", "2"]]);
+
+    // Sorting uses the computed value, and ties keep the groups' first-seen order
+    let (_, rows) = select(
+        &mut db_manager,
+        "SELECT g, AVG(v) FROM t x GROUP BY g ORDER BY AVG(x.v) DESC;",
+    )
+    .unwrap();
+    assert_eq!(
+        rows,
+        vec![vec!["2", "6.00"], vec!["1", "1.00"], vec!["3", "1.00"]]
+    );
+
+    let (_, rows) = select(
+        &mut db_manager,
+        "SELECT COUNT(*) FROM t GROUP BY g ORDER BY g DESC LIMIT 2 OFFSET 1;",
+    )
+    .unwrap();
+    assert_eq!(rows, vec![vec!["2"], vec!["1"]]);
+
+    assert!(
+        select(
+            &mut db_manager,
+            "SELECT g, COUNT(*) FROM t GROUP BY g ORDER BY SUM(v);"
         )
-        .unwrap();
-    assert_eq!(deleted, 1);
+        .is_err()
+    );
+    assert!(
+        select(
+            &mut db_manager,
+            "SELECT g, COUNT(*) FROM t GROUP BY g ORDER BY v;"
+        )
+        .is_err()
+    );
+    assert!(select(&mut db_manager, "SELECT g FROM t ORDER BY COUNT(*);").is_err());
+}
 
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["tcomp".to_string()],
-        where_clauses: vec![
-            WhereClause::Op(
-                TableColumn {
-                    table: None,
-                    column: "a".to_string(),
-                },
-                Operator::Eq,
-                Expression::Value(ParserValue::Integer(1)),
-            ),
-            WhereClause::Op(
-                TableColumn {
-                    table: None,
-                    column: "b".to_string(),
-                },
-                Operator::Eq,
-                Expression::Value(ParserValue::Integer(2)),
-            ),
-        ],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
+#[test]
+fn test_cached_index_handles_do_not_outlive_their_table() {
+    let (_temp, mut db_manager) = setup_test_db();
+
+    let count = |db_manager: &mut DatabaseManager, sql: &str| match run(db_manager, sql).unwrap() {
+        QueryResult::ResultSet(_, rows) => rows.len(),
+        other => panic!("not a result set: {:?}", other),
     };
-    let (_headers, rows) = db_manager.select(clause).unwrap();
-    assert!(rows.is_empty());
+    let create = "CREATE TABLE t (id INT NOT NULL, PRIMARY KEY (id));";
+
+    for db in ["a", "b"] {
+        db_manager.create_database(db).unwrap();
+    }
+    db_manager.use_database("a").unwrap();
+    run(&mut db_manager, create).unwrap();
+    run(&mut db_manager, "INSERT INTO t VALUES (1);").unwrap();
+    assert_eq!(count(&mut db_manager, "SELECT * FROM t WHERE id = 1;"), 1);
+    assert!(db_manager.index_manager.is_open("t", "id"));
+
+    // The same table name in another database gets its own index
+    db_manager.use_database("b").unwrap();
+    assert!(!db_manager.index_manager.is_open("t", "id"));
+    run(&mut db_manager, create).unwrap();
+    run(&mut db_manager, "INSERT INTO t VALUES (1);").unwrap();
+    assert_eq!(count(&mut db_manager, "SELECT * FROM t WHERE id = 1;"), 1);
+
+    // A recreated table does not inherit the dropped table's index
+    db_manager.drop_table("t").unwrap();
+    assert!(!db_manager.index_manager.is_open("t", "id"));
+    run(&mut db_manager, create).unwrap();
+    run(&mut db_manager, "INSERT INTO t VALUES (1), (2);").unwrap();
+    assert_eq!(count(&mut db_manager, "SELECT * FROM t WHERE id = 1;"), 1);
+    assert_eq!(count(&mut db_manager, "SELECT * FROM t WHERE id = 2;"), 1);
+}
+
+#[test]
+fn test_checkpoint_flushes_table_and_index_pages() {
+    let (temp, mut db_manager) = setup_test_db();
+
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+    let fields = vec![
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("v".to_string(), ColumnType::Int, false, None, false),
+        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
+            "t".to_string(),
+            vec!["id".to_string()],
+        ))),
+    ];
+    db_manager.create_table("t", fields).unwrap();
+    db_manager.checkpoint().unwrap();
 
+    let rows = (1..=3)
+        .map(|i| vec![ParserValue::Integer(i), ParserValue::Integer(i * 10)])
+        .collect();
+    db_manager.insert("t", rows).unwrap();
+    // An insert is a logged unit and writes its pages before returning
+    assert_eq!(db_manager.checkpoint().unwrap(), 0);
     db_manager
-        .execute_alter_statement(AlterStatement::DropIndex(
-            "tcomp".to_string(),
-            "idx_a_b".to_string(),
-        ))
+        .update("t", vec![("v".to_string(), ParserValue::Integer(0))], None)
         .unwrap();
-    assert!(!index_path.exists());
+    assert!(db_manager.checkpoint().unwrap() > 0);
+    assert_eq!(db_manager.checkpoint().unwrap(), 0);
+
+    // The files on disk are complete while the first manager is still alive
+    let mut reader = DatabaseManager::new(temp.path().to_str().unwrap()).unwrap();
+    reader.use_database("testdb").unwrap();
+    let rows: Vec<TypedRow> = reader
+        .scan_table("t")
+        .unwrap()
+        .collect::<DatabaseResult<_>>()
+        .unwrap();
+    assert_eq!(rows.len(), 3);
+
+    let result = db_manager.execute_db_statement(DBStatement::Flush).unwrap();
+    assert!(matches!(
+        result,
+        QueryResult::ResultSet(headers, rows) if headers == ["PAGES"] && rows == [["0"]]
+    ));
 }
 
 #[test]
-fn test_composite_index_partial_range() {
-    let (_temp, mut db_manager) = setup_test_db();
+fn test_select_across_databases() {
+    let mut db_manager = DatabaseManager::in_memory();
+
+    for db in ["app", "reports"] {
+        db_manager.create_database(db).unwrap();
+    }
+    db_manager.use_database("reports").unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT NOT NULL, total INT, PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(&mut db_manager, "INSERT INTO t VALUES (1, 100), (2, 200);").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT * FROM t WHERE id = 2;").len(),
+        1
+    );
+
+    db_manager.use_database("app").unwrap();
+    run(&mut db_manager, "CREATE TABLE t (id INT, name VARCHAR(8));").unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 'one'), (2, 'two'), (3, 'three');",
+    )
+    .unwrap();
+
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT t.total FROM reports.t WHERE id = 2;"
+        ),
+        vec![vec!["200"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT COUNT(*) FROM reports.t;"),
+        vec![vec!["2"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT a.name, r.total FROM t a, reports.t r WHERE a.id = r.id AND r.total > 150;",
+        ),
+        vec![vec!["two", "200"]]
+    );
+    // Naming the current database is the same as leaving it out
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT name FROM app.t WHERE id = 1;"),
+        vec![vec!["one"]]
+    );
+
+    // The attached table does not linger in the catalog
+    assert_eq!(db_manager.show_tables(None).unwrap(), vec!["t"]);
+    assert!(run(&mut db_manager, "SELECT * FROM t, reports.t;").is_err());
+    assert!(matches!(
+        run(&mut db_manager, "SELECT * FROM nowhere.t;"),
+        Err(DatabaseError::DatabaseNotFound(_))
+    ));
+
+    // Switching back reads the other database's own table again
+    db_manager.use_database("reports").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT COUNT(*) FROM t;"),
+        vec![vec!["2"]]
+    );
+}
 
+#[test]
+fn test_select_typed_reports_column_types() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
+    let select = |db_manager: &mut DatabaseManager, sql: &str| match crate::lexer_parser::parse(sql)
+        .unwrap()
+        .remove(0)
+    {
+        crate::lexer_parser::Query::TableStmt(TableStatement::Select(clause)) => {
+            db_manager.select_typed(clause).unwrap()
+        }
+        other => panic!("not a SELECT: {:?}", other),
+    };
+    let column = |name: &str, data_type: DataType, nullable: bool| ResultColumn {
+        name: name.to_string(),
+        data_type,
+        nullable,
+    };
+
     let fields = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col("b".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col("c".to_string(), ColumnType::Int, false, ParserValue::Null),
+        CreateTableField::Col("id".to_string(), ColumnType::Int, true, None, false),
+        CreateTableField::Col("code".to_string(), ColumnType::Char(4), false, None, false),
+        CreateTableField::Col("price".to_string(), ColumnType::Float, false, None, false),
     ];
-    db_manager.create_table("tcomp2", fields).unwrap();
+    db_manager.create_table("t", fields).unwrap();
+    db_manager
+        .insert(
+            "t",
+            vec![vec![
+                ParserValue::Integer(1),
+                ParserValue::String("12".into()),
+                ParserValue::Float(2.5),
+            ]],
+        )
+        .unwrap();
 
-    let rows = vec![
-        vec![
-            ParserValue::Integer(1),
-            ParserValue::Integer(10),
-            ParserValue::Integer(100),
-        ],
+    let (columns, rows) = select(&mut db_manager, "SELECT * FROM t;");
+    assert_eq!(
+        columns,
         vec![
-            ParserValue::Integer(1),
-            ParserValue::Integer(20),
-            ParserValue::Integer(200),
-        ],
+            column("id", DataType::Int, false),
+            column("code", DataType::Varchar(4), true),
+            column("price", DataType::Float, true),
+        ]
+    );
+    assert_eq!(rows, vec![vec!["1", "12", "2.50"]]);
+
+    let (columns, _) = select(&mut db_manager, "SELECT code, CAST(code AS INT) FROM t;");
+    assert_eq!(
+        columns,
         vec![
-            ParserValue::Integer(2),
-            ParserValue::Integer(10),
-            ParserValue::Integer(300),
-        ],
-    ];
-    db_manager.insert("tcomp2", rows).unwrap();
+            column("code", DataType::Varchar(4), true),
+            column("CAST(code AS INT)", DataType::Int, true),
+        ]
+    );
 
-    db_manager
-        .execute_alter_statement(AlterStatement::AddIndex(
-            "tcomp2".to_string(),
-            None,
-            vec!["a".to_string(), "b".to_string()],
-        ))
-        .unwrap();
+    let (columns, _) = select(
+        &mut db_manager,
+        "SELECT id, COUNT(*), SUM(id), AVG(price), MAX(code) FROM t GROUP BY id;",
+    );
+    assert_eq!(
+        columns,
+        vec![
+            column("id", DataType::Int, false),
+            column("COUNT(*)", DataType::Int, false),
+            column("SUM(id)", DataType::Int, true),
+            column("AVG(price)", DataType::Float, true),
+            column("MAX(code)", DataType::Varchar(4), true),
+        ]
+    );
 
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["tcomp2".to_string()],
-        where_clauses: vec![WhereClause::Op(
-            TableColumn {
-                table: None,
-                column: "a".to_string(),
-            },
-            Operator::Lt,
-            Expression::Value(ParserValue::Integer(2)),
-        )],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
-    };
-    let (_headers, rows) = db_manager.select(clause).unwrap();
+    let (columns, _) = select(&mut db_manager, "SELECT a.id, b.price FROM t a, t b;");
     assert_eq!(
-        rows,
+        columns,
         vec![
-            vec!["1", "10", "100"],
-            vec!["1", "20", "200"],
+            column("id", DataType::Int, false),
+            column("price", DataType::Float, true),
         ]
     );
 }
 
 #[test]
-fn test_select_specific_columns() {
-    let (_temp, mut db_manager) = setup_test_db();
-
+fn test_explain_access_path() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "b".to_string(),
-            ColumnType::Char(10),
-            true,
-            ParserValue::Null,
-        ),
-        CreateTableField::Col("c".to_string(), ColumnType::Float, true, ParserValue::Null),
-    ];
+    let explain = |db_manager: &mut DatabaseManager, sql: &str| match run(db_manager, sql).unwrap()
+    {
+        QueryResult::List(lines) => lines,
+        other => panic!("not a list: {:?}", other),
+    };
 
-    db_manager.create_table("test", fields).unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (a INT NOT NULL, b INT NOT NULL, v INT, s VARCHAR(8));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 1, 10, 'x'), (2, 2, 20, 'y');",
+    )
+    .unwrap();
 
-    let rows = vec![vec![
-        ParserValue::Integer(1),
-        ParserValue::String("hello".to_string()),
-        ParserValue::Float(1.5),
-    ]];
-    db_manager.insert("test", rows).unwrap();
+    assert_eq!(
+        explain(&mut db_manager, "EXPLAIN SELECT * FROM t WHERE v = 10;"),
+        vec!["full table scan on t"]
+    );
 
-    // Select specific columns
-    let clause = SelectClause {
-        selectors: Selectors::List(vec![
-            crate::lexer_parser::Selector::Column(crate::lexer_parser::TableColumn {
-                table: None,
-                column: "b".to_string(),
-            }),
-            crate::lexer_parser::Selector::Column(crate::lexer_parser::TableColumn {
-                table: None,
-                column: "a".to_string(),
-            }),
-        ]),
-        table: vec!["test".to_string()],
-        where_clauses: vec![],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
-    };
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (v);").unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (a, b);").unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (s);").unwrap();
+    assert_eq!(
+        explain(&mut db_manager, "EXPLAIN SELECT * FROM t WHERE v = 10;"),
+        vec!["index lookup on t using index v"]
+    );
+    assert_eq!(
+        explain(
+            &mut db_manager,
+            "EXPLAIN SELECT * FROM t x WHERE x.s = 'y';"
+        ),
+        vec!["index lookup on t using index s"]
+    );
+    assert_eq!(
+        explain(
+            &mut db_manager,
+            "EXPLAIN SELECT * FROM t WHERE b = 1 AND a = 1;"
+        ),
+        vec!["composite index lookup on t using index a__b"]
+    );
+    assert_eq!(
+        explain(
+            &mut db_manager,
+            "EXPLAIN SELECT * FROM t WHERE v > 5 AND v <= 20;"
+        ),
+        vec!["range scan on t using index v"]
+    );
+    assert_eq!(
+        explain(&mut db_manager, "EXPLAIN SELECT * FROM t WHERE a >= 2;"),
+        vec!["range scan on t using composite index a__b"]
+    );
+    assert_eq!(
+        explain(
+            &mut db_manager,
+            "EXPLAIN SELECT * FROM t WHERE v = 1 AND v = 2;"
+        ),
+        vec!["no rows read from t: WHERE never matches"]
+    );
+    // An OR is only checked row by row, and aggregates always scan
+    assert_eq!(
+        explain(
+            &mut db_manager,
+            "EXPLAIN SELECT * FROM t WHERE v = 10 OR v = 20;"
+        ),
+        vec!["full table scan on t"]
+    );
+    assert_eq!(
+        explain(
+            &mut db_manager,
+            "EXPLAIN SELECT COUNT(*) FROM t WHERE v = 10;"
+        ),
+        vec!["full table scan on t"]
+    );
 
-    let (headers, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(headers, vec!["b", "a"]);
-    assert_eq!(rows.len(), 1);
-    assert_eq!(rows[0], vec!["hello", "1"]);
+    // Joins cannot be explained
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "EXPLAIN SELECT * FROM t, t u WHERE t.a = u.a;"
+        ),
+        Err(DatabaseError::TypeMismatch(_))
+    ));
 }
 
 #[test]
-fn test_select_with_where() {
-    let (_temp, mut db_manager) = setup_test_db();
-
+fn test_null_safe_equality() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "b".to_string(),
-            ColumnType::Char(10),
-            true,
-            ParserValue::Null,
-        ),
-    ];
-
-    db_manager.create_table("test", fields).unwrap();
-
-    // Insert test data
-    let rows = vec![
-        vec![
-            ParserValue::Integer(1),
-            ParserValue::String("a".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(2),
-            ParserValue::String("b".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(3),
-            ParserValue::String("c".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(4),
-            ParserValue::String("d".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(5),
-            ParserValue::String("e".to_string()),
-        ],
-    ];
-    db_manager.insert("test", rows).unwrap();
+    run(&mut db_manager, "CREATE TABLE t (id INT, a INT, b INT);").unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, NULL, NULL), (2, NULL, 1), (3, 1, NULL), (4, 1, 1), (5, 1, 2);",
+    )
+    .unwrap();
 
-    // WHERE a > 2
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["test".to_string()],
-        where_clauses: vec![WhereClause::Op(
-            crate::lexer_parser::TableColumn {
-                table: None,
-                column: "a".to_string(),
-            },
-            Operator::Gt,
-            crate::lexer_parser::Expression::Value(ParserValue::Integer(2)),
-        )],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
-    };
+    // NULL <=> NULL holds, NULL <=> value does not either way round, values compare as =
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE a <=> b;"),
+        vec![vec!["1"], vec!["4"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE a = b;"),
+        vec![vec!["4"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE a <=> NULL;"),
+        vec![vec!["1"], vec!["2"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE b <=> 1;"),
+        vec![vec!["2"], vec!["4"]]
+    );
 
-    let (_, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(rows.len(), 3);
-    assert_eq!(rows[0][0], "3");
-    assert_eq!(rows[1][0], "4");
-    assert_eq!(rows[2][0], "5");
+    // A non-NULL literal can use the index; NULL has no index entries to look up
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (b);").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE b <=> 1;"),
+        vec![vec!["2"], vec!["4"]]
+    );
+    assert!(matches!(
+        run(&mut db_manager, "EXPLAIN SELECT id FROM t WHERE b <=> 1;").unwrap(),
+        QueryResult::List(lines) if lines == ["index lookup on t using index b"]
+    ));
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE b <=> NULL;"),
+        vec![vec!["1"], vec!["3"]]
+    );
+    assert!(matches!(
+        run(&mut db_manager, "EXPLAIN SELECT id FROM t WHERE b <=> NULL;").unwrap(),
+        QueryResult::List(lines) if lines == ["full table scan on t"]
+    ));
 }
 
 #[test]
-fn test_select_with_like() {
-    let (_temp, mut db_manager) = setup_test_db();
-
+fn test_is_not_null_uses_index() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![
-        CreateTableField::Col("id".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "name".to_string(),
-            ColumnType::Char(10),
-            true,
-            ParserValue::Null,
-        ),
-    ];
+    run(&mut db_manager, "CREATE TABLE t (id INT, v INT);").unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 10), (2, NULL), (3, 30), (4, NULL);",
+    )
+    .unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (v);").unwrap();
 
-    db_manager.create_table("test", fields).unwrap();
+    let not_null = "SELECT id FROM t WHERE v IS NOT NULL;";
+    assert_eq!(
+        select_rows(&mut db_manager, not_null),
+        vec![vec!["1"], vec!["3"]]
+    );
 
-    let rows = vec![
-        vec![
-            ParserValue::Integer(1),
-            ParserValue::String("hello".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(2),
-            ParserValue::String("help".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(3),
-            ParserValue::String("shell".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(4),
-            ParserValue::String("world".to_string()),
-        ],
-    ];
-    db_manager.insert("test", rows).unwrap();
+    // Hide row 3 from the index: it drops out, which shows the index answers
+    // IS NOT NULL, while IS NULL still scans the table
+    let rid = db_manager.index_manager.search("t", "v", 30).unwrap();
+    db_manager
+        .index_manager
+        .delete_entry("t", "v", 30, rid)
+        .unwrap();
+    assert_eq!(select_rows(&mut db_manager, not_null), vec![vec!["1"]]);
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE v IS NULL;"),
+        vec![vec!["2"], vec!["4"]]
+    );
 
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["test".to_string()],
-        where_clauses: vec![WhereClause::Like(
-            crate::lexer_parser::TableColumn {
-                table: None,
-                column: "name".to_string(),
-            },
-            "he%".to_string(),
-        )],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
-    };
+    db_manager.set_use_indexes(false);
+    assert_eq!(
+        select_rows(&mut db_manager, not_null),
+        vec![vec!["1"], vec!["3"]]
+    );
+}
 
-    let (_, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(rows.len(), 2);
-    assert_eq!(rows[0][1], "hello");
-    assert_eq!(rows[1][1], "help");
+#[test]
+fn test_quoted_identifiers_allow_keyword_names() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE `SELECT` (`ORDER` INT, \"GROUP BY\" VARCHAR(8));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO `SELECT` VALUES (1, 'a'), (2, 'b'), (3, 'c');",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "UPDATE `SELECT` SET \"GROUP BY\" = 'z' WHERE `ORDER` = 3;",
+    )
+    .unwrap();
+
+    match run(
+        &mut db_manager,
+        "SELECT `ORDER`, \"GROUP BY\" FROM `SELECT` WHERE `ORDER` >= 2;",
+    )
+    .unwrap()
+    {
+        QueryResult::ResultSet(headers, rows) => {
+            assert_eq!(headers, vec!["ORDER", "GROUP BY"]);
+            assert_eq!(rows, vec![vec!["2", "b"], vec!["3", "z"]]);
+        }
+        other => panic!("not a result set: {:?}", other),
+    }
 }
 
 #[test]
-fn test_select_with_multiple_where() {
-    let (_temp, mut db_manager) = setup_test_db();
-
+fn test_replace_into() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "b".to_string(),
-            ColumnType::Char(10),
-            true,
-            ParserValue::Null,
+    run(
+        &mut db_manager,
+        "CREATE TABLE p (id INT NOT NULL, name VARCHAR(8), PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE c (id INT, pid INT, FOREIGN KEY (pid) REFERENCES p(id));",
+    )
+    .unwrap();
+    run(&mut db_manager, "INSERT INTO p VALUES (1, 'a'), (2, 'b');").unwrap();
+
+    // Row 1 is replaced, row 3 is new; the later duplicate of 3 wins
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "REPLACE INTO p VALUES (1, 'x'), (3, 'y'), (3, 'z');"
         ),
-    ];
-
-    db_manager.create_table("test", fields).unwrap();
-
-    let rows = vec![
-        vec![
-            ParserValue::Integer(1),
-            ParserValue::String("a".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(2),
-            ParserValue::String("b".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(3),
-            ParserValue::String("c".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(4),
-            ParserValue::String("d".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(5),
-            ParserValue::String("e".to_string()),
-        ],
-    ];
-    db_manager.insert("test", rows).unwrap();
+        Ok(QueryResult::RowsAffected(3))
+    ));
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id, name FROM p ORDER BY id;"),
+        vec![vec!["1", "x"], vec!["2", "b"], vec!["3", "z"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT name FROM p WHERE id = 1;"),
+        vec![vec!["x"]]
+    );
 
-    // WHERE a >= 2 AND a <= 4
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["test".to_string()],
-        where_clauses: vec![
-            WhereClause::Op(
-                crate::lexer_parser::TableColumn {
-                    table: None,
-                    column: "a".to_string(),
-                },
-                Operator::Ge,
-                crate::lexer_parser::Expression::Value(ParserValue::Integer(2)),
-            ),
-            WhereClause::Op(
-                crate::lexer_parser::TableColumn {
-                    table: None,
-                    column: "a".to_string(),
-                },
-                Operator::Le,
-                crate::lexer_parser::Expression::Value(ParserValue::Integer(4)),
-            ),
-        ],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
-    };
+    // A referenced row cannot be replaced, and nothing else in the statement applies
+    run(&mut db_manager, "INSERT INTO c VALUES (1, 2);").unwrap();
+    assert!(matches!(
+        run(&mut db_manager, "REPLACE INTO p VALUES (1, 'q'), (2, 'r');"),
+        Err(DatabaseError::ForeignKeyViolation(_))
+    ));
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id, name FROM p ORDER BY id;"),
+        vec![vec!["1", "x"], vec!["2", "b"], vec!["3", "z"]]
+    );
 
-    let (_, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(rows.len(), 3);
-    assert_eq!(rows[0][0], "2");
-    assert_eq!(rows[1][0], "3");
-    assert_eq!(rows[2][0], "4");
+    // Without a primary key REPLACE is a plain insert
+    assert!(matches!(
+        run(&mut db_manager, "REPLACE INTO c VALUES (1, 3);"),
+        Ok(QueryResult::RowsAffected(1))
+    ));
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT COUNT(*) FROM c;"),
+        vec![vec!["2"]]
+    );
 }
 
 #[test]
-fn test_select_order_by_limit_offset() {
-    let (_temp, mut db_manager) = setup_test_db();
-
+fn test_insert_on_duplicate_key_update() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "b".to_string(),
-            ColumnType::Char(10),
-            true,
-            ParserValue::Null,
-        ),
-    ];
-
-    db_manager.create_table("test", fields).unwrap();
-
-    let rows = vec![
-        vec![
-            ParserValue::Integer(1),
-            ParserValue::String("one".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(2),
-            ParserValue::String("two".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(3),
-            ParserValue::String("three".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(4),
-            ParserValue::String("four".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(5),
-            ParserValue::String("five".to_string()),
-        ],
-    ];
-    db_manager.insert("test", rows).unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT NOT NULL, v INT, PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (v);").unwrap();
+    run(&mut db_manager, "INSERT INTO t VALUES (1, 10), (2, 20);").unwrap();
 
-    let clause = SelectClause {
-        selectors: Selectors::List(vec![Selector::Column(TableColumn {
-            table: None,
-            column: "b".to_string(),
-        })]),
-        table: vec!["test".to_string()],
-        where_clauses: vec![],
-        group_by: None,
-        order_by: Some((
-            TableColumn {
-                table: None,
-                column: "a".to_string(),
-            },
-            false,
-        )),
-        limit: Some(2),
-        offset: Some(1),
-    };
+    // Row 1 exists and is updated, row 3 is new and is inserted
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "INSERT INTO t VALUES (1, 11), (3, 30) ON DUPLICATE KEY UPDATE v = 99;"
+        ),
+        Ok(QueryResult::RowsAffected(2))
+    ));
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id, v FROM t ORDER BY id;"),
+        vec![vec!["1", "99"], vec!["2", "20"], vec!["3", "30"]]
+    );
+    // The index on the updated column follows the new value
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE v = 99;"),
+        vec![vec!["1"]]
+    );
+    assert!(select_rows(&mut db_manager, "SELECT id FROM t WHERE v = 10;").is_empty());
 
-    let (headers, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(headers, vec!["b"]);
+    // A later row sees the row an earlier one in the same statement inserted
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "INSERT INTO t VALUES (4, 40), (4, 41) ON DUPLICATE KEY UPDATE v = 0;"
+        ),
+        Ok(QueryResult::RowsAffected(2))
+    ));
     assert_eq!(
-        rows,
-        vec![vec!["four".to_string()], vec!["three".to_string()]]
+        select_rows(&mut db_manager, "SELECT v FROM t WHERE id = 4;"),
+        vec![vec!["0"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT COUNT(*) FROM t;"),
+        vec![vec!["4"]]
     );
 }
 
 #[test]
-fn test_select_order_by_offset_past_end() {
-    let (_temp, mut db_manager) = setup_test_db();
+fn test_foreign_key_cycles() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE a (id INT NOT NULL, b_id INT NOT NULL, PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE b (id INT NOT NULL, a_id INT NOT NULL, PRIMARY KEY (id), FOREIGN KEY (a_id) REFERENCES a(id));",
+    )
+    .unwrap();
+
+    // a -> b -> a through NOT NULL columns can never be populated
+    match run(
+        &mut db_manager,
+        "ALTER TABLE a ADD CONSTRAINT fk_b FOREIGN KEY (b_id) REFERENCES b(id);",
+    ) {
+        Err(DatabaseError::ForeignKeyCycle(cycle)) => assert_eq!(cycle, "a -> b -> a"),
+        other => panic!("expected a cycle error, got {:?}", other),
+    }
+    assert!(
+        db_manager
+            .describe_table("a")
+            .unwrap()
+            .foreign_keys
+            .is_empty()
+    );
+
+    // The same cycle through a nullable column is allowed
+    run(
+        &mut db_manager,
+        "CREATE TABLE c (id INT NOT NULL, d_id INT, PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE d (id INT NOT NULL, c_id INT NOT NULL, PRIMARY KEY (id), FOREIGN KEY (c_id) REFERENCES c(id));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "ALTER TABLE c ADD CONSTRAINT fk_d FOREIGN KEY (d_id) REFERENCES d(id);",
+    )
+    .unwrap();
+
+    // Self-references follow the same rule
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "CREATE TABLE e (id INT NOT NULL, parent INT NOT NULL, PRIMARY KEY (id), FOREIGN KEY (parent) REFERENCES e(id));",
+        ),
+        Err(DatabaseError::ForeignKeyCycle(_))
+    ));
+    run(
+        &mut db_manager,
+        "CREATE TABLE e (id INT NOT NULL, parent INT, PRIMARY KEY (id), FOREIGN KEY (parent) REFERENCES e(id));",
+    )
+    .unwrap();
+}
 
+#[test]
+fn test_composite_index_ranges_with_nulls() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![CreateTableField::Col(
-        "a".to_string(),
-        ColumnType::Int,
-        true,
-        ParserValue::Null,
-    )];
+    // Nullable second column: rows with b = NULL have no composite entry
+    run(&mut db_manager, "CREATE TABLE t (a INT, b INT);").unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (a, b);").unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, NULL), (1, 2), (-3, 4), (NULL, 5), (7, 8);",
+    )
+    .unwrap();
+    for use_indexes in [true, false] {
+        db_manager.set_use_indexes(use_indexes);
+        assert_eq!(
+            select_rows(
+                &mut db_manager,
+                "SELECT a, b FROM t WHERE a = 1 ORDER BY b;"
+            ),
+            vec![vec!["1", "NULL"], vec!["1", "2"]]
+        );
+        assert_eq!(
+            select_rows(
+                &mut db_manager,
+                "SELECT a, b FROM t WHERE a >= 1 AND a < 7 ORDER BY b;"
+            ),
+            vec![vec!["1", "NULL"], vec!["1", "2"]]
+        );
+        assert_eq!(
+            select_rows(&mut db_manager, "SELECT b FROM t WHERE a = 1 AND b = 2;"),
+            vec![vec!["2"]]
+        );
+    }
 
-    db_manager.create_table("test", fields).unwrap();
-    db_manager
-        .insert(
-            "test",
-            vec![vec![ParserValue::Integer(1)], vec![ParserValue::Integer(2)]],
-        )
-        .unwrap();
+    // NOT NULL second column (composite primary key): the prefix range uses the
+    // index and must include negative leading values when only an upper bound is given
+    run(
+        &mut db_manager,
+        "CREATE TABLE p (a INT NOT NULL, b INT NOT NULL, PRIMARY KEY (a, b));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO p VALUES (-5, 1), (-1, -1), (0, 3), (4, 2);",
+    )
+    .unwrap();
+    for use_indexes in [true, false] {
+        db_manager.set_use_indexes(use_indexes);
+        assert_eq!(
+            select_rows(
+                &mut db_manager,
+                "SELECT a, b FROM p WHERE a < 1 ORDER BY a;"
+            ),
+            vec![vec!["-5", "1"], vec!["-1", "-1"], vec!["0", "3"]]
+        );
+    }
+}
 
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["test".to_string()],
-        where_clauses: vec![],
-        group_by: None,
-        order_by: Some((
-            TableColumn {
-                table: None,
-                column: "a".to_string(),
-            },
-            true,
-        )),
-        limit: None,
-        offset: Some(5),
+#[test]
+fn test_add_index_with_order() {
+    let (temp_dir, mut db_manager) = setup_test_db();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(&mut db_manager, "CREATE TABLE t (a INT, b INT);").unwrap();
+    let values: Vec<String> = (0..100).map(|i| format!("({}, {})", i, i % 7)).collect();
+    run(
+        &mut db_manager,
+        &format!("INSERT INTO t VALUES {};", values.join(", ")),
+    )
+    .unwrap();
+
+    // Orders that cannot form a tree or do not fit a node in a page are rejected
+    for order in [0, 2, 10_000] {
+        let sql = format!("ALTER TABLE t ADD INDEX (a) WITH (order = {});", order);
+        assert!(matches!(
+            run(&mut db_manager, &sql),
+            Err(DatabaseError::TypeMismatch(_))
+        ));
+    }
+    assert!(db_manager.describe_table("t").unwrap().indexes.is_empty());
+
+    run(
+        &mut db_manager,
+        "ALTER TABLE t ADD INDEX (a) WITH (order = 4);",
+    )
+    .unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (b);").unwrap();
+    let meta = db_manager.describe_table("t").unwrap();
+    assert_eq!(meta.indexes[0].order, Some(4));
+    assert_eq!(meta.indexes[1].order, None);
+    let order_of = |db_manager: &DatabaseManager, column: &str| {
+        db_manager
+            .index_manager
+            .get_index("t", column)
+            .unwrap()
+            .order()
     };
+    assert_eq!(order_of(&db_manager, "a"), 4);
+    assert_eq!(order_of(&db_manager, "b"), DEFAULT_ORDER);
 
-    let (_, rows) = db_manager.select(clause).unwrap();
-    assert!(rows.is_empty());
+    // The order is read back from the index file and kept across a rebuild
+    drop(db_manager);
+    let mut db_manager = DatabaseManager::new(temp_dir.path().to_str().unwrap()).unwrap();
+    db_manager.use_database("testdb").unwrap();
+    let db_path = temp_dir.path().join("testdb");
+    db_manager
+        .index_manager
+        .open_index(db_path.to_str().unwrap(), "t", "a")
+        .unwrap();
+    assert_eq!(order_of(&db_manager, "a"), 4);
+    run(&mut db_manager, "REINDEX TABLE t;").unwrap();
+    assert_eq!(order_of(&db_manager, "a"), 4);
+    assert_eq!(order_of(&db_manager, "b"), DEFAULT_ORDER);
+    assert_eq!(db_manager.index_manager.search_all("t", "a", 42).len(), 1);
+    assert_eq!(db_manager.index_manager.search_all("t", "b", 3).len(), 14);
 }
 
 #[test]
-fn test_update() {
-    let (_temp, mut db_manager) = setup_test_db();
-
+fn test_show_columns_like() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "b".to_string(),
-            ColumnType::Char(10),
-            true,
-            ParserValue::Null,
-        ),
-    ];
+    let describe = |db_manager: &mut DatabaseManager, sql: &str| match run(db_manager, sql).unwrap()
+    {
+        QueryResult::TableDescription(meta) => meta,
+        other => panic!("not a table description: {:?}", other),
+    };
+    let names = |meta: &TableMetadata| -> Vec<String> {
+        meta.columns.iter().map(|c| c.name.clone()).collect()
+    };
 
-    db_manager.create_table("test", fields).unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE p (id INT NOT NULL, addr_city VARCHAR(20), addr_zip INT, name VARCHAR(20), PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(&mut db_manager, "ALTER TABLE p ADD INDEX (addr_zip);").unwrap();
+
+    let meta = describe(&mut db_manager, "SHOW COLUMNS FROM p;");
+    assert_eq!(names(&meta), ["id", "addr_city", "addr_zip", "name"]);
+    assert!(meta.primary_key.is_some());
+
+    // Keys and indexes over filtered-out columns are dropped with them
+    let meta = describe(&mut db_manager, "SHOW COLUMNS FROM p LIKE 'addr%';");
+    assert_eq!(names(&meta), ["addr_city", "addr_zip"]);
+    assert!(meta.primary_key.is_none());
+    assert_eq!(meta.indexes.len(), 1);
 
-    let rows = vec![
-        vec![
-            ParserValue::Integer(1),
-            ParserValue::String("a".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(2),
-            ParserValue::String("b".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(3),
-            ParserValue::String("c".to_string()),
-        ],
-    ];
-    db_manager.insert("test", rows).unwrap();
+    let meta = describe(&mut db_manager, "SHOW COLUMNS FROM p LIKE '_d';");
+    assert_eq!(names(&meta), ["id"]);
+    assert!(meta.primary_key.is_some());
+    assert!(meta.indexes.is_empty());
 
-    // Update WHERE a = 2
-    let updates = vec![("b".to_string(), ParserValue::String("updated".to_string()))];
-    let where_clauses = Some(vec![WhereClause::Op(
-        crate::lexer_parser::TableColumn {
-            table: None,
-            column: "a".to_string(),
-        },
-        Operator::Eq,
-        crate::lexer_parser::Expression::Value(ParserValue::Integer(2)),
-    )]);
+    let meta = describe(&mut db_manager, "SHOW COLUMNS FROM p LIKE 'zzz%';");
+    assert!(meta.columns.is_empty());
 
-    let count = db_manager.update("test", updates, where_clauses).unwrap();
-    assert_eq!(count, 1);
+    assert!(matches!(
+        run(&mut db_manager, "SHOW COLUMNS FROM missing;"),
+        Err(DatabaseError::CatalogError(CatalogError::TableNotFound(_)))
+    ));
+}
+
+#[test]
+fn test_show_tables_and_databases_like() {
+    let mut db_manager = DatabaseManager::in_memory();
+    for db in ["shop", "shop_archive", "Stats"] {
+        db_manager.create_database(db).unwrap();
+    }
+    db_manager.use_database("shop").unwrap();
+
+    for table in ["user_roles", "users", "orders", "User_prefs"] {
+        run(
+            &mut db_manager,
+            &format!("CREATE TABLE {} (id INT);", table),
+        )
+        .unwrap();
+    }
 
-    // Verify update
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["test".to_string()],
-        where_clauses: vec![],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
+    let mut names = |sql: &str| match run(&mut db_manager, sql) {
+        Ok(QueryResult::ResultSet(_, rows)) => rows
+            .into_iter()
+            .map(|mut row| row.remove(0))
+            .collect::<Vec<_>>(),
+        other => panic!("not a result set: {:?}", other),
     };
 
-    let (_, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(rows[1][1], "updated");
+    assert_eq!(names("SHOW TABLES LIKE 'user_%';"), ["user_roles", "users"]);
+    assert_eq!(names("SHOW TABLES LIKE '%rs';"), ["orders", "users"]);
+    assert!(names("SHOW TABLES LIKE 'x%';").is_empty());
+    assert_eq!(names("SHOW TABLES;").len(), 4);
+
+    assert_eq!(
+        names("SHOW DATABASES LIKE 'shop%';"),
+        ["shop", "shop_archive"]
+    );
+    assert_eq!(names("SHOW DATABASES LIKE 'S%';"), ["Stats"]);
+    assert_eq!(
+        db_manager.show_databases(Some("%a%")).unwrap(),
+        ["shop_archive", "Stats"]
+    );
+    assert_eq!(db_manager.show_tables(Some("order_")).unwrap(), ["orders"]);
 }
 
 #[test]
-fn test_delete() {
-    let (_temp, mut db_manager) = setup_test_db();
-
+fn test_unique_column_constraint() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![CreateTableField::Col(
-        "a".to_string(),
-        ColumnType::Int,
-        true,
-        ParserValue::Null,
-    )];
+    let is_unique_violation = |result: DatabaseResult<QueryResult>, index: &str| match result {
+        Err(DatabaseError::UniqueViolation(name, _)) => name == index,
+        _ => false,
+    };
 
-    db_manager.create_table("test", fields).unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE u (id INT NOT NULL, email VARCHAR(100) UNIQUE, code INT UNIQUE, PRIMARY KEY (id));",
+    )
+    .unwrap();
+    let meta = db_manager.describe_table("u").unwrap();
+    let unique: Vec<_> = meta
+        .indexes
+        .iter()
+        .filter(|idx| idx.unique)
+        .map(|idx| idx.name.as_str())
+        .collect();
+    assert_eq!(unique, ["uniq_email", "uniq_code"]);
+
+    run(
+        &mut db_manager,
+        "INSERT INTO u VALUES (1, 'a@x', 10), (2, 'b@x', 20), (3, NULL, NULL), (4, NULL, NULL);",
+    )
+    .unwrap();
+
+    // Against stored rows and within one statement; nothing is inserted on failure
+    assert!(is_unique_violation(
+        run(&mut db_manager, "INSERT INTO u VALUES (5, 'a@x', 50);"),
+        "uniq_email"
+    ));
+    assert!(is_unique_violation(
+        run(
+            &mut db_manager,
+            "INSERT INTO u VALUES (5, 'c@x', 50), (6, 'c@x', 60);"
+        ),
+        "uniq_email"
+    ));
+    for use_indexes in [true, false] {
+        db_manager.set_use_indexes(use_indexes);
+        // Build the index on code so the indexed probe is exercised too
+        select_rows(&mut db_manager, "SELECT id FROM u WHERE code = 10;");
+        assert!(is_unique_violation(
+            run(&mut db_manager, "INSERT INTO u VALUES (5, 'c@x', 20);"),
+            "uniq_code"
+        ));
+    }
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM u WHERE id > 4;").len(),
+        0
+    );
 
-    let rows = vec![
-        vec![ParserValue::Integer(1)],
-        vec![ParserValue::Integer(2)],
-        vec![ParserValue::Integer(3)],
-        vec![ParserValue::Integer(4)],
-        vec![ParserValue::Integer(5)],
-    ];
-    db_manager.insert("test", rows).unwrap();
+    // Updates may not collide with other rows or with each other
+    assert!(is_unique_violation(
+        run(&mut db_manager, "UPDATE u SET email = 'b@x' WHERE id = 1;"),
+        "uniq_email"
+    ));
+    assert!(is_unique_violation(
+        run(&mut db_manager, "UPDATE u SET code = 7 WHERE id > 2;"),
+        "uniq_code"
+    ));
+    run(&mut db_manager, "UPDATE u SET email = 'a@x' WHERE id = 1;").unwrap();
+    run(&mut db_manager, "UPDATE u SET code = 11 WHERE id = 1;").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM u WHERE code = 11;"),
+        vec![vec!["1"]]
+    );
 
-    // Delete WHERE a > 3
-    let where_clauses = Some(vec![WhereClause::Op(
-        crate::lexer_parser::TableColumn {
-            table: None,
-            column: "a".to_string(),
-        },
-        Operator::Gt,
-        crate::lexer_parser::Expression::Value(ParserValue::Integer(3)),
-    )]);
+    // REPLACE may reuse the values of the row it replaces, but not another row's
+    run(&mut db_manager, "REPLACE INTO u VALUES (1, 'a@x', 12);").unwrap();
+    assert!(is_unique_violation(
+        run(&mut db_manager, "REPLACE INTO u VALUES (1, 'b@x', 12);"),
+        "uniq_email"
+    ));
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT email, code FROM u WHERE id = 1;"),
+        vec![vec!["a@x", "12"]]
+    );
+}
 
-    let count = db_manager.delete("test", where_clauses).unwrap();
-    assert_eq!(count, 2);
+#[test]
+fn test_join_unknown_column_names_all_tables() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
 
-    // Verify deletion
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["test".to_string()],
-        where_clauses: vec![],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
-    };
+    run(&mut db_manager, "CREATE TABLE l (a INT, b INT);").unwrap();
+    run(&mut db_manager, "CREATE TABLE r (a INT, c INT);").unwrap();
+    run(&mut db_manager, "INSERT INTO l VALUES (1, 2);").unwrap();
+    run(&mut db_manager, "INSERT INTO r VALUES (1, 3);").unwrap();
 
-    let (_, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(rows.len(), 3);
+    // A column of the right table resolves, one in neither table names both
+    assert!(run(&mut db_manager, "SELECT c FROM l, r WHERE l.a = r.a;").is_ok());
+    let err = run(&mut db_manager, "SELECT d FROM l, r WHERE l.a = r.a;").unwrap_err();
+    assert!(matches!(
+        &err,
+        DatabaseError::ColumnNotFoundInJoin(column, tables) if column == "d" && tables == "l, r"
+    ));
+    assert_eq!(
+        err.to_string(),
+        "Column d not found in any of the joined tables (l, r)"
+    );
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "SELECT b FROM l, r WHERE l.a = r.a AND d = 1;"
+        ),
+        Err(DatabaseError::ColumnNotFoundInJoin(..))
+    ));
+
+    // A qualified column still blames the table it was qualified with
+    assert!(matches!(
+        run(&mut db_manager, "SELECT r.b FROM l, r WHERE l.a = r.a;"),
+        Err(DatabaseError::ColumnNotFound(column, table)) if column == "b" && table == "r"
+    ));
 }
 
 #[test]
-fn test_delete_all() {
-    let (_temp, mut db_manager) = setup_test_db();
-
+fn test_three_table_join() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![CreateTableField::Col(
-        "a".to_string(),
-        ColumnType::Int,
-        true,
-        ParserValue::Null,
-    )];
+    run(
+        &mut db_manager,
+        "CREATE TABLE customers (id INT NOT NULL, name VARCHAR(10), PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE products (id INT NOT NULL, title VARCHAR(10), price FLOAT, PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE orders (id INT NOT NULL, customer_id INT, product_id INT, qty INT, PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO customers VALUES (1, 'ann'), (2, 'bob'), (3, 'cid');",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO products VALUES (10, 'pen', 1.5), (20, 'ink', 4.0);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO orders VALUES (100, 1, 10, 3), (101, 2, 20, 1), (102, 1, 20, 2), (103, 3, 30, 5);",
+    )
+    .unwrap();
+
+    let (headers, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT * FROM orders, customers, products \
+         WHERE orders.customer_id = customers.id AND orders.product_id = products.id \
+         ORDER BY orders.id;",
+    );
+    assert_eq!(
+        headers,
+        [
+            "id",
+            "customer_id",
+            "product_id",
+            "qty",
+            "id",
+            "name",
+            "id",
+            "title",
+            "price"
+        ]
+    );
+    assert_eq!(
+        rows,
+        vec![
+            vec!["100", "1", "10", "3", "1", "ann", "10", "pen", "1.50"],
+            vec!["101", "2", "20", "1", "2", "bob", "20", "ink", "4.00"],
+            vec!["102", "1", "20", "2", "1", "ann", "20", "ink", "4.00"],
+        ]
+    );
 
-    db_manager.create_table("test", fields).unwrap();
+    // Aliases, filters on every table, ORDER BY on the last one and paging
+    let query = "SELECT c.name, p.title, o.qty FROM orders o, customers c, products p \
+                 WHERE o.customer_id = c.id AND p.id = o.product_id AND qty >= 2";
+    let (headers, rows) = select_with_headers(
+        &mut db_manager,
+        &format!("{} ORDER BY p.price DESC;", query),
+    );
+    assert_eq!(headers, ["name", "title", "qty"]);
+    assert_eq!(rows, vec![vec!["ann", "ink", "2"], vec!["ann", "pen", "3"]]);
+    let (_, rows) = select_with_headers(
+        &mut db_manager,
+        &format!(
+            "{} AND name LIKE 'a%' ORDER BY o.id LIMIT 1 OFFSET 1;",
+            query
+        ),
+    );
+    assert_eq!(rows, vec![vec!["ann", "ink", "2"]]);
 
-    let rows = vec![
-        vec![ParserValue::Integer(1)],
-        vec![ParserValue::Integer(2)],
-        vec![ParserValue::Integer(3)],
-    ];
-    db_manager.insert("test", rows).unwrap();
+    // The left table's own conditions can use its primary key index
+    let (_, rows) = select_with_headers(
+        &mut db_manager,
+        "SELECT name, title FROM orders, customers, products \
+         WHERE orders.id = 101 AND customer_id = customers.id AND product_id = products.id;",
+    );
+    assert_eq!(rows, vec![vec!["bob", "ink"]]);
 
-    // Delete all
-    let count = db_manager.delete("test", None).unwrap();
-    assert_eq!(count, 3);
+    // Unqualified names must be unique across all three tables
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "SELECT id FROM orders, customers, products WHERE qty = 1;"
+        ),
+        Err(DatabaseError::TypeMismatch(msg)) if msg == "Ambiguous column id"
+    ));
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "SELECT qty FROM orders, customers, products WHERE nope = 1;"
+        ),
+        Err(DatabaseError::ColumnNotFoundInJoin(column, tables))
+            if column == "nope" && tables == "orders, customers, products"
+    ));
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "SELECT qty FROM orders o, customers o, products p;"
+        ),
+        Err(DatabaseError::TypeMismatch(_))
+    ));
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "SELECT qty FROM orders, customers, products, orders o2;"
+        ),
+        Err(DatabaseError::TypeMismatch(_))
+    ));
+}
 
-    // Verify deletion
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["test".to_string()],
-        where_clauses: vec![],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
-    };
+#[test]
+fn test_drop_table_and_gc_remove_orphaned_index_files() {
+    let (temp_dir, mut db_manager) = setup_test_db();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+    let db_path = temp_dir.path().join("testdb");
+
+    run(&mut db_manager, "CREATE TABLE t (a INT, b INT);").unwrap();
+    run(&mut db_manager, "CREATE TABLE t_b (c INT);").unwrap();
+    run(&mut db_manager, "INSERT INTO t VALUES (1, 2);").unwrap();
+    run(&mut db_manager, "INSERT INTO t_b VALUES (3);").unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (b);").unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (a, b);").unwrap();
+    run(&mut db_manager, "ALTER TABLE t_b ADD INDEX (c);").unwrap();
+    // Left behind by an earlier index on t.a that the catalog no longer knows about
+    fs::write(db_path.join("t_a.idx"), b"stale").unwrap();
+
+    run(&mut db_manager, "DROP TABLE t;").unwrap();
+    for file in ["t_a.idx", "t_b.idx", "t_a__b.idx"] {
+        assert!(!db_path.join(file).exists(), "{} survived", file);
+    }
+    // t_b's own index starts with "t_" too but is kept
+    assert!(db_path.join("t_b_c.idx").exists());
 
-    let (_, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(rows.len(), 0);
+    for file in ["gone_x.idx", "t_b_zzz.idx", "notes.txt"] {
+        fs::write(db_path.join(file), b"junk").unwrap();
+    }
+    assert_eq!(
+        db_manager.gc_orphaned_indexes().unwrap(),
+        vec!["gone_x.idx".to_string(), "t_b_zzz.idx".to_string()]
+    );
+    assert!(db_path.join("notes.txt").exists());
+    assert!(db_path.join("t_b_c.idx").exists());
+    assert!(db_manager.gc_orphaned_indexes().unwrap().is_empty());
+    assert_eq!(db_manager.index_manager.search_all("t_b", "c", 3).len(), 1);
 }
 
 #[test]
-fn test_not_null_constraint() {
-    let (_temp, mut db_manager) = setup_test_db();
+fn test_prepared_statements() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT NOT NULL, name VARCHAR(10), PRIMARY KEY (id));",
+    )
+    .unwrap();
+
+    let mut insert = db_manager.prepare("INSERT INTO t VALUES (?, ?);").unwrap();
+    assert_eq!(insert.param_count(), 2);
+    for id in 0..20 {
+        let name = if id % 5 == 0 {
+            RecordValue::Null
+        } else {
+            RecordValue::String(format!("n{}", id))
+        };
+        insert.execute(&[RecordValue::Int(id), name]).unwrap();
+    }
+    assert!(matches!(
+        insert.execute(&[RecordValue::Int(1)]),
+        Err(DatabaseError::ParameterCount(2, 1))
+    ));
+    assert!(matches!(
+        insert.execute(&[RecordValue::Int(1), RecordValue::Null]),
+        Err(DatabaseError::PrimaryKeyViolation(_))
+    ));
+
+    let mut lookup = db_manager
+        .prepare("SELECT name FROM t WHERE id = ?; -- point lookup")
+        .unwrap();
+    for id in [3, 7, 10] {
+        match lookup.execute(&[RecordValue::Int(id)]).unwrap() {
+            QueryResult::ResultSet(_, rows) => {
+                let expected = if id % 5 == 0 {
+                    "NULL".to_string()
+                } else {
+                    format!("n{}", id)
+                };
+                assert_eq!(rows, vec![vec![expected]]);
+            }
+            other => panic!("not a result set: {:?}", other),
+        }
+    }
+
+    let mut update = db_manager
+        .prepare("UPDATE t SET name = ? WHERE id >= ? AND id < ? + 2;")
+        .unwrap();
+    assert!(matches!(
+        update
+            .execute(&[
+                RecordValue::String("x".into()),
+                RecordValue::Int(4),
+                RecordValue::Int(4),
+            ])
+            .unwrap(),
+        QueryResult::RowsAffected(2)
+    ));
 
+    let mut delete = db_manager.prepare("DELETE FROM t WHERE name = ?;").unwrap();
+    assert!(matches!(
+        delete.execute(&[RecordValue::String("x".into())]).unwrap(),
+        QueryResult::RowsAffected(2)
+    ));
+
+    // Running a statement with placeholders directly leaves them unbound
+    let unbound = crate::lexer_parser::parse("INSERT INTO t VALUES (?, 'a');")
+        .unwrap()
+        .remove(0);
+    assert!(matches!(
+        db_manager.execute_query(unbound),
+        Err(DatabaseError::UnboundParameter(0))
+    ));
+    assert!(matches!(
+        db_manager.prepare("SELECT * FROM t; SELECT * FROM t;"),
+        Err(DatabaseError::ParseError(_))
+    ));
+}
+
+#[test]
+fn test_limit_synonyms_and_percent() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![CreateTableField::Col(
-        "a".to_string(),
-        ColumnType::Int,
-        true, // NOT NULL
-        ParserValue::Null,
-    )];
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT NOT NULL, g INT, PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (0, 0), (1, 1), (2, 0), (3, 1), (4, 0), (5, 1), (6, 0), (7, 1), (8, 0), (9, 1);",
+    ).unwrap();
+
+    let ids = |rows: Vec<Vec<String>>| -> Vec<String> {
+        rows.into_iter().map(|mut row| row.remove(0)).collect()
+    };
 
-    db_manager.create_table("test", fields).unwrap();
+    assert_eq!(
+        ids(select_rows(
+            &mut db_manager,
+            "SELECT TOP 3 id FROM t ORDER BY id;"
+        )),
+        ["0", "1", "2"]
+    );
+    assert_eq!(
+        ids(select_rows(
+            &mut db_manager,
+            "SELECT id FROM t ORDER BY id DESC FETCH FIRST 2 ROWS ONLY;"
+        )),
+        ["9", "8"]
+    );
+    // 25% of 10 rows rounds up to 3
+    assert_eq!(
+        ids(select_rows(
+            &mut db_manager,
+            "SELECT id FROM t ORDER BY id LIMIT 25 PERCENT;"
+        )),
+        ["0", "1", "2"]
+    );
+    assert_eq!(
+        ids(select_rows(
+            &mut db_manager,
+            "SELECT id FROM t ORDER BY id LIMIT 25 PERCENT OFFSET 8;"
+        )),
+        ["8", "9"]
+    );
+    assert!(select_rows(&mut db_manager, "SELECT id FROM t LIMIT 0 PERCENT;").is_empty());
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT TOP 100 PERCENT id FROM t;").len(),
+        10
+    );
+    assert_eq!(
+        ids(select_rows(
+            &mut db_manager,
+            "SELECT g, COUNT(*) FROM t GROUP BY g ORDER BY g LIMIT 50 PERCENT;"
+        )),
+        ["0"]
+    );
 
-    // Try to insert NULL into NOT NULL column
-    let rows = vec![vec![ParserValue::Null]];
-    let result = db_manager.insert("test", rows);
-    assert!(result.is_err());
+    // Only reserved where the limit syntax expects them
+    run(&mut db_manager, "CREATE TABLE r (rows INT, percent INT);").unwrap();
+    run(&mut db_manager, "INSERT INTO r VALUES (1, 10), (2, 20);").unwrap();
+    match run(
+        &mut db_manager,
+        "SELECT TOP 1 percent FROM r ORDER BY rows DESC;",
+    )
+    .unwrap()
+    {
+        QueryResult::ResultSet(headers, rows) => {
+            assert_eq!(headers, vec!["percent"]);
+            assert_eq!(rows, vec![vec!["20"]]);
+        }
+        other => panic!("not a result set: {:?}", other),
+    }
 }
 
 #[test]
-fn test_load_data_infile() {
-    let (_temp, mut db_manager) = setup_test_db();
+fn test_corrupted_index_rebuilt_on_open() {
+    let (temp_dir, mut db_manager) = setup_test_db();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(&mut db_manager, "CREATE TABLE t (id INT NOT NULL, v INT);").unwrap();
+    let values: Vec<String> = (0..50).map(|i| format!("({}, {})", i, i * 10)).collect();
+    run(
+        &mut db_manager,
+        &format!("INSERT INTO t VALUES {};", values.join(", ")),
+    )
+    .unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX idx_id (id);").unwrap();
+    drop(db_manager);
+
+    // Flip the first key of the first leaf so it sorts after its neighbours
+    let index_path = temp_dir.path().join("testdb").join("t_id.idx");
+    let mut bytes = std::fs::read(&index_path).unwrap();
+    let first_leaf = u32::from_le_bytes(bytes[16..20].try_into().unwrap()) as usize;
+    let offset = (first_leaf + 1) * crate::file::PAGE_SIZE + 16;
+    bytes[offset + 7] ^= 0x40;
+    std::fs::write(&index_path, bytes).unwrap();
+
+    let mut db_manager = DatabaseManager::new(temp_dir.path().to_str().unwrap()).unwrap();
+    db_manager.use_database("testdb").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT v FROM t WHERE id = 0;"),
+        vec![vec!["0"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT v FROM t WHERE id = 42;"),
+        vec![vec!["420"]]
+    );
+}
 
+#[test]
+fn test_modify_column() {
+    let (temp_dir, mut db_manager) = setup_test_db();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "b".to_string(),
-            ColumnType::Char(10),
-            true,
-            ParserValue::Null,
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT NOT NULL, n INT DEFAULT 3, name VARCHAR(5), code VARCHAR(4), PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX idx_n (n);").unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 10, 'ab', '7'), (2, NULL, 'abcde', '8'), (3, 30, 'c', '9');",
+    )
+    .unwrap();
+
+    // Narrowing fails as a whole when one value does not fit
+    assert!(matches!(
+        run(&mut db_manager, "ALTER TABLE t MODIFY name VARCHAR(3);"),
+        Err(DatabaseError::TypeMismatch(_))
+    ));
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT name FROM t WHERE id = 2;"),
+        vec![vec!["abcde"]]
+    );
+    assert!(matches!(
+        run(
+            &mut db_manager,
+            "ALTER TABLE t MODIFY COLUMN name VARCHAR(10);"
         ),
-        CreateTableField::Col("c".to_string(), ColumnType::Float, true, ParserValue::Null),
-    ];
+        Ok(QueryResult::RowsAffected(3))
+    ));
+    run(
+        &mut db_manager,
+        "UPDATE t SET name = 'abcdefghij' WHERE id = 1;",
+    )
+    .unwrap();
+
+    // INT widens to FLOAT and loses its plain index
+    run(&mut db_manager, "ALTER TABLE t MODIFY n FLOAT;").unwrap();
+    let meta = db_manager.describe_table("t").unwrap();
+    assert_eq!(meta.columns[1].column_type, "FLOAT");
+    assert_eq!(meta.columns[1].default(), Some(RecordValue::Float(3.0)));
+    assert!(meta.indexes.is_empty());
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT n FROM t ORDER BY id;"),
+        vec![vec!["10.00"], vec!["NULL"], vec!["30.00"]]
+    );
 
-    db_manager.create_table("test", fields).unwrap();
+    // FLOAT back to INT only works for whole numbers
+    run(&mut db_manager, "UPDATE t SET n = 2.5 WHERE id = 3;").unwrap();
+    assert!(matches!(
+        run(&mut db_manager, "ALTER TABLE t MODIFY n INT;"),
+        Err(DatabaseError::TypeMismatch(_))
+    ));
+    run(&mut db_manager, "UPDATE t SET n = 30.0 WHERE id = 3;").unwrap();
+    run(&mut db_manager, "ALTER TABLE t MODIFY n INT;").unwrap();
+
+    // Text converts to numbers when every value parses; a VARCHAR index
+    // is rebuilt on INT keys rather than dropped
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX idx_code (code);").unwrap();
+    run(&mut db_manager, "ALTER TABLE t MODIFY code INT;").unwrap();
+    let meta = db_manager.describe_table("t").unwrap();
+    assert_eq!(meta.indexes.len(), 1);
+    assert_eq!(meta.indexes[0].name, "idx_code");
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE code = 8;"),
+        vec![vec!["2"]]
+    );
 
-    // Create test CSV file
-    let csv_content = "1,hello,1.5\n2, world,2.5\n3,rust,3.5\n";
-    let csv_file = _temp.path().join("test.csv");
-    std::fs::write(&csv_file, csv_content).unwrap();
+    // Foreign key columns keep their type
+    run(
+        &mut db_manager,
+        "CREATE TABLE c (pid INT, FOREIGN KEY (pid) REFERENCES t (id));",
+    )
+    .unwrap();
+    assert!(matches!(
+        run(&mut db_manager, "ALTER TABLE t MODIFY id FLOAT;"),
+        Err(DatabaseError::TypeMismatch(_))
+    ));
 
-    // Load data
-    let count = db_manager
-        .load_data_infile(csv_file.to_str().unwrap(), "test", ',')
-        .unwrap();
-    assert_eq!(count, 3);
+    // The rewritten table and its rebuilt primary key index survive a reopen
+    assert!(temp_dir.path().join("testdb").join("t_id.idx").exists());
+    drop(db_manager);
+    let mut db_manager = DatabaseManager::new(temp_dir.path().to_str().unwrap()).unwrap();
+    db_manager.use_database("testdb").unwrap();
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id, n, name, code FROM t WHERE id = 1;"
+        ),
+        vec![vec!["1", "10", "abcdefghij", "7"]]
+    );
+    assert!(matches!(
+        run(&mut db_manager, "INSERT INTO t VALUES (3, 1, 'x', 1);"),
+        Err(DatabaseError::PrimaryKeyViolation(..))
+    ));
+}
+
+#[test]
+fn test_in_list_uses_index_ranges() {
+    assert_eq!(
+        DatabaseManager::contiguous_runs(vec![7, 5, 6, 12, 14, 15, 7, i64::MAX, i64::MAX - 1]),
+        vec![(5, 7), (12, 12), (14, 15), (i64::MAX - 1, i64::MAX)]
+    );
+    assert!(DatabaseManager::contiguous_runs(Vec::new()).is_empty());
 
-    // Verify loaded data
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["test".to_string()],
-        where_clauses: vec![],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
-    };
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
 
-    let (_, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(rows.len(), 3);
-    assert_eq!(rows[1][1], " world");
+    run(&mut db_manager, "CREATE TABLE t (id INT, v INT);").unwrap();
+    let values: Vec<String> = (1..=20).map(|i| format!("({}, {})", i, i % 10)).collect();
+    run(
+        &mut db_manager,
+        &format!("INSERT INTO t VALUES {}, (21, NULL);", values.join(", ")),
+    )
+    .unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (v);").unwrap();
+
+    // Two runs, a lone value, a repeat and a value with no rows
+    let query = "SELECT id FROM t WHERE v IN (3, 1, 2, 7, 2, 5, 6, 42) ORDER BY id;";
+    let expected: Vec<Vec<String>> = [1, 2, 3, 5, 6, 7, 11, 12, 13, 15, 16, 17]
+        .iter()
+        .map(|id| vec![id.to_string()])
+        .collect();
+    assert_eq!(select_rows(&mut db_manager, query), expected);
+    db_manager.set_use_indexes(false);
+    assert_eq!(select_rows(&mut db_manager, query), expected);
 }
 
 #[test]
-fn test_varchar_as_fixed_char() {
-    let (_temp, mut db_manager) = setup_test_db();
-
+fn test_delete_returning() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields = vec![CreateTableField::Col(
-        "name".to_string(),
-        ColumnType::Char(5), // VARCHAR(5) treated as CHAR(5)
-        true,
-        ParserValue::Null,
-    )];
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT NOT NULL, name VARCHAR(10), score FLOAT, PRIMARY KEY (id));",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 'a', 1.5), (2, 'b', NULL), (3, 'c', 3.0);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE c (pid INT, FOREIGN KEY (pid) REFERENCES t (id));",
+    )
+    .unwrap();
+    run(&mut db_manager, "INSERT INTO c VALUES (3);").unwrap();
+
+    match run(&mut db_manager, "DELETE FROM t WHERE id <= 2 RETURNING *;").unwrap() {
+        QueryResult::ResultSet(headers, rows) => {
+            assert_eq!(headers, vec!["id", "name", "score"]);
+            assert_eq!(rows, vec![vec!["1", "a", "1.50"], vec!["2", "b", "NULL"]]);
+        }
+        other => panic!("not a result set: {:?}", other),
+    }
+    match run(&mut db_manager, "DELETE FROM t WHERE id = 9 RETURNING *;").unwrap() {
+        QueryResult::ResultSet(_, rows) => assert!(rows.is_empty()),
+        other => panic!("not a result set: {:?}", other),
+    }
 
-    db_manager.create_table("test", fields).unwrap();
+    // A refused delete returns the error, not the rows
+    assert!(matches!(
+        run(&mut db_manager, "DELETE FROM t RETURNING *;"),
+        Err(DatabaseError::ForeignKeyViolation(_))
+    ));
+    match run(&mut db_manager, "SELECT id FROM t;").unwrap() {
+        QueryResult::ResultSet(_, rows) => assert_eq!(rows, vec![vec!["3"]]),
+        other => panic!("not a result set: {:?}", other),
+    }
+}
 
-    // Insert short string
-    let rows = vec![vec![ParserValue::String("hi".to_string())]];
-    db_manager.insert("test", rows).unwrap();
+#[test]
+fn test_oversized_records_rejected() {
+    use crate::record::RecordError;
 
-    // Verify it's stored and retrieved correctly
-    let clause = SelectClause {
-        selectors: Selectors::All,
-        table: vec!["test".to_string()],
-        where_clauses: vec![],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
+    let (_temp_dir, mut db_manager) = setup_test_db();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    let too_large = |result: DatabaseResult<QueryResult>| {
+        matches!(
+            result,
+            Err(DatabaseError::RecordError(
+                RecordError::RecordTooLarge { .. }
+            ))
+        )
     };
 
-    let (_, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(rows.len(), 1);
-    // String should be trimmed when displayed
-    assert_eq!(rows[0][0], "hi");
+    assert!(too_large(run(
+        &mut db_manager,
+        "CREATE TABLE t (a INT, b VARCHAR(9000));"
+    )));
+    // Nothing is left behind, so the name is still free
+    assert!(db_manager.show_tables(None).unwrap().is_empty());
+    run(&mut db_manager, "CREATE TABLE t (a INT, b VARCHAR(90));").unwrap();
+    run(&mut db_manager, "INSERT INTO t VALUES (1, 'x');").unwrap();
+
+    assert!(too_large(run(
+        &mut db_manager,
+        "ALTER TABLE t MODIFY b VARCHAR(9000);"
+    )));
+    match run(&mut db_manager, "SELECT * FROM t;").unwrap() {
+        QueryResult::ResultSet(_, rows) => assert_eq!(rows, vec![vec!["1", "x"]]),
+        other => panic!("not a result set: {:?}", other),
+    }
+
+    // Variable-length pages hold slightly less than fixed-length ones; a
+    // width between the two limits is refused before the table is rewritten
+    let overhead = 1 + 4 + 2;
+    let widest = crate::record::Page::max_variable_record_size() - overhead;
+    assert!(overhead + widest < crate::record::Page::max_record_size());
+    assert!(too_large(run(
+        &mut db_manager,
+        &format!("ALTER TABLE t MODIFY b VARCHAR({});", widest + 1)
+    )));
+    run(
+        &mut db_manager,
+        &format!("ALTER TABLE t MODIFY b VARCHAR({});", widest),
+    )
+    .unwrap();
+    match run(&mut db_manager, "SELECT * FROM t;").unwrap() {
+        QueryResult::ResultSet(_, rows) => assert_eq!(rows, vec![vec!["1", "x"]]),
+        other => panic!("not a result set: {:?}", other),
+    }
 }
 
 #[test]
-fn test_two_table_join_basic() {
-    let (_temp, mut db_manager) = setup_test_db();
-
+fn test_string_index_with_duplicate_keys() {
+    let mut db_manager = DatabaseManager::in_memory();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let fields1 = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "b".to_string(),
-            ColumnType::Char(10),
-            true,
-            ParserValue::Null,
-        ),
-    ];
-    let fields2 = vec![
-        CreateTableField::Col("a".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "c".to_string(),
-            ColumnType::Char(10),
-            true,
-            ParserValue::Null,
-        ),
-    ];
-
-    db_manager.create_table("t1", fields1).unwrap();
-    db_manager.create_table("t2", fields2).unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT, name VARCHAR(10), score FLOAT);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 'alice', 1.0), (2, 'bob', 2.0), (3, 'alice', 3.0), (4, NULL, 4.0);",
+    )
+    .unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (name);").unwrap();
+    assert!(matches!(
+        run(&mut db_manager, "ALTER TABLE t ADD INDEX (score);"),
+        Err(DatabaseError::TypeMismatch(_))
+    ));
 
-    let rows_t1 = vec![
-        vec![
-            ParserValue::Integer(1),
-            ParserValue::String("x".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(2),
-            ParserValue::String("y".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(3),
-            ParserValue::String("z".to_string()),
-        ],
-    ];
-    let rows_t2 = vec![
-        vec![
-            ParserValue::Integer(2),
-            ParserValue::String("p".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(3),
-            ParserValue::String("q".to_string()),
-        ],
-        vec![
-            ParserValue::Integer(4),
-            ParserValue::String("r".to_string()),
-        ],
-    ];
+    let query = "SELECT id FROM t WHERE name = 'alice';";
+    assert_eq!(
+        select_rows(&mut db_manager, query),
+        vec![vec!["1"], vec!["3"]]
+    );
 
-    db_manager.insert("t1", rows_t1).unwrap();
-    db_manager.insert("t2", rows_t2).unwrap();
+    // Both rows share one key, so deleting one leaves the other findable
+    run(&mut db_manager, "DELETE FROM t WHERE id = 1;").unwrap();
+    run(&mut db_manager, "UPDATE t SET name = 'alice' WHERE id = 2;").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, query),
+        vec![vec!["2"], vec!["3"]]
+    );
+    assert!(select_rows(&mut db_manager, "SELECT id FROM t WHERE name = 'bob';").is_empty());
 
-    let clause = SelectClause {
-        selectors: Selectors::List(vec![
-            Selector::Column(TableColumn {
-                table: Some("t1".to_string()),
-                column: "a".to_string(),
-            }),
-            Selector::Column(TableColumn {
-                table: Some("t1".to_string()),
-                column: "b".to_string(),
-            }),
-            Selector::Column(TableColumn {
-                table: Some("t2".to_string()),
-                column: "a".to_string(),
-            }),
-            Selector::Column(TableColumn {
-                table: Some("t2".to_string()),
-                column: "c".to_string(),
-            }),
-        ]),
-        table: vec!["t1".to_string(), "t2".to_string()],
-        where_clauses: vec![WhereClause::Op(
-            TableColumn {
-                table: Some("t1".to_string()),
-                column: "a".to_string(),
-            },
-            Operator::Eq,
-            Expression::Column(TableColumn {
-                table: Some("t2".to_string()),
-                column: "a".to_string(),
-            }),
-        )],
-        group_by: None,
-        order_by: None,
-        limit: None,
-        offset: None,
-    };
+    // Pose as a hash collision: an entry under 'carol's key that points at
+    // an 'alice' row is returned by the index but dropped by the re-check
+    let rid = db_manager
+        .index_manager
+        .search("t", "name", string_index_key("alice"))
+        .unwrap();
+    db_manager
+        .index_manager
+        .insert("t", "name", string_index_key("carol"), rid)
+        .unwrap();
+    assert!(select_rows(&mut db_manager, "SELECT id FROM t WHERE name = 'carol';").is_empty());
+    assert_eq!(
+        db_manager
+            .index_manager
+            .search_all("t", "name", string_index_key("carol"))
+            .len(),
+        1
+    );
 
-    let (headers, rows) = db_manager.select(clause).unwrap();
-    assert_eq!(headers, vec!["a", "b", "a", "c"]);
+    // A VARCHAR primary key is backed by an index yet still rejects duplicates
+    run(
+        &mut db_manager,
+        "CREATE TABLE u (code VARCHAR(8) NOT NULL, PRIMARY KEY (code));",
+    )
+    .unwrap();
+    run(&mut db_manager, "INSERT INTO u VALUES ('a1'), ('b2');").unwrap();
+    assert!(matches!(
+        run(&mut db_manager, "INSERT INTO u VALUES ('a1');"),
+        Err(DatabaseError::PrimaryKeyViolation(_))
+    ));
     assert_eq!(
-        rows,
-        vec![
-            vec![
-                "2".to_string(),
-                "y".to_string(),
-                "2".to_string(),
-                "p".to_string()
-            ],
-            vec![
-                "3".to_string(),
-                "z".to_string(),
-                "3".to_string(),
-                "q".to_string()
-            ],
-        ]
+        select_rows(&mut db_manager, "SELECT code FROM u WHERE code = 'b2';"),
+        vec![vec!["b2"]]
     );
 }
 
 #[test]
-fn test_persistence() {
-    let temp_dir = TempDir::new().unwrap();
-    let data_path = temp_dir.path().to_str().unwrap();
+fn test_order_by_desc_reads_index_backwards() {
+    let mut db_manager = DatabaseManager::in_memory();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
 
-    // Create database and table in first session
-    {
-        let mut db_manager = DatabaseManager::new(data_path).unwrap();
-        db_manager.create_database("testdb").unwrap();
-        db_manager.use_database("testdb").unwrap();
+    run(&mut db_manager, "CREATE TABLE t (id INT, v INT);").unwrap();
+    let values: Vec<String> = (1..=30).map(|i| format!("({}, {})", i, i % 7)).collect();
+    run(
+        &mut db_manager,
+        &format!(
+            "INSERT INTO t VALUES {}, (31, NULL), (32, NULL);",
+            values.join(", ")
+        ),
+    )
+    .unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (v);").unwrap();
+
+    let queries = [
+        "SELECT id, v FROM t ORDER BY v DESC;",
+        "SELECT id FROM t WHERE id > 10 ORDER BY v DESC LIMIT 5 OFFSET 2;",
+        "SELECT id FROM t ORDER BY v DESC LIMIT 40;",
+    ];
+    let indexed: Vec<_> = queries
+        .iter()
+        .map(|sql| select_rows(&mut db_manager, sql))
+        .collect();
+    db_manager.set_use_indexes(false);
+    for (sql, rows) in queries.iter().zip(&indexed) {
+        assert_eq!(&select_rows(&mut db_manager, sql), rows, "{}", sql);
+    }
+    db_manager.set_use_indexes(true);
 
-        let fields = vec![CreateTableField::Col(
-            "a".to_string(),
-            ColumnType::Int,
-            true,
-            ParserValue::Null,
-        )];
-        db_manager.create_table("test", fields).unwrap();
+    // Ties keep row order and NULLs come last
+    let all = &indexed[0];
+    assert_eq!(all[0], vec!["6", "6"]);
+    assert_eq!(all[1], vec!["13", "6"]);
+    assert_eq!(all[30], vec!["31", "NULL"]);
+    assert_eq!(all[31], vec!["32", "NULL"]);
 
-        let rows = vec![vec![ParserValue::Integer(42)]];
-        db_manager.insert("test", rows).unwrap();
-    }
+    // Hide one row from the index: it drops out, which shows the index was read
+    let rid = db_manager.index_manager.search("t", "v", 6).unwrap();
+    db_manager
+        .index_manager
+        .delete_entry("t", "v", 6, rid)
+        .unwrap();
+    assert_eq!(select_rows(&mut db_manager, queries[0]).len(), 31);
+}
 
-    // Reopen and verify data persists
+#[test]
+fn test_page_checksum_detects_corruption() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_path = temp_dir.path().to_str().unwrap();
     {
         let mut db_manager = DatabaseManager::new(data_path).unwrap();
-        db_manager.use_database("testdb").unwrap();
-
-        let clause = SelectClause {
-            selectors: Selectors::All,
-            table: vec!["test".to_string()],
-            where_clauses: vec![],
-            group_by: None,
-            order_by: None,
-            limit: None,
-            offset: None,
-        };
-
-        let (_, rows) = db_manager.select(clause).unwrap();
-        assert_eq!(rows.len(), 1);
-        assert_eq!(rows[0][0], "42");
+        run(&mut db_manager, "CREATE DATABASE testdb;").unwrap();
+        run(&mut db_manager, "USE testdb;").unwrap();
+        run(&mut db_manager, "CREATE TABLE t (id INT, name VARCHAR(8));").unwrap();
+        run(&mut db_manager, "INSERT INTO t VALUES (1, 'a'), (2, 'b');").unwrap();
     }
+
+    // Flip a bit of the first page, as a torn write or bad sector might
+    let table_path = temp_dir.path().join("testdb").join("t.tbl");
+    let mut bytes = std::fs::read(&table_path).unwrap();
+    bytes[crate::file::PAGE_SIZE - 1] ^= 0x01;
+    std::fs::write(&table_path, &bytes).unwrap();
+
+    let mut db_manager = DatabaseManager::new(data_path).unwrap();
+    run(&mut db_manager, "USE testdb;").unwrap();
+    let err = run(&mut db_manager, "SELECT * FROM t;").unwrap_err();
+    assert!(
+        err.to_string().contains("Checksum mismatch: page_id=0"),
+        "{}",
+        err
+    );
 }
 
 #[test]
-fn test_foreign_key_insert_update_validation() {
+fn test_varchar_update_that_moves_row_keeps_index() {
     let (_temp, mut db_manager) = setup_test_db();
-
-    db_manager.create_database("testdb").unwrap();
-    db_manager.use_database("testdb").unwrap();
-
-    let parent_fields = vec![
-        CreateTableField::Col("id".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
-            "parent".to_string(),
-            vec!["id".to_string()],
-        ))),
-    ];
-    db_manager.create_table("parent", parent_fields).unwrap();
-    db_manager
-        .execute_alter_statement(AlterStatement::AddIndex(
-            "parent".to_string(),
-            None,
-            vec!["id".to_string()],
-        ))
-        .unwrap();
-
-    let child_fields = vec![
-        CreateTableField::Col("id".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "parent_id".to_string(),
-            ColumnType::Int,
-            false,
-            ParserValue::Null,
-        ),
-        CreateTableField::Fkey(Box::new(AlterStatement::AddFKey(
-            "child".to_string(),
-            Some("fk_parent".to_string()),
-            vec!["parent_id".to_string()],
-            "parent".to_string(),
-            vec!["id".to_string()],
-        ))),
-    ];
-    db_manager.create_table("child", child_fields).unwrap();
-
-    db_manager
-        .insert("parent", vec![vec![ParserValue::Integer(1)]])
-        .unwrap();
-    db_manager
-        .insert(
-            "child",
-            vec![vec![ParserValue::Integer(1), ParserValue::Integer(1)]],
-        )
-        .unwrap();
-    db_manager
-        .insert(
-            "child",
-            vec![vec![ParserValue::Integer(2), ParserValue::Null]],
+    run(&mut db_manager, "CREATE DATABASE testdb;").unwrap();
+    run(&mut db_manager, "USE testdb;").unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT, body VARCHAR(4000));",
+    )
+    .unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (id);").unwrap();
+    for id in 1..=3 {
+        run(
+            &mut db_manager,
+            &format!("INSERT INTO t VALUES ({}, '{}');", id, "a".repeat(2500)),
         )
         .unwrap();
+    }
 
-    let bad_insert = db_manager.insert(
-        "child",
-        vec![vec![ParserValue::Integer(3), ParserValue::Integer(99)]],
-    );
-    assert!(matches!(
-        bad_insert,
-        Err(DatabaseError::ForeignKeyViolation(_))
-    ));
+    // Three 2500-byte rows nearly fill a page, so this one has to move
+    let long = "b".repeat(4000);
+    run(
+        &mut db_manager,
+        &format!("UPDATE t SET body = '{}' WHERE id = 2;", long),
+    )
+    .unwrap();
+
+    let rows = match run(&mut db_manager, "SELECT body FROM t WHERE id = 2;").unwrap() {
+        QueryResult::ResultSet(_, rows) => rows,
+        other => panic!("not a result set: {:?}", other),
+    };
+    assert_eq!(rows, vec![vec![long.clone()]]);
+    let rows = match run(&mut db_manager, "SELECT id FROM t;").unwrap() {
+        QueryResult::ResultSet(_, rows) => rows,
+        other => panic!("not a result set: {:?}", other),
+    };
+    assert_eq!(rows.len(), 3);
 
-    let updates = vec![("parent_id".to_string(), ParserValue::Integer(100))];
-    let bad_update = db_manager.update("child", updates, None);
     assert!(matches!(
-        bad_update,
-        Err(DatabaseError::ForeignKeyViolation(_))
+        run(
+            &mut db_manager,
+            &format!("UPDATE t SET body = '{}x' WHERE id = 1;", long)
+        ),
+        Err(DatabaseError::TypeMismatch(_))
     ));
 }
 
 #[test]
-fn test_foreign_key_add_constraint_validation() {
+fn test_moved_row_keeps_every_index() {
     let (_temp, mut db_manager) = setup_test_db();
+    run(&mut db_manager, "CREATE DATABASE testdb;").unwrap();
+    run(&mut db_manager, "USE testdb;").unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT, k INT, name VARCHAR(20), body VARCHAR(4000));",
+    )
+    .unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (id);").unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (k);").unwrap();
+    run(&mut db_manager, "ALTER TABLE t ADD INDEX (name);").unwrap();
+    for id in 1..=3 {
+        run(
+            &mut db_manager,
+            &format!(
+                "INSERT INTO t VALUES ({}, {}, 'n{}', '{}');",
+                id,
+                id * 10,
+                id,
+                "a".repeat(2500)
+            ),
+        )
+        .unwrap();
+    }
 
-    db_manager.create_database("testdb").unwrap();
-    db_manager.use_database("testdb").unwrap();
+    // The row outgrows its page and gets a new RID
+    run(
+        &mut db_manager,
+        &format!("UPDATE t SET body = '{}' WHERE id = 2;", "b".repeat(4000)),
+    )
+    .unwrap();
+
+    // Every index must lead to the row at its new place, exactly once
+    for sql in [
+        "SELECT id, k, name FROM t WHERE id = 2;",
+        "SELECT id, k, name FROM t WHERE k = 20;",
+        "SELECT id, k, name FROM t WHERE name = 'n2';",
+    ] {
+        match run(&mut db_manager, sql).unwrap() {
+            QueryResult::ResultSet(_, rows) => {
+                assert_eq!(rows, vec![vec!["2", "20", "n2"]], "{}", sql)
+            }
+            other => panic!("not a result set: {:?}", other),
+        }
+    }
+    for (sql, expected) in [
+        ("SELECT id FROM t WHERE id > 0;", 3),
+        ("SELECT id FROM t WHERE k >= 10;", 3),
+        ("SELECT id FROM t;", 3),
+    ] {
+        match run(&mut db_manager, sql).unwrap() {
+            QueryResult::ResultSet(_, rows) => assert_eq!(rows.len(), expected, "{}", sql),
+            other => panic!("not a result set: {:?}", other),
+        }
+    }
+}
 
-    let parent_fields = vec![
-        CreateTableField::Col("id".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
-            "parent".to_string(),
-            vec!["id".to_string()],
-        ))),
-    ];
-    db_manager.create_table("parent", parent_fields).unwrap();
-    db_manager
-        .execute_alter_statement(AlterStatement::AddIndex(
-            "parent".to_string(),
-            None,
-            vec!["id".to_string()],
-        ))
+#[test]
+fn test_recover_rolls_back_interrupted_load() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_path = temp_dir.path().to_str().unwrap();
+
+    {
+        let mut db_manager = DatabaseManager::new(data_path).unwrap();
+        run(&mut db_manager, "CREATE DATABASE testdb;").unwrap();
+        run(&mut db_manager, "USE testdb;").unwrap();
+        run(
+            &mut db_manager,
+            "CREATE TABLE t (id INT, v INT, PRIMARY KEY (id));",
+        )
+        .unwrap();
+        run(
+            &mut db_manager,
+            "INSERT INTO t VALUES (1, 10), (2, 20), (3, 30);",
+        )
         .unwrap();
+        run(&mut db_manager, "ALTER TABLE t ADD INDEX (v);").unwrap();
+    }
 
-    let child_fields = vec![
-        CreateTableField::Col("id".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "parent_id".to_string(),
-            ColumnType::Int,
-            false,
-            ParserValue::Null,
-        ),
-    ];
-    db_manager.create_table("child", child_fields).unwrap();
+    // Pose as a LOAD DATA INFILE that died after clearing the table and its
+    // index and writing part of the new data
+    let db_dir = temp_dir.path().join("testdb");
+    let index_files: Vec<_> = std::fs::read_dir(&db_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "idx"))
+        .collect();
+    assert!(!index_files.is_empty());
+    {
+        let mut file_manager = PagedFileManager::new();
+        file_manager
+            .begin_logged_unit(temp_dir.path().join(".wal"))
+            .unwrap();
+        for path in index_files.iter().chain([&db_dir.join("t.tbl")]) {
+            file_manager.remove_file(path).unwrap();
+        }
+        file_manager.create_file(db_dir.join("t.tbl")).unwrap();
+        let handle = file_manager.open_file(db_dir.join("t.tbl")).unwrap();
+        file_manager
+            .write_page(handle, 3, &vec![0xAB; crate::file::PAGE_SIZE])
+            .unwrap();
+        // Dropped without committing
+    }
 
-    db_manager
-        .insert("parent", vec![vec![ParserValue::Integer(1)]])
-        .unwrap();
-    db_manager
-        .insert(
-            "child",
-            vec![vec![ParserValue::Integer(1), ParserValue::Integer(1)]],
+    let mut db_manager = DatabaseManager::new(data_path).unwrap();
+    assert!(!db_manager.recover().unwrap());
+    run(&mut db_manager, "USE testdb;").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT * FROM t;"),
+        vec![vec!["1", "10"], vec!["2", "20"], vec!["3", "30"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE v = 20;"),
+        vec![vec!["2"]]
+    );
+    assert!(std::fs::read_dir(&db_dir).unwrap().all(|entry| {
+        !entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .ends_with(".walbak")
+    }));
+}
+
+#[test]
+fn test_recover_rolls_back_interrupted_single_row_insert() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_path = temp_dir.path().to_str().unwrap();
+    let db_dir = temp_dir.path().join("testdb");
+    let data_files = || -> Vec<(std::path::PathBuf, Vec<u8>)> {
+        let mut files: Vec<_> = std::fs::read_dir(&db_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "tbl" || ext == "idx")
+            })
+            .map(|path| {
+                let bytes = std::fs::read(&path).unwrap();
+                (path, bytes)
+            })
+            .collect();
+        files.sort();
+        files
+    };
+
+    {
+        let mut db_manager = DatabaseManager::new(data_path).unwrap();
+        run(&mut db_manager, "CREATE DATABASE testdb;").unwrap();
+        run(&mut db_manager, "USE testdb;").unwrap();
+        run(
+            &mut db_manager,
+            "CREATE TABLE t (id INT, v INT, PRIMARY KEY (id));",
         )
         .unwrap();
-    db_manager
-        .insert(
-            "child",
-            vec![vec![ParserValue::Integer(2), ParserValue::Integer(99)]],
+        run(
+            &mut db_manager,
+            "INSERT INTO t VALUES (1, 10), (2, 20), (3, 30);",
         )
         .unwrap();
+        run(&mut db_manager, "ALTER TABLE t ADD INDEX (v);").unwrap();
+    }
+    let before = data_files();
+
+    // The row is on disk and the log empty as soon as the INSERT returns
+    let mut db_manager = DatabaseManager::new(data_path).unwrap();
+    run(&mut db_manager, "USE testdb;").unwrap();
+    run(&mut db_manager, "INSERT INTO t VALUES (4, 40);").unwrap();
+    let after = data_files();
+    assert_ne!(before, after);
+    assert_eq!(
+        std::fs::metadata(temp_dir.path().join(".wal"))
+            .unwrap()
+            .len(),
+        0
+    );
+    drop(db_manager);
 
-    let add_fk = db_manager.execute_alter_statement(AlterStatement::AddFKey(
-        "child".to_string(),
-        Some("fk_parent".to_string()),
-        vec!["parent_id".to_string()],
-        "parent".to_string(),
-        vec!["id".to_string()],
-    ));
-    assert!(matches!(add_fk, Err(DatabaseError::ForeignKeyViolation(_))));
+    // Replay the pages it wrote as a unit that died before committing
+    for (path, bytes) in &before {
+        std::fs::write(path, bytes).unwrap();
+    }
+    {
+        let mut file_manager = PagedFileManager::new();
+        file_manager
+            .begin_logged_unit(temp_dir.path().join(".wal"))
+            .unwrap();
+        for ((path, old), (_, new)) in before.iter().zip(&after) {
+            let handle = file_manager.open_file(path).unwrap();
+            for (page_id, page) in new.chunks(crate::file::PAGE_SIZE).enumerate() {
+                let offset = page_id * crate::file::PAGE_SIZE;
+                if old.get(offset..offset + page.len()) != Some(page) {
+                    file_manager.write_page(handle, page_id, page).unwrap();
+                }
+            }
+        }
+        // Dropped without committing
+    }
+    assert_eq!(data_files(), after);
 
-    let where_clauses = vec![WhereClause::Op(
-        TableColumn {
-            table: None,
-            column: "parent_id".to_string(),
-        },
-        Operator::Eq,
-        Expression::Value(ParserValue::Integer(99)),
-    )];
-    db_manager.delete("child", Some(where_clauses)).unwrap();
+    let mut db_manager = DatabaseManager::new(data_path).unwrap();
+    assert_eq!(data_files(), before);
+    run(&mut db_manager, "USE testdb;").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT * FROM t;"),
+        vec![vec!["1", "10"], vec!["2", "20"], vec!["3", "30"]]
+    );
+    assert!(select_rows(&mut db_manager, "SELECT id FROM t WHERE v = 40;").is_empty());
+    run(&mut db_manager, "INSERT INTO t VALUES (4, 40);").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE v = 40;"),
+        vec![vec!["4"]]
+    );
+}
 
-    db_manager
-        .execute_alter_statement(AlterStatement::AddFKey(
-            "child".to_string(),
-            Some("fk_parent".to_string()),
-            vec!["parent_id".to_string()],
-            "parent".to_string(),
-            vec!["id".to_string()],
-        ))
+#[test]
+fn test_database_named_wal() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_path = temp_dir.path().to_str().unwrap();
+    {
+        let mut db_manager = DatabaseManager::new(data_path).unwrap();
+        run(&mut db_manager, "CREATE DATABASE testdb;").unwrap();
+        run(&mut db_manager, "USE testdb;").unwrap();
+        run(&mut db_manager, "CREATE TABLE t (id INT);").unwrap();
+        // A logged unit leaves its (empty) log behind in the data directory
+        run(&mut db_manager, "INSERT INTO t VALUES (1), (2);").unwrap();
+
+        run(&mut db_manager, "CREATE DATABASE wal;").unwrap();
+        run(&mut db_manager, "USE wal;").unwrap();
+        run(&mut db_manager, "CREATE TABLE t (id INT);").unwrap();
+        run(&mut db_manager, "INSERT INTO t VALUES (1), (2);").unwrap();
+        assert!(matches!(
+            db_manager.create_database(".wal"),
+            Err(DatabaseError::InvalidDatabaseName(_))
+        ));
+    }
+
+    let mut db_manager = DatabaseManager::new(data_path).unwrap();
+    assert_eq!(
+        db_manager.show_databases(None).unwrap(),
+        vec!["testdb".to_string(), "wal".to_string()]
+    );
+    run(&mut db_manager, "USE wal;").unwrap();
+    run(&mut db_manager, "INSERT INTO t VALUES (3), (4);").unwrap();
+    match run(&mut db_manager, "SELECT * FROM t;").unwrap() {
+        QueryResult::ResultSet(_, rows) => assert_eq!(rows.len(), 4),
+        other => panic!("not a result set: {:?}", other),
+    }
+}
+
+#[test]
+fn test_transaction_commit_and_rollback() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_path = temp_dir.path().to_str().unwrap();
+
+    for mut db_manager in [
+        DatabaseManager::in_memory(),
+        DatabaseManager::new(data_path).unwrap(),
+    ] {
+        run(&mut db_manager, "CREATE DATABASE testdb;").unwrap();
+        run(&mut db_manager, "USE testdb;").unwrap();
+        run(
+            &mut db_manager,
+            "CREATE TABLE t (id INT, v INT, PRIMARY KEY (id));",
+        )
         .unwrap();
+        run(&mut db_manager, "INSERT INTO t VALUES (1, 10), (2, 20);").unwrap();
+        let version = db_manager.table_version("t").unwrap();
+
+        run(&mut db_manager, "BEGIN;").unwrap();
+        assert!(matches!(
+            run(&mut db_manager, "BEGIN;"),
+            Err(DatabaseError::TransactionInProgress)
+        ));
+        assert!(matches!(
+            run(&mut db_manager, "CREATE TABLE u (id INT);"),
+            Err(DatabaseError::NotAllowedInTransaction(_))
+        ));
+        run(&mut db_manager, "INSERT INTO t VALUES (3, 30);").unwrap();
+        run(&mut db_manager, "UPDATE t SET v = 0 WHERE id = 1;").unwrap();
+        run(&mut db_manager, "DELETE FROM t WHERE id = 2;").unwrap();
+        // The transaction reads its own changes
+        assert_eq!(
+            select_rows(&mut db_manager, "SELECT * FROM t;"),
+            vec![vec!["1", "0"], vec!["3", "30"]]
+        );
+        let version_inside = db_manager.table_version("t").unwrap();
+        run(&mut db_manager, "ROLLBACK;").unwrap();
+
+        assert!(!db_manager.in_transaction());
+        assert_eq!(
+            select_rows(&mut db_manager, "SELECT * FROM t;"),
+            vec![vec!["1", "10"], vec!["2", "20"]]
+        );
+        // No dangling index entry for the rolled-back insert
+        assert!(select_rows(&mut db_manager, "SELECT * FROM t WHERE id = 3;").is_empty());
+        assert_eq!(
+            select_rows(&mut db_manager, "SELECT v FROM t WHERE id = 2;"),
+            vec![vec!["20"]]
+        );
+        let version_after = db_manager.table_version("t").unwrap();
+        assert!(version_after != version && version_after != version_inside);
+
+        run(&mut db_manager, "BEGIN;").unwrap();
+        run(&mut db_manager, "INSERT INTO t VALUES (3, 30);").unwrap();
+        run(&mut db_manager, "COMMIT;").unwrap();
+        assert!(matches!(
+            run(&mut db_manager, "COMMIT;"),
+            Err(DatabaseError::NoTransaction)
+        ));
+        assert_eq!(
+            select_rows(&mut db_manager, "SELECT v FROM t WHERE id = 3;"),
+            vec![vec!["30"]]
+        );
+
+        // A transaction left open is rolled back when the manager goes away
+        run(&mut db_manager, "BEGIN;").unwrap();
+        run(&mut db_manager, "DELETE FROM t;").unwrap();
+    }
+
+    let mut db_manager = DatabaseManager::new(data_path).unwrap();
+    run(&mut db_manager, "USE testdb;").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t;"),
+        vec![vec!["1"], vec!["2"], vec!["3"]]
+    );
 }
 
 #[test]
-fn test_foreign_key_delete_restriction() {
+fn test_decimal_columns() {
     let (_temp, mut db_manager) = setup_test_db();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT, price DECIMAL(6,2) DEFAULT 1.5);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, 0.1), (2, 0.2), (3, 12.345), (4, -7), (5, NULL);",
+    )
+    .unwrap();
+    run(&mut db_manager, "INSERT INTO t VALUES (6);").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT price FROM t;"),
+        vec![
+            vec!["0.10"],
+            vec!["0.20"],
+            vec!["12.35"],
+            vec!["-7.00"],
+            vec!["NULL"],
+            vec!["1.50"]
+        ]
+    );
+    // Too many integer digits for DECIMAL(6,2)
+    assert!(matches!(
+        run(&mut db_manager, "INSERT INTO t VALUES (7, 10000);"),
+        Err(DatabaseError::TypeMismatch(_))
+    ));
+
+    // Literals are compared at the scale they are written with
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE price = 0.1;"),
+        vec![vec!["1"]]
+    );
+    assert!(select_rows(&mut db_manager, "SELECT id FROM t WHERE price = 12.345;").is_empty());
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE price < 0;"),
+        vec![vec!["4"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM t WHERE price IS NOT NULL ORDER BY price DESC;"
+        ),
+        vec![vec!["3"], vec!["6"], vec!["2"], vec!["1"], vec!["4"]]
+    );
+
+    // 0.1 + 0.2 is exactly 0.3, with no float drift
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT SUM(price), AVG(price), MIN(price), MAX(price) FROM t WHERE id < 3;"
+        ),
+        vec![vec!["0.30", "0.15", "0.10", "0.20"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT SUM(price) FROM t;"),
+        vec![vec!["7.15"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT CAST(price AS INT) FROM t WHERE id = 3;"
+        ),
+        vec![vec!["12"]]
+    );
+}
 
+#[test]
+fn test_boolean_columns() {
+    let (temp, mut db_manager) = setup_test_db();
     db_manager.create_database("testdb").unwrap();
     db_manager.use_database("testdb").unwrap();
 
-    let parent_fields = vec![
-        CreateTableField::Col("id".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
-            "parent".to_string(),
-            vec!["id".to_string()],
-        ))),
-    ];
-    db_manager.create_table("parent", parent_fields).unwrap();
-    db_manager
-        .execute_alter_statement(AlterStatement::AddIndex(
-            "parent".to_string(),
-            None,
-            vec!["id".to_string()],
-        ))
-        .unwrap();
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT, flag BOOL, active BOOLEAN NOT NULL DEFAULT TRUE);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (1, NULL, FALSE), (2, FALSE, TRUE), (3, NULL);",
+    )
+    .unwrap();
+    for sql in [
+        "INSERT INTO t VALUES (4, NULL, 1);",
+        "INSERT INTO t VALUES (4, 'true');",
+        "INSERT INTO t VALUES (TRUE, TRUE);",
+        "CREATE TABLE u (b BOOLEAN DEFAULT 0);",
+    ] {
+        assert!(
+            matches!(
+                run(&mut db_manager, sql),
+                Err(DatabaseError::TypeMismatch(_))
+            ),
+            "{}",
+            sql
+        );
+    }
+    assert!(matches!(
+        run(&mut db_manager, "INSERT INTO t VALUES (4, TRUE, NULL);"),
+        Err(DatabaseError::NotNullViolation(_))
+    ));
 
-    let child_fields = vec![
-        CreateTableField::Col("id".to_string(), ColumnType::Int, true, ParserValue::Null),
-        CreateTableField::Col(
-            "parent_id".to_string(),
-            ColumnType::Int,
-            false,
-            ParserValue::Null,
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT * FROM t;"),
+        vec![
+            vec!["1", "NULL", "false"],
+            vec!["2", "false", "true"],
+            vec!["3", "NULL", "true"]
+        ]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE active = TRUE;"),
+        vec![vec!["2"], vec!["3"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE active < TRUE;"),
+        vec![vec!["1"]]
+    );
+    assert_eq!(
+        select_rows(
+            &mut db_manager,
+            "SELECT id FROM t ORDER BY active DESC, id DESC;"
         ),
-        CreateTableField::Fkey(Box::new(AlterStatement::AddFKey(
-            "child".to_string(),
-            Some("fk_parent".to_string()),
-            vec!["parent_id".to_string()],
-            "parent".to_string(),
-            vec!["id".to_string()],
-        ))),
-    ];
-    db_manager.create_table("child", child_fields).unwrap();
+        vec![vec!["3"], vec!["2"], vec!["1"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT MIN(active), MAX(active) FROM t;"),
+        vec![vec!["false", "true"]]
+    );
 
-    db_manager
-        .insert("parent", vec![vec![ParserValue::Integer(1)]])
-        .unwrap();
-    db_manager
-        .insert(
-            "child",
-            vec![vec![ParserValue::Integer(1), ParserValue::Integer(1)]],
-        )
-        .unwrap();
+    // The type and default survive reopening the database
+    drop(db_manager);
+    let mut db_manager = DatabaseManager::new(temp.path().to_str().unwrap()).unwrap();
+    db_manager.use_database("testdb").unwrap();
+    run(&mut db_manager, "INSERT INTO t VALUES (4, TRUE);").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT active FROM t WHERE id = 4;"),
+        vec![vec!["true"]]
+    );
+}
 
-    let delete_parent = db_manager.delete("parent", None);
+#[test]
+fn test_auto_increment_column() {
+    let (temp, mut db_manager) = setup_test_db();
+    db_manager.create_database("testdb").unwrap();
+    db_manager.use_database("testdb").unwrap();
+
+    run(
+        &mut db_manager,
+        "CREATE TABLE t (id INT NOT NULL AUTO_INCREMENT, name VARCHAR(10), PRIMARY KEY (id));",
+    )
+    .unwrap();
     assert!(matches!(
-        delete_parent,
-        Err(DatabaseError::ForeignKeyViolation(_))
+        run(&mut db_manager, "CREATE TABLE u (id FLOAT AUTO_INCREMENT);"),
+        Err(DatabaseError::TypeMismatch(_))
     ));
 
-    let where_child = vec![WhereClause::Op(
-        TableColumn {
-            table: None,
-            column: "id".to_string(),
-        },
-        Operator::Eq,
-        Expression::Value(ParserValue::Integer(1)),
-    )];
-    db_manager.delete("child", Some(where_child)).unwrap();
-    db_manager.delete("parent", None).unwrap();
+    // NULL takes the next value; explicit values move the counter past them
+    run(
+        &mut db_manager,
+        "INSERT INTO t VALUES (NULL, 'a'), (NULL, 'b'), (10, 'c'), (NULL, 'd'), (5, 'e');",
+    )
+    .unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id, name FROM t ORDER BY id;"),
+        vec![
+            vec!["1", "a"],
+            vec!["2", "b"],
+            vec!["5", "e"],
+            vec!["10", "c"],
+            vec!["11", "d"]
+        ]
+    );
+
+    // The counter is saved in the catalog and survives a restart
+    drop(db_manager);
+    let mut db_manager = DatabaseManager::new(temp.path().to_str().unwrap()).unwrap();
+    db_manager.use_database("testdb").unwrap();
+    assert_eq!(
+        db_manager.describe_table("t").unwrap().auto_increment["id"],
+        12
+    );
+    run(
+        &mut db_manager,
+        "CREATE TABLE s (name VARCHAR(10), id INT AUTO_INCREMENT);",
+    )
+    .unwrap();
+    run(
+        &mut db_manager,
+        "INSERT INTO s VALUES ('x'), ('y', NULL), ('z', 7);",
+    )
+    .unwrap();
+    run(&mut db_manager, "INSERT INTO t VALUES (NULL, 'f');").unwrap();
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT id FROM t WHERE name = 'f';"),
+        vec![vec!["12"]]
+    );
+    assert_eq!(
+        select_rows(&mut db_manager, "SELECT name, id FROM s;"),
+        vec![vec!["x", "1"], vec!["y", "2"], vec!["z", "7"]]
+    );
 }
@@ -1,6 +1,11 @@
 use lru::LruCache;
+use std::collections::HashSet;
 use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
+use super::checksum::{seal_page, verify_page};
 use super::error::{FileError, FileResult};
 use super::file_manager::{FileHandle, PagedFileManager};
 use super::{BUFFER_POOL_SIZE, PAGE_SIZE, PageId};
@@ -18,17 +23,90 @@ struct BufferEntry {
     data: Vec<u8>,
     /// Whether this page has been modified
     dirty: bool,
+    /// Set by hits that could not reorder the pool: every hit under CLOCK,
+    /// but not the initial load, so pages touched only once are evicted
+    /// first; under LRU, hits served under the read lock, whose move to the
+    /// most recently used end waits for the next eviction. Atomic so shared
+    /// (read-lock) hits can set it
+    referenced: AtomicBool,
+    /// Number of outstanding pins; pinned pages are never evicted
+    pin_count: usize,
 }
 
-/// Manages a buffer pool with LRU eviction policy
+impl BufferEntry {
+    fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            dirty: false,
+            referenced: AtomicBool::new(false),
+            pin_count: 0,
+        }
+    }
+}
+
+/// Page replacement policy used when the buffer pool is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvictionPolicy {
+    /// Evict the least recently used page
+    #[default]
+    Lru,
+    /// Second-chance CLOCK: pages are kept in load order and a hit only sets a
+    /// reference bit, so a sequential scan cannot push hot pages out in one pass
+    Clock,
+}
+
+/// Counters returned by [`BufferManager::stats`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BufferStats {
+    /// Page requests, hits plus misses
+    pub fetches: usize,
+    /// Requests served from the pool
+    pub hits: usize,
+    /// Requests that loaded the page from disk
+    pub misses: usize,
+    /// Pages dropped from the pool, including those cleared by `flush_and_clear`
+    pub evictions: usize,
+    /// Dirty pages written back to disk
+    pub dirty_flushes: usize,
+}
+
+/// Manages a buffer pool with LRU or CLOCK eviction policy
+///
+/// Shared as `Arc<RwLock<BufferManager>>`: cached page reads go through
+/// `read_page_shared` under the read lock, everything that loads, evicts or
+/// dirties a page takes the write lock. Statements of one `DatabaseManager`
+/// never overlap, since all of its entry points take `&mut self`; the read
+/// lock is for table scans from `RecordManager::scan_iter`, which own their
+/// `Arc` and can run side by side on other threads.
+///
+/// Pages of files registered with `enable_checksums` are stamped with a
+/// format version and CRC32 when written back and checked when loaded again,
+/// so a torn write or flipped bit surfaces as `FileError::ChecksumMismatch`
+/// instead of garbage rows.
 pub struct BufferManager {
     /// Underlying file manager
     file_manager: PagedFileManager,
     /// Combined buffer pool and LRU tracker: single data structure for both storage and eviction policy
     /// This eliminates redundant hash lookups - every operation now hits only ONE hash table
+    /// Under CLOCK, hits never reorder the cache, so its LRU end is the clock hand
     buffer_pool: LruCache<BufferKey, BufferEntry>,
     /// Maximum size of the buffer pool
     max_pool_size: usize,
+    /// Page replacement policy
+    policy: EvictionPolicy,
+    /// Page requests served from the pool; atomic so shared (read-lock) hits can count
+    hits: AtomicUsize,
+    /// Number of pages loaded from disk (buffer misses)
+    misses: usize,
+    /// Pages dropped from the pool
+    evictions: usize,
+    /// Dirty pages written back to disk
+    dirty_flushes: usize,
+    /// Files whose pages carry a checksum
+    checksummed_files: HashSet<FileHandle>,
+    /// Files whose dirty pages from before the logged unit in progress were
+    /// written back unlogged, so all their dirty pages now belong to the unit
+    settled_files: HashSet<FileHandle>,
     /// Reusable buffer for loading pages (avoids allocation on every load)
     load_buffer: Vec<u8>,
 }
@@ -41,14 +119,80 @@ impl BufferManager {
 
     /// Create a new buffer manager with specified capacity
     pub fn with_capacity(file_manager: PagedFileManager, capacity: usize) -> Self {
+        Self::with_policy(file_manager, capacity, EvictionPolicy::default())
+    }
+
+    /// Create a new buffer manager with specified capacity and eviction policy
+    pub fn with_policy(
+        file_manager: PagedFileManager,
+        capacity: usize,
+        policy: EvictionPolicy,
+    ) -> Self {
         Self {
             file_manager,
             buffer_pool: LruCache::new(NonZeroUsize::new(capacity).unwrap()),
             max_pool_size: capacity,
+            policy,
+            hits: AtomicUsize::new(0),
+            misses: 0,
+            evictions: 0,
+            dirty_flushes: 0,
+            checksummed_files: HashSet::new(),
+            settled_files: HashSet::new(),
             load_buffer: vec![0u8; PAGE_SIZE], // Allocate once, reuse for all page loads
         }
     }
 
+    /// Get the current eviction policy
+    pub fn eviction_policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    /// Switch the eviction policy; cached pages are kept
+    pub fn set_eviction_policy(&mut self, policy: EvictionPolicy) {
+        self.policy = policy;
+    }
+
+    /// Number of pages loaded from disk since creation or the last `reset_miss_count`
+    pub fn miss_count(&self) -> usize {
+        self.misses
+    }
+
+    /// Reset the miss counter, e.g. between benchmark phases
+    pub fn reset_miss_count(&mut self) {
+        self.misses = 0;
+    }
+
+    /// Page requests, evictions and write-backs since creation or the last `reset_stats`
+    pub fn stats(&self) -> BufferStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        BufferStats {
+            fetches: hits + self.misses,
+            hits,
+            misses: self.misses,
+            evictions: self.evictions,
+            dirty_flushes: self.dirty_flushes,
+        }
+    }
+
+    /// Reset every counter reported by `stats`
+    pub fn reset_stats(&mut self) {
+        *self.hits.get_mut() = 0;
+        self.misses = 0;
+        self.evictions = 0;
+        self.dirty_flushes = 0;
+    }
+
+    /// Seal every page of `file` with a checksum when it is written and
+    /// verify it when it is loaded
+    ///
+    /// The file's page layout must leave the bytes at `PAGE_VERSION_OFFSET`
+    /// and `PAGE_CHECKSUM_OFFSET..PAGE_CHECKSUM_OFFSET + 4` to the buffer
+    /// manager. Pages written before, with both still zero, are read unchecked.
+    pub fn enable_checksums(&mut self, file: FileHandle) {
+        self.checksummed_files.insert(file);
+    }
+
     /// Get a reference to the file manager
     pub fn file_manager(&self) -> &PagedFileManager {
         &self.file_manager
@@ -67,32 +211,112 @@ impl BufferManager {
         if self.buffer_pool.peek(&key).is_none() {
             // Page not in buffer, need to load it
             self.load_page(file, page_id)?;
+        } else {
+            self.touch(&key);
         }
 
-        // Now get the page (this updates LRU automatically)
-        Ok(&self.buffer_pool.get(&key).unwrap().data)
+        Ok(&self.buffer_pool.peek(&key).unwrap().data)
+    }
+
+    /// Get a cached page without loading it
+    /// Takes `&self`, so it can be served under a shared (read) lock; the hit
+    /// only sets the page's reference bit, and under LRU the page moves to
+    /// the most recently used end when the next eviction runs
+    pub fn peek_page(&self, file: FileHandle, page_id: PageId) -> Option<&[u8]> {
+        let key = BufferKey { file, page_id };
+        self.buffer_pool.peek(&key).map(|entry| {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            entry.referenced.store(true, Ordering::Relaxed);
+            entry.data.as_slice()
+        })
+    }
+
+    /// Copy a page out of a shared buffer manager
+    /// Cache hits only take the read lock, so concurrent readers do not block each other;
+    /// misses fall back to the write lock to load the page into the pool
+    pub fn read_page_shared(
+        shared: &RwLock<BufferManager>,
+        file: FileHandle,
+        page_id: PageId,
+        out: &mut [u8],
+    ) -> FileResult<()> {
+        {
+            let buffer_manager = shared.read().unwrap();
+            if let Some(data) = buffer_manager.peek_page(file, page_id) {
+                out.copy_from_slice(data);
+                return Ok(());
+            }
+        }
+
+        let mut buffer_manager = shared.write().unwrap();
+        let data = buffer_manager.get_page(file, page_id)?;
+        out.copy_from_slice(data);
+        Ok(())
     }
 
     /// Get a mutable reference to a page, loading it if necessary
     /// This automatically marks the page as dirty
     pub fn get_page_mut(&mut self, file: FileHandle, page_id: PageId) -> FileResult<&mut [u8]> {
         let key = BufferKey { file, page_id };
+        self.settle_file(file)?;
 
         // Try to get the page first - single hash lookup
-        if self.buffer_pool.get_mut(&key).is_none() {
+        if self.buffer_pool.peek(&key).is_none() {
             // Page not in buffer, load it
             self.load_page(file, page_id)?;
+        } else {
+            self.touch(&key);
         }
 
-        // Get mutable reference again (this updates LRU automatically)
-        let entry = self.buffer_pool.get_mut(&key).unwrap();
+        let entry = self.buffer_pool.peek_mut(&key).unwrap();
         entry.dirty = true;
         Ok(&mut entry.data)
     }
 
+    /// Record a hit on a cached page according to the eviction policy
+    fn touch(&mut self, key: &BufferKey) {
+        *self.hits.get_mut() += 1;
+        match self.policy {
+            // Move the page to the most recently used end
+            EvictionPolicy::Lru => {
+                if let Some(entry) = self.buffer_pool.get_mut(key) {
+                    *entry.referenced.get_mut() = false;
+                }
+            }
+            EvictionPolicy::Clock => {
+                if let Some(entry) = self.buffer_pool.peek_mut(key) {
+                    *entry.referenced.get_mut() = true;
+                }
+            }
+        }
+    }
+
+    /// Pin a page in the buffer pool, loading it if necessary
+    /// A pinned page is never chosen for eviction until every pin is released with `unpin_page`
+    pub fn pin_page(&mut self, file: FileHandle, page_id: PageId) -> FileResult<()> {
+        let key = BufferKey { file, page_id };
+        if self.buffer_pool.peek(&key).is_none() {
+            self.load_page(file, page_id)?;
+        }
+        self.buffer_pool.peek_mut(&key).unwrap().pin_count += 1;
+        Ok(())
+    }
+
+    /// Release one pin on a page
+    pub fn unpin_page(&mut self, file: FileHandle, page_id: PageId) -> FileResult<()> {
+        let key = BufferKey { file, page_id };
+        let entry = self
+            .buffer_pool
+            .peek_mut(&key)
+            .ok_or(FileError::PageNotFound(page_id))?;
+        entry.pin_count = entry.pin_count.saturating_sub(1);
+        Ok(())
+    }
+
     /// Mark a page as dirty (modified)
     pub fn mark_dirty(&mut self, file: FileHandle, page_id: PageId) -> FileResult<()> {
         let key = BufferKey { file, page_id };
+        self.settle_file(file)?;
 
         let entry = self
             .buffer_pool
@@ -106,20 +330,25 @@ impl BufferManager {
     /// Flush a specific page to disk if it's dirty
     pub fn flush_page(&mut self, file: FileHandle, page_id: PageId) -> FileResult<()> {
         let key = BufferKey { file, page_id };
+        self.settle_file(file)?;
 
         // Single peek operation - doesn't update LRU since we're just flushing
         if let Some(entry) = self.buffer_pool.peek_mut(&key)
             && entry.dirty
         {
+            if self.checksummed_files.contains(&file) {
+                seal_page(&mut entry.data);
+            }
             self.file_manager.write_page(file, page_id, &entry.data)?;
             entry.dirty = false;
+            self.dirty_flushes += 1;
         }
 
         Ok(())
     }
 
-    /// Flush all dirty pages to disk
-    pub fn flush_all(&mut self) -> FileResult<()> {
+    /// Flush all dirty pages to disk, returning how many pages were written
+    pub fn flush_all(&mut self) -> FileResult<usize> {
         // Iterate and flush all dirty pages without collecting keys first
         // Use iter() to avoid updating LRU order during flush
         let mut dirty_pages = Vec::new();
@@ -130,21 +359,106 @@ impl BufferManager {
             }
         }
 
+        // Inside a logged unit, changes from before it are written unlogged first
+        let mut settled = 0;
+        for key in &dirty_pages {
+            settled += self.settle_file(key.file)?;
+        }
+
+        let mut flushed = 0;
         for key in dirty_pages {
             // Use peek_mut to avoid LRU update during flush
             if let Some(entry) = self.buffer_pool.peek_mut(&key)
                 && entry.dirty
             {
+                if self.checksummed_files.contains(&key.file) {
+                    seal_page(&mut entry.data);
+                }
                 self.file_manager
                     .write_page(key.file, key.page_id, &entry.data)?;
                 entry.dirty = false;
+                flushed += 1;
             }
         }
+        self.dirty_flushes += flushed;
 
         // Sync all files to ensure data is persisted to disk
         self.file_manager.sync_all()?;
 
-        Ok(())
+        Ok(settled + flushed)
+    }
+
+    /// Start a logged unit (see [`PagedFileManager::begin_logged_unit`])
+    ///
+    /// Nothing is written yet. Pages dirtied before the unit stay cached
+    /// until the unit first dirties or writes a page of the same file; then
+    /// they are written back unlogged (see [`Self::settle_file`]), so the
+    /// log only ever holds the unit's own changes.
+    pub fn begin_logged_unit<P: AsRef<Path>>(&mut self, wal_path: P) -> FileResult<()> {
+        self.file_manager.begin_logged_unit(wal_path)
+    }
+
+    /// Finish the innermost logged unit; when it is the outermost one, the
+    /// pages it dirtied are written and only the files it wrote are synced
+    pub fn commit_logged_unit(&mut self) -> FileResult<()> {
+        if self.file_manager.logged_unit_depth() == 1 {
+            let unit_pages: Vec<BufferKey> = self
+                .buffer_pool
+                .iter()
+                .filter(|(key, entry)| entry.dirty && self.settled_files.contains(&key.file))
+                .map(|(key, _)| *key)
+                .collect();
+            for key in unit_pages {
+                self.flush_page(key.file, key.page_id)?;
+            }
+            self.settled_files.clear();
+        }
+        self.file_manager.commit_logged_unit()
+    }
+
+    /// Undo the logged unit in progress: pages it dirtied are dropped
+    /// without being written, those dirtied before it are written back, and
+    /// the cache is cleared before the files are restored (see
+    /// [`PagedFileManager::rollback_logged_unit`])
+    pub fn rollback_logged_unit(&mut self) -> FileResult<()> {
+        let files: HashSet<FileHandle> = self
+            .buffer_pool
+            .iter()
+            .filter(|(_, entry)| entry.dirty)
+            .map(|(key, _)| key.file)
+            .collect();
+        for file in files {
+            self.settle_file(file)?;
+        }
+        self.settled_files.clear();
+        self.evictions += self.buffer_pool.len();
+        self.buffer_pool.clear();
+        self.file_manager.rollback_logged_unit()
+    }
+
+    /// Inside a logged unit, write back the pages of `file` dirtied before
+    /// the unit began, unlogged, the first time the unit is about to change
+    /// the file. Returns how many pages were written.
+    fn settle_file(&mut self, file: FileHandle) -> FileResult<usize> {
+        if self.file_manager.logged_unit_depth() == 0 || !self.settled_files.insert(file) {
+            return Ok(0);
+        }
+
+        let mut written = 0;
+        for (key, entry) in self.buffer_pool.iter_mut() {
+            if key.file != file || !entry.dirty {
+                continue;
+            }
+            if self.checksummed_files.contains(&file) {
+                seal_page(&mut entry.data);
+            }
+            self.file_manager
+                .write_page_unlogged(file, key.page_id, &entry.data)?;
+            entry.dirty = false;
+            written += 1;
+        }
+        self.dirty_flushes += written;
+        Ok(written)
     }
 
     /// Flush all dirty pages and clear the entire buffer pool
@@ -154,6 +468,7 @@ impl BufferManager {
         self.flush_all()?;
 
         // Clear the combined buffer pool and LRU cache
+        self.evictions += self.buffer_pool.len();
         self.buffer_pool.clear();
 
         Ok(())
@@ -170,6 +485,7 @@ impl BufferManager {
 
             // Remove from buffer pool (LRU is automatically updated)
             self.buffer_pool.pop(&key);
+            self.evictions += 1;
         }
 
         Ok(())
@@ -181,7 +497,7 @@ impl BufferManager {
 
         // Check if buffer pool is full - evict until we have space
         while self.buffer_pool.len() >= self.max_pool_size {
-            self.evict_lru_page()?;
+            self.evict_one_page()?;
         }
 
         // Ensure load_buffer has correct capacity (in case it was never initialized or shrunk)
@@ -192,28 +508,42 @@ impl BufferManager {
         // Load page from disk into reusable buffer (no allocation!)
         self.file_manager
             .read_page(file, page_id, &mut self.load_buffer)?;
+        if self.checksummed_files.contains(&file) {
+            verify_page(&self.load_buffer, page_id)?;
+        }
 
         // Move the loaded data into buffer pool using mem::take
         // This swaps ownership without copying - the buffer moves into the pool
         // and we get back an empty Vec (which will be replaced on next eviction or reused)
         let data = std::mem::take(&mut self.load_buffer);
 
-        // Single operation: insert into LRU cache (which handles eviction automatically)
-        self.buffer_pool
-            .put(key, BufferEntry { data, dirty: false });
+        // Single operation: insert into LRU cache (the pool was made room for above)
+        self.buffer_pool.put(key, BufferEntry::new(data));
+        self.misses += 1;
 
         Ok(())
     }
 
-    /// Evict the least recently used page from the buffer pool
-    fn evict_lru_page(&mut self) -> FileResult<()> {
-        // Pop LRU entry from the cache (single operation)
-        if let Some((key, entry)) = self.buffer_pool.pop_lru() {
+    /// Pick a victim according to the eviction policy and remove it from the buffer pool
+    fn evict_one_page(&mut self) -> FileResult<()> {
+        let victim = match self.policy {
+            EvictionPolicy::Lru => self.least_recently_used(),
+            EvictionPolicy::Clock => self.advance_clock_hand(),
+        };
+        let key = victim.ok_or(FileError::BufferPoolFull)?;
+        self.settle_file(key.file)?;
+
+        if let Some(mut entry) = self.buffer_pool.pop(&key) {
             // Flush if dirty before evicting
             if entry.dirty {
+                if self.checksummed_files.contains(&key.file) {
+                    seal_page(&mut entry.data);
+                }
                 self.file_manager
                     .write_page(key.file, key.page_id, &entry.data)?;
+                self.dirty_flushes += 1;
             }
+            self.evictions += 1;
 
             // Recycle the evicted buffer for future page loads (avoid allocation)
             self.load_buffer = entry.data;
@@ -222,6 +552,41 @@ impl BufferManager {
         Ok(())
     }
 
+    /// Find the least recently used page that is not pinned
+    /// Pages hit under the read lock since they last moved are moved to the
+    /// most recently used end first, so they count as recently used
+    fn least_recently_used(&mut self) -> Option<BufferKey> {
+        loop {
+            let (key, entry) = self
+                .buffer_pool
+                .iter()
+                .rev()
+                .find(|(_, entry)| entry.pin_count == 0)?;
+            let key = *key;
+            if !entry.referenced.load(Ordering::Relaxed) {
+                return Some(key);
+            }
+            let entry = self.buffer_pool.get_mut(&key).unwrap();
+            *entry.referenced.get_mut() = false;
+        }
+    }
+
+    /// Sweep the clock hand until it reaches an unpinned page whose reference bit is clear
+    /// Referenced pages get their bit cleared and a second chance at the back of the ring;
+    /// two full sweeps without a victim means every page is pinned
+    fn advance_clock_hand(&mut self) -> Option<BufferKey> {
+        for _ in 0..2 * self.buffer_pool.len() {
+            let (key, entry) = self.buffer_pool.peek_lru()?;
+            let key = *key;
+            if entry.pin_count == 0 && !entry.referenced.load(Ordering::Relaxed) {
+                return Some(key);
+            }
+            let entry = self.buffer_pool.get_mut(&key).unwrap();
+            *entry.referenced.get_mut() = false;
+        }
+        None
+    }
+
     /// Get the number of pages currently in the buffer pool
     pub fn buffer_pool_size(&self) -> usize {
         self.buffer_pool.len()
@@ -249,6 +614,9 @@ impl Drop for BufferManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::file::{PAGE_VERSION_CHECKSUM, PAGE_VERSION_OFFSET};
+    use std::path::PathBuf;
+    use std::sync::Arc;
     use tempfile::TempDir;
 
     fn setup_test_env() -> (TempDir, BufferManager, FileHandle) {
@@ -355,8 +723,9 @@ mod tests {
         assert_eq!(bm.dirty_page_count(), 5);
 
         // Flush all
-        bm.flush_all().unwrap();
+        assert_eq!(bm.flush_all().unwrap(), 5);
         assert_eq!(bm.dirty_page_count(), 0);
+        assert_eq!(bm.flush_all().unwrap(), 0);
 
         // Verify all were written
         for i in 0..5 {
@@ -378,6 +747,84 @@ mod tests {
         assert!(!bm.is_page_cached(handle, 0));
     }
 
+    #[test]
+    fn test_stats() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let test_file = temp_dir.path().join("test.db");
+
+        let mut file_manager = PagedFileManager::new();
+        file_manager.create_file(&test_file).unwrap();
+        let handle = file_manager.open_file(&test_file).unwrap();
+        let bm = BufferManager::with_capacity(file_manager, 2);
+        let shared = RwLock::new(bm);
+        let mut bm = shared.write().unwrap();
+
+        bm.get_page_mut(handle, 0).unwrap()[0] = 1;
+        bm.get_page(handle, 0).unwrap();
+        bm.get_page(handle, 1).unwrap();
+        // Page 0 is dirty and written back when page 2 pushes it out
+        bm.get_page(handle, 2).unwrap();
+        drop(bm);
+        let mut out = [0u8; PAGE_SIZE];
+        BufferManager::read_page_shared(&shared, handle, 2, &mut out).unwrap();
+
+        let mut bm = shared.write().unwrap();
+        assert_eq!(
+            bm.stats(),
+            BufferStats {
+                fetches: 5,
+                hits: 2,
+                misses: 3,
+                evictions: 1,
+                dirty_flushes: 1,
+            }
+        );
+
+        // Clearing the pool counts every cached page as evicted
+        bm.get_page_mut(handle, 1).unwrap();
+        bm.flush_and_clear().unwrap();
+        let stats = bm.stats();
+        assert_eq!((stats.evictions, stats.dirty_flushes), (3, 2));
+
+        bm.reset_stats();
+        assert_eq!(bm.stats(), BufferStats::default());
+    }
+
+    #[test]
+    fn test_checksum_detects_corruption() {
+        let (_temp_dir, mut bm, handle) = setup_test_env();
+        bm.enable_checksums(handle);
+        let read_raw = |bm: &mut BufferManager, page_id| {
+            let mut raw = vec![0u8; PAGE_SIZE];
+            bm.file_manager_mut()
+                .read_page(handle, page_id, &mut raw)
+                .unwrap();
+            raw
+        };
+
+        // A legacy page without a checksum is read as is and sealed when written back
+        let mut legacy = vec![0u8; PAGE_SIZE];
+        legacy[100] = 42;
+        bm.file_manager_mut()
+            .write_page(handle, 0, &legacy)
+            .unwrap();
+        assert_eq!(bm.get_page_mut(handle, 0).unwrap()[100], 42);
+        bm.flush_and_clear().unwrap();
+        let mut raw = read_raw(&mut bm, 0);
+        assert_eq!(raw[PAGE_VERSION_OFFSET], PAGE_VERSION_CHECKSUM);
+        assert_eq!(bm.get_page(handle, 0).unwrap()[100], 42);
+        bm.flush_and_clear().unwrap();
+
+        // Flip one bit on disk
+        raw[PAGE_SIZE - 1] ^= 0x01;
+        bm.file_manager_mut().write_page(handle, 0, &raw).unwrap();
+        assert!(matches!(
+            bm.get_page(handle, 0),
+            Err(FileError::ChecksumMismatch(0))
+        ));
+        assert!(!bm.is_page_cached(handle, 0));
+    }
+
     #[test]
     fn test_lru_eviction() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -432,6 +879,118 @@ mod tests {
         assert!(bm.is_page_cached(handle, 3));
     }
 
+    #[test]
+    fn test_shared_hit_counts_as_recent_use() {
+        for policy in [EvictionPolicy::Lru, EvictionPolicy::Clock] {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let test_file = temp_dir.path().join("test.db");
+
+            let mut file_manager = PagedFileManager::new();
+            file_manager.create_file(&test_file).unwrap();
+            let handle = file_manager.open_file(&test_file).unwrap();
+
+            let shared = RwLock::new(BufferManager::with_policy(file_manager, 3, policy));
+            for page_id in 0..3 {
+                shared.write().unwrap().get_page(handle, page_id).unwrap();
+            }
+
+            // Hit page 0 under the read lock only, then load a 4th page
+            let mut out = vec![0u8; PAGE_SIZE];
+            BufferManager::read_page_shared(&shared, handle, 0, &mut out).unwrap();
+            let mut bm = shared.into_inner().unwrap();
+            assert_eq!(bm.stats().hits, 1);
+            bm.get_page(handle, 3).unwrap();
+
+            assert!(bm.is_page_cached(handle, 0), "{:?}", policy);
+            assert!(!bm.is_page_cached(handle, 1), "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn test_concurrent_shared_reads() {
+        let (_temp_dir, mut bm, handle) = setup_test_env();
+        for page_id in 0..8 {
+            bm.get_page_mut(handle, page_id).unwrap()[0] = page_id as u8;
+        }
+
+        // Every page is cached, so all readers only ever take the read lock
+        let shared = Arc::new(RwLock::new(bm));
+        let readers: Vec<_> = (0..8)
+            .map(|_| {
+                let shared = shared.clone();
+                std::thread::spawn(move || {
+                    let mut out = vec![0u8; PAGE_SIZE];
+                    for round in 0..100 {
+                        let page_id = round % 8;
+                        BufferManager::read_page_shared(&shared, handle, page_id, &mut out)
+                            .unwrap();
+                        assert_eq!(out[0], page_id as u8);
+                    }
+                })
+            })
+            .collect();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let stats = shared.read().unwrap().stats();
+        assert_eq!(stats.hits, 800);
+        assert_eq!(stats.misses, 8);
+    }
+
+    #[test]
+    fn test_pinned_page_is_not_evicted() {
+        for policy in [EvictionPolicy::Lru, EvictionPolicy::Clock] {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let test_file = temp_dir.path().join("test.db");
+
+            let mut file_manager = PagedFileManager::new();
+            file_manager.create_file(&test_file).unwrap();
+            let handle = file_manager.open_file(&test_file).unwrap();
+            let mut bm = BufferManager::with_policy(file_manager, 2, policy);
+
+            bm.pin_page(handle, 0).unwrap();
+            bm.get_page(handle, 1).unwrap();
+            bm.get_page(handle, 2).unwrap();
+            bm.get_page(handle, 3).unwrap();
+            assert!(bm.is_page_cached(handle, 0), "{:?}", policy);
+
+            // Every slot pinned: loading another page cannot make room
+            bm.pin_page(handle, 3).unwrap();
+            assert!(matches!(
+                bm.get_page(handle, 4),
+                Err(FileError::BufferPoolFull)
+            ));
+
+            bm.unpin_page(handle, 0).unwrap();
+            bm.get_page(handle, 4).unwrap();
+            assert!(!bm.is_page_cached(handle, 0), "{:?}", policy);
+            assert!(bm.is_page_cached(handle, 3), "{:?}", policy);
+        }
+    }
+
+    #[test]
+    fn test_clock_keeps_hot_pages_across_scan() {
+        let run = |policy: EvictionPolicy| {
+            let temp_dir = tempfile::tempdir().unwrap();
+            let test_file = temp_dir.path().join("test.db");
+            let mut file_manager = PagedFileManager::new();
+            file_manager.create_file(&test_file).unwrap();
+            let handle = file_manager.open_file(&test_file).unwrap();
+            let mut bm = BufferManager::with_policy(file_manager, 4, policy);
+            assert_eq!(bm.eviction_policy(), policy);
+
+            // Two hot pages hit repeatedly, then a one-pass scan, then the hot pages again
+            for page_id in [0, 1, 0, 1, 10, 11, 12, 13, 0, 1] {
+                bm.get_page(handle, page_id).unwrap();
+            }
+            bm.miss_count()
+        };
+
+        assert_eq!(run(EvictionPolicy::Lru), 8);
+        assert_eq!(run(EvictionPolicy::Clock), 6);
+    }
+
     #[test]
     fn test_dirty_page_flushed_on_eviction() {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -511,4 +1070,54 @@ mod tests {
         let page = bm.get_page(handle, 0).unwrap();
         assert_eq!(page[0], 88);
     }
+
+    fn setup_two_files() -> (TempDir, BufferManager, [(PathBuf, FileHandle); 2]) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut file_manager = PagedFileManager::new();
+        let files = ["a.db", "b.db"].map(|name| {
+            let path = temp_dir.path().join(name);
+            file_manager.create_file(&path).unwrap();
+            let handle = file_manager.open_file(&path).unwrap();
+            file_manager
+                .write_page(handle, 0, &vec![0u8; PAGE_SIZE])
+                .unwrap();
+            (path, handle)
+        });
+        (temp_dir, BufferManager::new(file_manager), files)
+    }
+
+    #[test]
+    fn test_logged_unit_writes_only_its_own_pages() {
+        let (temp_dir, mut bm, [(a_path, a), (b_path, b)]) = setup_two_files();
+
+        // Dirtied before the unit, in a file the unit never touches
+        bm.get_page_mut(b, 0).unwrap()[0] = 1;
+
+        bm.begin_logged_unit(temp_dir.path().join(".wal")).unwrap();
+        bm.get_page_mut(a, 0).unwrap()[0] = 2;
+        bm.commit_logged_unit().unwrap();
+
+        assert_eq!(std::fs::read(&a_path).unwrap()[0], 2);
+        assert_eq!(std::fs::read(&b_path).unwrap()[0], 0);
+        assert_eq!(bm.dirty_page_count(), 1);
+        assert!(bm.is_page_cached(b, 0));
+    }
+
+    #[test]
+    fn test_rollback_keeps_changes_from_before_the_unit() {
+        let (temp_dir, mut bm, [(a_path, a), (b_path, b)]) = setup_two_files();
+
+        bm.get_page_mut(a, 0).unwrap()[0] = 1;
+        bm.get_page_mut(b, 0).unwrap()[0] = 1;
+
+        bm.begin_logged_unit(temp_dir.path().join(".wal")).unwrap();
+        bm.get_page_mut(a, 0).unwrap()[0] = 2;
+        bm.flush_page(a, 0).unwrap();
+        bm.get_page_mut(a, 0).unwrap()[0] = 3;
+        bm.rollback_logged_unit().unwrap();
+
+        assert_eq!(std::fs::read(&a_path).unwrap()[0], 1);
+        assert_eq!(std::fs::read(&b_path).unwrap()[0], 1);
+        assert_eq!(bm.buffer_pool_size(), 0);
+    }
 }
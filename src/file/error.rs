@@ -26,6 +26,9 @@ pub enum FileError {
 
     #[error("File handle limit reached")]
     TooManyOpenFiles,
+
+    #[error("Checksum mismatch: page_id={0}")]
+    ChecksumMismatch(usize),
 }
 
 pub type FileResult<T> = Result<T, FileError>;
@@ -4,6 +4,7 @@ use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 use super::error::{FileError, FileResult};
+use super::wal::WriteAheadLog;
 use super::{PAGE_SIZE, PageId};
 
 /// Handle to an open file
@@ -26,13 +27,29 @@ pub struct PagedFileManager {
     next_handle: usize,
     /// Maximum number of open files
     max_open_files: usize,
+    /// File contents when running without a backing filesystem
+    memory_files: Option<HashMap<PathBuf, Vec<u8>>>,
+    /// Nesting depth of the logged unit in progress, 0 outside of one
+    unit_depth: usize,
+    /// Undo log of the logged unit in progress, for files on disk
+    wal: Option<WriteAheadLog>,
+    /// Contents of in-memory files when the logged unit in progress first
+    /// changed them, `None` for files it created
+    memory_snapshots: Option<HashMap<PathBuf, Option<Vec<u8>>>>,
 }
 
 struct FileEntry {
-    file: File,
+    storage: FileStorage,
     path: PathBuf,
 }
 
+/// Where the bytes of an open file live
+enum FileStorage {
+    Disk(File),
+    /// Contents are kept in `memory_files` under the entry's path
+    Memory,
+}
+
 impl PagedFileManager {
     /// Create a new paged file manager
     pub fn new() -> Self {
@@ -46,17 +63,96 @@ impl PagedFileManager {
             path_to_handle: HashMap::new(),
             next_handle: 0,
             max_open_files,
+            memory_files: None,
+            unit_depth: 0,
+            wal: None,
+            memory_snapshots: None,
+        }
+    }
+
+    /// Create a paged file manager that keeps every file in memory
+    ///
+    /// Nothing is read from or written to the filesystem; all contents are
+    /// lost when the manager is dropped.
+    pub fn in_memory() -> Self {
+        Self {
+            memory_files: Some(HashMap::new()),
+            ..Self::new()
         }
     }
 
+    /// Whether files are kept in memory instead of on disk
+    pub fn is_in_memory(&self) -> bool {
+        self.memory_files.is_some()
+    }
+
+    /// Check whether a file exists
+    pub fn file_exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        match &self.memory_files {
+            Some(files) => files.contains_key(path.as_ref()),
+            None => path.as_ref().exists(),
+        }
+    }
+
+    /// List the files directly inside a directory, sorted by path
+    pub fn list_files<P: AsRef<Path>>(&self, path: P) -> FileResult<Vec<PathBuf>> {
+        let path = path.as_ref();
+
+        let mut found: Vec<PathBuf> = match &self.memory_files {
+            Some(files) => files
+                .keys()
+                .filter(|file| file.parent() == Some(path))
+                .cloned()
+                .collect(),
+            None => {
+                let mut found = Vec::new();
+                for entry in std::fs::read_dir(path)? {
+                    let entry = entry?;
+                    if entry.file_type()?.is_file() {
+                        found.push(entry.path());
+                    }
+                }
+                found
+            }
+        };
+        found.sort();
+        Ok(found)
+    }
+
+    /// Remove a directory and every file below it
+    pub fn remove_dir_all<P: AsRef<Path>>(&mut self, path: P) -> FileResult<()> {
+        let path = path.as_ref();
+
+        let Some(files) = &self.memory_files else {
+            std::fs::remove_dir_all(path)?;
+            return Ok(());
+        };
+
+        let doomed: Vec<PathBuf> = files
+            .keys()
+            .filter(|file| file.starts_with(path))
+            .cloned()
+            .collect();
+        for file in doomed {
+            self.remove_file(file)?;
+        }
+        Ok(())
+    }
+
     /// Create a new file
     pub fn create_file<P: AsRef<Path>>(&mut self, path: P) -> FileResult<()> {
         let path = path.as_ref();
 
-        if path.exists() {
+        if self.file_exists(path) {
             return Err(FileError::FileAlreadyExists(path.display().to_string()));
         }
 
+        if let Some(files) = &mut self.memory_files {
+            Self::snapshot_memory_file(&mut self.memory_snapshots, files, path);
+            files.insert(path.to_path_buf(), Vec::new());
+            return Ok(());
+        }
+
         // Create parent directories if they don't exist
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
@@ -64,15 +160,18 @@ impl PagedFileManager {
 
         // Create the file
         File::create(path)?;
+        if let Some(wal) = &mut self.wal {
+            wal.created(&path.canonicalize()?)?;
+        }
         Ok(())
     }
 
     /// Open an existing file
     pub fn open_file<P: AsRef<Path>>(&mut self, path: P) -> FileResult<FileHandle> {
         let path_ref = path.as_ref();
-        let path = path_ref
-            .canonicalize()
-            .map_err(|_| FileError::FileNotFound(path_ref.display().to_string()))?;
+        let path = self
+            .resolve_path(path_ref)
+            .ok_or_else(|| FileError::FileNotFound(path_ref.display().to_string()))?;
 
         // Check if file is already open
         if let Some(&handle) = self.path_to_handle.get(&path) {
@@ -84,8 +183,12 @@ impl PagedFileManager {
             return Err(FileError::TooManyOpenFiles);
         }
 
-        // Open the file for reading and writing
-        let file = OpenOptions::new().read(true).write(true).open(&path)?;
+        let storage = if self.memory_files.is_some() {
+            FileStorage::Memory
+        } else {
+            // Open the file for reading and writing
+            FileStorage::Disk(OpenOptions::new().read(true).write(true).open(&path)?)
+        };
 
         let handle = FileHandle(self.next_handle);
         self.next_handle += 1;
@@ -93,7 +196,7 @@ impl PagedFileManager {
         self.open_files.insert(
             handle,
             FileEntry {
-                file,
+                storage,
                 path: path.clone(),
             },
         );
@@ -109,6 +212,13 @@ impl PagedFileManager {
             .remove(&handle)
             .ok_or(FileError::InvalidHandle(handle.0))?;
 
+        // Commit only syncs the files still open, so sync this one now
+        if self.wal.is_some()
+            && let FileStorage::Disk(file) = &entry.storage
+        {
+            file.sync_data()?;
+        }
+
         self.path_to_handle.remove(&entry.path);
         Ok(())
     }
@@ -118,13 +228,26 @@ impl PagedFileManager {
         let path = path.as_ref();
 
         // If file is open, close it first
-        if let Ok(canonical_path) = path.canonicalize()
-            && let Some(&handle) = self.path_to_handle.get(&canonical_path)
+        let canonical_path = self.resolve_path(path);
+        if let Some(canonical_path) = &canonical_path
+            && let Some(&handle) = self.path_to_handle.get(canonical_path)
         {
             self.close_file(handle)?;
         }
 
-        std::fs::remove_file(path)?;
+        if let (Some(wal), Some(canonical_path)) = (&mut self.wal, &canonical_path) {
+            return wal.remove(canonical_path);
+        }
+
+        match &mut self.memory_files {
+            Some(files) => {
+                Self::snapshot_memory_file(&mut self.memory_snapshots, files, path);
+                files
+                    .remove(path)
+                    .ok_or_else(|| FileError::FileNotFound(path.display().to_string()))?;
+            }
+            None => std::fs::remove_file(path)?,
+        }
         Ok(())
     }
 
@@ -148,9 +271,19 @@ impl PagedFileManager {
             .ok_or(FileError::InvalidHandle(handle.0))?;
 
         let offset = (page_id * PAGE_SIZE) as u64;
-        entry.file.seek(SeekFrom::Start(offset))?;
-
-        let bytes_read = entry.file.read(buffer)?;
+        let bytes_read = match &mut entry.storage {
+            FileStorage::Disk(file) => {
+                file.seek(SeekFrom::Start(offset))?;
+                file.read(buffer)?
+            }
+            FileStorage::Memory => {
+                let data = Self::memory_data(&mut self.memory_files, &entry.path)?;
+                let start = (offset as usize).min(data.len());
+                let available = &data[start..(start + PAGE_SIZE).min(data.len())];
+                buffer[..available.len()].copy_from_slice(available);
+                available.len()
+            }
+        };
 
         // If we read less than PAGE_SIZE, fill the rest with zeros
         if bytes_read < PAGE_SIZE {
@@ -166,6 +299,28 @@ impl PagedFileManager {
         handle: FileHandle,
         page_id: PageId,
         buffer: &[u8],
+    ) -> FileResult<()> {
+        self.write_page_logged(handle, page_id, buffer, true)
+    }
+
+    /// Write a page to a file without logging it in the logged unit in
+    /// progress, for changes made before the unit began; rolling the unit
+    /// back keeps them. The unit must not have written the file yet.
+    pub fn write_page_unlogged(
+        &mut self,
+        handle: FileHandle,
+        page_id: PageId,
+        buffer: &[u8],
+    ) -> FileResult<()> {
+        self.write_page_logged(handle, page_id, buffer, false)
+    }
+
+    fn write_page_logged(
+        &mut self,
+        handle: FileHandle,
+        page_id: PageId,
+        buffer: &[u8],
+        logged: bool,
     ) -> FileResult<()> {
         if buffer.len() != PAGE_SIZE {
             return Err(FileError::InvalidPageSize {
@@ -182,14 +337,33 @@ impl PagedFileManager {
         let offset = (page_id * PAGE_SIZE) as u64;
         let required_size = offset + PAGE_SIZE as u64;
 
+        let file = match &mut entry.storage {
+            FileStorage::Disk(file) => file,
+            FileStorage::Memory => {
+                if logged && let Some(files) = &self.memory_files {
+                    Self::snapshot_memory_file(&mut self.memory_snapshots, files, &entry.path);
+                }
+                let data = Self::memory_data(&mut self.memory_files, &entry.path)?;
+                if (data.len() as u64) < required_size {
+                    data.resize(required_size as usize, 0);
+                }
+                data[offset as usize..required_size as usize].copy_from_slice(buffer);
+                return Ok(());
+            }
+        };
+
+        if logged && let Some(wal) = &mut self.wal {
+            wal.before_page_write(&entry.path, file, page_id)?;
+        }
+
         // Extend file if necessary to ensure we can write at this offset
-        let current_size = entry.file.metadata()?.len();
+        let current_size = file.metadata()?.len();
         if current_size < required_size {
-            entry.file.set_len(required_size)?;
+            file.set_len(required_size)?;
         }
 
-        entry.file.seek(SeekFrom::Start(offset))?;
-        entry.file.write_all(buffer)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(buffer)?;
         // Note: Don't sync on every write - let the OS buffer and batch writes
         // Sync will be called by flush_all() or when buffer manager drops
 
@@ -203,7 +377,12 @@ impl PagedFileManager {
             .get_mut(&handle)
             .ok_or(FileError::InvalidHandle(handle.0))?;
 
-        let file_size = entry.file.metadata()?.len();
+        let file_size = match &entry.storage {
+            FileStorage::Disk(file) => file.metadata()?.len(),
+            FileStorage::Memory => {
+                Self::memory_data(&mut self.memory_files, &entry.path)?.len() as u64
+            }
+        };
         let page_count = file_size.div_ceil(PAGE_SIZE as u64) as usize;
         Ok(page_count)
     }
@@ -215,18 +394,112 @@ impl PagedFileManager {
             .get_mut(&handle)
             .ok_or(FileError::InvalidHandle(handle.0))?;
 
-        entry.file.sync_data()?;
+        if let FileStorage::Disk(file) = &entry.storage {
+            file.sync_data()?;
+        }
         Ok(())
     }
 
     /// Sync all open files to disk
     pub fn sync_all(&mut self) -> FileResult<()> {
         for entry in self.open_files.values_mut() {
-            entry.file.sync_data()?;
+            if let FileStorage::Disk(file) = &entry.storage {
+                file.sync_data()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Start a logged unit whose undo log is kept at `wal_path`
+    ///
+    /// Until the matching [`Self::commit_logged_unit`], every page write,
+    /// file creation and file removal is logged first, so the whole unit can
+    /// be undone by [`Self::rollback_logged_unit`], or by [`Self::recover`]
+    /// if the process dies before it commits. Units nest; only the outermost
+    /// one logs and commits, and `wal_path` of inner units is ignored. Files
+    /// kept in memory are snapshotted in memory instead.
+    pub fn begin_logged_unit<P: AsRef<Path>>(&mut self, wal_path: P) -> FileResult<()> {
+        if self.unit_depth == 0 {
+            if self.is_in_memory() {
+                self.memory_snapshots = Some(HashMap::new());
+            } else {
+                self.wal = Some(WriteAheadLog::begin(wal_path.as_ref())?);
+            }
+        }
+        self.unit_depth += 1;
+        Ok(())
+    }
+
+    /// Finish the innermost logged unit
+    ///
+    /// When the outermost unit ends, the open files it wrote to are synced
+    /// and the log is emptied; pages still cached by a buffer manager must be
+    /// written first.
+    pub fn commit_logged_unit(&mut self) -> FileResult<()> {
+        if self.unit_depth == 0 {
+            return Ok(());
+        }
+        self.unit_depth -= 1;
+        if self.unit_depth == 0 {
+            self.memory_snapshots = None;
+            if let Some(wal) = self.wal.take() {
+                for entry in self.open_files.values() {
+                    if let FileStorage::Disk(file) = &entry.storage
+                        && wal.has_written(&entry.path)
+                    {
+                        file.sync_data()?;
+                    }
+                }
+                wal.commit()?;
+            }
         }
         Ok(())
     }
 
+    /// Undo everything the logged unit in progress wrote, at any nesting depth
+    ///
+    /// Every file is closed first, so all handles become invalid; pages
+    /// cached by a buffer manager must be discarded without being written.
+    pub fn rollback_logged_unit(&mut self) -> FileResult<()> {
+        if self.unit_depth == 0 {
+            return Ok(());
+        }
+        self.unit_depth = 0;
+        self.open_files.clear();
+        self.path_to_handle.clear();
+
+        if let (Some(snapshots), Some(files)) =
+            (self.memory_snapshots.take(), &mut self.memory_files)
+        {
+            for (path, contents) in snapshots {
+                match contents {
+                    Some(contents) => files.insert(path, contents),
+                    None => files.remove(&path),
+                };
+            }
+        }
+        if let Some(wal) = self.wal.take() {
+            wal.rollback()?;
+        }
+        Ok(())
+    }
+
+    /// Nesting depth of the logged unit in progress, 0 outside of one
+    pub fn logged_unit_depth(&self) -> usize {
+        self.unit_depth
+    }
+
+    /// Undo a logged unit that never committed, using the log at `wal_path`
+    ///
+    /// Call before opening any of the unit's files. Returns whether anything
+    /// was rolled back.
+    pub fn recover<P: AsRef<Path>>(&mut self, wal_path: P) -> FileResult<bool> {
+        if self.is_in_memory() {
+            return Ok(false);
+        }
+        WriteAheadLog::recover(wal_path.as_ref())
+    }
+
     /// Check if a file is open
     pub fn is_file_open(&self, handle: FileHandle) -> bool {
         self.open_files.contains_key(&handle)
@@ -236,6 +509,40 @@ impl PagedFileManager {
     pub fn open_file_count(&self) -> usize {
         self.open_files.len()
     }
+
+    /// Normalize a path to the key used in `path_to_handle`, or `None` if
+    /// the file does not exist
+    fn resolve_path(&self, path: &Path) -> Option<PathBuf> {
+        match &self.memory_files {
+            Some(files) => files.contains_key(path).then(|| path.to_path_buf()),
+            None => path.canonicalize().ok(),
+        }
+    }
+
+    /// Remember the contents of an in-memory file before a logged unit
+    /// first changes it
+    fn snapshot_memory_file(
+        memory_snapshots: &mut Option<HashMap<PathBuf, Option<Vec<u8>>>>,
+        files: &HashMap<PathBuf, Vec<u8>>,
+        path: &Path,
+    ) {
+        if let Some(snapshots) = memory_snapshots {
+            snapshots
+                .entry(path.to_path_buf())
+                .or_insert_with(|| files.get(path).cloned());
+        }
+    }
+
+    /// Contents of an in-memory file
+    fn memory_data<'a>(
+        memory_files: &'a mut Option<HashMap<PathBuf, Vec<u8>>>,
+        path: &Path,
+    ) -> FileResult<&'a mut Vec<u8>> {
+        memory_files
+            .as_mut()
+            .and_then(|files| files.get_mut(path))
+            .ok_or_else(|| FileError::FileNotFound(path.display().to_string()))
+    }
 }
 
 impl Default for PagedFileManager {
@@ -463,4 +770,119 @@ mod tests {
         let result = manager.open_file(&file3);
         assert!(matches!(result, Err(FileError::TooManyOpenFiles)));
     }
+
+    #[test]
+    fn test_list_files() {
+        let temp_dir = setup_test_dir();
+        let mut disk = PagedFileManager::new();
+        let mut memory = PagedFileManager::in_memory();
+        std::fs::create_dir(temp_dir.path().join("sub")).unwrap();
+
+        for manager in [&mut disk, &mut memory] {
+            manager.create_file(temp_dir.path().join("b.idx")).unwrap();
+            manager.create_file(temp_dir.path().join("a.tbl")).unwrap();
+            manager
+                .create_file(temp_dir.path().join("sub").join("c.idx"))
+                .unwrap();
+
+            // Nested files are not listed
+            assert_eq!(
+                manager.list_files(temp_dir.path()).unwrap(),
+                vec![temp_dir.path().join("a.tbl"), temp_dir.path().join("b.idx")]
+            );
+        }
+    }
+
+    #[test]
+    fn test_in_memory_files() {
+        let mut manager = PagedFileManager::in_memory();
+        let test_file = Path::new("memdb/test.db");
+
+        manager.create_file(test_file).unwrap();
+        assert!(manager.file_exists(test_file));
+        assert!(!test_file.exists());
+        assert!(matches!(
+            manager.create_file(test_file),
+            Err(FileError::FileAlreadyExists(_))
+        ));
+
+        let handle = manager.open_file(test_file).unwrap();
+        assert_eq!(manager.get_page_count(handle).unwrap(), 0);
+
+        let mut write_buffer = vec![0u8; PAGE_SIZE];
+        write_buffer[0] = 42;
+        write_buffer[PAGE_SIZE - 1] = 255;
+        manager.write_page(handle, 2, &write_buffer).unwrap();
+        assert_eq!(manager.get_page_count(handle).unwrap(), 3);
+
+        // Contents survive closing and reopening the file
+        manager.close_file(handle).unwrap();
+        let handle = manager.open_file(test_file).unwrap();
+        let mut read_buffer = vec![1u8; PAGE_SIZE];
+        manager.read_page(handle, 2, &mut read_buffer).unwrap();
+        assert_eq!(read_buffer, write_buffer);
+        manager.read_page(handle, 7, &mut read_buffer).unwrap();
+        assert!(read_buffer.iter().all(|&b| b == 0));
+
+        manager.remove_dir_all("memdb").unwrap();
+        assert!(!manager.file_exists(test_file));
+        assert!(!manager.is_file_open(handle));
+        assert!(matches!(
+            manager.open_file(test_file),
+            Err(FileError::FileNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_recover_undoes_uncommitted_unit() {
+        let temp_dir = setup_test_dir();
+        let test_file = temp_dir.path().join("test.db");
+        let wal_path = temp_dir.path().join("wal");
+        let mut manager = PagedFileManager::new();
+
+        manager.create_file(&test_file).unwrap();
+        let handle = manager.open_file(&test_file).unwrap();
+        manager
+            .write_page(handle, 0, &vec![1u8; PAGE_SIZE])
+            .unwrap();
+        manager
+            .write_page(handle, 1, &vec![2u8; PAGE_SIZE])
+            .unwrap();
+
+        // A committed unit leaves nothing to undo
+        manager.begin_logged_unit(&wal_path).unwrap();
+        manager
+            .write_page(handle, 1, &vec![3u8; PAGE_SIZE])
+            .unwrap();
+        manager.commit_logged_unit().unwrap();
+        assert_eq!(manager.logged_unit_depth(), 0);
+        assert!(!manager.recover(&wal_path).unwrap());
+
+        manager.begin_logged_unit(&wal_path).unwrap();
+        manager.begin_logged_unit(&wal_path).unwrap();
+        manager
+            .write_page(handle, 0, &vec![4u8; PAGE_SIZE])
+            .unwrap();
+        manager
+            .write_page(handle, 0, &vec![5u8; PAGE_SIZE])
+            .unwrap();
+        manager
+            .write_page(handle, 4, &vec![6u8; PAGE_SIZE])
+            .unwrap();
+        // Ending the inner unit does not commit the outer one
+        manager.commit_logged_unit().unwrap();
+        assert_eq!(manager.logged_unit_depth(), 1);
+        drop(manager);
+
+        let mut manager = PagedFileManager::new();
+        assert!(manager.recover(&wal_path).unwrap());
+        let handle = manager.open_file(&test_file).unwrap();
+        assert_eq!(manager.get_page_count(handle).unwrap(), 2);
+        let mut buffer = vec![0u8; PAGE_SIZE];
+        manager.read_page(handle, 0, &mut buffer).unwrap();
+        assert!(buffer.iter().all(|&b| b == 1));
+        manager.read_page(handle, 1, &mut buffer).unwrap();
+        assert!(buffer.iter().all(|&b| b == 3));
+        assert!(!manager.recover(&wal_path).unwrap());
+    }
 }
@@ -1,14 +1,23 @@
 mod buffer_manager;
+mod checksum;
 mod error;
 mod file_manager;
+mod wal;
 
-pub use buffer_manager::BufferManager;
+pub use buffer_manager::{BufferManager, BufferStats, EvictionPolicy};
+pub use checksum::{PAGE_VERSION_CHECKSUM, PAGE_VERSION_LEGACY, crc32};
 pub use error::{FileError, FileResult};
 pub use file_manager::{FileHandle, PagedFileManager};
 
 /// Page size in bytes (8KB)
 pub const PAGE_SIZE: usize = 8192;
 
+/// Offset of the format version byte the buffer manager stamps on every page it writes
+pub const PAGE_VERSION_OFFSET: usize = 10;
+
+/// Offset of the page's CRC32, computed with these four bytes zeroed
+pub const PAGE_CHECKSUM_OFFSET: usize = 12;
+
 /// Number of pages in the buffer pool
 /// With 3000 pages × 8KB = 24MB, leaving room for other data structures
 /// in systems with 256MB memory limit
@@ -0,0 +1,368 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::error::FileResult;
+use super::{PAGE_SIZE, PageId};
+
+const TAG_LENGTH: u8 = 1;
+const TAG_PAGE: u8 = 2;
+const TAG_CREATED: u8 = 3;
+const TAG_REMOVED: u8 = 4;
+
+/// Suffix of the copy a removed file is kept under until its unit commits
+const BACKUP_SUFFIX: &str = ".walbak";
+
+/// One undo record; rolling back applies them newest first
+#[derive(Debug, PartialEq)]
+enum WalRecord {
+    /// Length of a file when the unit first wrote to it
+    Length { path: PathBuf, len: u64 },
+    /// Contents of a page before the unit first overwrote it
+    Page {
+        path: PathBuf,
+        page_id: PageId,
+        image: Vec<u8>,
+    },
+    /// File the unit created
+    Created { path: PathBuf },
+    /// File the unit removed, kept under `backup` until commit
+    Removed { path: PathBuf, backup: PathBuf },
+}
+
+impl WalRecord {
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        match self {
+            WalRecord::Length { path, len } => {
+                buf.push(TAG_LENGTH);
+                encode_path(&mut buf, path);
+                buf.extend_from_slice(&len.to_le_bytes());
+            }
+            WalRecord::Page {
+                path,
+                page_id,
+                image,
+            } => {
+                buf.push(TAG_PAGE);
+                encode_path(&mut buf, path);
+                buf.extend_from_slice(&(*page_id as u64).to_le_bytes());
+                buf.extend_from_slice(image);
+            }
+            WalRecord::Created { path } => {
+                buf.push(TAG_CREATED);
+                encode_path(&mut buf, path);
+            }
+            WalRecord::Removed { path, backup } => {
+                buf.push(TAG_REMOVED);
+                encode_path(&mut buf, path);
+                encode_path(&mut buf, backup);
+            }
+        }
+        buf
+    }
+
+    /// Decode the record at the start of `bytes`, returning it and its size,
+    /// or `None` if the bytes end mid-record
+    fn decode(bytes: &[u8]) -> Option<(WalRecord, usize)> {
+        let mut reader = RecordReader { bytes, pos: 1 };
+        let record = match *bytes.first()? {
+            TAG_LENGTH => WalRecord::Length {
+                path: reader.path()?,
+                len: reader.u64()?,
+            },
+            TAG_PAGE => WalRecord::Page {
+                path: reader.path()?,
+                page_id: reader.u64()? as PageId,
+                image: reader.take(PAGE_SIZE)?.to_vec(),
+            },
+            TAG_CREATED => WalRecord::Created {
+                path: reader.path()?,
+            },
+            TAG_REMOVED => WalRecord::Removed {
+                path: reader.path()?,
+                backup: reader.path()?,
+            },
+            _ => return None,
+        };
+        Some((record, reader.pos))
+    }
+
+    /// Undo the change this record describes
+    fn undo(&self) -> FileResult<()> {
+        match self {
+            WalRecord::Length { path, len } => {
+                if path.exists() {
+                    let file = OpenOptions::new().write(true).open(path)?;
+                    file.set_len(*len)?;
+                    file.sync_data()?;
+                }
+            }
+            WalRecord::Page {
+                path,
+                page_id,
+                image,
+            } => {
+                if path.exists() {
+                    let mut file = OpenOptions::new().write(true).open(path)?;
+                    file.seek(SeekFrom::Start((page_id * PAGE_SIZE) as u64))?;
+                    file.write_all(image)?;
+                    file.sync_data()?;
+                }
+            }
+            WalRecord::Created { path } => {
+                if path.exists() {
+                    fs::remove_file(path)?;
+                }
+            }
+            WalRecord::Removed { path, backup } => {
+                if backup.exists() {
+                    fs::rename(backup, path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn encode_path(buf: &mut Vec<u8>, path: &Path) {
+    let path = path.to_string_lossy();
+    buf.extend_from_slice(&(path.len() as u32).to_le_bytes());
+    buf.extend_from_slice(path.as_bytes());
+}
+
+struct RecordReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl RecordReader<'_> {
+    fn take(&mut self, len: usize) -> Option<&[u8]> {
+        let slice = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(slice)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+
+    fn path(&mut self) -> Option<PathBuf> {
+        let len = u32::from_le_bytes(self.take(4)?.try_into().ok()?) as usize;
+        let path = std::str::from_utf8(self.take(len)?).ok()?;
+        Some(PathBuf::from(path))
+    }
+}
+
+/// Undo log of the logged unit in progress
+///
+/// Before a page that existed when the unit began is first overwritten on
+/// disk, its old contents are appended to the log and synced; so are the
+/// original length of every file written to and the files created or
+/// removed. Removed files are renamed aside rather than deleted. Committing
+/// empties the log, so a non-empty log found at startup belongs to a unit
+/// that never finished, and `recover` undoes it record by record.
+pub(super) struct WriteAheadLog {
+    file: File,
+    path: PathBuf,
+    /// Length of each file the unit has written to when it began, `None`
+    /// for files the unit created itself
+    original_lengths: HashMap<PathBuf, Option<u64>>,
+    /// Pages whose before-image is already in the log
+    logged_pages: HashSet<(PathBuf, PageId)>,
+    /// Copies of removed files, deleted once the unit commits
+    backups: Vec<PathBuf>,
+}
+
+impl WriteAheadLog {
+    /// Open the log at `path` for a new unit, discarding any old contents
+    pub(super) fn begin(path: &Path) -> FileResult<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            original_lengths: HashMap::new(),
+            logged_pages: HashSet::new(),
+            backups: Vec::new(),
+        })
+    }
+
+    fn append(&mut self, record: &WalRecord) -> FileResult<()> {
+        self.file.write_all(&record.encode())?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    /// Log what is needed to undo overwriting `page_id` of `file` at `path`
+    pub(super) fn before_page_write(
+        &mut self,
+        path: &Path,
+        file: &mut File,
+        page_id: PageId,
+    ) -> FileResult<()> {
+        let original_len = match self.original_lengths.get(path) {
+            Some(len) => *len,
+            None => {
+                let len = file.metadata()?.len();
+                self.append(&WalRecord::Length {
+                    path: path.to_path_buf(),
+                    len,
+                })?;
+                self.original_lengths.insert(path.to_path_buf(), Some(len));
+                Some(len)
+            }
+        };
+
+        // Pages past the original end are cut off again by the length record
+        let Some(original_len) = original_len else {
+            return Ok(());
+        };
+        if (page_id * PAGE_SIZE) as u64 >= original_len
+            || !self.logged_pages.insert((path.to_path_buf(), page_id))
+        {
+            return Ok(());
+        }
+
+        let mut image = vec![0u8; PAGE_SIZE];
+        file.seek(SeekFrom::Start((page_id * PAGE_SIZE) as u64))?;
+        let mut filled = 0;
+        while filled < PAGE_SIZE {
+            let read = file.read(&mut image[filled..])?;
+            if read == 0 {
+                break;
+            }
+            filled += read;
+        }
+        self.append(&WalRecord::Page {
+            path: path.to_path_buf(),
+            page_id,
+            image,
+        })
+    }
+
+    /// Whether the unit has written to or created the file at `path`
+    pub(super) fn has_written(&self, path: &Path) -> bool {
+        self.original_lengths.contains_key(path)
+    }
+
+    /// Log that the unit created the file at `path`
+    pub(super) fn created(&mut self, path: &Path) -> FileResult<()> {
+        self.append(&WalRecord::Created {
+            path: path.to_path_buf(),
+        })?;
+        self.original_lengths.insert(path.to_path_buf(), None);
+        Ok(())
+    }
+
+    /// Remove the file at `path`, keeping a copy unless the unit created it
+    pub(super) fn remove(&mut self, path: &Path) -> FileResult<()> {
+        if let Some(None) = self.original_lengths.get(path) {
+            fs::remove_file(path)?;
+            return Ok(());
+        }
+
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(BACKUP_SUFFIX);
+        let backup = PathBuf::from(backup);
+        self.append(&WalRecord::Removed {
+            path: path.to_path_buf(),
+            backup: backup.clone(),
+        })?;
+        fs::rename(path, &backup)?;
+        self.backups.push(backup);
+        self.original_lengths.remove(path);
+        self.logged_pages.retain(|(logged, _)| logged != path);
+        Ok(())
+    }
+
+    /// Make the unit permanent; every page it wrote must already be synced
+    pub(super) fn commit(mut self) -> FileResult<()> {
+        self.file.set_len(0)?;
+        self.file.sync_data()?;
+        for backup in self.backups.drain(..) {
+            let _ = fs::remove_file(backup);
+        }
+        Ok(())
+    }
+
+    /// Undo the unit while the process is still running; the files it
+    /// touched must be closed
+    pub(super) fn rollback(self) -> FileResult<()> {
+        let Self { file, path, .. } = self;
+        drop(file);
+        Self::recover(&path)?;
+        Ok(())
+    }
+
+    /// Undo the unit left in the log at `path`, if any, and empty the log
+    ///
+    /// Returns whether there was anything to undo. A record cut short by
+    /// the crash is ignored: its change was never made, since a record is
+    /// synced before the change it describes.
+    pub(super) fn recover(path: &Path) -> FileResult<bool> {
+        if !path.exists() {
+            return Ok(false);
+        }
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+
+        let mut records = Vec::new();
+        let mut pos = 0;
+        while let Some((record, len)) = WalRecord::decode(&bytes[pos..]) {
+            records.push(record);
+            pos += len;
+        }
+        for record in records.iter().rev() {
+            record.undo()?;
+        }
+
+        let log = OpenOptions::new().write(true).open(path)?;
+        log.set_len(0)?;
+        log.sync_data()?;
+        Ok(!records.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_round_trip_and_torn_tail() {
+        let records = [
+            WalRecord::Length {
+                path: PathBuf::from("/db/t.tbl"),
+                len: 3 * PAGE_SIZE as u64,
+            },
+            WalRecord::Page {
+                path: PathBuf::from("/db/t.tbl"),
+                page_id: 2,
+                image: vec![7u8; PAGE_SIZE],
+            },
+            WalRecord::Removed {
+                path: PathBuf::from("/db/t_id.idx"),
+                backup: PathBuf::from("/db/t_id.idx.walbak"),
+            },
+        ];
+        let mut bytes: Vec<u8> = records.iter().flat_map(|r| r.encode()).collect();
+        // A record cut short by a crash is dropped
+        let torn = WalRecord::Created {
+            path: PathBuf::from("/db/new.idx"),
+        }
+        .encode();
+        bytes.extend_from_slice(&torn[..torn.len() - 1]);
+
+        let mut decoded = Vec::new();
+        let mut pos = 0;
+        while let Some((record, len)) = WalRecord::decode(&bytes[pos..]) {
+            decoded.push(record);
+            pos += len;
+        }
+        assert_eq!(decoded, records);
+    }
+}
@@ -1,5 +1,6 @@
 //! Index file management
 
+use crate::btree::{BPlusTreeRangeIter, BPlusTreeRevIter};
 use crate::file::BufferManager;
 use crate::record::RecordId;
 
@@ -28,7 +29,7 @@ impl IndexFile {
         let file_path = Self::index_file_path(db_path, table_name, column_name);
 
         // Check if file already exists
-        if std::path::Path::new(&file_path).exists() {
+        if buffer_mgr.file_manager().file_exists(&file_path) {
             return Err(IndexError::IndexAlreadyExists(file_path));
         }
 
@@ -51,7 +52,7 @@ impl IndexFile {
         let file_path = Self::index_file_path(db_path, table_name, column_name);
 
         // Check if file exists
-        if !std::path::Path::new(&file_path).exists() {
+        if !buffer_mgr.file_manager().file_exists(&file_path) {
             return Err(IndexError::IndexNotFound(file_path));
         }
 
@@ -69,8 +70,8 @@ impl IndexFile {
         self.btree.close(buffer_mgr)
     }
 
-    /// Flush changes to disk
-    pub fn flush(&mut self, buffer_mgr: &mut BufferManager) -> IndexResult<()> {
+    /// Flush changes to disk, returning how many pages were written
+    pub fn flush(&mut self, buffer_mgr: &mut BufferManager) -> IndexResult<usize> {
         self.btree.flush(buffer_mgr)
     }
 
@@ -119,6 +120,15 @@ impl IndexFile {
         self.btree.bulk_load(entries)
     }
 
+    /// Bulk load with leaves filled only up to `fill_factor` percent, leaving
+    /// room for inserts that arrive after the index is built
+    pub fn bulk_load_with_fill_factor<I>(&mut self, entries: I, fill_factor: u8) -> IndexResult<()>
+    where
+        I: Iterator<Item = (i64, RecordId)>,
+    {
+        self.btree.bulk_load_with_fill_factor(entries, fill_factor)
+    }
+
     /// Delete all entries with the given key
     /// Returns whether any entries were deleted
     pub fn delete(&mut self, key: i64) -> IndexResult<bool> {
@@ -130,6 +140,11 @@ impl IndexFile {
         self.btree.delete_entry(key, rid)
     }
 
+    /// Delete many key-value pairs, returning how many were found
+    pub fn bulk_delete(&mut self, entries: &[(i64, RecordId)]) -> IndexResult<usize> {
+        self.btree.bulk_delete(entries)
+    }
+
     /// Search for a key (returns first match)
     pub fn search(&self, key: i64) -> Option<RecordId> {
         self.btree.search(key)
@@ -145,6 +160,26 @@ impl IndexFile {
         self.btree.range_search(lower, upper)
     }
 
+    /// Lazy range search [lower, upper]
+    pub fn range_iter(&self, lower: i64, upper: i64) -> BPlusTreeRangeIter<'_> {
+        self.btree.range_iter(lower, upper)
+    }
+
+    /// All entries in descending key order
+    pub fn rev_iter(&self) -> BPlusTreeRevIter<'_> {
+        self.btree.rev_iter()
+    }
+
+    /// Smallest indexed key
+    pub fn min_key(&self) -> Option<i64> {
+        self.btree.min_key()
+    }
+
+    /// Largest indexed key
+    pub fn max_key(&self) -> Option<i64> {
+        self.btree.max_key()
+    }
+
     /// Update a specific entry
     pub fn update(
         &mut self,
@@ -166,6 +201,11 @@ impl IndexFile {
         self.btree.is_empty()
     }
 
+    /// Get the B+ tree order
+    pub fn order(&self) -> usize {
+        self.btree.order()
+    }
+
     /// Get table name
     pub fn table_name(&self) -> &str {
         &self.table_name
@@ -15,24 +15,25 @@ use std::collections::{BinaryHeap, HashMap};
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, RwLock};
 
-use crate::btree::DEFAULT_ORDER;
+use crate::btree::{BPlusTreeError, DEFAULT_FILL_FACTOR, DEFAULT_ORDER};
 use crate::file::BufferManager;
 use crate::record::RecordId;
 
 /// High-level index manager
 pub struct IndexManager {
     /// Buffer manager
-    buffer_manager: Arc<Mutex<BufferManager>>,
+    buffer_manager: Arc<RwLock<BufferManager>>,
 
-    /// Open indexes: (table_name, column_name) -> IndexFile
+    /// Open indexes: (table_name, storage_name) -> IndexFile. A handle stays
+    /// cached across statements until it is closed or dropped.
     open_indexes: HashMap<(String, String), IndexFile>,
 }
 
 impl IndexManager {
     /// Create a new index manager
-    pub fn new(buffer_manager: Arc<Mutex<BufferManager>>) -> Self {
+    pub fn new(buffer_manager: Arc<RwLock<BufferManager>>) -> Self {
         Self {
             buffer_manager,
             open_indexes: HashMap::new(),
@@ -47,7 +48,7 @@ impl IndexManager {
         column_name: &str,
     ) -> IndexResult<()> {
         // Use default order optimized for 8KB pages
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
         let index_file = IndexFile::create(
             &mut buffer_manager,
             db_path,
@@ -116,6 +117,53 @@ impl IndexManager {
         column_name: &str,
         table_data: I,
     ) -> IndexResult<()>
+    where
+        I: Iterator<Item = IndexResult<(RecordId, i64)>>,
+    {
+        self.create_index_from_table_with_order(
+            db_path,
+            table_name,
+            column_name,
+            DEFAULT_ORDER,
+            table_data,
+        )
+    }
+
+    /// Like [`create_index_from_table`](Self::create_index_from_table), but
+    /// builds the B+ tree with the given order instead of the default one.
+    pub fn create_index_from_table_with_order<I>(
+        &mut self,
+        db_path: &str,
+        table_name: &str,
+        column_name: &str,
+        order: usize,
+        table_data: I,
+    ) -> IndexResult<()>
+    where
+        I: Iterator<Item = IndexResult<(RecordId, i64)>>,
+    {
+        self.create_index_from_table_with_options(
+            db_path,
+            table_name,
+            column_name,
+            order,
+            DEFAULT_FILL_FACTOR,
+            table_data,
+        )
+    }
+
+    /// Like [`create_index_from_table_with_order`](Self::create_index_from_table_with_order),
+    /// but fills leaves only up to `fill_factor` percent. Use a factor below
+    /// 100 for tables that expect many inserts after the index is built.
+    pub fn create_index_from_table_with_options<I>(
+        &mut self,
+        db_path: &str,
+        table_name: &str,
+        column_name: &str,
+        order: usize,
+        fill_factor: u8,
+        table_data: I,
+    ) -> IndexResult<()>
     where
         I: Iterator<Item = IndexResult<(RecordId, i64)>>,
     {
@@ -123,15 +171,34 @@ impl IndexManager {
         use std::io::{BufWriter, Write};
         use std::path::PathBuf;
 
+        // Reject a bad fill factor before any file is created
+        if !(1..=100).contains(&fill_factor) {
+            return Err(BPlusTreeError::InvalidFillFactor(fill_factor).into());
+        }
+
         // Memory limit for external sort: keep well under the 256MB cap.
         // Use actual in-memory entry size to avoid underestimating usage.
         const MEMORY_LIMIT_BYTES: usize = 64 * 1024 * 1024;
         const ENTRY_SIZE: usize = std::mem::size_of::<(i64, RecordId)>();
-        let chunk_size = (MEMORY_LIMIT_BYTES / ENTRY_SIZE).max(1);
+        // Without a backing filesystem there is nowhere to spill chunks to,
+        // so sort everything in a single in-memory chunk
+        let in_memory = self
+            .buffer_manager
+            .read()
+            .unwrap()
+            .file_manager()
+            .is_in_memory();
+        let chunk_size = if in_memory {
+            usize::MAX
+        } else {
+            (MEMORY_LIMIT_BYTES / ENTRY_SIZE).max(1)
+        };
 
         // Temporary directory for sorted chunks
         let temp_dir = PathBuf::from(db_path).join(".tmp_index_sort");
-        std::fs::create_dir_all(&temp_dir).map_err(|e| IndexError::IoError(e.to_string()))?;
+        if !in_memory {
+            std::fs::create_dir_all(&temp_dir).map_err(|e| IndexError::IoError(e.to_string()))?;
+        }
 
         // Phase 1: Split into sorted chunks
         let mut chunk_files = Vec::new();
@@ -185,7 +252,7 @@ impl IndexManager {
         }
 
         // Handle remaining entries
-        if !current_chunk.is_empty() {
+        if !in_memory && !current_chunk.is_empty() {
             current_chunk.sort_unstable_by_key(|e| e.0);
 
             let chunk_path = temp_dir.join(format!("chunk_{}.dat", chunk_files.len()));
@@ -218,14 +285,9 @@ impl IndexManager {
 
         if total_entries == 0 {
             // No entries, create empty index
-            let mut buffer_manager = self.buffer_manager.lock().unwrap();
-            let index_file = IndexFile::create(
-                &mut buffer_manager,
-                db_path,
-                table_name,
-                column_name,
-                DEFAULT_ORDER,
-            )?;
+            let mut buffer_manager = self.buffer_manager.write().unwrap();
+            let index_file =
+                IndexFile::create(&mut buffer_manager, db_path, table_name, column_name, order)?;
             drop(buffer_manager);
 
             self.open_indexes.insert(
@@ -234,33 +296,33 @@ impl IndexManager {
             );
 
             // Clean up temp directory
-            let _ = std::fs::remove_dir_all(&temp_dir);
+            if !in_memory {
+                let _ = std::fs::remove_dir_all(&temp_dir);
+            }
             return Ok(());
         }
 
         // Phase 2: K-way merge of sorted chunks
-        eprintln!("  Merging {} sorted chunks...", chunk_files.len());
-
-        let merged_iter = if chunk_files.len() == 1 {
+        let merged_iter: Box<dyn Iterator<Item = (i64, RecordId)>> = if in_memory {
+            current_chunk.sort_unstable_by_key(|e| e.0);
+            Box::new(current_chunk.into_iter())
+        } else if chunk_files.len() == 1 {
+            eprintln!("  Merging {} sorted chunks...", chunk_files.len());
             // Only one chunk, read it directly
             read_sorted_chunk(&chunk_files[0])?
         } else {
+            eprintln!("  Merging {} sorted chunks...", chunk_files.len());
             // Multiple chunks, perform k-way merge
             k_way_merge(chunk_files)?
         };
 
         // Phase 3: Build B+ tree from sorted data
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
-        let mut index_file = IndexFile::create(
-            &mut buffer_manager,
-            db_path,
-            table_name,
-            column_name,
-            DEFAULT_ORDER,
-        )?;
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
+        let mut index_file =
+            IndexFile::create(&mut buffer_manager, db_path, table_name, column_name, order)?;
 
         // Bulk load from the merged iterator
-        index_file.bulk_load_from_iter(merged_iter)?;
+        index_file.bulk_load_with_fill_factor(merged_iter, fill_factor)?;
 
         // Flush to disk
         index_file.flush(&mut buffer_manager)?;
@@ -273,7 +335,9 @@ impl IndexManager {
         );
 
         // Clean up temporary files
-        let _ = std::fs::remove_dir_all(&temp_dir);
+        if !in_memory {
+            let _ = std::fs::remove_dir_all(&temp_dir);
+        }
 
         eprintln!(
             "✓ Created index on {}.{} with {} entries",
@@ -293,19 +357,23 @@ impl IndexManager {
         // Close the index if open
         let key = (table_name.to_string(), column_name.to_string());
         if let Some(index_file) = self.open_indexes.remove(&key) {
-            let mut buffer_manager = self.buffer_manager.lock().unwrap();
+            let mut buffer_manager = self.buffer_manager.write().unwrap();
             index_file.close(&mut buffer_manager)?;
         }
 
         // Delete the file
         let file_path = format!("{}/{}_{}.idx", db_path, table_name, column_name);
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
+        if !buffer_manager.file_manager().file_exists(&file_path) {
+            return Err(IndexError::IndexNotFound(file_path));
+        }
         buffer_manager.file_manager_mut().remove_file(&file_path)?;
 
         Ok(())
     }
 
-    /// Open an index
+    /// Open an index. Reopening an index that is already open reuses the
+    /// cached handle without touching the file.
     pub fn open_index(
         &mut self,
         db_path: &str,
@@ -319,7 +387,7 @@ impl IndexManager {
             return Ok(());
         }
 
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
         let index_file = IndexFile::open(&mut buffer_manager, db_path, table_name, column_name)?;
         drop(buffer_manager);
 
@@ -334,10 +402,10 @@ impl IndexManager {
 
         if let Some(mut index_file) = self.open_indexes.remove(&key) {
             // Flush before closing to ensure all changes are persisted
-            let mut buffer_manager = self.buffer_manager.lock().unwrap();
+            let mut buffer_manager = self.buffer_manager.write().unwrap();
             index_file.flush(&mut buffer_manager)?;
             drop(buffer_manager);
-            let mut buffer_manager = self.buffer_manager.lock().unwrap();
+            let mut buffer_manager = self.buffer_manager.write().unwrap();
             index_file.close(&mut buffer_manager)?;
         }
 
@@ -355,27 +423,39 @@ impl IndexManager {
         Ok(())
     }
 
+    /// Forget every open index without writing its in-memory changes, e.g.
+    /// when a transaction is rolled back and the files are restored anyway
+    pub fn discard_all(&mut self) {
+        self.open_indexes.clear();
+    }
+
+    /// Whether an index currently has a cached open handle
+    pub fn is_open(&self, table_name: &str, column_name: &str) -> bool {
+        let key = (table_name.to_string(), column_name.to_string());
+        self.open_indexes.contains_key(&key)
+    }
+
     /// Flush an index to disk
     pub fn flush_index(&mut self, table_name: &str, column_name: &str) -> IndexResult<()> {
         let key = (table_name.to_string(), column_name.to_string());
 
-        if let Some(mut index_file) = self.open_indexes.remove(&key) {
-            // Flush before closing to ensure all changes are persisted
-            let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        if let Some(index_file) = self.open_indexes.get_mut(&key) {
+            let mut buffer_manager = self.buffer_manager.write().unwrap();
             index_file.flush(&mut buffer_manager)?;
         }
 
         Ok(())
     }
 
-    /// Flush all indexes to disk
-    pub fn flush_all(&mut self) -> IndexResult<()> {
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+    /// Flush all indexes to disk, returning how many pages were written
+    pub fn flush_all(&mut self) -> IndexResult<usize> {
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
+        let mut flushed = 0;
         for index_file in self.open_indexes.values_mut() {
-            index_file.flush(&mut buffer_manager)?;
+            flushed += index_file.flush(&mut buffer_manager)?;
         }
 
-        Ok(())
+        Ok(flushed)
     }
 
     /// Get a reference to an open index
@@ -439,6 +519,23 @@ impl IndexManager {
         index_file.delete_entry(key, rid)
     }
 
+    /// Delete many entries from an index in one pass
+    pub fn bulk_delete(
+        &mut self,
+        table_name: &str,
+        column_name: &str,
+        entries: &[(i64, RecordId)],
+    ) -> IndexResult<usize> {
+        let index_key = (table_name.to_string(), column_name.to_string());
+
+        let index_file = self
+            .open_indexes
+            .get_mut(&index_key)
+            .ok_or_else(|| IndexError::IndexNotOpen(format!("{}_{}", table_name, column_name)))?;
+
+        index_file.bulk_delete(entries)
+    }
+
     /// Search index
     pub fn search(&self, table_name: &str, column_name: &str, key: i64) -> Option<RecordId> {
         let index_key = (table_name.to_string(), column_name.to_string());
@@ -471,6 +568,50 @@ impl IndexManager {
             .unwrap_or_default()
     }
 
+    /// Range search index without collecting the entries; empty if the index is not open
+    pub fn range_iter(
+        &self,
+        table_name: &str,
+        column_name: &str,
+        lower: i64,
+        upper: i64,
+    ) -> impl Iterator<Item = (i64, RecordId)> {
+        let index_key = (table_name.to_string(), column_name.to_string());
+        self.open_indexes
+            .get(&index_key)
+            .into_iter()
+            .flat_map(move |index| index.range_iter(lower, upper))
+    }
+
+    /// All entries of an index in descending key order; empty if the index is not open
+    pub fn rev_iter(
+        &self,
+        table_name: &str,
+        column_name: &str,
+    ) -> impl Iterator<Item = (i64, RecordId)> {
+        let index_key = (table_name.to_string(), column_name.to_string());
+        self.open_indexes
+            .get(&index_key)
+            .into_iter()
+            .flat_map(|index| index.rev_iter())
+    }
+
+    /// Smallest key in an index
+    pub fn min_key(&self, table_name: &str, column_name: &str) -> Option<i64> {
+        let index_key = (table_name.to_string(), column_name.to_string());
+        self.open_indexes
+            .get(&index_key)
+            .and_then(|index| index.min_key())
+    }
+
+    /// Largest key in an index
+    pub fn max_key(&self, table_name: &str, column_name: &str) -> Option<i64> {
+        let index_key = (table_name.to_string(), column_name.to_string());
+        self.open_indexes
+            .get(&index_key)
+            .and_then(|index| index.max_key())
+    }
+
     /// Update entry in index
     pub fn update(
         &mut self,
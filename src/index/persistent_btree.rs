@@ -2,7 +2,10 @@
 
 use std::collections::HashSet;
 
-use crate::btree::{BPlusNode, BPlusTree, NodeId};
+use crate::btree::{
+    BPlusNode, BPlusTree, BPlusTreeError, BPlusTreeRangeIter, BPlusTreeRevIter,
+    DEFAULT_FILL_FACTOR, NodeId,
+};
 use crate::file::{BufferManager, FileHandle};
 use crate::record::RecordId;
 
@@ -75,16 +78,34 @@ impl PersistentBPlusTree {
 
         if let Some(root_id) = metadata.root_node_id {
             // Load all nodes recursively
-            Self::load_tree_nodes(buffer_mgr, file_handle, root_id, &mut nodes)?;
+            let page_count = buffer_mgr.file_manager_mut().get_page_count(file_handle)?;
+            Self::load_tree_nodes(
+                buffer_mgr,
+                file_handle,
+                metadata.order,
+                page_count,
+                root_id,
+                &mut nodes,
+            )?;
+
+            let first_leaf = metadata.first_leaf_id.unwrap_or(usize::MAX);
+            if !matches!(nodes.get(first_leaf), Some(Some(BPlusNode::Leaf(_)))) {
+                return Err(IndexError::CorruptedNode(first_leaf.wrapping_add(1)));
+            }
         }
 
+        // A leaf link to a missing or non-leaf node is corruption too
         let tree = BPlusTree::from_persistent_state(
             metadata.order,
             metadata.root_node_id,
             metadata.first_leaf_id,
             metadata.entry_count,
             nodes,
-        )?;
+        )
+        .map_err(|err| match err {
+            BPlusTreeError::NodeNotFound(id) => IndexError::CorruptedNode(id.wrapping_add(1)),
+            err => IndexError::BPlusTreeError(err),
+        })?;
 
         Ok(Self {
             tree,
@@ -95,40 +116,78 @@ impl PersistentBPlusTree {
     }
 
     /// Load all tree nodes recursively from disk
+    ///
+    /// A node that cannot be decoded, breaks a node invariant or is reached
+    /// twice (a cycle or a shared child) fails with `CorruptedNode`, so the
+    /// caller can rebuild the index instead of serving a broken tree.
     fn load_tree_nodes(
         buffer_mgr: &mut BufferManager,
         file_handle: FileHandle,
+        order: usize,
+        page_count: usize,
         node_id: NodeId,
         nodes: &mut Vec<Option<BPlusNode>>,
     ) -> IndexResult<()> {
+        // Node is stored at page (node_id + 1) because page 0 is metadata
+        let page_id = node_id + 1;
+        if page_id >= page_count {
+            return Err(IndexError::CorruptedNode(page_id));
+        }
+
         // Ensure nodes vec is large enough
         while nodes.len() <= node_id {
             nodes.push(None);
         }
+        if nodes[node_id].is_some() {
+            return Err(IndexError::CorruptedNode(page_id));
+        }
 
-        // Node is stored at page (node_id + 1) because page 0 is metadata
-        let page_id = node_id + 1;
         let page_bytes = buffer_mgr.get_page(file_handle, page_id)?;
-        let node = deserialize_node(page_bytes)?;
-
-        // If internal node, recursively load children
-        if let BPlusNode::Internal(ref internal) = node {
-            for &child_id in &internal.children {
-                if child_id < nodes.len() && nodes[child_id].is_some() {
-                    continue; // Already loaded
-                }
-                Self::load_tree_nodes(buffer_mgr, file_handle, child_id, nodes)?;
+        let node = match deserialize_node(page_bytes) {
+            Ok(node) if Self::node_is_valid(&node, order) => node,
+            Ok(_) | Err(IndexError::InvalidNodeType(_) | IndexError::DeserializationError(_)) => {
+                return Err(IndexError::CorruptedNode(page_id));
             }
-        }
+            Err(err) => return Err(err),
+        };
 
+        // Claim the node before visiting its children, so a cycle back to it is caught
+        let children = match &node {
+            BPlusNode::Internal(internal) => internal.children.clone(),
+            BPlusNode::Leaf(_) => Vec::new(),
+        };
         nodes[node_id] = Some(node);
+
+        for child_id in children {
+            Self::load_tree_nodes(buffer_mgr, file_handle, order, page_count, child_id, nodes)?;
+        }
         Ok(())
     }
 
+    /// Check the invariants of a node read back from disk: keys in
+    /// ascending order, one child or value per key, and no more entries
+    /// than the tree's order allows
+    fn node_is_valid(node: &BPlusNode, order: usize) -> bool {
+        let sorted = |keys: &[i64]| keys.windows(2).all(|pair| pair[0] <= pair[1]);
+        match node {
+            BPlusNode::Internal(internal) => {
+                !internal.children.is_empty()
+                    && internal.children.len() <= order
+                    && internal.keys.len() == internal.children.len()
+                    && sorted(&internal.keys)
+            }
+            BPlusNode::Leaf(leaf) => {
+                leaf.keys.len() < order
+                    && leaf.keys.len() == leaf.values.len()
+                    && sorted(&leaf.keys)
+            }
+        }
+    }
+
     // Reconstructing via re-insertion is avoided to reduce memory overhead.
 
     /// Flush all dirty pages to disk
-    pub fn flush(&mut self, buffer_mgr: &mut BufferManager) -> IndexResult<()> {
+    pub fn flush(&mut self, buffer_mgr: &mut BufferManager) -> IndexResult<usize> {
         // Write metadata if dirty
         if self.metadata_dirty {
             let metadata = BPlusTreeMetadata {
@@ -159,9 +218,7 @@ impl PersistentBPlusTree {
         self.dirty_pages.clear();
 
         // Ensure buffer manager flushes to disk
-        buffer_mgr.flush_all()?;
-
-        Ok(())
+        Ok(buffer_mgr.flush_all()?)
     }
 
     /// Close the index file (flushes automatically)
@@ -198,11 +255,19 @@ impl PersistentBPlusTree {
     /// * `Ok(())` - Index built successfully
     /// * `Err(...)` - If entries are not sorted or an error occurs
     pub fn bulk_load<I>(&mut self, entries: I) -> IndexResult<()>
+    where
+        I: Iterator<Item = (i64, RecordId)>,
+    {
+        self.bulk_load_with_fill_factor(entries, DEFAULT_FILL_FACTOR)
+    }
+
+    /// Bulk load with leaves filled only up to `fill_factor` percent
+    pub fn bulk_load_with_fill_factor<I>(&mut self, entries: I, fill_factor: u8) -> IndexResult<()>
     where
         I: Iterator<Item = (i64, RecordId)>,
     {
         // Perform bulk load on the tree
-        self.tree.bulk_load(entries)?;
+        self.tree.bulk_load_with_fill_factor(entries, fill_factor)?;
 
         // Mark everything as dirty since we rebuilt the tree
         self.metadata_dirty = true;
@@ -248,6 +313,18 @@ impl PersistentBPlusTree {
         Ok(deleted)
     }
 
+    /// Delete many key-value pairs, returning how many were found
+    pub fn bulk_delete(&mut self, entries: &[(i64, RecordId)]) -> IndexResult<usize> {
+        let deleted = self.tree.bulk_delete(entries)?;
+
+        if deleted > 0 {
+            self.metadata_dirty = true;
+            self.mark_all_nodes_dirty();
+        }
+
+        Ok(deleted)
+    }
+
     /// Search for a key (returns first match)
     pub fn search(&self, key: i64) -> Option<RecordId> {
         self.tree.search(key)
@@ -263,6 +340,26 @@ impl PersistentBPlusTree {
         self.tree.range_search(lower, upper)
     }
 
+    /// Lazy range search [lower, upper]
+    pub fn range_iter(&self, lower: i64, upper: i64) -> BPlusTreeRangeIter<'_> {
+        self.tree.range_iter(lower, upper)
+    }
+
+    /// All entries in descending key order
+    pub fn rev_iter(&self) -> BPlusTreeRevIter<'_> {
+        self.tree.rev_iter()
+    }
+
+    /// Smallest key in the tree
+    pub fn min_key(&self) -> Option<i64> {
+        self.tree.min_key()
+    }
+
+    /// Largest key in the tree
+    pub fn max_key(&self) -> Option<i64> {
+        self.tree.max_key()
+    }
+
     /// Update a specific entry
     pub fn update(
         &mut self,
@@ -298,6 +395,11 @@ impl PersistentBPlusTree {
         self.tree.is_empty()
     }
 
+    /// Get the B+ tree order
+    pub fn order(&self) -> usize {
+        self.tree.order()
+    }
+
     // Helper methods
 
     /// Mark all nodes as dirty (simplified approach)
@@ -282,6 +282,13 @@ pub fn deserialize_internal_node(buf: &[u8]) -> IndexResult<InternalNode> {
     // Skip reserved
     offset += 13;
 
+    if offset + entry_count * 12 > PAGE_SIZE {
+        return Err(IndexError::DeserializationError(format!(
+            "{} entries do not fit in an internal node page",
+            entry_count
+        )));
+    }
+
     // Read keys and children
     let mut keys = Vec::with_capacity(entry_count);
     let mut children = Vec::with_capacity(entry_count);
@@ -354,6 +361,13 @@ pub fn deserialize_leaf_node(buf: &[u8]) -> IndexResult<LeafNode> {
     // Skip reserved
     offset += 9;
 
+    if offset + entry_count * 16 > PAGE_SIZE {
+        return Err(IndexError::DeserializationError(format!(
+            "{} entries do not fit in a leaf node page",
+            entry_count
+        )));
+    }
+
     // Read key-value pairs
     let mut keys = Vec::with_capacity(entry_count);
     let mut values = Vec::with_capacity(entry_count);
@@ -1,11 +1,12 @@
 //! Integration tests for the index layer
 
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
     use crate::file::{BufferManager, PagedFileManager};
     use crate::index::IndexManager;
     use crate::record::RecordId;
-    use std::sync::{Arc, Mutex};
+    use std::sync::{Arc, RwLock};
     use tempfile::TempDir;
 
     #[test]
@@ -14,7 +15,7 @@ mod tests {
         let db_path = temp_dir.path().to_str().unwrap();
 
         let file_manager = PagedFileManager::new();
-        let buffer_mgr = Arc::new(Mutex::new(BufferManager::new(file_manager)));
+        let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
         let mut manager = IndexManager::new(buffer_mgr);
 
         // Create an index
@@ -76,7 +77,7 @@ mod tests {
         // First session: create and insert
         {
             let file_manager = PagedFileManager::new();
-            let buffer_mgr = Arc::new(Mutex::new(BufferManager::new(file_manager)));
+            let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
             let mut manager = IndexManager::new(buffer_mgr);
 
             manager.create_index(db_path, "test", "col").unwrap();
@@ -101,7 +102,7 @@ mod tests {
         // Second session: reopen and verify
         {
             let file_manager = PagedFileManager::new();
-            let buffer_mgr = Arc::new(Mutex::new(BufferManager::new(file_manager)));
+            let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
             let mut manager = IndexManager::new(buffer_mgr);
 
             manager.open_index(db_path, "test", "col").unwrap();
@@ -124,7 +125,7 @@ mod tests {
         let db_path = temp_dir.path().to_str().unwrap();
 
         let file_manager = PagedFileManager::new();
-        let buffer_mgr = Arc::new(Mutex::new(BufferManager::new(file_manager)));
+        let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
         let mut manager = IndexManager::new(buffer_mgr);
 
         manager.create_index(db_path, "test", "col").unwrap();
@@ -158,7 +159,7 @@ mod tests {
         let db_path = temp_dir.path().to_str().unwrap();
 
         let file_manager = PagedFileManager::new();
-        let buffer_mgr = Arc::new(Mutex::new(BufferManager::new(file_manager)));
+        let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
         let mut manager = IndexManager::new(buffer_mgr);
 
         manager.create_index(db_path, "test", "col").unwrap();
@@ -196,7 +197,7 @@ mod tests {
         let db_path = temp_dir.path().to_str().unwrap();
 
         let file_manager = PagedFileManager::new();
-        let buffer_mgr = Arc::new(Mutex::new(BufferManager::new(file_manager)));
+        let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
         let mut manager = IndexManager::new(buffer_mgr);
 
         manager.create_index(db_path, "test", "col").unwrap();
@@ -247,7 +248,7 @@ mod tests {
         let db_path = temp_dir.path().to_str().unwrap();
 
         let file_manager = PagedFileManager::new();
-        let buffer_mgr = Arc::new(Mutex::new(BufferManager::new(file_manager)));
+        let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
         let mut manager = IndexManager::new(buffer_mgr);
 
         manager.create_index(db_path, "test", "col").unwrap();
@@ -283,4 +284,244 @@ mod tests {
         let results = manager.range_search("test", "col", 2500, 2510);
         assert_eq!(results.len(), 11);
     }
+
+    #[test]
+    fn test_open_index_reuses_cached_handle() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().to_str().unwrap();
+
+        let file_manager = PagedFileManager::new();
+        let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
+        let mut manager = IndexManager::new(buffer_mgr);
+
+        manager.create_index(db_path, "test", "col").unwrap();
+        let rid = RecordId {
+            page_id: 3,
+            slot_id: 4,
+        };
+        manager.insert("test", "col", 7, rid).unwrap();
+
+        // The unflushed entry is still visible, so the file was not re-read
+        manager.open_index(db_path, "test", "col").unwrap();
+        assert_eq!(manager.search("test", "col", 7), Some(rid));
+
+        // Flushing keeps the handle; closing and dropping evict it
+        manager.flush_index("test", "col").unwrap();
+        assert!(manager.is_open("test", "col"));
+        manager.close_index("test", "col").unwrap();
+        assert!(!manager.is_open("test", "col"));
+
+        manager.open_index(db_path, "test", "col").unwrap();
+        assert_eq!(manager.search("test", "col", 7), Some(rid));
+        manager.drop_index(db_path, "test", "col").unwrap();
+        assert!(!manager.is_open("test", "col"));
+        assert!(manager.open_index(db_path, "test", "col").is_err());
+    }
+
+    #[test]
+    fn test_open_rejects_corrupted_nodes() {
+        use crate::file::PAGE_SIZE;
+        use crate::index::IndexError;
+
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().to_str().unwrap();
+        let path = temp_dir.path().join("test_col.idx");
+
+        {
+            let file_manager = PagedFileManager::new();
+            let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
+            let mut manager = IndexManager::new(buffer_mgr);
+            // A small order gives a tree several levels deep
+            let entries = (0..100).map(|i| {
+                Ok((
+                    RecordId {
+                        page_id: i as usize,
+                        slot_id: 0,
+                    },
+                    i,
+                ))
+            });
+            manager
+                .create_index_from_table_with_order(db_path, "test", "col", 4, entries)
+                .unwrap();
+            manager.close_index("test", "col").unwrap();
+        }
+
+        let original = std::fs::read(&path).unwrap();
+        let read_u32 = |offset: usize| {
+            u32::from_le_bytes(original[offset..offset + 4].try_into().unwrap()) as usize
+        };
+        let root_page = read_u32(12) + 1;
+        let leaf_page = read_u32(16) + 1;
+        assert_eq!(
+            original[root_page * PAGE_SIZE],
+            0,
+            "root should be internal"
+        );
+
+        let open = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            let file_manager = PagedFileManager::new();
+            let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
+            let mut manager = IndexManager::new(buffer_mgr);
+            manager.open_index(db_path, "test", "col")
+        };
+        let corrupt = |offset: usize, patch: &[u8]| {
+            let mut bytes = original.clone();
+            bytes[offset..offset + patch.len()].copy_from_slice(patch);
+            open(&bytes)
+        };
+
+        assert!(open(&original).is_ok());
+        let cases = [
+            // Keys out of order in the first leaf
+            (leaf_page * PAGE_SIZE + 16, i64::MAX.to_le_bytes().to_vec()),
+            // More entries than a page holds
+            (leaf_page * PAGE_SIZE + 1, vec![0xFF, 0xFF]),
+            // Unknown node type
+            (leaf_page * PAGE_SIZE, vec![7]),
+            // The root's first child points back at the root
+            (
+                root_page * PAGE_SIZE + 24,
+                ((root_page - 1) as u32).to_le_bytes().to_vec(),
+            ),
+            // A child past the end of the file
+            (root_page * PAGE_SIZE + 24, u32::MAX.to_le_bytes().to_vec()),
+            // The leaf chain starts at an internal node
+            (16, ((root_page - 1) as u32).to_le_bytes().to_vec()),
+        ];
+        for (offset, patch) in cases {
+            assert!(
+                matches!(corrupt(offset, &patch), Err(IndexError::CorruptedNode(_))),
+                "patch at offset {} was not detected",
+                offset
+            );
+        }
+    }
+
+    #[test]
+    fn test_min_max_key_after_reopen() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().to_str().unwrap();
+
+        let file_manager = PagedFileManager::new();
+        let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
+        let mut manager = IndexManager::new(buffer_mgr);
+        assert_eq!(manager.min_key("test", "col"), None);
+
+        let entries = (0..200).map(|i| {
+            Ok((
+                RecordId {
+                    page_id: i as usize,
+                    slot_id: 0,
+                },
+                (i * 7919) % 200 - 100,
+            ))
+        });
+        manager
+            .create_index_from_table_with_order(db_path, "test", "col", 4, entries)
+            .unwrap();
+        manager.close_index("test", "col").unwrap();
+
+        manager.open_index(db_path, "test", "col").unwrap();
+        assert_eq!(manager.min_key("test", "col"), Some(-100));
+        assert_eq!(manager.max_key("test", "col"), Some(99));
+    }
+
+    #[test]
+    fn test_bulk_delete_survives_reopen() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().to_str().unwrap();
+
+        let file_manager = PagedFileManager::new();
+        let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
+        let mut manager = IndexManager::new(buffer_mgr);
+        let rid = |i: i64| RecordId {
+            page_id: i as usize,
+            slot_id: 0,
+        };
+
+        let entries = (0..200).map(|i| Ok((rid(i), i)));
+        manager
+            .create_index_from_table_with_order(db_path, "test", "col", 4, entries)
+            .unwrap();
+        let victims: Vec<(i64, RecordId)> = (0..200)
+            .filter(|i| i % 4 != 0)
+            .map(|i| (i, rid(i)))
+            .collect();
+        assert_eq!(manager.bulk_delete("test", "col", &victims).unwrap(), 150);
+        manager.close_index("test", "col").unwrap();
+
+        // The rebuilt tree is written out, and its backward links are
+        // recovered on open
+        manager.open_index(db_path, "test", "col").unwrap();
+        assert_eq!(manager.search("test", "col", 5), None);
+        assert_eq!(manager.search("test", "col", 8), Some(rid(8)));
+        let keys: Vec<i64> = manager
+            .rev_iter("test", "col")
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(
+            keys,
+            (0..200).rev().filter(|i| i % 4 == 0).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_create_index_with_fill_factor() {
+        use crate::index::IndexError;
+
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().to_str().unwrap();
+
+        let file_manager = PagedFileManager::new();
+        let buffer_mgr = Arc::new(RwLock::new(BufferManager::new(file_manager)));
+        let mut manager = IndexManager::new(buffer_mgr);
+        let entries = || {
+            (0..1000).map(|i| {
+                Ok((
+                    RecordId {
+                        page_id: i as usize,
+                        slot_id: 0,
+                    },
+                    i,
+                ))
+            })
+        };
+
+        manager
+            .create_index_from_table_with_options(db_path, "full", "col", 11, 100, entries())
+            .unwrap();
+        manager
+            .create_index_from_table_with_options(db_path, "sparse", "col", 11, 70, entries())
+            .unwrap();
+        manager.close_index("full", "col").unwrap();
+        manager.close_index("sparse", "col").unwrap();
+
+        // Partially filled leaves take more pages on disk
+        let size = |table: &str| {
+            std::fs::metadata(temp_dir.path().join(format!("{}_col.idx", table)))
+                .unwrap()
+                .len()
+        };
+        assert!(size("sparse") > size("full"));
+
+        manager.open_index(db_path, "sparse", "col").unwrap();
+        for key in [0, 499, 999] {
+            assert_eq!(
+                manager.search("sparse", "col", key),
+                Some(RecordId {
+                    page_id: key as usize,
+                    slot_id: 0
+                })
+            );
+        }
+
+        // An invalid factor is rejected before any file is written
+        assert!(matches!(
+            manager.create_index_from_table_with_options(db_path, "bad", "col", 11, 0, entries()),
+            Err(IndexError::BPlusTreeError(_))
+        ));
+        assert!(!temp_dir.path().join("bad_col.idx").exists());
+    }
 }
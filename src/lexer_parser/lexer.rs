@@ -1,4 +1,5 @@
 use chumsky::{prelude::*, regex::regex, text::ascii::ident};
+use std::borrow::Cow;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum KeywordEnum {
@@ -55,6 +56,35 @@ pub enum KeywordEnum {
     Data,
     Default,
     Constraint,
+    Reindex,
+    Check,
+    As,
+    Cast,
+    Flush,
+    Checkpoint,
+    Replace,
+    With,
+    Columns,
+    Unique,
+    CurrentDate,
+    CurrentTimestamp,
+    Modify,
+    Column,
+    Returning,
+    Or,
+    Having,
+    Begin,
+    Commit,
+    Rollback,
+    Decimal,
+    Boolean,
+    True,
+    False,
+    AutoIncrement,
+    On,
+    Duplicate,
+    Explain,
+    Escape,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -64,9 +94,46 @@ pub enum SQLToken<'a> {
     Symbol(char),
     Integer(i64),
     Float(f64),
-    String(&'a str),
+    /// String literal contents with `''` and backslash escapes already decoded
+    String(Cow<'a, str>),
+    /// A `?` parameter placeholder, numbered from 0 in order of appearance
+    Placeholder(usize),
 }
 
+/// Decode the body of a string literal, borrowing it when there is nothing to decode
+///
+/// `''` stands for one quote. A backslash escapes the next character, with
+/// `\n`, `\t`, `\r` and `\0` naming control characters as in MySQL.
+fn unescape_string(body: &str) -> Cow<'_, str> {
+    if !body.contains(['\\', '\'']) {
+        return Cow::Borrowed(body);
+    }
+
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('r') => out.push('\r'),
+                Some('0') => out.push('\0'),
+                Some(other) => out.push(other),
+                None => {}
+            },
+            // The lexer only accepts quotes in pairs
+            '\'' => {
+                chars.next();
+                out.push('\'');
+            }
+            _ => out.push(c),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// The case-sensitive lexer, as `parse` uses it; only the lexer tests call it directly
+#[cfg(test)]
 pub fn lexer<'a>() -> impl Parser<'a, &'a str, Vec<SQLToken<'a>>, extra::Err<Rich<'a, char>>> {
     lexer_with_keyword_case(false)
 }
@@ -74,39 +141,57 @@ pub fn lexer<'a>() -> impl Parser<'a, &'a str, Vec<SQLToken<'a>>, extra::Err<Ric
 pub fn lexer_with_keyword_case<'a>(
     case_insensitive: bool,
 ) -> impl Parser<'a, &'a str, Vec<SQLToken<'a>>, extra::Err<Rich<'a, char>>> {
-    let comment = just("--")
+    let line_comment = just("--")
         .ignore_then(none_of([';', '\n']).repeated())
-        .padded()
         .ignored();
 
-    let number = regex(r"-?\d+\.\d*")
+    let block_comment = just("/*")
+        .ignore_then(any().and_is(just("*/").not()).repeated())
+        .then_ignore(just("*/"))
+        .ignored();
+
+    let comment = line_comment.or(block_comment).padded();
+
+    // Either a decimal point or an exponent (`e`/`E`, optionally signed) makes a float;
+    // a leading minus is a separate symbol, applied by the parser
+    let number = regex(r"\d+\.\d*(?:[eE][+-]?\d+)?|\d+[eE][+-]?\d+")
         .try_map(|s: &str, span| {
             s.parse::<f64>()
-                .map(|val| SQLToken::Float(val))
+                .map(SQLToken::Float)
                 .map_err(|err| Rich::custom(span, err.to_string()))
         })
         .padded();
 
-    let integer = regex(r"-?\d+")
-        .try_map(|s: &str, span| {
-            s.parse::<i64>()
-                .map(|val| SQLToken::Integer(val))
-                .map_err(|err| Rich::custom(span, err.to_string()))
+    // `9223372036854775808` only fits once negated, so it becomes Integer(i64::MIN)
+    // and the parser accepts it only after a minus
+    let integer = regex(r"\d+")
+        .try_map(|s: &str, span| match s.parse::<i64>() {
+            Ok(i) => Ok(SQLToken::Integer(i)),
+            Err(_) if s.parse::<u64>() == Ok(i64::MIN.unsigned_abs()) => {
+                Ok(SQLToken::Integer(i64::MIN))
+            }
+            Err(err) => Err(Rich::custom(span, err.to_string())),
         })
         .padded();
 
-    let string = regex(r#"'([^'\\]|\\.)*'"#)
-        .map(|s: &str| SQLToken::String(&s[1..s.len() - 1]))
+    // A quote inside a literal is written as '' or \'
+    let string = regex(r#"'([^'\\]|\\.|'')*'"#)
+        .map(|s: &str| SQLToken::String(unescape_string(&s[1..s.len() - 1])))
         .padded();
 
     let identifier = ident().map(|s: &str| SQLToken::Identifier(s)).padded();
 
+    // Backticks or double quotes let reserved words and special characters be used as names
+    let quoted_identifier = regex(r#"`[^`]+`|"[^"]+""#)
+        .map(|s: &str| SQLToken::Identifier(&s[1..s.len() - 1]))
+        .padded();
+
     let keyword = {
         // Use a regex with word boundaries so keywords aren't matched as prefixes of identifiers
         let pattern = if case_insensitive {
-            r"(?i)\b(?:CREATE|DATABASE|DATABASES|DROP|SHOW|USE|TABLES|INDEX|INDEXES|TABLE|ALTER|ADD|PRIMARY|KEY|FOREIGN|REFERENCES|INFILE|INTO|FIELDS|TERMINATED|BY|VALUES|INSERT|DELETE|FROM|UPDATE|SET|WHERE|SELECT|COUNT|AVERAGE|AVG|MAX|MIN|SUM|GROUP|ORDER|LIMIT|OFFSET|ASC|DESC|NULL|NOT|LIKE|INT|VARCHAR|FLOAT|IS|IN|AND|LOAD|DATA|DEFAULT|CONSTRAINT)\b"
+            r"(?i)\b(?:CREATE|DATABASE|DATABASES|DROP|SHOW|USE|TABLES|INDEX|INDEXES|TABLE|ALTER|ADD|PRIMARY|KEY|FOREIGN|REFERENCES|INFILE|INTO|FIELDS|TERMINATED|BY|VALUES|INSERT|DELETE|FROM|UPDATE|SET|WHERE|SELECT|COUNT|AVERAGE|AVG|MAX|MIN|SUM|GROUP|ORDER|LIMIT|OFFSET|ASC|DESC|NULL|NOT|LIKE|INT|VARCHAR|FLOAT|IS|IN|AND|LOAD|DATA|DEFAULT|CONSTRAINT|REINDEX|CHECK|AS|CAST|FLUSH|CHECKPOINT|REPLACE|WITH|COLUMNS|UNIQUE|CURRENT_DATE|CURRENT_TIMESTAMP|MODIFY|COLUMN|RETURNING|OR|HAVING|BEGIN|COMMIT|ROLLBACK|DECIMAL|BOOLEAN|BOOL|TRUE|FALSE|AUTO_INCREMENT|ON|DUPLICATE|EXPLAIN|ESCAPE)\b"
         } else {
-            r"\b(?:CREATE|DATABASE|DATABASES|DROP|SHOW|USE|TABLES|INDEX|INDEXES|TABLE|ALTER|ADD|PRIMARY|KEY|FOREIGN|REFERENCES|INFILE|INTO|FIELDS|TERMINATED|BY|VALUES|INSERT|DELETE|FROM|UPDATE|SET|WHERE|SELECT|COUNT|AVERAGE|AVG|MAX|MIN|SUM|GROUP|ORDER|LIMIT|OFFSET|ASC|DESC|NULL|NOT|LIKE|INT|VARCHAR|FLOAT|IS|IN|AND|LOAD|DATA|DEFAULT|CONSTRAINT)\b"
+            r"\b(?:CREATE|DATABASE|DATABASES|DROP|SHOW|USE|TABLES|INDEX|INDEXES|TABLE|ALTER|ADD|PRIMARY|KEY|FOREIGN|REFERENCES|INFILE|INTO|FIELDS|TERMINATED|BY|VALUES|INSERT|DELETE|FROM|UPDATE|SET|WHERE|SELECT|COUNT|AVERAGE|AVG|MAX|MIN|SUM|GROUP|ORDER|LIMIT|OFFSET|ASC|DESC|NULL|NOT|LIKE|INT|VARCHAR|FLOAT|IS|IN|AND|LOAD|DATA|DEFAULT|CONSTRAINT|REINDEX|CHECK|AS|CAST|FLUSH|CHECKPOINT|REPLACE|WITH|COLUMNS|UNIQUE|CURRENT_DATE|CURRENT_TIMESTAMP|MODIFY|COLUMN|RETURNING|OR|HAVING|BEGIN|COMMIT|ROLLBACK|DECIMAL|BOOLEAN|BOOL|TRUE|FALSE|AUTO_INCREMENT|ON|DUPLICATE|EXPLAIN|ESCAPE)\b"
         };
         regex(pattern)
             .map(move |s: &str| {
@@ -166,6 +251,36 @@ pub fn lexer_with_keyword_case<'a>(
                         "DATA" => KeywordEnum::Data,
                         "DEFAULT" => KeywordEnum::Default,
                         "CONSTRAINT" => KeywordEnum::Constraint,
+                        "REINDEX" => KeywordEnum::Reindex,
+                        "CHECK" => KeywordEnum::Check,
+                        "AS" => KeywordEnum::As,
+                        "CAST" => KeywordEnum::Cast,
+                        "FLUSH" => KeywordEnum::Flush,
+                        "CHECKPOINT" => KeywordEnum::Checkpoint,
+                        "REPLACE" => KeywordEnum::Replace,
+                        "WITH" => KeywordEnum::With,
+                        "COLUMNS" => KeywordEnum::Columns,
+                        "UNIQUE" => KeywordEnum::Unique,
+                        "CURRENT_DATE" => KeywordEnum::CurrentDate,
+                        "CURRENT_TIMESTAMP" => KeywordEnum::CurrentTimestamp,
+                        "MODIFY" => KeywordEnum::Modify,
+                        "COLUMN" => KeywordEnum::Column,
+                        "RETURNING" => KeywordEnum::Returning,
+                        "OR" => KeywordEnum::Or,
+                        "HAVING" => KeywordEnum::Having,
+                        "BEGIN" => KeywordEnum::Begin,
+                        "COMMIT" => KeywordEnum::Commit,
+                        "ROLLBACK" => KeywordEnum::Rollback,
+                        "DECIMAL" => KeywordEnum::Decimal,
+                        "BOOLEAN" => KeywordEnum::Boolean,
+                        "BOOL" => KeywordEnum::Boolean,
+                        "TRUE" => KeywordEnum::True,
+                        "FALSE" => KeywordEnum::False,
+                        "AUTO_INCREMENT" => KeywordEnum::AutoIncrement,
+                        "ON" => KeywordEnum::On,
+                        "DUPLICATE" => KeywordEnum::Duplicate,
+                        "EXPLAIN" => KeywordEnum::Explain,
+                        "ESCAPE" => KeywordEnum::Escape,
                         _ => unreachable!(),
                     }
                 } else {
@@ -224,16 +339,66 @@ pub fn lexer_with_keyword_case<'a>(
                         "DATA" => KeywordEnum::Data,
                         "DEFAULT" => KeywordEnum::Default,
                         "CONSTRAINT" => KeywordEnum::Constraint,
+                        "REINDEX" => KeywordEnum::Reindex,
+                        "CHECK" => KeywordEnum::Check,
+                        "AS" => KeywordEnum::As,
+                        "CAST" => KeywordEnum::Cast,
+                        "FLUSH" => KeywordEnum::Flush,
+                        "CHECKPOINT" => KeywordEnum::Checkpoint,
+                        "REPLACE" => KeywordEnum::Replace,
+                        "WITH" => KeywordEnum::With,
+                        "COLUMNS" => KeywordEnum::Columns,
+                        "UNIQUE" => KeywordEnum::Unique,
+                        "CURRENT_DATE" => KeywordEnum::CurrentDate,
+                        "CURRENT_TIMESTAMP" => KeywordEnum::CurrentTimestamp,
+                        "MODIFY" => KeywordEnum::Modify,
+                        "COLUMN" => KeywordEnum::Column,
+                        "RETURNING" => KeywordEnum::Returning,
+                        "OR" => KeywordEnum::Or,
+                        "HAVING" => KeywordEnum::Having,
+                        "BEGIN" => KeywordEnum::Begin,
+                        "COMMIT" => KeywordEnum::Commit,
+                        "ROLLBACK" => KeywordEnum::Rollback,
+                        "DECIMAL" => KeywordEnum::Decimal,
+                        "BOOLEAN" => KeywordEnum::Boolean,
+                        "BOOL" => KeywordEnum::Boolean,
+                        "TRUE" => KeywordEnum::True,
+                        "FALSE" => KeywordEnum::False,
+                        "AUTO_INCREMENT" => KeywordEnum::AutoIncrement,
+                        "ON" => KeywordEnum::On,
+                        "DUPLICATE" => KeywordEnum::Duplicate,
+                        "EXPLAIN" => KeywordEnum::Explain,
+                        "ESCAPE" => KeywordEnum::Escape,
                         _ => unreachable!(),
                     }
                 })
             })
             .padded()
     };
-    let symbol = one_of("(),;=*<>.").map(SQLToken::Symbol).padded();
+    let symbol = one_of("(),;=*<>!.+-/%").map(SQLToken::Symbol).padded();
+    let placeholder = just('?').to(SQLToken::Placeholder(0)).padded();
 
-    choice((number, integer, string, keyword, identifier, symbol))
-        .separated_by(comment.repeated().or_not())
-        .collect()
-        .delimited_by(comment.repeated().or_not(), comment.repeated().or_not())
+    choice((
+        number,
+        integer,
+        string,
+        quoted_identifier,
+        keyword,
+        identifier,
+        symbol,
+        placeholder,
+    ))
+    .separated_by(comment.repeated().or_not())
+    .collect::<Vec<_>>()
+    .delimited_by(comment.repeated().or_not(), comment.repeated().or_not())
+    .map(|mut tokens| {
+        let mut next = 0;
+        for token in &mut tokens {
+            if let SQLToken::Placeholder(n) = token {
+                *n = next;
+                next += 1;
+            }
+        }
+        tokens
+    })
 }
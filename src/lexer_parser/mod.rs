@@ -1,11 +1,12 @@
 mod lexer;
 mod parser;
 
+use lexer::lexer_with_keyword_case;
 pub use lexer::{KeywordEnum, SQLToken};
-use lexer::{lexer, lexer_with_keyword_case};
 pub use parser::{
-    AlterStatement, ColumnType, CreateTableField, DBStatement, Expression, Operator, Query,
-    SelectClause, Selector, Selectors, TableColumn, TableStatement, Value, WhereClause, parser,
+    AlterStatement, ArithmeticOperator, CastType, ColumnType, CreateTableField, DBStatement,
+    DefaultValue, Expression, Operator, Query, SelectClause, Selector, Selectors, TableColumn,
+    TableStatement, Value, WhereClause, parser,
 };
 
 use chumsky::Parser;
@@ -43,6 +44,7 @@ fn parse_with_keyword_case(input: &str, case_insensitive: bool) -> Result<Vec<Qu
 mod tests {
     use std::vec;
 
+    use crate::lexer_parser::lexer::lexer;
     use crate::lexer_parser::parser::{SelectClause, TableColumn, WhereClause};
 
     use super::*;
@@ -64,9 +66,10 @@ mod tests {
         let result = lexer().parse(query);
         assert!(!result.has_errors());
         let tokens = result.unwrap();
-        assert_eq!(tokens.len(), 2);
-        assert_eq!(tokens[0], SQLToken::Integer(-123));
-        assert_eq!(tokens[1], SQLToken::Float(45.67));
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[0], SQLToken::Symbol('-'));
+        assert_eq!(tokens[1], SQLToken::Integer(123));
+        assert_eq!(tokens[2], SQLToken::Float(45.67));
 
         let query = "1s";
         let result = lexer().parse(query);
@@ -77,6 +80,62 @@ mod tests {
         assert_eq!(tokens[1], SQLToken::Identifier("s"));
     }
 
+    #[test]
+    fn test_lexer_float_literals() {
+        let query = "0.0 1e9 2.5E-3 3.5e10 1E-4 6.02e+23 1.e2";
+        let tokens = lexer().parse(query).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                SQLToken::Float(0.0),
+                SQLToken::Float(1e9),
+                SQLToken::Float(2.5e-3),
+                SQLToken::Float(3.5e10),
+                SQLToken::Float(1e-4),
+                SQLToken::Float(6.02e23),
+                SQLToken::Float(100.0),
+            ]
+        );
+
+        // An exponent needs digits, otherwise the letter starts an identifier
+        let tokens = lexer().parse("1e e5").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                SQLToken::Integer(1),
+                SQLToken::Identifier("e"),
+                SQLToken::Identifier("e5"),
+            ]
+        );
+
+        let query = "2147483647 2147483648 9223372036854775807 9223372036854775808";
+        let tokens = lexer().parse(query).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                SQLToken::Integer(i32::MAX as i64),
+                SQLToken::Integer(i32::MAX as i64 + 1),
+                SQLToken::Integer(i64::MAX),
+                // Only valid after a minus sign, see the parser
+                SQLToken::Integer(i64::MIN),
+            ]
+        );
+        assert!(lexer().parse("9223372036854775809").has_errors());
+
+        let result = parse("INSERT INTO t VALUES (-3.5e10, 1E-4, -7);");
+        assert_eq!(
+            result.unwrap(),
+            vec![Query::TableStmt(parser::TableStatement::InsertInto(
+                "t".into(),
+                vec![vec![
+                    parser::Value::Float(-3.5e10),
+                    parser::Value::Float(1e-4),
+                    parser::Value::Integer(-7),
+                ]]
+            ))]
+        );
+    }
+
     #[test]
     fn test_lexer_annotation() {
         let query = "-- This is a comment;\nSELECT * FROM table;";
@@ -96,6 +155,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_lexer_block_comment() {
+        let query =
+            "/* header; with semicolon */ SELECT * /* inline */ FROM t; /*\n multi\n line */";
+        let tokens = lexer().parse(query).unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                SQLToken::Keyword(KeywordEnum::Select),
+                SQLToken::Symbol('*'),
+                SQLToken::Keyword(KeywordEnum::From),
+                SQLToken::Identifier("t"),
+                SQLToken::Symbol(';')
+            ]
+        );
+
+        // Division is still a symbol, and comment markers inside strings are data
+        let tokens = lexer().parse("a / 2 '/* x */' '-- y'").unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                SQLToken::Identifier("a"),
+                SQLToken::Symbol('/'),
+                SQLToken::Integer(2),
+                SQLToken::String("/* x */".into()),
+                SQLToken::String("-- y".into()),
+            ]
+        );
+
+        assert!(parse("SELECT * FROM t; /* unterminated").is_err());
+
+        let query = "-- create\nCREATE DATABASE a; /* then */ DROP DATABASE a; -- done";
+        assert_eq!(
+            parse(query).unwrap(),
+            vec![
+                Query::DBStmt(parser::DBStatement::CreateDatabase("a".into())),
+                Query::DBStmt(parser::DBStatement::DropDatabase("a".into())),
+            ]
+        );
+    }
+
     #[test]
     fn test_db_stmt() {
         let query = "CREATE DATABASE test_db; DROP DATABASE test_db; SHOW DATABASES; USE test_db; SHOW TABLES; SHOW INDEXES;";
@@ -107,9 +207,9 @@ mod tests {
             vec![
                 Query::DBStmt(DBStatement::CreateDatabase("test_db".into())),
                 Query::DBStmt(DBStatement::DropDatabase("test_db".into())),
-                Query::DBStmt(DBStatement::ShowDatabases),
+                Query::DBStmt(DBStatement::ShowDatabases(None)),
                 Query::DBStmt(DBStatement::UseDatabase("test_db".into())),
-                Query::DBStmt(DBStatement::ShowTables),
+                Query::DBStmt(DBStatement::ShowTables(None)),
                 Query::DBStmt(DBStatement::ShowIndexes),
             ]
         );
@@ -144,12 +244,14 @@ mod tests {
                 Query::AlterStmt(AlterStatement::AddIndex(
                     "my_table".into(),
                     Some("my_index".into()),
-                    vec!["col1".into(), "col2".into()]
+                    vec!["col1".into(), "col2".into()],
+                    None
                 )),
                 Query::AlterStmt(AlterStatement::AddIndex(
                     "my_table".into(),
                     None,
-                    vec!["col3".into()]
+                    vec!["col3".into()],
+                    None
                 )),
                 Query::AlterStmt(AlterStatement::DropIndex(
                     "my_table".into(),
@@ -190,6 +292,93 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_modify_column() {
+        let query = "
+            ALTER TABLE t MODIFY c VARCHAR(50);
+            ALTER TABLE t MODIFY COLUMN n FLOAT;
+            ";
+
+        assert_eq!(
+            parse(query).unwrap(),
+            vec![
+                Query::AlterStmt(AlterStatement::ModifyColumn(
+                    "t".into(),
+                    "c".into(),
+                    ColumnType::Char(50)
+                )),
+                Query::AlterStmt(AlterStatement::ModifyColumn(
+                    "t".into(),
+                    "n".into(),
+                    ColumnType::Float
+                )),
+            ]
+        );
+        assert!(parse("ALTER TABLE t MODIFY c;").is_err());
+    }
+
+    #[test]
+    fn test_add_index_with_order() {
+        let query = "
+            ALTER TABLE t ADD INDEX idx_a (a) WITH (order = 16);
+            ALTER TABLE t ADD INDEX (a, b) WITH (ORDER = 3);
+            ";
+
+        assert_eq!(
+            parse(query).unwrap(),
+            vec![
+                Query::AlterStmt(AlterStatement::AddIndex(
+                    "t".into(),
+                    Some("idx_a".into()),
+                    vec!["a".into()],
+                    Some(16)
+                )),
+                Query::AlterStmt(AlterStatement::AddIndex(
+                    "t".into(),
+                    None,
+                    vec!["a".into(), "b".into()],
+                    Some(3)
+                )),
+            ]
+        );
+
+        assert!(parse("ALTER TABLE t ADD INDEX (a) WITH (order = -1);").is_err());
+        assert!(parse("ALTER TABLE t ADD INDEX (a) WITH (fanout = 8);").is_err());
+        assert!(parse("ALTER TABLE t ADD INDEX (a) WITH ();").is_err());
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let result = parse("UPDATE t SET a = ? WHERE b = ? AND c IN (1, ?);").unwrap();
+        assert_eq!(
+            result,
+            vec![Query::TableStmt(parser::TableStatement::Update(
+                "t".into(),
+                vec![("a".into(), Value::Placeholder(0))],
+                Some(vec![
+                    WhereClause::Op(
+                        TableColumn {
+                            table: None,
+                            column: "b".into()
+                        },
+                        Operator::Eq,
+                        Expression::Value(Value::Placeholder(1))
+                    ),
+                    WhereClause::In(
+                        TableColumn {
+                            table: None,
+                            column: "c".into()
+                        },
+                        vec![Value::Integer(1), Value::Placeholder(2)]
+                    ),
+                ])
+            ))]
+        );
+
+        // A placeholder is a value, not a name
+        assert!(parse("SELECT * FROM ?;").is_err());
+    }
+
     #[test]
     fn test_annotation_null() {
         let query = "-- Leading Annotation;
@@ -216,6 +405,8 @@ CREATE DATABASE test_db; -- Trailing Annotation
         let query = "
         DROP TABLE my_table;
         DESC my_table;
+        SHOW COLUMNS FROM my_table;
+        SHOW COLUMNS FROM my_table LIKE 'addr%';
         INSERT INTO my_table VALUES (1, 'value'), ('other', 4.2);
         LOAD DATA INFILE 'data.txt' INTO TABLE my_table FIELDS TERMINATED BY ',';
         ";
@@ -227,6 +418,11 @@ CREATE DATABASE test_db; -- Trailing Annotation
             vec![
                 Query::TableStmt(parser::TableStatement::DropTable("my_table".into())),
                 Query::TableStmt(parser::TableStatement::DescribeTable("my_table".into())),
+                Query::TableStmt(parser::TableStatement::ShowColumns("my_table".into(), None)),
+                Query::TableStmt(parser::TableStatement::ShowColumns(
+                    "my_table".into(),
+                    Some("addr%".into())
+                )),
                 Query::TableStmt(parser::TableStatement::InsertInto(
                     "my_table".into(),
                     vec![
@@ -280,7 +476,8 @@ CREATE DATABASE test_db; -- Trailing Annotation
                             parser::Operator::Eq,
                             parser::Expression::Value(parser::Value::String("value".into()))
                         )
-                    ])
+                    ]),
+                    false
                 )),
                 Query::TableStmt(parser::TableStatement::Update(
                     "my_table".into(),
@@ -304,6 +501,282 @@ CREATE DATABASE test_db; -- Trailing Annotation
         )
     }
 
+    #[test]
+    fn test_delete_returning() {
+        let returning = |query: &str| match parse(query).unwrap().pop() {
+            Some(Query::TableStmt(parser::TableStatement::DeleteFrom(_, _, returning))) => {
+                returning
+            }
+            other => panic!("unexpected statement {:?}", other),
+        };
+        assert!(returning("DELETE FROM t WHERE a = 1 RETURNING *;"));
+        assert!(returning("DELETE FROM t RETURNING *;"));
+        assert!(!returning("DELETE FROM t WHERE a = 1;"));
+        assert!(parse("DELETE FROM t RETURNING;").is_err());
+    }
+
+    #[test]
+    fn test_where_constant() {
+        let where_clauses = |query: &str| match parse(query).unwrap().pop() {
+            Some(Query::TableStmt(parser::TableStatement::DeleteFrom(_, Some(clauses), _))) => {
+                clauses
+            }
+            other => panic!("unexpected statement {:?}", other),
+        };
+        assert_eq!(
+            where_clauses("DELETE FROM t WHERE 1 = 1 AND a = 2;"),
+            vec![
+                WhereClause::Constant(
+                    parser::Value::Integer(1),
+                    parser::Operator::Eq,
+                    parser::Value::Integer(1)
+                ),
+                WhereClause::Op(
+                    TableColumn {
+                        table: None,
+                        column: "a".into()
+                    },
+                    parser::Operator::Eq,
+                    parser::Expression::Value(parser::Value::Integer(2))
+                ),
+            ]
+        );
+        assert_eq!(
+            where_clauses("DELETE FROM t WHERE 'x' <> ?;"),
+            vec![WhereClause::Constant(
+                parser::Value::String("x".into()),
+                parser::Operator::Ne,
+                parser::Value::Placeholder(0)
+            )]
+        );
+    }
+
+    #[test]
+    fn test_where_or() {
+        let where_clauses = |query: &str| match parse(query).unwrap().pop() {
+            Some(Query::TableStmt(parser::TableStatement::DeleteFrom(_, Some(clauses), _))) => {
+                clauses
+            }
+            other => panic!("unexpected statement {:?}", other),
+        };
+        let eq = |column: &str, value: i64| {
+            WhereClause::Op(
+                TableColumn {
+                    table: None,
+                    column: column.into(),
+                },
+                parser::Operator::Eq,
+                parser::Expression::Value(parser::Value::Integer(value)),
+            )
+        };
+
+        // AND binds tighter than OR
+        assert_eq!(
+            where_clauses("DELETE FROM t WHERE a = 1 OR b = 2 AND c = 3;"),
+            vec![WhereClause::Or(vec![
+                vec![eq("a", 1)],
+                vec![eq("b", 2), eq("c", 3)]
+            ])]
+        );
+        assert_eq!(
+            where_clauses("DELETE FROM t WHERE (a = 1 OR b = 2) AND c = 3;"),
+            vec![
+                WhereClause::Or(vec![vec![eq("a", 1)], vec![eq("b", 2)]]),
+                eq("c", 3)
+            ]
+        );
+        // Parentheses around an AND list add nothing to it
+        assert_eq!(
+            where_clauses("DELETE FROM t WHERE (a = 1 AND (b = 2)) AND c = 3;"),
+            vec![eq("a", 1), eq("b", 2), eq("c", 3)]
+        );
+        assert_eq!(
+            where_clauses("DELETE FROM t WHERE a = 1 OR (b = 2 OR c = 3) OR d = 4;"),
+            vec![WhereClause::Or(vec![
+                vec![eq("a", 1)],
+                vec![WhereClause::Or(vec![vec![eq("b", 2)], vec![eq("c", 3)]])],
+                vec![eq("d", 4)]
+            ])]
+        );
+        assert!(parse("DELETE FROM t WHERE a = 1 OR;").is_err());
+        assert!(parse("DELETE FROM t WHERE (a = 1;").is_err());
+    }
+
+    #[test]
+    fn test_where_operators() {
+        let parse_op = |query: &str| match parse(query).unwrap().pop() {
+            Some(Query::TableStmt(parser::TableStatement::DeleteFrom(_, Some(mut clauses), _))) => {
+                match clauses.pop() {
+                    Some(WhereClause::Op(_, op, _)) => op,
+                    other => panic!("unexpected clause {:?}", other),
+                }
+            }
+            other => panic!("unexpected statement {:?}", other),
+        };
+        use parser::Operator as O;
+
+        for (spelling, op) in [
+            ("=", O::Eq),
+            ("<>", O::Ne),
+            ("!=", O::Ne),
+            ("<", O::Lt),
+            ("<=", O::Le),
+            (">", O::Gt),
+            (">=", O::Ge),
+            ("<=>", O::NullSafeEq),
+        ] {
+            assert_eq!(
+                parse_op(&format!("DELETE FROM t WHERE a {} 1;", spelling)),
+                op,
+                "operator {}",
+                spelling
+            );
+        }
+
+        let err = parse("DELETE FROM t WHERE a == 1;").unwrap_err();
+        assert!(err.contains("'==' is not a comparison operator"), "{}", err);
+        assert!(parse("DELETE FROM t WHERE a ! 1;").is_err());
+        assert!(parse("DELETE FROM t WHERE a =! 1;").is_err());
+    }
+
+    #[test]
+    fn test_where_arithmetic() {
+        let parse_expr = |query: &str| match parse(query).unwrap().pop() {
+            Some(Query::TableStmt(parser::TableStatement::DeleteFrom(_, Some(mut clauses), _))) => {
+                match clauses.pop() {
+                    Some(WhereClause::Op(_, _, expr)) => expr,
+                    other => panic!("unexpected clause {:?}", other),
+                }
+            }
+            other => panic!("unexpected statement {:?}", other),
+        };
+        let int = |i| Box::new(parser::Expression::Value(parser::Value::Integer(i)));
+        let col = |name: &str| {
+            Box::new(parser::Expression::Column(TableColumn {
+                table: None,
+                column: name.into(),
+            }))
+        };
+        use parser::ArithmeticOperator as A;
+        use parser::Expression::Arithmetic as Arith;
+
+        // Multiplicative operators bind tighter and everything is left-associative
+        assert_eq!(
+            parse_expr("DELETE FROM t WHERE a = 1 + 7 % 3 - 2;"),
+            Arith(
+                Box::new(Arith(
+                    int(1),
+                    A::Add,
+                    Box::new(Arith(int(7), A::Mod, int(3)))
+                )),
+                A::Sub,
+                int(2)
+            )
+        );
+        assert_eq!(
+            parse_expr("DELETE FROM t WHERE a = (b + 1) / 2;"),
+            Arith(Box::new(Arith(col("b"), A::Add, int(1))), A::Div, int(2))
+        );
+        assert_eq!(
+            parse_expr("DELETE FROM t WHERE a = b-1;"),
+            Arith(col("b"), A::Sub, int(1))
+        );
+        assert_eq!(
+            parse_expr("DELETE FROM t WHERE a = -1;"),
+            parser::Expression::Value(parser::Value::Integer(-1))
+        );
+
+        // A minus after an operand subtracts, anywhere else it negates and
+        // binds tighter than `*`
+        assert_eq!(
+            parse_expr("DELETE FROM t WHERE x = a -1*2;"),
+            Arith(col("a"), A::Sub, Box::new(Arith(int(1), A::Mul, int(2))))
+        );
+        assert_eq!(
+            parse_expr("DELETE FROM t WHERE x = -1*2;"),
+            Arith(int(-1), A::Mul, int(2))
+        );
+        assert_eq!(
+            parse_expr("DELETE FROM t WHERE x = a - -1;"),
+            Arith(col("a"), A::Sub, int(-1))
+        );
+        assert_eq!(
+            parse_expr("DELETE FROM t WHERE x = -a * 2;"),
+            Arith(Box::new(Arith(int(0), A::Sub, col("a"))), A::Mul, int(2))
+        );
+        assert_eq!(
+            parse_expr("DELETE FROM t WHERE x = -(a + 1);"),
+            Arith(int(0), A::Sub, Box::new(Arith(col("a"), A::Add, int(1))))
+        );
+        assert_eq!(
+            parse_expr("DELETE FROM t WHERE x = -2.5;"),
+            parser::Expression::Value(parser::Value::Float(-2.5))
+        );
+    }
+
+    #[test]
+    fn test_select_where_unary_minus() {
+        let where_clauses = |query: &str| match parse(query).unwrap().pop() {
+            Some(Query::TableStmt(parser::TableStatement::Select(clause))) => clause.where_clauses,
+            other => panic!("unexpected statement {:?}", other),
+        };
+        let x = || TableColumn {
+            table: None,
+            column: "x".into(),
+        };
+        let min = || parser::Expression::Value(parser::Value::Integer(i64::MIN));
+
+        assert_eq!(
+            where_clauses("SELECT * FROM t WHERE x = -9223372036854775808;"),
+            vec![WhereClause::Op(x(), Operator::Eq, min())]
+        );
+        assert_eq!(
+            where_clauses("SELECT * FROM t WHERE x = a -1*2;"),
+            vec![WhereClause::Op(
+                x(),
+                Operator::Eq,
+                parser::Expression::Arithmetic(
+                    Box::new(parser::Expression::Column(TableColumn {
+                        table: None,
+                        column: "a".into(),
+                    })),
+                    parser::ArithmeticOperator::Sub,
+                    Box::new(parser::Expression::Arithmetic(
+                        Box::new(parser::Expression::Value(parser::Value::Integer(1))),
+                        parser::ArithmeticOperator::Mul,
+                        Box::new(parser::Expression::Value(parser::Value::Integer(2))),
+                    )),
+                ),
+            )]
+        );
+        // Negating it again overflows, which is left to evaluation
+        assert_eq!(
+            where_clauses("SELECT * FROM t WHERE x = - -9223372036854775808;"),
+            vec![WhereClause::Op(
+                x(),
+                Operator::Eq,
+                parser::Expression::Arithmetic(
+                    Box::new(parser::Expression::Value(parser::Value::Integer(0))),
+                    parser::ArithmeticOperator::Sub,
+                    Box::new(min()),
+                ),
+            )]
+        );
+        assert_eq!(
+            parse("INSERT INTO t VALUES (-9223372036854775808, -0.0);").unwrap(),
+            vec![Query::TableStmt(parser::TableStatement::InsertInto(
+                "t".into(),
+                vec![vec![
+                    parser::Value::Integer(i64::MIN),
+                    parser::Value::Float(-0.0),
+                ]]
+            ))]
+        );
+        assert!(parse("SELECT * FROM t WHERE x = 9223372036854775808;").is_err());
+        assert!(parse("INSERT INTO t VALUES (9223372036854775808);").is_err());
+        assert!(parse("SELECT * FROM t WHERE x = -9223372036854775809;").is_err());
+    }
+
     #[test]
     fn test_table_stmt_select() {
         let query = "
@@ -319,15 +792,21 @@ CREATE DATABASE test_db; -- Trailing Annotation
             vec![
                 Query::TableStmt(parser::TableStatement::Select(SelectClause {
                     table: vec!["my_table".into()],
+                    table_aliases: vec![None],
+                    selector_aliases: vec![],
                     where_clauses: vec![],
                     selectors: parser::Selectors::All,
                     limit: None,
+                    limit_percent: false,
                     offset: None,
-                    order_by: None,
-                    group_by: None
+                    order_by: vec![],
+                    group_by: vec![],
+                    having: Vec::new()
                 })),
                 Query::TableStmt(parser::TableStatement::Select(SelectClause {
                     table: vec!["my_table".into()],
+                    table_aliases: vec![None],
+                    selector_aliases: vec![None, None],
                     where_clauses: vec![WhereClause::In(
                         TableColumn {
                             table: None,
@@ -350,20 +829,206 @@ CREATE DATABASE test_db; -- Trailing Annotation
                         })
                     ]),
                     limit: Some(5),
+                    limit_percent: false,
                     offset: Some(10),
-                    order_by: Some((
-                        TableColumn {
+                    order_by: vec![(
+                        parser::Selector::Column(TableColumn {
                             table: None,
                             column: "col1".into()
-                        },
+                        }),
                         false
-                    )),
-                    group_by: None
+                    )],
+                    group_by: vec![],
+                    having: Vec::new()
                 }))
             ]
         )
     }
 
+    #[test]
+    fn test_dynamic_defaults() {
+        let query = "CREATE TABLE t (d VARCHAR(10) DEFAULT CURRENT_DATE, ts VARCHAR(19) NOT NULL DEFAULT CURRENT_TIMESTAMP, s VARCHAR(12) DEFAULT 'CURRENT_DATE');";
+        assert_eq!(
+            parse(query).unwrap(),
+            vec![Query::TableStmt(parser::TableStatement::CreateTable(
+                "t".into(),
+                vec![
+                    parser::CreateTableField::Col(
+                        "d".into(),
+                        parser::ColumnType::Char(10),
+                        false,
+                        Some(DefaultValue::CurrentDate),
+                        false
+                    ),
+                    parser::CreateTableField::Col(
+                        "ts".into(),
+                        parser::ColumnType::Char(19),
+                        true,
+                        Some(DefaultValue::CurrentTimestamp),
+                        false
+                    ),
+                    parser::CreateTableField::Col(
+                        "s".into(),
+                        parser::ColumnType::Char(12),
+                        false,
+                        Some(DefaultValue::Literal(Value::String("CURRENT_DATE".into()))),
+                        false
+                    ),
+                ]
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_limit_synonyms() {
+        let limits = |sql: &str| match parse(sql).unwrap().remove(0) {
+            Query::TableStmt(parser::TableStatement::Select(clause)) => {
+                (clause.limit, clause.limit_percent, clause.offset)
+            }
+            other => panic!("expected SELECT, got {:?}", other),
+        };
+
+        assert_eq!(limits("SELECT * FROM t LIMIT 3;"), (Some(3), false, None));
+        assert_eq!(limits("SELECT TOP 3 * FROM t;"), (Some(3), false, None));
+        assert_eq!(
+            limits("SELECT * FROM t FETCH FIRST 3 ROWS ONLY;"),
+            (Some(3), false, None)
+        );
+        assert_eq!(
+            limits("SELECT * FROM t FETCH NEXT 1 ROW ONLY;"),
+            (Some(1), false, None)
+        );
+        assert_eq!(
+            limits("SELECT * FROM t LIMIT 25 PERCENT OFFSET 2;"),
+            (Some(25), true, Some(2))
+        );
+        assert_eq!(
+            limits("SELECT TOP 50 PERCENT a FROM t;"),
+            (Some(50), true, None)
+        );
+
+        assert!(parse("SELECT TOP 3 * FROM t LIMIT 3;").is_err());
+        assert!(parse("SELECT * FROM t LIMIT 101 PERCENT;").is_err());
+        assert!(parse("SELECT * FROM t FETCH FIRST 3 ROWS;").is_err());
+
+        // The words only mean something in those spots
+        assert_eq!(
+            limits("SELECT first, next, rows, percent FROM t FETCH FIRST 2 ROWS ONLY;"),
+            (Some(2), false, None)
+        );
+        assert_eq!(limits("SELECT top FROM row only;"), (None, false, None));
+        assert!(parse("CREATE TABLE top (row INT, fetch INT, only INT);").is_ok());
+    }
+
+    #[test]
+    fn test_select_table_aliases() {
+        let query = "SELECT c.name FROM customers c, orders o WHERE c.id = o.cid;";
+        let result = parse(query).unwrap();
+        let Query::TableStmt(parser::TableStatement::Select(clause)) = &result[0] else {
+            panic!("expected SELECT, got {:?}", result);
+        };
+        assert_eq!(clause.table, vec!["customers", "orders"]);
+        assert_eq!(
+            clause.table_aliases,
+            vec![Some("c".to_string()), Some("o".to_string())]
+        );
+        assert_eq!(
+            clause.where_clauses,
+            vec![WhereClause::Op(
+                TableColumn {
+                    table: Some("c".into()),
+                    column: "id".into()
+                },
+                parser::Operator::Eq,
+                parser::Expression::Column(TableColumn {
+                    table: Some("o".into()),
+                    column: "cid".into()
+                })
+            )]
+        );
+
+        // Aliases are optional per table
+        let result = parse("SELECT * FROM a x, b WHERE x.k = b.k;").unwrap();
+        let Query::TableStmt(parser::TableStatement::Select(clause)) = &result[0] else {
+            panic!("expected SELECT, got {:?}", result);
+        };
+        assert_eq!(clause.table, vec!["a", "b"]);
+        assert_eq!(clause.table_aliases, vec![Some("x".to_string()), None]);
+
+        assert!(parse("SELECT * FROM a x y;").is_err());
+    }
+
+    #[test]
+    fn test_not_like_and_escape() {
+        let where_clauses = |query: &str| match parse(query).unwrap().pop() {
+            Some(Query::TableStmt(parser::TableStatement::DeleteFrom(_, Some(clauses), _))) => {
+                clauses
+            }
+            other => panic!("unexpected statement {:?}", other),
+        };
+        let col = || TableColumn {
+            table: None,
+            column: "s".into(),
+        };
+
+        assert_eq!(
+            where_clauses("DELETE FROM t WHERE s NOT LIKE 'a%' AND s LIKE 'b!%' ESCAPE '!';"),
+            vec![
+                WhereClause::NotLike(col(), "a%".into(), None),
+                WhereClause::Like(col(), "b!%".into(), Some('!')),
+            ]
+        );
+        assert!(parse("DELETE FROM t WHERE s LIKE 'a' ESCAPE '';").is_err());
+        assert!(parse("DELETE FROM t WHERE s LIKE 'a' ESCAPE '!!';").is_err());
+        assert!(parse("DELETE FROM t WHERE s NOT 'a';").is_err());
+    }
+
+    #[test]
+    fn test_explain_select() {
+        let result = parse("EXPLAIN SELECT * FROM t WHERE v = 1;").unwrap();
+        let Query::TableStmt(parser::TableStatement::Explain(clause)) = &result[0] else {
+            panic!("expected EXPLAIN, got {:?}", result);
+        };
+        let Query::TableStmt(parser::TableStatement::Select(select)) =
+            &parse("SELECT * FROM t WHERE v = 1;").unwrap()[0]
+        else {
+            panic!("expected SELECT");
+        };
+        assert_eq!(clause, select);
+
+        assert!(parse("EXPLAIN DELETE FROM t;").is_err());
+    }
+
+    #[test]
+    fn test_select_column_aliases() {
+        let result =
+            parse("SELECT name AS n, COUNT(*) AS total, age FROM t GROUP BY name, age;").unwrap();
+        let Query::TableStmt(parser::TableStatement::Select(clause)) = &result[0] else {
+            panic!("expected SELECT, got {:?}", result);
+        };
+        assert_eq!(
+            clause.selectors,
+            Selectors::List(vec![
+                Selector::Column(TableColumn {
+                    table: None,
+                    column: "name".into()
+                }),
+                Selector::CountAll,
+                Selector::Column(TableColumn {
+                    table: None,
+                    column: "age".into()
+                }),
+            ])
+        );
+        assert_eq!(
+            clause.selector_aliases,
+            vec![Some("n".to_string()), Some("total".to_string()), None]
+        );
+
+        assert!(parse("SELECT * AS x FROM t;").is_err());
+        assert!(parse("SELECT a AS FROM t;").is_err());
+    }
+
     #[test]
     fn test_load_table_delimiter() {
         let query = "LOAD DATA INFILE 'data.txt' INTO TABLE my_table FIELDS TERMINATED BY 'abc';";
@@ -371,6 +1036,481 @@ CREATE DATABASE test_db; -- Trailing Annotation
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_select_into_outfile() {
+        let statement = |query: &str| match parse(query).unwrap().pop() {
+            Some(Query::TableStmt(stmt)) => stmt,
+            other => panic!("unexpected statement {:?}", other),
+        };
+
+        match statement("SELECT * FROM t WHERE id > 1 INTO OUTFILE 'out.csv';") {
+            parser::TableStatement::SelectIntoOutfile(clause, path, ',', false) => {
+                assert_eq!(clause.table, vec!["t"]);
+                assert_eq!(clause.where_clauses.len(), 1);
+                assert_eq!(path, "out.csv");
+            }
+            other => panic!("unexpected statement {:?}", other),
+        }
+        assert!(matches!(
+            statement(
+                "SELECT id FROM t ORDER BY id LIMIT 2 INTO OUTFILE 'o' FIELDS TERMINATED BY '|' WITH HEADER;"
+            ),
+            parser::TableStatement::SelectIntoOutfile(_, _, '|', true)
+        ));
+
+        assert!(parse("SELECT * FROM t INTO OUTFILE;").is_err());
+        assert!(parse("SELECT * FROM t INTO OUTFILE 'o' FIELDS TERMINATED BY '||';").is_err());
+
+        // OUTFILE and HEADER are only special after INTO and WITH
+        assert!(parse("CREATE TABLE outfile (header INT);").is_ok());
+        match parse_case_insensitive("select header from outfile into outfile 'o' with header;")
+            .unwrap()
+            .pop()
+        {
+            Some(Query::TableStmt(parser::TableStatement::SelectIntoOutfile(
+                clause,
+                _,
+                ',',
+                true,
+            ))) => {
+                assert_eq!(clause.table, vec!["outfile"]);
+            }
+            other => panic!("unexpected statement {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let strings = |sql: &str| -> Vec<String> {
+            lexer()
+                .parse(sql)
+                .unwrap()
+                .into_iter()
+                .filter_map(|token| match token {
+                    SQLToken::String(s) => Some(s.into_owned()),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        assert_eq!(strings("'O''Brien'"), ["O'Brien"]);
+        assert_eq!(strings(r"'O\'Brien'"), ["O'Brien"]);
+        assert_eq!(strings("'''quoted'''"), ["'quoted'"]);
+        assert_eq!(strings(r"'line1\nline2\ttab'"), ["line1\nline2\ttab"]);
+        assert_eq!(strings(r"'C:\\dir\\' 'a\%'"), [r"C:\dir\", "a%"]);
+        // Adjacent literals stay separate
+        assert_eq!(strings("'', 'a','b'"), ["", "a", "b"]);
+
+        assert_eq!(
+            parse("INSERT INTO t VALUES ('it''s', 'a\\'b');").unwrap(),
+            vec![Query::TableStmt(parser::TableStatement::InsertInto(
+                "t".into(),
+                vec![vec![
+                    Value::String("it's".into()),
+                    Value::String("a'b".into())
+                ]]
+            ))]
+        );
+        assert!(parse("INSERT INTO t VALUES ('it's');").is_err());
+
+        // Escapes make quote and control characters usable as delimiters
+        for (literal, delimiter) in [(r"'\t'", '\t'), ("''''", '\''), ("'|'", '|')] {
+            let query = format!(
+                "LOAD DATA INFILE 'data.txt' INTO TABLE t FIELDS TERMINATED BY {};",
+                literal
+            );
+            assert_eq!(
+                parse(&query).unwrap(),
+                vec![Query::TableStmt(parser::TableStatement::LoadDataInfile(
+                    "data.txt".into(),
+                    "t".into(),
+                    delimiter
+                ))]
+            );
+        }
+        assert!(
+            parse("LOAD DATA INFILE 'data.txt' INTO TABLE t FIELDS TERMINATED BY '';").is_err()
+        );
+    }
+
+    #[test]
+    fn test_maintenance_stmts() {
+        let query = "
+        REINDEX TABLE my_table;
+        REINDEX INDEX idx_id;
+        CHECK TABLE my_table;
+        ";
+
+        let result = parse(query);
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                Query::TableStmt(parser::TableStatement::ReindexTable("my_table".into())),
+                Query::TableStmt(parser::TableStatement::ReindexIndex("idx_id".into())),
+                Query::TableStmt(parser::TableStatement::CheckTable("my_table".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_order_by_aggregate() {
+        let order_by = |query: &str| match parse(query).unwrap().pop() {
+            Some(Query::TableStmt(parser::TableStatement::Select(clause))) => clause.order_by,
+            other => panic!("unexpected statement {:?}", other),
+        };
+        assert_eq!(
+            order_by("SELECT a, COUNT(*) FROM t GROUP BY a ORDER BY COUNT(*) DESC LIMIT 3;"),
+            vec![(parser::Selector::CountAll, false)]
+        );
+        assert_eq!(
+            order_by("SELECT a, SUM(b) FROM t GROUP BY a ORDER BY SUM(t.b);"),
+            vec![(
+                parser::Selector::Sum(TableColumn {
+                    table: Some("t".into()),
+                    column: "b".into()
+                }),
+                true
+            )]
+        );
+    }
+
+    #[test]
+    fn test_having() {
+        let clause = |query: &str| match parse(query).unwrap().pop() {
+            Some(Query::TableStmt(parser::TableStatement::Select(clause))) => clause,
+            other => panic!("unexpected statement {:?}", other),
+        };
+        let grouped = clause(
+            "SELECT dept, COUNT(*) FROM emp GROUP BY dept HAVING COUNT(*) > 5 AND AVG(salary) <= 1.5 ORDER BY dept;",
+        );
+        assert_eq!(
+            grouped.having,
+            vec![
+                (
+                    parser::Selector::CountAll,
+                    parser::Operator::Gt,
+                    parser::Value::Integer(5)
+                ),
+                (
+                    parser::Selector::Average(TableColumn {
+                        table: None,
+                        column: "salary".into()
+                    }),
+                    parser::Operator::Le,
+                    parser::Value::Float(1.5)
+                ),
+            ]
+        );
+        assert_eq!(grouped.order_by.len(), 1);
+        assert_eq!(
+            clause("SELECT a, t.b, COUNT(*) FROM t GROUP BY a, t.b;").group_by,
+            vec![
+                TableColumn {
+                    table: None,
+                    column: "a".into()
+                },
+                TableColumn {
+                    table: Some("t".into()),
+                    column: "b".into()
+                },
+            ]
+        );
+        assert!(parse("SELECT a FROM t GROUP BY a,;").is_err());
+        assert!(clause("SELECT COUNT(*) FROM emp;").having.is_empty());
+        assert!(parse("SELECT dept FROM emp GROUP BY dept HAVING;").is_err());
+        // HAVING comes after GROUP BY
+        assert!(parse("SELECT dept FROM emp HAVING COUNT(*) > 1 GROUP BY dept;").is_err());
+    }
+
+    #[test]
+    fn test_order_by_multiple() {
+        let column = |name: &str| {
+            parser::Selector::Column(TableColumn {
+                table: None,
+                column: name.into(),
+            })
+        };
+        let clause = match parse("SELECT * FROM t ORDER BY a, b DESC, c ASC LIMIT 1;")
+            .unwrap()
+            .pop()
+        {
+            Some(Query::TableStmt(parser::TableStatement::Select(clause))) => clause,
+            other => panic!("unexpected statement {:?}", other),
+        };
+        assert_eq!(
+            clause.order_by,
+            vec![
+                (column("a"), true),
+                (column("b"), false),
+                (column("c"), true)
+            ]
+        );
+        assert_eq!(clause.limit, Some(1));
+        assert!(parse("SELECT * FROM t ORDER BY a,;").is_err());
+    }
+
+    #[test]
+    fn test_select_from_other_database() {
+        let clause = match parse("SELECT * FROM t, other.t o;").unwrap().pop() {
+            Some(Query::TableStmt(parser::TableStatement::Select(clause))) => clause,
+            other => panic!("unexpected statement {:?}", other),
+        };
+        assert_eq!(clause.table, vec!["t".to_string(), "other.t".to_string()]);
+        assert_eq!(clause.table_aliases, vec![None, Some("o".to_string())]);
+    }
+
+    #[test]
+    fn test_show_like() {
+        assert_eq!(
+            parse("SHOW TABLES LIKE 'user_%'; SHOW DATABASES LIKE 'shop%';").unwrap(),
+            vec![
+                Query::DBStmt(DBStatement::ShowTables(Some("user_%".into()))),
+                Query::DBStmt(DBStatement::ShowDatabases(Some("shop%".into()))),
+            ]
+        );
+        assert!(parse("SHOW TABLES LIKE;").is_err());
+    }
+
+    #[test]
+    fn test_flush() {
+        for sql in ["FLUSH;", "CHECKPOINT;"] {
+            assert_eq!(
+                parse(sql).unwrap(),
+                vec![Query::DBStmt(parser::DBStatement::Flush)]
+            );
+        }
+        assert_eq!(
+            parse("FLUSH STATUS; SHOW STATUS;").unwrap(),
+            vec![
+                Query::DBStmt(parser::DBStatement::FlushStatus),
+                Query::DBStmt(parser::DBStatement::ShowStatus)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transaction_statements() {
+        assert_eq!(
+            parse("BEGIN; COMMIT; ROLLBACK;").unwrap(),
+            vec![
+                Query::DBStmt(parser::DBStatement::Begin),
+                Query::DBStmt(parser::DBStatement::Commit),
+                Query::DBStmt(parser::DBStatement::Rollback),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replace_into() {
+        assert_eq!(
+            parse("REPLACE INTO t VALUES (1, 'a'), (2, NULL);").unwrap(),
+            vec![Query::TableStmt(parser::TableStatement::Replace(
+                "t".into(),
+                vec![
+                    vec![parser::Value::Integer(1), parser::Value::String("a".into())],
+                    vec![parser::Value::Integer(2), parser::Value::Null],
+                ],
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_insert_on_duplicate_key_update() {
+        assert_eq!(
+            parse("INSERT INTO t VALUES (1, 'a') ON DUPLICATE KEY UPDATE name = 'b', n = 2;")
+                .unwrap(),
+            vec![Query::TableStmt(parser::TableStatement::Upsert(
+                "t".into(),
+                vec![vec![
+                    parser::Value::Integer(1),
+                    parser::Value::String("a".into())
+                ]],
+                vec![
+                    ("name".into(), parser::Value::String("b".into())),
+                    ("n".into(), parser::Value::Integer(2)),
+                ],
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_quoted_identifiers() {
+        let clause = match parse("SELECT `order`, \"my col\" FROM `my-table` WHERE `order` > 1;")
+            .unwrap()
+            .pop()
+        {
+            Some(Query::TableStmt(parser::TableStatement::Select(clause))) => clause,
+            other => panic!("unexpected statement {:?}", other),
+        };
+        let column = |name: &str| TableColumn {
+            table: None,
+            column: name.into(),
+        };
+        assert_eq!(
+            clause.selectors,
+            parser::Selectors::List(vec![
+                parser::Selector::Column(column("order")),
+                parser::Selector::Column(column("my col")),
+            ])
+        );
+        assert_eq!(clause.table, vec!["my-table".to_string()]);
+        assert_eq!(
+            clause.where_clauses,
+            vec![WhereClause::Op(
+                column("order"),
+                parser::Operator::Gt,
+                parser::Expression::Value(parser::Value::Integer(1)),
+            )]
+        );
+        // Quotes are still required for keywords
+        assert!(parse("CREATE TABLE t (ORDER INT);").is_err());
+        assert!(parse("CREATE TABLE t (`ORDER` INT);").is_ok());
+    }
+
+    #[test]
+    fn test_cast() {
+        let clause = match parse(
+            "SELECT CAST(code AS INT) FROM t WHERE CAST(code AS FLOAT) > CAST(limit_str AS FLOAT) + 1;",
+        )
+        .unwrap()
+        .pop()
+        {
+            Some(Query::TableStmt(parser::TableStatement::Select(clause))) => clause,
+            other => panic!("unexpected statement {:?}", other),
+        };
+        let column = |name: &str| TableColumn {
+            table: None,
+            column: name.into(),
+        };
+        assert_eq!(
+            clause.selectors,
+            parser::Selectors::List(vec![parser::Selector::Cast(column("code"), CastType::Int)])
+        );
+        assert_eq!(
+            clause.where_clauses,
+            vec![WhereClause::CastOp(
+                column("code"),
+                CastType::Float,
+                Operator::Gt,
+                Expression::Arithmetic(
+                    Box::new(Expression::Cast(
+                        Box::new(Expression::Column(column("limit_str"))),
+                        CastType::Float
+                    )),
+                    parser::ArithmeticOperator::Add,
+                    Box::new(Expression::Value(Value::Integer(1)))
+                )
+            )]
+        );
+        assert!(parse("SELECT * FROM t WHERE CAST(code AS VARCHAR(3)) = 1;").is_err());
+    }
+
+    #[test]
+    fn test_create_table_as_select() {
+        let result =
+            parse("CREATE TABLE backup AS SELECT id, name FROM users WHERE id > 3;").unwrap();
+        assert_eq!(
+            result,
+            vec![Query::TableStmt(parser::TableStatement::CreateTableAs(
+                "backup".into(),
+                SelectClause {
+                    selectors: parser::Selectors::List(vec![
+                        parser::Selector::Column(TableColumn {
+                            table: None,
+                            column: "id".into()
+                        }),
+                        parser::Selector::Column(TableColumn {
+                            table: None,
+                            column: "name".into()
+                        }),
+                    ]),
+                    table: vec!["users".into()],
+                    table_aliases: vec![None],
+                    selector_aliases: vec![None, None],
+                    where_clauses: vec![WhereClause::Op(
+                        TableColumn {
+                            table: None,
+                            column: "id".into()
+                        },
+                        parser::Operator::Gt,
+                        parser::Expression::Value(parser::Value::Integer(3))
+                    )],
+                    group_by: vec![],
+                    having: Vec::new(),
+                    order_by: vec![],
+                    limit: None,
+                    limit_percent: false,
+                    offset: None,
+                }
+            ))]
+        );
+        assert!(parse("CREATE TABLE backup AS users;").is_err());
+    }
+
+    #[test]
+    fn test_create_table_default_null() {
+        let result = parse("CREATE TABLE t (a INT DEFAULT NULL, b INT);").unwrap();
+        assert_eq!(
+            result,
+            vec![Query::TableStmt(parser::TableStatement::CreateTable(
+                "t".into(),
+                vec![
+                    parser::CreateTableField::Col(
+                        "a".into(),
+                        parser::ColumnType::Int,
+                        false,
+                        Some(parser::DefaultValue::Literal(parser::Value::Null)),
+                        false
+                    ),
+                    parser::CreateTableField::Col(
+                        "b".into(),
+                        parser::ColumnType::Int,
+                        false,
+                        None,
+                        false
+                    ),
+                ]
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_boolean_columns_and_literals() {
+        let result = parse(
+            "CREATE TABLE t (a BOOLEAN, b BOOL NOT NULL DEFAULT TRUE); INSERT INTO t VALUES (FALSE, TRUE);",
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            vec![
+                Query::TableStmt(parser::TableStatement::CreateTable(
+                    "t".into(),
+                    vec![
+                        parser::CreateTableField::Col(
+                            "a".into(),
+                            parser::ColumnType::Bool,
+                            false,
+                            None,
+                            false
+                        ),
+                        parser::CreateTableField::Col(
+                            "b".into(),
+                            parser::ColumnType::Bool,
+                            true,
+                            Some(parser::DefaultValue::Literal(parser::Value::Bool(true))),
+                            false
+                        ),
+                    ]
+                )),
+                Query::TableStmt(parser::TableStatement::InsertInto(
+                    "t".into(),
+                    vec![vec![parser::Value::Bool(false), parser::Value::Bool(true)]]
+                )),
+            ]
+        );
+    }
+
     #[test]
     fn test_table_stmt_create() {
         let query = "CREATE TABLE my_table (
@@ -393,19 +1533,24 @@ CREATE DATABASE test_db; -- Trailing Annotation
                         "id".into(),
                         parser::ColumnType::Int,
                         true,
-                        parser::Value::Integer(0)
+                        Some(parser::DefaultValue::Literal(parser::Value::Integer(0))),
+                        false
                     ),
                     parser::CreateTableField::Col(
                         "name".into(),
                         parser::ColumnType::Char(100),
                         false,
-                        parser::Value::String("unknown".into())
+                        Some(parser::DefaultValue::Literal(parser::Value::String(
+                            "unknown".into()
+                        ))),
+                        false
                     ),
                     parser::CreateTableField::Col(
                         "score".into(),
                         parser::ColumnType::Float,
                         false,
-                        parser::Value::Null
+                        None,
+                        false
                     ),
                     parser::CreateTableField::Pkey(Box::new(AlterStatement::AddPKey(
                         String::default(),
@@ -422,4 +1567,45 @@ CREATE DATABASE test_db; -- Trailing Annotation
             ))]
         );
     }
+
+    #[test]
+    fn test_table_stmt_create_inline_unique() {
+        let query = "CREATE TABLE my_table (
+            id INT NOT NULL,
+            name VARCHAR(100) DEFAULT 'unknown' UNIQUE,
+            score FLOAT UNIQUE
+        );";
+
+        assert_eq!(
+            parse(query).unwrap(),
+            vec![Query::TableStmt(parser::TableStatement::CreateTable(
+                "my_table".into(),
+                vec![
+                    parser::CreateTableField::Col(
+                        "id".into(),
+                        parser::ColumnType::Int,
+                        true,
+                        None,
+                        false
+                    ),
+                    parser::CreateTableField::Col(
+                        "name".into(),
+                        parser::ColumnType::Char(100),
+                        false,
+                        Some(parser::DefaultValue::Literal(parser::Value::String(
+                            "unknown".into()
+                        ))),
+                        true
+                    ),
+                    parser::CreateTableField::Col(
+                        "score".into(),
+                        parser::ColumnType::Float,
+                        false,
+                        None,
+                        true
+                    ),
+                ]
+            ))]
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use chumsky::{input::Emitter, prelude::*};
 
 use crate::lexer_parser::{KeywordEnum as K, SQLToken as T};
+use crate::record::MAX_DECIMAL_PRECISION;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum DBStatement {
@@ -10,17 +11,35 @@ pub enum DBStatement {
     // DROP DATABASE Identifier
     DropDatabase(String),
 
-    // SHOW DATABASES
-    ShowDatabases,
+    // SHOW DATABASES ( LIKE 'pattern' )?
+    ShowDatabases(Option<String>),
 
     // USE Identifier
     UseDatabase(String),
 
-    // SHOW TABLES
-    ShowTables,
+    // SHOW TABLES ( LIKE 'pattern' )?
+    ShowTables(Option<String>),
 
     // SHOW INDEXES
     ShowIndexes,
+
+    // FLUSH | CHECKPOINT
+    Flush,
+
+    // SHOW STATUS
+    ShowStatus,
+
+    // FLUSH STATUS
+    FlushStatus,
+
+    // BEGIN
+    Begin,
+
+    // COMMIT
+    Commit,
+
+    // ROLLBACK
+    Rollback,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -28,7 +47,10 @@ pub enum Value {
     Float(f64),
     Integer(i64),
     String(String),
+    Bool(bool),
     Null,
+    /// `?` parameter, bound to a value before the statement runs
+    Placeholder(usize),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -36,6 +58,16 @@ pub enum ColumnType {
     Int,
     Float,
     Char(usize),
+    /// DECIMAL(precision, scale)
+    Decimal(u8, u8),
+    Bool,
+}
+
+/// Target type of a `CAST(... AS type)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastType {
+    Int,
+    Float,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -46,6 +78,17 @@ pub enum Operator {
     Lt,
     Ge,
     Le,
+    // <=>: equality under which NULL equals NULL
+    NullSafeEq,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArithmeticOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -58,17 +101,28 @@ pub struct TableColumn {
 pub enum Expression {
     Value(Value),
     Column(TableColumn),
+    Arithmetic(Box<Expression>, ArithmeticOperator, Box<Expression>),
+    Cast(Box<Expression>, CastType),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum WhereClause {
     Op(TableColumn, Operator, Expression),
+    /// `CAST(column AS type) operator expression`
+    CastOp(TableColumn, CastType, Operator, Expression),
     // OpSubClause(TableColumn, Operator, Box<SelectClause>),
     Null(TableColumn),
     NotNull(TableColumn),
     In(TableColumn, Vec<Value>),
     // InSubClause(TableColumn, Box<SelectClause>),
-    Like(TableColumn, String),
+    /// `column LIKE 'pattern' (ESCAPE 'c')?`, with the escape character if one is given
+    Like(TableColumn, String, Option<char>),
+    /// `column NOT LIKE 'pattern' (ESCAPE 'c')?`
+    NotLike(TableColumn, String, Option<char>),
+    /// `literal operator literal`, folded to true or false before any row is read
+    Constant(Value, Operator, Value),
+    /// `a OR b OR ...`, where each alternative is itself an AND list
+    Or(Vec<Vec<WhereClause>>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -80,6 +134,7 @@ pub enum Selector {
     Max(TableColumn),
     Min(TableColumn),
     Sum(TableColumn),
+    Cast(TableColumn, CastType),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -91,17 +146,39 @@ pub enum Selectors {
 #[derive(Debug, Clone, PartialEq)]
 pub struct SelectClause {
     pub selectors: Selectors,
+    // alias given with AS after each selector; a missing entry means the selector has no alias
+    pub selector_aliases: Vec<Option<String>>,
     pub table: Vec<String>,
+    // alias given after each table in FROM; a missing entry means the table has no alias
+    pub table_aliases: Vec<Option<String>>,
     pub where_clauses: Vec<WhereClause>,
-    pub group_by: Option<TableColumn>,
-    pub order_by: Option<(TableColumn, bool)>, // bool: true for ASC, false for DESC
+    // GROUP BY columns; empty when the query has no GROUP BY
+    pub group_by: Vec<TableColumn>,
+    // HAVING conditions, all of which a group must meet: aggregate operator literal
+    pub having: Vec<(Selector, Operator, Value)>,
+    // columns or aggregates to sort by, most significant first; bool: true for ASC, false for DESC
+    pub order_by: Vec<(Selector, bool)>,
     pub limit: Option<usize>,
+    // LIMIT n PERCENT or TOP n PERCENT: limit is a percentage of the result rows
+    pub limit_percent: bool,
     pub offset: Option<usize>,
 }
 
+/// The value after DEFAULT in a column definition
+#[derive(Debug, Clone, PartialEq)]
+pub enum DefaultValue {
+    Literal(Value),
+    // Evaluated each time a row is inserted
+    CurrentDate,
+    CurrentTimestamp,
+    /// `AUTO_INCREMENT`: the table's next counter value
+    AutoIncrement,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum CreateTableField {
-    Col(String, ColumnType, bool, Value),
+    // name, type, NOT NULL, DEFAULT (None when no DEFAULT clause was given), UNIQUE
+    Col(String, ColumnType, bool, Option<DefaultValue>, bool),
     Pkey(Box<AlterStatement>),
     Fkey(Box<AlterStatement>),
 }
@@ -111,32 +188,60 @@ pub enum TableStatement {
     // CREATE TABLE Identifier ( field_list )
     CreateTable(String, Vec<CreateTableField>),
 
+    // CREATE TABLE Identifier AS select_clause
+    CreateTableAs(String, SelectClause),
+
     // DROP TABLE Identifier
     DropTable(String),
 
     // DESC Identifier
     DescribeTable(String),
 
+    // SHOW COLUMNS FROM Identifier ( LIKE 'pattern' )?
+    ShowColumns(String, Option<String>),
+
     // LOAD DATA INFILE 'file_path' INTO TABLE Identifier FIELDS TERMINATED BY 'delimiter'
     LoadDataInfile(String, String, char),
 
     // INSERT INTO Identifier VALUES value_lists
     InsertInto(String, Vec<Vec<Value>>),
 
-    // DELETE FROM Identifier
-    DeleteFrom(String, Option<Vec<WhereClause>>),
+    // REPLACE INTO Identifier VALUES value_lists
+    Replace(String, Vec<Vec<Value>>),
+
+    // INSERT INTO Identifier VALUES value_lists ON DUPLICATE KEY UPDATE set_clause
+    Upsert(String, Vec<Vec<Value>>, Vec<(String, Value)>),
+
+    // DELETE FROM Identifier where_clause? (RETURNING *)?
+    DeleteFrom(String, Option<Vec<WhereClause>>, bool),
 
     // UPDATE Identifier SET set_clause where_clause?
     Update(String, Vec<(String, Value)>, Option<Vec<WhereClause>>),
 
     // select_clause
     Select(SelectClause),
+
+    // EXPLAIN select_clause
+    Explain(SelectClause),
+
+    // select_clause INTO OUTFILE String (FIELDS TERMINATED BY String)? (WITH HEADER)?
+    // file path, delimiter (',' when not given), whether to write a header line
+    SelectIntoOutfile(SelectClause, String, char, bool),
+
+    // REINDEX TABLE Identifier
+    ReindexTable(String),
+
+    // REINDEX INDEX Identifier
+    ReindexIndex(String),
+
+    // CHECK TABLE Identifier
+    CheckTable(String),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AlterStatement {
-    // ALTER TABLE Identifier ADD INDEX Identifier? ( field_list )
-    AddIndex(String, Option<String>, Vec<String>),
+    // ALTER TABLE Identifier ADD INDEX Identifier? ( field_list ) ( WITH ( order = Integer ) )?
+    AddIndex(String, Option<String>, Vec<String>, Option<usize>),
 
     // ALTER TABLE Identifier DROP INDEX Identifier
     DropIndex(String, String),
@@ -152,6 +257,9 @@ pub enum AlterStatement {
 
     // ALTER TABLE Identifier ADD FOREIGN KEY Identifier? ( field_list ) REFERENCES Identifier ( field_list )
     AddFKey(String, Option<String>, Vec<String>, String, Vec<String>),
+
+    // ALTER TABLE Identifier MODIFY COLUMN? Identifier type
+    ModifyColumn(String, String, ColumnType),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -168,6 +276,74 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
         select! { T::Identifier(name) => name }
     }
 
+    /// A word the grammar only looks for in one spot, matched in any case;
+    /// it is not a keyword, so it stays usable as a table or column name
+    fn contextual<'a>(
+        word: &'static str,
+    ) -> impl Parser<'a, &'a [T<'a>], (), extra::Err<Rich<'a, T<'a>>>> + Clone {
+        select! { T::Identifier(name) if name.eq_ignore_ascii_case(word) => () }
+    }
+
+    /// Apply a prefix minus; number literals are folded, anything else
+    /// becomes `0 - operand`
+    fn negate(operand: Expression) -> Expression {
+        match operand {
+            Expression::Value(Value::Integer(i)) if i != i64::MIN => {
+                Expression::Value(Value::Integer(-i))
+            }
+            Expression::Value(Value::Float(f)) => Expression::Value(Value::Float(-f)),
+            operand => Expression::Arithmetic(
+                Box::new(Expression::Value(Value::Integer(0))),
+                ArithmeticOperator::Sub,
+                Box::new(operand),
+            ),
+        }
+    }
+
+    fn column_type<'a>() -> impl Parser<'a, &'a [T<'a>], ColumnType, extra::Err<Rich<'a, T<'a>>>> {
+        choice((
+            just(T::Keyword(K::Int)).to(ColumnType::Int),
+            just(T::Keyword(K::Float)).to(ColumnType::Float),
+            just(T::Keyword(K::Boolean)).to(ColumnType::Bool),
+            just(T::Keyword(K::Varchar))
+                .ignore_then(
+                    select! { T::Integer(i) => i as usize }
+                        .delimited_by(just(T::Symbol('(')), just(T::Symbol(')'))),
+                )
+                .map(ColumnType::Char),
+            // DECIMAL, DECIMAL(p) or DECIMAL(p, s); bare DECIMAL is DECIMAL(10, 0)
+            just(T::Keyword(K::Decimal))
+                .ignore_then(
+                    select! { T::Integer(i) => i }
+                        .then(
+                            just(T::Symbol(','))
+                                .ignore_then(select! { T::Integer(i) => i })
+                                .or_not(),
+                        )
+                        .delimited_by(just(T::Symbol('(')), just(T::Symbol(')')))
+                        .or_not(),
+                )
+                .try_map(|spec, span| {
+                    let (precision, scale) = match spec {
+                        Some((precision, scale)) => (precision, scale.unwrap_or(0)),
+                        None => (10, 0),
+                    };
+                    if !(1..=i64::from(MAX_DECIMAL_PRECISION)).contains(&precision)
+                        || !(0..=precision).contains(&scale)
+                    {
+                        return Err(Rich::custom(
+                            span,
+                            format!(
+                                "DECIMAL({}, {}) needs a precision of 1 to {} and a scale of 0 to the precision",
+                                precision, scale, MAX_DECIMAL_PRECISION
+                            ),
+                        ));
+                    }
+                    Ok(ColumnType::Decimal(precision as u8, scale as u8))
+                }),
+        ))
+    }
+
     fn db_statement<'a>() -> impl Parser<'a, &'a [T<'a>], DBStatement, extra::Err<Rich<'a, T<'a>>>>
     {
         let create_db = just([T::Keyword(K::Create), T::Keyword(K::Database)])
@@ -178,19 +354,42 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
             .ignore_then(identifier())
             .map(|db_name| DBStatement::DropDatabase(db_name.into()));
 
-        let show_dbs =
-            just([T::Keyword(K::Show), T::Keyword(K::Databases)]).to(DBStatement::ShowDatabases);
+        let like_pattern = just(T::Keyword(K::Like))
+            .ignore_then(select! { T::String(s) => s.into() })
+            .or_not();
+
+        let show_dbs = just([T::Keyword(K::Show), T::Keyword(K::Databases)])
+            .ignore_then(like_pattern.clone())
+            .map(DBStatement::ShowDatabases);
 
         let use_db = just(T::Keyword(K::Use))
             .ignore_then(identifier())
             .map(|db_name| DBStatement::UseDatabase(db_name.into()));
 
-        let show_tables =
-            just([T::Keyword(K::Show), T::Keyword(K::Tables)]).to(DBStatement::ShowTables);
+        let show_tables = just([T::Keyword(K::Show), T::Keyword(K::Tables)])
+            .ignore_then(like_pattern)
+            .map(DBStatement::ShowTables);
 
         let show_indexes =
             just([T::Keyword(K::Show), T::Keyword(K::Indexes)]).to(DBStatement::ShowIndexes);
 
+        let show_status = just(T::Keyword(K::Show))
+            .ignore_then(contextual("status"))
+            .to(DBStatement::ShowStatus);
+
+        let flush_status = just(T::Keyword(K::Flush))
+            .ignore_then(contextual("status"))
+            .to(DBStatement::FlushStatus);
+
+        let flush = choice((just(T::Keyword(K::Flush)), just(T::Keyword(K::Checkpoint))))
+            .to(DBStatement::Flush);
+
+        let transaction = choice((
+            just(T::Keyword(K::Begin)).to(DBStatement::Begin),
+            just(T::Keyword(K::Commit)).to(DBStatement::Commit),
+            just(T::Keyword(K::Rollback)).to(DBStatement::Rollback),
+        ));
+
         choice((
             create_db,
             drop_db,
@@ -198,6 +397,10 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
             use_db,
             show_tables,
             show_indexes,
+            show_status,
+            flush_status,
+            flush,
+            transaction,
         ))
         .boxed()
     }
@@ -223,12 +426,30 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
                     .delimited_by(just(T::Symbol('(')), just(T::Symbol(')')))
                     .boxed(),
             )
+            // ( WITH ( order = Integer ) )?
+            .then(
+                just(T::Keyword(K::With))
+                    .ignore_then(
+                        choice((
+                            just(T::Keyword(K::Order)).ignored(),
+                            select! { T::Identifier(s) if s.eq_ignore_ascii_case("order") => () },
+                        ))
+                        .ignore_then(just(T::Symbol('=')))
+                        .ignore_then(select! { T::Integer(i) if i >= 0 => i as usize })
+                        .delimited_by(just(T::Symbol('(')), just(T::Symbol(')'))),
+                    )
+                    .or_not(),
+            )
             .map(
-                |((table_ident, index_name), fields): ((&str, Option<&str>), Vec<&str>)| {
+                |(((table_ident, index_name), fields), order): (
+                    ((&str, Option<&str>), Vec<&str>),
+                    Option<usize>,
+                )| {
                     AlterStatement::AddIndex(
                         table_ident.into(),
                         index_name.map(|s| s.into()),
                         fields.into_iter().map(|s| s.into()).collect(),
+                        order,
                     )
                 },
             );
@@ -298,6 +519,21 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
                 )
             });
 
+        let modify_column = alter_table
+            .clone()
+            // MODIFY COLUMN? Identifier type
+            .then(
+                just(T::Keyword(K::Modify))
+                    .ignore_then(just(T::Keyword(K::Column)).or_not())
+                    .ignore_then(identifier())
+                    .then(column_type()),
+            )
+            .map(
+                |(table_ident, (column, column_type)): (&str, (&str, ColumnType))| {
+                    AlterStatement::ModifyColumn(table_ident.into(), column.into(), column_type)
+                },
+            );
+
         let add_fkey = alter_table
             // ADD (CONSTRAINT Identifier)? FOREIGN KEY Identifier?
             .then(
@@ -362,7 +598,13 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
             );
 
         choice((
-            add_index, drop_index, drop_pkey, drop_fkey, add_pkey, add_fkey,
+            add_index,
+            drop_index,
+            drop_pkey,
+            drop_fkey,
+            add_pkey,
+            add_fkey,
+            modify_column,
         ))
         .boxed()
     }
@@ -373,13 +615,26 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
 
     fn table_statement<'a>()
     -> impl Parser<'a, &'a [T<'a>], TableStatement, extra::Err<Rich<'a, T<'a>>>> {
+        // Comparison operators: =, <=>, <>, !=, <, <=, >, >=.
         // Match multi-character operators before single-character ones to avoid
         // prematurely consuming '>' or '<' and leaving a trailing '=' that
-        // causes a parse error on expressions like ">= 0".
+        // causes a parse error on expressions like ">= 0". '==' is rejected
+        // with an explicit message instead of a generic token error.
         let operator = choice((
+            just([T::Symbol('<'), T::Symbol('='), T::Symbol('>')]).to(Operator::NullSafeEq),
             just([T::Symbol('>'), T::Symbol('=')]).to(Operator::Ge),
             just([T::Symbol('<'), T::Symbol('=')]).to(Operator::Le),
             just([T::Symbol('<'), T::Symbol('>')]).to(Operator::Ne),
+            just([T::Symbol('!'), T::Symbol('=')]).to(Operator::Ne),
+            just([T::Symbol('='), T::Symbol('=')]).validate(
+                |_, map, emitter: &mut Emitter<Rich<T<'a>>>| {
+                    emitter.emit(Rich::custom(
+                        map.span(),
+                        "'==' is not a comparison operator, use '='".to_string(),
+                    ));
+                    Operator::Eq
+                },
+            ),
             just(T::Symbol('=')).to(Operator::Eq),
             just(T::Symbol('>')).to(Operator::Gt),
             just(T::Symbol('<')).to(Operator::Lt),
@@ -396,14 +651,26 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
             })
             .boxed();
 
-        let value = select! {
-            T::Integer(i) => Value::Integer(i),
+        // The lexer leaves signs out of number literals; `9223372036854775808`
+        // arrives as Integer(i64::MIN) and is only valid after a minus
+        let negative_number = just(T::Symbol('-')).ignore_then(select! {
+            T::Integer(i) if i == i64::MIN => Value::Integer(i64::MIN),
+            T::Integer(i) => Value::Integer(-i),
+            T::Float(f) => Value::Float(-f),
+        });
+        let literal = select! {
+            T::Integer(i) if i != i64::MIN => Value::Integer(i),
             T::Float(f) => Value::Float(f),
             T::String(s) => Value::String(s.into()),
+            T::Keyword(K::True) => Value::Bool(true),
+            T::Keyword(K::False) => Value::Bool(false),
             T::Keyword(K::Null) => Value::Null,
+            T::Placeholder(n) => Value::Placeholder(n),
         };
+        let value = negative_number.or(literal).boxed();
 
         let value_list = value
+            .clone()
             .separated_by(just(T::Symbol(',')))
             .collect::<Vec<Value>>()
             .delimited_by(just(T::Symbol('(')), just(T::Symbol(')')))
@@ -431,10 +698,81 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
             just(T::Keyword(K::Desc)).to(false),
         ));
 
-        let expression = choice((
-            value.map(Expression::Value),
-            table_column.clone().map(Expression::Column),
-        ))
+        let cast_type = choice((
+            just(T::Keyword(K::Int)).to(CastType::Int),
+            just(T::Keyword(K::Float)).to(CastType::Float),
+        ));
+
+        // CAST ( column AS type )
+        let column_cast = just(T::Keyword(K::Cast))
+            .ignore_then(
+                table_column
+                    .clone()
+                    .then_ignore(just(T::Keyword(K::As)))
+                    .then(cast_type.clone())
+                    .delimited_by(just(T::Symbol('(')), just(T::Symbol(')'))),
+            )
+            .boxed();
+
+        // expression := term (('+' | '-') term)*
+        // term       := unary (('*' | '/' | '%') unary)*
+        // unary      := '-' unary | atom
+        // atom       := value | column | CAST ( expression AS type ) | ( expression )
+        let expression = recursive(|expression| {
+            let cast = just(T::Keyword(K::Cast))
+                .ignore_then(
+                    expression
+                        .clone()
+                        .then_ignore(just(T::Keyword(K::As)))
+                        .then(cast_type.clone())
+                        .delimited_by(just(T::Symbol('(')), just(T::Symbol(')'))),
+                )
+                .map(|(expr, ty)| Expression::Cast(Box::new(expr), ty));
+
+            let atom = choice((
+                value.clone().map(Expression::Value),
+                cast,
+                table_column.clone().map(Expression::Column),
+                expression.delimited_by(just(T::Symbol('(')), just(T::Symbol(')'))),
+            ))
+            .boxed();
+
+            // `-9223372036854775808` fails as a minus applied to an out-of-range
+            // literal and is then read by `atom` as a negative number instead
+            let unary = recursive(|unary| {
+                choice((just(T::Symbol('-')).ignore_then(unary).map(negate), atom))
+            })
+            .boxed();
+
+            let term = unary
+                .clone()
+                .foldl(
+                    choice((
+                        just(T::Symbol('*')).to(ArithmeticOperator::Mul),
+                        just(T::Symbol('/')).to(ArithmeticOperator::Div),
+                        just(T::Symbol('%')).to(ArithmeticOperator::Mod),
+                    ))
+                    .then(unary)
+                    .repeated(),
+                    |left, (op, right)| Expression::Arithmetic(Box::new(left), op, Box::new(right)),
+                )
+                .boxed();
+
+            term.clone()
+                .foldl(
+                    choice((
+                        just(T::Symbol('+'))
+                            .to(ArithmeticOperator::Add)
+                            .then(term.clone()),
+                        just(T::Symbol('-'))
+                            .to(ArithmeticOperator::Sub)
+                            .then(term.clone()),
+                    ))
+                    .repeated(),
+                    |left, (op, right)| Expression::Arithmetic(Box::new(left), op, Box::new(right)),
+                )
+                .boxed()
+        })
         .boxed();
 
         let where_and_clause = {
@@ -445,6 +783,13 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
                 .then(expression.clone())
                 .map(|((col, op), expr)| WhereClause::Op(col, op, expr));
 
+            // CAST ( column AS type ) operator expression
+            let cast_op_expr = column_cast
+                .clone()
+                .then(operator.clone())
+                .then(expression.clone())
+                .map(|(((col, ty), op), expr)| WhereClause::CastOp(col, ty, op, expr));
+
             // column IS NULL
             let is_null = table_column
                 .clone()
@@ -468,20 +813,80 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
                 .then(value_list.clone())
                 .map(|(col, vals)| WhereClause::In(col, vals));
 
-            // column LIKE 'pattern'
+            // column NOT? LIKE 'pattern' (ESCAPE 'c')?
+            let escape = just(T::Keyword(K::Escape))
+                .ignore_then(select! { T::String(s) => s })
+                .try_map(|s, span| {
+                    let mut chars = s.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Ok(c),
+                        _ => Err(Rich::custom(
+                            span,
+                            "ESCAPE must be a single character".to_string(),
+                        )),
+                    }
+                });
             let like_clause = table_column
                 .clone()
+                .then(just(T::Keyword(K::Not)).or_not())
                 .then_ignore(just(T::Keyword(K::Like)))
                 .then(select! { T::String(s) => s.into() })
-                .map(|(col, s)| WhereClause::Like(col, s));
+                .then(escape.or_not())
+                .map(|(((col, not), s), escape)| match not {
+                    Some(_) => WhereClause::NotLike(col, s, escape),
+                    None => WhereClause::Like(col, s, escape),
+                });
+
+            // literal operator literal, e.g. the `1 = 1` of generated SQL
+            let constant = value
+                .clone()
+                .then(operator.clone())
+                .then(value.clone())
+                .map(|((left, op), right)| WhereClause::Constant(left, op, right));
+
+            let predicate = choice((
+                op_expr,
+                cast_op_expr,
+                is_null,
+                not_null,
+                in_clause,
+                like_clause,
+                constant,
+            ))
+            .boxed();
 
-            just(T::Keyword(K::Where))
-                .ignore_then(
-                    choice((op_expr, is_null, not_null, in_clause, like_clause))
-                        .separated_by(just(T::Keyword(K::And)))
-                        .collect(),
-                )
-                .boxed()
+            // condition   := conjunction ('OR' conjunction)*
+            // conjunction := primary ('AND' primary)*
+            // primary     := predicate | ( condition )
+            //
+            // A condition is the AND list of its clauses; ORs become a single
+            // `WhereClause::Or` holding one AND list per alternative.
+            let condition = recursive(|condition| {
+                let primary = choice((
+                    condition.delimited_by(just(T::Symbol('(')), just(T::Symbol(')'))),
+                    predicate.map(|clause| vec![clause]),
+                ));
+
+                let conjunction = primary
+                    .separated_by(just(T::Keyword(K::And)))
+                    .at_least(1)
+                    .collect::<Vec<Vec<WhereClause>>>()
+                    .map(|groups| groups.concat());
+
+                conjunction
+                    .separated_by(just(T::Keyword(K::Or)))
+                    .at_least(1)
+                    .collect::<Vec<Vec<WhereClause>>>()
+                    .map(|mut alternatives| {
+                        if alternatives.len() == 1 {
+                            alternatives.pop().unwrap()
+                        } else {
+                            vec![WhereClause::Or(alternatives)]
+                        }
+                    })
+            });
+
+            just(T::Keyword(K::Where)).ignore_then(condition).boxed()
         };
 
         // DROP TABLE Identifier
@@ -494,11 +899,52 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
             .ignore_then(identifier())
             .map(|table_name| TableStatement::DescribeTable(table_name.into()));
 
-        // INSERT INTO Identifier VALUES value_lists
+        // SHOW COLUMNS FROM Identifier ( LIKE 'pattern' )?
+        let show_columns = just([
+            T::Keyword(K::Show),
+            T::Keyword(K::Columns),
+            T::Keyword(K::From),
+        ])
+        .ignore_then(identifier())
+        .then(
+            just(T::Keyword(K::Like))
+                .ignore_then(select! { T::String(s) => s.into() })
+                .or_not(),
+        )
+        .map(|(table_name, pattern)| TableStatement::ShowColumns(table_name.into(), pattern));
+
+        let set_clause = identifier()
+            .then_ignore(just(T::Symbol('=')))
+            .then(value.clone())
+            .map(|(name, value)| (name.into(), value))
+            .separated_by(just(T::Symbol(',')))
+            .collect()
+            .boxed();
+
+        // INSERT INTO Identifier VALUES value_lists (ON DUPLICATE KEY UPDATE set_clause)?
         let insert_into_table = just([T::Keyword(K::Insert), T::Keyword(K::Into)])
+            .ignore_then(identifier())
+            .then(just(T::Keyword(K::Values)).ignore_then(value_lists.clone()))
+            .then(
+                just([
+                    T::Keyword(K::On),
+                    T::Keyword(K::Duplicate),
+                    T::Keyword(K::Key),
+                    T::Keyword(K::Update),
+                ])
+                .ignore_then(set_clause.clone())
+                .or_not(),
+            )
+            .map(|((table_name, vals), updates)| match updates {
+                Some(updates) => TableStatement::Upsert(table_name.into(), vals, updates),
+                None => TableStatement::InsertInto(table_name.into(), vals),
+            })
+            .boxed();
+
+        let replace_into_table = just([T::Keyword(K::Replace), T::Keyword(K::Into)])
             .ignore_then(identifier())
             .then(just(T::Keyword(K::Values)).ignore_then(value_lists))
-            .map(|(table_name, vals)| TableStatement::InsertInto(table_name.into(), vals))
+            .map(|(table_name, vals)| TableStatement::Replace(table_name.into(), vals))
             .boxed();
 
         // LOAD DATA INFILE 'file_path' INTO TABLE Identifier FIELDS TERMINATED BY 'delimiter'
@@ -515,9 +961,9 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
             T::Keyword(K::Terminated),
             T::Keyword(K::By),
         ]))
-        .then(select! { T::String(s) => s })
+        .then(select! { T::String(s) => s.into() })
         .validate(
-            |((file_path, table_name), delimiter): ((String, &str), &str),
+            |((file_path, table_name), delimiter): ((String, &str), String),
              _map,
              emitter: &mut Emitter<Rich<T<'a>>>| {
                 let delim_chars: Vec<char> = delimiter.chars().collect();
@@ -527,32 +973,31 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
                         "delimiter must be a single character".to_string(),
                     ));
                 }
-                TableStatement::LoadDataInfile(file_path, table_name.into(), delim_chars[0])
+                // An empty delimiter has already been reported above
+                let delimiter = delim_chars.first().copied().unwrap_or_default();
+                TableStatement::LoadDataInfile(file_path, table_name.into(), delimiter)
             },
         )
         .boxed();
 
-        // DELETE FROM Identifier ('WHERE' where_and_clause)?
+        // DELETE FROM Identifier ('WHERE' where_and_clause)? (RETURNING *)?
         let delete_from_table = just([T::Keyword(K::Delete), T::Keyword(K::From)])
             .ignore_then(identifier())
             .then(where_and_clause.clone().or_not())
-            .map(|(table_name, where_clause)| {
-                TableStatement::DeleteFrom(table_name.into(), where_clause)
+            .then(
+                just([T::Keyword(K::Returning), T::Symbol('*')])
+                    .or_not()
+                    .map(|returning| returning.is_some()),
+            )
+            .map(|((table_name, where_clause), returning)| {
+                TableStatement::DeleteFrom(table_name.into(), where_clause, returning)
             })
             .boxed();
 
-        let set_clause = identifier()
-            .then_ignore(just(T::Symbol('=')))
-            .then(value)
-            .map(|(name, value)| (name.into(), value))
-            .separated_by(just(T::Symbol(',')))
-            .collect()
-            .boxed();
-
         // UPDATE table SET set_clause WHERE where_and_clause
         let update_table = just(T::Keyword(K::Update))
             .ignore_then(identifier())
-            .then(just(T::Keyword(K::Set)).ignore_then(set_clause))
+            .then(just(T::Keyword(K::Set)).ignore_then(set_clause.clone()))
             .then(where_and_clause.clone().or_not())
             .map(
                 |((table_name, set_clause), where_clause): (
@@ -593,117 +1038,289 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
                 K::Sum => Selector::Sum(col),
                 _ => unreachable!(),
             }),
+            column_cast.map(|(col, ty)| Selector::Cast(col, ty)),
         ))
         .boxed();
 
+        // '*' | selector ('AS' Identifier)? (',' selector ('AS' Identifier)?)*
         let selectors = choice((
-            just(T::Symbol('*')).to(Selectors::All),
+            just(T::Symbol('*')).to((Selectors::All, Vec::new())),
             selector
+                .clone()
+                .then(
+                    just(T::Keyword(K::As))
+                        .ignore_then(identifier())
+                        .map(|alias| alias.to_string())
+                        .or_not(),
+                )
                 .separated_by(just(T::Symbol(',')))
-                .collect()
-                .map(Selectors::List),
+                .collect::<Vec<_>>()
+                .map(|selectors| {
+                    let (selectors, aliases) = selectors.into_iter().unzip();
+                    (Selectors::List(selectors), aliases)
+                }),
         ))
         .boxed();
 
-        // SELECT selectors
-        let select_table = just(T::Keyword(K::Select))
-            .ignore_then(selectors.clone())
-            // FROM identifiers
+        // Integer 'PERCENT'?
+        // (`TOP 5 percent FROM` selects a column named percent)
+        let row_count = select! { T::Integer(i) => i }
+            .then(
+                contextual("percent")
+                    .then_ignore(just(T::Keyword(K::From)).not())
+                    .or_not()
+                    .map(|p| p.is_some()),
+            )
+            .boxed();
+
+        // SELECT ('TOP' row_count)? selectors
+        let select_clause = just(T::Keyword(K::Select))
+            .ignore_then(contextual("top").ignore_then(row_count.clone()).or_not())
+            .then(selectors.clone())
+            // FROM table Identifier? (',' table Identifier?)*
+            // table := (Identifier '.')? Identifier, the prefix naming another database
             .then_ignore(just(T::Keyword(K::From)))
             .then(
                 identifier()
-                    .map(|s| s.to_string())
+                    .then(just(T::Symbol('.')).ignore_then(identifier()).or_not())
+                    .map(|(first, second)| match second {
+                        Some(table) => format!("{}.{}", first, table),
+                        None => first.to_string(),
+                    })
+                    // FETCH after the table starts the row limit, not an alias
+                    .then(
+                        identifier()
+                            .filter(|alias: &&str| !alias.eq_ignore_ascii_case("fetch"))
+                            .or_not(),
+                    )
+                    .map(|(table, alias)| (table, alias.map(|a| a.to_string())))
                     .separated_by(just(T::Symbol(',')))
-                    .collect::<Vec<String>>(),
+                    .collect::<Vec<(String, Option<String>)>>(),
             )
             // where_and_clause?
             .then(where_and_clause.clone().or_not())
-            // ('GROUP' 'BY' column)?
+            // ('GROUP' 'BY' column (',' column)*)?
             .then(
                 just([T::Keyword(K::Group), T::Keyword(K::By)])
-                    .ignore_then(table_column.clone())
+                    .ignore_then(
+                        table_column
+                            .clone()
+                            .separated_by(just(T::Symbol(',')))
+                            .at_least(1)
+                            .collect::<Vec<_>>(),
+                    )
+                    .or_not(),
+            )
+            // ('HAVING' selector operator value ('AND' selector operator value)*)?
+            .then(
+                just(T::Keyword(K::Having))
+                    .ignore_then(
+                        selector
+                            .clone()
+                            .then(operator.clone())
+                            .then(value.clone())
+                            .map(|((selector, op), value)| (selector, op, value))
+                            .separated_by(just(T::Keyword(K::And)))
+                            .at_least(1)
+                            .collect::<Vec<_>>(),
+                    )
                     .or_not(),
             )
             .boxed()
-            // ('ORDER' 'BY' column (order)?)?
+            // ('ORDER' 'BY' selector (order)? (',' selector (order)?)*)?
             .then(
                 just([T::Keyword(K::Order), T::Keyword(K::By)])
-                    .ignore_then(table_column.clone())
-                    .then(order.or_not())
+                    .ignore_then(
+                        selector
+                            .clone()
+                            .then(order.or_not())
+                            .separated_by(just(T::Symbol(',')))
+                            .at_least(1)
+                            .collect::<Vec<_>>(),
+                    )
                     .or_not(),
             )
-            // ('LIMIT' Integer ('OFFSET' Integer)?)?
+            // ('LIMIT' row_count ('OFFSET' Integer)?)?
+            // | ('FETCH' ('FIRST' | 'NEXT') Integer ('ROW' | 'ROWS') 'ONLY')
             .then(
-                just(T::Keyword(K::Limit))
-                    .ignore_then(select! { T::Integer(i) => i })
-                    .then(
-                        just(T::Keyword(K::Offset))
-                            .ignore_then(select! { T::Integer(i) => i })
-                            .or_not(),
-                    )
-                    .or_not()
-                    .boxed(),
+                choice((
+                    just(T::Keyword(K::Limit))
+                        .ignore_then(row_count.clone())
+                        .then(
+                            just(T::Keyword(K::Offset))
+                                .ignore_then(select! { T::Integer(i) => i })
+                                .or_not(),
+                        ),
+                    contextual("fetch")
+                        .ignore_then(contextual("first").or(contextual("next")))
+                        .ignore_then(select! { T::Integer(i) => i })
+                        .then_ignore(contextual("row").or(contextual("rows")))
+                        .then_ignore(contextual("only"))
+                        .map(|l| ((l, false), None)),
+                ))
+                .or_not()
+                .boxed(),
             )
             .boxed()
-            .map(
-                |(((((selectors, tables), where_clauses), group_by), order_by), limit_offset): (
+            .validate(
+                |(
                     (
                         (
-                            ((Selectors, Vec<String>), Option<Vec<WhereClause>>),
-                            Option<TableColumn>,
+                            (
+                                (((top, (selectors, selector_aliases)), tables), where_clauses),
+                                group_by,
+                            ),
+                            having,
                         ),
-                        Option<(TableColumn, Option<bool>)>,
+                        order_by,
                     ),
-                    Option<(i64, Option<i64>)>,
-                )| {
-                    let (limit, offset) = match limit_offset {
-                        Some((l, o)) => (Some(l), o),
-                        None => (None, None),
+                    limit_offset,
+                ): (
+                    (
+                        (
+                            (
+                                (
+                                    (
+                                        (Option<(i64, bool)>, (Selectors, Vec<Option<String>>)),
+                                        Vec<(String, Option<String>)>,
+                                    ),
+                                    Option<Vec<WhereClause>>,
+                                ),
+                                Option<Vec<TableColumn>>,
+                            ),
+                            Option<Vec<(Selector, Operator, Value)>>,
+                        ),
+                        Option<Vec<(Selector, Option<bool>)>>,
+                    ),
+                    Option<((i64, bool), Option<i64>)>,
+                ),
+                 _map,
+                 emitter: &mut Emitter<Rich<T<'a>>>| {
+                    if top.is_some() && limit_offset.is_some() {
+                        emitter.emit(Rich::custom(
+                            _map.span(),
+                            "TOP cannot be combined with LIMIT or FETCH FIRST".to_string(),
+                        ));
+                    }
+                    let (limit, offset) = match (top, limit_offset) {
+                        (_, Some((l, o))) => (Some(l), o),
+                        (Some(l), None) => (Some(l), None),
+                        (None, None) => (None, None),
                     };
-                    TableStatement::Select(SelectClause {
+                    if let Some((l, true)) = limit
+                        && l > 100
+                    {
+                        emitter.emit(Rich::custom(
+                            _map.span(),
+                            "percentage limit must be between 0 and 100".to_string(),
+                        ));
+                    }
+                    let (table, table_aliases) = tables.into_iter().unzip();
+                    SelectClause {
                         selectors,
-                        table: tables,
+                        selector_aliases,
+                        table,
+                        table_aliases,
                         where_clauses: where_clauses.unwrap_or_default(),
-                        group_by,
-                        order_by: order_by.map(|(col, asc)| (col, asc.unwrap_or(true))),
-                        limit: limit.map(|l| l as usize),
+                        group_by: group_by.unwrap_or_default(),
+                        having: having.unwrap_or_default(),
+                        order_by: order_by
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|(col, asc)| (col, asc.unwrap_or(true)))
+                            .collect(),
+                        limit: limit.map(|(l, _)| l as usize),
+                        limit_percent: limit.is_some_and(|(_, percent)| percent),
                         offset: offset.map(|o| o as usize),
-                    })
+                    }
                 },
             )
             .boxed();
 
-        let column_type = choice((
-            just(T::Keyword(K::Int)).to(ColumnType::Int),
-            just(T::Keyword(K::Float)).to(ColumnType::Float),
-            just(T::Keyword(K::Varchar))
-                .ignore_then(
-                    select! { T::Integer(i) => i as usize }
-                        .delimited_by(just(T::Symbol('(')), just(T::Symbol(')'))),
-                )
-                .map(ColumnType::Char),
-        ))
-        .boxed();
+        let select_table = select_clause
+            .clone()
+            .then(
+                just(T::Keyword(K::Into))
+                    .then_ignore(contextual("outfile"))
+                    .ignore_then(select! { T::String(s) => s.into() })
+                    .then(
+                        just([
+                            T::Keyword(K::Fields),
+                            T::Keyword(K::Terminated),
+                            T::Keyword(K::By),
+                        ])
+                        .ignore_then(select! { T::String(s) => s })
+                        .try_map(|delimiter, span| {
+                            let mut chars = delimiter.chars();
+                            match (chars.next(), chars.next()) {
+                                (Some(c), None) => Ok(c),
+                                _ => Err(Rich::custom(
+                                    span,
+                                    "delimiter must be a single character".to_string(),
+                                )),
+                            }
+                        })
+                        .or_not(),
+                    )
+                    .then(
+                        just(T::Keyword(K::With))
+                            .then_ignore(contextual("header"))
+                            .or_not()
+                            .map(|header| header.is_some()),
+                    )
+                    .or_not(),
+            )
+            .map(|(clause, outfile)| match outfile {
+                Some(((path, delimiter), header)) => TableStatement::SelectIntoOutfile(
+                    clause,
+                    path,
+                    delimiter.unwrap_or(','),
+                    header,
+                ),
+                None => TableStatement::Select(clause),
+            });
+
+        let explain_select = just(T::Keyword(K::Explain))
+            .ignore_then(select_clause.clone())
+            .map(TableStatement::Explain);
 
         let create_table_field = choice((
-            // Identifier type (NOT NULL)? (DEFAULT value)?
+            // Identifier type (NOT NULL)? (DEFAULT default_value | AUTO_INCREMENT)? (UNIQUE)?
+            // default_value := value | 'CURRENT_DATE' | 'CURRENT_TIMESTAMP'
             identifier()
-                .then(column_type)
+                .then(column_type())
                 .then(
                     just([T::Keyword(K::Not), T::Keyword(K::Null)])
                         .ignored()
                         .or_not(),
                 )
-                .then(just(T::Keyword(K::Default)).ignore_then(value).or_not())
+                .then(
+                    choice((
+                        just(T::Keyword(K::Default)).ignore_then(choice((
+                            value.clone().map(DefaultValue::Literal),
+                            just(T::Keyword(K::CurrentDate)).to(DefaultValue::CurrentDate),
+                            just(T::Keyword(K::CurrentTimestamp))
+                                .to(DefaultValue::CurrentTimestamp),
+                        ))),
+                        just(T::Keyword(K::AutoIncrement)).to(DefaultValue::AutoIncrement),
+                    ))
+                    .or_not(),
+                )
+                .then(just(T::Keyword(K::Unique)).ignored().or_not())
                 .map(
-                    |(((name, ctype), notnull), default_value): (
-                        ((&str, ColumnType), Option<()>),
-                        Option<Value>,
+                    |((((name, ctype), notnull), default_value), unique): (
+                        (((&str, ColumnType), Option<()>), Option<DefaultValue>),
+                        Option<()>,
                     )| {
                         let notnull = notnull.is_some();
-                        let default_value = default_value.unwrap_or(Value::Null);
 
-                        CreateTableField::Col(name.into(), ctype, notnull, default_value)
+                        CreateTableField::Col(
+                            name.into(),
+                            ctype,
+                            notnull,
+                            default_value,
+                            unique.is_some(),
+                        )
                     },
                 ),
             // PRIMARY KEY (Identifier)? ( identifiers )
@@ -778,15 +1395,46 @@ pub fn parser<'a>() -> impl Parser<'a, &'a [T<'a>], Vec<Query>, extra::Err<Rich<
             .map(|(table_name, fields)| TableStatement::CreateTable(table_name.into(), fields))
             .boxed();
 
+        // CREATE TABLE Identifier AS select_clause
+        let create_table_as = just([T::Keyword(K::Create), T::Keyword(K::Table)])
+            .ignore_then(identifier())
+            .then_ignore(just(T::Keyword(K::As)))
+            .then(select_clause)
+            .map(|(table_name, select)| TableStatement::CreateTableAs(table_name.into(), select))
+            .boxed();
+
+        // REINDEX TABLE Identifier | REINDEX INDEX Identifier
+        let reindex = just(T::Keyword(K::Reindex))
+            .ignore_then(choice((
+                just(T::Keyword(K::Table))
+                    .ignore_then(identifier())
+                    .map(|table_name| TableStatement::ReindexTable(table_name.into())),
+                just(T::Keyword(K::Index))
+                    .ignore_then(identifier())
+                    .map(|index_name| TableStatement::ReindexIndex(index_name.into())),
+            )))
+            .boxed();
+
+        // CHECK TABLE Identifier
+        let check_table = just([T::Keyword(K::Check), T::Keyword(K::Table)])
+            .ignore_then(identifier())
+            .map(|table_name| TableStatement::CheckTable(table_name.into()));
+
         choice((
             drop_table,
             describe_table,
+            show_columns,
             insert_into_table,
+            replace_into_table,
             load_data_infile,
             delete_from_table,
             update_table,
             select_table,
+            explain_select,
             create_table,
+            create_table_as,
+            reindex,
+            check_table,
         ))
         .boxed()
     }
@@ -7,7 +7,10 @@ pub mod lexer_parser;
 pub mod record;
 
 pub use btree::{BPlusKey, BPlusNode, BPlusTree, BPlusTreeError, BPlusTreeResult};
-pub use file::{BUFFER_POOL_SIZE, BufferManager, FileHandle, PAGE_SIZE, PagedFileManager};
+pub use file::{
+    BUFFER_POOL_SIZE, BufferManager, BufferStats, EvictionPolicy, FileHandle, PAGE_SIZE,
+    PagedFileManager,
+};
 pub use record::{
     ColumnDef, DataType, Page, PageHeader, Record, RecordError, RecordId, RecordManager,
     RecordResult, TableFile, TableSchema, Value,
@@ -1,15 +1,21 @@
-use adb::database::{DatabaseError, DatabaseManager, QueryResult};
+use adb::DataType;
+use adb::catalog::CatalogError;
+use adb::database::{DatabaseError, DatabaseManager, QueryResult, ResultColumn};
+use adb::file::FileError;
+use adb::index::IndexError;
 use adb::lexer_parser::{self, Query};
-use clap::Parser;
+use adb::record::RecordError;
+use clap::{Parser, ValueEnum};
+use std::fmt;
 use std::fs;
 use std::io::{self, BufRead, BufReader, IsTerminal, Write};
 use std::path::Path;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use prettytable::{Cell, Row, Table};
+use rustyline::Editor;
 use rustyline::error::ReadlineError;
 use rustyline::history::DefaultHistory;
-use rustyline::Editor;
 
 #[derive(Parser, Debug)]
 #[command(name = "adb")]
@@ -35,6 +41,10 @@ struct Args {
     #[arg(short, long, value_name = "TABLE")]
     table: Option<String>,
 
+    /// Run the given SQL, print its result and exit
+    #[arg(short, long, value_name = "SQL", conflicts_with = "file")]
+    query: Option<String>,
+
     /// Database to use at startup (executes USE <db>)
     #[arg(short, long, value_name = "DB")]
     database: Option<String>,
@@ -42,6 +52,26 @@ struct Args {
     /// Data directory path
     #[arg(long, default_value = "./data")]
     data_dir: String,
+
+    /// Print affected-row counts as a bare number, without the `rows` header line
+    #[arg(long)]
+    no_rows_header: bool,
+
+    /// Abort SELECT/UPDATE/DELETE statements running longer than this many milliseconds
+    #[arg(long, value_name = "MS")]
+    timeout: Option<u64>,
+
+    /// Format of printed results
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// Comma-separated lines, or tables in the interactive shell
+    Csv,
+    /// One JSON document per result
+    Json,
 }
 
 fn main() {
@@ -89,57 +119,208 @@ fn main() {
     if args.noindex {
         db_manager.set_use_indexes(false);
     }
+    if let Some(ms) = args.timeout {
+        db_manager.set_statement_timeout(Some(Duration::from_millis(ms)));
+    }
+    let rows_header = !args.no_rows_header;
+    let format = args.format;
 
     // If database is specified, execute USE command
     if let Some(db_name) = args.database {
         let use_query = format!("USE {};", db_name);
-        if let Err(e) =
-            execute_sql_line(&mut db_manager, &use_query, output_mode_from_batch(args.batch))
-        {
+        if let Err(e) = execute_sql_line(
+            &mut io::stdout(),
+            &mut db_manager,
+            &use_query,
+            output_mode_from_batch(args.batch),
+            rows_header,
+            format,
+        ) {
             eprintln!("Failed to use database {}: {}", db_name, e);
-            std::process::exit(1);
+            std::process::exit(e.exit_code());
         }
     }
 
     // Handle file import mode
     if let (Some(file_path), Some(table_name)) = (args.file, args.table) {
-        if let Err(e) = import_data_from_file(&mut db_manager, &file_path, &table_name, args.batch)
-        {
+        if let Err(e) = import_data_from_file(
+            &mut io::stdout(),
+            &mut db_manager,
+            &file_path,
+            &table_name,
+            args.batch,
+            rows_header,
+            format,
+        ) {
             eprintln!("Failed to import data: {}", e);
-            std::process::exit(1);
+            std::process::exit(e.exit_code());
+        }
+        std::process::exit(0);
+    }
+
+    if let Some(sql) = args.query {
+        let mut sql = sql.trim().to_string();
+        // The trailing `;` is optional for a single statement
+        if !statement_complete(&sql) {
+            sql.push(';');
+        }
+        let output_mode = if args.batch {
+            OutputMode::Batch
+        } else {
+            OutputMode::Interactive
+        };
+        let result = execute_sql_line(
+            &mut io::stdout(),
+            &mut db_manager,
+            &sql,
+            output_mode,
+            rows_header,
+            format,
+        );
+        // `exit` skips destructors, so flush the buffered pages first
+        drop(db_manager);
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(e.exit_code());
         }
         std::process::exit(0);
     }
 
     // Interactive or batch mode
     if !args.batch && io::stdin().is_terminal() {
-        run_interactive_repl(&mut db_manager);
+        run_interactive_repl(&mut db_manager, format);
     } else {
-        run_stdin_mode(&mut db_manager, args.batch);
+        run_stdin_mode(
+            io::stdin().lock(),
+            &mut io::stdout(),
+            &mut db_manager,
+            args.batch,
+            rows_header,
+            format,
+        );
+    }
+}
+
+/// What went wrong running SQL from the command line, which decides the exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorKind {
+    /// The SQL did not parse
+    Parse,
+    /// A primary key, UNIQUE, foreign key or NOT NULL constraint was violated
+    Constraint,
+    /// Reading the input or the data files failed
+    Io,
+    /// Any other failure while executing a statement
+    Execution,
+}
+
+#[derive(Debug)]
+struct CliError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl CliError {
+    fn new(kind: ErrorKind, message: String) -> Self {
+        Self { kind, message }
+    }
+
+    /// Prefix the message with the statement that failed
+    fn in_statement(self, statement: &str) -> Self {
+        Self::new(
+            self.kind,
+            format!("Error executing '{}': {}", statement, self.message),
+        )
+    }
+
+    /// 2 for parse errors, 3 for constraint violations, 4 for I/O errors and
+    /// 1 for anything else
+    fn exit_code(&self) -> i32 {
+        match self.kind {
+            ErrorKind::Execution => 1,
+            ErrorKind::Parse => 2,
+            ErrorKind::Constraint => 3,
+            ErrorKind::Io => 4,
+        }
+    }
+}
+
+impl From<DatabaseError> for CliError {
+    fn from(err: DatabaseError) -> Self {
+        let kind = match &err {
+            DatabaseError::ParseError(_) => ErrorKind::Parse,
+            DatabaseError::PrimaryKeyViolation(_)
+            | DatabaseError::UniqueViolation(..)
+            | DatabaseError::ForeignKeyViolation(_)
+            | DatabaseError::NotNullViolation(_) => ErrorKind::Constraint,
+            // Storage layers wrap the I/O error they hit in their own error type
+            DatabaseError::IoError(_)
+            | DatabaseError::FileError(FileError::Io(_))
+            | DatabaseError::RecordError(
+                RecordError::Io(_) | RecordError::File(FileError::Io(_)),
+            )
+            | DatabaseError::IndexError(IndexError::FileError(FileError::Io(_)))
+            | DatabaseError::CatalogError(CatalogError::IoError(_)) => ErrorKind::Io,
+            _ => ErrorKind::Execution,
+        };
+        Self::new(kind, err.to_string())
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(err: io::Error) -> Self {
+        Self::new(ErrorKind::Io, format!("Failed to write output: {}", err))
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
     }
 }
 
 fn import_data_from_file(
+    out: &mut impl Write,
     db_manager: &mut DatabaseManager,
     file_path: &str,
     _table_name: &str,
     batch_mode: bool,
-) -> Result<(), String> {
-    let file = fs::File::open(file_path).map_err(|e| format!("Cannot open file: {}", e))?;
+    rows_header: bool,
+    format: OutputFormat,
+) -> Result<(), CliError> {
+    let file = fs::File::open(file_path)
+        .map_err(|e| CliError::new(ErrorKind::Io, format!("Cannot open file: {}", e)))?;
     let reader = BufReader::new(file);
+    let mut pending = String::new();
 
     for line in reader.lines() {
-        let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
+        let line =
+            line.map_err(|e| CliError::new(ErrorKind::Io, format!("Failed to read line: {}", e)))?;
         let line = line.trim();
 
         if line.is_empty() {
             continue;
         }
 
-        // Parse and execute the SQL statement
-        if let Err(e) = execute_sql_line(db_manager, line, output_mode_from_batch(batch_mode)) {
-            return Err(format!("Error executing '{}': {}", line, e));
+        // A block comment may span several lines; wait until it is closed
+        pending.push_str(line);
+        pending.push('\n');
+        if scan_sql(&pending).in_block_comment {
+            continue;
         }
+        let statement = std::mem::take(&mut pending);
+        let line = statement.trim_end();
+
+        // Parse and execute the SQL statement
+        execute_sql_line(
+            out,
+            db_manager,
+            line,
+            output_mode_from_batch(batch_mode),
+            rows_header,
+            format,
+        )
+        .map_err(|e| e.in_statement(line))?;
     }
 
     Ok(())
@@ -152,6 +333,22 @@ enum OutputMode {
     Interactive,
 }
 
+impl OutputMode {
+    /// Where the `@query` echo line goes relative to a statement's output
+    ///
+    /// Batch mode prints each statement's result first and then its echo, so
+    /// an `@` line always terminates the output it belongs to. Non-batch
+    /// piped mode announces the statement before its result. The
+    /// interactive REPL does not echo.
+    fn echo_before(self) -> bool {
+        matches!(self, OutputMode::NonBatchPipe)
+    }
+
+    fn echo_after(self) -> bool {
+        matches!(self, OutputMode::Batch)
+    }
+}
+
 fn output_mode_from_batch(batch_mode: bool) -> OutputMode {
     if batch_mode {
         OutputMode::Batch
@@ -161,35 +358,58 @@ fn output_mode_from_batch(batch_mode: bool) -> OutputMode {
 }
 
 fn execute_sql_line(
+    out: &mut impl Write,
     db_manager: &mut DatabaseManager,
     line: &str,
     output_mode: OutputMode,
-) -> Result<(), String> {
+    rows_header: bool,
+    format: OutputFormat,
+) -> Result<(), CliError> {
     let queries = if matches!(output_mode, OutputMode::Interactive) {
         lexer_parser::parse_case_insensitive(line)
     } else {
         lexer_parser::parse(line)
     }
-    .map_err(|e| format!("Parse error: {}", e))?;
+    .map_err(|e| CliError::new(ErrorKind::Parse, format!("Parse error: {}", e)))?;
 
     for query in queries {
-        if matches!(output_mode, OutputMode::NonBatchPipe) {
-            print_query_echo(line);
+        if output_mode.echo_before() {
+            print_query_echo(out, line)?;
+        }
+
+        let result = execute_query(db_manager, query);
+        if let Ok((result, columns)) = &result {
+            print_result(
+                out,
+                result,
+                columns.as_deref(),
+                output_mode,
+                rows_header,
+                format,
+            )?;
         }
 
-        let result = execute_query(db_manager, query).map_err(|e| format!("{}", e))?;
+        if output_mode.echo_after() {
+            print_query_echo(out, line)?;
+        }
 
-        print_result(&result, output_mode);
+        result?;
     }
 
     Ok(())
 }
 
-fn run_stdin_mode(db_manager: &mut DatabaseManager, batch_mode: bool) {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+fn run_stdin_mode(
+    input: impl BufRead,
+    out: &mut impl Write,
+    db_manager: &mut DatabaseManager,
+    batch_mode: bool,
+    rows_header: bool,
+    format: OutputFormat,
+) {
+    let mut pending = String::new();
 
-    for line in stdin.lock().lines() {
+    for line in input.lines() {
         let line = match line {
             Ok(l) => l,
             Err(_) => break,
@@ -201,10 +421,27 @@ fn run_stdin_mode(db_manager: &mut DatabaseManager, batch_mode: bool) {
         }
 
         // Check for exit command
-        if line.eq_ignore_ascii_case("exit") {
+        if pending.is_empty() && line.eq_ignore_ascii_case("exit") {
             break;
         }
 
+        // Outside batch mode, pasted statements may span several lines: keep
+        // reading until the terminating `;`. Batch mode stays line-oriented,
+        // except that an open block comment runs on into the following lines
+        pending.push_str(line);
+        pending.push('\n');
+        let scan = scan_sql(&pending);
+        let ready = if batch_mode {
+            !scan.in_block_comment
+        } else {
+            scan.statement_complete()
+        };
+        if !ready {
+            continue;
+        }
+        let statement = std::mem::take(&mut pending);
+        let line = statement.trim_end();
+
         // Parse SQL
         let queries = match lexer_parser::parse(line) {
             Ok(q) => q,
@@ -219,40 +456,55 @@ fn run_stdin_mode(db_manager: &mut DatabaseManager, batch_mode: bool) {
         };
 
         // Execute each query
+        let output_mode = output_mode_from_batch(batch_mode);
         for query in queries {
-            // Echo the query (unless in batch mode)
-            if !batch_mode {
-                print_query_echo(line);
+            if output_mode.echo_before() {
+                print_query_echo(out, line).expect("failed printing to stdout");
             }
 
             let result = execute_query(db_manager, query);
             match result {
-                Ok(res) => print_result(&res, output_mode_from_batch(batch_mode)),
+                Ok((res, columns)) => print_result(
+                    out,
+                    &res,
+                    columns.as_deref(),
+                    output_mode,
+                    rows_header,
+                    format,
+                )
+                .expect("failed printing to stdout"),
                 Err(e) => {
                     if batch_mode {
-                        println!("!ERROR");
                         // Map long error messages to shorter ones for batch mode
-                        match e {
-                            DatabaseError::PrimaryKeyViolation => println!("duplicate"),
-                            DatabaseError::ForeignKeyViolation(_) => println!("foreign"),
-                            _ => println!("{}", e),
-                        }
+                        let message = match e {
+                            DatabaseError::PrimaryKeyViolation(_) => "duplicate".to_string(),
+                            DatabaseError::ForeignKeyViolation(_) => "foreign".to_string(),
+                            _ => e.to_string(),
+                        };
+                        writeln!(out, "!ERROR\n{}", message).expect("failed printing to stdout");
                     } else {
                         eprintln!("Error: {}", e);
                     }
                 }
             }
 
-            if batch_mode {
-                print_query_echo(line);
+            if output_mode.echo_after() {
+                print_query_echo(out, line).expect("failed printing to stdout");
             }
 
-            stdout.flush().unwrap();
+            out.flush().unwrap();
         }
     }
+
+    if !pending.trim().is_empty() {
+        eprintln!(
+            "Parse error: unterminated statement: {}",
+            pending.trim_end()
+        );
+    }
 }
 
-fn run_interactive_repl(db_manager: &mut DatabaseManager) {
+fn run_interactive_repl(db_manager: &mut DatabaseManager, format: OutputFormat) {
     let mut rl = match Editor::<(), DefaultHistory>::new() {
         Ok(editor) => editor,
         Err(e) => {
@@ -261,13 +513,16 @@ fn run_interactive_repl(db_manager: &mut DatabaseManager) {
         }
     };
 
-    loop {
-        let Some(statement) = read_statement(&mut rl) else {
-            break;
-        };
-
+    while let Some(statement) = read_statement(&mut rl) {
         let start = Instant::now();
-        match execute_sql_line(db_manager, &statement, OutputMode::Interactive) {
+        match execute_sql_line(
+            &mut io::stdout(),
+            db_manager,
+            &statement,
+            OutputMode::Interactive,
+            true,
+            format,
+        ) {
             Ok(()) => {
                 let elapsed = start.elapsed();
                 println!("Time: {:.3} ms", elapsed.as_secs_f64() * 1000.0);
@@ -318,10 +573,31 @@ fn read_statement(rl: &mut Editor<(), DefaultHistory>) -> Option<String> {
 }
 
 fn statement_complete(input: &str) -> bool {
+    scan_sql(input).statement_complete()
+}
+
+/// Lexical state at the end of a chunk of SQL text
+struct SqlScan {
+    /// A quoted string is still open
+    in_quote: bool,
+    /// A `/* ... */` comment is still open
+    in_block_comment: bool,
+    /// Last non-whitespace character outside comments
+    last_non_ws: Option<char>,
+}
+
+impl SqlScan {
+    fn statement_complete(&self) -> bool {
+        !self.in_quote && !self.in_block_comment && self.last_non_ws == Some(';')
+    }
+}
+
+fn scan_sql(input: &str) -> SqlScan {
     let mut in_single = false;
     let mut in_double = false;
     let mut escaped = false;
     let mut in_line_comment = false;
+    let mut in_block_comment = false;
     let mut last_non_ws = None;
     let mut iter = input.chars().peekable();
 
@@ -333,12 +609,24 @@ fn statement_complete(input: &str) -> bool {
             continue;
         }
 
-        if !in_single && !in_double && ch == '-' {
-            if matches!(iter.peek(), Some('-')) {
+        if in_block_comment {
+            if ch == '*' && matches!(iter.peek(), Some('/')) {
                 iter.next();
-                in_line_comment = true;
-                continue;
+                in_block_comment = false;
             }
+            continue;
+        }
+
+        if !in_single && !in_double && ch == '-' && matches!(iter.peek(), Some('-')) {
+            iter.next();
+            in_line_comment = true;
+            continue;
+        }
+
+        if !in_single && !in_double && ch == '/' && matches!(iter.peek(), Some('*')) {
+            iter.next();
+            in_block_comment = true;
+            continue;
         }
 
         if ch == '\\' && !escaped {
@@ -359,92 +647,204 @@ fn statement_complete(input: &str) -> bool {
         }
     }
 
-    !in_single && !in_double && last_non_ws == Some(';')
+    SqlScan {
+        in_quote: in_single || in_double,
+        in_block_comment,
+        last_non_ws,
+    }
 }
 
-fn print_query_echo(_original: &str) {
-    // Print original query as comment
-    println!("@{}", _original);
+fn print_query_echo(out: &mut impl Write, original: &str) -> io::Result<()> {
+    // Print original query as comment, marking every line so a multi-line
+    // statement or block comment can't be mistaken for result output
+    for line in original.lines() {
+        writeln!(out, "@{}", line)?;
+    }
+    Ok(())
 }
 
 fn execute_query(
     db: &mut DatabaseManager,
     query: Query,
-) -> Result<QueryResult, adb::database::DatabaseError> {
-    match query {
-        Query::DBStmt(stmt) => db.execute_db_statement(stmt),
-        Query::TableStmt(stmt) => db.execute_table_statement(stmt),
-        Query::AlterStmt(stmt) => db.execute_alter_statement(stmt),
-        Query::Null => Ok(QueryResult::Empty),
-    }
+) -> Result<(QueryResult, Option<Vec<ResultColumn>>), adb::database::DatabaseError> {
+    db.execute_query_typed(query)
 }
 
-fn print_result(result: &QueryResult, output_mode: OutputMode) {
+fn print_result(
+    out: &mut impl Write,
+    result: &QueryResult,
+    columns: Option<&[ResultColumn]>,
+    output_mode: OutputMode,
+    rows_header: bool,
+    format: OutputFormat,
+) -> io::Result<()> {
+    if format == OutputFormat::Json {
+        if let Some(json) = result_json(result, columns) {
+            writeln!(out, "{}", json)?;
+        }
+        return Ok(());
+    }
     match output_mode {
-        OutputMode::Interactive => print_result_interactive(result),
-        OutputMode::Batch | OutputMode::NonBatchPipe => print_result_legacy(result),
+        OutputMode::Interactive => print_result_interactive(out, result),
+        OutputMode::Batch | OutputMode::NonBatchPipe => {
+            write_result_legacy(out, result, rows_header)
+        }
+    }
+}
+
+/// A result as one line of JSON: rows become an array of objects keyed by
+/// header, in column order, and an affected-row count `{"rows_affected":n}`
+fn result_json(result: &QueryResult, columns: Option<&[ResultColumn]>) -> Option<String> {
+    match result {
+        QueryResult::Empty => None,
+        QueryResult::RowsAffected(count) => {
+            Some(serde_json::json!({ "rows_affected": count }).to_string())
+        }
+        QueryResult::ResultSet(headers, rows) => Some(rows_json(headers, rows, columns)),
+        QueryResult::List(items) => Some(serde_json::json!(items).to_string()),
+        QueryResult::TableDescription(meta) => {
+            let headers = ["Field", "Type", "Null", "Default"].map(String::from);
+            let rows: Vec<Vec<String>> = meta
+                .columns
+                .iter()
+                .map(|col| {
+                    let null_str = if col.not_null { "NO" } else { "YES" };
+                    let default_str = col
+                        .default_function
+                        .as_deref()
+                        .or(col.default_value.as_deref())
+                        .unwrap_or("NULL");
+                    vec![
+                        col.name.clone(),
+                        col.column_type.clone(),
+                        null_str.to_string(),
+                        default_str.to_string(),
+                    ]
+                })
+                .collect();
+            Some(rows_json(&headers, &rows, None))
+        }
+    }
+}
+
+fn rows_json(headers: &[String], rows: &[Vec<String>], columns: Option<&[ResultColumn]>) -> String {
+    // Objects are written by hand: a JSON map would not keep the column order
+    let objects: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let fields: Vec<String> = headers
+                .iter()
+                .zip(row)
+                .enumerate()
+                .map(|(i, (header, cell))| {
+                    let value = json_cell(cell, columns.and_then(|columns| columns.get(i)));
+                    format!("{}:{}", serde_json::Value::from(header.as_str()), value)
+                })
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        })
+        .collect();
+    format!("[{}]", objects.join(","))
+}
+
+/// Cells of numeric and boolean columns become JSON numbers and booleans and
+/// NULL becomes `null`; without a column type every cell stays a string
+fn json_cell(cell: &str, column: Option<&ResultColumn>) -> serde_json::Value {
+    let Some(column) = column else {
+        return cell.into();
+    };
+    let typed = match column.data_type {
+        DataType::Char(_) | DataType::Varchar(_) => None,
+        _ if cell == "NULL" => Some(serde_json::Value::Null),
+        DataType::Int | DataType::Float | DataType::Decimal(..) => cell
+            .parse::<serde_json::Number>()
+            .ok()
+            .map(serde_json::Value::Number),
+        DataType::Bool => cell.parse::<bool>().ok().map(serde_json::Value::Bool),
+    };
+    match typed {
+        Some(value) => value,
+        // A NULL string can't be told apart from NULL unless the column is nullable
+        None if column.nullable && cell == "NULL" => serde_json::Value::Null,
+        None => cell.into(),
     }
 }
 
-fn print_result_legacy(result: &QueryResult) {
+/// The batch-mode rendering of a result, shared by `-b` and piped input
+fn write_result_legacy(
+    out: &mut impl Write,
+    result: &QueryResult,
+    rows_header: bool,
+) -> io::Result<()> {
     match result {
         QueryResult::Empty => {}
         QueryResult::RowsAffected(count) => {
-            println!("rows");
-            println!("{}", count);
+            // Same shape as a one-column result set: header line, then the value
+            if rows_header {
+                writeln!(out, "rows")?;
+            }
+            writeln!(out, "{}", count)?;
         }
         QueryResult::ResultSet(headers, rows) => {
-            println!("{}", headers.join(","));
+            writeln!(out, "{}", headers.join(","))?;
             for row in rows {
-                println!("{}", row.join(","));
+                writeln!(out, "{}", row.join(","))?;
             }
         }
         QueryResult::List(items) => {
             for item in items {
-                println!("{}", item);
+                writeln!(out, "{}", item)?;
             }
         }
         QueryResult::TableDescription(meta) => {
-            println!("Field,Type,Null,Default");
+            writeln!(out, "Field,Type,Null,Default")?;
             for col in &meta.columns {
                 let null_str = if col.not_null { "NO" } else { "YES" };
-                let default_str = col.default_value.as_deref().unwrap_or("NULL");
-                println!(
+                let default_str = col
+                    .default_function
+                    .as_deref()
+                    .or(col.default_value.as_deref())
+                    .unwrap_or("NULL");
+                writeln!(
+                    out,
                     "{},{},{},{}",
                     col.name, col.column_type, null_str, default_str
-                );
+                )?;
             }
 
-            println!();
+            writeln!(out)?;
 
             if let Some(pk) = &meta.primary_key {
-                println!("PRIMARY KEY ({});", pk.join(", "));
+                writeln!(out, "PRIMARY KEY ({});", pk.join(", "))?;
             }
 
             for fk in &meta.foreign_keys {
-                println!(
+                writeln!(
+                    out,
                     "FOREIGN KEY ({}) REFERENCES {}({});",
                     fk.columns.join(", "),
                     fk.ref_table,
                     fk.ref_columns.join(", ")
-                );
+                )?;
             }
 
             for idx in &meta.indexes {
                 if idx.implicit {
                     continue;
                 }
-                println!("INDEX ({});", idx.columns.join(", "));
+                let kind = if idx.unique { "UNIQUE" } else { "INDEX" };
+                writeln!(out, "{} ({});", kind, idx.columns.join(", "))?;
             }
         }
     }
+    Ok(())
 }
 
-fn print_result_interactive(result: &QueryResult) {
+fn print_result_interactive(out: &mut impl Write, result: &QueryResult) -> io::Result<()> {
     match result {
         QueryResult::Empty => {}
         QueryResult::RowsAffected(count) => {
-            println!("{} rows affected", count);
+            writeln!(out, "{} rows affected", count)?;
         }
         QueryResult::ResultSet(headers, rows) => {
             let mut table = Table::new();
@@ -456,11 +856,11 @@ fn print_result_interactive(result: &QueryResult) {
                 table.add_row(Row::new(cells));
             }
 
-            table.printstd();
+            table.print(out)?;
         }
         QueryResult::List(items) => {
             for item in items {
-                println!("{}", item);
+                writeln!(out, "{}", item)?;
             }
         }
         QueryResult::TableDescription(meta) => {
@@ -474,7 +874,11 @@ fn print_result_interactive(result: &QueryResult) {
 
             for col in &meta.columns {
                 let null_str = if col.not_null { "NO" } else { "YES" };
-                let default_str = col.default_value.as_deref().unwrap_or("NULL");
+                let default_str = col
+                    .default_function
+                    .as_deref()
+                    .or(col.default_value.as_deref())
+                    .unwrap_or("NULL");
                 table.add_row(Row::new(vec![
                     Cell::new(&col.name),
                     Cell::new(&col.column_type),
@@ -483,27 +887,291 @@ fn print_result_interactive(result: &QueryResult) {
                 ]));
             }
 
-            table.printstd();
+            table.print(out)?;
 
             if let Some(pk) = &meta.primary_key {
-                println!("PRIMARY KEY ({});", pk.join(", "));
+                writeln!(out, "PRIMARY KEY ({});", pk.join(", "))?;
             }
 
             for fk in &meta.foreign_keys {
-                println!(
+                writeln!(
+                    out,
                     "FOREIGN KEY ({}) REFERENCES {}({});",
                     fk.columns.join(", "),
                     fk.ref_table,
                     fk.ref_columns.join(", ")
-                );
+                )?;
             }
 
             for idx in &meta.indexes {
                 if idx.implicit {
                     continue;
                 }
-                println!("INDEX ({});", idx.columns.join(", "));
+                let kind = if idx.unique { "UNIQUE" } else { "INDEX" };
+                writeln!(out, "{} ({});", kind, idx.columns.join(", "))?;
             }
         }
     }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run one statement as `adb <flags> -q <sql>` would, returning what it
+    /// printed and its exit code
+    fn run_sql(db: &mut DatabaseManager, flags: &[&str], sql: &str) -> (String, i32) {
+        let args = Args::try_parse_from(["adb"].iter().chain(flags)).unwrap();
+        let output_mode = if args.batch {
+            OutputMode::Batch
+        } else {
+            OutputMode::Interactive
+        };
+        let mut out = Vec::new();
+        let code = execute_sql_line(
+            &mut out,
+            db,
+            sql,
+            output_mode,
+            !args.no_rows_header,
+            args.format,
+        )
+        .err()
+        .map_or(0, |e| e.exit_code());
+        (String::from_utf8(out).unwrap(), code)
+    }
+
+    #[test]
+    fn test_statement_complete() {
+        assert!(statement_complete("SHOW DATABASES;"));
+        assert!(statement_complete("CREATE TABLE t (\n  a INT\n);\n"));
+        assert!(!statement_complete("CREATE TABLE t (\n  a INT,\n"));
+        assert!(!statement_complete("INSERT INTO t VALUES ('a;"));
+        assert!(statement_complete("INSERT INTO t VALUES ('a;b');"));
+        assert!(statement_complete("SELECT * FROM t; -- trailing comment"));
+        assert!(statement_complete("SELECT * FROM t -- no end yet\n;"));
+        assert!(statement_complete("/* a; */ SELECT * FROM t; /* b */"));
+        assert!(!statement_complete("SELECT * FROM t; /* still\nopen;"));
+        assert!(!statement_complete("SELECT * /* ; */ FROM t"));
+        assert!(statement_complete("INSERT INTO t VALUES ('/*');"));
+        assert!(statement_complete("INSERT INTO t VALUES ('it''s;');"));
+    }
+
+    #[test]
+    fn test_echo_placement() {
+        let script = "CREATE DATABASE d;\nUSE d;\nCREATE TABLE t (id INT, PRIMARY KEY (id));\n\
+                      INSERT INTO t VALUES (1), (2);\nINSERT INTO t VALUES (1);\nSELECT * FROM t;\n";
+        let stdin_output = |batch_mode: bool| {
+            let mut db = DatabaseManager::in_memory();
+            let mut out = Vec::new();
+            run_stdin_mode(
+                script.as_bytes(),
+                &mut out,
+                &mut db,
+                batch_mode,
+                true,
+                OutputFormat::Csv,
+            );
+            String::from_utf8(out).unwrap()
+        };
+
+        // Batch mode prints each result, errors included, and then its echo
+        assert_eq!(
+            stdin_output(true),
+            concat!(
+                "@CREATE DATABASE d;\n",
+                "@USE d;\n",
+                "@CREATE TABLE t (id INT, PRIMARY KEY (id));\n",
+                "rows\n2\n",
+                "@INSERT INTO t VALUES (1), (2);\n",
+                "!ERROR\nduplicate\n",
+                "@INSERT INTO t VALUES (1);\n",
+                "id\n1\n2\n",
+                "@SELECT * FROM t;\n",
+            )
+        );
+        // Piped without -b, the echo announces the result; errors go to stderr
+        assert_eq!(
+            stdin_output(false),
+            concat!(
+                "@CREATE DATABASE d;\n",
+                "@USE d;\n",
+                "@CREATE TABLE t (id INT, PRIMARY KEY (id));\n",
+                "@INSERT INTO t VALUES (1), (2);\n",
+                "rows\n2\n",
+                "@INSERT INTO t VALUES (1);\n",
+                "@SELECT * FROM t;\n",
+                "id\n1\n2\n",
+            )
+        );
+
+        // The interactive shell never echoes
+        let mut db = DatabaseManager::in_memory();
+        let mut out = Vec::new();
+        for sql in script.lines() {
+            let _ = execute_sql_line(
+                &mut out,
+                &mut db,
+                sql,
+                OutputMode::Interactive,
+                true,
+                OutputFormat::Csv,
+            );
+        }
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "2 rows affected\n",
+                "+----+\n",
+                "| id |\n",
+                "+----+\n",
+                "| 1  |\n",
+                "+----+\n",
+                "| 2  |\n",
+                "+----+\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let mut db = DatabaseManager::in_memory();
+        let mut code = |sql: &str| run_sql(&mut db, &["-b"], sql).1;
+        assert_eq!(code("CREATE DATABASE d;"), 0);
+        assert_eq!(code("USE d;"), 0);
+        assert_eq!(
+            code("CREATE TABLE t (id INT NOT NULL, PRIMARY KEY (id));"),
+            0
+        );
+        assert_eq!(code("INSERT INTO t VALUES (1);"), 0);
+
+        assert_eq!(code("SELEC * FROM t;"), 2);
+        assert_eq!(code("INSERT INTO t VALUES (1);"), 3);
+        assert_eq!(code("INSERT INTO t VALUES (NULL);"), 3);
+        assert_eq!(code("SELECT * FROM missing;"), 1);
+
+        let err = import_data_from_file(
+            &mut io::sink(),
+            &mut db,
+            "/nonexistent/input.sql",
+            "t",
+            true,
+            true,
+            OutputFormat::Csv,
+        )
+        .unwrap_err();
+        assert_eq!(err.exit_code(), 4);
+
+        // I/O errors wrapped by the storage layers count as I/O errors too
+        let disk = || io::Error::other("disk failure");
+        for err in [
+            DatabaseError::RecordError(RecordError::Io(disk())),
+            DatabaseError::RecordError(RecordError::File(FileError::Io(disk()))),
+            DatabaseError::IndexError(IndexError::FileError(FileError::Io(disk()))),
+            DatabaseError::CatalogError(CatalogError::IoError(disk())),
+        ] {
+            assert_eq!(CliError::from(err).exit_code(), 4);
+        }
+        let err = DatabaseError::RecordError(RecordError::TableNotOpen("t".to_string()));
+        assert_eq!(CliError::from(err).exit_code(), 1);
+    }
+
+    #[test]
+    fn test_no_rows_header_flag() {
+        let mut db = DatabaseManager::in_memory();
+        run_sql(&mut db, &["-b"], "CREATE DATABASE d;");
+        run_sql(&mut db, &["-b"], "USE d;");
+        run_sql(
+            &mut db,
+            &["-b"],
+            "CREATE TABLE t (id INT, name VARCHAR(8));",
+        );
+
+        assert_eq!(
+            run_sql(&mut db, &["-b"], "INSERT INTO t VALUES (1, 'a');"),
+            ("rows\n1\n@INSERT INTO t VALUES (1, 'a');\n".to_string(), 0)
+        );
+        assert_eq!(
+            run_sql(
+                &mut db,
+                &["-b", "--no-rows-header"],
+                "INSERT INTO t VALUES (2, 'b');"
+            ),
+            ("1\n@INSERT INTO t VALUES (2, 'b');\n".to_string(), 0)
+        );
+        // Result sets always keep their header line
+        for flags in [&["-b"][..], &["-b", "--no-rows-header"]] {
+            assert_eq!(
+                run_sql(&mut db, flags, "SELECT * FROM t;"),
+                ("id,name\n1,a\n2,b\n@SELECT * FROM t;\n".to_string(), 0)
+            );
+        }
+    }
+
+    #[test]
+    fn test_query_flag() {
+        let args = Args::try_parse_from(["adb", "-d", "db", "-q", "SELECT * FROM t"]).unwrap();
+        assert_eq!(args.query.as_deref(), Some("SELECT * FROM t"));
+        assert!(
+            Args::try_parse_from(["adb", "-q", "SELECT * FROM t", "-f", "a.sql", "-t", "t"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_json_output() {
+        let args = Args::try_parse_from(["adb", "--format", "json"]).unwrap();
+        assert_eq!(args.format, OutputFormat::Json);
+        assert_eq!(
+            Args::try_parse_from(["adb"]).unwrap().format,
+            OutputFormat::Csv
+        );
+        assert!(Args::try_parse_from(["adb", "--format", "xml"]).is_err());
+
+        let mut db = DatabaseManager::in_memory();
+        let mut json = |sql: &str| run_sql(&mut db, &["--format", "json"], sql).0;
+        assert_eq!(json("CREATE DATABASE d;"), "");
+        json("USE d;");
+        json("CREATE TABLE t (id INT NOT NULL, name VARCHAR(8), score FLOAT, ok BOOL);");
+        assert_eq!(
+            json("INSERT INTO t VALUES (1, 'a\"b', 2.5, TRUE), (2, NULL, NULL, NULL);"),
+            "{\"rows_affected\":2}\n"
+        );
+        assert_eq!(
+            json("SELECT * FROM t;"),
+            concat!(
+                r#"[{"id":1,"name":"a\"b","score":2.5,"ok":true},"#,
+                r#"{"id":2,"name":null,"score":null,"ok":null}]"#,
+                "\n"
+            )
+        );
+        assert_eq!(json("SELECT id FROM t WHERE id = 3;"), "[]\n");
+        // Results without column types print every cell as a string
+        assert_eq!(json("SHOW TABLES;"), "[{\"TABLES\":\"t\"}]\n");
+    }
+
+    #[test]
+    fn test_json_output_escaping_and_nulls() {
+        let mut db = DatabaseManager::in_memory();
+        let mut json = |sql: &str| run_sql(&mut db, &["--format", "json"], sql).0;
+        json("CREATE DATABASE d;");
+        json("USE d;");
+        json("CREATE TABLE t (id INT, name VARCHAR(20) NOT NULL, note VARCHAR(20));");
+        json(
+            r#"INSERT INTO t VALUES (1, 'back\\slash', NULL), (2, 'tab\there\nnext', 'x"y'), (3, 'NULL', NULL);"#,
+        );
+
+        // Control characters, quotes and backslashes are escaped; the text
+        // NULL in a NOT NULL column is a string, not null
+        assert_eq!(
+            json("SELECT * FROM t ORDER BY id;"),
+            concat!(
+                r#"[{"id":1,"name":"back\\slash","note":null},"#,
+                r#"{"id":2,"name":"tab\there\nnext","note":"x\"y"},"#,
+                r#"{"id":3,"name":"NULL","note":null}]"#,
+                "\n"
+            )
+        );
+    }
 }
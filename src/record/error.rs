@@ -19,6 +19,9 @@ pub enum RecordError {
     #[error("Invalid slot: page_id={0}, slot_id={1}")]
     InvalidSlot(usize, usize),
 
+    #[error("Record too large for page: {size} bytes, at most {max} fit")]
+    RecordTooLarge { size: usize, max: usize },
+
     #[error("Page full: page_id={0}")]
     PageFull(usize),
 
@@ -1,5 +1,6 @@
 mod error;
 mod page;
+#[allow(clippy::module_inception)]
 mod record;
 mod schema;
 mod table_file;
@@ -10,21 +11,24 @@ pub use page::{Page, PageHeader};
 pub use record::{Record, RecordId, SlotId};
 pub use schema::{ColumnDef, TableSchema};
 pub use table_file::{TableFile, TableScanIter};
-pub use value::{DataType, Value};
+pub use value::{
+    DataType, MAX_DECIMAL_PRECISION, Value, compare_decimals, decimal_fits, decimal_to_f64,
+    format_decimal, parse_decimal, rescale_decimal,
+};
 
 use crate::file::BufferManager;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, RwLock};
 
 /// High-level record manager for all tables
 pub struct RecordManager {
-    buffer_manager: Arc<Mutex<BufferManager>>,
+    buffer_manager: Arc<RwLock<BufferManager>>,
     open_tables: HashMap<String, TableFile>,
 }
 
 impl RecordManager {
     /// Create a new record manager
-    pub fn new(buffer_manager: Arc<Mutex<BufferManager>>) -> Self {
+    pub fn new(buffer_manager: Arc<RwLock<BufferManager>>) -> Self {
         Self {
             buffer_manager,
             open_tables: HashMap::new(),
@@ -33,7 +37,7 @@ impl RecordManager {
 
     /// Create a new table file
     pub fn create_table(&mut self, path: &str, schema: TableSchema) -> RecordResult<()> {
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
         let table_file = TableFile::create(&mut buffer_manager, path, schema)?;
         drop(buffer_manager);
         self.open_tables
@@ -48,7 +52,7 @@ impl RecordManager {
             return Ok(());
         }
 
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
         let table_file = TableFile::open(&mut buffer_manager, path, schema)?;
         drop(buffer_manager);
         self.open_tables
@@ -62,9 +66,14 @@ impl RecordManager {
         Ok(())
     }
 
+    /// Close every open table
+    pub fn close_all_tables(&mut self) {
+        self.open_tables.clear();
+    }
+
     /// Insert a record into a table
     pub fn insert(&mut self, table_name: &str, record: Record) -> RecordResult<RecordId> {
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
         let table = self
             .open_tables
             .get_mut(table_name)
@@ -81,7 +90,7 @@ impl RecordManager {
         table_name: &str,
         records: Vec<Record>,
     ) -> RecordResult<Vec<RecordId>> {
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
         let table = self
             .open_tables
             .get_mut(table_name)
@@ -100,7 +109,7 @@ impl RecordManager {
 
     /// Delete a record from a table
     pub fn delete(&mut self, table_name: &str, rid: RecordId) -> RecordResult<()> {
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
         let table = self
             .open_tables
             .get_mut(table_name)
@@ -108,9 +117,15 @@ impl RecordManager {
         table.delete_record(&mut buffer_manager, rid)
     }
 
-    /// Update a record in a table
-    pub fn update(&mut self, table_name: &str, rid: RecordId, record: Record) -> RecordResult<()> {
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+    /// Update a record in a table, returning its RID afterwards (see
+    /// [`TableFile::update_record`])
+    pub fn update(
+        &mut self,
+        table_name: &str,
+        rid: RecordId,
+        record: Record,
+    ) -> RecordResult<RecordId> {
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
         let table = self
             .open_tables
             .get_mut(table_name)
@@ -120,7 +135,7 @@ impl RecordManager {
 
     /// Get a record from a table
     pub fn get(&mut self, table_name: &str, rid: RecordId) -> RecordResult<Record> {
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
         let table = self
             .open_tables
             .get_mut(table_name)
@@ -130,7 +145,7 @@ impl RecordManager {
 
     /// Scan all records in a table
     pub fn scan(&mut self, table_name: &str) -> RecordResult<Vec<(RecordId, Record)>> {
-        let mut buffer_manager = self.buffer_manager.lock().unwrap();
+        let mut buffer_manager = self.buffer_manager.write().unwrap();
         let table = self
             .open_tables
             .get_mut(table_name)
@@ -146,4 +161,13 @@ impl RecordManager {
             .ok_or_else(|| RecordError::TableNotOpen(table_name.to_string()))?;
         Ok(table.scan_iter(self.buffer_manager.clone()))
     }
+
+    /// Create a streaming iterator over a table's records, newest slots first.
+    pub fn scan_iter_rev(&self, table_name: &str) -> RecordResult<TableScanIter> {
+        let table = self
+            .open_tables
+            .get(table_name)
+            .ok_or_else(|| RecordError::TableNotOpen(table_name.to_string()))?;
+        Ok(table.scan_iter_rev(self.buffer_manager.clone()))
+    }
 }
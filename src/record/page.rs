@@ -1,6 +1,20 @@
 use super::error::{RecordError, RecordResult};
 use super::record::SlotId;
-use crate::file::{PAGE_SIZE, PageId};
+use super::schema::TableSchema;
+use crate::file::{PAGE_CHECKSUM_OFFSET, PAGE_SIZE, PAGE_VERSION_OFFSET, PageId};
+
+/// `record_size` in the header of a page holding variable-length records
+const VARIABLE_RECORD_SIZE: u16 = 0;
+
+// A variable-length page keeps two u16s after its header: where the record
+// heap starts and how many bytes in it belong to no record. The slot
+// directory follows and grows upward; records are packed downward from the
+// end of the page.
+const HEAP_START_OFFSET: usize = PageHeader::SIZE;
+const DEAD_BYTES_OFFSET: usize = PageHeader::SIZE + 2;
+const SLOT_DIRECTORY_START: usize = PageHeader::SIZE + 4;
+/// A directory entry is the record's offset and length; offset 0 marks a free slot
+const SLOT_ENTRY_SIZE: usize = 4;
 
 /// Page header stored at the beginning of each page
 #[derive(Debug, Clone, Copy)]
@@ -8,8 +22,10 @@ pub struct PageHeader {
     pub next_page: u32,   // 4 bytes - Link to next page (0 = no next)
     pub slot_count: u16,  // 2 bytes - Max slots in this page
     pub free_slots: u16,  // 2 bytes - Number of free slots
-    pub record_size: u16, // 2 bytes - Size of each record
-    _padding: [u8; 6],    // 6 bytes - Padding to 16 bytes
+    pub record_size: u16, // 2 bytes - Size of each record, 0 for variable-length records
+    pub version: u8,      // 1 byte  - Page format version, stamped by the buffer manager
+    _padding: u8,         // 1 byte  - Padding
+    pub checksum: u32,    // 4 bytes - CRC32 of the page, stamped by the buffer manager
 }
 
 impl PageHeader {
@@ -21,7 +37,9 @@ impl PageHeader {
             slot_count,
             free_slots: slot_count,
             record_size,
-            _padding: [0; 6],
+            version: 0,
+            _padding: 0,
+            checksum: 0,
         }
     }
 
@@ -31,6 +49,9 @@ impl PageHeader {
         result[4..6].copy_from_slice(&self.slot_count.to_le_bytes());
         result[6..8].copy_from_slice(&self.free_slots.to_le_bytes());
         result[8..10].copy_from_slice(&self.record_size.to_le_bytes());
+        result[PAGE_VERSION_OFFSET] = self.version;
+        result[PAGE_CHECKSUM_OFFSET..PAGE_CHECKSUM_OFFSET + 4]
+            .copy_from_slice(&self.checksum.to_le_bytes());
         result
     }
 
@@ -46,13 +67,21 @@ impl PageHeader {
         let slot_count = u16::from_le_bytes([data[4], data[5]]);
         let free_slots = u16::from_le_bytes([data[6], data[7]]);
         let record_size = u16::from_le_bytes([data[8], data[9]]);
+        let version = data[PAGE_VERSION_OFFSET];
+        let checksum = u32::from_le_bytes(
+            data[PAGE_CHECKSUM_OFFSET..PAGE_CHECKSUM_OFFSET + 4]
+                .try_into()
+                .unwrap(),
+        );
 
         Ok(Self {
             next_page,
             slot_count,
             free_slots,
             record_size,
-            _padding: [0; 6],
+            version,
+            _padding: 0,
+            checksum,
         })
     }
 }
@@ -83,6 +112,25 @@ impl<'a> Page<'a> {
         max_slots.min(u16::MAX as usize)
     }
 
+    /// Largest record size that still leaves room for one slot
+    pub fn max_record_size() -> usize {
+        PAGE_SIZE - PageHeader::SIZE - 1
+    }
+
+    /// Largest variable-length record that fits an empty page
+    pub fn max_variable_record_size() -> usize {
+        PAGE_SIZE - SLOT_DIRECTORY_START - SLOT_ENTRY_SIZE
+    }
+
+    /// Largest record a page of `schema`'s layout can hold
+    pub fn max_record_size_for(schema: &TableSchema) -> usize {
+        if schema.is_variable_length() {
+            Self::max_variable_record_size()
+        } else {
+            Self::max_record_size()
+        }
+    }
+
     /// Create a new empty page in the provided buffer
     /// The buffer must be exactly PAGE_SIZE bytes
     pub fn new(buffer: &'a mut [u8], record_size: usize) -> RecordResult<Self> {
@@ -96,10 +144,10 @@ impl<'a> Page<'a> {
 
         let slot_count = Self::calculate_slot_count(record_size);
         if slot_count == 0 {
-            return Err(RecordError::InvalidRecord(format!(
-                "Record size {} is too large for page",
-                record_size
-            )));
+            return Err(RecordError::RecordTooLarge {
+                size: record_size,
+                max: Self::max_record_size(),
+            });
         }
 
         let header = PageHeader::new(slot_count as u16, record_size as u16);
@@ -113,6 +161,25 @@ impl<'a> Page<'a> {
         Ok(Self { buffer, header })
     }
 
+    /// Create a new empty page for variable-length records
+    pub fn new_variable(buffer: &'a mut [u8]) -> RecordResult<Self> {
+        if buffer.len() != PAGE_SIZE {
+            return Err(RecordError::Deserialization(format!(
+                "Buffer must be PAGE_SIZE ({}) bytes, got {}",
+                PAGE_SIZE,
+                buffer.len()
+            )));
+        }
+
+        let header = PageHeader::new(0, VARIABLE_RECORD_SIZE);
+        buffer.fill(0);
+        buffer[..PageHeader::SIZE].copy_from_slice(&header.serialize());
+
+        let mut page = Self { buffer, header };
+        page.set_heap_start(PAGE_SIZE);
+        Ok(page)
+    }
+
     /// Wrap an existing page buffer (zero-copy)
     /// This replaces the old from_bytes that allocated Vecs
     pub fn from_buffer(buffer: &'a mut [u8]) -> RecordResult<Self> {
@@ -125,6 +192,16 @@ impl<'a> Page<'a> {
 
         let header = PageHeader::deserialize(&buffer[..PageHeader::SIZE])?;
 
+        if header.record_size == VARIABLE_RECORD_SIZE {
+            let page = Self { buffer, header };
+            if page.directory_end() > page.heap_start() || page.heap_start() > PAGE_SIZE {
+                return Err(RecordError::Deserialization(
+                    "Page layout exceeds page size".to_string(),
+                ));
+            }
+            return Ok(page);
+        }
+
         let slot_count = header.slot_count as usize;
         let record_size = header.record_size as usize;
         let bitmap_size = slot_count.div_ceil(8);
@@ -143,6 +220,136 @@ impl<'a> Page<'a> {
         Ok(Self { buffer, header })
     }
 
+    /// Whether the page holds variable-length records
+    pub fn is_variable_length(&self) -> bool {
+        self.header.record_size == VARIABLE_RECORD_SIZE
+    }
+
+    fn read_u16(&self, pos: usize) -> usize {
+        u16::from_le_bytes([self.buffer[pos], self.buffer[pos + 1]]) as usize
+    }
+
+    fn write_u16(&mut self, pos: usize, value: usize) {
+        self.buffer[pos..pos + 2].copy_from_slice(&(value as u16).to_le_bytes());
+    }
+
+    fn heap_start(&self) -> usize {
+        self.read_u16(HEAP_START_OFFSET)
+    }
+
+    fn set_heap_start(&mut self, heap_start: usize) {
+        self.write_u16(HEAP_START_OFFSET, heap_start);
+    }
+
+    fn dead_bytes(&self) -> usize {
+        self.read_u16(DEAD_BYTES_OFFSET)
+    }
+
+    fn set_dead_bytes(&mut self, dead_bytes: usize) {
+        self.write_u16(DEAD_BYTES_OFFSET, dead_bytes);
+    }
+
+    fn directory_end(&self) -> usize {
+        SLOT_DIRECTORY_START + self.header.slot_count as usize * SLOT_ENTRY_SIZE
+    }
+
+    /// Offset and length of a variable-length slot's record
+    fn slot_entry(&self, slot_id: SlotId) -> (usize, usize) {
+        let pos = SLOT_DIRECTORY_START + slot_id * SLOT_ENTRY_SIZE;
+        (self.read_u16(pos), self.read_u16(pos + 2))
+    }
+
+    fn set_slot_entry(&mut self, slot_id: SlotId, offset: usize, len: usize) {
+        let pos = SLOT_DIRECTORY_START + slot_id * SLOT_ENTRY_SIZE;
+        self.write_u16(pos, offset);
+        self.write_u16(pos + 2, len);
+    }
+
+    /// Bytes a variable-length page can still give out, counting the dead
+    /// bytes that compaction reclaims
+    pub fn free_space(&self) -> usize {
+        if !self.is_variable_length() {
+            return self.header.free_slots as usize * self.header.record_size as usize;
+        }
+        self.heap_start() - self.directory_end() + self.dead_bytes()
+    }
+
+    /// Pack the live records against the end of the page; slot ids stay put
+    fn compact(&mut self) {
+        let mut live: Vec<(SlotId, usize, usize)> = (0..self.slot_count())
+            .map(|slot_id| {
+                let (offset, len) = self.slot_entry(slot_id);
+                (slot_id, offset, len)
+            })
+            .filter(|&(_, offset, _)| offset != 0)
+            .collect();
+        // Highest offset first, so every record moves up into space already vacated
+        live.sort_unstable_by_key(|&(_, offset, _)| std::cmp::Reverse(offset));
+
+        let mut heap_start = PAGE_SIZE;
+        for (slot_id, offset, len) in live {
+            heap_start -= len;
+            self.buffer.copy_within(offset..offset + len, heap_start);
+            self.set_slot_entry(slot_id, heap_start, len);
+        }
+        self.set_heap_start(heap_start);
+        self.set_dead_bytes(0);
+    }
+
+    /// Copy `data` into the heap, compacting first if the gap below it is too small
+    fn place(&mut self, data: &[u8]) -> usize {
+        if self.heap_start() - self.directory_end() < data.len() {
+            self.compact();
+        }
+        let offset = self.heap_start() - data.len();
+        self.buffer[offset..offset + data.len()].copy_from_slice(data);
+        self.set_heap_start(offset);
+        offset
+    }
+
+    /// Store `data` in a free slot, returning `None` when the page has no
+    /// room for it
+    pub fn insert_record(&mut self, data: &[u8]) -> RecordResult<Option<SlotId>> {
+        if !self.is_variable_length() {
+            let Some(slot_id) = self.find_free_slot() else {
+                return Ok(None);
+            };
+            self.set_record(slot_id, data)?;
+            self.mark_slot_used(slot_id)?;
+            return Ok(Some(slot_id));
+        }
+
+        let free_slot = self.find_free_slot();
+        let needed = match free_slot {
+            Some(_) => data.len(),
+            None if self.header.slot_count < u16::MAX => data.len() + SLOT_ENTRY_SIZE,
+            None => return Ok(None),
+        };
+        if needed > self.free_space() {
+            return Ok(None);
+        }
+
+        let slot_id = match free_slot {
+            Some(slot_id) => {
+                self.header.free_slots -= 1;
+                slot_id
+            }
+            None => {
+                // Make room for the new directory entry before growing into it
+                if self.heap_start() - self.directory_end() < needed {
+                    self.compact();
+                }
+                self.header.slot_count += 1;
+                self.header.slot_count as usize - 1
+            }
+        };
+        self.buffer[..PageHeader::SIZE].copy_from_slice(&self.header.serialize());
+
+        let offset = self.place(data);
+        self.set_slot_entry(slot_id, offset, data.len());
+        Ok(Some(slot_id))
+    }
+
     /// Get bitmap slice from buffer
     fn bitmap_slice(&self) -> &[u8] {
         let slot_count = self.header.slot_count as usize;
@@ -182,6 +389,7 @@ impl<'a> Page<'a> {
     }
 
     /// Find a free slot, returns None if page is full
+    /// (on a variable-length page, a free directory entry whatever the space left)
     pub fn find_free_slot(&self) -> Option<SlotId> {
         if self.header.free_slots == 0 {
             return None;
@@ -192,6 +400,10 @@ impl<'a> Page<'a> {
 
     /// Check if a slot is used
     pub fn is_slot_used(&self, slot_id: SlotId) -> bool {
+        if self.is_variable_length() {
+            return slot_id < self.slot_count() && self.slot_entry(slot_id).0 != 0;
+        }
+
         let byte_idx = slot_id / 8;
         let bit_idx = slot_id % 8;
         let bitmap = self.bitmap_slice();
@@ -212,6 +424,14 @@ impl<'a> Page<'a> {
             return Err(RecordError::InvalidSlot(0, slot_id));
         }
 
+        if self.is_variable_length() {
+            // A variable-length slot is only taken together with its record
+            if !self.is_slot_used(slot_id) {
+                return Err(RecordError::InvalidSlot(0, slot_id));
+            }
+            return Ok(());
+        }
+
         let byte_idx = slot_id / 8;
         let bit_idx = slot_id % 8;
 
@@ -232,6 +452,17 @@ impl<'a> Page<'a> {
             return Err(RecordError::InvalidSlot(0, slot_id));
         }
 
+        if self.is_variable_length() {
+            let (offset, len) = self.slot_entry(slot_id);
+            if offset != 0 {
+                self.set_dead_bytes(self.dead_bytes() + len);
+                self.set_slot_entry(slot_id, 0, 0);
+                self.header.free_slots += 1;
+                self.buffer[..PageHeader::SIZE].copy_from_slice(&self.header.serialize());
+            }
+            return Ok(());
+        }
+
         let byte_idx = slot_id / 8;
         let bit_idx = slot_id % 8;
 
@@ -256,6 +487,11 @@ impl<'a> Page<'a> {
             return Err(RecordError::InvalidSlot(0, slot_id));
         }
 
+        if self.is_variable_length() {
+            let (offset, len) = self.slot_entry(slot_id);
+            return Ok(&self.buffer[offset..offset + len]);
+        }
+
         let record_size = self.header.record_size as usize;
         let data = self.data_slice();
         let start = slot_id * record_size;
@@ -265,11 +501,37 @@ impl<'a> Page<'a> {
     }
 
     /// Set record data in a slot
+    ///
+    /// On a variable-length page the slot must hold a record already; the
+    /// new data replaces it, or `PageFull` comes back if it no longer fits
+    /// and the page is left unchanged.
     pub fn set_record(&mut self, slot_id: SlotId, data: &[u8]) -> RecordResult<()> {
         if slot_id >= self.header.slot_count as usize {
             return Err(RecordError::InvalidSlot(0, slot_id));
         }
 
+        if self.is_variable_length() {
+            if !self.is_slot_used(slot_id) {
+                return Err(RecordError::InvalidSlot(0, slot_id));
+            }
+            let (offset, len) = self.slot_entry(slot_id);
+            if data.len() <= len {
+                self.buffer[offset..offset + data.len()].copy_from_slice(data);
+                self.set_slot_entry(slot_id, offset, data.len());
+                self.set_dead_bytes(self.dead_bytes() + len - data.len());
+                return Ok(());
+            }
+            if data.len() > self.free_space() + len {
+                return Err(RecordError::PageFull(0));
+            }
+            // Give up the old bytes so compaction can reuse them
+            self.set_slot_entry(slot_id, 0, 0);
+            self.set_dead_bytes(self.dead_bytes() + len);
+            let offset = self.place(data);
+            self.set_slot_entry(slot_id, offset, data.len());
+            return Ok(());
+        }
+
         let record_size = self.header.record_size as usize;
         if data.len() != record_size {
             return Err(RecordError::InvalidRecord(format!(
@@ -311,6 +573,9 @@ impl<'a> Page<'a> {
 
     /// Check if page is full
     pub fn is_full(&self) -> bool {
+        if self.is_variable_length() {
+            return self.header.free_slots == 0 && self.free_space() < SLOT_ENTRY_SIZE;
+        }
         self.header.free_slots == 0
     }
 
@@ -339,6 +604,20 @@ mod tests {
         assert!(slot_count > 5 && slot_count < 10);
     }
 
+    #[test]
+    fn test_max_record_size() {
+        let max = Page::max_record_size();
+        assert_eq!(Page::calculate_slot_count(max), 1);
+        assert_eq!(Page::calculate_slot_count(max + 1), 0);
+
+        let mut buffer = vec![0u8; PAGE_SIZE];
+        assert_eq!(Page::new(&mut buffer, max).unwrap().slot_count(), 1);
+        assert!(matches!(
+            Page::new(&mut buffer, max + 1),
+            Err(RecordError::RecordTooLarge { size, .. }) if size == max + 1
+        ));
+    }
+
     #[test]
     fn test_page_creation() {
         let mut buffer = vec![0u8; PAGE_SIZE];
@@ -421,9 +700,6 @@ mod tests {
         let slot_count_before = page.slot_count();
         let free_slots_before = page.free_slot_count();
 
-        // Drop page to release buffer
-        drop(page);
-
         // Restore from buffer
         let restored = Page::from_buffer(&mut buffer).unwrap();
         assert_eq!(restored.slot_count(), slot_count_before);
@@ -449,4 +725,116 @@ mod tests {
         assert!(page.is_full());
         assert_eq!(page.find_free_slot(), None);
     }
+
+    #[test]
+    fn test_variable_records_keep_slots_across_compaction() {
+        let mut buffer = vec![0u8; PAGE_SIZE];
+        let mut page = Page::new_variable(&mut buffer).unwrap();
+        assert!(page.is_variable_length());
+        assert!(page.is_empty());
+
+        let a = page.insert_record(b"alpha").unwrap().unwrap();
+        let b = page.insert_record(b"bravo").unwrap().unwrap();
+        let c = page.insert_record(b"charlie").unwrap().unwrap();
+        assert_eq!((a, b, c), (0, 1, 2));
+        assert_eq!(page.get_record(b).unwrap(), b"bravo");
+
+        // Shrinking stays in place, growing moves the record within the page
+        page.set_record(a, b"al").unwrap();
+        page.set_record(c, b"charlie-the-longer").unwrap();
+        page.mark_slot_free(b).unwrap();
+        assert!(page.is_slot_free(b));
+        assert!(page.set_record(b, b"bravo").is_err());
+
+        // The freed slot is reused first
+        assert_eq!(page.insert_record(b"delta").unwrap(), Some(b));
+
+        page.compact();
+        assert_eq!(page.dead_bytes(), 0);
+        assert_eq!(page.get_record(a).unwrap(), b"al");
+        assert_eq!(page.get_record(b).unwrap(), b"delta");
+        assert_eq!(page.get_record(c).unwrap(), b"charlie-the-longer");
+
+        let restored = Page::from_buffer(&mut buffer).unwrap();
+        assert!(restored.is_variable_length());
+        assert_eq!(restored.slot_count(), 3);
+        assert_eq!(restored.get_record(c).unwrap(), b"charlie-the-longer");
+    }
+
+    #[test]
+    fn test_variable_page_reclaims_dead_space() {
+        let mut buffer = vec![0u8; PAGE_SIZE];
+        let mut page = Page::new_variable(&mut buffer).unwrap();
+
+        let record = vec![7u8; 1000];
+        let mut slots = Vec::new();
+        while let Some(slot_id) = page.insert_record(&record).unwrap() {
+            slots.push(slot_id);
+        }
+        assert_eq!(slots.len(), 8);
+        assert!(page.is_full() || page.free_space() < record.len());
+
+        // A record that cannot grow in place leaves the page untouched
+        assert!(matches!(
+            page.set_record(slots[0], &[1u8; 1500]),
+            Err(RecordError::PageFull(_))
+        ));
+        assert_eq!(page.get_record(slots[0]).unwrap(), &record[..]);
+
+        // Space freed in the middle of the heap is found again by compacting
+        page.mark_slot_free(slots[3]).unwrap();
+        page.mark_slot_free(slots[5]).unwrap();
+        let big = vec![9u8; 1900];
+        assert_eq!(page.insert_record(&big).unwrap(), Some(slots[3]));
+        assert_eq!(page.get_record(slots[3]).unwrap(), &big[..]);
+        assert_eq!(page.get_record(slots[4]).unwrap(), &record[..]);
+
+        let max = vec![1u8; Page::max_variable_record_size()];
+        let mut buffer = vec![0u8; PAGE_SIZE];
+        let mut page = Page::new_variable(&mut buffer).unwrap();
+        assert_eq!(page.insert_record(&max).unwrap(), Some(0));
+        assert_eq!(page.insert_record(&[]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_page_packs_more_short_varchars_than_chars() {
+        use crate::record::{ColumnDef, DataType, Record, Value};
+
+        let schema = |name_type| {
+            TableSchema::new(
+                "t".to_string(),
+                vec![
+                    ColumnDef::new("id".to_string(), DataType::Int, true, Value::Null),
+                    ColumnDef::new("name".to_string(), name_type, false, Value::Null),
+                ],
+            )
+        };
+        let fill = |schema: &TableSchema| {
+            let mut buffer = vec![0u8; PAGE_SIZE];
+            let mut page = if schema.is_variable_length() {
+                Page::new_variable(&mut buffer).unwrap()
+            } else {
+                Page::new(&mut buffer, schema.record_size()).unwrap()
+            };
+            let mut count = 0;
+            loop {
+                let record = Record::new(vec![Value::Int(count), Value::String("abc".to_string())]);
+                let bytes = record.serialize(schema).unwrap();
+                match page.insert_record(&bytes).unwrap() {
+                    Some(_) => count += 1,
+                    None => return count,
+                }
+            }
+        };
+
+        let fixed = fill(&schema(DataType::Char(100)));
+        let variable = fill(&schema(DataType::Varchar(100)));
+        assert_eq!(fixed as usize, Page::calculate_slot_count(1 + 4 + 100));
+        assert!(
+            variable > 4 * fixed,
+            "{} varchars vs {} chars",
+            variable,
+            fixed
+        );
+    }
 }
@@ -1,12 +1,20 @@
 use super::error::{RecordError, RecordResult};
 use super::schema::TableSchema;
-use super::value::Value;
+use super::value::{DataType, VARCHAR_PREFIX_SIZE, Value};
 use crate::file::PageId;
 
 /// Slot identifier within a page
 pub type SlotId = usize;
 
 /// Physical identifier for a record (page + slot)
+///
+/// A RID stays valid for as long as the row exists: fixed-length records are
+/// updated in place, and compacting a page of variable-length records keeps
+/// their slot numbers. The one exception is an `UPDATE` that grows a
+/// variable-length record past the free space of its page, which moves the
+/// row and hands back its new RID. Once a row is deleted its slot is free
+/// and a later insert may reuse the same RID for a different row. Dropping
+/// and recreating a table (or re-importing it) invalidates all of its RIDs.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct RecordId {
     pub page_id: PageId,
@@ -51,6 +59,11 @@ impl Record {
         &self.values
     }
 
+    /// Consume the record and return its values
+    pub fn into_values(self) -> Vec<Value> {
+        self.values
+    }
+
     /// Set a value by index
     pub fn set(&mut self, idx: usize, value: Value) {
         if idx < self.values.len() {
@@ -60,6 +73,8 @@ impl Record {
 
     /// Serialize record to bytes according to schema
     /// Format: [NULL bitmap] [col0 data] [col1 data] ...
+    /// A VARCHAR column's data is its length prefix and only its own bytes,
+    /// so such records are shorter than `record_size`
     pub fn serialize(&self, schema: &TableSchema) -> RecordResult<Vec<u8>> {
         // Validate record against schema
         schema.validate_record(&self.values)?;
@@ -90,6 +105,9 @@ impl Record {
 
     /// Deserialize record from bytes according to schema
     pub fn deserialize(data: &[u8], schema: &TableSchema) -> RecordResult<Self> {
+        if schema.is_variable_length() {
+            return Self::deserialize_variable(data, schema);
+        }
         if data.len() != schema.record_size() {
             return Err(RecordError::Deserialization(format!(
                 "Expected {} bytes, got {}",
@@ -121,6 +139,45 @@ impl Record {
 
         Ok(Record { values })
     }
+
+    /// Deserialize a record whose VARCHAR columns are length-prefixed
+    fn deserialize_variable(data: &[u8], schema: &TableSchema) -> RecordResult<Self> {
+        let truncated =
+            || RecordError::Deserialization(format!("Record truncated at {} bytes", data.len()));
+        let bitmap_size = schema.null_bitmap_size();
+        let bitmap = data.get(..bitmap_size).ok_or_else(truncated)?;
+        let mut offset = bitmap_size;
+
+        let mut values = Vec::with_capacity(schema.column_count());
+
+        for (i, col) in schema.columns().iter().enumerate() {
+            let is_null = (bitmap[i / 8] & (1 << (i % 8))) != 0;
+
+            let col_size = match col.data_type {
+                DataType::Varchar(_) => {
+                    let prefix = data
+                        .get(offset..offset + VARCHAR_PREFIX_SIZE)
+                        .ok_or_else(truncated)?;
+                    VARCHAR_PREFIX_SIZE + u16::from_le_bytes([prefix[0], prefix[1]]) as usize
+                }
+                _ => col.size(),
+            };
+            let col_data = data.get(offset..offset + col_size).ok_or_else(truncated)?;
+            values.push(Value::deserialize(col_data, &col.data_type, is_null)?);
+
+            offset += col_size;
+        }
+
+        if offset != data.len() {
+            return Err(RecordError::Deserialization(format!(
+                "Expected {} bytes, got {}",
+                offset,
+                data.len()
+            )));
+        }
+
+        Ok(Record { values })
+    }
 }
 
 #[cfg(test)]
@@ -153,6 +210,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_record_serialization() {
         let schema = create_test_schema();
         let record = Record::new(vec![
@@ -206,6 +264,61 @@ mod tests {
         assert_eq!(original, restored);
     }
 
+    #[test]
+    fn test_varchar_record_round_trip() {
+        let schema = TableSchema::new(
+            "test".to_string(),
+            vec![
+                ColumnDef::new("id".to_string(), DataType::Int, true, Value::Null),
+                ColumnDef::new(
+                    "name".to_string(),
+                    DataType::Varchar(50),
+                    false,
+                    Value::Null,
+                ),
+                ColumnDef::new("note".to_string(), DataType::Varchar(8), false, Value::Null),
+                ColumnDef::new("score".to_string(), DataType::Float, false, Value::Null),
+            ],
+        );
+
+        let records = [
+            Record::new(vec![
+                Value::Int(1),
+                Value::String("Alice".to_string()),
+                Value::String(String::new()),
+                Value::Float(95.5),
+            ]),
+            Record::new(vec![Value::Int(2), Value::Null, Value::Null, Value::Null]),
+            Record::new(vec![
+                Value::Int(3),
+                Value::String("x".repeat(50)),
+                Value::String("tail".to_string()),
+                Value::Float(-1.0),
+            ]),
+        ];
+        for record in &records {
+            let bytes = record.serialize(&schema).unwrap();
+            assert_eq!(Record::deserialize(&bytes, &schema).unwrap(), *record);
+        }
+
+        // Only the actual bytes are stored: bitmap, int, 2+5, 2+0, float
+        let bytes = records[0].serialize(&schema).unwrap();
+        assert_eq!(bytes.len(), 1 + 4 + 7 + 2 + 8);
+        assert!(bytes.len() < schema.record_size());
+        assert!(Record::deserialize(&bytes[..bytes.len() - 1], &schema).is_err());
+
+        let too_long = Record::new(vec![
+            Value::Int(4),
+            Value::String("x".repeat(51)),
+            Value::Null,
+            Value::Null,
+        ]);
+        assert!(matches!(
+            too_long.serialize(&schema),
+            Err(RecordError::TypeMismatch { .. })
+        ));
+    }
+
     #[test]
     fn test_record_validation_error() {
         let schema = create_test_schema();
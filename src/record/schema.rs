@@ -1,5 +1,5 @@
 use super::error::{RecordError, RecordResult};
-use super::value::{DataType, Value};
+use super::value::{DataType, Value, decimal_fits};
 
 /// Column definition with metadata
 #[derive(Debug, Clone)]
@@ -34,6 +34,7 @@ pub struct TableSchema {
     pub columns: Vec<ColumnDef>,
     null_bitmap_size: usize,
     record_size: usize,
+    variable_length: bool,
 }
 
 impl TableSchema {
@@ -41,12 +42,14 @@ impl TableSchema {
     pub fn new(table_name: String, columns: Vec<ColumnDef>) -> Self {
         let null_bitmap_size = columns.len().div_ceil(8); // ⌈n/8⌉
         let record_size = null_bitmap_size + columns.iter().map(|c| c.size()).sum::<usize>();
+        let variable_length = columns.iter().any(|c| c.data_type.is_variable_length());
 
         Self {
             table_name,
             columns,
             null_bitmap_size,
             record_size,
+            variable_length,
         }
     }
 
@@ -80,12 +83,19 @@ impl TableSchema {
         self.null_bitmap_size
     }
 
-    /// Get total record size in bytes (including NULL bitmap)
+    /// Get total record size in bytes (including NULL bitmap); for a
+    /// variable-length schema this is the largest a record can be
     pub fn record_size(&self) -> usize {
         self.record_size
     }
 
-    /// Get the byte offset of a column within a record (after NULL bitmap)
+    /// Whether records vary in size, i.e. some column is a VARCHAR
+    pub fn is_variable_length(&self) -> bool {
+        self.variable_length
+    }
+
+    /// Get the byte offset of a column within a record (after NULL bitmap);
+    /// columns after a VARCHAR only start there when it is at its longest
     pub fn column_offset(&self, col_idx: usize) -> usize {
         self.null_bitmap_size
             + self.columns[..col_idx]
@@ -119,6 +129,10 @@ impl TableSchema {
                     (DataType::Int, Value::Int(_)) => {}
                     (DataType::Float, Value::Float(_)) => {}
                     (DataType::Char(_), Value::String(_)) => {}
+                    (DataType::Varchar(max_len), Value::String(s)) if s.len() <= *max_len => {}
+                    (DataType::Bool, Value::Bool(_)) => {}
+                    (DataType::Decimal(precision, scale), Value::Decimal(units, value_scale))
+                        if scale == value_scale && decimal_fits(*units, *precision) => {}
                     (dt, val) => {
                         return Err(RecordError::TypeMismatch {
                             expected: format!("{:?}", dt),
@@ -162,6 +176,27 @@ mod tests {
         assert_eq!(schema.record_size(), 1 + 4 + 20 + 8); // bitmap + int + char(20) + float
     }
 
+    #[test]
+    fn test_varchar_schema() {
+        let schema = TableSchema::new(
+            "t".to_string(),
+            vec![
+                ColumnDef::new("id".to_string(), DataType::Int, true, Value::Null),
+                ColumnDef::new("name".to_string(), DataType::Varchar(5), false, Value::Null),
+            ],
+        );
+        assert!(schema.is_variable_length());
+        assert!(!create_test_schema().is_variable_length());
+        assert_eq!(schema.record_size(), 1 + 4 + 2 + 5);
+
+        let values = |name: &str| vec![Value::Int(1), Value::String(name.to_string())];
+        assert!(schema.validate_record(&values("hello")).is_ok());
+        assert!(matches!(
+            schema.validate_record(&values("hello!")),
+            Err(RecordError::TypeMismatch { .. })
+        ));
+    }
+
     #[test]
     fn test_column_offset() {
         let schema = create_test_schema();
@@ -1,9 +1,9 @@
-use super::error::RecordResult;
+use super::error::{RecordError, RecordResult};
 use super::page::Page;
 use super::record::{Record, RecordId};
 use super::schema::TableSchema;
-use crate::file::{BufferManager, FileHandle, PageId};
-use std::sync::{Arc, Mutex};
+use crate::file::{BufferManager, FileHandle, PAGE_SIZE, PageId};
+use std::sync::{Arc, RwLock};
 
 /// Manages a table's file with multiple pages
 pub struct TableFile {
@@ -21,13 +21,23 @@ impl TableFile {
         path: &str,
         schema: TableSchema,
     ) -> RecordResult<Self> {
+        // Records never span pages, so even the largest one a schema allows
+        // must fit; refuse before leaving an unusable file behind
+        if schema.record_size() > Page::max_record_size_for(&schema) {
+            return Err(RecordError::RecordTooLarge {
+                size: schema.record_size(),
+                max: Page::max_record_size_for(&schema),
+            });
+        }
+
         // Create the file
         buffer_mgr.file_manager_mut().create_file(path)?;
         let file_handle = buffer_mgr.file_manager_mut().open_file(path)?;
+        buffer_mgr.enable_checksums(file_handle);
 
         // Create the first page - zero-copy directly in buffer
         let page_buffer = buffer_mgr.get_page_mut(file_handle, 0)?;
-        Page::new(page_buffer, schema.record_size())?;
+        Self::init_page(page_buffer, &schema)?;
 
         Ok(Self {
             file_handle,
@@ -45,6 +55,7 @@ impl TableFile {
         schema: TableSchema,
     ) -> RecordResult<Self> {
         let file_handle = buffer_mgr.file_manager_mut().open_file(path)?;
+        buffer_mgr.enable_checksums(file_handle);
         let page_count = buffer_mgr.file_manager_mut().get_page_count(file_handle)?;
 
         Ok(Self {
@@ -100,10 +111,7 @@ impl TableFile {
             let mut page = Page::from_buffer(page_buffer)?;
 
             // Check if page has free space
-            if let Some(slot_id) = page.find_free_slot() {
-                // Found free slot, insert record
-                page.set_record(slot_id, &record_bytes)?;
-                page.mark_slot_used(slot_id)?;
+            if let Some(slot_id) = page.insert_record(&record_bytes)? {
                 // No need to write back - page modified buffer in-place!
 
                 // Update last insert location for next time
@@ -150,13 +158,16 @@ impl TableFile {
         Ok(())
     }
 
-    /// Update a record in the table (in-place for fixed-length records)
+    /// Update a record in the table, returning its RID afterwards
+    ///
+    /// Records are updated in place; only a variable-length record that no
+    /// longer fits its page is moved, and then the returned RID differs.
     pub fn update_record(
         &mut self,
         buffer_mgr: &mut BufferManager,
         rid: RecordId,
         record: &Record,
-    ) -> RecordResult<()> {
+    ) -> RecordResult<RecordId> {
         // Validate record
         self.schema.validate_record(record.values())?;
 
@@ -168,9 +179,16 @@ impl TableFile {
         let mut page = Page::from_buffer(page_buffer)?;
 
         // Update record in slot (modifies buffer in-place)
-        page.set_record(rid.slot_id, &record_bytes)?;
-
-        Ok(())
+        match page.set_record(rid.slot_id, &record_bytes) {
+            Ok(()) => Ok(rid),
+            Err(RecordError::PageFull(_)) => {
+                // Store the new version before giving up the old one
+                let new_rid = self.insert_record(buffer_mgr, record)?;
+                self.delete_record(buffer_mgr, rid)?;
+                Ok(new_rid)
+            }
+            Err(err) => Err(err),
+        }
     }
 
     /// Get a record from the table
@@ -179,6 +197,10 @@ impl TableFile {
         buffer_mgr: &mut BufferManager,
         rid: RecordId,
     ) -> RecordResult<Record> {
+        if rid.page_id >= self.page_count {
+            return Err(RecordError::InvalidSlot(rid.page_id, rid.slot_id));
+        }
+
         // Load the page - for reads we need to work around immutable borrow
         // We'll use get_page_mut but treat it as read-only
         let page_buffer = buffer_mgr.get_page_mut(self.file_handle, rid.page_id)?;
@@ -231,8 +253,14 @@ impl TableFile {
 
     /// Create a streaming iterator over all records in the table.
     /// This avoids loading the entire table into memory at once.
-    pub fn scan_iter(&self, buffer_manager: Arc<Mutex<BufferManager>>) -> TableScanIter {
-        TableScanIter::new(self, buffer_manager)
+    pub fn scan_iter(&self, buffer_manager: Arc<RwLock<BufferManager>>) -> TableScanIter {
+        TableScanIter::new(self, buffer_manager, false)
+    }
+
+    /// Create a streaming iterator over all records in descending RID order.
+    /// Inserts fill pages front to back, so this approximates newest-first.
+    pub fn scan_iter_rev(&self, buffer_manager: Arc<RwLock<BufferManager>>) -> TableScanIter {
+        TableScanIter::new(self, buffer_manager, true)
     }
 
     /// Allocate a new page and link it from the previous page
@@ -246,7 +274,7 @@ impl TableFile {
 
         // Create new page directly in buffer
         let page_buffer = buffer_mgr.get_page_mut(self.file_handle, new_page_id)?;
-        Page::new(page_buffer, self.schema.record_size())?;
+        Self::init_page(page_buffer, &self.schema)?;
 
         // Update previous page's next_page pointer
         let prev_page_buffer = buffer_mgr.get_page_mut(self.file_handle, prev_page_id)?;
@@ -255,6 +283,16 @@ impl TableFile {
 
         Ok(new_page_id)
     }
+
+    /// Format an empty page for `schema`'s records
+    fn init_page(buffer: &mut [u8], schema: &TableSchema) -> RecordResult<()> {
+        if schema.is_variable_length() {
+            Page::new_variable(buffer)?;
+        } else {
+            Page::new(buffer, schema.record_size())?;
+        }
+        Ok(())
+    }
 }
 
 /// Streaming table scan iterator (yields records one-by-one).
@@ -262,21 +300,37 @@ pub struct TableScanIter {
     file_handle: FileHandle,
     schema: TableSchema,
     page_count: usize,
-    buffer_manager: Arc<Mutex<BufferManager>>,
+    buffer_manager: Arc<RwLock<BufferManager>>,
+    first_page_id: PageId,
     page_id: PageId,
+    /// Next slot to look at; in reverse, the slots below it remain
     slot_id: usize,
+    reverse: bool,
+    /// Private copy of the current page, so records are decoded without holding the lock
+    page_buffer: Vec<u8>,
+    page_loaded: bool,
     done: bool,
 }
 
 impl TableScanIter {
-    fn new(table: &TableFile, buffer_manager: Arc<Mutex<BufferManager>>) -> Self {
+    fn new(table: &TableFile, buffer_manager: Arc<RwLock<BufferManager>>, reverse: bool) -> Self {
+        // Pages are only ever appended to the end of the chain, so the chain
+        // runs through page ids in ascending order
         Self {
             file_handle: table.file_handle,
             schema: table.schema.clone(),
             page_count: table.page_count,
-            page_id: table.first_page_id,
+            first_page_id: table.first_page_id,
+            page_id: if reverse {
+                table.page_count - 1
+            } else {
+                table.first_page_id
+            },
             buffer_manager,
-            slot_id: 0,
+            slot_id: if reverse { usize::MAX } else { 0 },
+            reverse,
+            page_buffer: vec![0u8; PAGE_SIZE],
+            page_loaded: false,
             done: false,
         }
     }
@@ -296,17 +350,48 @@ impl Iterator for TableScanIter {
                 return None;
             }
 
-            let mut buffer_manager = self.buffer_manager.lock().unwrap();
-            let page_buffer = match buffer_manager.get_page_mut(self.file_handle, self.page_id) {
-                Ok(buf) => buf,
-                Err(err) => return Some(Err(err.into())),
-            };
+            if !self.page_loaded {
+                // Only a read lock on cache hits, so concurrent scans proceed in parallel
+                if let Err(err) = BufferManager::read_page_shared(
+                    &self.buffer_manager,
+                    self.file_handle,
+                    self.page_id,
+                    &mut self.page_buffer,
+                ) {
+                    return Some(Err(err.into()));
+                }
+                self.page_loaded = true;
+            }
 
-            let page = match Page::from_buffer(page_buffer) {
+            let page = match Page::from_buffer(&mut self.page_buffer) {
                 Ok(page) => page,
                 Err(err) => return Some(Err(err)),
             };
 
+            if self.reverse {
+                for slot_id in (0..self.slot_id.min(page.slot_count())).rev() {
+                    if page.is_slot_used(slot_id) {
+                        let record = match page.get_record(slot_id) {
+                            Ok(bytes) => Record::deserialize(bytes, &self.schema),
+                            Err(err) => Err(err),
+                        };
+                        self.slot_id = slot_id;
+                        return Some(
+                            record.map(|record| (RecordId::new(self.page_id, slot_id), record)),
+                        );
+                    }
+                }
+
+                self.slot_id = usize::MAX;
+                self.page_loaded = false;
+                if self.page_id == self.first_page_id {
+                    self.done = true;
+                    return None;
+                }
+                self.page_id -= 1;
+                continue;
+            }
+
             for slot_id in self.slot_id..page.slot_count() {
                 if page.is_slot_used(slot_id) {
                     let record_bytes = match page.get_record(slot_id) {
@@ -325,6 +410,7 @@ impl Iterator for TableScanIter {
 
             let next_page = page.next_page();
             self.slot_id = 0;
+            self.page_loaded = false;
             if next_page == 0 {
                 self.done = true;
                 return None;
@@ -371,6 +457,27 @@ mod tests {
         assert_eq!(table.page_count, 1);
     }
 
+    #[test]
+    fn test_create_rejects_oversized_records() {
+        let (temp_dir, mut buffer_mgr) = setup_test_env();
+        let test_file = temp_dir.path().join("test.tbl");
+        let schema = TableSchema::new(
+            "wide".to_string(),
+            vec![ColumnDef::new(
+                "text".to_string(),
+                DataType::Char(PAGE_SIZE),
+                false,
+                Value::Null,
+            )],
+        );
+
+        assert!(matches!(
+            TableFile::create(&mut buffer_mgr, test_file.to_str().unwrap(), schema),
+            Err(RecordError::RecordTooLarge { .. })
+        ));
+        assert!(!test_file.exists());
+    }
+
     #[test]
     fn test_insert_and_get_record() {
         let (temp_dir, mut buffer_mgr) = setup_test_env();
@@ -548,4 +655,153 @@ mod tests {
             assert_eq!(record.values()[0], Value::Int(i as i32));
         }
     }
+
+    #[test]
+    fn test_concurrent_scan_iters() {
+        let (temp_dir, mut buffer_mgr) = setup_test_env();
+        let test_file = temp_dir.path().join("test.tbl");
+        let schema = create_test_schema();
+
+        let mut table =
+            TableFile::create(&mut buffer_mgr, test_file.to_str().unwrap(), schema).unwrap();
+        for i in 0..1000 {
+            let record = Record::new(vec![
+                Value::Int(i),
+                Value::String(format!("User{}", i)),
+                Value::Float(i as f64),
+            ]);
+            table.insert_record(&mut buffer_mgr, &record).unwrap();
+        }
+
+        // Readers share the buffer manager; cached pages are served under the read lock
+        let shared = Arc::new(RwLock::new(buffer_mgr));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let iter = table.scan_iter(shared.clone());
+                std::thread::spawn(move || {
+                    let mut sum = 0i64;
+                    for item in iter {
+                        let (_rid, record) = item.unwrap();
+                        if let Value::Int(v) = record.values()[0] {
+                            sum += v as i64;
+                        }
+                    }
+                    sum
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), (0..1000).sum::<i64>());
+        }
+    }
+
+    #[test]
+    fn test_scan_iter_rev() {
+        let (temp_dir, mut buffer_mgr) = setup_test_env();
+        let test_file = temp_dir.path().join("test.tbl");
+        let schema = create_test_schema();
+
+        let mut table =
+            TableFile::create(&mut buffer_mgr, test_file.to_str().unwrap(), schema).unwrap();
+        let mut rids = Vec::new();
+        for i in 0..500 {
+            let record = Record::new(vec![
+                Value::Int(i),
+                Value::String(format!("User{}", i)),
+                Value::Float(i as f64),
+            ]);
+            rids.push(table.insert_record(&mut buffer_mgr, &record).unwrap());
+        }
+        assert!(rids.last().unwrap().page_id > 0);
+
+        // Holes, including a whole page's worth at the start, are skipped
+        for rid in rids
+            .iter()
+            .filter(|rid| rid.page_id == 0 || rid.slot_id % 3 == 0)
+        {
+            table.delete_record(&mut buffer_mgr, *rid).unwrap();
+        }
+
+        let shared = Arc::new(RwLock::new(buffer_mgr));
+        let forward: Vec<_> = table
+            .scan_iter(shared.clone())
+            .map(|item| item.unwrap())
+            .collect();
+        let mut backward: Vec<_> = table
+            .scan_iter_rev(shared)
+            .map(|item| item.unwrap())
+            .collect();
+        assert!(!forward.is_empty());
+        assert_eq!(backward.first(), forward.last());
+        backward.reverse();
+        assert_eq!(backward, forward);
+    }
+
+    #[test]
+    fn test_varchar_update_moves_record_that_outgrows_page() {
+        let (temp_dir, mut buffer_mgr) = setup_test_env();
+        let test_file = temp_dir.path().join("test.tbl");
+        let schema = TableSchema::new(
+            "notes".to_string(),
+            vec![
+                ColumnDef::new("id".to_string(), DataType::Int, true, Value::Null),
+                ColumnDef::new(
+                    "text".to_string(),
+                    DataType::Varchar(4000),
+                    false,
+                    Value::Null,
+                ),
+            ],
+        );
+
+        let mut table =
+            TableFile::create(&mut buffer_mgr, test_file.to_str().unwrap(), schema).unwrap();
+        let note =
+            |id: i32, len: usize| Record::new(vec![Value::Int(id), Value::String("n".repeat(len))]);
+        let rids: Vec<RecordId> = (0..4)
+            .map(|id| {
+                table
+                    .insert_record(&mut buffer_mgr, &note(id, 1500))
+                    .unwrap()
+            })
+            .collect();
+        assert!(rids.iter().all(|rid| rid.page_id == 0));
+
+        // Shrinking and modest growth stay in place
+        let rid = table
+            .update_record(&mut buffer_mgr, rids[1], &note(1, 10))
+            .unwrap();
+        assert_eq!(rid, rids[1]);
+        let rid = table
+            .update_record(&mut buffer_mgr, rids[2], &note(2, 3000))
+            .unwrap();
+        assert_eq!(rid, rids[2]);
+
+        // Growing past the page's free space moves the row
+        let moved = table
+            .update_record(&mut buffer_mgr, rids[3], &note(3, 4000))
+            .unwrap();
+        assert_ne!(moved.page_id, 0);
+        assert!(table.get_record(&mut buffer_mgr, rids[3]).is_err());
+        assert_eq!(
+            table.get_record(&mut buffer_mgr, moved).unwrap(),
+            note(3, 4000)
+        );
+
+        let mut rows: Vec<Record> = table
+            .scan(&mut buffer_mgr)
+            .unwrap()
+            .into_iter()
+            .map(|(_, record)| record)
+            .collect();
+        rows.sort_by_key(|record| match record.values()[0] {
+            Value::Int(id) => id,
+            _ => unreachable!(),
+        });
+        assert_eq!(
+            rows,
+            vec![note(0, 1500), note(1, 10), note(2, 3000), note(3, 4000)]
+        );
+    }
 }
@@ -1,13 +1,24 @@
+use std::cmp::Ordering;
+
 use super::error::{RecordError, RecordResult};
 
 /// Represents a column data type
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DataType {
-    Int,         // 4 bytes
-    Float,       // 8 bytes
-    Char(usize), // n bytes (fixed length)
+    Int,             // 4 bytes
+    Float,           // 8 bytes
+    Char(usize),     // n bytes (fixed length)
+    Varchar(usize),  // 2-byte length prefix + up to n bytes
+    Decimal(u8, u8), // 8 bytes: (precision, scale), stored as i64 units of 10^-scale
+    Bool,            // 1 byte
 }
 
+/// Bytes of the length prefix in front of a VARCHAR value
+pub const VARCHAR_PREFIX_SIZE: usize = 2;
+
+/// Largest DECIMAL precision whose values still fit an `i64` of scaled units
+pub const MAX_DECIMAL_PRECISION: u8 = 18;
+
 impl DataType {
     /// Get the size in bytes for this data type
     pub fn size(&self) -> usize {
@@ -15,25 +26,123 @@ impl DataType {
             DataType::Int => 4,
             DataType::Float => 8,
             DataType::Char(n) => *n,
+            DataType::Varchar(n) => VARCHAR_PREFIX_SIZE + *n,
+            DataType::Decimal(..) => 8,
+            DataType::Bool => 1,
         }
     }
 
+    /// Whether values of this type take a varying number of bytes; `size`
+    /// is then the most they can take
+    pub fn is_variable_length(&self) -> bool {
+        matches!(self, DataType::Varchar(_))
+    }
+
     /// Convert from parser's ColumnType
     pub fn from_column_type(ct: &crate::lexer_parser::ColumnType) -> Self {
         match ct {
             crate::lexer_parser::ColumnType::Int => DataType::Int,
             crate::lexer_parser::ColumnType::Float => DataType::Float,
-            crate::lexer_parser::ColumnType::Char(n) => DataType::Char(*n),
+            crate::lexer_parser::ColumnType::Char(n) => DataType::Varchar(*n),
+            crate::lexer_parser::ColumnType::Decimal(precision, scale) => {
+                DataType::Decimal(*precision, *scale)
+            }
+            crate::lexer_parser::ColumnType::Bool => DataType::Bool,
         }
     }
 }
 
+/// Parse decimal text such as `-12.345` into units of 10^-`scale`, rounding
+/// extra fractional digits half away from zero
+///
+/// Returns `None` for text that is not a plain decimal number or whose
+/// units overflow an `i64`.
+pub fn parse_decimal(text: &str, scale: u8) -> Option<i64> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+    if (int_part.is_empty() && frac_part.is_empty())
+        || !int_part
+            .bytes()
+            .chain(frac_part.bytes())
+            .all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let push_digit = |units: i64, digit: u8| units.checked_mul(10)?.checked_add(i64::from(digit));
+    let mut units = 0i64;
+    for digit in int_part.bytes() {
+        units = push_digit(units, digit - b'0')?;
+    }
+    let mut frac = frac_part.bytes();
+    for _ in 0..scale {
+        units = push_digit(units, frac.next().map_or(0, |digit| digit - b'0'))?;
+    }
+    if frac.next().is_some_and(|digit| digit >= b'5') {
+        units = units.checked_add(1)?;
+    }
+    Some(if negative { -units } else { units })
+}
+
+/// Render units of 10^-`scale` with exactly `scale` fractional digits
+pub fn format_decimal(units: i64, scale: u8) -> String {
+    let sign = if units < 0 { "-" } else { "" };
+    let abs = units.unsigned_abs();
+    if scale == 0 {
+        return format!("{}{}", sign, abs);
+    }
+    let factor = 10u64.pow(u32::from(scale));
+    format!(
+        "{}{}.{:0width$}",
+        sign,
+        abs / factor,
+        abs % factor,
+        width = usize::from(scale)
+    )
+}
+
+/// Whether `units` has at most `precision` digits in total
+pub fn decimal_fits(units: i64, precision: u8) -> bool {
+    units.unsigned_abs() < 10u64.pow(u32::from(precision))
+}
+
+/// Convert units of 10^-`from` to units of 10^-`to`, rounding half away
+/// from zero when digits are dropped; `None` if the result overflows
+pub fn rescale_decimal(units: i64, from: u8, to: u8) -> Option<i64> {
+    if to >= from {
+        return units.checked_mul(10i64.checked_pow(u32::from(to - from))?);
+    }
+    let factor = 10i128.pow(u32::from(from - to));
+    let units = i128::from(units);
+    let half = if units < 0 { -factor / 2 } else { factor / 2 };
+    i64::try_from((units + half) / factor).ok()
+}
+
+/// Compare two decimals of possibly different scales exactly
+pub fn compare_decimals(left: i64, left_scale: u8, right: i64, right_scale: u8) -> Ordering {
+    let scale = left_scale.max(right_scale);
+    let widen = |units: i64, from: u8| i128::from(units) * 10i128.pow(u32::from(scale - from));
+    widen(left, left_scale).cmp(&widen(right, right_scale))
+}
+
+/// Nearest `f64` to a decimal
+pub fn decimal_to_f64(units: i64, scale: u8) -> f64 {
+    units as f64 / 10f64.powi(i32::from(scale))
+}
+
 /// Represents a single column value
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Int(i32),
     Float(f64),
     String(String),
+    /// Units of 10^-scale and the scale, e.g. `Decimal(1250, 2)` is 12.50
+    Decimal(i64, u8),
+    Bool(bool),
     Null,
 }
 
@@ -48,17 +157,26 @@ impl Value {
         match self {
             Value::Int(_) => Some(DataType::Int),
             Value::Float(_) => Some(DataType::Float),
-            Value::String(_) => None, // Need max_len from schema
+            Value::String(_) => None,   // Need max_len from schema
+            Value::Decimal(..) => None, // Need precision from schema
+            Value::Bool(_) => Some(DataType::Bool),
             Value::Null => None,
         }
     }
 
     /// Serialize value to bytes
-    /// For String, max_len must be provided and value is padded to that length
+    /// For CHAR the string is padded to max_len; VARCHAR stores a 2-byte
+    /// length followed by only the string's own bytes
     pub fn serialize(&self, data_type: &DataType) -> RecordResult<Vec<u8>> {
         match (self, data_type) {
             (Value::Int(i), DataType::Int) => Ok(i.to_le_bytes().to_vec()),
             (Value::Float(f), DataType::Float) => Ok(f.to_le_bytes().to_vec()),
+            (Value::Decimal(units, scale), DataType::Decimal(_, column_scale))
+                if scale == column_scale =>
+            {
+                Ok(units.to_le_bytes().to_vec())
+            }
+            (Value::Bool(b), DataType::Bool) => Ok(vec![u8::from(*b)]),
             (Value::String(s), DataType::Char(max_len)) => {
                 let bytes = s.as_bytes();
                 if bytes.len() > *max_len {
@@ -72,6 +190,21 @@ impl Value {
                 result[..bytes.len()].copy_from_slice(bytes);
                 Ok(result)
             }
+            (Value::String(s), DataType::Varchar(max_len)) => {
+                let bytes = s.as_bytes();
+                if bytes.len() > *max_len {
+                    return Err(RecordError::TypeMismatch {
+                        expected: format!("{:?}", data_type),
+                        actual: format!("string of {} bytes", bytes.len()),
+                    });
+                }
+                let mut result = Vec::with_capacity(VARCHAR_PREFIX_SIZE + bytes.len());
+                result.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+                result.extend_from_slice(bytes);
+                Ok(result)
+            }
+            // An empty length prefix
+            (Value::Null, DataType::Varchar(_)) => Ok(vec![0u8; VARCHAR_PREFIX_SIZE]),
             (Value::Null, _) => {
                 // NULL values are represented by zeros
                 Ok(vec![0u8; data_type.size()])
@@ -112,6 +245,25 @@ impl Value {
                 buf.copy_from_slice(bytes);
                 Ok(Value::Float(f64::from_le_bytes(buf)))
             }
+            DataType::Decimal(_, scale) => {
+                if bytes.len() != 8 {
+                    return Err(RecordError::Deserialization(format!(
+                        "Expected 8 bytes for DECIMAL, got {}",
+                        bytes.len()
+                    )));
+                }
+                let mut buf = [0u8; 8];
+                buf.copy_from_slice(bytes);
+                Ok(Value::Decimal(i64::from_le_bytes(buf), *scale))
+            }
+            DataType::Bool => match bytes {
+                [0] => Ok(Value::Bool(false)),
+                [1] => Ok(Value::Bool(true)),
+                _ => Err(RecordError::Deserialization(format!(
+                    "Expected a 0 or 1 byte for BOOLEAN, got {:?}",
+                    bytes
+                ))),
+            },
             DataType::Char(max_len) => {
                 if bytes.len() != *max_len {
                     return Err(RecordError::Deserialization(format!(
@@ -127,16 +279,35 @@ impl Value {
                     .map_err(|e| RecordError::Deserialization(format!("Invalid UTF-8: {}", e)))?;
                 Ok(Value::String(s))
             }
+            DataType::Varchar(max_len) => {
+                let len = match bytes {
+                    [lo, hi, ..] => u16::from_le_bytes([*lo, *hi]) as usize,
+                    _ => usize::MAX,
+                };
+                if len > *max_len || bytes.len() != VARCHAR_PREFIX_SIZE + len {
+                    return Err(RecordError::Deserialization(format!(
+                        "Invalid VARCHAR({}) value of {} bytes",
+                        max_len,
+                        bytes.len()
+                    )));
+                }
+                let s = String::from_utf8(bytes[VARCHAR_PREFIX_SIZE..].to_vec())
+                    .map_err(|e| RecordError::Deserialization(format!("Invalid UTF-8: {}", e)))?;
+                Ok(Value::String(s))
+            }
         }
     }
 
-    /// Convert from parser's Value
+    /// Convert from parser's Value (an unbound placeholder becomes NULL)
     pub fn from_parser_value(pv: &crate::lexer_parser::Value) -> Self {
         match pv {
             crate::lexer_parser::Value::Integer(i) => Value::Int(*i as i32),
             crate::lexer_parser::Value::Float(f) => Value::Float(*f),
             crate::lexer_parser::Value::String(s) => Value::String(s.clone()),
-            crate::lexer_parser::Value::Null => Value::Null,
+            crate::lexer_parser::Value::Bool(b) => Value::Bool(*b),
+            crate::lexer_parser::Value::Null | crate::lexer_parser::Value::Placeholder(_) => {
+                Value::Null
+            }
         }
     }
 }
@@ -154,6 +325,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_value_is_null() {
         assert!(Value::Null.is_null());
         assert!(!Value::Int(42).is_null());
@@ -173,6 +345,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_float_serialization() {
         let val = Value::Float(3.14159);
         let dt = DataType::Float;
@@ -183,6 +356,43 @@ mod tests {
         assert_eq!(val, deserialized);
     }
 
+    #[test]
+    fn test_bool_serialization() {
+        let dt = DataType::Bool;
+        for val in [Value::Bool(true), Value::Bool(false)] {
+            let bytes = val.serialize(&dt).unwrap();
+            assert_eq!(bytes.len(), 1);
+            assert_eq!(Value::deserialize(&bytes, &dt, false).unwrap(), val);
+        }
+        assert!(Value::Int(1).serialize(&dt).is_err());
+        assert!(Value::deserialize(&[2], &dt, false).is_err());
+    }
+
+    #[test]
+    fn test_decimal_serialization_and_text() {
+        let dt = DataType::Decimal(10, 2);
+        let val = Value::Decimal(-1250, 2);
+        let bytes = val.serialize(&dt).unwrap();
+        assert_eq!(bytes.len(), 8);
+        assert_eq!(Value::deserialize(&bytes, &dt, false).unwrap(), val);
+        // The value must already be at the column's scale
+        assert!(Value::Decimal(125, 1).serialize(&dt).is_err());
+
+        assert_eq!(parse_decimal("12.345", 2), Some(1235));
+        assert_eq!(parse_decimal("-12.344", 2), Some(-1234));
+        assert_eq!(parse_decimal(".5", 0), Some(1));
+        assert_eq!(parse_decimal("7", 3), Some(7000));
+        assert_eq!(parse_decimal("1e5", 2), None);
+        assert_eq!(parse_decimal("-", 2), None);
+        assert_eq!(parse_decimal("99999999999999999999", 0), None);
+
+        assert_eq!(format_decimal(-1250, 2), "-12.50");
+        assert_eq!(format_decimal(-5, 2), "-0.05");
+        assert_eq!(format_decimal(42, 0), "42");
+        assert!(decimal_fits(99_999, 5));
+        assert!(!decimal_fits(-100_000, 5));
+    }
+
     #[test]
     fn test_string_serialization() {
         let val = Value::String("hello".to_string());
@@ -204,6 +414,29 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_varchar_serialization() {
+        let dt = DataType::Varchar(10);
+        assert_eq!(dt.size(), 12);
+        for text in ["", "hello", "0123456789"] {
+            let val = Value::String(text.to_string());
+            let bytes = val.serialize(&dt).unwrap();
+            assert_eq!(bytes.len(), VARCHAR_PREFIX_SIZE + text.len());
+            assert_eq!(Value::deserialize(&bytes, &dt, false).unwrap(), val);
+        }
+
+        let bytes = Value::Null.serialize(&dt).unwrap();
+        assert_eq!(bytes, vec![0u8; VARCHAR_PREFIX_SIZE]);
+        assert_eq!(Value::deserialize(&bytes, &dt, true).unwrap(), Value::Null);
+
+        assert!(matches!(
+            Value::String("hello world".to_string()).serialize(&dt),
+            Err(RecordError::TypeMismatch { .. })
+        ));
+        // The length prefix must account for every byte
+        assert!(Value::deserialize(&[3, 0, b'a', b'b'], &dt, false).is_err());
+    }
+
     #[test]
     fn test_null_serialization() {
         let val = Value::Null;